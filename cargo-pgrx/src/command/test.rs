@@ -40,6 +40,10 @@ pub(crate) struct Test {
     /// Don't regenerate the schema
     #[clap(long, short)]
     no_schema: bool,
+    /// Report per-test MemoryContext growth, failing tests whose `TopMemoryContext` or
+    /// `CurTransactionContext` grows by more than BYTES (default 1MB).  Requires Postgres 14+
+    #[clap(long, value_name = "BYTES", num_args = 0..=1, default_missing_value = "1048576")]
+    leak_report: Option<usize>,
     #[clap(flatten)]
     features: clap_cargo::Features,
     #[clap(from_global, action = clap::ArgAction::Count)]
@@ -73,6 +77,7 @@ impl CommandExecute for Test {
                 me.package.as_ref(),
                 &profile,
                 me.no_schema,
+                me.leak_report,
                 &features,
                 me.testname,
             )?;
@@ -108,6 +113,7 @@ pub fn test_extension(
     user_package: Option<&String>,
     profile: &CargoProfile,
     no_schema: bool,
+    leak_report: Option<usize>,
     features: &clap_cargo::Features,
     testname: Option<impl AsRef<str>>,
 ) -> eyre::Result<()> {
@@ -135,6 +141,10 @@ pub fn test_extension(
         .env("PGRX_BUILD_PROFILE", profile.name())
         .env("PGRX_NO_SCHEMA", if no_schema { "true" } else { "false" });
 
+    if let Some(threshold) = leak_report {
+        command.env("PGRX_TEST_LEAK_THRESHOLD", threshold.to_string());
+    }
+
     if let Ok(rust_log) = std::env::var("RUST_LOG") {
         command.env("RUST_LOG", rust_log);
     }
@@ -0,0 +1,106 @@
+/*
+Portions Copyright 2019-2021 ZomboDB, LLC.
+Portions Copyright 2021-2022 Technology Concepts & Design, Inc. <support@tcdi.com>
+
+All rights reserved.
+
+Use of this source code is governed by the MIT license that can be found in the LICENSE file.
+*/
+use proc_macro2::{Span, TokenStream};
+use quote::quote;
+use syn::parse::Parser;
+use syn::punctuated::Punctuated;
+use syn::{Expr, Lit, Token};
+
+const USAGE: &str = "cdc_table! accepts `table`, `shadow`, `name` and `requires`";
+
+fn string_value(expr: &Expr) -> syn::Result<String> {
+    match expr {
+        Expr::Lit(syn::ExprLit { lit: Lit::Str(value), .. }) => Ok(value.value()),
+        other => Err(syn::Error::new_spanned(other, "expected a string literal")),
+    }
+}
+
+// the unqualified name of a possibly schema-qualified table
+fn relname(table: &str) -> &str {
+    table.rsplit('.').next().unwrap_or(table)
+}
+
+pub(crate) fn impl_cdc_table(input: TokenStream) -> syn::Result<TokenStream> {
+    let mut table = None;
+    let mut shadow = None;
+    let mut name = None;
+    let mut requires = Vec::new();
+    for arg in Punctuated::<Expr, Token![,]>::parse_terminated.parse2(input)? {
+        let (left, right) = match &arg {
+            Expr::Assign(assign) => (&*assign.left, &*assign.right),
+            other => return Err(syn::Error::new_spanned(other, USAGE)),
+        };
+        let key = match left {
+            Expr::Path(path) => path.path.get_ident().map(|ident| ident.to_string()),
+            _ => None,
+        };
+        match key.as_deref() {
+            Some("table") => table = Some(string_value(right)?),
+            Some("shadow") => shadow = Some(string_value(right)?),
+            Some("name") => name = Some(string_value(right)?),
+            Some("requires") => match right {
+                Expr::Array(array) => requires.extend(array.elems.iter().cloned()),
+                other => return Err(syn::Error::new_spanned(other, "expected an array")),
+            },
+            _ => return Err(syn::Error::new_spanned(left, USAGE)),
+        }
+    }
+    let table = table.ok_or_else(|| syn::Error::new(Span::call_site(), "missing `table`"))?;
+    let shadow = shadow.unwrap_or_else(|| format!("{}_changes", table));
+    let name = name.unwrap_or_else(|| format!("{}_cdc", shadow.replace('.', "_")));
+
+    let capture = syn::Ident::new(
+        &format!(
+            "{}_capture",
+            shadow
+                .chars()
+                .map(|c| if c.is_ascii_alphanumeric() { c.to_ascii_lowercase() } else { '_' })
+                .collect::<String>()
+        ),
+        Span::call_site(),
+    );
+    let trigger = format!("{}_cdc", relname(&table));
+    let argument = shadow.replace('\'', "''");
+    let sql = format!(
+        r#"
+CREATE TABLE {shadow} (
+    change_id bigserial PRIMARY KEY,
+    txid bigint NOT NULL DEFAULT txid_current(),
+    changed_at timestamptz NOT NULL DEFAULT now(),
+    operation text NOT NULL,
+    old_row jsonb,
+    new_row jsonb
+);
+CREATE INDEX ON {shadow} (changed_at);
+SELECT pg_catalog.pg_extension_config_dump('{argument}', '');
+SELECT pg_catalog.pg_extension_config_dump(pg_catalog.pg_get_serial_sequence('{argument}', 'change_id'), '');
+CREATE TRIGGER {trigger}
+    AFTER INSERT OR UPDATE OR DELETE ON {table}
+    FOR EACH ROW EXECUTE PROCEDURE {capture}('{argument}');
+CREATE TRIGGER {trigger}_truncate
+    AFTER TRUNCATE ON {table}
+    FOR EACH STATEMENT EXECUTE PROCEDURE {capture}('{argument}');
+"#
+    );
+    let sql = syn::LitStr::new(&sql, Span::call_site());
+
+    Ok(quote! {
+        #[::pgrx::pg_trigger]
+        fn #capture<'a>(
+            trigger: &'a ::pgrx::PgTrigger<'a>,
+        ) -> ::core::result::Result<
+            ::core::option::Option<::pgrx::heap_tuple::PgHeapTuple<'a, ::pgrx::AllocatedByPostgres>>,
+            ::pgrx::cdc::CdcError,
+        > {
+            ::pgrx::cdc::capture(trigger)
+        }
+
+        ::pgrx::extension_sql!(#sql, name = #name, requires = [#capture, #(#requires),*]);
+    })
+}
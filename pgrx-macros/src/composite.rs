@@ -0,0 +1,232 @@
+/*
+Portions Copyright 2019-2021 ZomboDB, LLC.
+Portions Copyright 2021-2022 Technology Concepts & Design, Inc. <support@tcdi.com>
+
+All rights reserved.
+
+Use of this source code is governed by the MIT license that can be found in the LICENSE file.
+*/
+use pgrx_sql_entity_graph::PositioningRef;
+use proc_macro2::{Span, TokenStream};
+use quote::quote;
+use syn::punctuated::Punctuated;
+use syn::spanned::Spanned;
+use syn::{Attribute, Data, DeriveInput, Token};
+
+use crate::option_inner_type;
+
+/// The attribute name given by a field's `#[pgrx(rename = "...")]`, if it has one
+fn rename(attrs: &[Attribute]) -> syn::Result<Option<String>> {
+    let mut rename = None;
+    for attr in attrs.iter().filter(|attr| attr.path.is_ident("pgrx")) {
+        let meta = match attr.parse_meta()? {
+            syn::Meta::List(list) => list,
+            meta => return Err(syn::Error::new(meta.span(), "expected #[pgrx(rename = \"...\")]")),
+        };
+        for nested in meta.nested {
+            match nested {
+                syn::NestedMeta::Meta(syn::Meta::NameValue(syn::MetaNameValue {
+                    path,
+                    lit: syn::Lit::Str(name),
+                    ..
+                })) if path.is_ident("rename") => rename = Some(name.value()),
+                nested => {
+                    return Err(syn::Error::new(
+                        nested.span(),
+                        "expected #[pgrx(rename = \"...\")]",
+                    ))
+                }
+            }
+        }
+    }
+    Ok(rename)
+}
+
+/// The name of the Rust type a field's SQL type is named after, looking through `Vec`s
+fn rust_name(ty: &syn::Type) -> String {
+    let ty = option_inner_type(ty).unwrap_or(ty);
+    let syn::Type::Path(path) = ty else { return quote!(#ty).to_string() };
+    let Some(segment) = path.path.segments.last() else { return quote!(#ty).to_string() };
+    match &segment.arguments {
+        syn::PathArguments::AngleBracketed(args) if segment.ident == "Vec" => {
+            match args.args.first() {
+                Some(syn::GenericArgument::Type(inner)) => rust_name(inner),
+                _ => segment.ident.to_string(),
+            }
+        }
+        _ => segment.ident.to_string(),
+    }
+}
+
+pub(crate) fn impl_postgres_composite(ast: DeriveInput) -> syn::Result<TokenStream> {
+    let fields = match &ast.data {
+        Data::Struct(syn::DataStruct { fields: syn::Fields::Named(fields), .. }) => &fields.named,
+        _ => {
+            return Err(syn::Error::new(
+                ast.span(),
+                "#[derive(PostgresComposite)] can only be applied to structs with named fields",
+            ))
+        }
+    };
+    if !ast.generics.params.is_empty() {
+        return Err(syn::Error::new(
+            ast.generics.span(),
+            "#[derive(PostgresComposite)] can't be applied to generic structs",
+        ));
+    }
+    let name = &ast.ident;
+    let type_name = name.to_string();
+
+    let mut requires = Vec::new();
+    for attr in ast.attrs.iter().filter(|attr| attr.path.is_ident("requires")) {
+        requires.extend(
+            attr.parse_args_with(Punctuated::<PositioningRef, Token![,]>::parse_terminated)?,
+        );
+    }
+
+    let mut unpacked = Vec::with_capacity(fields.len());
+    let mut packed = Vec::with_capacity(fields.len());
+    let mut attributes = Vec::with_capacity(fields.len());
+    for field in fields {
+        let ident = field.ident.as_ref().unwrap();
+        let attribute = match rename(&field.attrs)? {
+            Some(rename) => rename,
+            None => {
+                let attribute = ident.to_string();
+                attribute.strip_prefix("r#").unwrap_or(&attribute).to_string()
+            }
+        };
+        let ty = &field.ty;
+        let inner = option_inner_type(ty).unwrap_or(ty);
+
+        let get = quote! { ::pgrx::composite::get_attribute::<#inner, A>(tuple, #attribute)? };
+        unpacked.push(if option_inner_type(ty).is_some() {
+            quote! { #ident: #get }
+        } else {
+            quote! {
+                #ident: #get.ok_or(::pgrx::composite::CompositeError::MissingAttribute {
+                    type_name: #type_name,
+                    attribute: #attribute,
+                })?
+            }
+        });
+        packed.push(quote! {
+            ::pgrx::composite::set_attribute(&mut tuple, #attribute, self.#ident)?;
+        });
+
+        let quoted = format!("\"{}\"", attribute.replace('"', "\"\""));
+        let rust_name = rust_name(ty);
+        attributes.push(quote! {
+            (
+                #quoted,
+                ::pgrx::composite::attribute_sql(
+                    #type_name,
+                    #attribute,
+                    <#ty as ::pgrx::pgrx_sql_entity_graph::metadata::SqlTranslatable>::argument_sql(),
+                    #rust_name,
+                ),
+            )
+        });
+    }
+
+    let sql_graph_entity_fn_name =
+        syn::Ident::new(&format!("__pgrx_internals_sql_{}", type_name), Span::call_site());
+
+    Ok(quote! {
+        impl ::pgrx::composite::PostgresComposite for #name {
+            const TYPE_NAME: &'static str = #type_name;
+
+            fn from_heap_tuple<A: ::pgrx::WhoAllocated>(
+                tuple: &::pgrx::heap_tuple::PgHeapTuple<'_, A>,
+            ) -> ::core::result::Result<Self, ::pgrx::composite::CompositeError> {
+                Ok(Self { #(#unpacked),* })
+            }
+
+            fn into_heap_tuple(
+                self,
+            ) -> ::core::result::Result<
+                ::pgrx::heap_tuple::PgHeapTuple<'static, ::pgrx::AllocatedByRust>,
+                ::pgrx::composite::CompositeError,
+            > {
+                let mut tuple = ::pgrx::heap_tuple::PgHeapTuple::new_composite_type(#type_name)?;
+                #(#packed)*
+                Ok(tuple)
+            }
+        }
+
+        impl ::pgrx::datum::FromDatum for #name {
+            unsafe fn from_polymorphic_datum(
+                datum: ::pgrx::pg_sys::Datum,
+                is_null: bool,
+                _typoid: ::pgrx::pg_sys::Oid,
+            ) -> Option<Self> {
+                if is_null {
+                    None
+                } else {
+                    let tuple = ::pgrx::heap_tuple::PgHeapTuple::from_composite_datum(datum);
+                    Some(
+                        <Self as ::pgrx::composite::PostgresComposite>::from_heap_tuple(&tuple)
+                            .unwrap_or_else(|e| panic!("{}", e)),
+                    )
+                }
+            }
+        }
+
+        impl ::pgrx::datum::IntoDatum for #name {
+            fn into_datum(self) -> Option<::pgrx::pg_sys::Datum> {
+                <Self as ::pgrx::composite::PostgresComposite>::into_heap_tuple(self)
+                    .unwrap_or_else(|e| panic!("{}", e))
+                    .into_composite_datum()
+            }
+
+            fn type_oid() -> ::pgrx::pg_sys::Oid {
+                ::pgrx::wrappers::regtypein(#type_name)
+            }
+        }
+
+        unsafe impl ::pgrx::pgrx_sql_entity_graph::metadata::SqlTranslatable for #name {
+            fn argument_sql() -> ::core::result::Result<
+                ::pgrx::pgrx_sql_entity_graph::metadata::SqlMapping,
+                ::pgrx::pgrx_sql_entity_graph::metadata::ArgumentError,
+            > {
+                Ok(::pgrx::pgrx_sql_entity_graph::metadata::SqlMapping::literal(#type_name))
+            }
+
+            fn return_sql() -> ::core::result::Result<
+                ::pgrx::pgrx_sql_entity_graph::metadata::Returns,
+                ::pgrx::pgrx_sql_entity_graph::metadata::ReturnsError,
+            > {
+                Ok(::pgrx::pgrx_sql_entity_graph::metadata::Returns::One(
+                    ::pgrx::pgrx_sql_entity_graph::metadata::SqlMapping::literal(#type_name),
+                ))
+            }
+        }
+
+        #[no_mangle]
+        #[doc(hidden)]
+        #[allow(unknown_lints, clippy::no_mangle_with_rust_abi)]
+        pub extern "Rust" fn #sql_graph_entity_fn_name() -> ::pgrx::pgrx_sql_entity_graph::SqlGraphEntity {
+            extern crate alloc;
+            use alloc::vec;
+            let submission = ::pgrx::pgrx_sql_entity_graph::ExtensionSqlEntity {
+                sql: ::pgrx::composite::create_type_sql(#type_name, &[#(#attributes),*]),
+                module_path: module_path!(),
+                full_path: concat!(file!(), ':', line!()),
+                file: file!(),
+                line: line!(),
+                name: #type_name,
+                bootstrap: false,
+                finalize: false,
+                requires: vec![#(#requires),*],
+                creates: vec![
+                    ::pgrx::pgrx_sql_entity_graph::SqlDeclaredEntity::build(
+                        "Type",
+                        concat!(module_path!(), "::", stringify!(#name)),
+                    )
+                    .unwrap(),
+                ],
+            };
+            ::pgrx::pgrx_sql_entity_graph::SqlGraphEntity::CustomSql(submission)
+        }
+    })
+}
@@ -0,0 +1,122 @@
+/*
+Portions Copyright 2019-2021 ZomboDB, LLC.
+Portions Copyright 2021-2022 Technology Concepts & Design, Inc. <support@tcdi.com>
+
+All rights reserved.
+
+Use of this source code is governed by the MIT license that can be found in the LICENSE file.
+*/
+use proc_macro2::{Span, TokenStream};
+use quote::quote;
+use syn::parse::Parser;
+use syn::punctuated::Punctuated;
+use syn::{Expr, ItemFn, Lit, Token};
+
+const EVENTS: &[&str] = &["ddl_command_start", "ddl_command_end", "sql_drop", "table_rewrite"];
+
+// `tags = ["CREATE TABLE", ...]` doesn't parse as a `Meta`, so the arguments are parsed as
+// assignment expressions instead
+fn string_value(expr: &Expr) -> syn::Result<String> {
+    match expr {
+        Expr::Lit(syn::ExprLit { lit: Lit::Str(value), .. }) => Ok(value.value()),
+        other => Err(syn::Error::new_spanned(other, "expected a string literal")),
+    }
+}
+
+pub(crate) fn impl_pg_event_trigger(attr: TokenStream, func: ItemFn) -> syn::Result<TokenStream> {
+    let func_ident = &func.sig.ident;
+    let func_name = func_ident.to_string();
+
+    let mut trigger_name = func_name.clone();
+    let mut event = None;
+    let mut tags = Vec::new();
+    for arg in Punctuated::<Expr, Token![,]>::parse_terminated.parse2(attr)? {
+        let (left, right) = match &arg {
+            Expr::Assign(assign) => (&*assign.left, &*assign.right),
+            other => {
+                return Err(syn::Error::new_spanned(
+                    other,
+                    "#[pg_event_trigger] only accepts `event = \"...\"`, `name = \"...\"` and `tags = [...]`",
+                ))
+            }
+        };
+        let key = match left {
+            Expr::Path(path) => path.path.get_ident().map(|ident| ident.to_string()),
+            _ => None,
+        };
+        match key.as_deref() {
+            Some("event") => {
+                let value = string_value(right)?;
+                if !EVENTS.contains(&value.as_str()) {
+                    return Err(syn::Error::new_spanned(
+                        right,
+                        format!("unknown event trigger event, expected one of {}", EVENTS.join(", ")),
+                    ));
+                }
+                event = Some(value);
+            }
+            Some("name") => trigger_name = string_value(right)?,
+            Some("tags") => match right {
+                Expr::Array(array) => {
+                    for tag in &array.elems {
+                        tags.push(string_value(tag)?);
+                    }
+                }
+                other => return Err(syn::Error::new_spanned(other, "expected an array of strings")),
+            },
+            _ => {
+                return Err(syn::Error::new_spanned(
+                    left,
+                    "#[pg_event_trigger] only accepts `event = \"...\"`, `name = \"...\"` and `tags = [...]`",
+                ))
+            }
+        }
+    }
+    if event.is_none() && !tags.is_empty() {
+        return Err(syn::Error::new(Span::call_site(), "`tags` requires an `event`"));
+    }
+
+    let wrapper = syn::Ident::new(&format!("{}_wrapper", func_name), Span::call_site());
+    let finfo = syn::Ident::new(&format!("pg_finfo_{}", wrapper), Span::call_site());
+
+    let mut sql = format!(
+        "\n\
+        CREATE FUNCTION \"{func_name}\"() RETURNS event_trigger\n\
+        LANGUAGE c AS 'MODULE_PATHNAME', '{wrapper}';\n"
+    );
+    if let Some(event) = &event {
+        sql.push_str(&format!("CREATE EVENT TRIGGER \"{trigger_name}\" ON {event}\n"));
+        if !tags.is_empty() {
+            let tags = tags
+                .iter()
+                .map(|tag| format!("'{}'", tag.replace('\'', "''")))
+                .collect::<Vec<_>>()
+                .join(", ");
+            sql.push_str(&format!("WHEN TAG IN ({tags})\n"));
+        }
+        sql.push_str(&format!("EXECUTE FUNCTION \"{func_name}\"();\n"));
+    }
+    let sql = syn::LitStr::new(&sql, Span::call_site());
+
+    Ok(quote! {
+        #func
+
+        #[no_mangle]
+        #[doc(hidden)]
+        #[::pgrx::pgrx_macros::pg_guard]
+        pub unsafe extern "C" fn #wrapper(
+            fcinfo: ::pgrx::pg_sys::FunctionCallInfo,
+        ) -> ::pgrx::pg_sys::Datum {
+            ::pgrx::event_trigger::call_event_trigger(fcinfo, #func_name, #func_ident)
+        }
+
+        #[no_mangle]
+        #[doc(hidden)]
+        pub extern "C" fn #finfo() -> &'static ::pgrx::pg_sys::Pg_finfo_record {
+            const V1_API: ::pgrx::pg_sys::Pg_finfo_record = ::pgrx::pg_sys::Pg_finfo_record { api_version: 1 };
+            &V1_API
+        }
+
+        ::pgrx::extension_sql!(#sql, name = #func_name);
+    })
+}
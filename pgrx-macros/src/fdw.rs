@@ -0,0 +1,103 @@
+/*
+Portions Copyright 2019-2021 ZomboDB, LLC.
+Portions Copyright 2021-2022 Technology Concepts & Design, Inc. <support@tcdi.com>
+
+All rights reserved.
+
+Use of this source code is governed by the MIT license that can be found in the LICENSE file.
+*/
+use proc_macro2::{Span, TokenStream};
+use quote::quote;
+use syn::parse::Parser;
+use syn::punctuated::Punctuated;
+use syn::{ItemImpl, Lit, Meta, MetaNameValue, Token};
+
+pub(crate) fn impl_pg_fdw(attr: TokenStream, item_impl: ItemImpl) -> syn::Result<TokenStream> {
+    let type_ident = match &*item_impl.self_ty {
+        syn::Type::Path(path) => path.path.segments.last().map(|segment| segment.ident.clone()),
+        _ => None,
+    }
+    .ok_or_else(|| {
+        syn::Error::new_spanned(&item_impl.self_ty, "#[pg_fdw] must be used on an impl of a type")
+    })?;
+    let self_ty = &item_impl.self_ty;
+
+    let mut name = type_ident.to_string().to_lowercase();
+    for meta in Punctuated::<Meta, Token![,]>::parse_terminated.parse2(attr)? {
+        match meta {
+            Meta::NameValue(MetaNameValue { path, lit: Lit::Str(value), .. })
+                if path.is_ident("name") =>
+            {
+                name = value.value();
+            }
+            other => {
+                return Err(syn::Error::new_spanned(
+                    other,
+                    "#[pg_fdw] only accepts `name = \"...\"`",
+                ))
+            }
+        }
+    }
+
+    let handler = format!("{}_handler", name);
+    let validator = format!("{}_validator", name);
+    let handler_wrapper = syn::Ident::new(&format!("{}_wrapper", handler), Span::call_site());
+    let validator_wrapper = syn::Ident::new(&format!("{}_wrapper", validator), Span::call_site());
+    let handler_finfo =
+        syn::Ident::new(&format!("pg_finfo_{}", handler_wrapper), Span::call_site());
+    let validator_finfo =
+        syn::Ident::new(&format!("pg_finfo_{}", validator_wrapper), Span::call_site());
+
+    let sql = format!(
+        "\n\
+        CREATE FUNCTION {handler}() RETURNS fdw_handler\n\
+        STRICT LANGUAGE c AS 'MODULE_PATHNAME', '{handler_wrapper}';\n\
+        CREATE FUNCTION {validator}(text[], oid) RETURNS void\n\
+        LANGUAGE c AS 'MODULE_PATHNAME', '{validator_wrapper}';\n\
+        CREATE FOREIGN DATA WRAPPER {name} HANDLER {handler} VALIDATOR {validator};\n"
+    );
+    let sql = syn::LitStr::new(&sql, Span::call_site());
+
+    Ok(quote! {
+        #item_impl
+
+        #[no_mangle]
+        #[doc(hidden)]
+        #[::pgrx::pgrx_macros::pg_guard]
+        pub unsafe extern "C" fn #handler_wrapper(
+            _fcinfo: ::pgrx::pg_sys::FunctionCallInfo,
+        ) -> ::pgrx::pg_sys::Datum {
+            ::pgrx::fdw::fdw_routine::<#self_ty>()
+        }
+
+        #[no_mangle]
+        #[doc(hidden)]
+        pub extern "C" fn #handler_finfo() -> &'static ::pgrx::pg_sys::Pg_finfo_record {
+            const V1_API: ::pgrx::pg_sys::Pg_finfo_record = ::pgrx::pg_sys::Pg_finfo_record { api_version: 1 };
+            &V1_API
+        }
+
+        #[no_mangle]
+        #[doc(hidden)]
+        #[::pgrx::pgrx_macros::pg_guard]
+        pub unsafe extern "C" fn #validator_wrapper(
+            fcinfo: ::pgrx::pg_sys::FunctionCallInfo,
+        ) -> ::pgrx::pg_sys::Datum {
+            let options = ::pgrx::fcinfo::pg_getarg_datum(fcinfo, 0)
+                .unwrap_or(::pgrx::pg_sys::Datum::from(0));
+            let catalog = ::pgrx::fcinfo::pg_getarg::<::pgrx::pg_sys::Oid>(fcinfo, 1)
+                .unwrap_or(::pgrx::pg_sys::InvalidOid);
+            ::pgrx::fdw::validate_options::<#self_ty>(options, catalog);
+            ::pgrx::pg_sys::Datum::from(0)
+        }
+
+        #[no_mangle]
+        #[doc(hidden)]
+        pub extern "C" fn #validator_finfo() -> &'static ::pgrx::pg_sys::Pg_finfo_record {
+            const V1_API: ::pgrx::pg_sys::Pg_finfo_record = ::pgrx::pg_sys::Pg_finfo_record { api_version: 1 };
+            &V1_API
+        }
+
+        ::pgrx::extension_sql!(#sql, name = #name);
+    })
+}
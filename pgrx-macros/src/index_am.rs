@@ -0,0 +1,79 @@
+/*
+Portions Copyright 2019-2021 ZomboDB, LLC.
+Portions Copyright 2021-2022 Technology Concepts & Design, Inc. <support@tcdi.com>
+
+All rights reserved.
+
+Use of this source code is governed by the MIT license that can be found in the LICENSE file.
+*/
+use proc_macro2::{Span, TokenStream};
+use quote::quote;
+use syn::parse::Parser;
+use syn::punctuated::Punctuated;
+use syn::{ItemImpl, Lit, Meta, MetaNameValue, Token};
+
+pub(crate) fn impl_pg_index_am(attr: TokenStream, item_impl: ItemImpl) -> syn::Result<TokenStream> {
+    let type_ident = match &*item_impl.self_ty {
+        syn::Type::Path(path) => path.path.segments.last().map(|segment| segment.ident.clone()),
+        _ => None,
+    }
+    .ok_or_else(|| {
+        syn::Error::new_spanned(
+            &item_impl.self_ty,
+            "#[pg_index_am] must be used on an impl of a type",
+        )
+    })?;
+    let self_ty = &item_impl.self_ty;
+
+    let mut name = type_ident.to_string().to_lowercase();
+    for meta in Punctuated::<Meta, Token![,]>::parse_terminated.parse2(attr)? {
+        match meta {
+            Meta::NameValue(MetaNameValue { path, lit: Lit::Str(value), .. })
+                if path.is_ident("name") =>
+            {
+                name = value.value();
+            }
+            other => {
+                return Err(syn::Error::new_spanned(
+                    other,
+                    "#[pg_index_am] only accepts `name = \"...\"`",
+                ))
+            }
+        }
+    }
+
+    let handler = format!("{}_handler", name);
+    let handler_wrapper = syn::Ident::new(&format!("{}_wrapper", handler), Span::call_site());
+    let handler_finfo =
+        syn::Ident::new(&format!("pg_finfo_{}", handler_wrapper), Span::call_site());
+
+    let sql = format!(
+        "\n\
+        CREATE FUNCTION {handler}(internal) RETURNS index_am_handler\n\
+        STRICT LANGUAGE c AS 'MODULE_PATHNAME', '{handler_wrapper}';\n\
+        CREATE ACCESS METHOD {name} TYPE INDEX HANDLER {handler};\n"
+    );
+    let sql = syn::LitStr::new(&sql, Span::call_site());
+
+    Ok(quote! {
+        #item_impl
+
+        #[no_mangle]
+        #[doc(hidden)]
+        #[::pgrx::pgrx_macros::pg_guard]
+        pub unsafe extern "C" fn #handler_wrapper(
+            _fcinfo: ::pgrx::pg_sys::FunctionCallInfo,
+        ) -> ::pgrx::pg_sys::Datum {
+            ::pgrx::index_am::index_am_routine::<#self_ty>()
+        }
+
+        #[no_mangle]
+        #[doc(hidden)]
+        pub extern "C" fn #handler_finfo() -> &'static ::pgrx::pg_sys::Pg_finfo_record {
+            const V1_API: ::pgrx::pg_sys::Pg_finfo_record = ::pgrx::pg_sys::Pg_finfo_record { api_version: 1 };
+            &V1_API
+        }
+
+        ::pgrx::extension_sql!(#sql, name = #name);
+    })
+}
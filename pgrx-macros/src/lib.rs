@@ -25,8 +25,14 @@ use pgrx_sql_entity_graph::{
 
 use crate::rewriter::PgGuardRewriter;
 
+mod cdc;
+mod composite;
+mod event_trigger;
+mod fdw;
+mod index_am;
 mod operators;
 mod rewriter;
+mod tenancy;
 
 /// Declare a function as `#[pg_guard]` to indicate that it is called from a Postgres `extern "C"`
 /// function so that Rust `panic!()`s (and Postgres `elog(ERROR)`s) will be properly handled by `pgrx`
@@ -389,6 +395,54 @@ pub fn extension_sql(input: TokenStream) -> TokenStream {
     }
 }
 
+/**
+Capture the changes to a table into a shadow table, as `jsonb` rows.
+
+`table` is required.  The shadow table is named `{table}_changes` unless given a `shadow` name.
+`requires` is passed to the generated [`macro@extension_sql`], to order it after the table's
+creation:
+
+```rust,ignore
+cdc_table!(table = "orders", shadow = "orders_changes", requires = ["orders"]);
+```
+
+This generates the shadow table, a `{shadow}_capture` trigger function, and `{table}_cdc` triggers
+running it for every row inserted, updated or deleted, and for every `TRUNCATE`.  The shadow table
+is marked as configuration, so `pg_dump` includes its contents.
+
+See `pgrx::cdc` for the shadow table's columns, and purging old changes.
+*/
+#[proc_macro]
+pub fn cdc_table(input: TokenStream) -> TokenStream {
+    cdc::impl_cdc_table(input.into()).unwrap_or_else(syn::Error::into_compile_error).into()
+}
+
+/**
+Enable row level security on a table, with a policy only letting the rows of the tenant named by a
+setting be seen or written.
+
+`table`, `column` and `setting` are required.  The setting is cast to `column_type`, `text` by
+default, and compared to the column.  The policy is named `{table}_tenant_isolation` unless given a
+`name`.  `force = true` applies it to the table's owner too, and `requires` is passed to the
+generated [`macro@extension_sql`], to order it after the table's creation:
+
+```rust,ignore
+tenant_policy!(
+    table = "documents",
+    column = "tenant_id",
+    setting = "my_extension.tenant_id",
+    column_type = "bigint",
+    requires = ["documents"]
+);
+```
+
+See `pgrx::tenancy::TenantSetting` for reading the setting from Rust.
+*/
+#[proc_macro]
+pub fn tenant_policy(input: TokenStream) -> TokenStream {
+    tenancy::impl_tenant_policy(input.into()).unwrap_or_else(syn::Error::into_compile_error).into()
+}
+
 /**
 Declare SQL (from a file) to be included in generated extension script.
 
@@ -449,6 +503,11 @@ Optionally accepts the following attributes:
 * `immutable`: Corresponds to [`IMMUTABLE`](https://www.postgresql.org/docs/current/sql-createfunction.html).
 * `strict`: Corresponds to [`STRICT`](https://www.postgresql.org/docs/current/sql-createfunction.html).
   + In most cases, `#[pg_extern]` can detect when no `Option<T>`s are used, and automatically set this.
+  + It is a compile error to combine `strict` with `Option<T>` arguments, as they could never be `None`.
+* `called_on_null_input`: Corresponds to [`CALLED ON NULL INPUT`](https://www.postgresql.org/docs/current/sql-createfunction.html).
+  + Disables the automatic `STRICT` detection.  If a `NULL` is then passed for an argument that isn't
+    an `Option<T>`, the function isn't called and returns `NULL` (or an empty set) instead, exactly as
+    if it were `STRICT`.  The same applies to functions mixing `Option<T>` and non-`Option<T>` arguments.
 * `stable`: Corresponds to [`STABLE`](https://www.postgresql.org/docs/current/sql-createfunction.html).
 * `volatile`: Corresponds to [`VOLATILE`](https://www.postgresql.org/docs/current/sql-createfunction.html).
 * `raw`: Corresponds to [`RAW`](https://www.postgresql.org/docs/current/sql-createfunction.html).
@@ -457,6 +516,10 @@ Optionally accepts the following attributes:
 * `parallel_safe`: Corresponds to [`PARALLEL SAFE`](https://www.postgresql.org/docs/current/sql-createfunction.html).
 * `parallel_unsafe`: Corresponds to [`PARALLEL UNSAFE`](https://www.postgresql.org/docs/current/sql-createfunction.html).
 * `parallel_restricted`: Corresponds to [`PARALLEL RESTRICTED`](https://www.postgresql.org/docs/current/sql-createfunction.html).
+* `window`: Corresponds to [`WINDOW`](https://www.postgresql.org/docs/current/sql-createfunction.html).
+    Postgres calls a window function with all of its arguments `NULL`, so they must be `Option<T>`s.
+    The function reads them, and the rest of its partition, through a `pgrx::window::WindowObject`
+    made from its `fcinfo: pg_sys::FunctionCallInfo` argument.
 * `no_guard`: Do not use `#[pg_guard]` with the function.
 * `sql`: Same arguments as [`#[pgrx(sql = ..)]`](macro@pgrx).
 * `name`: Specifies target function name. Defaults to Rust function name.
@@ -708,9 +771,15 @@ Optionally accepts the following attributes:
 
 * `inoutfuncs(some_in_fn, some_out_fn)`: Define custom in/out functions for the type.
 * `pgvarlena_inoutfuncs(some_in_fn, some_out_fn)`: Define custom in/out functions for the `PgVarlena` of this type.
+* `fmt_inoutfuncs`: Use the type's `Display` and `FromStr` impls as its in/out functions.
+* `versioned`: Store values with the version given by the type's `VersionedType` impl, and use its
+  migrations to decode values stored by older versions.
 * `sql`: Same arguments as [`#[pgrx(sql = ..)]`](macro@pgrx).
 */
-#[proc_macro_derive(PostgresType, attributes(inoutfuncs, pgvarlena_inoutfuncs, requires, pgrx))]
+#[proc_macro_derive(
+    PostgresType,
+    attributes(inoutfuncs, pgvarlena_inoutfuncs, fmt_inoutfuncs, versioned, requires, pgrx)
+)]
 pub fn postgres_type(input: TokenStream) -> TokenStream {
     let ast = parse_macro_input!(input as syn::DeriveInput);
 
@@ -724,6 +793,7 @@ fn impl_postgres_type(ast: DeriveInput) -> syn::Result<proc_macro2::TokenStream>
     let funcname_in = Ident::new(&format!("{}_in", name).to_lowercase(), name.span());
     let funcname_out = Ident::new(&format!("{}_out", name).to_lowercase(), name.span());
     let mut args = parse_postgres_type_args(&ast.attrs);
+    let versioned = args.remove(&PostgresTypeAttribute::Versioned);
     let mut stream = proc_macro2::TokenStream::new();
 
     // validate that we're only operating on a struct
@@ -752,10 +822,25 @@ fn impl_postgres_type(ast: DeriveInput) -> syn::Result<proc_macro2::TokenStream>
         None => quote! {'static},
     };
 
-    // all #[derive(PostgresType)] need to implement that trait
-    stream.extend(quote! {
-        impl #generics ::pgrx::PostgresType for #name #generics { }
-    });
+    // all #[derive(PostgresType)] need to implement that trait, and #[versioned] ones store their
+    // values with the version from their VersionedType impl
+    if versioned {
+        stream.extend(quote! {
+            impl #generics ::pgrx::PostgresType for #name #generics {
+                fn storage_version() -> Option<u8> {
+                    Some(<Self as ::pgrx::datum::VersionedType>::VERSION)
+                }
+
+                fn storage_migrations() -> ::pgrx::datum::Migrations<Self> {
+                    <Self as ::pgrx::datum::VersionedType>::migrations()
+                }
+            }
+        });
+    } else {
+        stream.extend(quote! {
+            impl #generics ::pgrx::PostgresType for #name #generics { }
+        });
+    }
 
     // and if we don't have custom inout/funcs, we use the JsonInOutFuncs trait
     // which implements _in and _out #[pg_extern] functions that just return the type itself
@@ -789,6 +874,30 @@ fn impl_postgres_type(ast: DeriveInput) -> syn::Result<proc_macro2::TokenStream>
             }
 
         });
+    } else if args.contains(&PostgresTypeAttribute::FmtInOutFuncs) {
+        // otherwise if it's FmtInOutFuncs our _in/_out functions use its Display and FromStr impls
+        stream.extend(quote! {
+            impl #generics ::pgrx::inoutfuncs::FmtInOutFuncs for #name #generics {}
+
+            #[doc(hidden)]
+            #[::pgrx::pgrx_macros::pg_extern(immutable,parallel_safe)]
+            pub fn #funcname_in #generics(input: Option<&#lifetime ::core::ffi::CStr>) -> Option<#name #generics> {
+                input.map_or_else(|| {
+                    for m in <#name as ::pgrx::inoutfuncs::FmtInOutFuncs>::NULL_ERROR_MESSAGE {
+                        ::pgrx::pg_sys::error!("{}", m);
+                    }
+                    None
+                }, |i| Some(<#name as ::pgrx::inoutfuncs::FmtInOutFuncs>::input(i)))
+            }
+
+            #[doc(hidden)]
+            #[::pgrx::pgrx_macros::pg_extern(immutable,parallel_safe)]
+            pub fn #funcname_out #generics(input: #name #generics) -> &#lifetime ::core::ffi::CStr {
+                let mut buffer = ::pgrx::stringinfo::StringInfo::new();
+                ::pgrx::inoutfuncs::FmtInOutFuncs::output(&input, &mut buffer);
+                buffer.into()
+            }
+        });
     } else if args.contains(&PostgresTypeAttribute::InOutFuncs) {
         // otherwise if it's InOutFuncs our _in/_out functions use an owned type instance
         stream.extend(quote! {
@@ -929,10 +1038,212 @@ fn impl_guc_enum(ast: DeriveInput) -> syn::Result<proc_macro2::TokenStream> {
     Ok(stream)
 }
 
+/**
+Maps a struct with named fields to a composite type of the same name, with one attribute per field.
+
+```rust,ignore
+use pgrx::prelude::*;
+
+#[derive(PostgresComposite)]
+struct Dog {
+    name: String,
+    #[pgrx(rename = "scritches")]
+    scritch_count: i32,
+    owner: Option<String>,
+}
+```
+
+Generates the type's `CREATE TYPE ... AS (...)` and implements `FromDatum` and `IntoDatum` by packing
+fields into heap tuples by attribute name.  See `pgrx::composite`.
+
+Optionally accepts the following attributes:
+
+* `#[pgrx(rename = "...")]` on a field: The name of its attribute.
+* `#[requires(...)]`: Same arguments as `requires` in `extension_sql!()`.
+*/
+#[proc_macro_derive(PostgresComposite, attributes(pgrx, requires))]
+pub fn postgres_composite(input: TokenStream) -> TokenStream {
+    let ast = parse_macro_input!(input as syn::DeriveInput);
+
+    composite::impl_postgres_composite(ast).unwrap_or_else(|e| e.to_compile_error()).into()
+}
+
+/// Derives the `TupleDescriptor` trait for a struct with named fields, describing a row with one
+/// column per field.  Fields that aren't `Option`s become `NOT NULL` columns.
+#[proc_macro_derive(TupleDescriptor)]
+pub fn tuple_descriptor(input: TokenStream) -> TokenStream {
+    let ast = parse_macro_input!(input as syn::DeriveInput);
+
+    impl_tuple_descriptor(ast).unwrap_or_else(|e| e.to_compile_error()).into()
+}
+
+fn impl_tuple_descriptor(ast: DeriveInput) -> syn::Result<proc_macro2::TokenStream> {
+    let fields = match &ast.data {
+        Data::Struct(syn::DataStruct { fields: syn::Fields::Named(fields), .. }) => &fields.named,
+        _ => {
+            return Err(syn::Error::new(
+                ast.span(),
+                "#[derive(TupleDescriptor)] can only be applied to structs with named fields",
+            ))
+        }
+    };
+    let name = &ast.ident;
+    let (impl_generics, ty_generics, where_clause) = ast.generics.split_for_impl();
+
+    let columns = fields.iter().map(|field| {
+        let ty = &field.ty;
+        let column_name = field.ident.as_ref().unwrap().to_string();
+        let column_name = column_name.strip_prefix("r#").unwrap_or(&column_name);
+        let is_option = match ty {
+            syn::Type::Path(path) => {
+                path.path.segments.last().map(|segment| segment.ident == "Option").unwrap_or(false)
+            }
+            _ => false,
+        };
+        let not_null = if is_option {
+            quote! {}
+        } else {
+            quote! { .not_null() }
+        };
+        quote! { ::pgrx::rel::ColumnSpec::new::<#ty>(#column_name)#not_null }
+    });
+
+    Ok(quote! {
+        impl #impl_generics ::pgrx::tupdesc::TupleDescriptor for #name #ty_generics #where_clause {
+            fn columns() -> Vec<::pgrx::rel::ColumnSpec> {
+                vec![#(#columns),*]
+            }
+        }
+    })
+}
+
+/// Derives the `FromSpiRow` trait, decoding an SPI result row into a struct.  Named fields are
+/// decoded from the column of the same name, or the one named by `#[spi(rename = "...")]`, and
+/// tuple struct fields from the column at the same position.  A NULL column is an error unless its
+/// field is an `Option`.
+#[proc_macro_derive(FromSpiRow, attributes(spi))]
+pub fn from_spi_row(input: TokenStream) -> TokenStream {
+    let ast = parse_macro_input!(input as syn::DeriveInput);
+
+    impl_from_spi_row(ast).unwrap_or_else(|e| e.to_compile_error()).into()
+}
+
+fn impl_from_spi_row(ast: DeriveInput) -> syn::Result<proc_macro2::TokenStream> {
+    let fields = match &ast.data {
+        Data::Struct(syn::DataStruct { fields, .. }) => fields,
+        _ => {
+            return Err(syn::Error::new(
+                ast.span(),
+                "#[derive(FromSpiRow)] can only be applied to structs",
+            ))
+        }
+    };
+    let name = &ast.ident;
+    let (impl_generics, ty_generics, where_clause) = ast.generics.split_for_impl();
+
+    let mut decoded = Vec::with_capacity(fields.len());
+    for (index, field) in fields.iter().enumerate() {
+        let ty = option_inner_type(&field.ty).unwrap_or(&field.ty);
+        let (get, column) = match &field.ident {
+            Some(ident) => {
+                let column_name = match spi_rename(&field.attrs)? {
+                    Some(rename) => rename,
+                    None => {
+                        let column_name = ident.to_string();
+                        column_name.strip_prefix("r#").unwrap_or(&column_name).to_string()
+                    }
+                };
+                (quote! { get_by_name::<#ty, _> }, quote! { #column_name })
+            }
+            None => {
+                if let Some(attr) = field.attrs.iter().find(|attr| attr.path.is_ident("spi")) {
+                    return Err(syn::Error::new(
+                        attr.span(),
+                        "#[spi(rename)] can only be applied to named fields",
+                    ));
+                }
+                let ordinal = index + 1;
+                (quote! { get::<#ty> }, quote! { #ordinal })
+            }
+        };
+
+        let value = if option_inner_type(&field.ty).is_some() {
+            quote! { row.#get(#column)? }
+        } else {
+            quote! {
+                row.#get(#column)?.ok_or_else(|| {
+                    ::pgrx::spi::Error::UnexpectedNull(#column.to_string())
+                })?
+            }
+        };
+        decoded.push(match &field.ident {
+            Some(ident) => quote! { #ident: #value },
+            None => value,
+        });
+    }
+
+    let construct = match fields {
+        syn::Fields::Named(_) => quote! { Self { #(#decoded),* } },
+        syn::Fields::Unnamed(_) => quote! { Self(#(#decoded),*) },
+        syn::Fields::Unit => quote! { Self },
+    };
+
+    Ok(quote! {
+        impl #impl_generics ::pgrx::spi::FromSpiRow for #name #ty_generics #where_clause {
+            #[allow(unused_variables)]
+            fn from_spi_row(row: &::pgrx::spi::SpiHeapTupleData) -> ::pgrx::spi::Result<Self> {
+                Ok(#construct)
+            }
+        }
+    })
+}
+
+/// The column name given by a field's `#[spi(rename = "...")]` attribute, if it has one
+fn spi_rename(attrs: &[Attribute]) -> syn::Result<Option<String>> {
+    let mut rename = None;
+    for attr in attrs.iter().filter(|attr| attr.path.is_ident("spi")) {
+        let meta = match attr.parse_meta()? {
+            syn::Meta::List(list) => list,
+            meta => return Err(syn::Error::new(meta.span(), "expected #[spi(rename = \"...\")]")),
+        };
+        for nested in meta.nested {
+            match nested {
+                syn::NestedMeta::Meta(syn::Meta::NameValue(syn::MetaNameValue {
+                    path,
+                    lit: syn::Lit::Str(name),
+                    ..
+                })) if path.is_ident("rename") => rename = Some(name.value()),
+                nested => {
+                    return Err(syn::Error::new(nested.span(), "expected #[spi(rename = \"...\")]"))
+                }
+            }
+        }
+    }
+    Ok(rename)
+}
+
+/// `T`, if `ty` is an `Option<T>`
+fn option_inner_type(ty: &syn::Type) -> Option<&syn::Type> {
+    let syn::Type::Path(path) = ty else { return None };
+    let segment = path.path.segments.last()?;
+    if segment.ident != "Option" {
+        return None;
+    }
+    match &segment.arguments {
+        syn::PathArguments::AngleBracketed(args) => match args.args.first()? {
+            syn::GenericArgument::Type(inner) => Some(inner),
+            _ => None,
+        },
+        _ => None,
+    }
+}
+
 #[derive(Debug, Hash, Ord, PartialOrd, Eq, PartialEq)]
 enum PostgresTypeAttribute {
     InOutFuncs,
     PgVarlenaInOutFuncs,
+    FmtInOutFuncs,
+    Versioned,
     Default,
 }
 
@@ -951,6 +1262,14 @@ fn parse_postgres_type_args(attributes: &[Attribute]) -> HashSet<PostgresTypeAtt
                 categorized_attributes.insert(PostgresTypeAttribute::PgVarlenaInOutFuncs);
             }
 
+            "fmt_inoutfuncs" => {
+                categorized_attributes.insert(PostgresTypeAttribute::FmtInOutFuncs);
+            }
+
+            "versioned" => {
+                categorized_attributes.insert(PostgresTypeAttribute::Versioned);
+            }
+
             _ => {
                 // we can just ignore attributes we don't understand
             }
@@ -1058,6 +1377,71 @@ pub fn pg_aggregate(_attr: TokenStream, item: TokenStream) -> TokenStream {
     }
 }
 
+/**
+Declare a function as a Postgres event trigger function.
+
+The function takes a `&pgrx::event_trigger::EventTriggerData`.  Generates its `RETURNS
+event_trigger` SQL function, and, given an `event`, the `CREATE EVENT TRIGGER` statement firing it
+on `ddl_command_start`, `ddl_command_end`, `sql_drop` or `table_rewrite`.  The trigger is named
+after the function unless given a `name`, and fires for every command unless given `tags`:
+
+```rust,ignore
+#[pg_event_trigger(event = "sql_drop", tags = ["DROP TABLE"])]
+fn audit_drops(event: &EventTriggerData) {
+    // ...
+}
+```
+*/
+#[proc_macro_attribute]
+pub fn pg_event_trigger(attr: TokenStream, item: TokenStream) -> TokenStream {
+    let func = parse_macro_input!(item as syn::ItemFn);
+    event_trigger::impl_pg_event_trigger(attr.into(), func)
+        .unwrap_or_else(syn::Error::into_compile_error)
+        .into()
+}
+
+/**
+Declare a `pgrx::fdw::ForeignDataWrapper` implementation on a type as a Postgres foreign data wrapper.
+
+Generates the wrapper's handler and validator functions, and its `CREATE FOREIGN DATA WRAPPER`
+statement.  The wrapper is named after the type, lowercased, unless given a `name`:
+
+```rust,ignore
+#[pg_fdw(name = "numbers_fdw")]
+impl ForeignDataWrapper for Numbers {
+    // ...
+}
+```
+*/
+#[proc_macro_attribute]
+pub fn pg_fdw(attr: TokenStream, item: TokenStream) -> TokenStream {
+    let item_impl = parse_macro_input!(item as syn::ItemImpl);
+    fdw::impl_pg_fdw(attr.into(), item_impl).unwrap_or_else(syn::Error::into_compile_error).into()
+}
+
+/**
+Declare a `pgrx::index_am::IndexAm` implementation on a type as a Postgres index access method.
+
+Generates the access method's handler function, and its `CREATE ACCESS METHOD` statement.  The
+access method is named after the type, lowercased, unless given a `name`:
+
+```rust,ignore
+#[pg_index_am(name = "exact")]
+impl IndexAm for Exact {
+    // ...
+}
+```
+
+Its operator classes are left to `CREATE OPERATOR CLASS ... USING` it.
+*/
+#[proc_macro_attribute]
+pub fn pg_index_am(attr: TokenStream, item: TokenStream) -> TokenStream {
+    let item_impl = parse_macro_input!(item as syn::ItemImpl);
+    index_am::impl_pg_index_am(attr.into(), item_impl)
+        .unwrap_or_else(syn::Error::into_compile_error)
+        .into()
+}
+
 /**
 A helper attribute for various contexts.
 
@@ -0,0 +1,84 @@
+/*
+Portions Copyright 2019-2021 ZomboDB, LLC.
+Portions Copyright 2021-2022 Technology Concepts & Design, Inc. <support@tcdi.com>
+
+All rights reserved.
+
+Use of this source code is governed by the MIT license that can be found in the LICENSE file.
+*/
+use proc_macro2::{Span, TokenStream};
+use quote::quote;
+use syn::parse::Parser;
+use syn::punctuated::Punctuated;
+use syn::{Expr, Lit, Token};
+
+const USAGE: &str = "tenant_policy! accepts `table`, `column`, `setting`, `column_type`, `name`, \
+                     `force` and `requires`";
+
+fn string_value(expr: &Expr) -> syn::Result<String> {
+    match expr {
+        Expr::Lit(syn::ExprLit { lit: Lit::Str(value), .. }) => Ok(value.value()),
+        other => Err(syn::Error::new_spanned(other, "expected a string literal")),
+    }
+}
+
+fn bool_value(expr: &Expr) -> syn::Result<bool> {
+    match expr {
+        Expr::Lit(syn::ExprLit { lit: Lit::Bool(value), .. }) => Ok(value.value),
+        other => Err(syn::Error::new_spanned(other, "expected `true` or `false`")),
+    }
+}
+
+pub(crate) fn impl_tenant_policy(input: TokenStream) -> syn::Result<TokenStream> {
+    let mut table = None;
+    let mut column = None;
+    let mut setting = None;
+    let mut column_type = "text".to_string();
+    let mut name = None;
+    let mut force = false;
+    let mut requires = None;
+    for arg in Punctuated::<Expr, Token![,]>::parse_terminated.parse2(input)? {
+        let (left, right) = match &arg {
+            Expr::Assign(assign) => (&*assign.left, &*assign.right),
+            other => return Err(syn::Error::new_spanned(other, USAGE)),
+        };
+        let key = match left {
+            Expr::Path(path) => path.path.get_ident().map(|ident| ident.to_string()),
+            _ => None,
+        };
+        match key.as_deref() {
+            Some("table") => table = Some(string_value(right)?),
+            Some("column") => column = Some(string_value(right)?),
+            Some("setting") => setting = Some(string_value(right)?),
+            Some("column_type") => column_type = string_value(right)?,
+            Some("name") => name = Some(string_value(right)?),
+            Some("force") => force = bool_value(right)?,
+            Some("requires") => requires = Some(right.clone()),
+            _ => return Err(syn::Error::new_spanned(left, USAGE)),
+        }
+    }
+    let missing = |what: &str| syn::Error::new(Span::call_site(), format!("missing `{}`", what));
+    let table = table.ok_or_else(|| missing("table"))?;
+    let column = column.ok_or_else(|| missing("column"))?;
+    let setting = setting.ok_or_else(|| missing("setting"))?;
+    let name = name.unwrap_or_else(|| format!("{}_tenant_isolation", table.replace('.', "_")));
+
+    // an unset setting is NULL, and a reset one is empty, which both match no rows
+    let check = format!(
+        "{column} = NULLIF(pg_catalog.current_setting('{}', true), '')::{column_type}",
+        setting.replace('\'', "''")
+    );
+    let mut sql = format!("\nALTER TABLE {table} ENABLE ROW LEVEL SECURITY;\n");
+    if force {
+        sql.push_str(&format!("ALTER TABLE {table} FORCE ROW LEVEL SECURITY;\n"));
+    }
+    sql.push_str(&format!(
+        "CREATE POLICY {name} ON {table}\nUSING ({check})\nWITH CHECK ({check});\n"
+    ));
+    let sql = syn::LitStr::new(&sql, Span::call_site());
+
+    let requires = requires.map(|requires| quote! { , requires = #requires });
+    Ok(quote! {
+        ::pgrx::extension_sql!(#sql, name = #name #requires);
+    })
+}
@@ -14,15 +14,18 @@ Use of this source code is governed by the MIT license that can be found in the
 
 #include "access/amapi.h"
 #include "access/genam.h"
+#include "access/generic_xlog.h"
 #include "access/gin.h"
 #include "access/gist.h"
 #include "access/heapam.h"
 #include "access/htup.h"
 #include "access/htup_details.h"
+#include "access/multixact.h"
 #include "access/reloptions.h"
 #include "access/relscan.h"
 #include "access/skey.h"
 #include "access/sysattr.h"
+#include "access/visibilitymap.h"
 #include "access/xact.h"
 #include "catalog/dependency.h"
 #include "catalog/index.h"
@@ -33,19 +36,29 @@ Use of this source code is governed by the MIT license that can be found in the
 #include "catalog/pg_authid.h"
 #include "catalog/pg_class.h"
 #include "catalog/pg_database.h"
+#include "catalog/pg_depend.h"
 #include "catalog/pg_enum.h"
 #include "catalog/pg_extension.h"
+#include "catalog/pg_foreign_data_wrapper.h"
+#include "catalog/pg_foreign_server.h"
+#include "catalog/pg_foreign_table.h"
 #include "catalog/pg_operator.h"
 #include "catalog/pg_proc.h"
 #include "catalog/pg_namespace.h"
+#include "catalog/pg_rewrite.h"
 #include "catalog/pg_tablespace.h"
 #include "catalog/pg_trigger.h"
 #include "catalog/pg_type.h"
+#include "catalog/pg_user_mapping.h"
+#include "catalog/storage_xlog.h"
+#include "commands/cluster.h"
 #include "commands/comment.h"
 #include "commands/dbcommands.h"
 #include "commands/defrem.h"
 #include "commands/event_trigger.h"
 #include "commands/explain.h"
+#include "commands/extension.h"
+#include "commands/matview.h"
 #include "commands/proclang.h"
 #include "commands/tablespace.h"
 #include "commands/tablecmds.h"
@@ -57,6 +70,7 @@ Use of this source code is governed by the MIT license that can be found in the
 #include "executor/spi.h"
 #include "foreign/fdwapi.h"
 #include "foreign/foreign.h"
+#include "libpq/auth.h"
 #include "mb/pg_wchar.h"
 
 #define ScanKey struct ScanKeyData *
@@ -90,6 +104,7 @@ Use of this source code is governed by the MIT license that can be found in the
 #include "parser/parsetree.h"
 #include "plpgsql.h"
 #include "postmaster/bgworker.h"
+#include "postmaster/bgwriter.h"
 #include "replication/logical.h"
 #include "replication/output_plugin.h"
 #include "rewrite/rewriteHandler.h"
@@ -97,11 +112,14 @@ Use of this source code is governed by the MIT license that can be found in the
 #include "storage/block.h"
 #include "storage/bufmgr.h"
 #include "storage/buffile.h"
+#include "storage/freespace.h"
 #include "storage/ipc.h"
 #include "storage/itemptr.h"
+#include "storage/lmgr.h"
 #include "storage/lwlock.h"
 #include "storage/procarray.h"
 #include "storage/spin.h"
+#include "tcop/pquery.h"
 #include "tcop/tcopprot.h"
 #include "tcop/utility.h"
 #include "tsearch/ts_public.h"
@@ -111,23 +129,30 @@ Use of this source code is governed by the MIT license that can be found in the
 #include "utils/datetime.h"
 
 #define double float8
+#include "utils/datum.h"
+#include "utils/fmgroids.h"
 #include "utils/geo_decls.h"
 #undef double
 
 #include "utils/elog.h"
 #include "utils/fmgrprotos.h"
 #include "utils/guc.h"
+#include "utils/inval.h"
 #include "utils/json.h"
 #include "utils/jsonb.h"
 #include "utils/lsyscache.h"
 #include "utils/memutils.h"
 #include "utils/numeric.h"
 #include "utils/palloc.h"
+#include "utils/portal.h"
 #include "utils/rel.h"
 #include "utils/relcache.h"
 #include "utils/sampling.h"
 #include "utils/selfuncs.h"
 #include "utils/snapmgr.h"
+#include "utils/spccache.h"
 #include "utils/syscache.h"
+#include "utils/timeout.h"
 #include "utils/typcache.h"
 #include "utils/rangetypes.h"
+#include "windowapi.h"
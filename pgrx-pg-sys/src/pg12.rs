@@ -22711,6 +22711,38 @@ extern "C" {
     pub fn LockRelease(locktag: *const LOCKTAG, lockmode: LOCKMODE, sessionLock: bool) -> bool;
 }
 #[pgrx_macros::pg_guard]
+extern "C" {
+    pub fn LockRelationOid(relid: Oid, lockmode: LOCKMODE);
+}
+#[pgrx_macros::pg_guard]
+extern "C" {
+    pub fn ConditionalLockRelationOid(relid: Oid, lockmode: LOCKMODE) -> bool;
+}
+#[pgrx_macros::pg_guard]
+extern "C" {
+    pub fn UnlockRelationOid(relid: Oid, lockmode: LOCKMODE);
+}
+#[pgrx_macros::pg_guard]
+extern "C" {
+    pub fn LockRelation(relation: Relation, lockmode: LOCKMODE);
+}
+#[pgrx_macros::pg_guard]
+extern "C" {
+    pub fn ConditionalLockRelation(relation: Relation, lockmode: LOCKMODE) -> bool;
+}
+#[pgrx_macros::pg_guard]
+extern "C" {
+    pub fn UnlockRelation(relation: Relation, lockmode: LOCKMODE);
+}
+#[pgrx_macros::pg_guard]
+extern "C" {
+    pub fn CheckRelationLockedByMe(
+        relation: Relation,
+        lockmode: LOCKMODE,
+        orstronger: bool,
+    ) -> bool;
+}
+#[pgrx_macros::pg_guard]
 extern "C" {
     pub fn LockReleaseAll(lockmethodid: LOCKMETHODID, allLocks: bool);
 }
@@ -61552,3 +61584,206 @@ impl std::fmt::Display for XmlSerialize {
         write!(f, "{}", self.display_node())
     }
 }
+#[pgrx_macros::pg_guard]
+extern "C" {
+    pub fn make_new_heap(OIDOldHeap: Oid, NewTableSpace: Oid, relpersistence: ::std::os::raw::c_char, lockmode: LOCKMODE) -> Oid;
+}
+#[pgrx_macros::pg_guard]
+extern "C" {
+    pub fn finish_heap_swap(OIDOldHeap: Oid, OIDNewHeap: Oid, is_system_catalog: bool, swap_toast_by_content: bool, check_constraints: bool, is_internal: bool, frozenXid: TransactionId, minMulti: MultiXactId, newrelpersistence: ::std::os::raw::c_char);
+}
+#[pgrx_macros::pg_guard]
+extern "C" {
+    pub fn ReadNextMultiXactId() -> MultiXactId;
+}
+pub const VISIBILITYMAP_ALL_VISIBLE: u32 = 1;
+pub const VISIBILITYMAP_ALL_FROZEN: u32 = 2;
+#[pgrx_macros::pg_guard]
+extern "C" {
+    pub fn visibilitymap_get_status(rel: Relation, heapBlk: BlockNumber, vmbuf: *mut Buffer) -> uint8;
+}
+#[pgrx_macros::pg_guard]
+extern "C" {
+    pub fn visibilitymap_count(rel: Relation, all_visible: *mut BlockNumber, all_frozen: *mut BlockNumber);
+}
+#[pgrx_macros::pg_guard]
+extern "C" {
+    pub fn GetRecordedFreeSpace(rel: Relation, heapBlk: BlockNumber) -> Size;
+}
+#[pgrx_macros::pg_guard]
+extern "C" {
+    pub fn get_tablespace_page_costs(spcid: Oid, spc_random_page_cost: *mut f64, spc_seq_page_cost: *mut f64);
+}
+pub type SyscacheCallbackFunction = ::std::option::Option<
+    unsafe extern "C" fn(arg: Datum, cacheid: ::std::os::raw::c_int, hashvalue: uint32),
+>;
+#[pgrx_macros::pg_guard]
+extern "C" {
+    pub fn CacheRegisterSyscacheCallback(cacheid: ::std::os::raw::c_int, func: SyscacheCallbackFunction, arg: Datum);
+}
+#[pgrx_macros::pg_guard]
+extern "C" {
+    pub fn get_extension_oid(extname: *const ::std::os::raw::c_char, missing_ok: bool) -> Oid;
+}
+#[pgrx_macros::pg_guard]
+extern "C" {
+    pub fn PortalRunFetch(portal: Portal, fdirection: FetchDirection, count: ::std::os::raw::c_long, dest: *mut DestReceiver) -> uint64;
+}
+#[pgrx_macros::pg_guard]
+extern "C" {
+    pub fn RequestCheckpoint(flags: ::std::os::raw::c_int);
+}
+pub const SyncRequestType_SYNC_REQUEST: SyncRequestType = 0;
+pub const SyncRequestType_SYNC_UNLINK_REQUEST: SyncRequestType = 1;
+pub const SyncRequestType_SYNC_FORGET_REQUEST: SyncRequestType = 2;
+pub const SyncRequestType_SYNC_FILTER_REQUEST: SyncRequestType = 3;
+pub type SyncRequestType = ::std::os::raw::c_uint;
+pub const SyncRequestHandler_SYNC_HANDLER_MD: SyncRequestHandler = 0;
+pub type SyncRequestHandler = ::std::os::raw::c_uint;
+#[repr(C)]
+#[derive(Debug, Copy, Clone)]
+pub struct FileTag {
+    pub handler: int16,
+    pub forknum: int16,
+    pub rnode: RelFileNode,
+    pub segno: uint32,
+}
+#[pgrx_macros::pg_guard]
+extern "C" {
+    pub fn RegisterSyncRequest(ftag: *const FileTag, type_: SyncRequestType, retryOnError: bool) -> bool;
+}
+pub type ClientAuthentication_hook_type = ::std::option::Option<unsafe extern "C" fn(arg1: *mut Port, arg2: ::std::os::raw::c_int)>;
+#[pgrx_macros::pg_guard]
+extern "C" {
+    pub static mut ClientAuthentication_hook: ClientAuthentication_hook_type;
+}
+pub const TimeoutId_STATEMENT_TIMEOUT: TimeoutId = 3;
+pub const TimeoutId_USER_TIMEOUT: TimeoutId = 8;
+pub const TimeoutId_MAX_TIMEOUTS: TimeoutId = 18;
+pub type TimeoutId = ::std::os::raw::c_uint;
+pub type timeout_handler_proc = ::std::option::Option<unsafe extern "C" fn()>;
+#[pgrx_macros::pg_guard]
+extern "C" {
+    pub fn RegisterTimeout(id: TimeoutId, handler: timeout_handler_proc) -> TimeoutId;
+}
+#[pgrx_macros::pg_guard]
+extern "C" {
+    pub fn enable_timeout_after(id: TimeoutId, delay_ms: ::std::os::raw::c_int);
+}
+#[pgrx_macros::pg_guard]
+extern "C" {
+    pub fn disable_timeout(id: TimeoutId, keep_indicator: bool);
+}
+#[pgrx_macros::pg_guard]
+extern "C" {
+    pub fn get_timeout_indicator(id: TimeoutId, reset_indicator: bool) -> bool;
+}
+#[pgrx_macros::pg_guard]
+extern "C" {
+    pub static mut ActivePortal: Portal;
+}
+#[repr(C)]
+#[derive(Debug, Copy, Clone)]
+pub struct GenericXLogState {
+    _unused: [u8; 0],
+}
+pub const GENERIC_XLOG_FULL_IMAGE: u32 = 1;
+#[pgrx_macros::pg_guard]
+extern "C" {
+    pub fn GenericXLogStart(relation: Relation) -> *mut GenericXLogState;
+}
+#[pgrx_macros::pg_guard]
+extern "C" {
+    pub fn GenericXLogRegisterBuffer(state: *mut GenericXLogState, buffer: Buffer, flags: ::std::os::raw::c_int) -> Page;
+}
+#[pgrx_macros::pg_guard]
+extern "C" {
+    pub fn GenericXLogFinish(state: *mut GenericXLogState) -> XLogRecPtr;
+}
+#[pgrx_macros::pg_guard]
+extern "C" {
+    pub fn GenericXLogAbort(state: *mut GenericXLogState);
+}
+#[pgrx_macros::pg_guard]
+extern "C" {
+    pub fn LockRelationForExtension(relation: Relation, lockmode: LOCKMODE);
+}
+#[pgrx_macros::pg_guard]
+extern "C" {
+    pub fn UnlockRelationForExtension(relation: Relation, lockmode: LOCKMODE);
+}
+#[pgrx_macros::pg_guard]
+extern "C" {
+    pub fn log_smgrcreate(rnode: *const RelFileNode, forkNum: ForkNumber);
+}
+pub const WINDOW_SEEK_CURRENT: u32 = 0;
+pub const WINDOW_SEEK_HEAD: u32 = 1;
+pub const WINDOW_SEEK_TAIL: u32 = 2;
+pub type WindowObject = *mut WindowObjectData;
+#[pgrx_macros::pg_guard]
+extern "C" {
+    pub fn WinGetPartitionLocalMemory(winobj: WindowObject, sz: Size) -> *mut ::std::os::raw::c_void;
+}
+#[pgrx_macros::pg_guard]
+extern "C" {
+    pub fn WinGetCurrentPosition(winobj: WindowObject) -> int64;
+}
+#[pgrx_macros::pg_guard]
+extern "C" {
+    pub fn WinGetPartitionRowCount(winobj: WindowObject) -> int64;
+}
+#[pgrx_macros::pg_guard]
+extern "C" {
+    pub fn WinSetMarkPosition(winobj: WindowObject, markpos: int64);
+}
+#[pgrx_macros::pg_guard]
+extern "C" {
+    pub fn WinRowsArePeers(winobj: WindowObject, pos1: int64, pos2: int64) -> bool;
+}
+#[pgrx_macros::pg_guard]
+extern "C" {
+    pub fn WinGetFuncArgInPartition(winobj: WindowObject, argno: ::std::os::raw::c_int, relpos: ::std::os::raw::c_int, seektype: ::std::os::raw::c_int, set_mark: bool, isnull: *mut bool, isout: *mut bool) -> Datum;
+}
+#[pgrx_macros::pg_guard]
+extern "C" {
+    pub fn WinGetFuncArgInFrame(winobj: WindowObject, argno: ::std::os::raw::c_int, relpos: ::std::os::raw::c_int, seektype: ::std::os::raw::c_int, set_mark: bool, isnull: *mut bool, isout: *mut bool) -> Datum;
+}
+#[pgrx_macros::pg_guard]
+extern "C" {
+    pub fn WinGetFuncArgCurrent(winobj: WindowObject, argno: ::std::os::raw::c_int, isnull: *mut bool) -> Datum;
+}
+pub const DependRelationId: Oid = Oid(2608);
+pub const RewriteRelationId: Oid = Oid(2618);
+pub const Anum_pg_depend_classid: u32 = 1;
+pub const Anum_pg_depend_objid: u32 = 2;
+pub const F_OIDEQ: u32 = 184;
+#[repr(C)]
+#[derive(Debug, Default, Copy, Clone)]
+pub struct FormData_pg_depend {
+    pub classid: Oid,
+    pub objid: Oid,
+    pub objsubid: int32,
+    pub refclassid: Oid,
+    pub refobjid: Oid,
+    pub refobjsubid: int32,
+    pub deptype: ::std::os::raw::c_char,
+}
+pub type Form_pg_depend = *mut FormData_pg_depend;
+#[pgrx_macros::pg_guard]
+extern "C" {
+    pub fn ExecRefreshMatView(stmt: *mut RefreshMatViewStmt, queryString: *const ::std::os::raw::c_char, params: ParamListInfo, completionTag: *mut ::std::os::raw::c_char) -> ObjectAddress;
+}
+#[pgrx_macros::pg_guard]
+extern "C" {
+    pub fn datumCopy(value: Datum, typByVal: bool, typLen: ::std::os::raw::c_int) -> Datum;
+}
+pub const ForeignDataWrapperRelationId: Oid = Oid(2328);
+pub const ForeignServerRelationId: Oid = Oid(1417);
+pub const UserMappingRelationId: Oid = Oid(1418);
+pub const ForeignTableRelationId: Oid = Oid(3118);
+pub type RelcacheCallbackFunction =
+    ::std::option::Option<unsafe extern "C" fn(arg: Datum, relid: Oid)>;
+#[pgrx_macros::pg_guard]
+extern "C" {
+    pub fn CacheRegisterRelcacheCallback(func: RelcacheCallbackFunction, arg: Datum);
+}
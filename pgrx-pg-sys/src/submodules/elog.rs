@@ -319,6 +319,10 @@ macro_rules! function_name {
 /// - `message: String`
 /// - (optional) `detail: String`
 ///
+/// Or, after the message and a `;`, any of the [`ErrorReport`](crate::panic::ErrorReport) setters,
+/// chained with `.`, to fill in the report's hint, detail, context, the names of the objects it's
+/// about, and whether Postgres should log a backtrace.
+///
 /// ## Examples
 ///
 /// ```rust,no_run
@@ -334,8 +338,33 @@ macro_rules! function_name {
 /// # use pgrx_pg_sys::errcodes::PgSqlErrorCode;
 /// ereport!(PgLogLevel::LOG, PgSqlErrorCode::ERRCODE_SUCCESSFUL_COMPLETION, "this is just a message"); // log output only
 /// ```
+///
+/// ```rust,no_run
+/// # use pgrx_pg_sys::ereport;
+/// # use pgrx_pg_sys::errcodes::PgSqlErrorCode;
+/// ereport!(ERROR, PgSqlErrorCode::ERRCODE_CHECK_VIOLATION, "order total is negative";
+///     set_detail("the total was -10")
+///     .set_hint("refunds are recorded in the refunds table")
+///     .set_table_name("orders")
+///     .set_column_name("total")
+///     .set_constraint_name("orders_total_check")
+/// );
+/// ```
 #[macro_export]
 macro_rules! ereport {
+    (ERROR, $errcode:expr, $message:expr; $($setter:ident($($arg:expr),* $(,)?)).+ $(,)?) => {
+        $crate::panic::ErrorReport::new($errcode, $message, $crate::function_name!())
+            $(.$setter($($arg),*))+
+            .report($crate::elog::PgLogLevel::ERROR);
+        unreachable!();
+    };
+
+    ($loglevel:expr, $errcode:expr, $message:expr; $($setter:ident($($arg:expr),* $(,)?)).+ $(,)?) => {
+        $crate::panic::ErrorReport::new($errcode, $message, $crate::function_name!())
+            $(.$setter($($arg),*))+
+            .report($loglevel);
+    };
+
     (ERROR, $errcode:expr, $message:expr) => {
         $crate::panic::ErrorReport::new($errcode, $message, $crate::function_name!())
             .report($crate::elog::PgLogLevel::ERROR);
@@ -1,6 +1,7 @@
 use std::fmt::{Display, Formatter};
 
-/// This list of SQL Error Codes is taken directly from Postgres 12's generated "utils/errcodes.h"
+/// This list of SQL Error Codes is taken directly from Postgres 12's generated "utils/errcodes.h",
+/// plus those added by later versions
 #[allow(non_camel_case_types)]
 #[derive(Clone, Copy, Debug, Eq, PartialEq, Hash, Ord, PartialOrd)]
 pub enum PgSqlErrorCode {
@@ -317,6 +318,7 @@ pub enum PgSqlErrorCode {
     ERRCODE_CRASH_SHUTDOWN = MAKE_SQLSTATE('5', '7', 'P', '0', '2') as isize,
     ERRCODE_CANNOT_CONNECT_NOW = MAKE_SQLSTATE('5', '7', 'P', '0', '3') as isize,
     ERRCODE_DATABASE_DROPPED = MAKE_SQLSTATE('5', '7', 'P', '0', '4') as isize,
+    ERRCODE_IDLE_SESSION_TIMEOUT = MAKE_SQLSTATE('5', '7', 'P', '0', '5') as isize,
 
     /// Class 58 - System Error (errors external to PostgreSQL itself) as isize,
     ERRCODE_SYSTEM_ERROR = MAKE_SQLSTATE('5', '8', '0', '0', '0') as isize,
@@ -384,6 +386,29 @@ impl Display for PgSqlErrorCode {
 
 impl std::error::Error for PgSqlErrorCode {}
 
+impl PgSqlErrorCode {
+    /// The five character SQLSTATE of this error code, such as `"23505"`
+    pub fn sqlstate(&self) -> String {
+        let code = *self as i32;
+        (0..5).map(|i| (((code >> (6 * i)) & 0x3F) as u8 + b'0') as char).collect()
+    }
+
+    /// The error code of a five character SQLSTATE, such as `"23505"`.  Returns `None` if
+    /// `sqlstate` isn't one
+    pub fn from_sqlstate(sqlstate: &str) -> Option<Self> {
+        match sqlstate.as_bytes() {
+            &[a, b, c, d, e]
+                if sqlstate.bytes().all(|ch| ch.is_ascii_digit() || ch.is_ascii_uppercase()) =>
+            {
+                let code = MAKE_SQLSTATE(a as char, b as char, c as char, d as char, e as char);
+                let errcode = PgSqlErrorCode::from(code);
+                (errcode as i32 == code).then(|| errcode)
+            }
+            _ => None,
+        }
+    }
+}
+
 impl From<i32> for PgSqlErrorCode {
     fn from(error_code: i32) -> Self {
         (error_code as isize).into()
@@ -1127,6 +1152,9 @@ impl From<isize> for PgSqlErrorCode {
             x if x == PgSqlErrorCode::ERRCODE_DATABASE_DROPPED as isize => {
                 PgSqlErrorCode::ERRCODE_DATABASE_DROPPED
             }
+            x if x == PgSqlErrorCode::ERRCODE_IDLE_SESSION_TIMEOUT as isize => {
+                PgSqlErrorCode::ERRCODE_IDLE_SESSION_TIMEOUT
+            }
 
             x if x == PgSqlErrorCode::ERRCODE_SYSTEM_ERROR as isize => {
                 PgSqlErrorCode::ERRCODE_SYSTEM_ERROR
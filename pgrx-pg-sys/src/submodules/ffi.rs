@@ -75,7 +75,9 @@ being converted into a transaction-aborting Postgres `ERROR` by PGRX.
 [trivially-deallocated stack frame]: https://github.com/rust-lang/rfcs/blob/master/text/2945-c-unwind-abi.md#plain-old-frames
 **/
 use crate as pg_sys;
-use crate::panic::{CaughtError, ErrorReport, ErrorReportLocation, ErrorReportWithLevel};
+use crate::panic::{
+    CaughtError, ErrorReport, ErrorReportFields, ErrorReportLocation, ErrorReportWithLevel,
+};
 use core::ffi::CStr;
 
 #[inline(always)]
@@ -145,6 +147,22 @@ unsafe fn pg_guard_ffi_boundary_impl<T, F: FnOnce() -> T>(f: F) -> T {
             let hint = errdata.hint.is_null().then(|| None).unwrap_or_else(|| {
                 Some(CStr::from_ptr(errdata.hint).to_string_lossy().to_string())
             });
+            let context = errdata.context.is_null().then(|| None).unwrap_or_else(|| {
+                Some(CStr::from_ptr(errdata.context).to_string_lossy().to_string())
+            });
+            let field = |value: *const std::os::raw::c_char| {
+                value
+                    .is_null()
+                    .then(|| None)
+                    .unwrap_or_else(|| Some(CStr::from_ptr(value).to_string_lossy().to_string()))
+            };
+            let fields = ErrorReportFields {
+                schema_name: field(errdata.schema_name),
+                table_name: field(errdata.table_name),
+                column_name: field(errdata.column_name),
+                datatype_name: field(errdata.datatype_name),
+                constraint_name: field(errdata.constraint_name),
+            };
             let funcname = errdata.funcname.is_null().then(|| None).unwrap_or_else(|| {
                 Some(CStr::from_ptr(errdata.funcname).to_string_lossy().to_string())
             });
@@ -169,6 +187,9 @@ unsafe fn pg_guard_ffi_boundary_impl<T, F: FnOnce() -> T>(f: F) -> T {
                     message,
                     detail,
                     hint,
+                    context,
+                    fields,
+                    errbacktrace: false,
                     location: ErrorReportLocation { file, funcname, line, col: 0, backtrace: None },
                 },
             }))
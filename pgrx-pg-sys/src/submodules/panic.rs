@@ -128,9 +128,23 @@ pub struct ErrorReport {
     pub(crate) message: String,
     pub(crate) hint: Option<String>,
     pub(crate) detail: Option<String>,
+    pub(crate) context: Option<String>,
+    pub(crate) fields: ErrorReportFields,
+    pub(crate) errbacktrace: bool,
     pub(crate) location: ErrorReportLocation,
 }
 
+/// The names of the database objects an [`ErrorReport`] is about, which clients can read as fields
+/// of the error apart from its message
+#[derive(Debug, Default, Clone, PartialEq, Eq)]
+pub struct ErrorReportFields {
+    pub schema_name: Option<String>,
+    pub table_name: Option<String>,
+    pub column_name: Option<String>,
+    pub datatype_name: Option<String>,
+    pub constraint_name: Option<String>,
+}
+
 impl Display for ErrorReport {
     fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
         write!(f, "{}: {}", self.sqlerrcode, self.message)?;
@@ -140,6 +154,9 @@ impl Display for ErrorReport {
         if let Some(detail) = &self.detail {
             write!(f, "\nDETAIL: {}", detail)?;
         }
+        if let Some(context) = &self.context {
+            write!(f, "\nCONTEXT: {}", context)?;
+        }
         write!(f, "\nLOCATION: {}", self.location)
     }
 }
@@ -202,6 +219,16 @@ impl ErrorReportWithLevel {
         self.inner.hint()
     }
 
+    /// Returns the context of this error report, if there is one
+    pub fn context(&self) -> Option<&str> {
+        self.inner.context()
+    }
+
+    /// Returns the names of the database objects this error report is about
+    pub fn fields(&self) -> &ErrorReportFields {
+        self.inner.fields()
+    }
+
     /// Returns the name of the source file that generated this error report
     pub fn file(&self) -> &str {
         &self.inner.location.file
@@ -223,9 +250,8 @@ impl ErrorReportWithLevel {
     }
 
     /// Returns the context message of this error report, if any
-    fn context_message(&self) -> Option<String> {
-        // NB:  holding this here for future use
-        None
+    fn context_message(&self) -> Option<&str> {
+        self.inner.context()
     }
 }
 
@@ -243,7 +269,16 @@ impl ErrorReport {
         let mut location: ErrorReportLocation = Location::caller().into();
         location.funcname = Some(funcname.to_string());
 
-        Self { sqlerrcode, message: message.into(), hint: None, detail: None, location }
+        Self {
+            sqlerrcode,
+            message: message.into(),
+            hint: None,
+            detail: None,
+            context: None,
+            fields: Default::default(),
+            errbacktrace: false,
+            location,
+        }
     }
 
     /// Create a [PgErrorReport] which can be raised via Rust's [std::panic::panic_any()] or as
@@ -255,7 +290,16 @@ impl ErrorReport {
         message: S,
         location: ErrorReportLocation,
     ) -> Self {
-        Self { sqlerrcode, message: message.into(), hint: None, detail: None, location }
+        Self {
+            sqlerrcode,
+            message: message.into(),
+            hint: None,
+            detail: None,
+            context: None,
+            fields: Default::default(),
+            errbacktrace: false,
+            location,
+        }
     }
 
     /// Set the `detail` property, whose default is `None`
@@ -270,6 +314,56 @@ impl ErrorReport {
         self
     }
 
+    /// Set the `context` property, whose default is `None`.  Postgres adds this to the context of
+    /// the error, before that of any functions it was raised within
+    pub fn set_context<S: Into<String>>(mut self, context: S) -> Self {
+        self.context = Some(context.into());
+        self
+    }
+
+    /// Set the name of the schema this error is about, whose default is `None`
+    pub fn set_schema_name<S: Into<String>>(mut self, schema_name: S) -> Self {
+        self.fields.schema_name = Some(schema_name.into());
+        self
+    }
+
+    /// Set the name of the table this error is about, whose default is `None`
+    pub fn set_table_name<S: Into<String>>(mut self, table_name: S) -> Self {
+        self.fields.table_name = Some(table_name.into());
+        self
+    }
+
+    /// Set the name of the column this error is about, whose default is `None`
+    pub fn set_column_name<S: Into<String>>(mut self, column_name: S) -> Self {
+        self.fields.column_name = Some(column_name.into());
+        self
+    }
+
+    /// Set the name of the data type this error is about, whose default is `None`
+    pub fn set_datatype_name<S: Into<String>>(mut self, datatype_name: S) -> Self {
+        self.fields.datatype_name = Some(datatype_name.into());
+        self
+    }
+
+    /// Set the name of the constraint this error is about, whose default is `None`
+    pub fn set_constraint_name<S: Into<String>>(mut self, constraint_name: S) -> Self {
+        self.fields.constraint_name = Some(constraint_name.into());
+        self
+    }
+
+    /// Ask Postgres to log a backtrace of the server where this error is reported, as its
+    /// `errbacktrace()` does.  The default is `false`.  Postgres 11 and 12 don't support this, and
+    /// ignore it
+    pub fn set_errbacktrace(mut self, errbacktrace: bool) -> Self {
+        self.errbacktrace = errbacktrace;
+        self
+    }
+
+    /// Returns the error code of this error report
+    pub fn sql_error_code(&self) -> PgSqlErrorCode {
+        self.sqlerrcode
+    }
+
     /// Returns the error message of this error report
     pub fn message(&self) -> &str {
         &self.message
@@ -285,6 +379,16 @@ impl ErrorReport {
         self.hint.as_ref().map(|s| s.as_str())
     }
 
+    /// Returns the context message of this error report
+    pub fn context(&self) -> Option<&str> {
+        self.context.as_ref().map(|s| s.as_str())
+    }
+
+    /// Returns the names of the database objects this error report is about
+    pub fn fields(&self) -> &ErrorReportFields {
+        &self.fields
+    }
+
     /// Report this [PgErrorReport], which will ultimately be reported by Postgres at the specified [PgLogLevel]
     ///
     /// If the provided `level` is >= [`PgLogLevel::ERROR`] this function will not return.
@@ -325,6 +429,35 @@ pub enum CaughtError {
 }
 
 impl CaughtError {
+    /// The error report of this [CaughtError], whichever kind it is
+    pub fn ereport(&self) -> &ErrorReportWithLevel {
+        match self {
+            CaughtError::PostgresError(ereport)
+            | CaughtError::ErrorReport(ereport)
+            | CaughtError::RustPanic { ereport, .. } => ereport,
+        }
+    }
+
+    /// Returns the sql error code of this error
+    pub fn sql_error_code(&self) -> PgSqlErrorCode {
+        self.ereport().sql_error_code()
+    }
+
+    /// Returns the error message of this error
+    pub fn message(&self) -> &str {
+        self.ereport().message()
+    }
+
+    /// Returns the detail line of this error, if there is one
+    pub fn detail(&self) -> Option<&str> {
+        self.ereport().detail()
+    }
+
+    /// Returns the hint line of this error, if there is one
+    pub fn hint(&self) -> Option<&str> {
+        self.ereport().hint()
+    }
+
     /// Rethrow this [CaughtError].  
     ///
     /// This is the same as [std::panic::resume_unwind()] and has the same semantics.
@@ -500,6 +633,36 @@ fn do_ereport(ereport: ErrorReportWithLevel) {
         fn errdetail(fmt: *const ::std::os::raw::c_char, ...) -> ::std::os::raw::c_int;
         fn errhint(fmt: *const ::std::os::raw::c_char, ...) -> ::std::os::raw::c_int;
         fn errcontext_msg(fmt: *const ::std::os::raw::c_char, ...) -> ::std::os::raw::c_int;
+        fn err_generic_string(
+            field: ::std::os::raw::c_int,
+            str_: *const ::std::os::raw::c_char,
+        ) -> ::std::os::raw::c_int;
+        #[cfg(any(feature = "pg13", feature = "pg14", feature = "pg15"))]
+        fn errbacktrace() -> ::std::os::raw::c_int;
+    }
+
+    /// The object names of `fields`, as palloc'd strings for [`report_fields()`]
+    fn field_cstrs(fields: &ErrorReportFields) -> [(u8, *mut ::std::os::raw::c_char); 5] {
+        [
+            (crate::PG_DIAG_SCHEMA_NAME, (&fields.schema_name).as_pg_cstr()),
+            (crate::PG_DIAG_TABLE_NAME, (&fields.table_name).as_pg_cstr()),
+            (crate::PG_DIAG_COLUMN_NAME, (&fields.column_name).as_pg_cstr()),
+            (crate::PG_DIAG_DATATYPE_NAME, (&fields.datatype_name).as_pg_cstr()),
+            (crate::PG_DIAG_CONSTRAINT_NAME, (&fields.constraint_name).as_pg_cstr()),
+        ]
+    }
+
+    /// Attach the object names from [`field_cstrs()`] to the error being reported, and free them
+    ///
+    /// # Safety
+    ///
+    /// Must be called between `errstart()` returning true and `errfinish()`
+    #[rustfmt::skip]
+    unsafe fn report_fields(fields: [(u8, *mut ::std::os::raw::c_char); 5]) {
+        for (field, value) in fields {
+            // SAFETY:  `err_generic_string()` copies `value` into the error's memory context
+            if !value.is_null() { unsafe { err_generic_string(field as _, value); pfree(value.cast()); } }
+        }
     }
 
     /// do_ereport impl for postgres 13 and later
@@ -523,6 +686,8 @@ fn do_ereport(ereport: ErrorReportWithLevel) {
                 let detail = ereport.detail_with_backtrace().as_pg_cstr();
                 let hint = ereport.hint().as_pg_cstr();
                 let context = ereport.context_message().as_pg_cstr();
+                let fields = field_cstrs(ereport.fields());
+                let backtrace = ereport.inner.errbacktrace;
                 let lineno = ereport.line_number();
 
                 // SAFETY:  We know that `crate::ErrorContext` is a valid memory context pointer and one
@@ -548,6 +713,8 @@ fn do_ereport(ereport: ErrorReportWithLevel) {
                 if !detail.is_null()  { errdetail(PERCENT_S.as_ptr(), detail);       pfree(detail.cast());  }
                 if !hint.is_null()    { errhint(PERCENT_S.as_ptr(), hint);           pfree(hint.cast());    }
                 if !context.is_null() { errcontext_msg(PERCENT_S.as_ptr(), context); pfree(context.cast()); }
+                report_fields(fields);
+                if backtrace          { errbacktrace(); }
 
                 errfinish(file, lineno as _, funcname);
 
@@ -597,7 +764,7 @@ fn do_ereport(ereport: ErrorReportWithLevel) {
                 let detail = ereport.detail_with_backtrace().as_pg_cstr();
                 let hint = ereport.hint().as_pg_cstr();
                 let context = ereport.context_message().as_pg_cstr();
-
+                let fields = field_cstrs(ereport.fields());
 
                 // do not leak the Rust `ErrorReportWithLocation` instance
                 drop(ereport);
@@ -613,6 +780,7 @@ fn do_ereport(ereport: ErrorReportWithLevel) {
                 if !detail.is_null()  { errdetail(PERCENT_S.as_ptr(), detail);       pfree(detail.cast());  }
                 if !hint.is_null()    { errhint(PERCENT_S.as_ptr(), hint);           pfree(hint.cast());    }
                 if !context.is_null() { errcontext_msg(PERCENT_S.as_ptr(), context); pfree(context.cast()); }
+                report_fields(fields);
 
                 errfinish(0);
             }
@@ -7,6 +7,10 @@ use std::panic::{catch_unwind, resume_unwind, AssertUnwindSafe, RefUnwindSafe, U
 ///
 /// A primary difference is that the [`PgTryBuilder::finally()`] block runs even if a catch handler
 /// rethrows (or throws a new) error.
+///
+/// A caught Postgres error leaves behind whatever the closure had done before it was raised, just as
+/// it would in C.  To recover from one and carry on with the transaction, run the closure in a
+/// [`PgTryBuilder::subtransaction()`], which is rolled back before any catch handler runs.
 pub struct PgTryBuilder<'a, R, F: FnOnce() -> R + UnwindSafe> {
     func: F,
     when: BTreeMap<
@@ -16,6 +20,28 @@ pub struct PgTryBuilder<'a, R, F: FnOnce() -> R + UnwindSafe> {
     others: Option<Box<dyn FnMut(CaughtError) -> R + 'a + UnwindSafe + RefUnwindSafe>>,
     rust: Option<Box<dyn FnMut(CaughtError) -> R + 'a + UnwindSafe + RefUnwindSafe>>,
     finally: Option<Box<dyn FnMut() + 'a>>,
+    subtransaction: bool,
+}
+
+/// Start building a [`PgTryBuilder`] for `func`.  This is the same as [`PgTryBuilder::new()`]
+///
+/// ## Example
+///
+/// ```rust,no_run
+/// # use pgrx_pg_sys::{ereport, pg_try};
+/// # use pgrx_pg_sys::errcodes::PgSqlErrorCode;
+/// let sqlstate = pg_try(|| -> String {
+///     ereport!(ERROR, PgSqlErrorCode::ERRCODE_DIVISION_BY_ZERO, "division by zero");
+/// })
+/// .subtransaction()
+/// .catch_others(|e| e.sql_error_code().sqlstate())
+/// .execute();
+///
+/// assert_eq!(sqlstate, "22012");
+/// ```
+#[must_use = "must call `PgTryBuilder::execute(self)` in order for it to run"]
+pub fn pg_try<'a, R, F: FnOnce() -> R + UnwindSafe>(func: F) -> PgTryBuilder<'a, R, F> {
+    PgTryBuilder::new(func)
 }
 
 impl<'a, R, F: FnOnce() -> R + UnwindSafe> PgTryBuilder<'a, R, F> {
@@ -48,7 +74,14 @@ impl<'a, R, F: FnOnce() -> R + UnwindSafe> PgTryBuilder<'a, R, F> {
     /// ```
     #[must_use = "must call `PgTryBuilder::execute(self)` in order for it to run"]
     pub fn new(func: F) -> Self {
-        Self { func, when: Default::default(), others: None, rust: None, finally: None }
+        Self {
+            func,
+            when: Default::default(),
+            others: None,
+            rust: None,
+            finally: None,
+            subtransaction: false,
+        }
     }
 
     /// Add a catch handler to run should a specific error occur during execution.
@@ -125,10 +158,30 @@ impl<'a, R, F: FnOnce() -> R + UnwindSafe> PgTryBuilder<'a, R, F> {
         self
     }
 
+    /// Run the main execution block closure in an internal subtransaction, like PL/pgSQL runs a
+    /// block with an `EXCEPTION` clause.
+    ///
+    /// The closure runs in the caller's memory context, so what it returns outlives the
+    /// subtransaction.  If it raises an error, the subtransaction is rolled back, undoing whatever
+    /// it did in the database and releasing its locks, buffers and SPI connections, before a catch
+    /// handler sees the [`CaughtError`].  After that, the transaction can carry on as if the closure
+    /// had never run.
+    ///
+    /// This must be used within a transaction.
+    #[must_use = "must call `PgTryBuilder::execute(self)` in order for it to run"]
+    pub fn subtransaction(mut self) -> Self {
+        self.subtransaction = true;
+        self
+    }
+
     /// Run the main execution block closure.  Any error raised will be passed to a registered
     /// catch handler, and when finished, the finally block will be run.
     pub fn execute(mut self) -> R {
+        let subtransaction = self.subtransaction.then(Subtransaction::begin);
         let result = catch_unwind(self.func);
+        if let Some(subtransaction) = subtransaction {
+            subtransaction.end(result.is_ok());
+        }
 
         fn finally<F: FnMut()>(f: &mut Option<F>) {
             if let Some(f) = f {
@@ -218,3 +271,40 @@ impl<'a, R, F: FnOnce() -> R + UnwindSafe> PgTryBuilder<'a, R, F> {
         result
     }
 }
+
+/// The caller's state across an internal subtransaction, to return to when it ends
+struct Subtransaction {
+    memory_context: crate::MemoryContext,
+    resource_owner: crate::ResourceOwner,
+}
+
+impl Subtransaction {
+    fn begin() -> Self {
+        unsafe {
+            // SAFETY:  these are Postgres' globals of the current backend, and it's up to our
+            // caller to be in a transaction
+            let memory_context = crate::CurrentMemoryContext;
+            let resource_owner = crate::CurrentResourceOwner;
+            crate::BeginInternalSubTransaction(std::ptr::null());
+
+            // the closure's allocations belong to our caller, not the subtransaction
+            crate::MemoryContextSwitchTo(memory_context);
+            Subtransaction { memory_context, resource_owner }
+        }
+    }
+
+    fn end(self, commit: bool) {
+        unsafe {
+            // SAFETY:  we started this subtransaction in `begin()`, and a closure that returned or
+            // unwound can't have left a subtransaction of its own open over it
+            if commit {
+                crate::ReleaseCurrentSubTransaction();
+            } else {
+                crate::MemoryContextSwitchTo(self.memory_context);
+                crate::RollbackAndReleaseCurrentSubTransaction();
+            }
+            crate::MemoryContextSwitchTo(self.memory_context);
+            crate::CurrentResourceOwner = self.resource_owner;
+        }
+    }
+}
@@ -430,7 +430,46 @@ impl PgAggregate {
             None
         };
 
+        // Postgres only accepts a moving-aggregate implementation with all of `MSFUNC`,
+        // `MINVFUNC` and `MSTYPE`, and the other `M*` options only with them.
         let fn_moving_state = get_impl_func_by_name(&item_impl_snapshot, "moving_state");
+        let fn_moving_state_inverse =
+            get_impl_func_by_name(&item_impl_snapshot, "moving_state_inverse");
+        match (fn_moving_state, fn_moving_state_inverse, impl_type_moving_state) {
+            (Some(_), Some(_), Some(_)) | (None, None, None) => (),
+            (Some(found), None, _) => return Err(syn::Error::new(
+                found.sig.ident.span(),
+                "`#[pg_aggregate]` requires `moving_state_inverse` when `moving_state` is implemented.",
+            )),
+            (None, Some(found), _) => return Err(syn::Error::new(
+                found.sig.ident.span(),
+                "`#[pg_aggregate]` requires `moving_state` when `moving_state_inverse` is implemented.",
+            )),
+            (Some(found), Some(_), None) => return Err(syn::Error::new(
+                found.sig.ident.span(),
+                "`#[pg_aggregate]` requires a `MovingState` type when `moving_state` is implemented.",
+            )),
+            (None, None, Some(found)) => return Err(syn::Error::new(
+                found.ident.span(),
+                "`#[pg_aggregate]` requires `moving_state` and `moving_state_inverse` when `MovingState` is set.",
+            )),
+        }
+        if fn_moving_state.is_none() {
+            let moving_only = get_impl_func_by_name(&item_impl_snapshot, "moving_finalize")
+                .map(|found| found.sig.ident.span())
+                .or_else(|| {
+                    ["MOVING_INITIAL_CONDITION", "MOVING_FINALIZE_MODIFY"].iter().find_map(|name| {
+                        get_impl_const_by_name(&item_impl_snapshot, name).map(|c| c.ident.span())
+                    })
+                });
+            if let Some(span) = moving_only {
+                return Err(syn::Error::new(
+                    span,
+                    "`#[pg_aggregate]` requires `moving_state` and `moving_state_inverse` for moving-aggregate options.",
+                ));
+            }
+        }
+
         let fn_moving_state_name = if let Some(found) = fn_moving_state {
             let fn_name = Ident::new(
                 &format!("{}_moving_state", snake_case_target_ident),
@@ -466,8 +505,6 @@ impl PgAggregate {
             None
         };
 
-        let fn_moving_state_inverse =
-            get_impl_func_by_name(&item_impl_snapshot, "moving_state_inverse");
         let fn_moving_state_inverse_name = if let Some(found) = fn_moving_state_inverse {
             let fn_name = Ident::new(
                 &format!("{}_moving_state_inverse", snake_case_target_ident),
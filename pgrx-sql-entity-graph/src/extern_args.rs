@@ -8,6 +8,7 @@ pub enum ExternArgs {
     CreateOrReplace,
     Immutable,
     Strict,
+    CalledOnNullInput,
     Stable,
     Volatile,
     Raw,
@@ -17,6 +18,7 @@ pub enum ExternArgs {
     ParallelSafe,
     ParallelUnsafe,
     ParallelRestricted,
+    Window,
     Error(String),
     Schema(String),
     Name(String),
@@ -30,6 +32,7 @@ impl core::fmt::Display for ExternArgs {
             ExternArgs::CreateOrReplace => write!(f, "CREATE OR REPLACE"),
             ExternArgs::Immutable => write!(f, "IMMUTABLE"),
             ExternArgs::Strict => write!(f, "STRICT"),
+            ExternArgs::CalledOnNullInput => write!(f, "CALLED ON NULL INPUT"),
             ExternArgs::Stable => write!(f, "STABLE"),
             ExternArgs::Volatile => write!(f, "VOLATILE"),
             ExternArgs::Raw => Ok(()),
@@ -38,6 +41,7 @@ impl core::fmt::Display for ExternArgs {
             ExternArgs::SecurityDefiner => write!(f, "SECURITY DEFINER"),
             ExternArgs::SecurityInvoker => write!(f, "SECURITY INVOKER"),
             ExternArgs::ParallelRestricted => write!(f, "PARALLEL RESTRICTED"),
+            ExternArgs::Window => write!(f, "WINDOW"),
             ExternArgs::Error(_) => Ok(()),
             ExternArgs::NoGuard => Ok(()),
             ExternArgs::Schema(_) => Ok(()),
@@ -54,6 +58,7 @@ impl ToTokens for ExternArgs {
             ExternArgs::CreateOrReplace => tokens.append(format_ident!("CreateOrReplace")),
             ExternArgs::Immutable => tokens.append(format_ident!("Immutable")),
             ExternArgs::Strict => tokens.append(format_ident!("Strict")),
+            ExternArgs::CalledOnNullInput => tokens.append(format_ident!("CalledOnNullInput")),
             ExternArgs::Stable => tokens.append(format_ident!("Stable")),
             ExternArgs::Volatile => tokens.append(format_ident!("Volatile")),
             ExternArgs::Raw => tokens.append(format_ident!("Raw")),
@@ -63,6 +68,7 @@ impl ToTokens for ExternArgs {
             ExternArgs::ParallelSafe => tokens.append(format_ident!("ParallelSafe")),
             ExternArgs::ParallelUnsafe => tokens.append(format_ident!("ParallelUnsafe")),
             ExternArgs::ParallelRestricted => tokens.append(format_ident!("ParallelRestricted")),
+            ExternArgs::Window => tokens.append(format_ident!("Window")),
             ExternArgs::Error(_s) => {
                 tokens.append_all(
                     quote! {
@@ -123,6 +129,7 @@ pub fn parse_extern_attributes(attr: TokenStream) -> HashSet<ExternArgs> {
                     "create_or_replace" => args.insert(ExternArgs::CreateOrReplace),
                     "immutable" => args.insert(ExternArgs::Immutable),
                     "strict" => args.insert(ExternArgs::Strict),
+                    "called_on_null_input" => args.insert(ExternArgs::CalledOnNullInput),
                     "stable" => args.insert(ExternArgs::Stable),
                     "volatile" => args.insert(ExternArgs::Volatile),
                     "raw" => args.insert(ExternArgs::Raw),
@@ -132,6 +139,7 @@ pub fn parse_extern_attributes(attr: TokenStream) -> HashSet<ExternArgs> {
                     "parallel_safe" => args.insert(ExternArgs::ParallelSafe),
                     "parallel_unsafe" => args.insert(ExternArgs::ParallelUnsafe),
                     "parallel_restricted" => args.insert(ExternArgs::ParallelRestricted),
+                    "window" => args.insert(ExternArgs::Window),
                     "error" => {
                         let _punc = itr.next().unwrap();
                         let literal = itr.next().unwrap();
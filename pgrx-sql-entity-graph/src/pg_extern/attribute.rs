@@ -26,6 +26,7 @@ use syn::Token;
 pub enum Attribute {
     Immutable,
     Strict,
+    CalledOnNullInput,
     Stable,
     Volatile,
     Raw,
@@ -36,6 +37,7 @@ pub enum Attribute {
     ParallelSafe,
     ParallelUnsafe,
     ParallelRestricted,
+    Window,
     Error(syn::LitStr),
     Schema(syn::LitStr),
     Name(syn::LitStr),
@@ -51,6 +53,9 @@ impl Attribute {
                 quote! { ::pgrx::pgrx_sql_entity_graph::ExternArgs::Immutable }
             }
             Attribute::Strict => quote! { ::pgrx::pgrx_sql_entity_graph::ExternArgs::Strict },
+            Attribute::CalledOnNullInput => {
+                quote! { ::pgrx::pgrx_sql_entity_graph::ExternArgs::CalledOnNullInput }
+            }
             Attribute::Stable => quote! { ::pgrx::pgrx_sql_entity_graph::ExternArgs::Stable },
             Attribute::Volatile => {
                 quote! { ::pgrx::pgrx_sql_entity_graph::ExternArgs::Volatile }
@@ -75,6 +80,7 @@ impl Attribute {
             Attribute::ParallelRestricted => {
                 quote! { ::pgrx::pgrx_sql_entity_graph::ExternArgs::ParallelRestricted }
             }
+            Attribute::Window => quote! { ::pgrx::pgrx_sql_entity_graph::ExternArgs::Window },
             Attribute::Error(s) => {
                 quote! { ::pgrx::pgrx_sql_entity_graph::ExternArgs::Error(String::from(#s)) }
             }
@@ -104,6 +110,7 @@ impl ToTokens for Attribute {
         let quoted = match self {
             Attribute::Immutable => quote! { immutable },
             Attribute::Strict => quote! { strict },
+            Attribute::CalledOnNullInput => quote! { called_on_null_input },
             Attribute::Stable => quote! { stable },
             Attribute::Volatile => quote! { volatile },
             Attribute::Raw => quote! { raw },
@@ -124,6 +131,7 @@ impl ToTokens for Attribute {
             Attribute::ParallelRestricted => {
                 quote! { parallel_restricted }
             }
+            Attribute::Window => quote! { window },
             Attribute::Error(s) => {
                 quote! { error = #s }
             }
@@ -155,6 +163,7 @@ impl Parse for Attribute {
         let found = match ident.to_string().as_str() {
             "immutable" => Self::Immutable,
             "strict" => Self::Strict,
+            "called_on_null_input" => Self::CalledOnNullInput,
             "stable" => Self::Stable,
             "volatile" => Self::Volatile,
             "raw" => Self::Raw,
@@ -165,6 +174,7 @@ impl Parse for Attribute {
             "parallel_safe" => Self::ParallelSafe,
             "parallel_unsafe" => Self::ParallelUnsafe,
             "parallel_restricted" => Self::ParallelRestricted,
+            "window" => Self::Window,
             "error" => {
                 let _eq: Token![=] = input.parse()?;
                 let literal: syn::LitStr = input.parse()?;
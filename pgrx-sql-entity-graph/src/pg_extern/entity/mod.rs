@@ -77,9 +77,11 @@ impl ToSql for PgExternEntity {
     fn to_sql(&self, context: &PgrxSql) -> eyre::Result<String> {
         let self_index = context.externs[self];
         let mut extern_attrs = self.extern_attrs.clone();
-        // if we already have a STRICT marker we do not need to add it
-        // presume we can upgrade, then disprove it
-        let mut strict_upgrade = !extern_attrs.iter().any(|i| i == &ExternArgs::Strict);
+        // if we already have a STRICT marker we do not need to add it, and if the user asked for
+        // CALLED ON NULL INPUT we must not.  Otherwise presume we can upgrade, then disprove it
+        let mut strict_upgrade = !extern_attrs
+            .iter()
+            .any(|i| i == &ExternArgs::Strict || i == &ExternArgs::CalledOnNullInput);
         if strict_upgrade {
             // It may be possible to infer a `STRICT` marker though.
             // But we can only do that if the user hasn't used `Option<T>` or `pgrx::Internal`
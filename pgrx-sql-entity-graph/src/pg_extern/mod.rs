@@ -116,8 +116,10 @@ impl PgExtern {
         let operator = Self::operator(&func)?;
         let search_path = Self::search_path(&func)?;
         let inputs = Self::inputs(&func)?;
+        Self::validate_strictness(&attrs, &inputs)?;
         let input_types = Self::input_types(&func)?;
         let returns = Returning::try_from(&func.sig.output)?;
+        Self::validate_window(&attrs, &inputs, &returns)?;
         Ok(CodeEnrichment(Self {
             attrs,
             func,
@@ -130,6 +132,75 @@ impl PgExtern {
         }))
     }
 
+    /// `strict` and `called_on_null_input` are mutually exclusive, and as Postgres never calls a
+    /// `STRICT` function with a NULL argument, none of its arguments should be an `Option<T>`
+    fn validate_strictness(attrs: &[Attribute], inputs: &[PgExternArgument]) -> syn::Result<()> {
+        if !attrs.contains(&Attribute::Strict) {
+            return Ok(());
+        }
+
+        if attrs.contains(&Attribute::CalledOnNullInput) {
+            return Err(syn::Error::new(
+                Span::call_site(),
+                "`strict` and `called_on_null_input` are mutually exclusive",
+            ));
+        }
+
+        match inputs.iter().find(|arg| arg.used_ty.optional.is_some()) {
+            Some(arg) => Err(syn::Error::new(
+                arg.fn_arg.span(),
+                format!(
+                    "`{}` can never be NULL in a `strict` function, so it should not be an `Option`",
+                    arg.pat
+                ),
+            )),
+            None => Ok(()),
+        }
+    }
+
+    /// Postgres calls a `WINDOW` function with all of its arguments NULL, as it reads them through
+    /// its `WindowObject` instead, so they must all be `Option<T>`s, and the function can't be
+    /// `strict`.  Nor can it return a set
+    fn validate_window(
+        attrs: &[Attribute],
+        inputs: &[PgExternArgument],
+        returns: &Returning,
+    ) -> syn::Result<()> {
+        if !attrs.contains(&Attribute::Window) {
+            return Ok(());
+        }
+
+        if attrs.contains(&Attribute::Strict) {
+            return Err(syn::Error::new(
+                Span::call_site(),
+                "a `window` function's arguments are always NULL, so it can't be `strict`",
+            ));
+        }
+
+        if let Returning::SetOf { .. } | Returning::Iterated { .. } = returns {
+            return Err(syn::Error::new(
+                Span::call_site(),
+                "a `window` function can't return a set",
+            ));
+        }
+
+        let is_fcinfo = |arg: &&PgExternArgument| {
+            let ty = arg.used_ty.resolved_ty.to_token_stream().to_string();
+            ty.ends_with("FunctionCallInfo")
+        };
+        match inputs.iter().filter(|arg| !is_fcinfo(arg)).find(|arg| arg.used_ty.optional.is_none())
+        {
+            Some(arg) => Err(syn::Error::new(
+                arg.fn_arg.span(),
+                format!(
+                    "`{}` is always NULL in a `window` function, which reads it with `pgrx::window::WindowObject` instead, so it should be an `Option`",
+                    arg.pat
+                ),
+            )),
+            None => Ok(()),
+        }
+    }
+
     fn input_types(func: &syn::ItemFn) -> syn::Result<Vec<syn::Type>> {
         func.sig
             .inputs
@@ -362,6 +433,17 @@ impl PgExtern {
         );
         let func_generics = &self.func.sig.generics;
         let is_raw = self.extern_attrs().contains(&Attribute::Raw);
+        let is_strict = self.extern_attrs().contains(&Attribute::Strict);
+        // In debug builds, `IMMUTABLE` functions let `::pgrx::volatility` know they're running
+        let volatility_guard = if self.extern_attrs().contains(&Attribute::Immutable) {
+            let name = self.name();
+            quote! {
+                #[cfg(debug_assertions)]
+                let _volatility_guard = ::pgrx::volatility::enter_immutable_function(#name);
+            }
+        } else {
+            quote! {}
+        };
         // We use a `_` prefix to make functions with no args more satisfied during linting.
         let fcinfo_ident = syn::Ident::new("_fcinfo", self.func.sig.ident.span());
 
@@ -370,6 +452,16 @@ impl PgExtern {
             .iter()
             .map(|v| syn::Ident::new(&format!("{}_", &v.pat), self.func.sig.span()))
             .collect::<Vec<_>>();
+        // Unless the function is `STRICT`, Postgres may call it with a NULL for an argument that
+        // isn't an `Option<T>`.  Like `STRICT` would, the result is then NULL (or an empty set)
+        // without calling the function
+        let null_return = match &self.returns {
+            Returning::None => quote! { return },
+            Returning::Type(_) => quote! {
+                return unsafe { ::pgrx::fcinfo::pg_return_null(#fcinfo_ident) }
+            },
+            Returning::SetOf { .. } | Returning::Iterated { .. } => quote! { return None },
+        };
         let arg_fetches = args.iter().enumerate().map(|(idx, arg)| {
             let pat = &arg_pats[idx];
             let resolved_ty = &arg.used_ty.resolved_ty;
@@ -390,9 +482,15 @@ impl PgExtern {
                     (true, None) | (true, Some(_)) => quote_spanned! { pat.span() =>
                         let #pat = unsafe { ::pgrx::fcinfo::pg_getarg_datum_raw(#fcinfo_ident, #idx) as #resolved_ty };
                     },
-                    (false, None) => quote_spanned! { pat.span() =>
+                    (false, None) if is_strict => quote_spanned! { pat.span() =>
                         let #pat = unsafe { ::pgrx::fcinfo::pg_getarg::<#resolved_ty>(#fcinfo_ident, #idx).unwrap_or_else(|| panic!("{} is null", stringify!{#pat})) };
                     },
+                    (false, None) => quote_spanned! { pat.span() =>
+                        let #pat = match unsafe { ::pgrx::fcinfo::pg_getarg::<#resolved_ty>(#fcinfo_ident, #idx) } {
+                            Some(value) => value,
+                            None => #null_return,
+                        };
+                    },
                     (false, Some(inner)) => quote_spanned! { pat.span() =>
                         let #pat = unsafe { ::pgrx::fcinfo::pg_getarg::<#inner>(#fcinfo_ident, #idx) };
                     },
@@ -406,6 +504,7 @@ impl PgExtern {
                   #[doc(hidden)]
                   #[::pgrx::pgrx_macros::pg_guard]
                   pub unsafe extern "C" fn #func_name_wrapper #func_generics(#fcinfo_ident: ::pgrx::pg_sys::FunctionCallInfo) {
+                      #volatility_guard
                       #(
                           #arg_fetches
                       )*
@@ -425,7 +524,7 @@ impl PgExtern {
                         // returning `Result<Option<T>>`
                         quote_spanned! {
                             self.func.sig.output.span() =>
-                                match ::pgrx::datum::IntoDatum::into_datum(#result_ident) {
+                                match ::pgrx::datum::counted_into_datum(#result_ident) {
                                     Some(datum) => datum,
                                     None => unsafe { ::pgrx::fcinfo::pg_return_null(#fcinfo_ident) },
                                 }
@@ -434,7 +533,7 @@ impl PgExtern {
                         // returning Result<T>
                         quote_spanned! {
                             self.func.sig.output.span() =>
-                                ::pgrx::datum::IntoDatum::into_datum(#result_ident).unwrap_or_else(|| panic!("returned Datum was NULL"))
+                                ::pgrx::datum::counted_into_datum(#result_ident).unwrap_or_else(|| panic!("returned Datum was NULL"))
                         }
                     }
                 } else if retval_ty.resolved_ty == syn::parse_quote!(pg_sys::Datum)
@@ -448,14 +547,14 @@ impl PgExtern {
                     quote_spanned! { self.func.sig.output.span() =>
                         match #result_ident {
                             Some(result) => {
-                                ::pgrx::datum::IntoDatum::into_datum(result).unwrap_or_else(|| panic!("returned Option<T> was NULL"))
+                                ::pgrx::datum::counted_into_datum(result).unwrap_or_else(|| panic!("returned Option<T> was NULL"))
                             },
                             None => unsafe { ::pgrx::fcinfo::pg_return_null(#fcinfo_ident) }
                         }
                     }
                 } else {
                     quote_spanned! { self.func.sig.output.span() =>
-                        ::pgrx::datum::IntoDatum::into_datum(#result_ident).unwrap_or_else(|| panic!("returned Datum was NULL"))
+                        ::pgrx::datum::counted_into_datum(#result_ident).unwrap_or_else(|| panic!("returned Datum was NULL"))
                     }
                 };
 
@@ -464,6 +563,7 @@ impl PgExtern {
                     #[doc(hidden)]
                     #[::pgrx::pgrx_macros::pg_guard]
                     pub unsafe extern "C" fn #func_name_wrapper #func_generics(#fcinfo_ident: ::pgrx::pg_sys::FunctionCallInfo) -> ::pgrx::pg_sys::Datum {
+                        #volatility_guard
                         #(
                             #arg_fetches
                         )*
@@ -475,28 +575,12 @@ impl PgExtern {
                     }
                 }
             }
-            Returning::SetOf { ty: _retval_ty, optional, result } => {
-                let result_handler = if *optional && !*result {
-                    // don't need unsafe annotations because of the larger unsafe block coming up
-                    quote_spanned! { self.func.sig.span() =>
-                        #func_name(#(#arg_pats),*)
-                    }
-                } else if *result {
-                    if *optional {
-                        quote_spanned! { self.func.sig.span() =>
-                            use ::pgrx::pg_sys::panic::ErrorReportable;
-                            #func_name(#(#arg_pats),*).report()
-                        }
-                    } else {
-                        quote_spanned! { self.func.sig.span() =>
-                            use ::pgrx::pg_sys::panic::ErrorReportable;
-                            Some(#func_name(#(#arg_pats),*).report())
-                        }
-                    }
-                } else {
-                    quote_spanned! { self.func.sig.span() =>
-                        Some(#func_name(#(#arg_pats),*))
-                    }
+            Returning::SetOf { .. } => {
+                // any nesting of `Option` and `Result` around the `SetOfIterator` is unwrapped,
+                // with `None` being an empty set and `Err` being raised as an ERROR
+                // don't need unsafe annotations because of the larger unsafe block coming up
+                let result_handler = quote_spanned! { self.func.sig.span() =>
+                    ::pgrx::srf::IntoSrfIterator::into_srf_iterator(#func_name(#(#arg_pats),*))
                 };
 
                 quote_spanned! { self.func.sig.span() =>
@@ -504,6 +588,7 @@ impl PgExtern {
                     #[doc(hidden)]
                     #[::pgrx::pgrx_macros::pg_guard]
                     pub unsafe extern "C" fn #func_name_wrapper #func_generics(#fcinfo_ident: ::pgrx::pg_sys::FunctionCallInfo) -> ::pgrx::pg_sys::Datum {
+                        #volatility_guard
                         #[allow(unused_unsafe)]
                         unsafe {
                             // SAFETY: the caller has asserted that `fcinfo` is a valid FunctionCallInfo pointer, allocated by Postgres
@@ -517,23 +602,12 @@ impl PgExtern {
                     }
                 }
             }
-            Returning::Iterated { tys: _retval_tys, optional, result } => {
-                let result_handler = if *optional {
-                    // don't need unsafe annotations because of the larger unsafe block coming up
-                    quote_spanned! { self.func.sig.span() =>
-                        #func_name(#(#arg_pats),*)
-                    }
-                } else if *result {
-                    quote_spanned! { self.func.sig.span() =>
-                        {
-                            use ::pgrx::pg_sys::panic::ErrorReportable;
-                            Some(#func_name(#(#arg_pats),*).report())
-                        }
-                    }
-                } else {
-                    quote_spanned! { self.func.sig.span() =>
-                        Some(#func_name(#(#arg_pats),*))
-                    }
+            Returning::Iterated { .. } => {
+                // any nesting of `Option` and `Result` around the `TableIterator` is unwrapped,
+                // with `None` being an empty set and `Err` being raised as an ERROR
+                // don't need unsafe annotations because of the larger unsafe block coming up
+                let result_handler = quote_spanned! { self.func.sig.span() =>
+                    ::pgrx::srf::IntoSrfIterator::into_srf_iterator(#func_name(#(#arg_pats),*))
                 };
 
                 quote_spanned! { self.func.sig.span() =>
@@ -541,6 +615,7 @@ impl PgExtern {
                     #[doc(hidden)]
                     #[::pgrx::pgrx_macros::pg_guard]
                     pub unsafe extern "C" fn #func_name_wrapper #func_generics(#fcinfo_ident: ::pgrx::pg_sys::FunctionCallInfo) -> ::pgrx::pg_sys::Datum {
+                        #volatility_guard
                         #[allow(unused_unsafe)]
                         unsafe {
                             // SAFETY: the caller has asserted that `fcinfo` is a valid FunctionCallInfo pointer, allocated by Postgres
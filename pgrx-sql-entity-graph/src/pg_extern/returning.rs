@@ -64,96 +64,23 @@ impl TryFrom<&syn::ReturnType> for Returning {
                 let mut ty = *ty.clone();
 
                 match ty {
-                    syn::Type::Path(mut typepath) => {
-                        let path = &mut typepath.path;
+                    syn::Type::Path(typepath) => {
                         let mut saw_option_ident = false;
                         let mut saw_result_ident = false;
-                        let mut saw_setof_iterator = false;
-                        let mut saw_table_iterator = false;
-
-                        for segment in &mut path.segments {
-                            let ident_string = segment.ident.to_string();
-                            match ident_string.as_str() {
-                                "Option" => saw_option_ident = true,
-                                "Result" => saw_result_ident = true,
-                                "SetOfIterator" => saw_setof_iterator = true,
-                                "TableIterator" => saw_table_iterator = true,
-                                _ => (),
-                            };
-                        }
-                        if saw_option_ident
-                            || saw_result_ident
-                            || saw_setof_iterator
-                            || saw_table_iterator
-                        {
-                            let option_inner_path = if saw_option_ident || saw_result_ident {
-                                match path.segments.last_mut().map(|s| &mut s.arguments) {
-                                    Some(syn::PathArguments::AngleBracketed(args)) => {
-                                        let args_span = args.span();
-                                        match args.args.first_mut() {
-                                            Some(syn::GenericArgument::Type(syn::Type::Path(syn::TypePath { qself: _, path }))) => path.clone(),
-                                            Some(syn::GenericArgument::Type(_)) => {
-                                                let used_ty = UsedType::new(syn::Type::Path(typepath.clone()))?;
-                                                return Ok(Returning::Type(used_ty))
-                                            },
-                                            other => {
-                                                return Err(syn::Error::new(
-                                                    other.as_ref().map(|s| s.span()).unwrap_or(args_span),
-                                                    &format!(
-                                                        "Got unexpected generic argument for Option inner: {other:?}"
-                                                    ),
-                                                ))
-                                            }
-                                        }
-                                    }
-                                    other => {
-                                        return Err(syn::Error::new(
-                                            other.span(),
-                                            &format!(
-                                                "Got unexpected path argument for Option inner: {other:?}"
-                                            ),
-                                        ))
-                                    }
-                                }
-                            } else {
-                                path.clone()
-                            };
-
-                            let mut segments = option_inner_path.segments.clone();
-                            'outer: loop {
-                                for segment in &segments {
-                                    let ident_string = segment.ident.to_string();
-                                    match ident_string.as_str() {
-                                        "Option" => match &segment.arguments {
-                                            PathArguments::AngleBracketed(bracketed) => {
-                                                match bracketed.args.first().unwrap() {
-                                                    GenericArgument::Type(ty) => match ty {
-                                                        Type::Path(this_path) => {
-                                                            segments =
-                                                                this_path.path.segments.clone();
-                                                            saw_option_ident = true;
-                                                            continue 'outer;
-                                                        }
-                                                        _ => continue,
-                                                    },
-                                                    _ => continue,
-                                                };
-                                            }
-                                            _ => continue,
-                                        },
-                                        "SetOfIterator" => saw_setof_iterator = true,
-                                        "TableIterator" => saw_table_iterator = true,
-                                        _ => (),
-                                    };
-                                }
-                                break;
-                            }
-
-                            if saw_setof_iterator {
-                                let last_path_segment = option_inner_path.segments.last();
-                                let (used_ty, optional) = match &last_path_segment.map(|ps| &ps.arguments) {
-                                    Some(syn::PathArguments::AngleBracketed(args)) => {
-                                        match args.args.last().unwrap() {
+                        let mut iterator_path = peel_option_and_result(
+                            &typepath.path,
+                            &mut saw_option_ident,
+                            &mut saw_result_ident,
+                        );
+                        let iterator_ident =
+                            iterator_path.segments.last().map(|segment| segment.ident.to_string());
+                        if iterator_ident.as_deref() == Some("SetOfIterator") {
+                            let last_path_segment = iterator_path.segments.last();
+                            let (used_ty, optional) = match &last_path_segment
+                                .map(|ps| &ps.arguments)
+                            {
+                                Some(syn::PathArguments::AngleBracketed(args)) => {
+                                    match args.args.last().unwrap() {
                                             syn::GenericArgument::Type(ty) => {
                                                 match &ty {
                                                     syn::Type::Path(path) => {
@@ -180,155 +107,109 @@ impl TryFrom<&syn::ReturnType> for Returning {
                                                 ))
                                             }
                                         }
-                                    }
-                                    other => {
-                                        return Err(syn::Error::new(
-                                            other.map(|s| s.span()).unwrap_or_else(proc_macro2::Span::call_site),
-                                            &format!(
-                                                "Got unexpected path argument for SetOfIterator: {other:?}"
-                                            ),
-                                        ))
-                                    }
-                                };
-                                Ok(Returning::SetOf {
-                                    ty: used_ty,
-                                    optional,
-                                    result: saw_result_ident,
-                                })
-                            } else if saw_table_iterator {
-                                let iterator_path = if saw_option_ident || saw_result_ident {
-                                    let inner_path =
-                                        match &mut path.segments.first_mut().unwrap().arguments {
-                                            syn::PathArguments::AngleBracketed(args) => {
-                                                match args.args.first_mut().unwrap() {
-                                                    syn::GenericArgument::Type(syn::Type::Path(syn::TypePath { qself: _, path })) => path,
-                                                    other => {
-                                                        return Err(syn::Error::new(
-                                                            other.span(),
-                                                            &format!(
-                                                                "Got unexpected generic argument for Option/Result inner: {other:?}"
-                                                            ),
-                                                        ))
+                                }
+                                other => {
+                                    return Err(syn::Error::new(
+                                        other
+                                            .map(|s| s.span())
+                                            .unwrap_or_else(proc_macro2::Span::call_site),
+                                        &format!(
+                                        "Got unexpected path argument for SetOfIterator: {other:?}"
+                                    ),
+                                    ))
+                                }
+                            };
+                            Ok(Returning::SetOf { ty: used_ty, optional, result: saw_result_ident })
+                        } else if iterator_ident.as_deref() == Some("TableIterator") {
+                            let last_path_segment = iterator_path.segments.last_mut().unwrap();
+                            let mut iterated_items = vec![];
+                            match &mut last_path_segment.arguments {
+                                syn::PathArguments::AngleBracketed(args) => {
+                                    match args.args.last_mut().unwrap() {
+                                        syn::GenericArgument::Type(syn::Type::Tuple(
+                                            type_tuple,
+                                        )) => {
+                                            for elem in &type_tuple.elems {
+                                                match &elem {
+                                                    syn::Type::Path(path) => {
+                                                        let iterated_item = ReturningIteratedItem {
+                                                            name: None,
+                                                            used_ty: UsedType::new(
+                                                                syn::Type::Path(path.clone()),
+                                                            )?,
+                                                        };
+                                                        iterated_items.push(iterated_item);
                                                     }
-                                                }
-                                            },
-                                            other => {
-                                                return Err(syn::Error::new(
-                                                    other.span(),
-                                                    &format!(
-                                                        "Got unexpected path argument for Option/Result inner: {other:?}"
-                                                    ),
-                                                ))
-                                            }
-                                        };
-                                    inner_path
-                                } else {
-                                    path
-                                };
-                                let last_path_segment = iterator_path.segments.last_mut().unwrap();
-                                let mut iterated_items = vec![];
-                                match &mut last_path_segment.arguments {
-                                    syn::PathArguments::AngleBracketed(args) => {
-                                        match args.args.last_mut().unwrap() {
-                                            syn::GenericArgument::Type(syn::Type::Tuple(
-                                                type_tuple,
-                                            )) => {
-                                                for elem in &type_tuple.elems {
-                                                    match &elem {
-                                                        syn::Type::Path(path) => {
-                                                            let iterated_item =
-                                                                ReturningIteratedItem {
-                                                                    name: None,
-                                                                    used_ty: UsedType::new(
-                                                                        syn::Type::Path(
-                                                                            path.clone(),
-                                                                        ),
-                                                                    )?,
-                                                                };
-                                                            iterated_items.push(iterated_item);
-                                                        }
-                                                        syn::Type::Macro(type_macro) => {
-                                                            let mac = &type_macro.mac;
-                                                            let archetype =
-                                                                mac.path.segments.last().unwrap();
-                                                            match archetype
-                                                                .ident
-                                                                .to_string()
-                                                                .as_str()
-                                                            {
-                                                                "name" => {
-                                                                    let out: NameMacro =
-                                                                        mac.parse_body()?;
-                                                                    let iterated_item =
-                                                                        ReturningIteratedItem {
-                                                                            name: Some(out.ident),
-                                                                            used_ty: out.used_ty,
-                                                                        };
-                                                                    iterated_items
-                                                                        .push(iterated_item)
-                                                                }
-                                                                _ => {
-                                                                    let iterated_item =
-                                                                        ReturningIteratedItem {
-                                                                            name: None,
-                                                                            used_ty: UsedType::new(
-                                                                                syn::Type::Macro(
-                                                                                    type_macro
-                                                                                        .clone(),
-                                                                                ),
-                                                                            )?,
-                                                                        };
-                                                                    iterated_items
-                                                                        .push(iterated_item);
-                                                                }
+                                                    syn::Type::Macro(type_macro) => {
+                                                        let mac = &type_macro.mac;
+                                                        let archetype =
+                                                            mac.path.segments.last().unwrap();
+                                                        match archetype.ident.to_string().as_str() {
+                                                            "name" => {
+                                                                let out: NameMacro =
+                                                                    mac.parse_body()?;
+                                                                let iterated_item =
+                                                                    ReturningIteratedItem {
+                                                                        name: Some(out.ident),
+                                                                        used_ty: out.used_ty,
+                                                                    };
+                                                                iterated_items.push(iterated_item)
+                                                            }
+                                                            _ => {
+                                                                let iterated_item =
+                                                                    ReturningIteratedItem {
+                                                                        name: None,
+                                                                        used_ty: UsedType::new(
+                                                                            syn::Type::Macro(
+                                                                                type_macro.clone(),
+                                                                            ),
+                                                                        )?,
+                                                                    };
+                                                                iterated_items.push(iterated_item);
                                                             }
                                                         }
-                                                        reference @ syn::Type::Reference(_) => {
-                                                            let iterated_item =
-                                                                ReturningIteratedItem {
-                                                                    name: None,
-                                                                    used_ty: UsedType::new(
-                                                                        (*reference).clone(),
-                                                                    )?,
-                                                                };
-                                                            iterated_items.push(iterated_item);
-                                                        }
-                                                        ty => {
-                                                            return Err(syn::Error::new(
-                                                                ty.span(),
-                                                                "Table Iterator must have an item",
-                                                            ));
-                                                        }
-                                                    };
-                                                }
-                                            }
-                                            syn::GenericArgument::Lifetime(_) => (),
-                                            other => {
-                                                return Err(syn::Error::new(
-                                                    other.span(),
-                                                    &format!(
-                                                        "Got unexpected generic argument: {other:?}"
-                                                    ),
-                                                ))
+                                                    }
+                                                    reference @ syn::Type::Reference(_) => {
+                                                        let iterated_item = ReturningIteratedItem {
+                                                            name: None,
+                                                            used_ty: UsedType::new(
+                                                                (*reference).clone(),
+                                                            )?,
+                                                        };
+                                                        iterated_items.push(iterated_item);
+                                                    }
+                                                    ty => {
+                                                        return Err(syn::Error::new(
+                                                            ty.span(),
+                                                            "Table Iterator must have an item",
+                                                        ));
+                                                    }
+                                                };
                                             }
-                                        };
-                                    }
-                                    other => {
-                                        return Err(syn::Error::new(
-                                            other.span(),
-                                            &format!("Got unexpected path argument: {other:?}"),
-                                        ))
-                                    }
-                                };
-                                Ok(Returning::Iterated {
-                                    tys: iterated_items,
-                                    optional: saw_option_ident,
-                                    result: saw_result_ident,
-                                })
-                            } else {
-                                let used_ty = UsedType::new(syn::Type::Path(typepath.clone()))?;
-                                Ok(Returning::Type(used_ty))
-                            }
+                                        }
+                                        syn::GenericArgument::Lifetime(_) => (),
+                                        other => {
+                                            return Err(syn::Error::new(
+                                                other.span(),
+                                                &format!(
+                                                    "Got unexpected generic argument: {other:?}"
+                                                ),
+                                            ))
+                                        }
+                                    };
+                                }
+                                other => {
+                                    return Err(syn::Error::new(
+                                        other.span(),
+                                        &format!("Got unexpected path argument: {other:?}"),
+                                    ))
+                                }
+                            };
+                            Ok(Returning::Iterated {
+                                tys: iterated_items,
+                                optional: saw_option_ident,
+                                result: saw_result_ident,
+                            })
                         } else {
                             let used_ty = UsedType::new(syn::Type::Path(typepath.clone()))?;
                             Ok(Returning::Type(used_ty))
@@ -360,6 +241,36 @@ impl TryFrom<&syn::ReturnType> for Returning {
     }
 }
 
+/// Peel any number of `Option<...>` and `Result<..., E>` wrappers off of `path`, in any nesting,
+/// noting which were seen
+fn peel_option_and_result(
+    path: &syn::Path,
+    saw_option_ident: &mut bool,
+    saw_result_ident: &mut bool,
+) -> syn::Path {
+    let mut path = path.clone();
+    loop {
+        let Some(last_segment) = path.segments.last() else { return path };
+        let is_option = last_segment.ident == "Option";
+        let is_result = last_segment.ident == "Result";
+        if !is_option && !is_result {
+            return path;
+        }
+
+        let inner_path = match &last_segment.arguments {
+            PathArguments::AngleBracketed(args) => match args.args.first() {
+                Some(GenericArgument::Type(Type::Path(inner))) => inner.path.clone(),
+                _ => return path,
+            },
+            _ => return path,
+        };
+
+        *saw_option_ident |= is_option;
+        *saw_result_ident |= is_result;
+        path = inner_path;
+    }
+}
+
 impl ToTokens for Returning {
     fn to_tokens(&self, tokens: &mut TokenStream2) {
         let quoted = match self {
@@ -115,12 +115,25 @@ pub fn run_test(
     let (mut client, session_id) = client()?;
 
     let schema = "tests"; // get_extension_schema();
+    let leak_threshold = leak_report_threshold();
+    let mut leak_report = None;
     let result = match client.transaction() {
         // run the test function in a transaction
         Ok(mut tx) => {
+            let before = match leak_threshold {
+                Some(_) => Some(memory_context_usage(&mut tx)?),
+                None => None,
+            };
+
             let result = tx.simple_query(&format!("SELECT \"{schema}\".\"{sql_funcname}\"();"));
 
             if result.is_ok() {
+                if let (Some(threshold), Some(before)) = (leak_threshold, before) {
+                    let after = memory_context_usage(&mut tx)?;
+                    leak_report =
+                        Some(report_memory_growth(sql_funcname, threshold, &before, &after));
+                }
+
                 // and abort the transaction when complete
                 tx.rollback().expect("test rollback didn't work");
             }
@@ -183,7 +196,75 @@ pub fn run_test(
         // we expected an ERROR, but didn't get one
         return Err(eyre!("Expected error: {message}"));
     } else {
+        leak_report.unwrap_or(Ok(()))
+    }
+}
+
+/// The MemoryContexts whose growth during a test is considered a leak by `cargo pgrx test --leak-report`
+const LEAK_CHECKED_CONTEXTS: [&str; 2] = ["TopMemoryContext", "CurTransactionContext"];
+
+/// The growth threshold, in bytes, set by `cargo pgrx test --leak-report`, if it's enabled
+fn leak_report_threshold() -> Option<i64> {
+    let threshold = std::env::var("PGRX_TEST_LEAK_THRESHOLD").ok()?.parse().ok()?;
+
+    // `pg_backend_memory_contexts` is new in Postgres 14
+    if pg_sys::get_pg_major_version_num() < 14 {
+        static WARNED: std::sync::Once = std::sync::Once::new();
+        WARNED.call_once(|| {
+            eprintln!(
+                "{}",
+                "--leak-report requires Postgres 14 or later and will be ignored".bold().yellow()
+            )
+        });
+        return None;
+    }
+
+    Some(threshold)
+}
+
+/// Bytes used by each MemoryContext in the test's backend, summed by context name
+fn memory_context_usage(tx: &mut postgres::Transaction) -> eyre::Result<HashMap<String, i64>> {
+    let query =
+        "SELECT name, sum(used_bytes)::bigint FROM pg_backend_memory_contexts GROUP BY name;";
+    let rows = query_wrapper(Some(query.to_string()), Some(&[]), |query, query_params| {
+        tx.query(&query.unwrap(), query_params.unwrap())
+    })?;
+
+    Ok(rows.into_iter().map(|row| (row.get(0), row.get(1))).collect())
+}
+
+/// Print how much each MemoryContext grew while `sql_funcname` ran, and fail if any of the
+/// [`LEAK_CHECKED_CONTEXTS`] grew by more than `threshold` bytes
+fn report_memory_growth(
+    sql_funcname: &str,
+    threshold: i64,
+    before: &HashMap<String, i64>,
+    after: &HashMap<String, i64>,
+) -> eyre::Result<()> {
+    let mut deltas = after
+        .iter()
+        .map(|(name, used)| (name.as_str(), used - before.get(name).copied().unwrap_or(0)))
+        .filter(|(_, delta)| *delta != 0)
+        .collect::<Vec<_>>();
+    deltas.sort_by(|a, b| b.1.cmp(&a.1).then(a.0.cmp(b.0)));
+
+    eprintln!("{} {sql_funcname}", "MemoryContext growth for".bold());
+    for (name, delta) in &deltas {
+        eprintln!("  {delta:>12} bytes  {name}");
+    }
+
+    let leaks = deltas
+        .iter()
+        .filter(|(name, delta)| LEAK_CHECKED_CONTEXTS.contains(name) && *delta > threshold)
+        .map(|(name, delta)| format!("{name} grew by {delta} bytes"))
+        .collect::<Vec<_>>();
+    if leaks.is_empty() {
         Ok(())
+    } else {
+        Err(eyre!(
+            "possible memory leak in {sql_funcname} (threshold is {threshold} bytes): {}",
+            leaks.join(", ")
+        ))
     }
 }
 
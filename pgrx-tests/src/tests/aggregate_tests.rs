@@ -6,6 +6,7 @@ All rights reserved.
 
 Use of this source code is governed by the MIT license that can be found in the LICENSE file.
 */
+use pgrx::aggregate::OrderedSetSort;
 use pgrx::prelude::*;
 use pgrx::{Aggregate, Internal, ParallelOption};
 use serde::{Deserialize, Serialize};
@@ -142,6 +143,46 @@ impl Aggregate for DemoPercentileDisc {
     }
 }
 
+#[derive(Copy, Clone, Default, Debug)]
+pub struct DemoSortedPercentileDisc;
+
+// sorts with the `WITHIN GROUP (ORDER BY ...)` clause's own ordering, rather than `Ord`
+#[pg_aggregate]
+impl Aggregate for DemoSortedPercentileDisc {
+    const NAME: &'static str = "demo_sorted_percentile_disc";
+    type Args = name!(input, Option<String>);
+    type State = Internal;
+    type Finalize = Option<String>;
+    const ORDERED_SET: bool = true;
+    type OrderedSetArgs = name!(percentile, f64);
+
+    fn state(
+        mut current: Self::State,
+        arg: Self::Args,
+        fcinfo: pg_sys::FunctionCallInfo,
+    ) -> Self::State {
+        let sort = unsafe { OrderedSetSort::<String>::from_state(&mut current, fcinfo) };
+        // like percentile_disc(), NULLs are ignored
+        if arg.is_some() {
+            sort.push(arg);
+        }
+        current
+    }
+
+    fn finalize(
+        mut current: Self::State,
+        direct_arg: Self::OrderedSetArgs,
+        fcinfo: pg_sys::FunctionCallInfo,
+    ) -> Self::Finalize {
+        let sort = unsafe { OrderedSetSort::<String>::from_state(&mut current, fcinfo) };
+        if sort.is_empty() {
+            return None;
+        }
+        let rownum = (direct_arg * sort.len() as f64).ceil() as usize;
+        sort.sorted().nth(rownum.saturating_sub(1)).flatten()
+    }
+}
+
 #[pgrx::pg_schema]
 mod demo_schema {
     use pgrx::PostgresType;
@@ -186,6 +227,79 @@ impl Aggregate for DemoCustomState {
     }
 }
 
+#[derive(Copy, Clone, Default, Debug, PostgresType, Serialize, Deserialize)]
+pub struct DemoAvgState {
+    sum: f64,
+    count: i64,
+}
+
+// Subtracting from a floating-point sum accumulates error, so the moving state sums exactly
+#[derive(Copy, Clone, Default, Debug, PostgresType, Serialize, Deserialize)]
+pub struct DemoMovingAvgState {
+    sum: i64,
+    count: i64,
+}
+
+pub struct DemoAvg;
+
+#[pg_aggregate]
+impl Aggregate for DemoAvg {
+    const NAME: &'static str = "demo_avg";
+    const INITIAL_CONDITION: Option<&'static str> = Some(r#"{ "sum": 0.0, "count": 0 }"#);
+    const MOVING_INITIAL_CONDITION: Option<&'static str> = Some(r#"{ "sum": 0, "count": 0 }"#);
+
+    type Args = i32;
+    type State = DemoAvgState;
+    type MovingState = DemoMovingAvgState;
+    type Finalize = Option<f64>;
+
+    fn state(
+        mut current: Self::State,
+        arg: Self::Args,
+        _fcinfo: pg_sys::FunctionCallInfo,
+    ) -> Self::State {
+        current.sum += arg as f64;
+        current.count += 1;
+        current
+    }
+
+    fn finalize(
+        current: Self::State,
+        _direct_args: Self::OrderedSetArgs,
+        _fcinfo: pg_sys::FunctionCallInfo,
+    ) -> Self::Finalize {
+        (current.count > 0).then(|| current.sum / current.count as f64)
+    }
+
+    fn moving_state(
+        mut mstate: Self::MovingState,
+        arg: Self::Args,
+        _fcinfo: pg_sys::FunctionCallInfo,
+    ) -> Self::MovingState {
+        mstate.sum += arg as i64;
+        mstate.count += 1;
+        mstate
+    }
+
+    fn moving_state_inverse(
+        mut mstate: Self::MovingState,
+        arg: Self::Args,
+        _fcinfo: pg_sys::FunctionCallInfo,
+    ) -> Self::MovingState {
+        mstate.sum -= arg as i64;
+        mstate.count -= 1;
+        mstate
+    }
+
+    fn moving_finalize(
+        mstate: Self::MovingState,
+        _direct_args: Self::OrderedSetArgs,
+        _fcinfo: pg_sys::FunctionCallInfo,
+    ) -> Self::Finalize {
+        (mstate.count > 0).then(|| mstate.sum as f64 / mstate.count as f64)
+    }
+}
+
 #[cfg(any(test, feature = "pg_test"))]
 #[pgrx::pg_schema]
 mod tests {
@@ -212,6 +326,53 @@ mod tests {
         assert_eq!(retval, Ok(Some(vec![1, 21, 320, 4300])));
     }
 
+    #[pg_test]
+    fn aggregate_demo_avg() {
+        let retval =
+            Spi::get_one::<f64>("SELECT demo_avg(value) FROM UNNEST(ARRAY [1, 2, 6]) as value;");
+        assert_eq!(retval, Ok(Some(3.0)));
+    }
+
+    #[pg_test]
+    fn aggregate_demo_avg_moving() -> Result<(), pgrx::spi::Error> {
+        let catalog = Spi::get_three::<String, String, String>(
+            "SELECT aggmtransfn::text, aggminvtransfn::text, aggmfinalfn::text
+            FROM pg_aggregate WHERE aggfnoid = 'demo_avg'::regproc;",
+        )?;
+        assert_eq!(
+            catalog,
+            (
+                Some("demo_avg_moving_state".into()),
+                Some("demo_avg_moving_state_inverse".into()),
+                Some("demo_avg_moving_finalize".into())
+            )
+        );
+
+        let retval = Spi::get_one::<Vec<f64>>(
+            "
+            SELECT array_agg(calculated ORDER BY i) FROM (
+                SELECT i, demo_avg(value) OVER (
+                    ORDER BY i ROWS BETWEEN 1 PRECEDING AND CURRENT ROW
+                ) as calculated FROM UNNEST(ARRAY [1, 3, 8, 20]) WITH ORDINALITY as t(value, i)
+            ) as results;
+        ",
+        )?;
+        assert_eq!(retval, Some(vec![1.0, 2.0, 5.5, 14.0]));
+
+        // The frame only ever shrinks from the front, so it goes through the inverse function
+        let retval = Spi::get_one::<Vec<f64>>(
+            "
+            SELECT array_agg(calculated ORDER BY i) FROM (
+                SELECT i, demo_avg(value) OVER (
+                    ORDER BY i ROWS BETWEEN CURRENT ROW AND UNBOUNDED FOLLOWING
+                ) as calculated FROM UNNEST(ARRAY [1, 3, 8, 20]) WITH ORDINALITY as t(value, i)
+            ) as results;
+        ",
+        )?;
+        assert_eq!(retval, Some(vec![8.0, 31.0 / 3.0, 14.0, 20.0]));
+        Ok(())
+    }
+
     #[pg_test]
     fn aggregate_demo_unique() {
         let retval = Spi::get_one::<i32>(
@@ -234,6 +395,41 @@ mod tests {
         assert_eq!(retval, Ok(Some(5)));
     }
 
+    #[pg_test]
+    fn aggregate_demo_sorted_percentile_disc() {
+        for order_by in ["v", "v DESC", "v COLLATE \"C\"", "v NULLS FIRST"] {
+            for percentile in ["0", "0.05", "0.5", "0.99", "1"] {
+                let query = format!(
+                    "SELECT demo_sorted_percentile_disc({percentile}) WITHIN GROUP (ORDER BY {order_by})
+                               IS NOT DISTINCT FROM percentile_disc({percentile}) WITHIN GROUP (ORDER BY {order_by})
+                       FROM UNNEST(ARRAY ['b', 'A', NULL, 'c', 'a', 'B', NULL, 'aa']) AS v"
+                );
+                assert_eq!(Ok(Some(true)), Spi::get_one::<bool>(&query), "{query}");
+            }
+        }
+
+        let retval = Spi::get_one::<String>(
+            "SELECT demo_sorted_percentile_disc(0.5) WITHIN GROUP (ORDER BY v) FROM UNNEST(ARRAY []::text[]) AS v",
+        );
+        assert_eq!(retval, Ok(None));
+    }
+
+    #[pg_test]
+    fn aggregate_demo_sorted_percentile_disc_spills() -> Result<(), spi::Error> {
+        // more values than fit in the smallest work_mem, per group
+        Spi::run("SET LOCAL work_mem = '64kB'")?;
+        let retval = Spi::get_one::<bool>(
+            "SELECT bool_and(ours = theirs) FROM (
+                SELECT demo_sorted_percentile_disc(0.3) WITHIN GROUP (ORDER BY md5(i::text)) AS ours,
+                       percentile_disc(0.3) WITHIN GROUP (ORDER BY md5(i::text)) AS theirs
+                  FROM generate_series(1, 30000) i
+                 GROUP BY i % 3
+            ) results",
+        );
+        assert_eq!(retval, Ok(Some(true)));
+        Ok(())
+    }
+
     #[pg_test]
     fn aggregate_demo_custom_state() {
         let retval = Spi::get_one::<i32>(
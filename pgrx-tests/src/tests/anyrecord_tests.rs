@@ -0,0 +1,83 @@
+/*
+Portions Copyright 2019-2021 ZomboDB, LLC.
+Portions Copyright 2021-2022 Technology Concepts & Design, Inc. <support@tcdi.com>
+
+All rights reserved.
+
+Use of this source code is governed by the MIT license that can be found in the LICENSE file.
+*/
+
+use pgrx::prelude::*;
+use pgrx::AnyRecord;
+
+#[pg_extern]
+fn anyrecord_fields(record: AnyRecord) -> String {
+    record.field_names().join(",")
+}
+
+#[pg_extern]
+fn anyrecord_get_text(record: AnyRecord, field: &str) -> Option<String> {
+    record.get_by_name(field).expect("field should be text")
+}
+
+#[pg_extern]
+fn anyrecord_type_name(record: AnyRecord) -> String {
+    record.type_name()
+}
+
+#[cfg(any(test, feature = "pg_test"))]
+#[pgrx::pg_schema]
+mod tests {
+    #[allow(unused_imports)]
+    use crate as pgrx_tests;
+
+    use pgrx::prelude::*;
+
+    #[pg_test]
+    fn test_anonymous_record() -> Result<(), spi::Error> {
+        let fields = Spi::get_one::<String>("SELECT anyrecord_fields(ROW(1, 'a'::text))")?;
+        assert_eq!(fields.as_deref(), Some("f1,f2"));
+        let value = Spi::get_one::<String>("SELECT anyrecord_get_text(ROW(1, 'a'::text), 'f2')")?;
+        assert_eq!(value.as_deref(), Some("a"));
+        let name = Spi::get_one::<String>("SELECT anyrecord_type_name(ROW(1, 'a'::text))")?;
+        assert_eq!(name.as_deref(), Some("record"));
+        Ok(())
+    }
+
+    #[pg_test]
+    fn test_named_composite_record() -> Result<(), spi::Error> {
+        Spi::run("CREATE TYPE anyrecord_pet AS (name text, legs int)")?;
+        let value = Spi::get_one::<String>(
+            "SELECT anyrecord_get_text(ROW('rex', 4)::anyrecord_pet, 'name')",
+        )?;
+        assert_eq!(value.as_deref(), Some("rex"));
+        let name =
+            Spi::get_one::<String>("SELECT anyrecord_type_name(ROW('rex', 4)::anyrecord_pet)")?;
+        assert_eq!(name.as_deref(), Some("anyrecord_pet"));
+        Ok(())
+    }
+
+    #[pg_test]
+    fn test_plpgsql_record_variable() -> Result<(), spi::Error> {
+        Spi::run(
+            "CREATE FUNCTION anyrecord_plpgsql() RETURNS text LANGUAGE plpgsql AS $$
+            DECLARE
+                r RECORD;
+            BEGIN
+                SELECT 'first' AS label, 2 AS n INTO r;
+                RETURN anyrecord_get_text(r, 'label');
+            END;
+            $$",
+        )?;
+        let value = Spi::get_one::<String>("SELECT anyrecord_plpgsql()")?;
+        assert_eq!(value.as_deref(), Some("first"));
+        Ok(())
+    }
+
+    #[pg_test]
+    fn test_null_record() -> Result<(), spi::Error> {
+        let fields = Spi::get_one::<String>("SELECT anyrecord_fields(NULL::record)")?;
+        assert_eq!(fields, None);
+        Ok(())
+    }
+}
@@ -161,6 +161,107 @@ fn enum_array_roundtrip(a: Array<ArrayTestEnum>) -> Vec<Option<ArrayTestEnum>> {
     a.into_iter().collect()
 }
 
+#[pg_extern]
+fn arr_par_squares(values: Array<i64>, chunk_size: i32) -> Vec<Option<i64>> {
+    let chunks = values.par_chunks(chunk_size as usize);
+    let results = std::thread::scope(|scope| {
+        let handles = chunks
+            .into_iter()
+            .map(|chunk| scope.spawn(move || chunk.into_iter().map(|v| v.map(|v| v * v)).collect()))
+            .collect::<Vec<_>>();
+        handles.into_iter().map(|handle| handle.join().unwrap()).collect::<Vec<Vec<_>>>()
+    });
+    pgrx::merge_array_chunks(results)
+}
+
+#[pg_extern]
+fn arr_chunk_lengths(values: Array<i32>, chunk_size: i32) -> Vec<i32> {
+    values.par_chunks(chunk_size as usize).iter().map(|chunk| chunk.len() as i32).collect()
+}
+
+#[pg_extern]
+fn arr_builder_evens(limit: i32) -> pgrx::ArrayBuilder<i32> {
+    let mut builder = pgrx::ArrayBuilder::with_capacity(limit as usize / 2);
+    for i in (0..limit).step_by(2) {
+        builder.push(i);
+    }
+    builder.push(None);
+    builder
+}
+
+#[pg_extern]
+fn arr_builder_text(values: Array<&str>) -> pgrx::ArrayBuilder<String> {
+    values.iter().map(|v| v.map(str::to_uppercase)).collect()
+}
+
+#[pg_extern]
+fn arr_borrowed_lengths(values: Array<String>) -> Vec<Option<i32>> {
+    values.iter_borrowed().map(|v| v.map(|v| v.len() as i32)).collect()
+}
+
+#[pg_extern]
+fn arr_borrowed_concat(values: Array<&str>) -> String {
+    values.iter_borrowed().flatten().collect()
+}
+
+#[pg_extern]
+fn arr_borrowed_byte_sum(values: Array<Vec<u8>>) -> i64 {
+    values.iter_borrowed().flatten().flat_map(|bytes| bytes.iter()).map(|&b| b as i64).sum()
+}
+
+#[pg_extern]
+fn arr_get_reversed_int(values: Array<i32>) -> Vec<Option<i32>> {
+    (0..values.len()).rev().map(|i| values.get(i).flatten()).collect()
+}
+
+#[pg_extern]
+fn arr_get_reversed_text(values: Array<String>) -> Vec<Option<String>> {
+    (0..values.len()).rev().map(|i| values.get(i).flatten()).collect()
+}
+
+#[pg_extern]
+fn arr_get_repeated(values: Array<String>, index: i32, times: i32) -> Vec<Option<String>> {
+    (0..times).map(|_| values.get(index as usize).flatten()).collect()
+}
+
+#[pg_extern]
+fn arr_window_sums(values: Array<i64>, window: i32) -> Vec<i64> {
+    let window = window as usize;
+    (0..=values.len().saturating_sub(window))
+        .map(|start| values.slice(start..start + window).iter().flatten().sum())
+        .collect()
+}
+
+#[pg_extern]
+fn arr_slice_text(values: Array<String>, start: i32, end: i32) -> Vec<Option<String>> {
+    values.slice(start as usize..end as usize).to_vec()
+}
+
+#[pg_extern]
+fn arr_contains_text(haystack: Array<String>, needles: Array<String>) -> bool {
+    haystack.contains(&needles)
+}
+
+#[pg_extern]
+fn arr_overlaps_int(a: Array<i32>, b: Array<i32>) -> bool {
+    a.overlaps(&b)
+}
+
+#[pg_extern]
+fn arr_position_text(values: Array<String>, value: Option<String>) -> Option<i32> {
+    values.position(value).map(|i| i as i32)
+}
+
+#[pg_extern]
+fn arr_sorted_text(values: Array<String>) -> Vec<Option<String>> {
+    values.sorted()
+}
+
+#[pg_extern]
+fn arr_dedup_int(values: Array<i32>) -> Vec<Option<i32>> {
+    values.dedup()
+}
+
 #[cfg(any(test, feature = "pg_test"))]
 #[pgrx::pg_schema]
 mod tests {
@@ -354,4 +455,311 @@ mod tests {
     fn test_arr_sort_uniq_with_null() -> Result<(), pgrx::spi::Error> {
         Spi::get_one::<Vec<i32>>("SELECT arr_sort_uniq(ARRAY[3,2,NULL,2,1]::integer[])").map(|_| ())
     }
+
+    #[pg_test]
+    fn test_arr_par_chunks() -> Result<(), pgrx::spi::Error> {
+        let lengths = Spi::get_one::<Vec<i32>>(
+            "SELECT arr_chunk_lengths(ARRAY[1,2,3,4,5,6,7]::integer[], 3)",
+        )?;
+        assert_eq!(lengths, Some(vec![3, 3, 1]));
+
+        let empty = Spi::get_one::<Vec<i32>>("SELECT arr_chunk_lengths(ARRAY[]::integer[], 3)")?;
+        assert_eq!(empty, Some(vec![]));
+        Ok(())
+    }
+
+    #[pg_test]
+    fn test_arr_par_chunks_merge() -> Result<(), pgrx::spi::Error> {
+        let squares = Spi::get_one::<Vec<Option<i64>>>(
+            "SELECT arr_par_squares(ARRAY[1,2,NULL,4,5]::bigint[], 2)",
+        )?;
+        assert_eq!(squares, Some(vec![Some(1), Some(4), None, Some(16), Some(25)]));
+        Ok(())
+    }
+
+    #[pg_test]
+    #[should_panic(expected = "chunk_size must be greater than zero")]
+    fn test_arr_par_chunks_zero() -> Result<(), pgrx::spi::Error> {
+        Spi::get_one::<Vec<i32>>("SELECT arr_chunk_lengths(ARRAY[1]::integer[], 0)").map(|_| ())
+    }
+
+    #[pg_test]
+    fn test_arr_builder_return() -> Result<(), pgrx::spi::Error> {
+        let evens = Spi::get_one::<Vec<Option<i32>>>("SELECT arr_builder_evens(7)")?;
+        assert_eq!(evens, Some(vec![Some(0), Some(2), Some(4), Some(6), None]));
+
+        let empty = Spi::get_one::<Vec<Option<i32>>>("SELECT arr_builder_evens(0)")?;
+        assert_eq!(empty, Some(vec![None]));
+
+        let text =
+            Spi::get_one::<Vec<Option<String>>>("SELECT arr_builder_text(ARRAY['a', NULL, 'bc'])")?;
+        assert_eq!(text, Some(vec![Some("A".to_string()), None, Some("BC".to_string())]));
+        Ok(())
+    }
+
+    #[pg_test]
+    fn test_arr_builder_into_array() {
+        let mut builder = pgrx::ArrayBuilder::<i64>::new();
+        assert!(builder.is_empty());
+        builder.reserve(100);
+        builder.extend((1..=100).map(Some));
+        builder.push(None);
+        assert_eq!(builder.len(), 101);
+
+        let array = builder.into_array();
+        assert_eq!(array.len(), 101);
+        assert_eq!(array.get(0), Some(Some(1)));
+        assert_eq!(array.get(99), Some(Some(100)));
+        assert_eq!(array.get(100), Some(None));
+    }
+
+    #[pg_test]
+    fn test_arr_builder_finish_datum() -> Result<(), pgrx::spi::Error> {
+        let mut builder = pgrx::ArrayBuilder::<i32>::new();
+        builder.push(3);
+        builder.push(4);
+        let length = Spi::get_one_with_args::<i32>(
+            "SELECT array_length($1, 1)",
+            vec![(PgBuiltInOids::INT4ARRAYOID.oid(), Some(builder.finish()))],
+        )?;
+        assert_eq!(length, Some(2));
+        Ok(())
+    }
+
+    #[pg_test]
+    fn test_arr_builder_dropped_unfinished() {
+        let mut builder = pgrx::ArrayBuilder::<String>::with_capacity(10);
+        builder.push("unused".to_string());
+        drop(builder);
+    }
+
+    #[pg_test]
+    fn test_arr_iter_borrowed_text() -> Result<(), pgrx::spi::Error> {
+        let lengths = Spi::get_one::<Vec<Option<i32>>>(
+            "SELECT arr_borrowed_lengths(ARRAY['a', NULL, repeat('x', 200), ''])",
+        )?;
+        assert_eq!(lengths, Some(vec![Some(1), None, Some(200), Some(0)]));
+
+        let lengths = Spi::get_one::<Vec<Option<i32>>>(
+            "SELECT arr_borrowed_lengths(ARRAY[NULL, NULL, 'a', repeat('x', 200)])",
+        )?;
+        assert_eq!(lengths, Some(vec![None, None, Some(1), Some(200)]));
+
+        let concat =
+            Spi::get_one::<String>("SELECT arr_borrowed_concat(ARRAY['ab', NULL, 'cd', 'é'])")?;
+        assert_eq!(concat.as_deref(), Some("abcdé"));
+        Ok(())
+    }
+
+    #[pg_test]
+    fn test_arr_iter_borrowed_bytea() -> Result<(), pgrx::spi::Error> {
+        let sum = Spi::get_one::<i64>(
+            "SELECT arr_borrowed_byte_sum(ARRAY['\\x0102'::bytea, NULL, '\\xff'::bytea])",
+        )?;
+        assert_eq!(sum, Some(258));
+        Ok(())
+    }
+
+    #[pg_test]
+    fn test_arr_get_fixed_size() -> Result<(), pgrx::spi::Error> {
+        let reversed = Spi::get_one::<Vec<Option<i32>>>(
+            "SELECT arr_get_reversed_int(array_agg(i)) FROM generate_series(1, 1000) i",
+        )?
+        .unwrap();
+        assert_eq!(reversed, (1..=1000).rev().map(Some).collect::<Vec<_>>());
+
+        let reversed = Spi::get_one::<Vec<Option<i32>>>(
+            "SELECT arr_get_reversed_int(ARRAY[1, NULL, 3, NULL, 5])",
+        )?;
+        assert_eq!(reversed, Some(vec![Some(5), None, Some(3), None, Some(1)]));
+        Ok(())
+    }
+
+    #[pg_test]
+    fn test_arr_get_varlena() -> Result<(), pgrx::spi::Error> {
+        let reversed = Spi::get_one::<Vec<Option<String>>>(
+            "SELECT arr_get_reversed_text(ARRAY['a', NULL, repeat('b', 300), '', NULL, 'c'])",
+        )?;
+        assert_eq!(
+            reversed,
+            Some(vec![
+                Some("c".to_string()),
+                None,
+                Some("".to_string()),
+                Some("b".repeat(300)),
+                None,
+                Some("a".to_string())
+            ])
+        );
+
+        let repeated = Spi::get_one::<Vec<Option<String>>>(
+            "SELECT arr_get_repeated(ARRAY['a', NULL, 'b', 'c'], 2, 3)",
+        )?;
+        assert_eq!(repeated, Some(vec![Some("b".to_string()); 3]));
+        Ok(())
+    }
+
+    #[pg_test]
+    fn test_arr_slice_windows() -> Result<(), pgrx::spi::Error> {
+        let sums = Spi::get_one::<Vec<i64>>(
+            "SELECT arr_window_sums(ARRAY[1, 2, NULL, 4, 5]::bigint[], 2)",
+        )?;
+        assert_eq!(sums, Some(vec![3, 2, 4, 9]));
+        Ok(())
+    }
+
+    #[pg_test]
+    fn test_arr_slice_text() -> Result<(), pgrx::spi::Error> {
+        let slice = Spi::get_one::<Vec<Option<String>>>(
+            "SELECT arr_slice_text(ARRAY['a', NULL, 'bb', 'ccc', NULL], 1, 4)",
+        )?;
+        assert_eq!(slice, Some(vec![None, Some("bb".to_string()), Some("ccc".to_string())]));
+
+        let empty =
+            Spi::get_one::<Vec<Option<String>>>("SELECT arr_slice_text(ARRAY['a', 'b'], 2, 2)")?;
+        assert_eq!(empty, Some(vec![]));
+        Ok(())
+    }
+
+    #[pg_test]
+    fn test_arr_slice_of_slice() {
+        let array = vec![Some(1), None, Some(3), Some(4), Some(5)].into_datum().unwrap();
+        let array = unsafe { Array::<i32>::from_datum(array, false) }.unwrap();
+        let slice = array.slice(1..);
+        assert_eq!(slice.len(), 4);
+        assert_eq!(slice.get(0), Some(None));
+        assert_eq!(slice.get(4), None);
+        let inner = slice.slice(1..=2);
+        assert_eq!(inner.start(), 2);
+        assert_eq!(inner.to_vec(), vec![Some(3), Some(4)]);
+    }
+
+    #[pg_test(error = "range end index 4 out of range for slice of length 3")]
+    fn test_arr_slice_out_of_range() {
+        let array = vec![1, 2, 3].into_datum().unwrap();
+        let array = unsafe { Array::<i32>::from_datum(array, false) }.unwrap();
+        array.slice(1..4);
+    }
+
+    #[pg_test]
+    fn test_arr_contains_matches_core() -> Result<(), pgrx::spi::Error> {
+        for (haystack, needles) in [
+            ("ARRAY['a', 'b', 'c']", "ARRAY['c', 'a', 'a']"),
+            ("ARRAY['a', 'b', NULL]", "ARRAY['a', NULL]"),
+            ("ARRAY['a', 'b']", "ARRAY['d']"),
+            ("ARRAY['a']", "'{}'::text[]"),
+        ] {
+            let ours =
+                Spi::get_one::<bool>(&format!("SELECT arr_contains_text({haystack}, {needles})"))?;
+            let core = Spi::get_one::<bool>(&format!("SELECT {haystack} @> {needles}"))?;
+            assert_eq!(ours, core, "{haystack} @> {needles}");
+        }
+        Ok(())
+    }
+
+    #[pg_test]
+    fn test_arr_overlaps() -> Result<(), pgrx::spi::Error> {
+        assert_eq!(
+            Spi::get_one::<bool>("SELECT arr_overlaps_int(ARRAY[1, 2], ARRAY[3, 2])")?,
+            Some(true)
+        );
+        assert_eq!(
+            Spi::get_one::<bool>("SELECT arr_overlaps_int(ARRAY[1, NULL], ARRAY[NULL, 3])")?,
+            Some(false)
+        );
+        Ok(())
+    }
+
+    #[pg_test]
+    fn test_arr_position() -> Result<(), pgrx::spi::Error> {
+        let array = "ARRAY['a', NULL, 'b', 'b']";
+        assert_eq!(
+            Spi::get_one::<i32>(&format!("SELECT arr_position_text({array}, 'b')"))?,
+            Some(2)
+        );
+        assert_eq!(
+            Spi::get_one::<i32>(&format!("SELECT arr_position_text({array}, NULL)"))?,
+            Some(1)
+        );
+        assert_eq!(Spi::get_one::<i32>(&format!("SELECT arr_position_text({array}, 'z')"))?, None);
+        Ok(())
+    }
+
+    #[pg_test]
+    fn test_arr_sorted_matches_order_by() -> Result<(), pgrx::spi::Error> {
+        let ours = Spi::get_one::<Vec<Option<String>>>(
+            "SELECT arr_sorted_text(ARRAY['pear', NULL, 'Apple', 'banana', 'apple'])",
+        )?;
+        let core = Spi::get_one::<Vec<Option<String>>>(
+            "SELECT array_agg(v ORDER BY v) FROM unnest(ARRAY['pear', NULL, 'Apple', 'banana', 'apple']) v",
+        )?;
+        assert_eq!(ours, core);
+        Ok(())
+    }
+
+    #[pg_test]
+    fn test_arr_dedup() -> Result<(), pgrx::spi::Error> {
+        let deduped = Spi::get_one::<Vec<Option<i32>>>(
+            "SELECT arr_dedup_int(ARRAY[3, 1, NULL, 3, 2, 1, NULL])",
+        )?;
+        assert_eq!(deduped, Some(vec![Some(3), Some(1), None, Some(2)]));
+        Ok(())
+    }
+
+    #[pg_test(error = "could not identify a comparison function for type point")]
+    fn test_arr_sorted_unsupported_type() -> Result<(), pgrx::spi::Error> {
+        Spi::connect(|client| {
+            let datum = client
+                .select("SELECT ARRAY[point(1, 2)]", None, None)?
+                .first()
+                .get_datum_by_ordinal(1)?;
+            let points =
+                unsafe { Array::<pg_sys::Datum>::from_datum(datum.unwrap(), false) }.unwrap();
+            points.sorted();
+            Ok(())
+        })
+    }
+
+    #[pg_test]
+    fn test_arr_push_returning_datum() {
+        let array = vec![Some(1), None].into_datum().unwrap();
+        let array = unsafe { Array::<i32>::from_datum(array, false) }.unwrap();
+        let pushed = array.push_returning_datum(3);
+        let pushed = unsafe { Vec::<Option<i32>>::from_datum(pushed, false) };
+        assert_eq!(pushed, Some(vec![Some(1), None, Some(3)]));
+
+        let empty = Vec::<String>::new().into_datum().unwrap();
+        let empty = unsafe { Array::<String>::from_datum(empty, false) }.unwrap();
+        let pushed = empty.push_returning_datum(None);
+        let pushed = unsafe { Vec::<Option<String>>::from_datum(pushed, false) };
+        assert_eq!(pushed, Some(vec![None]));
+    }
+
+    #[pg_test]
+    fn test_arr_concat() -> Result<(), pgrx::spi::Error> {
+        let a = vec!["a".to_string(), "bb".to_string()].into_datum().unwrap();
+        let a = unsafe { Array::<String>::from_datum(a, false) }.unwrap();
+        let b = vec![None, Some("ccc".to_string())].into_datum().unwrap();
+        let b = unsafe { Array::<String>::from_datum(b, false) }.unwrap();
+        let concat = a.concat(&b);
+
+        let text = Spi::get_one_with_args::<String>(
+            "SELECT $1::text",
+            vec![(PgBuiltInOids::TEXTARRAYOID.oid(), Some(concat))],
+        )?;
+        assert_eq!(text.as_deref(), Some("{a,bb,NULL,ccc}"));
+        Ok(())
+    }
+
+    #[pg_test]
+    fn test_arr_set_element() {
+        let array = vec![1, 2, 3].into_datum().unwrap();
+        let array = unsafe { Array::<i64>::from_datum(array, false) }.unwrap();
+
+        let set = unsafe { Vec::<Option<i64>>::from_datum(array.set_element(1, None), false) };
+        assert_eq!(set, Some(vec![Some(1), None, Some(3)]));
+
+        let set = unsafe { Vec::<Option<i64>>::from_datum(array.set_element(4, 5), false) };
+        assert_eq!(set, Some(vec![Some(1), Some(2), Some(3), None, Some(5)]));
+    }
 }
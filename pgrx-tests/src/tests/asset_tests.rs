@@ -0,0 +1,80 @@
+/*
+Portions Copyright 2019-2021 ZomboDB, LLC.
+Portions Copyright 2021-2022 Technology Concepts & Design, Inc. <support@tcdi.com>
+
+All rights reserved.
+
+Use of this source code is governed by the MIT license that can be found in the LICENSE file.
+*/
+
+#[cfg(any(test, feature = "pg_test"))]
+#[pgrx::pg_schema]
+mod tests {
+    #[allow(unused_imports)]
+    use crate as pgrx_tests;
+
+    use pgrx::asset::{self, MappedAsset};
+    use pgrx::data_dir::ExtensionDataDir;
+    use pgrx::prelude::*;
+    use std::sync::Arc;
+
+    #[pg_test]
+    fn test_map() {
+        let dir = ExtensionDataDir::open("pgrx_tests_map").unwrap();
+        dir.write("model.bin", [1, 2, 3, 4]).unwrap();
+        dir.write("empty.bin", []).unwrap();
+
+        let model = MappedAsset::map(dir.file_path("model.bin").unwrap()).unwrap();
+        assert_eq!(&*model, [1, 2, 3, 4]);
+        assert!(!model.is_stale());
+
+        let empty = MappedAsset::map(dir.file_path("empty.bin").unwrap()).unwrap();
+        assert!(empty.is_empty());
+
+        let missing = MappedAsset::map(dir.path().join("missing.bin"));
+        assert_eq!(missing.unwrap_err().kind(), std::io::ErrorKind::NotFound);
+        dir.remove_all().unwrap();
+    }
+
+    #[pg_test]
+    fn test_load_cached() {
+        let dir = ExtensionDataDir::open("pgrx_tests_load").unwrap();
+        let path = dir.file_path("words.txt").unwrap();
+        dir.write("words.txt", "nami").unwrap();
+
+        let first = asset::load(&path).unwrap();
+        let second = asset::load(&path).unwrap();
+        assert!(Arc::ptr_eq(&first, &second));
+        assert!(asset::is_cached(&path));
+
+        asset::invalidate(&path);
+        assert!(!asset::is_cached(&path));
+        let third = asset::load(&path).unwrap();
+        assert!(!Arc::ptr_eq(&first, &third));
+        assert_eq!(first.as_bytes(), third.as_bytes());
+
+        asset::invalidate(&path);
+        dir.remove_all().unwrap();
+    }
+
+    #[pg_test]
+    fn test_load_changed() {
+        let dir = ExtensionDataDir::open("pgrx_tests_changed").unwrap();
+        let path = dir.file_path("words.txt").unwrap();
+        dir.write("words.txt", "nami").unwrap();
+        let old = asset::load(&path).unwrap();
+
+        dir.write("words.txt", "brandy").unwrap();
+        assert!(old.is_stale());
+        let new = asset::load(&path).unwrap();
+        assert_eq!(new.as_bytes(), b"brandy");
+        // the old mapping is still readable
+        assert_eq!(old.as_bytes(), b"nami");
+
+        dir.remove("words.txt").unwrap();
+        assert!(new.is_stale());
+        assert!(asset::load(&path).is_err());
+        assert!(!asset::is_cached(&path));
+        dir.remove_all().unwrap();
+    }
+}
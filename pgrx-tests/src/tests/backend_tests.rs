@@ -0,0 +1,43 @@
+/*
+Portions Copyright 2019-2021 ZomboDB, LLC.
+Portions Copyright 2021-2022 Technology Concepts & Design, Inc. <support@tcdi.com>
+
+All rights reserved.
+
+Use of this source code is governed by the MIT license that can be found in the LICENSE file.
+*/
+
+#[cfg(any(test, feature = "pg_test"))]
+#[pgrx::pg_schema]
+mod tests {
+    #[allow(unused_imports)]
+    use crate as pgrx_tests;
+
+    use pgrx::backend::{before_shmem_exit, on_backend_startup, on_proc_exit};
+    use pgrx::prelude::*;
+    use std::cell::Cell;
+    use std::rc::Rc;
+
+    #[pg_test]
+    fn test_startup_runs_immediately_in_a_backend() {
+        let ran = Rc::new(Cell::new(0));
+        let counter = Rc::clone(&ran);
+        on_backend_startup(move || counter.set(counter.get() + 1));
+        assert_eq!(ran.get(), 1);
+
+        let counter = Rc::clone(&ran);
+        on_backend_startup(move || counter.set(counter.get() + 1));
+        assert_eq!(ran.get(), 2);
+    }
+
+    #[pg_test]
+    fn test_exit_callbacks_are_deferred() {
+        let ran = Rc::new(Cell::new(false));
+        let flag = Rc::clone(&ran);
+        before_shmem_exit(move || flag.set(true));
+        let flag = Rc::clone(&ran);
+        on_proc_exit(move || flag.set(true));
+        on_proc_exit(|| panic!("exit callbacks can panic without stopping the others"));
+        assert!(!ran.get());
+    }
+}
@@ -82,6 +82,121 @@ pub extern "C" fn bgworker_return_value(arg: pg_sys::Datum) {
     .expect("bgworker transaction failed");
 }
 
+#[pg_guard]
+#[no_mangle]
+/// Records the `nice` value, CPU affinity, and `extra` the worker has, once it has applied its
+/// resource controls
+pub extern "C" fn bgworker_resource_controls(_arg: pg_sys::Datum) {
+    use pgrx::bgworkers::*;
+    use std::time::Duration;
+    BackgroundWorker::attach_signal_handlers(SignalWakeFlags::SIGHUP | SignalWakeFlags::SIGTERM);
+    BackgroundWorker::apply_resource_controls().expect("failed to apply resource controls");
+    BackgroundWorker::connect_worker_to_spi(
+        Some(crate::framework::get_pg_dbname()),
+        Some(crate::framework::get_pg_user().as_str()),
+    );
+
+    let nice = unsafe { libc::getpriority(libc::PRIO_PROCESS, 0) };
+    let cpus = unsafe {
+        let mut set = std::mem::zeroed::<libc::cpu_set_t>();
+        libc::sched_getaffinity(0, std::mem::size_of::<libc::cpu_set_t>(), &mut set);
+        libc::CPU_COUNT(&set)
+    };
+    BackgroundWorker::transaction(|| {
+        Spi::run(
+            "CREATE TABLE tests.bgworker_resources (nice INTEGER, cpus INTEGER, extra TEXT);",
+        )?;
+        Spi::connect(|mut client| {
+            client.update(
+                "INSERT INTO tests.bgworker_resources VALUES ($1, $2, $3);",
+                None,
+                Some(vec![
+                    (PgOid::BuiltIn(PgBuiltInOids::INT4OID), nice.into_datum()),
+                    (PgOid::BuiltIn(PgBuiltInOids::INT4OID), cpus.into_datum()),
+                    (
+                        PgOid::BuiltIn(PgBuiltInOids::TEXTOID),
+                        BackgroundWorker::get_extra().into_datum(),
+                    ),
+                ]),
+            )
+        })
+    })
+    .expect("bgworker transaction failed");
+    while BackgroundWorker::wait_latch(Some(Duration::from_millis(100))) {}
+}
+
+#[pg_guard]
+#[no_mangle]
+/// Counts the timeouts of its latch loop, until it has seen `arg` of them, or forever if `arg` is
+/// zero, and records the count and how the loop ended
+pub extern "C" fn bgworker_latch_loop(arg: pg_sys::Datum) {
+    use pgrx::bgworkers::*;
+    use std::ops::ControlFlow;
+    use std::time::Duration;
+    BackgroundWorker::attach_signal_handlers(SignalWakeFlags::SIGHUP | SignalWakeFlags::SIGTERM);
+    BackgroundWorker::connect_worker_to_spi(
+        Some(crate::framework::get_pg_dbname()),
+        Some(crate::framework::get_pg_user().as_str()),
+    );
+
+    let limit = unsafe { i32::from_datum(arg, false) }.expect("invalid arg");
+    let mut timeouts = 0;
+    let result = BackgroundWorker::wait_latch_loop(Some(Duration::from_millis(10)), |event| {
+        if event == LatchEvent::Timeout {
+            timeouts += 1;
+        }
+        if timeouts == limit {
+            ControlFlow::Break("limit")
+        } else {
+            ControlFlow::Continue(())
+        }
+    });
+    let ended_by = result.unwrap_or("sigterm");
+    BackgroundWorker::transaction(|| {
+        Spi::run(&format!(
+            "CREATE TABLE tests.bgworker_latch_loop_{} AS SELECT {} AS timeouts, '{}' AS ended_by;",
+            limit, timeouts, ended_by
+        ))
+    })
+    .expect("bgworker transaction failed");
+}
+
+#[derive(serde::Serialize, serde::Deserialize)]
+pub struct BgworkerJob {
+    table: String,
+    values: Vec<i32>,
+}
+
+#[pg_guard]
+#[no_mangle]
+/// Creates the table the job named, and inserts the job's values into it
+pub extern "C" fn bgworker_extra_data(_arg: pg_sys::Datum) {
+    use pgrx::bgworkers::*;
+    use std::time::Duration;
+    BackgroundWorker::attach_signal_handlers(SignalWakeFlags::SIGHUP | SignalWakeFlags::SIGTERM);
+    BackgroundWorker::connect_worker_to_spi(
+        Some(crate::framework::get_pg_dbname()),
+        Some(crate::framework::get_pg_user().as_str()),
+    );
+
+    let job = BackgroundWorker::get_extra_data::<BgworkerJob>().expect("invalid extra data");
+    BackgroundWorker::transaction(|| {
+        Spi::run(&format!("CREATE TABLE tests.{} (v INTEGER);", job.table))?;
+        Spi::connect(|mut client| {
+            for value in &job.values {
+                client.update(
+                    &format!("INSERT INTO tests.{} VALUES ($1);", job.table),
+                    None,
+                    Some(vec![(PgOid::BuiltIn(PgBuiltInOids::INT4OID), value.into_datum())]),
+                )?;
+            }
+            Ok::<_, pgrx::spi::Error>(())
+        })
+    })
+    .expect("bgworker transaction failed");
+    while BackgroundWorker::wait_latch(Some(Duration::from_millis(100))) {}
+}
+
 #[cfg(any(test, feature = "pg_test"))]
 #[pgrx::pg_schema]
 mod tests {
@@ -155,4 +270,121 @@ mod tests {
 
         assert_eq!(Ok(Some(123)), Spi::get_one::<i32>("SELECT v FROM tests.bgworker_test_return;"));
     }
+
+    #[pg_test]
+    fn test_bgworker_resource_controls() {
+        // a CPU this backend is allowed to run on, so the worker is too
+        let cpu = unsafe {
+            let mut set = std::mem::zeroed::<libc::cpu_set_t>();
+            libc::sched_getaffinity(0, std::mem::size_of::<libc::cpu_set_t>(), &mut set);
+            (0..libc::CPU_SETSIZE as usize).find(|&cpu| libc::CPU_ISSET(cpu, &set)).unwrap()
+        };
+        let worker = BackgroundWorkerBuilder::new("dynamic_bgworker")
+            .set_library("pgrx_tests")
+            .set_function("bgworker_resource_controls")
+            .set_extra("controlled")
+            .set_nice(5)
+            .set_cpu_affinity(&[cpu])
+            .enable_spi_access()
+            .set_notify_pid(unsafe { pg_sys::MyProcPid })
+            .load_dynamic();
+        worker.wait_for_startup().expect("no PID from the worker");
+        let handle = worker.terminate();
+        handle.wait_for_shutdown().expect("aborted shutdown");
+
+        let nice = Spi::get_one::<i32>("SELECT nice FROM tests.bgworker_resources;");
+        let cpus = Spi::get_one::<i32>("SELECT cpus FROM tests.bgworker_resources;");
+        let extra = Spi::get_one::<&str>("SELECT extra FROM tests.bgworker_resources;");
+        assert_eq!(Ok(Some(5)), nice);
+        assert_eq!(Ok(Some(1)), cpus);
+        assert_eq!(Ok(Some("controlled")), extra);
+    }
+
+    #[pg_test]
+    fn test_dynamic_bgworker_extra_data() {
+        let job = super::BgworkerJob { table: "bgworker_job".into(), values: vec![1, 2, 3] };
+        let (worker, pid) = BackgroundWorkerBuilder::new("dynamic_bgworker")
+            .set_library("pgrx_tests")
+            .set_function("bgworker_extra_data")
+            .set_extra_data(&job)
+            .enable_spi_access()
+            .load_dynamic_and_wait()
+            .expect("the worker didn't start");
+        assert!(pid > 0);
+        worker.terminate().wait_for_shutdown().expect("aborted shutdown");
+
+        let sum = Spi::get_one::<i64>("SELECT sum(v) FROM tests.bgworker_job;");
+        assert_eq!(Ok(Some(6)), sum);
+    }
+
+    #[pg_test(error = "extra data must serialize to less than 128 bytes, not 205")]
+    fn test_bgworker_extra_data_too_long() {
+        BackgroundWorkerBuilder::new("dynamic_bgworker").set_extra_data(&"x".repeat(203));
+    }
+
+    #[pg_test]
+    fn test_try_load_dynamic() {
+        let worker = BackgroundWorkerBuilder::new("dynamic_bgworker")
+            .set_library("pgrx_tests")
+            .set_function("bgworker")
+            .set_argument(0i32.into_datum())
+            .enable_spi_access()
+            .set_notify_pid(unsafe { pg_sys::MyProcPid })
+            .try_load_dynamic()
+            .expect("the worker wasn't registered");
+        worker.wait_for_startup().expect("no PID from the worker");
+        worker.terminate().wait_for_shutdown().expect("aborted shutdown");
+    }
+
+    #[pg_test]
+    fn test_bgworker_latch_loop_break() {
+        let (worker, _) = BackgroundWorkerBuilder::new("dynamic_bgworker")
+            .set_library("pgrx_tests")
+            .set_function("bgworker_latch_loop")
+            .set_argument(3i32.into_datum())
+            .enable_spi_access()
+            .load_dynamic_and_wait()
+            .expect("the worker didn't start");
+        worker.wait_for_shutdown().expect("aborted shutdown");
+
+        let timeouts = Spi::get_one::<i32>("SELECT timeouts FROM tests.bgworker_latch_loop_3;");
+        let ended_by = Spi::get_one::<&str>("SELECT ended_by FROM tests.bgworker_latch_loop_3;");
+        assert_eq!(Ok(Some(3)), timeouts);
+        assert_eq!(Ok(Some("limit")), ended_by);
+    }
+
+    #[pg_test]
+    fn test_bgworker_latch_loop_sigterm() {
+        let (worker, _) = BackgroundWorkerBuilder::new("dynamic_bgworker")
+            .set_library("pgrx_tests")
+            .set_function("bgworker_latch_loop")
+            .set_argument(0i32.into_datum())
+            .enable_spi_access()
+            .load_dynamic_and_wait()
+            .expect("the worker didn't start");
+        std::thread::sleep(std::time::Duration::from_millis(100));
+        worker.terminate().wait_for_shutdown().expect("aborted shutdown");
+
+        let timeouts = Spi::get_one::<i32>("SELECT timeouts FROM tests.bgworker_latch_loop_0;");
+        let ended_by = Spi::get_one::<&str>("SELECT ended_by FROM tests.bgworker_latch_loop_0;");
+        assert!(timeouts.unwrap().unwrap() > 0);
+        assert_eq!(Ok(Some("sigterm")), ended_by);
+    }
+
+    #[pg_test]
+    fn test_cpu_budget() {
+        let budget = CpuBudget::new(0.5);
+        // burn some CPU, faster than half of it is earned
+        let start = std::time::Instant::now();
+        while start.elapsed() < std::time::Duration::from_millis(50) {
+            std::hint::black_box(0u64.wrapping_add(1));
+        }
+        assert!(budget.used() > std::time::Duration::ZERO);
+        assert!(budget.overage() > std::time::Duration::ZERO);
+    }
+
+    #[pg_test(error = "a CPU budget's share must be in (0, 1]")]
+    fn test_cpu_budget_share_out_of_range() {
+        CpuBudget::new(1.5);
+    }
 }
@@ -0,0 +1,119 @@
+/*
+Portions Copyright 2019-2021 ZomboDB, LLC.
+Portions Copyright 2021-2022 Technology Concepts & Design, Inc. <support@tcdi.com>
+
+All rights reserved.
+
+Use of this source code is governed by the MIT license that can be found in the LICENSE file.
+*/
+
+#[cfg(any(test, feature = "pg_test"))]
+#[pgrx::pg_schema]
+mod tests {
+    #[allow(unused_imports)]
+    use crate as pgrx_tests;
+
+    use pgrx::binary_copy::{BinaryCopyReader, BinaryCopyWriter, SIGNATURE};
+    use pgrx::prelude::*;
+
+    fn copy_file(name: &str) -> String {
+        std::env::temp_dir()
+            .join(format!("pgrx_{}_{}.copy", name, std::process::id()))
+            .to_string_lossy()
+            .into_owned()
+    }
+
+    #[pg_test]
+    fn test_binary_copy_raw_format() -> std::io::Result<()> {
+        let mut writer = BinaryCopyWriter::new(Vec::new())?;
+        writer.write_raw_row(&[Some(&[0, 0, 0, 42]), None])?;
+        let bytes = writer.finish()?;
+
+        let mut expected = SIGNATURE.to_vec();
+        expected.extend([0, 0, 0, 0, 0, 0, 0, 0]);
+        expected.extend([0, 2, 0, 0, 0, 4, 0, 0, 0, 42, 0xff, 0xff, 0xff, 0xff]);
+        expected.extend([0xff, 0xff]);
+        assert_eq!(bytes, expected);
+
+        let mut reader = BinaryCopyReader::new(bytes.as_slice())?;
+        assert_eq!(reader.read_raw_row()?, Some(vec![Some(vec![0, 0, 0, 42]), None]));
+        assert_eq!(reader.read_raw_row()?, None);
+        Ok(())
+    }
+
+    #[pg_test]
+    fn test_binary_copy_round_trip() -> std::io::Result<()> {
+        let types = [PgBuiltInOids::INT8OID.oid(), PgBuiltInOids::TEXTOID.oid()];
+        let mut writer = BinaryCopyWriter::new(Vec::new())?;
+        writer.write_row(&[(types[0], 7i64.into_datum()), (types[1], "seven".into_datum())])?;
+        writer.write_row(&[(types[0], None), (types[1], "none".into_datum())])?;
+        let bytes = writer.finish()?;
+
+        let mut reader = BinaryCopyReader::new(bytes.as_slice())?;
+        let row = reader.read_row(&types)?.expect("no first row");
+        assert_eq!(row.get::<i64>(1), Some(7));
+        assert_eq!(row.get::<String>(2), Some("seven".into()));
+        let row = reader.read_row(&types)?.expect("no second row");
+        assert_eq!(row.get::<i64>(1), None);
+        assert_eq!(row.get::<String>(2), Some("none".into()));
+        assert!(reader.read_row(&types)?.is_none());
+        Ok(())
+    }
+
+    #[pg_test]
+    fn test_binary_copy_from() -> Result<(), Box<dyn std::error::Error>> {
+        let path = copy_file("copy_from");
+        let file = std::fs::File::create(&path)?;
+        let mut writer = BinaryCopyWriter::new(std::io::BufWriter::new(file))?;
+        for i in 1..=100 {
+            writer.write_row(&[
+                (PgBuiltInOids::INT4OID.oid(), i.into_datum()),
+                (PgBuiltInOids::TEXTOID.oid(), format!("row {}", i).into_datum()),
+            ])?;
+        }
+        writer.finish()?;
+
+        Spi::run("CREATE TABLE tests.copied (id int, name text)")?;
+        Spi::run(&format!("COPY tests.copied FROM '{}' (FORMAT binary)", path))?;
+        std::fs::remove_file(&path)?;
+        assert_eq!(Spi::get_one::<i64>("SELECT count(*) FROM tests.copied")?, Some(100));
+        assert_eq!(
+            Spi::get_one::<String>("SELECT name FROM tests.copied WHERE id = 42")?,
+            Some("row 42".into())
+        );
+        Ok(())
+    }
+
+    #[pg_test]
+    fn test_binary_copy_to() -> Result<(), Box<dyn std::error::Error>> {
+        let path = copy_file("copy_to");
+        Spi::run(&format!(
+            "COPY (SELECT i, i::text || '!' FROM generate_series(1, 3) i) TO '{}' (FORMAT binary)",
+            path
+        ))?;
+
+        let file = std::fs::File::open(&path)?;
+        let mut reader = BinaryCopyReader::new(std::io::BufReader::new(file))?;
+        let types = [PgBuiltInOids::INT4OID.oid(), PgBuiltInOids::TEXTOID.oid()];
+        let mut rows = Vec::new();
+        while let Some(row) = reader.read_row(&types)? {
+            rows.push((row.get::<i32>(1), row.get::<String>(2)));
+        }
+        std::fs::remove_file(&path)?;
+        assert_eq!(
+            rows,
+            vec![
+                (Some(1), Some("1!".into())),
+                (Some(2), Some("2!".into())),
+                (Some(3), Some("3!".into()))
+            ]
+        );
+        Ok(())
+    }
+
+    #[pg_test]
+    fn test_binary_copy_bad_signature() {
+        let error = BinaryCopyReader::new(&b"PGCOPY\nnot binary copy"[..]).err().unwrap();
+        assert_eq!(error.kind(), std::io::ErrorKind::InvalidData);
+    }
+}
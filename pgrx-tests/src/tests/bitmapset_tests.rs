@@ -0,0 +1,93 @@
+/*
+Portions Copyright 2019-2021 ZomboDB, LLC.
+Portions Copyright 2021-2022 Technology Concepts & Design, Inc. <support@tcdi.com>
+
+All rights reserved.
+
+Use of this source code is governed by the MIT license that can be found in the LICENSE file.
+*/
+
+#[cfg(any(test, feature = "pg_test"))]
+#[pgrx::pg_schema]
+mod tests {
+    #[allow(unused_imports)]
+    use crate as pgrx_tests;
+
+    use pgrx::bitmapset::Bitmapset;
+    use pgrx::prelude::*;
+    use std::collections::BTreeSet;
+
+    #[pg_test]
+    fn test_bitmapset_members() {
+        let mut set = Bitmapset::new();
+        assert!(set.is_empty());
+        assert!(set.as_ptr().is_null());
+        assert_eq!(set.min(), None);
+
+        assert!(set.insert(3));
+        assert!(!set.insert(3));
+        assert_eq!(set.singleton_member(), Some(3));
+        set.extend([70, 1, 130]);
+        assert_eq!(set.len(), 4);
+        assert!(set.contains(70));
+        assert!(!set.contains(71));
+        assert_eq!(set.min(), Some(1));
+        assert_eq!(set.max(), Some(130));
+        assert_eq!(set.singleton_member(), None);
+        assert_eq!(set.to_vec(), vec![1, 3, 70, 130]);
+
+        assert!(set.remove(70));
+        assert!(!set.remove(70));
+        assert_eq!(format!("{:?}", set), "{1, 3, 130}");
+    }
+
+    #[pg_test]
+    fn test_bitmapset_operations() {
+        let a = Bitmapset::from(&[1, 2, 3][..]);
+        let b = Bitmapset::from(&[3, 4][..]);
+
+        assert_eq!(a.union(&b).to_vec(), vec![1, 2, 3, 4]);
+        assert_eq!(a.intersection(&b).to_vec(), vec![3]);
+        assert_eq!(a.difference(&b).to_vec(), vec![1, 2]);
+        assert!(a.overlaps(&b));
+        assert!(!a.is_subset(&b));
+        assert!(Bitmapset::singleton(2).is_subset(&a));
+        assert!(a.intersection(&Bitmapset::singleton(9)).is_empty());
+
+        let mut c = a.clone();
+        c.union_with(&b);
+        assert_eq!(c, a.union(&b));
+        c.intersect_with(&b);
+        assert_eq!(c, b);
+        c.difference_with(&Bitmapset::singleton(4));
+        assert_eq!(c, Bitmapset::singleton(3));
+    }
+
+    #[pg_test]
+    fn test_bitmapset_conversions() {
+        let set: Bitmapset = [0, 63, 64, 200].into_iter().collect();
+        let words = set.to_words();
+        assert_eq!(words, vec![1 | 1 << 63, 1, 0, 1 << 8]);
+        assert_eq!(Bitmapset::from_words(&words), set);
+
+        let members = BTreeSet::from(&set);
+        assert_eq!(members, BTreeSet::from([0, 63, 64, 200]));
+        assert_eq!(Bitmapset::from(&members), set);
+    }
+
+    #[pg_test]
+    fn test_bitmapset_from_postgres() {
+        let set = unsafe {
+            let bms = pg_sys::bms_add_member(pg_sys::bms_make_singleton(5), 9);
+            Bitmapset::from_pg(bms)
+        };
+        assert_eq!((&set).into_iter().collect::<Vec<_>>(), vec![5, 9]);
+        assert!(unsafe { pg_sys::bms_is_member(9, set.into_pg()) });
+    }
+
+    #[pg_test]
+    #[should_panic(expected = "negative bitmapset member not allowed")]
+    fn test_bitmapset_negative_member() {
+        Bitmapset::new().insert(-1);
+    }
+}
@@ -0,0 +1,72 @@
+/*
+Portions Copyright 2019-2021 ZomboDB, LLC.
+Portions Copyright 2021-2022 Technology Concepts & Design, Inc. <support@tcdi.com>
+
+All rights reserved.
+
+Use of this source code is governed by the MIT license that can be found in the LICENSE file.
+*/
+
+#[cfg(any(test, feature = "pg_test"))]
+#[pgrx::pg_schema]
+mod tests {
+    #[allow(unused_imports)]
+    use crate as pgrx_tests;
+
+    use pgrx::catalog::exists::*;
+    use pgrx::prelude::*;
+
+    #[pg_test]
+    fn test_type_exists() {
+        assert!(type_exists("int4"));
+        assert!(type_exists("pg_catalog.text[]"));
+        assert!(type_exists("varchar(10)"));
+        assert!(!type_exists("no_such_type"));
+
+        Spi::run("CREATE TYPE catalog_test_type AS (a int)").unwrap();
+        assert!(type_exists("catalog_test_type"));
+    }
+
+    #[pg_test]
+    fn test_function_exists() {
+        assert!(function_exists("pg_catalog.lower(text)"));
+        assert!(function_exists("pg_backend_pid"));
+        assert!(!function_exists("no_such_function(int)"));
+        assert!(!function_exists("no_such_function"));
+
+        Spi::run("CREATE FUNCTION no_such_function(int) RETURNS int LANGUAGE sql AS 'SELECT 1'")
+            .unwrap();
+        // make our own catalog changes visible, which is when the cached answers are forgotten
+        unsafe { pg_sys::CommandCounterIncrement() };
+        assert!(function_exists("no_such_function(int)"));
+        assert!(function_exists("no_such_function"));
+    }
+
+    #[pg_test]
+    fn test_extension_installed() {
+        assert!(extension_installed("plpgsql"));
+        assert!(!extension_installed("no_such_extension"));
+    }
+
+    #[pg_test]
+    fn test_alternatives() {
+        fn fallback(x: i32) -> i32 {
+            x
+        }
+        fn doubled(x: i32) -> i32 {
+            x * 2
+        }
+        fn tripled(x: i32) -> i32 {
+            x * 3
+        }
+
+        let alternatives = Alternatives::new(fallback)
+            .when(&[Requirement::Extension("no_such_extension")], doubled)
+            .when(&[Requirement::Type("int4"), Requirement::Function("pg_backend_pid")], tripled);
+        assert_eq!(alternatives.call(2), 6);
+
+        let alternatives = Alternatives::new(fallback)
+            .when(&[Requirement::Type("int4"), Requirement::Type("no_such_type")], doubled);
+        assert_eq!(alternatives.call(2), 2);
+    }
+}
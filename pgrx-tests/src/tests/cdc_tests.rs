@@ -0,0 +1,94 @@
+/*
+Portions Copyright 2019-2021 ZomboDB, LLC.
+Portions Copyright 2021-2022 Technology Concepts & Design, Inc. <support@tcdi.com>
+
+All rights reserved.
+
+Use of this source code is governed by the MIT license that can be found in the LICENSE file.
+*/
+use pgrx::prelude::*;
+
+extension_sql!("CREATE TABLE cdc_orders (id int PRIMARY KEY, total int);", name = "cdc_orders");
+
+pgrx::cdc_table!(table = "cdc_orders", requires = ["cdc_orders"]);
+
+#[cfg(any(test, feature = "pg_test"))]
+#[pgrx::pg_schema]
+mod tests {
+    #[allow(unused_imports)]
+    use crate as pgrx_tests;
+
+    use pgrx::prelude::*;
+    use std::time::Duration;
+
+    fn changes() -> Vec<(String, Option<String>, Option<String>)> {
+        Spi::connect(|client| {
+            client
+                .select(
+                    "SELECT operation, old_row::text, new_row::text \
+                     FROM cdc_orders_changes ORDER BY change_id",
+                    None,
+                    None,
+                )?
+                .map(|row| Ok((row.get(1)?.unwrap(), row.get(2)?, row.get(3)?)))
+                .collect::<Result<Vec<_>, pgrx::spi::Error>>()
+        })
+        .unwrap()
+    }
+
+    #[pg_test]
+    fn test_cdc_captures_changes() {
+        Spi::run("INSERT INTO cdc_orders VALUES (1, 10)").unwrap();
+        Spi::run("UPDATE cdc_orders SET total = 20 WHERE id = 1").unwrap();
+        Spi::run("DELETE FROM cdc_orders WHERE id = 1").unwrap();
+        Spi::run("TRUNCATE cdc_orders").unwrap();
+
+        let row = |total: i32| Some(format!(r#"{{"id": 1, "total": {}}}"#, total));
+        assert_eq!(
+            changes(),
+            vec![
+                ("INSERT".to_string(), None, row(10)),
+                ("UPDATE".to_string(), row(10), row(20)),
+                ("DELETE".to_string(), row(20), None),
+                ("TRUNCATE".to_string(), None, None),
+            ]
+        );
+    }
+
+    #[pg_test]
+    fn test_cdc_records_transaction() {
+        Spi::run("INSERT INTO cdc_orders VALUES (2, 10)").unwrap();
+        let same = Spi::get_one::<bool>(
+            "SELECT txid = txid_current() AND changed_at = now() FROM cdc_orders_changes",
+        );
+        assert_eq!(same, Ok(Some(true)));
+    }
+
+    #[pg_test]
+    fn test_cdc_purge() {
+        Spi::run("INSERT INTO cdc_orders VALUES (3, 10), (4, 10)").unwrap();
+        Spi::run(
+            "UPDATE cdc_orders_changes SET changed_at = now() - interval '2 hours' \
+             WHERE new_row->>'id' = '3'",
+        )
+        .unwrap();
+
+        let purged = pgrx::cdc::CdcRetention::new("pgrx_tests")
+            .retain("cdc_orders_changes", Duration::from_secs(60 * 60))
+            .purge();
+        assert_eq!(purged.unwrap(), 1);
+        let purged = pgrx::cdc::purge("public.cdc_orders_changes", Duration::from_secs(60 * 60));
+        assert_eq!(purged.unwrap(), 0);
+        assert_eq!(changes().len(), 1);
+    }
+
+    #[pg_test]
+    fn test_cdc_purge_unknown_table() {
+        let purged = pgrx::cdc::purge("cdc_orders_changes; DROP TABLE cdc_orders", Duration::ZERO);
+        assert!(matches!(purged, Err(pgrx::cdc::CdcError::UnknownShadowTable(_))));
+        assert_eq!(
+            Spi::get_one::<bool>("SELECT to_regclass('cdc_orders') IS NOT NULL"),
+            Ok(Some(true))
+        );
+    }
+}
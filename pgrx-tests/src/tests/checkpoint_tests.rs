@@ -0,0 +1,80 @@
+/*
+Portions Copyright 2019-2021 ZomboDB, LLC.
+Portions Copyright 2021-2022 Technology Concepts & Design, Inc. <support@tcdi.com>
+
+All rights reserved.
+
+Use of this source code is governed by the MIT license that can be found in the LICENSE file.
+*/
+
+#[cfg(any(test, feature = "pg_test"))]
+#[pgrx::pg_schema]
+mod tests {
+    #[allow(unused_imports)]
+    use crate as pgrx_tests;
+
+    use pgrx::checkpoint::*;
+    use pgrx::prelude::*;
+    use pgrx::rel::PgRelation;
+    use std::time::{Duration, Instant};
+
+    fn checkpoint_count() -> Result<Option<i64>, spi::Error> {
+        Spi::get_one("SELECT checkpoints_req FROM pg_stat_bgwriter")
+    }
+
+    #[pg_test]
+    fn test_request_checkpoint() -> Result<(), spi::Error> {
+        let before = checkpoint_count()?.unwrap();
+        request_checkpoint(
+            CheckpointFlags::IMMEDIATE | CheckpointFlags::FORCE | CheckpointFlags::WAIT,
+        );
+
+        // the checkpointer reports it to the statistics, which may take a moment to catch up
+        let deadline = Instant::now() + Duration::from_secs(10);
+        loop {
+            Spi::run("SELECT pg_stat_clear_snapshot()")?;
+            let after = checkpoint_count()?.unwrap();
+            if after > before {
+                return Ok(());
+            }
+            assert!(Instant::now() < deadline, "checkpoints_req is still {after}");
+            std::thread::sleep(Duration::from_millis(100));
+        }
+    }
+
+    #[pg_test]
+    fn test_flush_and_register_sync() -> Result<(), spi::Error> {
+        Spi::run("CREATE TABLE checkpoint_flush AS SELECT i FROM generate_series(1, 1000) i")?;
+        let relation = PgRelation::open_with_name_and_share_lock("checkpoint_flush").unwrap();
+        flush_relation_buffers(&relation);
+
+        // the pages were only zeroes on disk, as the table was extended, until they were written
+        let path = Spi::get_one::<String>("SELECT pg_relation_filepath('checkpoint_flush')")?;
+        let file = std::fs::read(path.unwrap()).unwrap();
+        let pages = file.chunks(pg_sys::BLCKSZ as usize).collect::<Vec<_>>();
+        assert!(!pages.is_empty());
+        assert!(pages.iter().all(|page| page.iter().any(|&byte| byte != 0)));
+
+        register_relation_sync(&relation, pg_sys::ForkNumber_MAIN_FORKNUM);
+        flush_database_buffers(unsafe { pg_sys::MyDatabaseId });
+
+        Spi::run("CREATE TEMP TABLE checkpoint_temp (i int)")?;
+        let temp = PgRelation::open_with_name_and_share_lock("checkpoint_temp").unwrap();
+        register_relation_sync(&temp, pg_sys::ForkNumber_MAIN_FORKNUM);
+        Ok(())
+    }
+
+    #[pg_test]
+    fn test_fsync_and_durable_rename() -> Result<(), spi::Error> {
+        fsync_file("PG_VERSION", false);
+        fsync_file("global", true);
+
+        let from = format!("pgrx_checkpoint_test_{}.tmp", std::process::id());
+        let to = format!("pgrx_checkpoint_test_{}", std::process::id());
+        std::fs::write(&from, b"contents").unwrap();
+        durable_rename(&from, &to);
+        assert_eq!(std::fs::read(&to).unwrap(), b"contents");
+        std::fs::remove_file(&to).unwrap();
+        Ok(())
+    }
+}
@@ -0,0 +1,98 @@
+/*
+Portions Copyright 2019-2021 ZomboDB, LLC.
+Portions Copyright 2021-2022 Technology Concepts & Design, Inc. <support@tcdi.com>
+
+All rights reserved.
+
+Use of this source code is governed by the MIT license that can be found in the LICENSE file.
+*/
+
+#[cfg(any(test, feature = "pg_test"))]
+#[pgrx::pg_schema]
+mod tests {
+    #[allow(unused_imports)]
+    use crate as pgrx_tests;
+
+    use pgrx::compat;
+    use pgrx::pg_sys::AsPgCStr;
+    use pgrx::prelude::*;
+
+    #[pg_test]
+    fn test_tuple_slot_round_trip() {
+        unsafe {
+            let tupdesc = compat::create_template_tuple_desc(2);
+            pg_sys::TupleDescInitEntry(tupdesc, 1, "a".as_pg_cstr(), pg_sys::INT4OID, -1, 0);
+            pg_sys::TupleDescInitEntry(tupdesc, 2, "b".as_pg_cstr(), pg_sys::INT8OID, -1, 0);
+            let tupdesc = pg_sys::BlessTupleDesc(tupdesc);
+
+            let mut values = [42i32.into_datum().unwrap(), pg_sys::Datum::from(0)];
+            let mut nulls = [false, true];
+            let tuple = pg_sys::heap_form_tuple(tupdesc, values.as_mut_ptr(), nulls.as_mut_ptr());
+
+            let slot = compat::make_heap_tuple_slot(tupdesc);
+            compat::store_heap_tuple(tuple, slot, false);
+            assert_eq!(
+                compat::slot_getattr(slot, 1).and_then(|d| i32::from_datum(d, false)),
+                Some(42)
+            );
+            assert_eq!(compat::slot_getattr(slot, 2), None);
+
+            let modified = compat::modify_tuple(
+                compat::fetch_heap_tuple(slot),
+                tupdesc,
+                &[(2, 7i64.into_datum())],
+            );
+            compat::clear_slot(slot);
+            compat::store_heap_tuple(modified, slot, true);
+            assert_eq!(
+                compat::slot_getattr(slot, 1).and_then(|d| i32::from_datum(d, false)),
+                Some(42)
+            );
+            assert_eq!(
+                compat::slot_getattr(slot, 2).and_then(|d| i64::from_datum(d, false)),
+                Some(7)
+            );
+            pg_sys::ExecDropSingleTupleTableSlot(slot);
+        }
+    }
+
+    #[pg_test]
+    fn test_table_open() {
+        Spi::run("CREATE TABLE compat_table (id int)").unwrap();
+        let relid =
+            Spi::get_one::<pg_sys::Oid>("SELECT 'compat_table'::regclass::oid").unwrap().unwrap();
+        unsafe {
+            let rel = compat::table_open(relid, pg_sys::AccessShareLock as _);
+            assert_eq!((*rel).rd_id, relid);
+            compat::table_close(rel, pg_sys::AccessShareLock as _);
+        }
+    }
+
+    #[pg_test]
+    fn test_lists() {
+        unsafe {
+            assert_eq!(compat::list_length(std::ptr::null()), 0);
+            assert_eq!(compat::list_nth_int(std::ptr::null(), 0), None);
+
+            let mut ints = std::ptr::null_mut();
+            let mut oids = std::ptr::null_mut();
+            for i in 0..10 {
+                ints = pg_sys::lappend_int(ints, i * 10);
+                oids = pg_sys::lappend_oid(oids, pg_sys::Oid::from_u32_unchecked(i as u32 + 100));
+            }
+            assert_eq!(compat::list_length(ints), 10);
+            assert_eq!(compat::list_nth_int(ints, 3), Some(30));
+            assert_eq!(compat::list_nth_int(ints, 10), None);
+            assert_eq!(compat::list_nth_oid(oids, 9), Some(pg_sys::Oid::from_u32_unchecked(109)));
+
+            let more = pg_sys::lappend_int(std::ptr::null_mut(), 100);
+            let ints = compat::list_concat(ints, more);
+            assert_eq!(compat::list_length(ints), 11);
+            assert_eq!(compat::list_nth_int(ints, 10), Some(100));
+
+            let name = "hello".as_pg_cstr();
+            let ptrs = pg_sys::lappend(std::ptr::null_mut(), name.cast());
+            assert_eq!(compat::list_nth_ptr(ptrs, 0), Some(name.cast()));
+        }
+    }
+}
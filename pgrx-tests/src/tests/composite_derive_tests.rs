@@ -0,0 +1,118 @@
+/*
+Portions Copyright 2019-2021 ZomboDB, LLC.
+Portions Copyright 2021-2022 Technology Concepts & Design, Inc. <support@tcdi.com>
+
+All rights reserved.
+
+Use of this source code is governed by the MIT license that can be found in the LICENSE file.
+*/
+
+#[cfg(any(test, feature = "pg_test"))]
+#[pgrx::pg_schema]
+mod tests {
+    #[allow(unused_imports)]
+    use crate as pgrx_tests;
+
+    use pgrx::prelude::*;
+
+    #[derive(Debug, PartialEq, PostgresComposite)]
+    struct Puppy {
+        name: String,
+        #[pgrx(rename = "scritches")]
+        scritch_count: i32,
+        owner: Option<String>,
+    }
+
+    #[derive(Debug, PartialEq, PostgresComposite)]
+    #[requires("Puppy")]
+    struct Kennel {
+        address: String,
+        resident: Option<Puppy>,
+    }
+
+    #[pg_extern]
+    fn make_puppy(name: &str, scritch_count: i32) -> Puppy {
+        Puppy { name: name.to_string(), scritch_count, owner: Some("Eric".to_string()) }
+    }
+
+    #[pg_extern]
+    fn pet_puppy(puppy: Puppy) -> Puppy {
+        Puppy { scritch_count: puppy.scritch_count + 1, ..puppy }
+    }
+
+    #[pg_extern]
+    fn kennel_resident(kennel: Kennel) -> Option<String> {
+        kennel.resident.map(|puppy| puppy.name)
+    }
+
+    #[pg_test]
+    fn test_create_type() {
+        let attributes = Spi::get_one::<String>(
+            "SELECT string_agg(attname || ' ' || format_type(atttypid, atttypmod), ', ' ORDER BY attnum) \
+               FROM pg_attribute WHERE attrelid = 'puppy'::regclass",
+        );
+        assert_eq!(attributes, Ok(Some("name text, scritches integer, owner text".to_string())));
+    }
+
+    #[pg_test]
+    fn test_round_trip() {
+        let puppy = Spi::get_one::<Puppy>("SELECT pet_puppy(make_puppy('Nami', 41))");
+        assert_eq!(
+            puppy,
+            Ok(Some(Puppy {
+                name: "Nami".to_string(),
+                scritch_count: 42,
+                owner: Some("Eric".to_string())
+            }))
+        );
+
+        let scritches = Spi::get_one::<i32>("SELECT (make_puppy('Brandy', 7)).scritches");
+        assert_eq!(scritches, Ok(Some(7)));
+    }
+
+    #[pg_test]
+    fn test_null_attribute() {
+        let puppy = Spi::get_one::<Puppy>("SELECT ROW('Nami', 1, NULL)::puppy");
+        assert_eq!(
+            puppy,
+            Ok(Some(Puppy { name: "Nami".to_string(), scritch_count: 1, owner: None }))
+        );
+    }
+
+    #[pg_test(error = "attribute `name` of composite type `Puppy` is missing or NULL")]
+    fn test_null_required_attribute() {
+        Spi::get_one::<Puppy>("SELECT pet_puppy(ROW(NULL, 1, NULL)::puppy)").unwrap();
+    }
+
+    #[pg_test]
+    fn test_nested() {
+        let resident = Spi::get_one::<String>(
+            "SELECT kennel_resident(ROW('1 Main St', make_puppy('Brandy', 3))::kennel)",
+        );
+        assert_eq!(resident, Ok(Some("Brandy".to_string())));
+    }
+
+    #[pg_test]
+    fn test_changed_attributes() {
+        Spi::run("ALTER TYPE puppy DROP ATTRIBUTE owner").unwrap();
+        Spi::run("ALTER TYPE puppy ADD ATTRIBUTE breed text").unwrap();
+
+        // the dropped attribute is left out, and the added one left NULL
+        let row = Spi::get_three::<String, i32, String>(
+            "SELECT (p).name, (p).scritches, (p).breed FROM (SELECT make_puppy('Nami', 1) p) x",
+        );
+        assert_eq!(row, Ok((Some("Nami".to_string()), Some(1), None)));
+
+        let puppy = Spi::get_one::<Puppy>("SELECT ROW('Nami', 2, 'beagle')::puppy");
+        assert_eq!(
+            puppy,
+            Ok(Some(Puppy { name: "Nami".to_string(), scritch_count: 2, owner: None }))
+        );
+    }
+
+    #[pg_test(error = "attribute `scritches` of composite type `Puppy` is missing or NULL")]
+    fn test_dropped_required_attribute() {
+        Spi::run("ALTER TYPE puppy DROP ATTRIBUTE scritches").unwrap();
+        Spi::get_one::<Puppy>("SELECT ROW('Nami', 'Eric')::puppy").unwrap();
+    }
+}
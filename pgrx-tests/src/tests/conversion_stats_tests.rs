@@ -0,0 +1,73 @@
+/*
+Portions Copyright 2019-2021 ZomboDB, LLC.
+Portions Copyright 2021-2022 Technology Concepts & Design, Inc. <support@tcdi.com>
+
+All rights reserved.
+
+Use of this source code is governed by the MIT license that can be found in the LICENSE file.
+*/
+
+pgrx::conversion_stats_functions!();
+
+#[cfg(any(test, feature = "pg_test"))]
+#[pgrx::pg_schema]
+mod tests {
+    #[allow(unused_imports)]
+    use crate as pgrx_tests;
+
+    use pgrx::datum::conversion_stats::{
+        conversion_stats, reset_conversion_stats, ConversionDirection, ConversionStats,
+    };
+    use pgrx::prelude::*;
+
+    #[pg_extern]
+    fn conversion_stats_widen(value: i32) -> i64 {
+        value as i64
+    }
+
+    fn stats_for(type_name: &str, direction: ConversionDirection) -> Option<ConversionStats> {
+        conversion_stats()
+            .into_iter()
+            .find(|stats| stats.type_name == type_name && stats.direction == direction)
+    }
+
+    #[pg_test]
+    fn test_conversion_stats_counts_pg_extern() {
+        reset_conversion_stats();
+        Spi::run("SELECT tests.conversion_stats_widen(x) FROM generate_series(1, 10) x").unwrap();
+
+        let args = stats_for("i32", ConversionDirection::FromDatum).unwrap();
+        let results = stats_for("i64", ConversionDirection::IntoDatum).unwrap();
+        assert_eq!(args.count, 10);
+        assert_eq!(results.count, 10);
+        assert!(args.mean_time() <= args.total_time);
+    }
+
+    #[pg_test]
+    fn test_conversion_stats_counts_spi() {
+        reset_conversion_stats();
+        let value = Spi::get_one::<String>("SELECT 'hello'::text").unwrap();
+        assert_eq!(value.as_deref(), Some("hello"));
+        let stats = stats_for("alloc::string::String", ConversionDirection::FromDatum).unwrap();
+        assert_eq!(stats.count, 1);
+    }
+
+    #[pg_test]
+    fn test_conversion_stats_reset() {
+        Spi::run("SELECT tests.conversion_stats_widen(1)").unwrap();
+        assert!(!conversion_stats().is_empty());
+        reset_conversion_stats();
+        assert!(conversion_stats().is_empty());
+    }
+
+    #[pg_test]
+    fn test_conversion_stats_sql() {
+        Spi::run("SELECT tests.reset_datum_conversion_stats()").unwrap();
+        Spi::run("SELECT tests.conversion_stats_widen(x) FROM generate_series(1, 5) x").unwrap();
+        let count = Spi::get_one::<i64>(
+            "SELECT count FROM tests.datum_conversion_stats() \
+              WHERE type_name = 'i32' AND direction = 'from_datum'",
+        );
+        assert_eq!(count, Ok(Some(5)));
+    }
+}
@@ -0,0 +1,64 @@
+/*
+Portions Copyright 2019-2021 ZomboDB, LLC.
+Portions Copyright 2021-2022 Technology Concepts & Design, Inc. <support@tcdi.com>
+
+All rights reserved.
+
+Use of this source code is governed by the MIT license that can be found in the LICENSE file.
+*/
+
+#[cfg(any(test, feature = "pg_test"))]
+#[pgrx::pg_schema]
+mod tests {
+    #[allow(unused_imports)]
+    use crate as pgrx_tests;
+
+    use pgrx::cost::{clamp_row_est, CostParameters, PathCost, RelationSize};
+    use pgrx::prelude::*;
+
+    #[pg_test]
+    fn test_cost_parameters_follow_gucs() {
+        Spi::run("SET LOCAL seq_page_cost = 2.5").unwrap();
+        Spi::run("SET LOCAL cpu_tuple_cost = 0.02").unwrap();
+        let params = CostParameters::current();
+        assert_eq!(params.seq_page_cost, 2.5);
+        assert_eq!(params.cpu_tuple_cost, 0.02);
+        assert_eq!(params.random_page_cost, 4.0);
+    }
+
+    #[pg_test]
+    fn test_seq_scan_cost() {
+        Spi::run("CREATE TABLE tests.costed (id int)").unwrap();
+        Spi::run("INSERT INTO tests.costed SELECT generate_series(1, 10000)").unwrap();
+        Spi::run("ANALYZE tests.costed").unwrap();
+        let relid =
+            Spi::get_one::<pg_sys::Oid>("SELECT 'tests.costed'::regclass::oid").unwrap().unwrap();
+
+        let size = RelationSize::estimate(relid);
+        assert_eq!(size.tuples, 10000.0);
+        assert!(size.pages > 0.0);
+
+        // matches what the planner comes up with for its own sequential scan
+        let params = CostParameters::for_relation(relid);
+        let cost = params.seq_scan(size, 0.0);
+        let plan = Spi::explain("SELECT * FROM tests.costed").unwrap();
+        let total = plan.0[0]["Plan"]["Total Cost"].as_f64().unwrap();
+        assert!((cost.total - total).abs() < 0.01, "{} != {}", cost.total, total);
+        assert_eq!(cost.rows, 10000.0);
+
+        // an index scan for a handful of rows beats reading the whole table
+        let index = RelationSize { pages: 30.0, tuples: 10000.0 };
+        let index_cost = params.index_scan(size, index, 0.001, 1.0, 0.0);
+        assert!(index_cost.total < cost.total);
+        assert_eq!(index_cost.rows, 10.0);
+    }
+
+    #[pg_test]
+    fn test_path_cost_adjustments() {
+        let cost = PathCost { startup: 1.0, total: 11.0, rows: 100.0 };
+        assert_eq!(cost.with_selectivity(0.1).rows, 10.0);
+        assert_eq!(cost.plus_per_row(0.1).total, 21.0);
+        assert_eq!(clamp_row_est(0.2), 1.0);
+        assert_eq!(clamp_row_est(2.6), 3.0);
+    }
+}
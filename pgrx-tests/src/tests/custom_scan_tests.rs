@@ -0,0 +1,145 @@
+/*
+Portions Copyright 2019-2021 ZomboDB, LLC.
+Portions Copyright 2021-2022 Technology Concepts & Design, Inc. <support@tcdi.com>
+
+All rights reserved.
+
+Use of this source code is governed by the MIT license that can be found in the LICENSE file.
+*/
+
+#[cfg(any(test, feature = "pg_test"))]
+#[pgrx::pg_schema]
+mod tests {
+    #[allow(unused_imports)]
+    use crate as pgrx_tests;
+
+    use pgrx::custom_scan::*;
+    use pgrx::explain::Explain;
+    use pgrx::fdw::{FdwColumn, FdwRow};
+    use pgrx::pathlist::PathEstimates;
+    use pgrx::prelude::*;
+    use std::cell::Cell;
+
+    thread_local! {
+        static REGISTERED: Cell<bool> = const { Cell::new(false) };
+    }
+
+    // scans any table named `custom_scan_numbers`, whatever it holds, as the numbers 1 to 5
+    struct Numbers;
+
+    impl CustomScanMethods for Numbers {
+        const NAME: &'static str = "PgrxNumbers";
+        type Exec = NumbersScan;
+
+        fn create_scan_state(private: &[String]) -> NumbersScan {
+            NumbersScan { next: 1, last: private[0].parse().unwrap(), rescans: 0 }
+        }
+    }
+
+    impl CustomPathMethods for Numbers {
+        fn create_paths(rel: &CustomScanRel) -> Vec<CustomPathInfo> {
+            if rel.name() != "custom_scan_numbers" {
+                return vec![];
+            }
+            // cheap enough to always be chosen
+            let estimates = PathEstimates { startup_cost: 0.0, total_cost: 0.0, rows: 5.0 };
+            vec![CustomPathInfo::new(estimates).with_private(vec!["5".into()])]
+        }
+    }
+
+    struct NumbersScan {
+        next: i32,
+        last: i32,
+        rescans: i64,
+    }
+
+    impl CustomExecMethods for NumbersScan {
+        fn begin_scan(&mut self, columns: &[FdwColumn]) {
+            assert_eq!(columns[0].name, "n");
+        }
+
+        fn exec_scan(&mut self, row: &mut FdwRow) -> bool {
+            if self.next > self.last {
+                return false;
+            }
+            row.set("n", Some(self.next));
+            self.next += 1;
+            true
+        }
+
+        fn rescan(&mut self) {
+            self.next = 1;
+            self.rescans += 1;
+        }
+
+        fn explain(&self, explain: &mut Explain) {
+            explain.property_integer("Last Number", None, self.last.into());
+            if explain.analyze() {
+                explain.property_integer("Rescans", None, self.rescans);
+            }
+        }
+    }
+
+    fn register() {
+        if !REGISTERED.with(|registered| registered.replace(true)) {
+            // SAFETY:  `REGISTERED` makes this the backend's only registration.  Tests aren't run
+            // from `_PG_init()`, but nothing is planned while this one registers
+            unsafe { register_custom_scan::<Numbers>() }
+        }
+    }
+
+    #[pg_test]
+    fn test_custom_scan() {
+        register();
+        Spi::run("CREATE TABLE custom_scan_numbers (n int)").unwrap();
+        assert_eq!(Spi::get_one::<i64>("SELECT sum(n) FROM custom_scan_numbers"), Ok(Some(15)));
+    }
+
+    #[pg_test]
+    fn test_custom_scan_quals() {
+        register();
+        Spi::run("CREATE TABLE custom_scan_numbers (n int)").unwrap();
+        assert_eq!(
+            Spi::get_one::<i64>("SELECT count(*) FROM custom_scan_numbers WHERE n > 2"),
+            Ok(Some(3))
+        );
+    }
+
+    #[pg_test]
+    fn test_custom_scan_other_tables() {
+        register();
+        Spi::run(
+            "CREATE TABLE custom_scan_others (n int); INSERT INTO custom_scan_others VALUES (1)",
+        )
+        .unwrap();
+        assert_eq!(Spi::get_one::<i64>("SELECT sum(n) FROM custom_scan_others"), Ok(Some(1)));
+    }
+
+    #[pg_test]
+    fn test_custom_scan_explain() {
+        register();
+        Spi::run("CREATE TABLE custom_scan_numbers (n int)").unwrap();
+        let explain = Spi::explain("SELECT * FROM custom_scan_numbers").unwrap();
+        let plan = &explain.0[0]["Plan"];
+        assert_eq!(plan["Node Type"], "Custom Scan");
+        assert_eq!(plan["Custom Plan Provider"], "PgrxNumbers");
+        assert_eq!(plan["Last Number"], 5);
+    }
+
+    #[pg_test]
+    fn test_custom_scan_rescan() {
+        register();
+        Spi::run("CREATE TABLE custom_scan_numbers (n int)").unwrap();
+        // the inner side of a nested loop is rescanned for every outer row
+        Spi::run(
+            "SET enable_hashjoin = off; SET enable_mergejoin = off; SET enable_material = off",
+        )
+        .unwrap();
+        assert_eq!(
+            Spi::get_one::<i64>(
+                "SELECT count(*) FROM generate_series(1, 3) g, custom_scan_numbers n WHERE n.n <= g"
+            ),
+            Ok(Some(6))
+        );
+    }
+}
@@ -0,0 +1,88 @@
+/*
+Portions Copyright 2019-2021 ZomboDB, LLC.
+Portions Copyright 2021-2022 Technology Concepts & Design, Inc. <support@tcdi.com>
+
+All rights reserved.
+
+Use of this source code is governed by the MIT license that can be found in the LICENSE file.
+*/
+
+#[cfg(any(test, feature = "pg_test"))]
+#[pgrx::pg_schema]
+mod tests {
+    #[allow(unused_imports)]
+    use crate as pgrx_tests;
+
+    use pgrx::data_dir::{extension_data_path, ExtensionDataDir};
+    use pgrx::prelude::*;
+    use std::io::{ErrorKind, Write};
+
+    #[pg_test]
+    fn test_open() {
+        let dir = ExtensionDataDir::open("pgrx_tests_open").unwrap();
+        assert!(dir.path().is_dir());
+        assert!(dir.path().ends_with("pgrx_data/pgrx_tests_open"));
+        assert_eq!(extension_data_path("pgrx_tests_open").unwrap(), dir.path());
+
+        // opening it again is fine
+        ExtensionDataDir::open("pgrx_tests_open").unwrap().remove_all().unwrap();
+        assert!(!dir.path().exists());
+    }
+
+    #[pg_test]
+    fn test_write_read() {
+        let dir = ExtensionDataDir::open("pgrx_tests_write").unwrap();
+        dir.write("model.bin", [1, 2, 3]).unwrap();
+        assert_eq!(dir.read("model.bin").unwrap(), [1, 2, 3]);
+
+        // replaced, not appended to
+        dir.write("model.bin", [4, 5]).unwrap();
+        assert_eq!(dir.read("model.bin").unwrap(), [4, 5]);
+
+        dir.write_with("words.txt", |out| writeln!(out, "nami\nbrandy")).unwrap();
+        assert_eq!(dir.list().unwrap(), ["model.bin", "words.txt"]);
+
+        dir.remove("model.bin").unwrap();
+        assert!(!dir.exists("model.bin").unwrap());
+        assert_eq!(dir.list().unwrap(), ["words.txt"]);
+        dir.remove_all().unwrap();
+    }
+
+    #[pg_test]
+    fn test_failed_write() {
+        let dir = ExtensionDataDir::open("pgrx_tests_failed").unwrap();
+        dir.write("model.bin", "old").unwrap();
+        let result = dir.write_with("model.bin", |out| {
+            out.write_all(b"half of the new")?;
+            Err(std::io::Error::new(ErrorKind::Other, "interrupted"))
+        });
+        assert_eq!(result.unwrap_err().to_string(), "interrupted");
+
+        // the old contents are kept, and the temporary file removed
+        assert_eq!(dir.read("model.bin").unwrap(), b"old");
+        assert_eq!(std::fs::read_dir(dir.path()).unwrap().count(), 1);
+        dir.remove_all().unwrap();
+    }
+
+    #[pg_test]
+    fn test_remove_temp_files() {
+        let dir = ExtensionDataDir::open("pgrx_tests_temp").unwrap();
+        std::fs::write(dir.path().join(".model.bin.1234.tmp"), "crashed").unwrap();
+        dir.write("model.bin", "ok").unwrap();
+        assert_eq!(dir.list().unwrap(), ["model.bin"]);
+
+        dir.remove_temp_files().unwrap();
+        assert_eq!(std::fs::read_dir(dir.path()).unwrap().count(), 1);
+        dir.remove_all().unwrap();
+    }
+
+    #[pg_test]
+    fn test_invalid_names() {
+        assert_eq!(ExtensionDataDir::open("../base").unwrap_err().kind(), ErrorKind::InvalidInput);
+        let dir = ExtensionDataDir::open("pgrx_tests_names").unwrap();
+        for name in ["", "../postgresql.conf", "a/b", ".hidden"] {
+            assert_eq!(dir.write(name, "x").unwrap_err().kind(), ErrorKind::InvalidInput);
+        }
+        dir.remove_all().unwrap();
+    }
+}
@@ -0,0 +1,103 @@
+/*
+Portions Copyright 2019-2021 ZomboDB, LLC.
+Portions Copyright 2021-2022 Technology Concepts & Design, Inc. <support@tcdi.com>
+
+All rights reserved.
+
+Use of this source code is governed by the MIT license that can be found in the LICENSE file.
+*/
+
+#[cfg(any(test, feature = "pg_test"))]
+#[pgrx::pg_schema]
+mod tests {
+    #[allow(unused_imports)]
+    use crate as pgrx_tests;
+
+    use pgrx::deferred::{validate_all_pending, DeferredValidation, ValidateAt};
+    use pgrx::prelude::*;
+    use std::cell::RefCell;
+
+    thread_local! {
+        static BATCHES: RefCell<Vec<Vec<i32>>> = RefCell::new(Vec::new());
+    }
+
+    fn positive(values: Vec<i32>) -> Result<(), String> {
+        BATCHES.with(|batches| batches.borrow_mut().push(values.clone()));
+        match values.into_iter().find(|value| *value <= 0) {
+            Some(value) => Err(format!("{} is not positive", value)),
+            None => Ok(()),
+        }
+    }
+
+    static POSITIVE_STATEMENT: DeferredValidation<i32> =
+        DeferredValidation::new("positive_statement", ValidateAt::StatementEnd, positive);
+    static POSITIVE_TRANSACTION: DeferredValidation<i32> =
+        DeferredValidation::new("positive_transaction", ValidateAt::TransactionEnd, positive);
+    static NONEMPTY_TRANSACTION: DeferredValidation<String> =
+        DeferredValidation::new("nonempty_transaction", ValidateAt::TransactionEnd, |values| {
+            match values.iter().any(|value| value.is_empty()) {
+                true => Err("empty string".into()),
+                false => Ok(()),
+            }
+        });
+
+    #[pg_extern]
+    fn defer_positive_statement(value: i32) -> i32 {
+        POSITIVE_STATEMENT.defer(value);
+        value
+    }
+
+    #[pg_test]
+    fn test_deferred_until_statement_end() {
+        BATCHES.with(|batches| batches.borrow_mut().clear());
+        Spi::run("SELECT tests.defer_positive_statement(x) FROM generate_series(1, 3) x").unwrap();
+
+        // the statement above is nested within the one running this test, which hasn't ended
+        assert_eq!(POSITIVE_STATEMENT.pending(), 3);
+        assert!(BATCHES.with(|batches| batches.borrow().is_empty()));
+
+        validate_all_pending();
+        assert_eq!(POSITIVE_STATEMENT.pending(), 0);
+        assert_eq!(BATCHES.with(|batches| batches.borrow().clone()), vec![vec![1, 2, 3]]);
+    }
+
+    #[pg_test(error = "deferred validation \"positive_statement\" failed: -1 is not positive")]
+    fn test_deferred_statement_end_error() {
+        Spi::run("SELECT tests.defer_positive_statement(x) FROM generate_series(-1, 1) x").unwrap();
+    }
+
+    #[pg_test]
+    fn test_deferred_transaction_end_survives_statements() {
+        POSITIVE_TRANSACTION.defer(1);
+        NONEMPTY_TRANSACTION.defer("value".to_string());
+        Spi::run("SELECT 1").unwrap();
+        assert_eq!(POSITIVE_TRANSACTION.pending(), 1);
+        assert_eq!(NONEMPTY_TRANSACTION.pending(), 1);
+        assert_eq!(pgrx::deferred::pending_count(), 2);
+        validate_all_pending();
+        assert_eq!(pgrx::deferred::pending_count(), 0);
+    }
+
+    #[pg_test(error = "deferred validation \"nonempty_transaction\" failed: empty string")]
+    fn test_deferred_transaction_end_error() {
+        NONEMPTY_TRANSACTION.defer("".to_string());
+        validate_all_pending();
+    }
+
+    #[pg_test]
+    fn test_deferred_forgotten_on_subxact_abort() {
+        POSITIVE_TRANSACTION.defer(1);
+        unsafe {
+            let memory_context = pg_sys::CurrentMemoryContext;
+            let resource_owner = pg_sys::CurrentResourceOwner;
+            pg_sys::BeginInternalSubTransaction(std::ptr::null());
+            POSITIVE_TRANSACTION.defer(-1);
+            assert_eq!(POSITIVE_TRANSACTION.pending(), 2);
+            pg_sys::RollbackAndReleaseCurrentSubTransaction();
+            pg_sys::MemoryContextSwitchTo(memory_context);
+            pg_sys::CurrentResourceOwner = resource_owner;
+        }
+        assert_eq!(POSITIVE_TRANSACTION.pending(), 1);
+        validate_all_pending();
+    }
+}
@@ -0,0 +1,95 @@
+/*
+Portions Copyright 2019-2021 ZomboDB, LLC.
+Portions Copyright 2021-2022 Technology Concepts & Design, Inc. <support@tcdi.com>
+
+All rights reserved.
+
+Use of this source code is governed by the MIT license that can be found in the LICENSE file.
+*/
+
+#[cfg(any(test, feature = "pg_test"))]
+#[pgrx::pg_schema]
+mod tests {
+    #[allow(unused_imports)]
+    use crate as pgrx_tests;
+
+    use pgrx::dest_receiver::RowReceiver;
+    use pgrx::prelude::*;
+
+    #[pg_test]
+    fn test_select_into() -> Result<(), spi::Error> {
+        let mut names = Vec::new();
+        let mut sum = 0;
+        let mut receiver = RowReceiver::new(|row| {
+            assert_eq!(row.columns(), 2);
+            sum += row.get::<i32>(1).unwrap().unwrap();
+            names.push(row.get_by_name::<String, _>("name").unwrap().unwrap());
+            true
+        });
+        let rows = Spi::connect(|client| {
+            client.select_into(
+                "SELECT i, 'row ' || i AS name FROM generate_series(1, $1) i",
+                Some(vec![(PgBuiltInOids::INT4OID.oid(), 10.into_datum())]),
+                &mut receiver,
+            )
+        })?;
+        assert_eq!(rows, 10);
+        assert!(!receiver.stopped());
+        drop(receiver);
+
+        assert_eq!(sum, 55);
+        assert_eq!(names.len(), 10);
+        assert_eq!(names[9], "row 10");
+        Ok(())
+    }
+
+    #[pg_test]
+    fn test_select_into_stops_early() -> Result<(), spi::Error> {
+        let mut seen = Vec::new();
+        let mut receiver = RowReceiver::new(|row| {
+            seen.push(row.get::<i64>(1).unwrap().unwrap());
+            seen.len() < 3
+        });
+        let rows = Spi::connect(|client| {
+            client.select_into(
+                "SELECT i FROM generate_series(1, 1000::bigint) i",
+                None,
+                &mut receiver,
+            )
+        })?;
+        assert_eq!(rows, 3);
+        assert!(receiver.stopped());
+        drop(receiver);
+
+        assert_eq!(seen, vec![1, 2, 3]);
+        Ok(())
+    }
+
+    #[pg_test]
+    fn test_received_row_errors() -> Result<(), spi::Error> {
+        let mut receiver = RowReceiver::new(|row| {
+            assert_eq!(row.column_name(1), Ok("n"));
+            assert_eq!(row.column_type_oid(1), Ok(pg_sys::TEXTOID));
+            assert_eq!(row.get::<String>(1), Ok(None));
+            assert!(matches!(row.get::<i32>(2), Err(spi::Error::SpiError(_))));
+            assert!(matches!(row.get_by_name::<i32, _>("missing"), Err(spi::Error::SpiError(_))));
+            assert!(matches!(row.get::<i32>(1), Ok(None)));
+            true
+        });
+        let rows = Spi::connect(|client| {
+            client.select_into("SELECT NULL::text AS n", None, &mut receiver)
+        })?;
+        assert_eq!(rows, 1);
+        Ok(())
+    }
+
+    #[pg_test]
+    fn test_datum_type_mismatch() -> Result<(), spi::Error> {
+        let mut receiver = RowReceiver::new(|row| {
+            assert!(matches!(row.get::<i32>(1), Err(spi::Error::DatumError(_))));
+            true
+        });
+        Spi::connect(|client| client.select_into("SELECT 'text'::text", None, &mut receiver))?;
+        Ok(())
+    }
+}
@@ -0,0 +1,87 @@
+/*
+Portions Copyright 2019-2021 ZomboDB, LLC.
+Portions Copyright 2021-2022 Technology Concepts & Design, Inc. <support@tcdi.com>
+
+All rights reserved.
+
+Use of this source code is governed by the MIT license that can be found in the LICENSE file.
+*/
+
+#[cfg(any(test, feature = "pg_test"))]
+#[pgrx::pg_schema]
+mod tests {
+    #[allow(unused_imports)]
+    use crate as pgrx_tests;
+
+    use pgrx::dsm::{DsaArea, DsaPointer, DsmSegment};
+    use pgrx::prelude::*;
+
+    #[derive(Copy, Clone, Debug, PartialEq)]
+    struct Point {
+        x: i32,
+        y: i32,
+    }
+
+    unsafe impl pgrx::PGRXSharedMemory for Point {}
+
+    #[pg_test]
+    fn test_dsm_segment() {
+        let mut segment = DsmSegment::create(1024);
+        assert!(segment.len() >= 1024);
+        unsafe { segment.as_mut_slice()[..5].copy_from_slice(b"hello") };
+
+        // pinned, the segment outlives our mapping of it
+        segment.pin();
+        let handle = segment.handle();
+        drop(segment);
+
+        let segment = DsmSegment::attach(handle).expect("pinned segment should still exist");
+        assert_eq!(segment.handle(), handle);
+        assert_eq!(unsafe { &segment.as_slice()[..5] }, b"hello");
+        DsmSegment::unpin(handle);
+    }
+
+    #[pg_test]
+    fn test_dsa_area() {
+        let area = DsaArea::create();
+        let point = area.allocate(Point { x: 1, y: 2 });
+        let values = area.allocate_slice(&[1i64, 2, 3]);
+        let empty = area.allocate_slice::<i64>(&[]);
+        assert_eq!(unsafe { *area.get(point) }, Point { x: 1, y: 2 });
+        assert_eq!(unsafe { area.get_slice(values) }, &[1, 2, 3]);
+        assert!(empty.is_empty());
+
+        unsafe { area.get_mut(point).y = 3 };
+        unsafe { area.get_slice_mut(values)[0] = 4 };
+
+        area.pin();
+        let handle = area.handle();
+        drop(area);
+
+        let area = DsaArea::attach(handle);
+        let point = unsafe { DsaPointer::<Point>::from_raw(point.as_raw()) };
+        assert_eq!(unsafe { *area.get(point) }, Point { x: 1, y: 3 });
+        assert_eq!(unsafe { area.get_slice(values) }, &[4, 2, 3]);
+        unsafe {
+            area.free(point);
+            area.free(values);
+            area.free(empty);
+        }
+        area.unpin();
+    }
+
+    #[pg_test]
+    fn test_dsa_pointer_in_shared_struct() {
+        #[derive(Copy, Clone)]
+        struct Shared {
+            values: DsaPointer<[i32]>,
+        }
+        unsafe impl pgrx::PGRXSharedMemory for Shared {}
+
+        let area = DsaArea::create();
+        let shared = area.allocate(Shared { values: area.allocate_slice(&[7, 8, 9]) });
+        let values = unsafe { area.get(shared) }.values;
+        assert_eq!(values.len(), 3);
+        assert_eq!(unsafe { area.get_slice(values) }.iter().sum::<i32>(), 24);
+    }
+}
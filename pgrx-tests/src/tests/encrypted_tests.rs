@@ -0,0 +1,191 @@
+/*
+Portions Copyright 2019-2021 ZomboDB, LLC.
+Portions Copyright 2021-2022 Technology Concepts & Design, Inc. <support@tcdi.com>
+
+All rights reserved.
+
+Use of this source code is governed by the MIT license that can be found in the LICENSE file.
+*/
+
+#[cfg(any(test, feature = "pg_test"))]
+#[pgrx::pg_schema]
+mod tests {
+    #[allow(unused_imports)]
+    use crate as pgrx_tests;
+
+    use pgrx::encrypted::*;
+    use pgrx::prelude::*;
+    use std::cell::Cell;
+    use std::collections::hash_map::DefaultHasher;
+    use std::hash::Hasher;
+
+    thread_local! {
+        static CURRENT_KEY: Cell<&'static str> = const { Cell::new("k1") };
+        static AUDITED: Cell<usize> = const { Cell::new(0) };
+        static AUDIT_FAILURES: Cell<usize> = const { Cell::new(0) };
+    }
+
+    fn hash(parts: &[&[u8]]) -> u64 {
+        let mut hasher = DefaultHasher::new();
+        for part in parts {
+            hasher.write(part);
+        }
+        hasher.finish()
+    }
+
+    // a stand-in for a real AEAD:  xors with a keystream, and appends a checksum
+    struct ToyCipher;
+
+    impl ToyCipher {
+        fn xor(key: &[u8], nonce: &[u8], bytes: &[u8]) -> Vec<u8> {
+            let counter = Cell::new(0u64);
+            bytes
+                .chunks(8)
+                .flat_map(|chunk| {
+                    let n = counter.get();
+                    counter.set(n + 1);
+                    let stream = hash(&[key, nonce, &n.to_le_bytes()]).to_le_bytes();
+                    chunk.iter().zip(stream).map(|(b, s)| b ^ s).collect::<Vec<_>>()
+                })
+                .collect()
+        }
+    }
+
+    impl Cipher for ToyCipher {
+        fn nonce_len(&self) -> usize {
+            12
+        }
+
+        fn encrypt(&self, key: &[u8], nonce: &[u8], aad: &[u8], plaintext: &[u8]) -> Vec<u8> {
+            let mut ciphertext = Self::xor(key, nonce, plaintext);
+            let tag = hash(&[key, nonce, aad, &ciphertext]);
+            ciphertext.extend_from_slice(&tag.to_le_bytes());
+            ciphertext
+        }
+
+        fn decrypt(
+            &self,
+            key: &[u8],
+            nonce: &[u8],
+            aad: &[u8],
+            ciphertext: &[u8],
+        ) -> Option<Vec<u8>> {
+            let (ciphertext, tag) = ciphertext.split_at(ciphertext.len().checked_sub(8)?);
+            if hash(&[key, nonce, aad, ciphertext]).to_le_bytes() != tag {
+                return None;
+            }
+            Some(Self::xor(key, nonce, ciphertext))
+        }
+    }
+
+    struct TestKeys;
+
+    impl KeyProvider for TestKeys {
+        fn current_key_id(&self, _scope: &KeyScope) -> String {
+            CURRENT_KEY.get().to_string()
+        }
+
+        fn key(&self, scope: &KeyScope, key_id: &str) -> Option<Vec<u8>> {
+            match key_id {
+                "k1" | "k2" => Some(format!("{}/{:?}", key_id, scope).into_bytes()),
+                _ => None,
+            }
+        }
+    }
+
+    fn audit(event: &AuditEvent) {
+        AUDITED.set(AUDITED.get() + 1);
+        if !event.succeeded {
+            AUDIT_FAILURES.set(AUDIT_FAILURES.get() + 1);
+        }
+    }
+
+    fn setup() {
+        CURRENT_KEY.set("k1");
+        AUDITED.set(0);
+        AUDIT_FAILURES.set(0);
+        set_encryption(ToyCipher, TestKeys);
+        set_audit_hook(Some(audit));
+    }
+
+    #[pg_extern]
+    fn encrypt_secret(secret: String) -> Encrypted<String> {
+        Encrypted::in_scope(secret, KeyScope::column("accounts", "secret"))
+    }
+
+    #[pg_extern]
+    fn decrypt_secret(secret: Encrypted<String>) -> String {
+        secret.into_inner_in(&KeyScope::column("accounts", "secret"))
+    }
+
+    #[pg_extern]
+    fn encrypt_note(note: String) -> Encrypted<String> {
+        Encrypted::in_scope(note, KeyScope::column("accounts", "note"))
+    }
+
+    #[pg_test]
+    fn test_encrypted_round_trip() {
+        setup();
+        assert_eq!(
+            Spi::get_one::<String>("SELECT decrypt_secret(encrypt_secret('hunter2'))"),
+            Ok(Some("hunter2".into()))
+        );
+        assert_eq!((AUDITED.get(), AUDIT_FAILURES.get()), (2, 0));
+    }
+
+    #[pg_test]
+    fn test_encrypted_hides_value() {
+        setup();
+        let stored = Spi::get_one::<Vec<u8>>("SELECT encrypt_secret('hunter2')").unwrap().unwrap();
+        assert!(!stored.windows(7).any(|window| window == b"hunter2"));
+        // every value gets a new nonce
+        assert_eq!(
+            Spi::get_one::<bool>("SELECT encrypt_secret('hunter2') = encrypt_secret('hunter2')"),
+            Ok(Some(false))
+        );
+    }
+
+    #[pg_test]
+    fn test_encrypted_key_rotation() {
+        setup();
+        Spi::run("CREATE TABLE encrypted_secrets (secret bytea)").unwrap();
+        Spi::run("INSERT INTO encrypted_secrets VALUES (encrypt_secret('old'))").unwrap();
+        CURRENT_KEY.set("k2");
+        Spi::run("INSERT INTO encrypted_secrets VALUES (encrypt_secret('new'))").unwrap();
+        assert_eq!(
+            Spi::get_one::<String>(
+                "SELECT string_agg(decrypt_secret(secret), ',' ORDER BY 1) FROM encrypted_secrets"
+            ),
+            Ok(Some("new,old".into()))
+        );
+    }
+
+    #[pg_test(error = "could not decrypt an encrypted value")]
+    fn test_encrypted_tampered() {
+        setup();
+        Spi::run(
+            "SELECT decrypt_secret(set_byte(s, length(s) - 1, get_byte(s, length(s) - 1) # 1)) \
+             FROM (SELECT encrypt_secret('hunter2') s) t",
+        )
+        .unwrap();
+    }
+
+    #[pg_test(error = "encryption key \"k3\" does not exist for accounts.secret")]
+    fn test_encrypted_unknown_key() {
+        setup();
+        CURRENT_KEY.set("k3");
+        Spi::run("SELECT encrypt_secret('hunter2')").unwrap();
+    }
+
+    #[pg_test(error = "encrypted value belongs to accounts.note, not accounts.secret")]
+    fn test_encrypted_other_scope() {
+        setup();
+        Spi::run("SELECT decrypt_secret(encrypt_note('hunter2'))").unwrap();
+    }
+
+    #[pg_test(error = "invalid encrypted value")]
+    fn test_encrypted_invalid() {
+        setup();
+        Spi::run("SELECT decrypt_secret('\\x0203'::bytea)").unwrap();
+    }
+}
@@ -0,0 +1,111 @@
+/*
+Portions Copyright 2019-2021 ZomboDB, LLC.
+Portions Copyright 2021-2022 Technology Concepts & Design, Inc. <support@tcdi.com>
+
+All rights reserved.
+
+Use of this source code is governed by the MIT license that can be found in the LICENSE file.
+*/
+
+#[cfg(any(test, feature = "pg_test"))]
+#[pgrx::pg_schema]
+mod tests {
+    #[allow(unused_imports)]
+    use crate as pgrx_tests;
+
+    use pgrx::event_trigger::{EventTriggerData, EventTriggerEvent};
+    use pgrx::prelude::*;
+    use std::cell::RefCell;
+
+    thread_local! {
+        static SEEN: RefCell<Vec<String>> = RefCell::new(Vec::new());
+    }
+
+    fn seen() -> Vec<String> {
+        SEEN.with(|seen| seen.take())
+    }
+
+    // only fires for commands the other tests don't run
+    #[pg_event_trigger(
+        event = "ddl_command_end",
+        name = "pgrx_tests_ts_created",
+        tags = ["CREATE TEXT SEARCH CONFIGURATION"]
+    )]
+    fn ts_config_created(event: &EventTriggerData) {
+        assert_eq!(event.event(), EventTriggerEvent::DdlCommandEnd);
+        let commands = event.ddl_commands().unwrap();
+        SEEN.with(|seen| {
+            seen.borrow_mut().extend(
+                commands
+                    .into_iter()
+                    .map(|command| format!("{} {}", command.command_tag, command.object_identity)),
+            )
+        });
+    }
+
+    #[pg_event_trigger]
+    fn record_event(event: &EventTriggerData) {
+        let seen = match event.event() {
+            EventTriggerEvent::SqlDrop => event
+                .dropped_objects()
+                .unwrap()
+                .into_iter()
+                .filter(|object| object.original)
+                .map(|object| format!("dropped {} {}", object.object_type, object.object_identity))
+                .collect(),
+            EventTriggerEvent::TableRewrite => {
+                let (oid, _reason) = event.table_rewrite().unwrap();
+                let name = unsafe { pg_sys::get_rel_name(oid) };
+                let name = unsafe { std::ffi::CStr::from_ptr(name) }.to_str().unwrap();
+                vec![format!("rewrite {}", name)]
+            }
+            other => vec![format!("{} {}", other.as_str(), event.tag())],
+        };
+        SEEN.with(|cell| cell.borrow_mut().extend(seen));
+    }
+
+    #[pg_test]
+    fn test_ddl_command_end() {
+        seen();
+        Spi::run("CREATE TEXT SEARCH CONFIGURATION public.pgrx_ts (COPY = simple)").unwrap();
+        assert_eq!(seen(), vec!["CREATE TEXT SEARCH CONFIGURATION public.pgrx_ts".to_string()]);
+    }
+
+    #[pg_test]
+    fn test_ddl_command_start() {
+        Spi::run(
+            "CREATE EVENT TRIGGER pgrx_tests_start ON ddl_command_start \
+             WHEN TAG IN ('CREATE SCHEMA') EXECUTE FUNCTION record_event()",
+        )
+        .unwrap();
+        seen();
+        Spi::run("CREATE SCHEMA pgrx_event_schema").unwrap();
+        assert_eq!(seen(), vec!["ddl_command_start CREATE SCHEMA".to_string()]);
+    }
+
+    #[pg_test]
+    fn test_sql_drop() {
+        Spi::run("CREATE TABLE public.pgrx_dropped (id int)").unwrap();
+        Spi::run(
+            "CREATE EVENT TRIGGER pgrx_tests_drop ON sql_drop \
+             WHEN TAG IN ('DROP TABLE') EXECUTE FUNCTION record_event()",
+        )
+        .unwrap();
+        seen();
+        Spi::run("DROP TABLE public.pgrx_dropped").unwrap();
+        assert_eq!(seen(), vec!["dropped table public.pgrx_dropped".to_string()]);
+    }
+
+    #[pg_test]
+    fn test_table_rewrite() {
+        Spi::run("CREATE TABLE public.pgrx_rewritten (id int)").unwrap();
+        Spi::run(
+            "CREATE EVENT TRIGGER pgrx_tests_rewrite ON table_rewrite \
+             EXECUTE FUNCTION record_event()",
+        )
+        .unwrap();
+        seen();
+        Spi::run("ALTER TABLE public.pgrx_rewritten ALTER COLUMN id TYPE bigint").unwrap();
+        assert_eq!(seen(), vec!["rewrite pgrx_rewritten".to_string()]);
+    }
+}
@@ -0,0 +1,80 @@
+/*
+Portions Copyright 2019-2021 ZomboDB, LLC.
+Portions Copyright 2021-2022 Technology Concepts & Design, Inc. <support@tcdi.com>
+
+All rights reserved.
+
+Use of this source code is governed by the MIT license that can be found in the LICENSE file.
+*/
+
+#[cfg(any(test, feature = "pg_test"))]
+#[pgrx::pg_schema]
+mod tests {
+    #[allow(unused_imports)]
+    use crate as pgrx_tests;
+
+    use pgrx::explain::{register_explain_annotator, Explain, ExplainFormat};
+    use pgrx::prelude::*;
+    use std::cell::Cell;
+
+    thread_local! {
+        static REGISTERED: Cell<bool> = const { Cell::new(false) };
+    }
+
+    fn annotate(explain: &mut Explain, query: &pg_sys::Query) {
+        let format = match explain.format() {
+            ExplainFormat::Text => "text",
+            ExplainFormat::Xml => "xml",
+            ExplainFormat::Json => "json",
+            ExplainFormat::Yaml => "yaml",
+        };
+        explain.property_text("Seen Format", format);
+        explain.property_bool("Select", query.commandType == pg_sys::CmdType_CMD_SELECT);
+        explain.property_bool("Analyzed", explain.analyze());
+        explain.group("Details", |explain| {
+            explain.property_float("Ratio", Some("x"), 0.5, 2);
+        });
+    }
+
+    fn register() {
+        if !REGISTERED.with(|registered| registered.replace(true)) {
+            register_explain_annotator("pgrx_tests", annotate);
+        }
+    }
+
+    fn explain_text(query: &str) -> String {
+        Spi::connect(|client| {
+            client
+                .select(query, None, None)?
+                .map(|row| row.get::<String>(1).map(Option::unwrap_or_default))
+                .collect::<Result<Vec<_>, _>>()
+        })
+        .unwrap()
+        .join("\n")
+    }
+
+    #[pg_test]
+    fn test_explain_annotator_json() {
+        register();
+        Spi::run("CREATE TABLE explain_json (id int)").unwrap();
+        let explain = Spi::explain("SELECT * FROM explain_json").unwrap();
+        let output = &explain.0.as_array().unwrap();
+        assert!(output[0]["Plan"].is_object());
+        let ours = &output[1]["pgrx_tests"];
+        assert_eq!(ours["Seen Format"], "json");
+        assert_eq!(ours["Select"], true);
+        assert_eq!(ours["Analyzed"], false);
+        assert_eq!(ours["Details"]["Ratio"], 0.5);
+    }
+
+    #[pg_test]
+    fn test_explain_annotator_text() {
+        register();
+        Spi::run("CREATE TABLE explain_text (id int)").unwrap();
+        let output = explain_text("EXPLAIN (ANALYZE) SELECT * FROM explain_text");
+        assert!(output.contains("Seq Scan on explain_text"), "{output}");
+        assert!(output.contains("Seen Format: text"), "{output}");
+        assert!(output.contains("Analyzed: true"), "{output}");
+        assert!(output.contains("Ratio: 0.50 x"), "{output}");
+    }
+}
@@ -0,0 +1,96 @@
+/*
+Portions Copyright 2019-2021 ZomboDB, LLC.
+Portions Copyright 2021-2022 Technology Concepts & Design, Inc. <support@tcdi.com>
+
+All rights reserved.
+
+Use of this source code is governed by the MIT license that can be found in the LICENSE file.
+*/
+
+#[cfg(any(test, feature = "pg_test"))]
+#[pgrx::pg_schema]
+mod tests {
+    #[allow(unused_imports)]
+    use crate as pgrx_tests;
+
+    use pgrx::extended_stats::{read_extended_statistics, ExtendedStatistics, StatisticsKind};
+    use pgrx::prelude::*;
+
+    fn create_table() {
+        Spi::run("CREATE TABLE tests.correlated (a int, b int)").unwrap();
+        Spi::run(
+            "INSERT INTO tests.correlated SELECT i % 10, i % 10 FROM generate_series(1, 1000) i",
+        )
+        .unwrap();
+    }
+
+    #[pg_test]
+    fn test_extended_statistics_sql() {
+        let stats = ExtendedStatistics::new("tests.ab", "tests.correlated")
+            .columns(["a", "B"])
+            .kind(StatisticsKind::NDistinct)
+            .kind(StatisticsKind::Dependencies)
+            .kind(StatisticsKind::NDistinct)
+            .if_not_exists();
+        assert_eq!(
+            stats.to_sql(),
+            r#"CREATE STATISTICS IF NOT EXISTS tests.ab (ndistinct, dependencies) ON a, "B" FROM tests.correlated"#
+        );
+    }
+
+    #[pg_test]
+    fn test_create_and_read_extended_statistics() {
+        create_table();
+        let stxoid = ExtendedStatistics::new("tests.ab", "tests.correlated")
+            .column("a")
+            .column("b")
+            .kind(StatisticsKind::NDistinct)
+            .kind(StatisticsKind::Dependencies)
+            .create()
+            .unwrap();
+        assert_eq!(
+            Spi::get_one_with_args::<String>(
+                "SELECT stxname::text FROM pg_statistic_ext WHERE oid = $1",
+                vec![(PgBuiltInOids::OIDOID.oid(), stxoid.into_datum())]
+            ),
+            Ok(Some("ab".into()))
+        );
+
+        Spi::run("ANALYZE tests.correlated").unwrap();
+        let contents = read_extended_statistics(stxoid).unwrap();
+
+        let ndistinct = contents.ndistinct.unwrap();
+        assert_eq!(ndistinct.len(), 1);
+        assert_eq!(ndistinct[0].attnums, vec![1, 2]);
+        assert_eq!(ndistinct[0].ndistinct, 10.0);
+
+        let dependencies = contents.dependencies.unwrap();
+        assert_eq!(dependencies.len(), 2);
+        assert!(dependencies.iter().all(|dependency| dependency.degree == 1.0));
+        assert!(dependencies
+            .iter()
+            .any(|dependency| dependency.determinants == vec![1] && dependency.dependent == 2));
+
+        assert_eq!(contents.mcv, None);
+    }
+
+    #[cfg(not(feature = "pg11"))]
+    #[pg_test]
+    fn test_read_mcv_statistics() {
+        create_table();
+        let stxoid = ExtendedStatistics::new("ab_mcv", "tests.correlated")
+            .columns(["a", "b"])
+            .kind(StatisticsKind::Mcv)
+            .create()
+            .unwrap();
+        assert_eq!(read_extended_statistics(stxoid).unwrap(), Default::default());
+
+        Spi::run("ANALYZE tests.correlated").unwrap();
+        let mcv = read_extended_statistics(stxoid).unwrap().mcv.unwrap();
+        assert_eq!(mcv.len(), 10);
+        for item in mcv {
+            assert_eq!(item.values[0], item.values[1]);
+            assert_eq!(item.frequency, 0.1);
+        }
+    }
+}
@@ -0,0 +1,150 @@
+/*
+Portions Copyright 2019-2021 ZomboDB, LLC.
+Portions Copyright 2021-2022 Technology Concepts & Design, Inc. <support@tcdi.com>
+
+All rights reserved.
+
+Use of this source code is governed by the MIT license that can be found in the LICENSE file.
+*/
+use pgrx::fdw::{FdwColumn, FdwOptions, FdwOptionsCatalog, FdwRow, FdwRowId, ForeignDataWrapper};
+use pgrx::prelude::*;
+use std::cell::RefCell;
+use std::collections::BTreeMap;
+
+thread_local! {
+    static STORE: RefCell<BTreeMap<i64, String>> = RefCell::new(BTreeMap::new());
+}
+
+/// A key-value store in the backend's memory
+struct KvFdw {
+    rows: Vec<(i64, String)>,
+    next: usize,
+}
+
+#[pg_fdw(name = "pgrx_kv_fdw")]
+impl ForeignDataWrapper for KvFdw {
+    const INSERT: bool = true;
+    const UPDATE: bool = true;
+    const DELETE: bool = true;
+
+    fn begin_scan(_options: &FdwOptions, _columns: &[FdwColumn]) -> Self {
+        let rows = STORE.with(|store| store.borrow().clone().into_iter().collect());
+        KvFdw { rows, next: 0 }
+    }
+
+    fn iterate_scan(&mut self, row: &mut FdwRow) -> bool {
+        match self.rows.get(self.next) {
+            Some((key, value)) => {
+                row.set("key", Some(*key));
+                row.set("value", Some(value.clone()));
+                self.next += 1;
+                true
+            }
+            None => false,
+        }
+    }
+
+    fn re_scan(&mut self) {
+        self.next = 0;
+    }
+
+    fn insert(&mut self, row: &FdwRow) {
+        let key = row.get::<i64>("key").expect("key can't be null");
+        let value = row.get::<String>("value").unwrap_or_default();
+        STORE.with(|store| store.borrow_mut().insert(key, value));
+    }
+
+    fn update(&mut self, rowid: &FdwRowId, row: &FdwRow) {
+        let key = rowid.value::<i64>().expect("key can't be null");
+        let value = row.get::<String>("value").unwrap_or_default();
+        STORE.with(|store| store.borrow_mut().insert(key, value));
+    }
+
+    fn delete(&mut self, rowid: &FdwRowId) {
+        let key = rowid.value::<i64>().expect("key can't be null");
+        STORE.with(|store| store.borrow_mut().remove(&key));
+    }
+
+    fn validate_options(options: &FdwOptions, catalog: FdwOptionsCatalog) -> Result<(), String> {
+        match options
+            .keys()
+            .find(|name| catalog != FdwOptionsCatalog::Table || name.as_str() != "rowid_column")
+        {
+            Some(name) => Err(format!("invalid option \"{}\"", name)),
+            None => Ok(()),
+        }
+    }
+}
+
+#[cfg(any(test, feature = "pg_test"))]
+#[pgrx::pg_schema]
+mod tests {
+    #[allow(unused_imports)]
+    use crate as pgrx_tests;
+
+    use super::STORE;
+    use pgrx::prelude::*;
+
+    fn create_kv_table() {
+        STORE.with(|store| store.borrow_mut().clear());
+        Spi::run(
+            "CREATE SERVER kv_server FOREIGN DATA WRAPPER pgrx_kv_fdw; \
+             CREATE FOREIGN TABLE kv (key bigint, value text) \
+                SERVER kv_server OPTIONS (rowid_column 'key');",
+        )
+        .unwrap();
+    }
+
+    #[pg_test]
+    fn test_fdw_scan() {
+        create_kv_table();
+        STORE.with(|store| {
+            let mut store = store.borrow_mut();
+            store.insert(1, "one".into());
+            store.insert(2, "two".into());
+            store.insert(3, "three".into());
+        });
+        assert_eq!(Spi::get_one::<i64>("SELECT count(*) FROM kv"), Ok(Some(3)));
+        assert_eq!(
+            Spi::get_one::<String>("SELECT value FROM kv WHERE key = 2"),
+            Ok(Some("two".into()))
+        );
+        assert_eq!(
+            Spi::get_one::<String>(
+                "SELECT string_agg(value, ',' ORDER BY key) FROM kv WHERE key > 1"
+            ),
+            Ok(Some("two,three".into()))
+        );
+    }
+
+    #[pg_test]
+    fn test_fdw_modify() {
+        create_kv_table();
+        Spi::run("INSERT INTO kv VALUES (1, 'one'), (2, 'two'), (3, 'three')").unwrap();
+        Spi::run("UPDATE kv SET value = 'TWO' WHERE key = 2").unwrap();
+        Spi::run("DELETE FROM kv WHERE key = 3").unwrap();
+        let store = STORE.with(|store| store.borrow().clone());
+        assert_eq!(
+            store.into_iter().collect::<Vec<_>>(),
+            vec![(1, "one".to_string()), (2, "TWO".to_string())]
+        );
+    }
+
+    #[pg_test(error = "invalid option \"color\"")]
+    fn test_fdw_invalid_option() {
+        Spi::run("CREATE SERVER kv_colored FOREIGN DATA WRAPPER pgrx_kv_fdw OPTIONS (color 'red')")
+            .unwrap();
+    }
+
+    #[pg_test(
+        error = "foreign table \"kv_no_rowid\" needs a rowid_column option to be updated or deleted from"
+    )]
+    fn test_fdw_update_needs_rowid_column() {
+        Spi::run(
+            "CREATE SERVER kv_server FOREIGN DATA WRAPPER pgrx_kv_fdw; \
+             CREATE FOREIGN TABLE kv_no_rowid (key bigint, value text) SERVER kv_server; \
+             DELETE FROM kv_no_rowid;",
+        )
+        .unwrap();
+    }
+}
@@ -0,0 +1,51 @@
+/*
+Portions Copyright 2019-2021 ZomboDB, LLC.
+Portions Copyright 2021-2022 Technology Concepts & Design, Inc. <support@tcdi.com>
+
+All rights reserved.
+
+Use of this source code is governed by the MIT license that can be found in the LICENSE file.
+*/
+
+pgrx::feature_flag!(pub TEST_FLAG, "test.feature_flag", "a feature flag for testing", false);
+pgrx::feature_flags_view!();
+
+#[cfg(any(test, feature = "pg_test"))]
+#[pgrx::pg_schema]
+mod tests {
+    #[allow(unused_imports)]
+    use crate as pgrx_tests;
+
+    use super::TEST_FLAG;
+    use pgrx::feature_flags::registered_feature_flags;
+    use pgrx::prelude::*;
+
+    #[pg_test]
+    fn test_feature_flag() {
+        TEST_FLAG.register();
+        assert_eq!(
+            registered_feature_flags().map(|flag| flag.name()).last(),
+            Some("test.feature_flag")
+        );
+        assert!(!TEST_FLAG.is_enabled());
+
+        Spi::run("SET test.feature_flag TO on").unwrap();
+        assert!(TEST_FLAG.is_enabled());
+        assert!(TEST_FLAG.current_value());
+        assert_eq!(TEST_FLAG.checks(), 2);
+        assert_eq!(TEST_FLAG.enabled_checks(), 1);
+
+        let (enabled, checks) = Spi::get_two::<bool, i64>(
+            "SELECT enabled, checks FROM feature_flags WHERE name = 'test.feature_flag'",
+        )
+        .unwrap();
+        assert_eq!(enabled, Some(true));
+        assert_eq!(checks, Some(2));
+
+        TEST_FLAG.reset_counters();
+        Spi::run("SET test.feature_flag TO off").unwrap();
+        assert!(!TEST_FLAG.is_enabled());
+        assert_eq!(TEST_FLAG.checks(), 1);
+        assert_eq!(TEST_FLAG.enabled_checks(), 0);
+    }
+}
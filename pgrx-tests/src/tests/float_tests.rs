@@ -0,0 +1,84 @@
+/*
+Portions Copyright 2019-2021 ZomboDB, LLC.
+Portions Copyright 2021-2022 Technology Concepts & Design, Inc. <support@tcdi.com>
+
+All rights reserved.
+
+Use of this source code is governed by the MIT license that can be found in the LICENSE file.
+*/
+
+#[cfg(any(test, feature = "pg_test"))]
+#[pgrx::pg_schema]
+mod tests {
+    #[allow(unused_imports)]
+    use crate as pgrx_tests;
+
+    use pgrx::datum::float::*;
+    use pgrx::prelude::*;
+
+    fn sql_float8_text(value: f64) -> String {
+        Spi::get_one_with_args::<String>(
+            "SELECT $1::text",
+            vec![(PgBuiltInOids::FLOAT8OID.oid(), value.into_datum())],
+        )
+        .unwrap()
+        .unwrap()
+    }
+
+    fn sql_float4_text(value: f32) -> String {
+        Spi::get_one_with_args::<String>(
+            "SELECT $1::text",
+            vec![(PgBuiltInOids::FLOAT4OID.oid(), value.into_datum())],
+        )
+        .unwrap()
+        .unwrap()
+    }
+
+    #[pg_test]
+    fn test_format_matches_core() {
+        for value in [0.0, -0.0, 0.1, 1.5, 1e20, 1e-7, 123456789.123, f64::MAX, f64::MIN_POSITIVE] {
+            assert_eq!(format_float8(value), sql_float8_text(value));
+        }
+        for value in [0.0f32, 0.1, 3.4e38, 1e-7, 16777217.0] {
+            assert_eq!(format_float4(value), sql_float4_text(value));
+        }
+        assert_eq!(format_float8(f64::INFINITY), "Infinity");
+        assert_eq!(format_float8(f64::NEG_INFINITY), "-Infinity");
+        assert_eq!(format_float8(f64::NAN), "NaN");
+        assert_eq!(format_float4(f32::INFINITY), "Infinity");
+    }
+
+    #[pg_test]
+    fn test_format_with_extra_digits() -> Result<(), spi::Error> {
+        Spi::run("SET LOCAL extra_float_digits = -3")?;
+        let expected = sql_float8_text(0.1);
+        Spi::run("SET LOCAL extra_float_digits = 1")?;
+        assert_eq!(format_float8_with_extra_digits(0.1, -3), expected);
+        assert_eq!(format_float8(0.1), sql_float8_text(0.1));
+
+        Spi::run("SET LOCAL extra_float_digits = -2")?;
+        let expected = sql_float4_text(1.1);
+        Spi::run("SET LOCAL extra_float_digits = 1")?;
+        assert_eq!(format_float4_with_extra_digits(1.1, -2), expected);
+        Ok(())
+    }
+
+    #[pg_test]
+    fn test_parse() {
+        assert_eq!(parse_float8(" 1.5 "), Ok(1.5));
+        assert_eq!(parse_float8("1e+20"), Ok(1e20));
+        assert_eq!(parse_float8("Infinity"), Ok(f64::INFINITY));
+        assert_eq!(parse_float8("-inf"), Ok(f64::NEG_INFINITY));
+        assert!(parse_float8("nan").unwrap().is_nan());
+        assert_eq!(parse_float4("-INFINITY"), Ok(f32::NEG_INFINITY));
+        assert_eq!(parse_float4("0.25"), Ok(0.25));
+    }
+
+    #[pg_test]
+    fn test_parse_errors() {
+        assert!(matches!(parse_float8("abc"), Err(FloatParseError::Invalid(_))));
+        assert!(matches!(parse_float8(""), Err(FloatParseError::Invalid(_))));
+        assert!(matches!(parse_float8("1e400"), Err(FloatParseError::OutOfRange(_))));
+        assert!(matches!(parse_float4("1e40"), Err(FloatParseError::OutOfRange(_))));
+    }
+}
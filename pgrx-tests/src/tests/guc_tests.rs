@@ -15,6 +15,7 @@ mod tests {
 
     use pgrx::guc::*;
     use pgrx::prelude::*;
+    use std::cell::RefCell;
 
     #[pg_test]
     fn test_bool_guc() {
@@ -168,4 +169,174 @@ mod tests {
         Spi::run("SET test.enum = 'three'").expect("SPI failed");
         assert_eq!(GUC.get(), TestEnum::Three);
     }
+
+    #[pg_test]
+    fn test_define_gucs() {
+        #[derive(PostgresGucEnum, Clone, Copy, PartialEq, Debug)]
+        enum Mode {
+            Fast,
+            Safe,
+        }
+
+        pgrx::define_gucs! {
+            fn register();
+
+            /// A defined bool
+            static ENABLED: bool = false, "test.defined_bool";
+
+            /// A defined int
+            ///
+            /// In milliseconds.
+            static TIMEOUT: i32 = 100, "test.defined_int",
+                min = 0,
+                max = 1000,
+                flags = GucFlags::UNIT_MS;
+
+            /// A defined float
+            static RATIO: f64 = 0.5, "test.defined_float", min = 0.0, max = 1.0;
+
+            /// A defined string
+            static LABEL: Option<&'static str> = Some("label"), "test.defined_string";
+
+            /// A defined enum
+            static MODE: Mode = Mode::Safe, "test.defined_enum", context = GucContext::Userset;
+        }
+        register();
+
+        assert_eq!(ENABLED.get(), false);
+        assert_eq!(TIMEOUT.get(), 100);
+        assert_eq!(RATIO.get(), 0.5);
+        assert_eq!(LABEL.get(), Some("label".into()));
+        assert_eq!(MODE.get(), Mode::Safe);
+
+        Spi::run(
+            "SET test.defined_bool = on; SET test.defined_int = '1s'; \
+             SET test.defined_float = 0.25; SET test.defined_string = 'other'; \
+             SET test.defined_enum = 'fast'",
+        )
+        .expect("SPI failed");
+        assert_eq!(ENABLED.get(), true);
+        assert_eq!(TIMEOUT.get(), 1000);
+        assert_eq!(RATIO.get(), 0.25);
+        assert_eq!(LABEL.get(), Some("other".into()));
+        assert_eq!(MODE.get(), Mode::Fast);
+
+        assert_eq!(
+            Spi::get_two::<String, String>(
+                "SELECT short_desc, extra_desc FROM pg_settings WHERE name = 'test.defined_int'"
+            ),
+            Ok((Some("A defined int".into()), Some("In milliseconds.".into())))
+        );
+    }
+
+    #[pg_test(
+        error = "1500 is outside the valid range for parameter \"test.defined_range\" (0 .. 1000)"
+    )]
+    fn test_define_gucs_range() {
+        pgrx::define_gucs! {
+            fn register();
+
+            /// A defined int with a range
+            static TIMEOUT: i32 = 100, "test.defined_range", min = 0, max = 1000;
+        }
+        register();
+        Spi::run("SET test.defined_range = 1500").expect("SPI failed");
+    }
+
+    #[pg_test]
+    fn test_define_gucs_hooks() {
+        thread_local! {
+            static ASSIGNED: RefCell<Option<String>> = const { RefCell::new(None) };
+        }
+
+        pgrx::define_gucs! {
+            fn register();
+
+            /// A string that must be lowercase
+            static LOWER: Option<&'static str> = None, "test.defined_hooks",
+                check = |value: &Option<String>| match value {
+                    Some(value) if value.to_lowercase() != *value => {
+                        Err(format!("\"{}\" is not lowercase", value))
+                    }
+                    _ => Ok(()),
+                },
+                assign = |value: &Option<String>| {
+                    ASSIGNED.with(|assigned| assigned.replace(value.clone()));
+                };
+        }
+        register();
+
+        Spi::run("SET test.defined_hooks = 'abc'").expect("SPI failed");
+        assert_eq!(LOWER.get(), Some("abc".into()));
+        assert_eq!(ASSIGNED.with(|assigned| assigned.borrow().clone()), Some("abc".into()));
+
+        let result = Spi::run("SET test.defined_hooks = 'ABC'");
+        assert!(result.is_err());
+        assert_eq!(LOWER.get(), Some("abc".into()));
+        assert_eq!(ASSIGNED.with(|assigned| assigned.borrow().clone()), Some("abc".into()));
+    }
+
+    #[pg_test(error = "invalid value for parameter \"test.defined_check\": 3")]
+    fn test_define_gucs_check() {
+        pgrx::define_gucs! {
+            fn register();
+
+            /// An even number
+            static EVEN: i32 = 2, "test.defined_check",
+                check = |value: &i32| {
+                    if value % 2 == 0 {
+                        Ok(())
+                    } else {
+                        Err(String::from("The value must be even."))
+                    }
+                };
+        }
+        register();
+        Spi::run("SET test.defined_check = 3").expect("SPI failed");
+    }
+
+    #[pg_test]
+    fn test_setting_scopes() {
+        Spi::run("CREATE ROLE guc_scope_role").unwrap();
+        let database = Spi::get_one::<String>("SELECT current_database()::text").unwrap().unwrap();
+        let role = "guc_scope_role";
+
+        let in_database = SettingScope::RoleInDatabase { role, database: &database };
+        assert_eq!(SettingScope::Role(role).get("work_mem"), Ok(None));
+        assert_eq!(SettingScope::effective(role, &database, "work_mem"), Ok(None));
+
+        SettingScope::Database(&database).set("work_mem", "8MB").unwrap();
+        SettingScope::Role(role).set("work_mem", "16MB").unwrap();
+        assert_eq!(SettingScope::Role(role).get("WORK_MEM"), Ok(Some("16MB".to_string())));
+        assert_eq!(
+            SettingScope::effective(role, &database, "work_mem"),
+            Ok(Some(("16MB".to_string(), SettingScope::Role(role))))
+        );
+
+        in_database.set("work_mem", "32MB").unwrap();
+        SettingScope::Role(role).set("statement_timeout", "5s").unwrap();
+        assert_eq!(
+            SettingScope::effective(role, &database, "work_mem"),
+            Ok(Some(("32MB".to_string(), in_database)))
+        );
+        assert_eq!(SettingScope::Role(role).get("statement_timeout"), Ok(Some("5s".to_string())));
+
+        in_database.reset("work_mem").unwrap();
+        SettingScope::Role(role).reset("work_mem").unwrap();
+        assert_eq!(
+            SettingScope::effective(role, &database, "work_mem"),
+            Ok(Some(("8MB".to_string(), SettingScope::Database(&database))))
+        );
+
+        SettingScope::Role(role).set_list("search_path", &["public", "my schema"]).unwrap();
+        assert_eq!(
+            SettingScope::Role(role).get("search_path"),
+            Ok(Some("public, \"my schema\"".to_string()))
+        );
+    }
+
+    #[pg_test(error = "role \"no_such_role\" does not exist")]
+    fn test_setting_scope_missing_role() {
+        SettingScope::Role("no_such_role").get("work_mem").unwrap();
+    }
 }
@@ -0,0 +1,78 @@
+/*
+Portions Copyright 2019-2021 ZomboDB, LLC.
+Portions Copyright 2021-2022 Technology Concepts & Design, Inc. <support@tcdi.com>
+
+All rights reserved.
+
+Use of this source code is governed by the MIT license that can be found in the LICENSE file.
+*/
+
+#[cfg(any(test, feature = "pg_test"))]
+#[pgrx::pg_schema]
+mod tests {
+    #[allow(unused_imports)]
+    use crate as pgrx_tests;
+
+    use pgrx::hash::*;
+    use pgrx::prelude::*;
+
+    #[pg_test]
+    fn test_hash_datum_matches_core() -> Result<(), pgrx::spi::Error> {
+        let hash = hash_datum(42i32.into_datum().unwrap(), pg_sys::INT4OID);
+        assert_eq!(Spi::get_one::<i32>("SELECT hashint4(42)")?, Some(hash as i32));
+
+        let hash = hash_datum("hello".into_datum().unwrap(), pg_sys::TEXTOID);
+        assert_eq!(Spi::get_one::<i32>("SELECT hashtext('hello')")?, Some(hash as i32));
+        Ok(())
+    }
+
+    #[pg_test]
+    fn test_hash_datum_extended_matches_core() -> Result<(), pgrx::spi::Error> {
+        let hash = hash_datum_extended(42i64.into_datum().unwrap(), pg_sys::INT8OID, 7);
+        assert_eq!(Spi::get_one::<i64>("SELECT hashint8extended(42, 7)")?, Some(hash as i64));
+
+        let hash = hash_datum_extended("hello".into_datum().unwrap(), pg_sys::TEXTOID, 0);
+        assert_eq!(hash as u32, hash_datum("hello".into_datum().unwrap(), pg_sys::TEXTOID));
+        Ok(())
+    }
+
+    #[pg_test]
+    fn test_hash_partition_bucket_matches_core() -> Result<(), pgrx::spi::Error> {
+        Spi::run(
+            "CREATE TABLE hash_parted (id bigint, name text) PARTITION BY HASH (id, name);
+             CREATE TABLE hash_parted_0 PARTITION OF hash_parted FOR VALUES WITH (MODULUS 3, REMAINDER 0);
+             CREATE TABLE hash_parted_1 PARTITION OF hash_parted FOR VALUES WITH (MODULUS 3, REMAINDER 1);
+             CREATE TABLE hash_parted_2 PARTITION OF hash_parted FOR VALUES WITH (MODULUS 3, REMAINDER 2);
+             INSERT INTO hash_parted SELECT i, CASE WHEN i % 5 <> 0 THEN 'row ' || i END FROM generate_series(1, 100) i;",
+        )?;
+
+        Spi::connect(|client| {
+            let rows = client.select(
+                "SELECT id, name, right(tableoid::regclass::text, 1)::bigint FROM hash_parted",
+                None,
+                None,
+            )?;
+            for row in rows {
+                let id = row.get::<i64>(1)?;
+                let name = row.get::<String>(2)?;
+                let remainder = row.get::<i64>(3)?.unwrap();
+                let keys =
+                    [(id.into_datum(), pg_sys::INT8OID), (name.into_datum(), pg_sys::TEXTOID)];
+                assert_eq!(hash_partition_bucket(&keys, 3), remainder as u64);
+            }
+            Ok(())
+        })
+    }
+
+    #[pg_test]
+    fn test_hash_combine() {
+        assert_eq!(hash_combine(0, 0), 0x9e3779b9);
+        assert_eq!(hash_combine64(0, 0), 0x49a0f4dd15e5a8e3);
+    }
+
+    #[pg_test(error = "could not identify a hash function for type point")]
+    fn test_hash_datum_unhashable() {
+        // the type is checked before the value is looked at
+        hash_datum(pg_sys::Datum::from(0), pg_sys::POINTOID);
+    }
+}
@@ -623,7 +623,7 @@ mod tests {
     #[cfg(test)]
     use crate as pgrx_tests;
     use pgrx::datum::TryFromDatumError;
-    use pgrx::heap_tuple::PgHeapTupleError;
+    use pgrx::heap_tuple::{PgHeapTupleBuilder, PgHeapTupleError};
     use pgrx::prelude::*;
     use pgrx::AllocatedByRust;
     use std::num::NonZeroUsize;
@@ -924,4 +924,60 @@ mod tests {
         let table = result.expect("unable to select table result");
         assert_eq!(table.len(), 10_000);
     }
+
+    #[pg_test]
+    fn test_builder() {
+        let mut builder = PgHeapTupleBuilder::for_composite_type("Dog").unwrap();
+        builder.set_by_name("name", "Nami").unwrap();
+        builder.set_by_index(NonZeroUsize::new(2).unwrap(), 7).unwrap();
+        let dog = builder.build();
+
+        assert_eq!(dog.get_by_name("name"), Ok(Some("Nami".to_string())));
+        assert_eq!(dog.get_by_name("scritches"), Ok(Some(7)));
+
+        let scritches = Spi::get_one_with_args::<i32>(
+            "SELECT ($1::Dog).scritches",
+            vec![(PgOid::from(pgrx::regtypein("Dog")), dog.into_datum())],
+        );
+        assert_eq!(scritches, Ok(Some(7)));
+    }
+
+    #[pg_test]
+    fn test_builder_unset_attributes_are_null() {
+        let mut builder = PgHeapTupleBuilder::for_composite_type("Dog").unwrap();
+        builder.set_by_name("scritches", 1).unwrap();
+        let dog = builder.build();
+
+        assert_eq!(dog.get_by_name::<String>("name"), Ok(None));
+        assert_eq!(dog.get_by_name("scritches"), Ok(Some(1)));
+    }
+
+    #[pg_test]
+    fn test_builder_errors() {
+        assert_eq!(
+            PgHeapTupleBuilder::for_composite_type("NoSuchDog").err(),
+            Some(PgHeapTupleError::NoSuchType("NoSuchDog".to_string()))
+        );
+
+        Spi::run("CREATE TYPE DogWithAge AS (name text, dropped int, age int);").unwrap();
+        Spi::run("ALTER TYPE DogWithAge DROP ATTRIBUTE dropped").unwrap();
+        let mut builder = PgHeapTupleBuilder::for_composite_type("DogWithAge").unwrap();
+        assert_eq!(
+            builder.set_by_name("breed", "beagle").err(),
+            Some(TryFromDatumError::NoSuchAttributeName("breed".to_string()))
+        );
+        let dropped = NonZeroUsize::new(2).unwrap();
+        assert_eq!(
+            builder.set_by_index(dropped, 1).err(),
+            Some(TryFromDatumError::NoSuchAttributeNumber(dropped))
+        );
+        assert!(matches!(
+            builder.set_by_name("age", "Brandy"),
+            Err(TryFromDatumError::IncompatibleTypes { .. })
+        ));
+
+        builder.set_by_name("name", "Brandy").unwrap().set_by_name("age", 42).unwrap();
+        let dog = builder.build();
+        assert_eq!(dog.get_by_name("age"), Ok(Some(42)));
+    }
 }
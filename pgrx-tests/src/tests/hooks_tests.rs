@@ -16,6 +16,7 @@ mod tests {
     use pgrx::hooks::*;
     use pgrx::prelude::*;
     use pgrx::PgList;
+    use std::cell::RefCell;
 
     #[pg_test]
     unsafe fn test_callbacks() {
@@ -128,4 +129,37 @@ mod tests {
         // TODO:  it'd be nice to also test that .commit() and .abort() also get called
         //    but I don't see how to do that since we're running *inside* a transaction here
     }
+
+    #[pg_test]
+    unsafe fn test_stacked_hooks() {
+        use pgrx::pg_sys::*;
+
+        thread_local! {
+            static CALLS: RefCell<Vec<&'static str>> = const { RefCell::new(Vec::new()) };
+        }
+
+        struct NamedHook {
+            name: &'static str,
+        }
+        impl PgHooks for NamedHook {
+            fn executor_start(
+                &mut self,
+                query_desc: PgBox<QueryDesc>,
+                eflags: i32,
+                prev_hook: fn(PgBox<QueryDesc>, i32) -> HookResult<()>,
+            ) -> HookResult<()> {
+                CALLS.with(|calls| calls.borrow_mut().push(self.name));
+                let result = prev_hook(query_desc, eflags);
+                CALLS.with(|calls| calls.borrow_mut().push(self.name));
+                result
+            }
+        }
+
+        pgrx::hooks::register_hook(Box::leak(Box::new(NamedHook { name: "first" })));
+        pgrx::hooks::register_hook(Box::leak(Box::new(NamedHook { name: "second" })));
+        Spi::run("SELECT 1").expect("SPI failed");
+
+        // the most recently registered runs first, and its prev_hook runs the one before it
+        assert_eq!(CALLS.with(|calls| calls.take()), vec!["second", "first", "first", "second"]);
+    }
 }
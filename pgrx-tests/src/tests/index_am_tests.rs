@@ -0,0 +1,141 @@
+/*
+Portions Copyright 2019-2021 ZomboDB, LLC.
+Portions Copyright 2021-2022 Technology Concepts & Design, Inc. <support@tcdi.com>
+
+All rights reserved.
+
+Use of this source code is governed by the MIT license that can be found in the LICENSE file.
+*/
+use pgrx::index_am::{IndexAm, IndexEntry, IndexMatch, IndexScanKey};
+use pgrx::prelude::*;
+use pgrx::PgRelation;
+use std::cell::{Cell, RefCell};
+use std::collections::HashMap;
+
+thread_local! {
+    static ENTRIES: RefCell<HashMap<(pg_sys::Oid, i32), Vec<pg_sys::ItemPointerData>>> =
+        RefCell::new(HashMap::new());
+    static SCANS: Cell<usize> = Cell::new(0);
+}
+
+/// An index of `int4` values, for `=` only, in the backend's memory
+struct ExactAm {
+    index: pg_sys::Oid,
+    matches: Vec<pg_sys::ItemPointerData>,
+}
+
+#[pg_index_am(name = "pgrx_exact")]
+impl IndexAm for ExactAm {
+    const STRATEGIES: u16 = 1;
+
+    fn build_empty(index: &PgRelation) {
+        ENTRIES.with(|entries| entries.borrow_mut().retain(|(oid, _), _| *oid != index.oid()));
+    }
+
+    fn insert(index: &PgRelation, entry: &IndexEntry) {
+        if let Some(value) = entry.get::<i32>(1) {
+            ENTRIES.with(|entries| {
+                entries.borrow_mut().entry((index.oid(), value)).or_default().push(entry.tid())
+            });
+        }
+    }
+
+    fn bulk_delete(
+        index: &PgRelation,
+        is_dead: &mut dyn FnMut(pg_sys::ItemPointerData) -> bool,
+    ) -> usize {
+        let mut removed = 0;
+        ENTRIES.with(|entries| {
+            for ((oid, _), tids) in entries.borrow_mut().iter_mut() {
+                if *oid == index.oid() {
+                    let before = tids.len();
+                    tids.retain(|tid| !is_dead(*tid));
+                    removed += before - tids.len();
+                }
+            }
+        });
+        removed
+    }
+
+    fn begin_scan(index: &PgRelation) -> Self {
+        SCANS.with(|scans| scans.set(scans.get() + 1));
+        ExactAm { index: index.oid(), matches: Vec::new() }
+    }
+
+    fn rescan(&mut self, keys: &[IndexScanKey]) {
+        self.matches = match keys.iter().map(|key| key.argument::<i32>()).next() {
+            Some(Some(value)) => ENTRIES.with(|entries| {
+                entries.borrow().get(&(self.index, value)).cloned().unwrap_or_default()
+            }),
+            _ => Vec::new(),
+        };
+    }
+
+    fn get_tuple(&mut self) -> Option<IndexMatch> {
+        self.matches.pop().map(|tid| IndexMatch { tid, recheck: false })
+    }
+}
+
+extension_sql!(
+    "CREATE OPERATOR CLASS int4_pgrx_exact_ops DEFAULT FOR TYPE int4 USING pgrx_exact AS OPERATOR 1 =;",
+    name = "int4_pgrx_exact_ops",
+    requires = ["pgrx_exact"]
+);
+
+#[cfg(any(test, feature = "pg_test"))]
+#[pgrx::pg_schema]
+mod tests {
+    #[allow(unused_imports)]
+    use crate as pgrx_tests;
+
+    use super::SCANS;
+    use pgrx::prelude::*;
+
+    fn scans() -> usize {
+        SCANS.with(|scans| scans.get())
+    }
+
+    fn create_indexed_table() {
+        Spi::run(
+            "CREATE TABLE exact_things (n int4); \
+             INSERT INTO exact_things SELECT i % 10 FROM generate_series(1, 100) i; \
+             CREATE INDEX exact_things_n ON exact_things USING pgrx_exact (n); \
+             SET LOCAL enable_seqscan = off;",
+        )
+        .unwrap();
+    }
+
+    #[pg_test]
+    fn test_index_am_build_and_scan() {
+        create_indexed_table();
+        let before = scans();
+        assert_eq!(
+            Spi::get_one::<i64>("SELECT count(*) FROM exact_things WHERE n = 3"),
+            Ok(Some(10))
+        );
+        assert_eq!(
+            Spi::get_one::<i64>("SELECT count(*) FROM exact_things WHERE n = 42"),
+            Ok(Some(0))
+        );
+        assert!(scans() > before);
+    }
+
+    #[pg_test]
+    fn test_index_am_insert() {
+        create_indexed_table();
+        Spi::run("INSERT INTO exact_things VALUES (42), (42), (NULL)").unwrap();
+        assert_eq!(
+            Spi::get_one::<i64>("SELECT count(*) FROM exact_things WHERE n = 42"),
+            Ok(Some(2))
+        );
+    }
+
+    #[pg_test(error = "the index access method does not support options")]
+    fn test_index_am_options() {
+        Spi::run(
+            "CREATE TABLE exact_options (n int4); \
+             CREATE INDEX ON exact_options USING pgrx_exact (n) WITH (fillfactor = 50);",
+        )
+        .unwrap();
+    }
+}
@@ -0,0 +1,168 @@
+/*
+Portions Copyright 2019-2021 ZomboDB, LLC.
+Portions Copyright 2021-2022 Technology Concepts & Design, Inc. <support@tcdi.com>
+
+All rights reserved.
+
+Use of this source code is governed by the MIT license that can be found in the LICENSE file.
+*/
+
+#[cfg(any(test, feature = "pg_test"))]
+#[pgrx::pg_schema]
+mod tests {
+    #[allow(unused_imports)]
+    use crate as pgrx_tests;
+
+    use pgrx::prelude::*;
+    use pgrx::{AdvisoryLock, AdvisoryLockKey, LockMode, RelationLock};
+    use std::time::Duration;
+
+    /// how many advisory locks does our backend hold with the given key fields?
+    fn advisory_locks(classid: i64, objid: i64, objsubid: i32) -> i64 {
+        Spi::get_one_with_args(
+            "SELECT count(*) FROM pg_locks
+              WHERE locktype = 'advisory' AND pid = pg_backend_pid()
+                AND classid::bigint = $1 AND objid::bigint = $2 AND objsubid = $3",
+            vec![
+                (PgBuiltInOids::INT8OID.oid(), classid.into_datum()),
+                (PgBuiltInOids::INT8OID.oid(), objid.into_datum()),
+                (PgBuiltInOids::INT4OID.oid(), objsubid.into_datum()),
+            ],
+        )
+        .unwrap()
+        .unwrap()
+    }
+
+    #[pg_test]
+    fn test_advisory_lock_session() {
+        let lock = AdvisoryLock::session(42);
+        assert_eq!(AdvisoryLockKey::Int8(42), lock.key());
+        assert!(lock.is_session());
+        assert!(!lock.is_shared());
+        assert_eq!(1, advisory_locks(0, 42, 1));
+
+        // a session lock is released as soon as it's dropped
+        drop(lock);
+        assert_eq!(0, advisory_locks(0, 42, 1));
+    }
+
+    #[pg_test]
+    fn test_advisory_lock_pair_key() {
+        let _lock = AdvisoryLock::session_shared((1, 2));
+        assert_eq!(1, advisory_locks(1, 2, 2));
+
+        // the bigint and (int, int) key spaces are distinct
+        assert_eq!(0, advisory_locks(1, 2, 1));
+    }
+
+    #[pg_test]
+    fn test_advisory_lock_xact() {
+        let lock = AdvisoryLock::try_xact(43).expect("lock should be available");
+        assert!(!lock.is_session());
+
+        // transaction locks are held until the end of the transaction
+        drop(lock);
+        assert_eq!(1, advisory_locks(0, 43, 1));
+    }
+
+    #[pg_test]
+    fn test_advisory_lock_matches_sql() {
+        let _lock = AdvisoryLock::xact(-1);
+        let held_by_sql = Spi::get_one::<bool>(
+            "SELECT count(*) = 1 FROM pg_locks
+              WHERE locktype = 'advisory' AND pid = pg_backend_pid() AND mode = 'ExclusiveLock'
+                AND objid::bigint = (-1::bigint & x'ffffffff'::bigint)
+                AND classid::bigint = (-1::bigint >> 32 & x'ffffffff'::bigint)",
+        );
+        assert_eq!(Ok(Some(true)), held_by_sql);
+    }
+
+    /// which modes does our backend hold on the given relation?
+    fn relation_locks(relid: pg_sys::Oid) -> Vec<String> {
+        Spi::connect(|client| {
+            client
+                .select(
+                    "SELECT mode FROM pg_locks
+                      WHERE locktype = 'relation' AND pid = pg_backend_pid() AND relation = $1
+                      ORDER BY mode",
+                    None,
+                    Some(vec![(PgBuiltInOids::OIDOID.oid(), relid.into_datum())]),
+                )?
+                .map(|row| row["mode"].value::<String>().map(Option::unwrap))
+                .collect::<Result<Vec<_>, _>>()
+        })
+        .unwrap()
+    }
+
+    fn create_table() -> pg_sys::Oid {
+        Spi::run("CREATE TABLE tests.relation_lock_test (id int)").unwrap();
+        Spi::get_one::<pg_sys::Oid>("SELECT 'tests.relation_lock_test'::regclass::oid")
+            .unwrap()
+            .unwrap()
+    }
+
+    #[pg_test]
+    fn test_relation_lock() {
+        let relid = create_table();
+
+        let lock = RelationLock::lock(relid, LockMode::ShareUpdateExclusive);
+        assert_eq!(relid, lock.relid());
+        assert_eq!(LockMode::ShareUpdateExclusive, lock.mode());
+        assert!(relation_locks(relid).contains(&"ShareUpdateExclusiveLock".to_string()));
+
+        drop(lock);
+        assert!(!relation_locks(relid).contains(&"ShareUpdateExclusiveLock".to_string()));
+    }
+
+    #[pg_test]
+    fn test_relation_lock_conditional() {
+        let relid = create_table();
+
+        // we already hold AccessExclusiveLock from creating the table, and our own locks never
+        // conflict with each other
+        let lock =
+            RelationLock::try_lock(relid, LockMode::Share).expect("lock should be available");
+        lock.hold_until_xact_end();
+        let lock =
+            RelationLock::try_lock_for(relid, LockMode::Exclusive, Duration::from_millis(10))
+                .expect("lock should be available");
+        drop(lock);
+        assert!(relation_locks(relid).contains(&"ShareLock".to_string()));
+        assert!(!relation_locks(relid).contains(&"ExclusiveLock".to_string()));
+    }
+
+    #[pg_test]
+    fn test_lock_mode_conflicts() {
+        use LockMode::*;
+        let modes = [
+            AccessShare,
+            RowShare,
+            RowExclusive,
+            ShareUpdateExclusive,
+            Share,
+            ShareRowExclusive,
+            Exclusive,
+            AccessExclusive,
+        ];
+
+        // Postgres' conflict table, as a bitmask of the conflicting `modes`, indexed by mode
+        let conflicts = [
+            0b1000_0000,
+            0b1100_0000,
+            0b1111_0000,
+            0b1111_1000,
+            0b1110_1100,
+            0b1111_1100,
+            0b1111_1110,
+            0b1111_1111,
+        ];
+
+        for (i, mode) in modes.iter().enumerate() {
+            assert!(!mode.conflicts_with(NoLock));
+            for (j, other) in modes.iter().enumerate() {
+                let expected = conflicts[i] & (1 << j) != 0;
+                assert_eq!(expected, mode.conflicts_with(*other), "{mode:?} vs {other:?}");
+            }
+        }
+    }
+}
@@ -12,6 +12,7 @@ Use of this source code is governed by the MIT license that can be found in the
 mod tests {
     #[allow(unused_imports)]
     use crate as pgrx_tests;
+    use pgrx::pg_sys::panic::{CaughtError, ErrorReportWithLevel};
     use pgrx::prelude::*;
 
     #[pg_test]
@@ -74,6 +75,73 @@ mod tests {
         pgrx::ereport!(PgLogLevel::ERROR, PgSqlErrorCode::ERRCODE_INTERNAL_ERROR, "ereport error")
     }
 
+    #[pg_test(error = "ereport with fields")]
+    fn test_ereport_with_fields() {
+        pgrx::ereport!(ERROR, PgSqlErrorCode::ERRCODE_CHECK_VIOLATION, "ereport with fields";
+            set_detail("the detail")
+            .set_hint("the hint")
+            .set_context("the context")
+            .set_table_name("orders")
+            .set_constraint_name("orders_total_check")
+            .set_errbacktrace(true)
+        );
+    }
+
+    #[pg_test]
+    fn test_ereport_fields_are_caught() {
+        let report = PgTryBuilder::new(|| -> Option<ErrorReportWithLevel> {
+            pgrx::ereport!(ERROR, PgSqlErrorCode::ERRCODE_CHECK_VIOLATION, "caught";
+                set_hint("the hint")
+                .set_context("the context")
+                .set_schema_name("public")
+                .set_table_name("orders")
+                .set_column_name("total")
+                .set_datatype_name("numeric")
+                .set_constraint_name("orders_total_check")
+            );
+        })
+        .catch_when(PgSqlErrorCode::ERRCODE_CHECK_VIOLATION, |e| match e {
+            CaughtError::ErrorReport(report) => Some(report),
+            _ => None,
+        })
+        .execute()
+        .expect("the ereport wasn't caught");
+
+        assert_eq!(report.message(), "caught");
+        assert_eq!(report.hint(), Some("the hint"));
+        assert_eq!(report.context(), Some("the context"));
+        let fields = report.fields();
+        assert_eq!(fields.schema_name.as_deref(), Some("public"));
+        assert_eq!(fields.table_name.as_deref(), Some("orders"));
+        assert_eq!(fields.column_name.as_deref(), Some("total"));
+        assert_eq!(fields.datatype_name.as_deref(), Some("numeric"));
+        assert_eq!(fields.constraint_name.as_deref(), Some("orders_total_check"));
+    }
+
+    #[pg_test]
+    fn test_ereport_warning_with_fields() {
+        pgrx::ereport!(PgLogLevel::WARNING, PgSqlErrorCode::ERRCODE_WARNING, "warning with fields";
+            set_hint("the hint").set_column_name("total"),
+        );
+    }
+
+    #[pg_test]
+    fn test_sqlstate() {
+        assert_eq!(PgSqlErrorCode::ERRCODE_UNIQUE_VIOLATION.sqlstate(), "23505");
+        assert_eq!(PgSqlErrorCode::ERRCODE_IDLE_SESSION_TIMEOUT.sqlstate(), "57P05");
+        assert_eq!(
+            PgSqlErrorCode::from_sqlstate("23505"),
+            Some(PgSqlErrorCode::ERRCODE_UNIQUE_VIOLATION)
+        );
+        assert_eq!(
+            PgSqlErrorCode::from_sqlstate("XX000"),
+            Some(PgSqlErrorCode::ERRCODE_INTERNAL_ERROR)
+        );
+        assert_eq!(PgSqlErrorCode::from_sqlstate("99999"), None);
+        assert_eq!(PgSqlErrorCode::from_sqlstate("2350"), None);
+        assert_eq!(PgSqlErrorCode::from_sqlstate("2350a"), None);
+    }
+
     #[pg_test(error = "panic message")]
     fn test_panic() {
         panic!("panic message")
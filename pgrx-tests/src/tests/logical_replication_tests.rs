@@ -0,0 +1,117 @@
+/*
+Portions Copyright 2019-2021 ZomboDB, LLC.
+Portions Copyright 2021-2022 Technology Concepts & Design, Inc. <support@tcdi.com>
+
+All rights reserved.
+
+Use of this source code is governed by the MIT license that can be found in the LICENSE file.
+*/
+
+#[cfg(any(test, feature = "pg_test"))]
+#[pgrx::pg_schema]
+mod tests {
+    #[allow(unused_imports)]
+    use crate as pgrx_tests;
+
+    use pgrx::logical_replication::{
+        subscriptions, CreatePublication, Publication, PublishActions,
+    };
+    use pgrx::prelude::*;
+
+    fn create_tables() -> Vec<pg_sys::Oid> {
+        Spi::run(
+            "CREATE TABLE tests.pub_orders (id int PRIMARY KEY);
+            CREATE TABLE tests.\"pub Items\" (id int PRIMARY KEY);",
+        )
+        .unwrap();
+        ["tests.pub_orders", "tests.\"pub Items\""]
+            .iter()
+            .map(|name| {
+                Spi::get_one::<pg_sys::Oid>(&format!("SELECT '{}'::regclass::oid", name))
+                    .unwrap()
+                    .unwrap()
+            })
+            .collect()
+    }
+
+    fn sorted(mut relids: Vec<pg_sys::Oid>) -> Vec<pg_sys::Oid> {
+        relids.sort_unstable_by_key(|relid| relid.as_u32());
+        relids
+    }
+
+    #[pg_test]
+    fn test_create_publication() {
+        let tables = create_tables();
+        let publication = CreatePublication::new("Test Pub")
+            .tables(tables.iter().copied())
+            .publish(PublishActions { delete: false, truncate: false, ..Default::default() })
+            .create()
+            .unwrap();
+
+        assert_eq!(Publication::open("Test Pub").unwrap(), Some(publication.clone()));
+        assert_eq!(publication.tables().unwrap(), sorted(tables));
+        assert_eq!(publication.all_tables(), Ok(false));
+        assert_eq!(
+            publication.publish(),
+            Ok(PublishActions { insert: true, update: true, delete: false, truncate: false })
+        );
+
+        publication.drop().unwrap();
+        assert_eq!(Publication::open("Test Pub"), Ok(None));
+    }
+
+    #[pg_test]
+    fn test_alter_publication() {
+        let tables = create_tables();
+        let publication = CreatePublication::new("test_alter_pub").create().unwrap();
+        assert_eq!(publication.tables(), Ok(vec![]));
+
+        publication.add_tables(&tables).unwrap();
+        assert_eq!(publication.tables().unwrap(), sorted(tables.clone()));
+        publication.drop_tables(&tables[..1]).unwrap();
+        assert_eq!(publication.tables(), Ok(vec![tables[1]]));
+        publication.set_tables(&tables[..1]).unwrap();
+        assert_eq!(publication.tables(), Ok(vec![tables[0]]));
+
+        let insert_only =
+            PublishActions { insert: true, update: false, delete: false, truncate: false };
+        publication.set_publish(insert_only).unwrap();
+        assert_eq!(publication.publish(), Ok(insert_only));
+    }
+
+    #[pg_test]
+    fn test_publication_for_all_tables() {
+        let tables = create_tables();
+        let publication = CreatePublication::new("test_all_pub").all_tables().create().unwrap();
+        assert_eq!(publication.all_tables(), Ok(true));
+        let published = publication.tables().unwrap();
+        assert!(tables.iter().all(|relid| published.contains(relid)));
+    }
+
+    #[pg_test(error = "relation with OID 0 does not exist")]
+    fn test_publication_missing_table() {
+        CreatePublication::new("test_missing_pub").table(pg_sys::Oid::INVALID).to_sql();
+    }
+
+    #[pg_test]
+    fn test_subscriptions() {
+        assert_eq!(subscriptions().unwrap().iter().filter(|s| s.name == "test_sub").count(), 0);
+        Spi::run(
+            "CREATE SUBSCRIPTION test_sub CONNECTION 'dbname=pgrx_no_such_db'
+                PUBLICATION pub_a, pub_b WITH (connect = false)",
+        )
+        .unwrap();
+
+        let subscriptions = subscriptions().unwrap();
+        let subscription = subscriptions.iter().find(|s| s.name == "test_sub").unwrap();
+        assert!(!subscription.enabled);
+        assert_eq!(subscription.publications, vec!["pub_a".to_string(), "pub_b".to_string()]);
+        assert_eq!(subscription.pid, None);
+        assert_eq!(subscription.received_lsn, None);
+        assert_eq!(subscription.tables(), Ok(vec![]));
+
+        // a subscription with a slot can't be dropped in a transaction block
+        Spi::run("ALTER SUBSCRIPTION test_sub SET (slot_name = NONE)").unwrap();
+        Spi::run("DROP SUBSCRIPTION test_sub").unwrap();
+    }
+}
@@ -0,0 +1,113 @@
+/*
+Portions Copyright 2019-2021 ZomboDB, LLC.
+Portions Copyright 2021-2022 Technology Concepts & Design, Inc. <support@tcdi.com>
+
+All rights reserved.
+
+Use of this source code is governed by the MIT license that can be found in the LICENSE file.
+*/
+
+#[cfg(any(test, feature = "pg_test"))]
+#[pgrx::pg_schema]
+mod tests {
+    #[allow(unused_imports)]
+    use crate as pgrx_tests;
+
+    use pgrx::matview::{self, RefreshOptions};
+    use pgrx::prelude::*;
+
+    fn oid(relname: &str) -> pg_sys::Oid {
+        Spi::get_one::<pg_sys::Oid>(&format!("SELECT '{}'::regclass::oid", relname))
+            .unwrap()
+            .unwrap()
+    }
+
+    fn create_matviews() -> pg_sys::Oid {
+        Spi::run(
+            "CREATE TABLE tests.mv_source (id int, body text);
+            CREATE TABLE tests.mv_other (id int);
+            CREATE VIEW tests.mv_view AS SELECT id FROM tests.mv_other;
+            INSERT INTO tests.mv_source SELECT i, 'row ' || i FROM generate_series(1, 10) i;
+            CREATE MATERIALIZED VIEW tests.mv_summary AS
+                SELECT s.id, s.body FROM tests.mv_source s
+                WHERE s.id NOT IN (SELECT id FROM tests.mv_view)
+                WITH NO DATA;",
+        )
+        .unwrap();
+        oid("tests.mv_summary")
+    }
+
+    #[pg_test]
+    fn test_refresh_materialized_view() {
+        let relid = create_matviews();
+        assert!(matview::is_materialized_view(relid));
+        assert!(!matview::is_populated(relid));
+
+        matview::refresh_materialized_view(relid, RefreshOptions::default());
+        assert!(matview::is_populated(relid));
+        assert_eq!(Spi::get_one::<i64>("SELECT count(*) FROM tests.mv_summary"), Ok(Some(10)));
+
+        Spi::run("INSERT INTO tests.mv_other VALUES (1), (2)").unwrap();
+        matview::refresh_materialized_view(relid, RefreshOptions::default());
+        assert_eq!(Spi::get_one::<i64>("SELECT count(*) FROM tests.mv_summary"), Ok(Some(8)));
+
+        matview::refresh_materialized_view(
+            relid,
+            RefreshOptions { with_no_data: true, ..Default::default() },
+        );
+        assert!(!matview::is_populated(relid));
+    }
+
+    #[pg_test]
+    fn test_refresh_materialized_view_concurrently() {
+        let relid = create_matviews();
+        matview::refresh_materialized_view(relid, RefreshOptions::default());
+        assert!(!matview::can_refresh_concurrently(relid));
+
+        Spi::run("CREATE UNIQUE INDEX mv_summary_partial ON tests.mv_summary (id) WHERE id > 1")
+            .unwrap();
+        assert!(!matview::can_refresh_concurrently(relid));
+        Spi::run("CREATE UNIQUE INDEX mv_summary_id ON tests.mv_summary (id)").unwrap();
+        assert!(matview::can_refresh_concurrently(relid));
+
+        Spi::run("DELETE FROM tests.mv_source WHERE id > 5").unwrap();
+        matview::refresh_materialized_view(
+            relid,
+            RefreshOptions { concurrently: true, ..Default::default() },
+        );
+        assert_eq!(Spi::get_one::<i64>("SELECT count(*) FROM tests.mv_summary"), Ok(Some(5)));
+    }
+
+    #[pg_test]
+    fn test_matview_dependencies() {
+        let relid = create_matviews();
+        let mut expected = vec![oid("tests.mv_source"), oid("tests.mv_view")];
+        expected.sort_unstable_by_key(|oid| oid.as_u32());
+        assert_eq!(matview::dependencies(relid), expected);
+
+        Spi::run("CREATE MATERIALIZED VIEW tests.mv_upper AS SELECT upper(body) FROM tests.mv_summary WITH NO DATA")
+            .unwrap();
+        assert_eq!(matview::dependencies(oid("tests.mv_upper")), vec![relid]);
+        assert!(!matview::is_materialized_view(oid("tests.mv_view")));
+    }
+
+    #[pg_test(error = "\"mv_source\" is not a materialized view")]
+    fn test_refresh_table() {
+        create_matviews();
+        matview::refresh_materialized_view(oid("tests.mv_source"), RefreshOptions::default());
+    }
+
+    #[pg_test(error = "REFRESH options CONCURRENTLY and WITH NO DATA cannot be used together")]
+    fn test_refresh_concurrently_with_no_data() {
+        let relid = create_matviews();
+        matview::refresh_materialized_view(
+            relid,
+            RefreshOptions { concurrently: true, with_no_data: true },
+        );
+    }
+
+    #[pg_test(error = "relation with OID 0 does not exist")]
+    fn test_missing_matview() {
+        matview::is_populated(pg_sys::Oid::INVALID);
+    }
+}
@@ -13,6 +13,8 @@ mod tests {
     #[allow(unused_imports)]
     use crate as pgrx_tests;
 
+    use pgrx::memcxt::run_in_per_tuple_context;
+    use pgrx::memcxt_tracking;
     use pgrx::prelude::*;
     use pgrx::PgMemoryContexts;
     use std::sync::atomic::{AtomicBool, Ordering};
@@ -97,4 +99,134 @@ mod tests {
         drop(ctx);
         assert_eq!(unsafe { pg_sys::CurrentMemoryContext }, ctx_parent);
     }
+
+    #[pg_test(error = "PgBox used after its MemoryContext `pgbox_test` was reset or deleted")]
+    fn test_pgbox_use_after_context_reset() {
+        let mut ctx = PgMemoryContexts::new("pgbox_test");
+        let mut pgbox =
+            unsafe { PgBox::<i64>::alloc0_in_context(PgMemoryContexts::For(ctx.value())) };
+        *pgbox = 42;
+
+        unsafe {
+            // SAFETY:  we're intentionally misusing `pgbox` here
+            ctx.reset();
+        }
+
+        assert_eq!(*pgbox, 42);
+    }
+
+    #[pg_test(error = "PgVarlena used after its MemoryContext `varlena_test` was reset or deleted")]
+    fn test_varlena_use_after_context_reset() {
+        let mut ctx = PgMemoryContexts::new("varlena_test");
+        let varlena = unsafe { ctx.switch_to(|_| PgVarlena::<i64>::new()) };
+
+        unsafe {
+            // SAFETY:  we're intentionally misusing `varlena` here
+            ctx.reset();
+        }
+
+        assert_eq!(*varlena, 0);
+    }
+
+    #[pg_test]
+    fn test_pgbox_survives_sibling_reset() {
+        let mut ctx = PgMemoryContexts::new("reset_me");
+        let other = PgMemoryContexts::new("keep_me");
+        let _doomed =
+            unsafe { PgBox::<i64>::alloc0_in_context(PgMemoryContexts::For(ctx.value())) }
+                .into_pg_boxed();
+        let mut kept =
+            unsafe { PgBox::<i64>::alloc0_in_context(PgMemoryContexts::For(other.value())) };
+
+        unsafe {
+            ctx.reset();
+        }
+
+        // allocations made after the reset are tracked separately from the ones it freed
+        let mut fresh =
+            unsafe { PgBox::<i64>::alloc0_in_context(PgMemoryContexts::For(ctx.value())) };
+        *fresh = 1;
+        *kept = 2;
+        assert_eq!(*fresh + *kept, 3);
+    }
+
+    #[pg_test]
+    fn test_context_reset_stops_tracking_its_allocations() {
+        let mut ctx = PgMemoryContexts::new("tracked");
+        let before = memcxt_tracking::tracked_allocations();
+        let _boxes = (0..3)
+            .map(|_| {
+                unsafe { PgBox::<i64>::alloc0_in_context(PgMemoryContexts::For(ctx.value())) }
+                    .into_pg_boxed()
+            })
+            .collect::<Vec<_>>();
+        assert_eq!(memcxt_tracking::tracked_allocations(), before + 3);
+
+        unsafe {
+            ctx.reset();
+        }
+        assert_eq!(memcxt_tracking::tracked_allocations(), before);
+    }
+
+    #[pg_test]
+    fn test_from_rust_after_context_reset() {
+        let mut ctx = PgMemoryContexts::new("reused");
+        let freed = unsafe { PgBox::<i64>::alloc0_in_context(PgMemoryContexts::For(ctx.value())) }
+            .into_pg_boxed()
+            .as_ptr();
+        unsafe {
+            ctx.reset();
+            ctx.switch_to(|_| {
+                let ptr = pg_sys::palloc(std::mem::size_of::<i64>()).cast::<i64>();
+                // a reset context hands out its memory again from the start
+                assert_eq!(ptr, freed);
+                ptr.write(42);
+                let reused = PgBox::<i64>::from_rust(ptr);
+                assert_eq!(*reused, 42);
+            });
+        }
+    }
+
+    #[pg_test]
+    fn test_run_in_per_tuple_context() {
+        let outer = PgMemoryContexts::CurrentMemoryContext.value();
+        let did_drop = Arc::new(AtomicBool::new(false));
+        let inner_did_drop = Arc::new(AtomicBool::new(false));
+
+        let length = run_in_per_tuple_context(|| {
+            let context = PgMemoryContexts::CurrentMemoryContext.value();
+            assert_ne!(context, outer);
+            PgMemoryContexts::CurrentMemoryContext
+                .leak_and_drop_on_delete(TestObject { did_drop: did_drop.clone() });
+
+            run_in_per_tuple_context(|| {
+                assert_ne!(PgMemoryContexts::CurrentMemoryContext.value(), context);
+                PgMemoryContexts::CurrentMemoryContext
+                    .leak_and_drop_on_delete(TestObject { did_drop: inner_did_drop.clone() });
+            });
+            // the nested call's memory is freed, but not this call's
+            assert!(inner_did_drop.load(Ordering::SeqCst));
+            assert!(!did_drop.load(Ordering::SeqCst));
+            assert_eq!(PgMemoryContexts::CurrentMemoryContext.value(), context);
+
+            "a row".to_string().len()
+        });
+
+        assert_eq!(length, 5);
+        assert!(did_drop.load(Ordering::SeqCst));
+        assert_eq!(PgMemoryContexts::CurrentMemoryContext.value(), outer);
+    }
+
+    #[pg_test]
+    fn test_run_in_per_tuple_context_error() {
+        let outer = PgMemoryContexts::CurrentMemoryContext.value();
+        PgTryBuilder::new(|| run_in_per_tuple_context(|| error!("failed processing a row")))
+            .catch_others(|_| {})
+            .execute();
+        assert_eq!(PgMemoryContexts::CurrentMemoryContext.value(), outer);
+
+        // the context is usable again
+        let context = run_in_per_tuple_context(|| PgMemoryContexts::CurrentMemoryContext.value());
+        assert_ne!(context, outer);
+    }
 }
@@ -0,0 +1,96 @@
+/*
+Portions Copyright 2019-2021 ZomboDB, LLC.
+Portions Copyright 2021-2022 Technology Concepts & Design, Inc. <support@tcdi.com>
+
+All rights reserved.
+
+Use of this source code is governed by the MIT license that can be found in the LICENSE file.
+*/
+use pgrx::metrics::{Counter, Gauge, Histogram, MetricsRegistry};
+use pgrx::prelude::*;
+use pgrx::{pg_shmem_init, PgSharedMemoryInitialization};
+
+static METRICS: MetricsRegistry = MetricsRegistry::new("pgrx_tests");
+static REQUESTS: Counter = Counter::new("pgrx_tests_requests_total", "Requests served");
+static QUEUED: Gauge = Gauge::new("pgrx_tests_queued", "Requests waiting");
+static LATENCY: Histogram =
+    Histogram::new("pgrx_tests_latency_seconds", "How long requests took", &[0.1, 1.0]);
+
+pgrx::metrics_functions!(METRICS, fn pgrx_tests_metrics, fn pgrx_tests_prometheus);
+
+pub(crate) fn init() {
+    METRICS.register(&REQUESTS);
+    METRICS.register(&QUEUED);
+    METRICS.register(&LATENCY);
+    pg_shmem_init!(METRICS);
+}
+
+#[cfg(any(test, feature = "pg_test"))]
+#[pgrx::pg_schema]
+mod tests {
+    #[allow(unused_imports)]
+    use crate as pgrx_tests;
+
+    use super::{LATENCY, QUEUED, REQUESTS};
+    use pgrx::prelude::*;
+
+    #[pg_test]
+    fn test_counter() {
+        let before = REQUESTS.get();
+        REQUESTS.inc();
+        REQUESTS.inc_by(2);
+        assert_eq!(REQUESTS.get(), before + 3);
+    }
+
+    #[pg_test]
+    fn test_gauge() {
+        QUEUED.set(5.0);
+        QUEUED.add(-1.5);
+        assert_eq!(QUEUED.get(), 3.5);
+    }
+
+    #[pg_test]
+    fn test_histogram() {
+        let (buckets, sum) = (LATENCY.buckets(), LATENCY.sum());
+        LATENCY.observe(0.05);
+        LATENCY.observe(0.5);
+        LATENCY.observe(0.5);
+        LATENCY.observe(10.0);
+        let after = LATENCY.buckets();
+        assert_eq!(after[0] - buckets[0], 1);
+        assert_eq!(after[1] - buckets[1], 3);
+        assert_eq!(after[2] - buckets[2], 4);
+        assert_eq!(LATENCY.count(), after[2]);
+        assert!((LATENCY.sum() - sum - 11.05).abs() < 1e-9);
+    }
+
+    #[pg_test]
+    fn test_metrics_function() {
+        let kind = Spi::get_one::<String>(
+            "SELECT kind FROM pgrx_tests_metrics() WHERE name = 'pgrx_tests_queued'",
+        );
+        assert_eq!(kind, Ok(Some("gauge".to_string())));
+        let buckets = Spi::get_one::<i64>(
+            "SELECT count(*) FROM pgrx_tests_metrics() \
+             WHERE name = 'pgrx_tests_latency_seconds_bucket' AND kind = 'histogram'",
+        );
+        assert_eq!(buckets, Ok(Some(3)));
+        let inf = Spi::get_one::<bool>(
+            "SELECT le = 'Infinity' FROM pgrx_tests_metrics() \
+             WHERE name = 'pgrx_tests_latency_seconds_bucket' ORDER BY le DESC LIMIT 1",
+        );
+        assert_eq!(inf, Ok(Some(true)));
+    }
+
+    #[pg_test]
+    fn test_prometheus_text() {
+        let text = Spi::get_one::<String>("SELECT pgrx_tests_prometheus()").unwrap().unwrap();
+        assert!(text.contains("# HELP pgrx_tests_requests_total Requests served\n"), "{text}");
+        assert!(text.contains("# TYPE pgrx_tests_requests_total counter\n"), "{text}");
+        assert!(text.contains("\npgrx_tests_queued "), "{text}");
+        assert!(text.contains("# TYPE pgrx_tests_latency_seconds histogram\n"), "{text}");
+        assert!(text.contains("pgrx_tests_latency_seconds_bucket{le=\"0.1\"} "), "{text}");
+        assert!(text.contains("pgrx_tests_latency_seconds_bucket{le=\"+Inf\"} "), "{text}");
+        assert!(text.contains("\npgrx_tests_latency_seconds_count "), "{text}");
+    }
+}
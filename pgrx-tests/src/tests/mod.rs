@@ -9,46 +9,109 @@ Use of this source code is governed by the MIT license that can be found in the
 
 mod aggregate_tests;
 mod anyarray_tests;
+mod anyrecord_tests;
 mod array_tests;
+mod asset_tests;
 mod attributes_tests;
+mod backend_tests;
 mod bgworker_tests;
+mod binary_copy_tests;
+mod bitmapset_tests;
 mod bytea_tests;
+mod catalog_tests;
+mod cdc_tests;
+mod checkpoint_tests;
 mod cfg_tests;
+mod compat_tests;
+mod composite_derive_tests;
+#[cfg(feature = "conversion-stats")]
+mod conversion_stats_tests;
+mod cost_tests;
+#[cfg(feature = "cshim")]
+mod custom_scan_tests;
+mod data_dir_tests;
 mod datetime_tests;
 mod default_arg_value_tests;
+#[cfg(feature = "cshim")]
+mod deferred_tests;
 mod derive_pgtype_lifetimes;
+mod dsm_tests;
+mod dest_receiver_tests;
+mod encrypted_tests;
 mod enum_type_tests;
+mod event_trigger_tests;
+mod explain_tests;
+mod extended_stats_tests;
 mod fcinfo_tests;
+mod fdw_tests;
+mod feature_flags_tests;
+mod float_tests;
 mod from_into_datum_tests;
 mod geo_tests;
 mod guc_tests;
+mod hash_tests;
 mod heap_tuple;
 #[cfg(feature = "cshim")]
 mod hooks_tests;
+mod index_am_tests;
 mod inet_tests;
 mod internal_tests;
 mod json_tests;
 mod lifetime_tests;
+mod lock_tests;
 mod log_tests;
+mod logical_replication_tests;
+mod matview_tests;
 mod memcxt_tests;
+mod metrics_tests;
+mod multirange_tests;
 mod name_tests;
 mod numeric_tests;
+mod owned_datum_tests;
+#[cfg(feature = "cshim")]
+mod pathlist_tests;
 mod pg_extern_tests;
 mod pg_guard_tests;
 mod pg_try_tests;
 mod pgbox_tests;
 mod pgrx_module_qualification;
+mod portal_tests;
 mod postgres_type_tests;
 mod range_tests;
+mod rate_limit_tests;
+mod rel_tests;
+mod relfork_tests;
 mod result_tests;
+mod roaring_tests;
 mod schema_tests;
+mod selectivity_tests;
+mod session_tests;
 mod shmem_tests;
+mod sketch_tests;
 mod spi_tests;
 mod srf_tests;
+mod stats_import_tests;
+mod storage_maps_tests;
+mod stringinfo_tests;
 mod struct_type_tests;
+mod table_rewrite_tests;
+mod tenancy_tests;
+mod text_search_tests;
+mod timeout_tests;
 mod trigger_tests;
+mod tupdesc_tests;
+mod tuplesort_tests;
+mod typed_list_tests;
 mod uuid_tests;
+mod vacuum_tests;
+mod value_tests;
+mod version_tests;
+mod versioned_type_tests;
 mod variadic_tests;
+mod varlena_tests;
+mod volatility_tests;
+mod wal_tests;
+mod window_tests;
 mod xact_callback_tests;
 mod xid64_tests;
 mod zero_datum_edge_cases;
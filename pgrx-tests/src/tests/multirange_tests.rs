@@ -0,0 +1,91 @@
+/*
+Portions Copyright 2019-2021 ZomboDB, LLC.
+Portions Copyright 2021-2022 Technology Concepts & Design, Inc. <support@tcdi.com>
+
+All rights reserved.
+
+Use of this source code is governed by the MIT license that can be found in the LICENSE file.
+*/
+
+#[cfg(all(any(test, feature = "pg_test"), any(feature = "pg14", feature = "pg15")))]
+#[pgrx::pg_schema]
+mod tests {
+    #[allow(unused_imports)]
+    use crate as pgrx_tests;
+
+    use pgrx::prelude::*;
+
+    #[pg_extern]
+    fn accept_multirange_i32(multirange: MultiRange<i32>) -> MultiRange<i32> {
+        multirange
+    }
+
+    #[pg_extern]
+    fn accept_multirange_date(multirange: MultiRange<Date>) -> MultiRange<Date> {
+        multirange
+    }
+
+    #[pg_extern]
+    fn multirange_i64_counts(multiranges: Vec<MultiRange<i64>>) -> Vec<i64> {
+        multiranges.iter().map(|multirange| multirange.len() as i64).collect()
+    }
+
+    #[pg_extern]
+    fn make_multirange_i64(lower: i64, upper: i64) -> MultiRange<i64> {
+        (lower..upper).step_by(10).map(|start| Range::from(start..start + 5)).collect()
+    }
+
+    #[pg_test]
+    fn test_multirange_round_trip() {
+        let matched = Spi::get_one::<bool>(
+            "SELECT accept_multirange_i32(int4multirange'{[1,5), [10,20)}') = int4multirange'{[1,5), [10,20)}'",
+        );
+        assert_eq!(matched, Ok(Some(true)));
+
+        let matched = Spi::get_one::<bool>(
+            "SELECT accept_multirange_date(datemultirange'{(,2020-01-01], [2021-01-01,)}') = datemultirange'{(,2020-01-01], [2021-01-01,)}'",
+        );
+        assert_eq!(matched, Ok(Some(true)));
+
+        let matched =
+            Spi::get_one::<bool>("SELECT accept_multirange_i32('{}') = int4multirange'{}'");
+        assert_eq!(matched, Ok(Some(true)));
+    }
+
+    #[pg_test]
+    fn test_multirange_ranges() {
+        let multirange =
+            Spi::get_one::<MultiRange<i32>>("SELECT int4multirange'{[10,20), [1,5), empty}'")
+                .unwrap()
+                .unwrap();
+        assert_eq!(
+            multirange.iter().cloned().collect::<Vec<_>>(),
+            vec![
+                Range::new(1, RangeBound::Exclusive(5)),
+                Range::new(10, RangeBound::Exclusive(20))
+            ]
+        );
+    }
+
+    #[pg_test]
+    fn test_multirange_normalized() {
+        let multirange = MultiRange::new(vec![
+            Range::<i32>::from(10..20),
+            Range::from(1..5),
+            Range::from(4..8),
+            Range::empty(),
+        ]);
+        let normalized = unsafe {
+            MultiRange::<i32>::from_datum(multirange.into_datum().unwrap(), false).unwrap()
+        };
+        assert_eq!(normalized, MultiRange::new(vec![Range::from(1..8), Range::from(10..20)]));
+    }
+
+    #[pg_test]
+    fn test_multirange_arrays() {
+        let counts = Spi::get_one::<Vec<i64>>(
+            "SELECT multirange_i64_counts(ARRAY[make_multirange_i64(0, 30), int8multirange'{}'])",
+        );
+        assert_eq!(counts, Ok(Some(vec![3, 0])));
+    }
+}
@@ -0,0 +1,92 @@
+/*
+Portions Copyright 2019-2021 ZomboDB, LLC.
+Portions Copyright 2021-2022 Technology Concepts & Design, Inc. <support@tcdi.com>
+
+All rights reserved.
+
+Use of this source code is governed by the MIT license that can be found in the LICENSE file.
+*/
+
+#[cfg(any(test, feature = "pg_test"))]
+#[pgrx::pg_schema]
+mod tests {
+    #[allow(unused_imports)]
+    use crate as pgrx_tests;
+
+    use pgrx::datum::TryFromDatumError;
+    use pgrx::prelude::*;
+    use pgrx::{OwnedDatum, PgMemoryContexts};
+
+    #[pg_test]
+    fn test_heterogeneous_values() {
+        let values = vec![
+            OwnedDatum::new(42i64).unwrap(),
+            OwnedDatum::new("Nami").unwrap(),
+            OwnedDatum::new(AnyNumeric::try_from(1.5f64).unwrap()).unwrap(),
+            OwnedDatum::new(pgrx::Uuid::from_bytes([7; 16])).unwrap(),
+        ];
+        assert_eq!(
+            values.iter().map(OwnedDatum::type_oid).collect::<Vec<_>>(),
+            [pg_sys::INT8OID, pg_sys::TEXTOID, pg_sys::NUMERICOID, pg_sys::UUIDOID]
+        );
+        assert_eq!(values[0].get::<i64>(), Ok(42));
+        assert_eq!(values[1].get::<String>(), Ok("Nami".to_string()));
+        assert_eq!(values[2].get::<AnyNumeric>(), Ok(AnyNumeric::try_from(1.5f64).unwrap()));
+        assert_eq!(values[3].get::<pgrx::Uuid>(), Ok(pgrx::Uuid::from_bytes([7; 16])));
+    }
+
+    #[pg_test]
+    fn test_null() {
+        assert!(OwnedDatum::new(None::<i32>).is_none());
+    }
+
+    #[pg_test]
+    fn test_incompatible_type() {
+        let value = OwnedDatum::new("Nami").unwrap();
+        assert!(matches!(value.get::<i32>(), Err(TryFromDatumError::IncompatibleTypes { .. })));
+    }
+
+    #[pg_test]
+    fn test_outlives_source() {
+        let mut context = PgMemoryContexts::new("source");
+        let value = unsafe {
+            let datum = context.switch_to(|_| "Brandy".to_string().into_datum()).unwrap();
+            OwnedDatum::copy_from(&PgMemoryContexts::CurrentMemoryContext, datum, pg_sys::TEXTOID)
+        };
+        drop(context);
+        assert_eq!(value.get::<String>(), Ok("Brandy".to_string()));
+    }
+
+    #[pg_test]
+    fn test_clone() {
+        let value = OwnedDatum::new("Nami").unwrap();
+        let clone = value.clone();
+        assert_ne!(value.as_datum(), clone.as_datum());
+        drop(value);
+        assert_eq!(clone.get::<String>(), Ok("Nami".to_string()));
+    }
+
+    #[pg_test]
+    fn test_detoasts() {
+        Spi::run("CREATE TABLE tests.toasty (t text)").unwrap();
+        Spi::run("ALTER TABLE tests.toasty ALTER t SET STORAGE EXTERNAL").unwrap();
+        Spi::run("INSERT INTO tests.toasty SELECT repeat('x', 10000)").unwrap();
+
+        let value = Spi::connect(|client| {
+            let row = client.select("SELECT t FROM tests.toasty", None, None)?.first();
+            let datum = row.get_datum_by_ordinal(1)?.unwrap();
+            Ok::<_, pgrx::spi::Error>(unsafe {
+                OwnedDatum::copy_from(
+                    &PgMemoryContexts::CurrentMemoryContext,
+                    datum,
+                    pg_sys::TEXTOID,
+                )
+            })
+        })
+        .unwrap();
+        unsafe {
+            assert!(!pgrx::varlena::varatt_is_1b_e(value.as_datum().cast_mut_ptr()));
+        }
+        assert_eq!(value.get::<String>().map(|t| t.len()), Ok(10000));
+    }
+}
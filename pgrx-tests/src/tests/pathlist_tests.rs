@@ -0,0 +1,88 @@
+/*
+Portions Copyright 2019-2021 ZomboDB, LLC.
+Portions Copyright 2021-2022 Technology Concepts & Design, Inc. <support@tcdi.com>
+
+All rights reserved.
+
+Use of this source code is governed by the MIT license that can be found in the LICENSE file.
+*/
+
+#[cfg(any(test, feature = "pg_test"))]
+#[pgrx::pg_schema]
+mod tests {
+    #[allow(unused_imports)]
+    use crate as pgrx_tests;
+
+    use pgrx::pathlist::{index_oid, scan_type, RelPathList};
+    use pgrx::prelude::*;
+    use std::cell::Cell;
+
+    #[derive(Copy, Clone)]
+    enum Shaping {
+        OnlySeqScan,
+        DisableSeqScan,
+        RemoveEverything,
+    }
+
+    thread_local! {
+        static SHAPING: Cell<Shaping> = const { Cell::new(Shaping::OnlySeqScan) };
+        static INDEXES_SEEN: Cell<usize> = const { Cell::new(0) };
+    }
+
+    #[pg_guard]
+    unsafe extern "C" fn shape_paths(
+        _root: *mut pg_sys::PlannerInfo,
+        rel: *mut pg_sys::RelOptInfo,
+        _rti: pg_sys::Index,
+        _rte: *mut pg_sys::RangeTblEntry,
+    ) {
+        let mut paths = RelPathList::from_pg(rel);
+        let seen = paths.iter().filter(|path| index_oid(path).is_some()).count();
+        INDEXES_SEEN.set(INDEXES_SEEN.get() + seen);
+        match SHAPING.get() {
+            Shaping::OnlySeqScan => {
+                paths.retain(|path| scan_type(path) == pg_sys::NodeTag_T_SeqScan);
+                paths.retain_partial(|_| false);
+            }
+            Shaping::DisableSeqScan => {
+                paths.disable(|path| scan_type(path) == pg_sys::NodeTag_T_SeqScan)
+            }
+            Shaping::RemoveEverything => paths.retain(|_| false),
+        }
+    }
+
+    fn explain_with(shaping: Shaping, query: &str) -> String {
+        Spi::run("CREATE TABLE tests.shaped (id int PRIMARY KEY)").unwrap();
+        Spi::run("INSERT INTO tests.shaped SELECT generate_series(1, 10000)").unwrap();
+        Spi::run("ANALYZE tests.shaped").unwrap();
+
+        SHAPING.set(shaping);
+        INDEXES_SEEN.set(0);
+        unsafe {
+            let prev = pg_sys::set_rel_pathlist_hook;
+            pg_sys::set_rel_pathlist_hook = Some(shape_paths);
+            let plan = Spi::explain(query);
+            pg_sys::set_rel_pathlist_hook = prev;
+            plan.unwrap().0[0]["Plan"]["Node Type"].as_str().unwrap().to_string()
+        }
+    }
+
+    #[pg_test]
+    fn test_retain_only_seq_scan() {
+        let query = "SELECT * FROM tests.shaped WHERE id = 42";
+        assert_eq!(explain_with(Shaping::OnlySeqScan, query), "Seq Scan");
+        assert!(INDEXES_SEEN.get() > 0);
+    }
+
+    #[pg_test]
+    fn test_disable_seq_scan() {
+        // this would normally be a seq scan, as it returns nearly every row
+        let query = "SELECT * FROM tests.shaped WHERE id > 10";
+        assert_ne!(explain_with(Shaping::DisableSeqScan, query), "Seq Scan");
+    }
+
+    #[pg_test(error = "cannot remove every path for a relation")]
+    fn test_cannot_remove_every_path() {
+        explain_with(Shaping::RemoveEverything, "SELECT * FROM tests.shaped");
+    }
+}
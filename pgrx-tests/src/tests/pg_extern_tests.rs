@@ -146,4 +146,63 @@ mod tests {
         let result = Spi::get_one::<bool>(r#"SELECT tests."custom_name"()"#);
         assert_eq!(result, Ok(Some(true)));
     }
+
+    #[pg_extern(strict)]
+    fn is_strict(a: i32) -> i32 {
+        a
+    }
+
+    #[pg_test]
+    fn test_strict() {
+        let result =
+            Spi::get_one::<bool>("SELECT proisstrict FROM pg_proc WHERE proname = 'is_strict'");
+        assert_eq!(result, Ok(Some(true)));
+        assert_eq!(Spi::get_one::<i32>("SELECT tests.is_strict(NULL)"), Ok(None));
+    }
+
+    #[pg_extern(called_on_null_input)]
+    fn is_called_on_null_input(a: i32) -> i32 {
+        a
+    }
+
+    #[pg_test]
+    fn test_called_on_null_input() {
+        let result = Spi::get_one::<bool>(
+            "SELECT NOT proisstrict FROM pg_proc WHERE proname = 'is_called_on_null_input'",
+        );
+        assert_eq!(result, Ok(Some(true)));
+
+        // a NULL for a non-`Option` argument returns NULL without calling the function
+        assert_eq!(Spi::get_one::<i32>("SELECT tests.is_called_on_null_input(NULL)"), Ok(None));
+        assert_eq!(Spi::get_one::<i32>("SELECT tests.is_called_on_null_input(42)"), Ok(Some(42)));
+    }
+
+    #[pg_extern]
+    fn mixed_nullability(a: i32, b: Option<i32>) -> i32 {
+        a + b.unwrap_or_default()
+    }
+
+    #[pg_test]
+    fn test_mixed_nullability() {
+        assert_eq!(Spi::get_one::<i32>("SELECT tests.mixed_nullability(1, 2)"), Ok(Some(3)));
+        assert_eq!(Spi::get_one::<i32>("SELECT tests.mixed_nullability(1, NULL)"), Ok(Some(1)));
+        assert_eq!(Spi::get_one::<i32>("SELECT tests.mixed_nullability(NULL, 2)"), Ok(None));
+    }
+
+    #[pg_extern]
+    fn mixed_nullability_setof(a: i32, b: Option<i32>) -> SetOfIterator<'static, i32> {
+        SetOfIterator::new(vec![a, b.unwrap_or_default()].into_iter())
+    }
+
+    #[pg_test]
+    fn test_mixed_nullability_setof() {
+        assert_eq!(
+            Spi::get_one::<i64>("SELECT count(*) FROM tests.mixed_nullability_setof(1, NULL)"),
+            Ok(Some(2))
+        );
+        assert_eq!(
+            Spi::get_one::<i64>("SELECT count(*) FROM tests.mixed_nullability_setof(NULL, 2)"),
+            Ok(Some(0))
+        );
+    }
 }
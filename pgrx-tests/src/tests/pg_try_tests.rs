@@ -328,4 +328,70 @@ mod tests {
         // really just testing that the finally block ran
         assert_eq!(true, finally.load(Ordering::SeqCst));
     }
+
+    #[pg_test]
+    fn test_pg_try_caught_error_fields() {
+        let (sqlstate, message) = pg_try(|| -> (String, String) {
+            Spi::run("SELECT 1 / 0").unwrap();
+            unreachable!("1 / 0 didn't raise an error")
+        })
+        .subtransaction()
+        .catch_when(PgSqlErrorCode::ERRCODE_DIVISION_BY_ZERO, |e| {
+            (e.sql_error_code().sqlstate(), e.message().to_string())
+        })
+        .execute();
+
+        assert_eq!(sqlstate, "22012");
+        assert_eq!(message, "division by zero");
+    }
+
+    #[pg_test]
+    fn test_pg_try_subtransaction_recovers() -> Result<(), pgrx::spi::Error> {
+        Spi::run("CREATE TABLE pg_try_recover (id int PRIMARY KEY)")?;
+        Spi::run("INSERT INTO pg_try_recover VALUES (1)")?;
+
+        let inserted = pg_try(|| {
+            Spi::run("INSERT INTO pg_try_recover VALUES (2)").unwrap();
+            Spi::run("INSERT INTO pg_try_recover VALUES (1)").unwrap();
+            true
+        })
+        .subtransaction()
+        .catch_when(PgSqlErrorCode::ERRCODE_UNIQUE_VIOLATION, |e| {
+            assert_eq!(e.detail(), Some("Key (id)=(1) already exists."));
+            false
+        })
+        .execute();
+        assert!(!inserted);
+
+        // the subtransaction's insert of 2 was rolled back, and the transaction carries on
+        Spi::run("INSERT INTO pg_try_recover VALUES (3)")?;
+        let ids = Spi::get_one::<Vec<i32>>("SELECT array_agg(id ORDER BY id) FROM pg_try_recover")?;
+        assert_eq!(ids, Some(vec![1, 3]));
+        Ok(())
+    }
+
+    #[pg_test]
+    fn test_pg_try_subtransaction_commits() -> Result<(), pgrx::spi::Error> {
+        Spi::run("CREATE TABLE pg_try_commit (id int)")?;
+        let result = pg_try(|| {
+            Spi::run("INSERT INTO pg_try_commit VALUES (1)").unwrap();
+            String::from("allocated in the caller's memory context")
+        })
+        .subtransaction()
+        .catch_others(|e| e.rethrow())
+        .execute();
+
+        assert_eq!(result, "allocated in the caller's memory context");
+        assert_eq!(Spi::get_one::<i64>("SELECT count(*) FROM pg_try_commit")?, Some(1));
+        Ok(())
+    }
+
+    #[pg_test(error = "division by zero")]
+    fn test_pg_try_subtransaction_rethrow() {
+        pg_try(|| Spi::run("SELECT 1 / 0"))
+            .subtransaction()
+            .catch_when(PgSqlErrorCode::ERRCODE_DIVISION_BY_ZERO, |e| e.rethrow())
+            .execute()
+            .unwrap();
+    }
 }
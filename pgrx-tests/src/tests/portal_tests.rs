@@ -0,0 +1,76 @@
+/*
+Portions Copyright 2019-2021 ZomboDB, LLC.
+Portions Copyright 2021-2022 Technology Concepts & Design, Inc. <support@tcdi.com>
+
+All rights reserved.
+
+Use of this source code is governed by the MIT license that can be found in the LICENSE file.
+*/
+
+#[cfg(any(test, feature = "pg_test"))]
+#[pgrx::pg_schema]
+mod tests {
+    #[allow(unused_imports)]
+    use crate as pgrx_tests;
+
+    use pgrx::portal::{active_portals, find_portal, PortalStatus};
+    use pgrx::prelude::*;
+    use pgrx::spi::FetchDirection;
+
+    #[pg_test]
+    fn test_declared_cursor_is_listed() -> Result<(), spi::Error> {
+        Spi::run("DECLARE portal_test_a SCROLL CURSOR FOR SELECT i FROM generate_series(1, 10) i")?;
+        Spi::run("DECLARE portal_test_b NO SCROLL CURSOR WITH HOLD FOR SELECT 1")?;
+
+        let portals = active_portals()?;
+        let names = portals.iter().map(|portal| portal.name.as_str()).collect::<Vec<_>>();
+        assert!(names.contains(&"portal_test_a"));
+        assert!(names.contains(&"portal_test_b"));
+
+        let a = portals.iter().find(|portal| portal.name == "portal_test_a").unwrap();
+        assert_eq!(a.status, PortalStatus::Ready);
+        assert!(a.scrollable);
+        assert!(!a.holdable);
+        assert!(a.at_start);
+        assert_eq!(a.position, 0);
+        assert!(a.statement.as_deref().unwrap().contains("generate_series(1, 10)"));
+
+        let b = find_portal("portal_test_b").unwrap();
+        assert!(b.holdable);
+        assert!(!b.scrollable);
+
+        assert_eq!(find_portal("no_such_portal"), None);
+        Spi::run("CLOSE portal_test_b")
+    }
+
+    #[pg_test]
+    fn test_fetch_from_cursor() -> Result<(), spi::Error> {
+        Spi::run("DECLARE portal_test_c SCROLL CURSOR FOR SELECT i FROM generate_series(1, 10) i")?;
+
+        Spi::connect(|client| {
+            let page = client.fetch_from_cursor("portal_test_c", FetchDirection::Forward(3))?;
+            assert_eq!(
+                page.map(|row| row.get::<i32>(1)).collect::<Result<Vec<_>, _>>()?,
+                vec![Some(1), Some(2), Some(3)]
+            );
+
+            let row = client.fetch_from_cursor("portal_test_c", FetchDirection::Absolute(8))?;
+            assert_eq!(row.first().get_one::<i32>()?, Some(8));
+
+            let row = client.fetch_from_cursor("portal_test_c", FetchDirection::Relative(-2))?;
+            assert_eq!(row.first().get_one::<i32>()?, Some(6));
+
+            let rows = client.fetch_from_cursor("portal_test_c", FetchDirection::Backward(2))?;
+            assert_eq!(rows.len(), 2);
+
+            let missing = client.fetch_from_cursor("no_such_cursor", FetchDirection::Forward(1));
+            assert_eq!(missing.err(), Some(spi::Error::CursorNotFound("no_such_cursor".into())));
+            Ok::<_, spi::Error>(())
+        })?;
+
+        // the cursor is still open, and positioned where the fetches left it
+        let portal = find_portal("portal_test_c").unwrap();
+        assert_eq!(portal.position, 4);
+        Ok(())
+    }
+}
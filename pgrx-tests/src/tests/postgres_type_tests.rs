@@ -152,6 +152,34 @@ pub enum JsonEnumType {
     E2 { b: f32 },
 }
 
+#[derive(Debug, PartialEq, Serialize, Deserialize, PostgresType)]
+#[fmt_inoutfuncs]
+pub struct FmtVersionType {
+    major: u16,
+    minor: u16,
+}
+
+impl std::fmt::Display for FmtVersionType {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "v{}.{}", self.major, self.minor)
+    }
+}
+
+impl FromStr for FmtVersionType {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let (major, minor) = s
+            .strip_prefix('v')
+            .and_then(|s| s.split_once('.'))
+            .ok_or_else(|| "expected vMAJOR.MINOR".to_string())?;
+        Ok(FmtVersionType {
+            major: major.parse().map_err(|_| format!("bad major version `{}`", major))?,
+            minor: minor.parse().map_err(|_| format!("bad minor version `{}`", minor))?,
+        })
+    }
+}
+
 #[cfg(any(test, feature = "pg_test"))]
 #[pgrx::pg_schema]
 mod tests {
@@ -159,8 +187,8 @@ mod tests {
     use crate as pgrx_tests;
 
     use crate::tests::postgres_type_tests::{
-        CustomTextFormatSerializedEnumType, CustomTextFormatSerializedType, JsonEnumType, JsonType,
-        VarlenaEnumType, VarlenaType,
+        CustomTextFormatSerializedEnumType, CustomTextFormatSerializedType, FmtVersionType,
+        JsonEnumType, JsonType, VarlenaEnumType, VarlenaType,
     };
     use pgrx::prelude::*;
     use pgrx::PgVarlena;
@@ -253,4 +281,19 @@ mod tests {
         assert!(matches!(result, JsonEnumType::E1 { a } if a == 1.0));
         Ok(())
     }
+
+    #[pg_test]
+    fn test_fmt_type() -> Result<(), pgrx::spi::Error> {
+        let result = Spi::get_one::<FmtVersionType>("SELECT 'v1.12'::FmtVersionType")?;
+        assert_eq!(result, Some(FmtVersionType { major: 1, minor: 12 }));
+
+        let text = Spi::get_one::<String>("SELECT 'v3.4'::FmtVersionType::text")?;
+        assert_eq!(text, Some("v3.4".to_string()));
+        Ok(())
+    }
+
+    #[pg_test(error = "invalid input syntax for type fmtversiontype: \"1.2\"")]
+    fn test_fmt_type_invalid_input() -> Result<(), pgrx::spi::Error> {
+        Spi::get_one::<FmtVersionType>("SELECT '1.2'::FmtVersionType").map(|_| ())
+    }
 }
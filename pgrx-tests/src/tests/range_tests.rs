@@ -344,4 +344,48 @@ mod tests {
             Spi::get_one::<bool>("SELECT range_date_rt_bounds(daterange'(,)') = daterange'(,)'");
         assert_eq!(matched, Ok(Some(true)));
     }
+
+    #[pg_test]
+    fn test_range_contains() {
+        let range = Range::<i32>::new(1, RangeBound::Exclusive(5));
+        assert!(range.contains(&1));
+        assert!(range.contains(&4));
+        assert!(!range.contains(&5));
+        assert!(!range.contains(&0));
+
+        let range = Range::<i64>::new(RangeBound::Exclusive(1), RangeBound::Infinite);
+        assert!(!range.contains(&1));
+        assert!(range.contains(&i64::MAX));
+
+        let range = Range::<AnyNumeric>::new(
+            RangeBound::Exclusive(AnyNumeric::from(1)),
+            RangeBound::Inclusive(AnyNumeric::from(2)),
+        );
+        assert!(range.contains(&AnyNumeric::try_from(1.5f64).unwrap()));
+        assert!(range.contains(&AnyNumeric::from(2)));
+        assert!(!range.contains(&AnyNumeric::from(1)));
+
+        assert!(Range::<i32>::new(RangeBound::Infinite, RangeBound::Infinite).contains(&i32::MIN));
+        assert!(!Range::<i32>::empty().contains(&0));
+    }
+
+    #[pg_test]
+    fn test_range_contains_range() {
+        // `[1,3]` is canonicalized to `[1,4)`
+        let range = Range::<i32>::new(1, RangeBound::Exclusive(4));
+        assert!(range.contains_range(&Range::new(1, 3)));
+        assert!(!range.contains_range(&Range::new(1, 4)));
+        assert!(range.contains_range(&Range::empty()));
+        assert!(!Range::empty().contains_range(&range));
+        assert!(Range::new(RangeBound::Infinite, RangeBound::Infinite).contains_range(&range));
+    }
+
+    #[pg_test]
+    fn test_range_overlaps() {
+        let range = Range::<i64>::new(1, RangeBound::Exclusive(5));
+        assert!(range.overlaps(&Range::new(4, 10)));
+        assert!(!range.overlaps(&Range::new(5, 10)));
+        assert!(range.overlaps(&Range::new(RangeBound::Infinite, RangeBound::Inclusive(1))));
+        assert!(!range.overlaps(&Range::empty()));
+    }
 }
@@ -0,0 +1,98 @@
+/*
+Portions Copyright 2019-2021 ZomboDB, LLC.
+Portions Copyright 2021-2022 Technology Concepts & Design, Inc. <support@tcdi.com>
+
+All rights reserved.
+
+Use of this source code is governed by the MIT license that can be found in the LICENSE file.
+*/
+use pgrx::prelude::*;
+use pgrx::rate_limit::{CircuitBreaker, RateLimiter};
+use pgrx::{pg_shmem_init, PgSharedMemoryInitialization};
+use std::time::Duration;
+
+static LIMITER: RateLimiter = RateLimiter::new("pgrx_tests.limiter", 1.0, 3);
+static BREAKER: CircuitBreaker =
+    CircuitBreaker::new("pgrx_tests.breaker", 2, Duration::from_secs(3600));
+static QUICK_BREAKER: CircuitBreaker =
+    CircuitBreaker::new("pgrx_tests.quick_breaker", 1, Duration::ZERO);
+
+pub(crate) fn init() {
+    pg_shmem_init!(LIMITER);
+    pg_shmem_init!(BREAKER);
+    pg_shmem_init!(QUICK_BREAKER);
+}
+
+#[cfg(any(test, feature = "pg_test"))]
+#[pgrx::pg_schema]
+mod tests {
+    #[allow(unused_imports)]
+    use crate as pgrx_tests;
+
+    use super::{BREAKER, LIMITER, QUICK_BREAKER};
+    use pgrx::prelude::*;
+    use pgrx::rate_limit::{CircuitBreakerError, CircuitState, RateLimiter};
+
+    #[pg_test]
+    fn test_rate_limiter() {
+        LIMITER.reset();
+        assert_eq!(LIMITER.available(), 3);
+        assert!(LIMITER.try_acquire_n(2));
+        assert!(LIMITER.try_acquire());
+        assert!(!LIMITER.try_acquire());
+        assert_eq!(LIMITER.available(), 0);
+        assert!(!LIMITER.try_acquire_n(4));
+    }
+
+    #[pg_test]
+    fn test_rate_limiter_refills() {
+        LIMITER.reset();
+        assert!(LIMITER.try_acquire_n(3));
+        // blocks for about a second, until a token is refilled
+        LIMITER.acquire();
+        assert!(!LIMITER.try_acquire());
+    }
+
+    #[pg_test]
+    fn test_circuit_breaker_opens() {
+        BREAKER.reset();
+        assert_eq!(BREAKER.call(|| Ok::<_, String>(1)), Ok(1));
+        assert_eq!(
+            BREAKER.call(|| Err::<i32, _>("down")),
+            Err(CircuitBreakerError::Failed("down"))
+        );
+        assert_eq!(BREAKER.state(), CircuitState::Closed);
+        assert_eq!(BREAKER.failures(), 1);
+        assert_eq!(
+            BREAKER.call(|| Err::<i32, _>("down")),
+            Err(CircuitBreakerError::Failed("down"))
+        );
+        assert_eq!(BREAKER.state(), CircuitState::Open);
+        assert!(!BREAKER.allow());
+        assert_eq!(BREAKER.call(|| Ok::<_, &str>(1)), Err(CircuitBreakerError::Open));
+        BREAKER.reset();
+        assert_eq!(BREAKER.state(), CircuitState::Closed);
+        assert_eq!(BREAKER.failures(), 0);
+    }
+
+    #[pg_test]
+    fn test_circuit_breaker_trial_call() {
+        QUICK_BREAKER.reset();
+        QUICK_BREAKER.record_failure();
+        assert_eq!(QUICK_BREAKER.state(), CircuitState::Open);
+        // the cooldown has passed, so one trial call is let through
+        assert!(QUICK_BREAKER.allow());
+        assert_eq!(QUICK_BREAKER.state(), CircuitState::HalfOpen);
+        QUICK_BREAKER.record_failure();
+        assert_eq!(QUICK_BREAKER.state(), CircuitState::Open);
+        assert!(QUICK_BREAKER.allow());
+        QUICK_BREAKER.record_success();
+        assert_eq!(QUICK_BREAKER.state(), CircuitState::Closed);
+    }
+
+    #[pg_test(error = "a rate limiter's per_second must be greater than zero")]
+    fn test_rate_limiter_rejects_zero_rate() {
+        let per_second = 0.0;
+        RateLimiter::new("tests.never_refilled", per_second, 1);
+    }
+}
@@ -0,0 +1,85 @@
+/*
+Portions Copyright 2019-2021 ZomboDB, LLC.
+Portions Copyright 2021-2022 Technology Concepts & Design, Inc. <support@tcdi.com>
+
+All rights reserved.
+
+Use of this source code is governed by the MIT license that can be found in the LICENSE file.
+*/
+
+#[cfg(any(test, feature = "pg_test"))]
+#[pgrx::pg_schema]
+mod tests {
+    #[allow(unused_imports)]
+    use crate as pgrx_tests;
+
+    use pgrx::prelude::*;
+    use pgrx::rel::{ColumnSpec, OnCommit};
+    use pgrx::PgRelation;
+
+    #[pg_test]
+    fn test_create_temp() {
+        let rel = PgRelation::create_temp(
+            "scratch",
+            &[
+                ColumnSpec::new::<i64>("id").not_null(),
+                ColumnSpec::new::<String>("Label"),
+                ColumnSpec::with_type_oid("code", pg_sys::VARCHAROID).typmod(10 + 4),
+            ],
+        )
+        .unwrap();
+        assert!(rel.is_temp());
+        assert!(!rel.is_unlogged());
+        assert_eq!(rel.name(), "scratch");
+        assert_eq!(rel.tuple_desc().len(), 3);
+
+        Spi::run("INSERT INTO scratch VALUES (1, 'one', 'abc')").unwrap();
+        let count = Spi::get_one::<i64>("SELECT count(*) FROM pg_temp.scratch").unwrap();
+        assert_eq!(count, Some(1));
+
+        let types = Spi::get_one::<String>(
+            "SELECT string_agg(format_type(atttypid, atttypmod), ', ' ORDER BY attnum) \
+               FROM pg_attribute WHERE attrelid = 'scratch'::regclass AND attnum > 0",
+        )
+        .unwrap();
+        assert_eq!(types.as_deref(), Some("bigint, text, character varying(10)"));
+    }
+
+    #[pg_test]
+    fn test_create_temp_not_null() {
+        let rel = PgRelation::create_temp(
+            "scratch_not_null",
+            &[ColumnSpec::new::<i32>("id").not_null(), ColumnSpec::new::<i32>("other")],
+        )
+        .unwrap();
+        let tupdesc = rel.tuple_desc();
+        assert!(tupdesc.get(0).unwrap().attnotnull);
+        assert!(!tupdesc.get(1).unwrap().attnotnull);
+    }
+
+    #[pg_test]
+    fn test_create_temp_on_commit() {
+        let rel = PgRelation::create_temp_on_commit(
+            "scratch_on_commit",
+            &[ColumnSpec::new::<i32>("id")],
+            OnCommit::Drop,
+        )
+        .unwrap();
+        assert!(rel.is_temp());
+        let exists = Spi::get_one::<bool>(
+            "SELECT EXISTS (SELECT 1 FROM pg_class WHERE relname = 'scratch_on_commit')",
+        )
+        .unwrap();
+        assert_eq!(exists, Some(true));
+    }
+
+    #[pg_test]
+    fn test_create_unlogged() {
+        let rel = PgRelation::create_unlogged("scratch_unlogged", &[ColumnSpec::new::<i32>("id")])
+            .unwrap();
+        assert!(rel.is_unlogged());
+        assert!(!rel.is_temp());
+        let schema = Spi::get_one::<String>("SELECT current_schema()::text").unwrap();
+        assert_eq!(Some(rel.namespace()), schema.as_deref());
+    }
+}
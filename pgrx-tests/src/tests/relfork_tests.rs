@@ -0,0 +1,79 @@
+/*
+Portions Copyright 2019-2021 ZomboDB, LLC.
+Portions Copyright 2021-2022 Technology Concepts & Design, Inc. <support@tcdi.com>
+
+All rights reserved.
+
+Use of this source code is governed by the MIT license that can be found in the LICENSE file.
+*/
+
+#[cfg(any(test, feature = "pg_test"))]
+#[pgrx::pg_schema]
+mod tests {
+    #[allow(unused_imports)]
+    use crate as pgrx_tests;
+
+    use pgrx::prelude::*;
+    use pgrx::relfork::{Fork, RelationFork};
+    use pgrx::PgRelation;
+
+    fn relation() -> PgRelation {
+        Spi::run("CREATE TABLE tests.relfork_test (id int)").unwrap();
+        PgRelation::open_with_name_and_share_lock("tests.relfork_test").unwrap()
+    }
+
+    #[pg_test]
+    fn test_relfork_extend_and_read() {
+        let relation = relation();
+        let fork = RelationFork::new(&relation, Fork::Main);
+        assert_eq!(0, fork.blocks());
+
+        let mut block = fork.extend();
+        block.init(16);
+        block.special_mut().copy_from_slice(&[42; 16]);
+        assert_eq!(0, block.finish());
+
+        assert_eq!(1, fork.blocks());
+        let block = fork.read(0);
+        assert_eq!(0, block.block_number());
+        assert_eq!(&[42; 16], block.special());
+        drop(block);
+
+        assert_eq!(
+            Ok(Some(pg_sys::BLCKSZ as i64)),
+            Spi::get_one::<i64>("SELECT pg_relation_size('tests.relfork_test')")
+        );
+    }
+
+    #[pg_test]
+    fn test_relfork_unfinished_write_is_discarded() {
+        let relation = relation();
+        let fork = RelationFork::new(&relation, Fork::Main);
+        let mut block = fork.extend();
+        block.init(4);
+        block.special_mut().copy_from_slice(b"pgrx");
+        block.finish();
+
+        let mut block = fork.write(0);
+        block.special_mut().copy_from_slice(b"oops");
+        drop(block);
+        assert_eq!(b"pgrx", fork.read(0).special());
+
+        let mut block = fork.write(0);
+        block.special_mut().copy_from_slice(b"done");
+        block.finish();
+        assert_eq!(b"done", fork.read(0).special());
+    }
+
+    #[pg_test]
+    fn test_relfork_create() {
+        let relation = relation();
+        assert!(RelationFork::new(&relation, Fork::Main).exists());
+
+        let fsm = RelationFork::new(&relation, Fork::FreeSpaceMap);
+        assert!(!fsm.exists());
+        fsm.create();
+        assert!(fsm.exists());
+        assert_eq!(0, fsm.blocks());
+    }
+}
@@ -0,0 +1,224 @@
+/*
+Portions Copyright 2019-2021 ZomboDB, LLC.
+Portions Copyright 2021-2022 Technology Concepts & Design, Inc. <support@tcdi.com>
+
+All rights reserved.
+
+Use of this source code is governed by the MIT license that can be found in the LICENSE file.
+*/
+use pgrx::prelude::*;
+use pgrx::roaring::RoaringBitmap;
+use pgrx::{Aggregate, Internal};
+
+#[pg_extern]
+fn rb_build(values: Array<i32>) -> RoaringBitmap {
+    values.iter().flatten().map(|value| value as u32).collect()
+}
+
+#[pg_extern]
+fn rb_to_array(bitmap: RoaringBitmap) -> Vec<i64> {
+    bitmap.iter().map(|value| value as i64).collect()
+}
+
+#[pg_operator(immutable, parallel_safe)]
+#[opname(&)]
+fn rb_and(left: RoaringBitmap, right: RoaringBitmap) -> RoaringBitmap {
+    &left & &right
+}
+
+#[pg_operator(immutable, parallel_safe)]
+#[opname(|)]
+fn rb_or(left: RoaringBitmap, right: RoaringBitmap) -> RoaringBitmap {
+    &left | &right
+}
+
+#[pg_operator(immutable, parallel_safe)]
+#[opname(#)]
+fn rb_xor(left: RoaringBitmap, right: RoaringBitmap) -> RoaringBitmap {
+    &left ^ &right
+}
+
+#[pg_operator(immutable, parallel_safe)]
+#[opname(@>)]
+fn rb_contains(bitmap: RoaringBitmap, value: i32) -> bool {
+    bitmap.contains(value as u32)
+}
+
+pub struct RbOrAgg;
+
+#[pg_aggregate]
+impl Aggregate for RbOrAgg {
+    const NAME: &'static str = "rb_or_agg";
+    type Args = RoaringBitmap;
+    type State = Internal;
+    type Finalize = RoaringBitmap;
+
+    fn state(
+        mut current: Self::State,
+        arg: Self::Args,
+        _fcinfo: pg_sys::FunctionCallInfo,
+    ) -> Self::State {
+        *unsafe { current.get_or_insert_default::<RoaringBitmap>() } |= &arg;
+        current
+    }
+
+    fn finalize(
+        mut current: Self::State,
+        _direct_args: Self::OrderedSetArgs,
+        _fcinfo: pg_sys::FunctionCallInfo,
+    ) -> Self::Finalize {
+        unsafe { current.get_or_insert_default::<RoaringBitmap>() }.clone()
+    }
+}
+
+#[cfg(any(test, feature = "pg_test"))]
+#[pgrx::pg_schema]
+mod tests {
+    #[allow(unused_imports)]
+    use crate as pgrx_tests;
+
+    use pgrx::prelude::*;
+    use pgrx::roaring::{RoaringBitmap, RoaringError};
+    use std::collections::BTreeSet;
+
+    /// Values that fill some chunks densely enough to be stored as bitmaps, and others sparsely
+    fn mixed(step: u32) -> BTreeSet<u32> {
+        (0..20_000u32).map(|i| i * step).chain((0..3u32).map(|i| u32::MAX - i * 7)).collect()
+    }
+
+    #[pg_test]
+    fn test_roaring_bitmap_set() {
+        let expected = mixed(3);
+        let mut bitmap: RoaringBitmap = expected.iter().copied().collect();
+        assert_eq!(expected.len() as u64, bitmap.len());
+        assert!(expected.iter().all(|value| bitmap.contains(*value)));
+        assert!(!bitmap.contains(1));
+        assert_eq!(expected.iter().copied().collect::<Vec<_>>(), bitmap.iter().collect::<Vec<_>>());
+        assert_eq!(Some(0), bitmap.min());
+        assert_eq!(Some(u32::MAX), bitmap.max());
+
+        assert!(!bitmap.insert(3));
+        assert!(bitmap.insert(4));
+        assert!(bitmap.remove(4));
+        assert!(!bitmap.remove(4));
+        assert_eq!(expected.iter().copied().collect::<RoaringBitmap>(), bitmap);
+
+        // removing values until a chunk is sparse again stores it as an array, so it's still equal
+        for value in expected.iter().filter(|value| (3000..65536).contains(*value)) {
+            bitmap.remove(*value);
+        }
+        let sparse: RoaringBitmap =
+            expected.iter().copied().filter(|value| !(3000..65536).contains(value)).collect();
+        assert_eq!(sparse, bitmap);
+
+        let empty = RoaringBitmap::new();
+        assert!(empty.is_empty());
+        assert_eq!(None, empty.min());
+        assert_eq!(None, empty.max());
+    }
+
+    #[pg_test]
+    fn test_roaring_bitmap_set_operations() {
+        for (left, right) in
+            [(mixed(2), mixed(3)), (mixed(5), mixed(7)), (mixed(2), BTreeSet::new())]
+        {
+            let (a, b): (RoaringBitmap, RoaringBitmap) =
+                (left.iter().copied().collect(), right.iter().copied().collect());
+            let check = |bitmap: RoaringBitmap, expected: BTreeSet<u32>| {
+                assert_eq!(
+                    expected.into_iter().collect::<Vec<_>>(),
+                    bitmap.iter().collect::<Vec<_>>()
+                );
+            };
+            check(&a & &b, left.intersection(&right).copied().collect());
+            check(&a | &b, left.union(&right).copied().collect());
+            check(&a ^ &b, left.symmetric_difference(&right).copied().collect());
+            check(&a - &b, left.difference(&right).copied().collect());
+            assert_eq!(left.is_subset(&right), a.is_subset(&b));
+            assert_eq!(!left.is_disjoint(&right), a.intersects(&b));
+
+            let mut c = a.clone();
+            c |= &b;
+            assert_eq!(&a | &b, c);
+            c -= &b;
+            assert_eq!(&a - &b, c);
+        }
+    }
+
+    #[pg_test]
+    fn test_roaring_bitmap_bytes_round_trip() {
+        for bitmap in
+            [RoaringBitmap::new(), mixed(1).into_iter().collect(), mixed(13).into_iter().collect()]
+        {
+            let bytes = bitmap.to_bytes();
+            assert_eq!(bitmap.serialized_size(), bytes.len());
+            assert_eq!(Ok(bitmap), RoaringBitmap::from_bytes(&bytes));
+        }
+
+        let bytes = mixed(2).into_iter().collect::<RoaringBitmap>().to_bytes();
+        assert_eq!(
+            Err(RoaringError::Truncated),
+            RoaringBitmap::from_bytes(&bytes[..bytes.len() - 1])
+        );
+        assert_eq!(Err(RoaringError::UnknownCookie(1)), RoaringBitmap::from_bytes(&[1, 0, 0, 0]));
+    }
+
+    #[pg_test]
+    fn test_roaring_bitmap_portable_format() {
+        // {1, 2, 3, 65536}, as serialized by CRoaring
+        let bytes = [
+            0x3a, 0x30, 0, 0, 2, 0, 0, 0, 0, 0, 2, 0, 1, 0, 0, 0, 24, 0, 0, 0, 30, 0, 0, 0, 1, 0,
+            2, 0, 3, 0, 0, 0,
+        ];
+        let bitmap = RoaringBitmap::from_bytes(&bytes).unwrap();
+        assert_eq!(vec![1, 2, 3, 65536], bitmap.iter().collect::<Vec<_>>());
+        assert_eq!(bytes.to_vec(), bitmap.to_bytes());
+
+        // 10..=1009 in one run-length encoded chunk
+        let bytes = [0x3b, 0x30, 0, 0, 1, 0, 0, 231, 3, 1, 0, 10, 0, 231, 3];
+        let bitmap = RoaringBitmap::from_bytes(&bytes).unwrap();
+        assert_eq!((10..=1009).collect::<Vec<_>>(), bitmap.iter().collect::<Vec<_>>());
+    }
+
+    #[pg_test]
+    fn test_roaring_bitmap_sql() -> Result<(), spi::Error> {
+        assert_eq!(
+            Some(vec![2, 3]),
+            Spi::get_one::<Vec<i64>>(
+                "SELECT rb_to_array(rb_build(ARRAY[1, 2, 3]) & rb_build(ARRAY[2, 3, 4]))"
+            )?
+        );
+        assert_eq!(
+            Some(vec![1, 2, 3, 4]),
+            Spi::get_one::<Vec<i64>>(
+                "SELECT rb_to_array(rb_build(ARRAY[1, 2, 3]) | rb_build(ARRAY[2, 3, 4]))"
+            )?
+        );
+        assert_eq!(
+            Some(vec![1, 4]),
+            Spi::get_one::<Vec<i64>>(
+                "SELECT rb_to_array(rb_build(ARRAY[1, 2, 3]) # rb_build(ARRAY[2, 3, 4]))"
+            )?
+        );
+        assert_eq!(Some(true), Spi::get_one::<bool>("SELECT rb_build(ARRAY[1, 2, 3]) @> 2")?);
+        assert_eq!(Some(false), Spi::get_one::<bool>("SELECT rb_build(ARRAY[1, 2, 3]) @> 4")?);
+        Ok(())
+    }
+
+    #[pg_test]
+    fn test_rb_or_agg() -> Result<(), spi::Error> {
+        let bitmap = Spi::get_one::<RoaringBitmap>(
+            "SELECT rb_or_agg(rb_build(ARRAY[i, i * 100000]))
+               FROM generate_series(1, 10000) i",
+        )?
+        .unwrap();
+        assert_eq!(20000, bitmap.len());
+        assert!((1..=10000u32).all(|i| bitmap.contains(i) && bitmap.contains(i * 100000)));
+        Ok(())
+    }
+
+    #[pg_test(error = "invalid RoaringBitmap: the bytes are too short")]
+    fn test_roaring_bitmap_invalid_bytea() -> Result<(), spi::Error> {
+        Spi::get_one::<RoaringBitmap>("SELECT '\\x3a300000'::bytea").map(|_| ())
+    }
+}
@@ -0,0 +1,82 @@
+/*
+Portions Copyright 2019-2021 ZomboDB, LLC.
+Portions Copyright 2021-2022 Technology Concepts & Design, Inc. <support@tcdi.com>
+
+All rights reserved.
+
+Use of this source code is governed by the MIT license that can be found in the LICENSE file.
+*/
+
+#[cfg(any(test, feature = "pg_test"))]
+#[pgrx::pg_schema]
+mod tests {
+    #[allow(unused_imports)]
+    use crate as pgrx_tests;
+
+    use pgrx::prelude::*;
+    use pgrx::selectivity::ColumnStatistics;
+
+    fn analyzed_table() -> pg_sys::Oid {
+        // `common` is 1 in half the rows, `uniform` is evenly spread over 1..=1000
+        Spi::run("CREATE TABLE tests.sel (common int, uniform int)").unwrap();
+        Spi::run(
+            "INSERT INTO tests.sel \
+             SELECT CASE WHEN i % 2 = 0 THEN 1 ELSE i END, i FROM generate_series(1, 1000) i",
+        )
+        .unwrap();
+        Spi::run("ANALYZE tests.sel").unwrap();
+        Spi::get_one::<pg_sys::Oid>("SELECT 'tests.sel'::regclass::oid").unwrap().unwrap()
+    }
+
+    fn operator(name: &str) -> pg_sys::Oid {
+        Spi::get_one::<pg_sys::Oid>(&format!("SELECT '{name}(int4, int4)'::regoperator::oid"))
+            .unwrap()
+            .unwrap()
+    }
+
+    #[pg_test]
+    fn test_unanalyzed_column() {
+        Spi::run("CREATE TABLE tests.unanalyzed (id int)").unwrap();
+        let relid = Spi::get_one::<pg_sys::Oid>("SELECT 'tests.unanalyzed'::regclass::oid")
+            .unwrap()
+            .unwrap();
+        assert!(ColumnStatistics::lookup_by_name(relid, "id", false).is_none());
+        assert!(ColumnStatistics::lookup_by_name(relid, "missing", false).is_none());
+    }
+
+    #[pg_test]
+    fn test_most_common_values() {
+        let relid = analyzed_table();
+        let stats = ColumnStatistics::lookup_by_name(relid, "common", false).unwrap();
+        assert_eq!(stats.null_frac(), 0.0);
+        assert_eq!(stats.avg_width(), 4);
+
+        let mcv = stats.most_common_values::<i32>().unwrap();
+        assert_eq!(mcv[0], (1, 0.5));
+        assert_eq!(stats.eq_selectivity(operator("="), 1.into(), true), 0.5);
+
+        // the other 500 values are unique
+        let selec = stats.eq_selectivity(operator("="), 3.into(), false);
+        assert!((selec - 0.001).abs() < 0.0001, "selectivity was {selec}");
+    }
+
+    #[pg_test]
+    fn test_histogram() {
+        let relid = analyzed_table();
+        let stats = ColumnStatistics::lookup_by_name(relid, "uniform", false).unwrap();
+        assert!(stats.most_common_values::<i32>().is_none());
+        assert_eq!(stats.n_distinct(), 1000.0);
+
+        let histogram = stats.histogram::<i32>().unwrap();
+        assert_eq!(histogram.first(), Some(&1));
+        assert_eq!(histogram.last(), Some(&1000));
+        assert!(histogram.windows(2).all(|w| w[0] <= w[1]));
+
+        let lt = stats.ineq_selectivity(operator("<"), 250.into(), true);
+        assert!((lt - 0.25).abs() < 0.02, "selectivity was {lt}");
+        let gt = stats.ineq_selectivity(operator("<"), 250.into(), false);
+        assert!((gt - 0.75).abs() < 0.02, "selectivity was {gt}");
+        assert_eq!(stats.ineq_selectivity(operator("<"), 0.into(), true), 0.0);
+        assert_eq!(stats.ineq_selectivity(operator("<"), 2000.into(), true), 1.0);
+    }
+}
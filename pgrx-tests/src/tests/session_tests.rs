@@ -0,0 +1,94 @@
+/*
+Portions Copyright 2019-2021 ZomboDB, LLC.
+Portions Copyright 2021-2022 Technology Concepts & Design, Inc. <support@tcdi.com>
+
+All rights reserved.
+
+Use of this source code is governed by the MIT license that can be found in the LICENSE file.
+*/
+
+#[cfg(any(test, feature = "pg_test"))]
+#[pgrx::pg_schema]
+mod tests {
+    #[allow(unused_imports)]
+    use crate as pgrx_tests;
+
+    use pgrx::prelude::*;
+    use pgrx::session::{on_session_reset, SessionReset, SessionSnapshot, SessionState};
+
+    static RESETS: std::sync::atomic::AtomicUsize = std::sync::atomic::AtomicUsize::new(0);
+
+    fn count_reset(reset: SessionReset) {
+        if reset == SessionReset::ResetAll {
+            RESETS.fetch_add(1, std::sync::atomic::Ordering::SeqCst);
+        }
+    }
+
+    fn work_mem() -> Result<Option<String>, spi::Error> {
+        Spi::get_one("SELECT current_setting('work_mem')")
+    }
+
+    #[pg_test]
+    fn test_snapshot_and_restore() -> Result<(), spi::Error> {
+        let state = SessionState::new();
+        state.set("work_mem", "12MB")?;
+        state.create_temp("session_snap", "CREATE TEMP TABLE session_snap (id int)")?;
+        state.prepare("session_snap_stmt", "SELECT 1")?;
+
+        let snapshot = state.snapshot()?;
+        assert_eq!(snapshot.settings, vec![("work_mem".to_string(), "12MB".to_string())]);
+        assert_eq!(snapshot.temp_objects.len(), 1);
+        assert_eq!(
+            snapshot.prepared_statements,
+            vec![("session_snap_stmt".to_string(), "SELECT 1".to_string())]
+        );
+
+        Spi::run("SET work_mem = '4MB'")?;
+        Spi::run("DROP TABLE session_snap")?;
+        Spi::run("DEALLOCATE session_snap_stmt")?;
+
+        state.restore(&snapshot)?;
+        assert_eq!(work_mem()?.as_deref(), Some("12MB"));
+        assert_eq!(
+            Spi::get_one::<bool>("SELECT to_regclass('pg_temp.session_snap') IS NOT NULL")?,
+            Some(true)
+        );
+        assert_eq!(
+            Spi::get_one::<bool>(
+                "SELECT EXISTS (SELECT 1 FROM pg_prepared_statements WHERE name = 'session_snap_stmt')"
+            )?,
+            Some(true)
+        );
+        Ok(())
+    }
+
+    #[pg_test]
+    fn test_restore_empty_snapshot() -> Result<(), spi::Error> {
+        let state = SessionState::new();
+        state.restore(&SessionSnapshot::default())?;
+        assert_eq!(state.snapshot()?, SessionSnapshot::default());
+        Ok(())
+    }
+
+    #[pg_test]
+    fn test_restore_on_reset() -> Result<(), spi::Error> {
+        static STATE: SessionState = SessionState::new();
+        STATE.restore_on_reset();
+        on_session_reset(count_reset);
+
+        STATE.set("work_mem", "13MB")?;
+        STATE.create_temp("session_reset", "CREATE TEMP TABLE session_reset (id int)")?;
+
+        let before = RESETS.load(std::sync::atomic::Ordering::SeqCst);
+        Spi::run("RESET ALL")?;
+        assert_eq!(work_mem()?.as_deref(), Some("13MB"));
+        assert_eq!(RESETS.load(std::sync::atomic::Ordering::SeqCst), before + 1);
+
+        Spi::run("DISCARD TEMP")?;
+        assert_eq!(
+            Spi::get_one::<bool>("SELECT to_regclass('pg_temp.session_reset') IS NOT NULL")?,
+            Some(true)
+        );
+        Ok(())
+    }
+}
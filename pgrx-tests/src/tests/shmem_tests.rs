@@ -7,17 +7,39 @@ All rights reserved.
 Use of this source code is governed by the MIT license that can be found in the LICENSE file.
 */
 use pgrx::prelude::*;
-use pgrx::{pg_shmem_init, PgAtomic, PgLwLock, PgSharedMemoryInitialization};
-use std::sync::atomic::AtomicBool;
+use pgrx::{
+    pg_shmem, pg_shmem_init, PGRXSharedMemory, PgAtomic, PgLwLock, PgSharedMemoryInitialization,
+};
+use std::sync::atomic::{AtomicBool, AtomicU64};
 
 static ATOMIC: PgAtomic<AtomicBool> = PgAtomic::new();
 static LWLOCK: PgLwLock<bool> = PgLwLock::new();
 
+#[derive(Copy, Clone, Default)]
+pub struct Counters {
+    pub hits: u64,
+    pub misses: u64,
+}
+unsafe impl PGRXSharedMemory for Counters {}
+
+pg_shmem! {
+    static COUNTERS: PgLwLock<Counters>;
+    pub(crate) static CALLS: PgAtomic<AtomicU64>;
+}
+
 #[pg_guard]
 pub extern "C" fn _PG_init() {
     // This ensures that this functionality works across PostgreSQL versions
     pg_shmem_init!(ATOMIC);
     pg_shmem_init!(LWLOCK);
+    pg_shmem_init!(COUNTERS);
+    pg_shmem_init!(CALLS);
+    crate::tests::metrics_tests::init();
+    crate::tests::rate_limit_tests::init();
+    crate::tests::tenancy_tests::init();
+
+    #[cfg(feature = "cshim")]
+    pgrx::deferred::init();
 }
 #[cfg(any(test, feature = "pg_test"))]
 #[pgrx::pg_schema]
@@ -25,8 +47,9 @@ mod tests {
     #[allow(unused_imports)]
     use crate as pgrx_tests;
 
-    use crate::tests::shmem_tests::LWLOCK;
+    use crate::tests::shmem_tests::{CALLS, COUNTERS, LWLOCK};
     use pgrx::prelude::*;
+    use std::sync::atomic::Ordering;
 
     #[pg_test]
     #[should_panic(expected = "cache lookup failed for type 0")]
@@ -52,4 +75,27 @@ mod tests {
         });
         let _lock = LWLOCK.exclusive();
     }
+
+    #[pg_test]
+    pub fn test_pg_shmem_names() {
+        assert_eq!(COUNTERS.get_name(), "pgrx_tests::tests::shmem_tests::COUNTERS");
+        assert_eq!(CALLS.get_name(), "pgrx_tests::tests::shmem_tests::CALLS");
+    }
+
+    #[pg_test]
+    pub fn test_pg_shmem_lwlock() {
+        let hits = COUNTERS.share().hits;
+        {
+            let mut counters = COUNTERS.exclusive();
+            counters.hits += 2;
+            counters.misses += 1;
+        }
+        assert_eq!(COUNTERS.share().hits, hits + 2);
+    }
+
+    #[pg_test]
+    pub fn test_pg_shmem_atomic() {
+        let calls = CALLS.get().fetch_add(1, Ordering::SeqCst);
+        assert_eq!(CALLS.get().load(Ordering::SeqCst), calls + 1);
+    }
 }
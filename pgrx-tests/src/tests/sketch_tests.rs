@@ -0,0 +1,154 @@
+/*
+Portions Copyright 2019-2021 ZomboDB, LLC.
+Portions Copyright 2021-2022 Technology Concepts & Design, Inc. <support@tcdi.com>
+
+All rights reserved.
+
+Use of this source code is governed by the MIT license that can be found in the LICENSE file.
+*/
+use pgrx::prelude::*;
+use pgrx::sketch::HyperLogLog;
+
+#[pg_extern]
+fn sketch_hll(values: Array<i32>) -> HyperLogLog {
+    let mut hll = HyperLogLog::new(12);
+    for value in values.iter() {
+        hll.add(value);
+    }
+    hll
+}
+
+#[pg_extern]
+fn sketch_hll_estimate(hll: HyperLogLog) -> f64 {
+    hll.estimate()
+}
+
+#[cfg(any(test, feature = "pg_test"))]
+#[pgrx::pg_schema]
+mod tests {
+    #[allow(unused_imports)]
+    use crate as pgrx_tests;
+
+    use pgrx::prelude::*;
+    use pgrx::sketch::{BloomFilter, CountMinSketch, HyperLogLog, SketchError};
+
+    #[pg_test]
+    fn test_bloom_filter() {
+        let mut bloom = BloomFilter::new(1000, 0.01);
+        for i in 0..1000i32 {
+            bloom.insert(i);
+        }
+        assert!((0..1000i32).all(|i| bloom.contains(i)));
+
+        let false_positives = (1000..11000i32).filter(|&i| bloom.contains(i)).count();
+        assert!(false_positives < 300, "{false_positives} false positives");
+        assert!(!bloom.contains(Option::<i32>::None));
+    }
+
+    #[pg_test]
+    fn test_bloom_filter_hashes_like_postgres() {
+        // equal text values hash the same however they got here
+        let mut bloom = BloomFilter::new(10, 0.01);
+        bloom.insert(String::from("pgrx"));
+        assert!(bloom.contains("pgrx"));
+        assert!(bloom.contains_datum("pgrx".into_datum().unwrap(), pg_sys::TEXTOID));
+    }
+
+    #[pg_test]
+    fn test_bloom_filter_union() {
+        let mut evens = BloomFilter::with_params(4096, 3);
+        let mut odds = BloomFilter::with_params(4096, 3);
+        (0..100i64).for_each(|i| if i % 2 == 0 { evens.insert(i) } else { odds.insert(i) });
+        evens.union(&odds).unwrap();
+        assert!((0..100i64).all(|i| evens.contains(i)));
+        assert_eq!(Err(SketchError::Incompatible), evens.union(&BloomFilter::with_params(64, 3)));
+    }
+
+    #[pg_test]
+    fn test_hyperloglog() {
+        let mut hll = HyperLogLog::new(12);
+        for i in 0..10000i32 {
+            hll.add(i);
+            hll.add(i);
+        }
+        let estimate = hll.estimate();
+        assert!((estimate - 10000.0).abs() < 500.0, "estimated {estimate}");
+
+        assert_eq!(0.0, HyperLogLog::new(4).estimate());
+    }
+
+    #[pg_test]
+    fn test_hyperloglog_merge() {
+        let mut low = HyperLogLog::new(10);
+        let mut high = HyperLogLog::new(10);
+        (0..500i64).for_each(|i| low.add(i));
+        (500..1000i64).for_each(|i| high.add(i));
+        low.merge(&high).unwrap();
+        let estimate = low.estimate();
+        assert!((estimate - 1000.0).abs() < 100.0, "estimated {estimate}");
+        assert_eq!(Err(SketchError::Incompatible), low.merge(&HyperLogLog::new(11)));
+    }
+
+    #[pg_test]
+    fn test_count_min_sketch() {
+        let mut cms = CountMinSketch::new(0.001, 0.01);
+        for i in 0..1000i32 {
+            cms.add(i, (i % 10) as u64);
+        }
+        cms.add("hot", 5000);
+        assert!((0..1000i32).all(|i| cms.estimate(i) >= (i % 10) as u64));
+        assert!(cms.estimate("hot") >= 5000);
+        assert!(cms.estimate("hot") < 5100);
+
+        let mut other = CountMinSketch::with_params(cms.width(), cms.depth());
+        other.add("hot", 1);
+        cms.merge(&other).unwrap();
+        assert!(cms.estimate("hot") >= 5001);
+    }
+
+    #[pg_test]
+    fn test_sketch_bytes_round_trip() {
+        let mut bloom = BloomFilter::new(100, 0.05);
+        let mut hll = HyperLogLog::new(8);
+        let mut cms = CountMinSketch::with_params(64, 4);
+        for i in 0..100i32 {
+            bloom.insert(i);
+            hll.add(i);
+            cms.add(i, 2);
+        }
+        assert_eq!(Ok(bloom.clone()), BloomFilter::from_bytes(&bloom.to_bytes()));
+        assert_eq!(Ok(hll.clone()), HyperLogLog::from_bytes(&hll.to_bytes()));
+        assert_eq!(Ok(cms.clone()), CountMinSketch::from_bytes(&cms.to_bytes()));
+
+        assert_eq!(
+            Err(SketchError::WrongKind { expected: "BloomFilter", found: "HyperLogLog" }),
+            BloomFilter::from_bytes(&hll.to_bytes())
+        );
+        let bytes = cms.to_bytes();
+        assert_eq!(
+            Err(SketchError::Truncated),
+            CountMinSketch::from_bytes(&bytes[..bytes.len() - 1])
+        );
+        assert_eq!(Err(SketchError::UnsupportedVersion(9)), HyperLogLog::from_bytes(&[b'H', 9]));
+    }
+
+    #[pg_test]
+    fn test_sketch_stored_as_bytea() -> Result<(), spi::Error> {
+        Spi::run("CREATE TABLE tests.sketches (hll bytea)")?;
+        Spi::run(
+            "INSERT INTO tests.sketches
+                SELECT sketch_hll(array_agg(i)) FROM generate_series(1, 1000) i",
+        )?;
+        let hll = Spi::get_one::<HyperLogLog>("SELECT hll FROM tests.sketches")?.unwrap();
+        assert!((hll.estimate() - 1000.0).abs() < 100.0);
+
+        let estimate = Spi::get_one::<f64>("SELECT sketch_hll_estimate(hll) FROM tests.sketches")?;
+        assert_eq!(Some(hll.estimate()), estimate);
+        Ok(())
+    }
+
+    #[pg_test(error = "invalid HyperLogLog: the bytes are too short")]
+    fn test_sketch_invalid_bytea() -> Result<(), spi::Error> {
+        Spi::get_one::<HyperLogLog>("SELECT '\\x4801'::bytea").map(|_| ())
+    }
+}
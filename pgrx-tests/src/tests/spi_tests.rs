@@ -16,6 +16,7 @@ mod tests {
 
     use pgrx::prelude::*;
     use pgrx::spi;
+    use pgrx::PgRelation;
 
     #[pg_test(error = "syntax error at or near \"THIS\"")]
     fn test_spi_failure() -> Result<(), spi::Error> {
@@ -272,6 +273,46 @@ mod tests {
         Ok(())
     }
 
+    #[pg_test]
+    fn test_holdable_cursor() -> Result<(), pgrx::spi::Error> {
+        let cursor_name = Spi::connect(|mut client| {
+            client.update("CREATE TABLE tests.cursor_table (id int)", None, None)?;
+            client.update(
+                "INSERT INTO tests.cursor_table (id) \
+            SELECT i FROM generate_series(1, 10) AS t(i)",
+                None,
+                None,
+            )?;
+            let mut cursor = client.open_holdable_cursor("SELECT * FROM tests.cursor_table", None);
+            assert!(cursor.is_holdable());
+            assert_eq!(sum_all(cursor.fetch(3)?), 1 + 2 + 3);
+            Ok::<_, spi::Error>(cursor.detach_into_name())
+        })?;
+
+        let is_holdable = Spi::get_one_with_args::<bool>(
+            "SELECT is_holdable FROM pg_cursors WHERE name = $1",
+            vec![(PgBuiltInOids::TEXTOID.oid(), cursor_name.clone().into_datum())],
+        )?;
+        assert_eq!(is_holdable, Some(true));
+
+        Spi::connect(|client| {
+            let mut cursor = client.find_cursor(&cursor_name)?;
+            assert!(cursor.is_holdable());
+            assert_eq!(sum_all(cursor.fetch(7)?), 4 + 5 + 6 + 7 + 8 + 9 + 10);
+            Ok::<_, spi::Error>(())
+        })?;
+
+        assert!(Spi::connect(|client| client.find_cursor(&cursor_name).is_err()));
+        Ok(())
+    }
+
+    #[pg_test]
+    fn test_cursor_not_holdable() {
+        Spi::connect(|client| {
+            assert!(!client.open_cursor("SELECT 1", None).is_holdable());
+        });
+    }
+
     #[pg_test(error = "syntax error at or near \"THIS\"")]
     fn test_cursor_failure() {
         Spi::connect(|client| {
@@ -479,6 +520,107 @@ mod tests {
         Spi::get_one::<pgrx::Inet>("select '10.0.0.1/32'::cidr")
     }
 
+    #[pg_test]
+    fn test_spi_column_metadata() -> Result<(), spi::Error> {
+        Spi::connect(|client| {
+            let table = client.select(
+                "SELECT 1::int4 AS a, 'x'::varchar(10) AS b, 'y'::text AS c",
+                None,
+                None,
+            )?;
+            let columns = table.column_metadata()?;
+            assert_eq!(3, columns.len());
+            assert_eq!("a", columns[0].name());
+            assert_eq!(PgOid::from(pg_sys::INT4OID), columns[0].type_oid());
+            assert_eq!(-1, columns[0].typmod());
+            assert_eq!("b", columns[1].name());
+            assert_eq!(PgOid::from(pg_sys::VARCHAROID), columns[1].type_oid());
+            // varchar's typmod includes the 4 byte varlena header
+            assert_eq!(14, table.column_typmod(2)?);
+            assert_eq!(3, columns[2].ordinal());
+            assert!(table.column(4).is_err());
+            Ok(())
+        })
+    }
+
+    #[pg_test]
+    fn test_spi_dynamic_values() -> Result<(), spi::Error> {
+        use pgrx::Value;
+
+        Spi::connect(|client| {
+            let row = client
+                .select(
+                    "SELECT 42::int8 AS i, 'hi' AS s, NULL::bool AS n, '[1]'::jsonb AS j",
+                    None,
+                    None,
+                )?
+                .next()
+                .expect("no rows");
+            assert_eq!(Value::Int8(42), row.get_value(1)?);
+            assert_eq!(Value::Text("hi".into()), row.get_value_by_name("s")?);
+            assert_eq!(Value::Null, row["n"].to_value());
+            assert_eq!(
+                serde_json::json!({"i": 42, "s": "hi", "n": null, "j": [1]}),
+                serde_json::Value::Object(
+                    row.column_metadata()
+                        .into_iter()
+                        .map(|column| {
+                            let value = row.get_value(column.ordinal()).unwrap();
+                            (column.name().to_string(), serde_json::to_value(value).unwrap())
+                        })
+                        .collect()
+                )
+            );
+            Ok(())
+        })
+    }
+
+    #[derive(Debug, PartialEq, serde::Deserialize)]
+    struct Person {
+        name: String,
+        age: Option<i32>,
+        score: f64,
+    }
+
+    #[pg_test]
+    fn test_spi_get_as() -> Result<(), spi::Error> {
+        let people = Spi::get_as::<Person>(
+            "SELECT * FROM (VALUES ('Bob', 42, 1.5::numeric), ('Alice', NULL, 2)) t(name, age, score) ORDER BY name",
+        )?;
+        assert_eq!(
+            vec![
+                Person { name: "Alice".into(), age: None, score: 2.0 },
+                Person { name: "Bob".into(), age: Some(42), score: 1.5 },
+            ],
+            people
+        );
+        Ok(())
+    }
+
+    #[pg_test]
+    fn test_spi_get_as_mismatch() {
+        let result = Spi::get_as::<Person>("SELECT 'Bob' AS name");
+        assert!(matches!(result, Err(spi::Error::DeserializeError(_))));
+    }
+
+    #[pg_test]
+    fn test_spi_cursor_deserialize_rows() -> Result<(), spi::Error> {
+        #[derive(serde::Deserialize)]
+        struct Row {
+            i: i64,
+        }
+
+        let sum = Spi::connect(|client| {
+            client
+                .open_cursor("SELECT i FROM generate_series(1, 1000) i", None)
+                .deserialize_rows::<Row>(7)
+                .map(|row| row.map(|row| row.i))
+                .sum::<Result<i64, spi::Error>>()
+        })?;
+        assert_eq!(500500, sum);
+        Ok(())
+    }
+
     #[pg_test]
     fn test_quote_identifier() {
         assert_eq!("unquoted", spi::quote_identifier("unquoted"));
@@ -508,4 +650,359 @@ mod tests {
         assert_eq!("'quoted-with-''quotes'''", spi::quote_literal("quoted-with-'quotes'"));
         assert_eq!("'quoted-string'", spi::quote_literal(String::from("quoted-string")));
     }
+
+    #[pg_test]
+    fn test_insert_batch() -> Result<(), spi::Error> {
+        Spi::run("CREATE TABLE tests.batch_table (id int, name text, score float8)")?;
+        let relation = PgRelation::open_with_name_and_share_lock("tests.batch_table").unwrap();
+        let rows = (1..=2500).map(|i| {
+            let name = if i % 10 == 0 { None } else { Some(format!("row {i}")) };
+            (i, name, i as f64 / 2.0)
+        });
+
+        let inserted = Spi::insert_batch_with_size(&relation, rows, 1000)?;
+        assert_eq!(inserted, 2500);
+        assert_eq!(Spi::get_one::<i64>("SELECT count(*) FROM tests.batch_table")?, Some(2500));
+        assert_eq!(
+            Spi::get_one::<i64>("SELECT count(*) FROM tests.batch_table WHERE name IS NULL")?,
+            Some(250)
+        );
+        assert_eq!(
+            Spi::get_one::<String>("SELECT name FROM tests.batch_table WHERE id = 2499")?,
+            Some("row 2499".to_string())
+        );
+        assert_eq!(
+            Spi::get_one::<f64>("SELECT score FROM tests.batch_table WHERE id = 7")?,
+            Some(3.5)
+        );
+        Ok(())
+    }
+
+    #[pg_test]
+    fn test_insert_batch_empty() -> Result<(), spi::Error> {
+        Spi::run("CREATE TABLE tests.batch_empty (id int)")?;
+        let relation = PgRelation::open_with_name_and_share_lock("tests.batch_empty").unwrap();
+        assert_eq!(Spi::insert_batch(&relation, Vec::<(i32,)>::new())?, 0);
+        assert_eq!(Spi::insert_batch(&relation, vec![(1,), (2,), (3,)])?, 3);
+        assert_eq!(Spi::get_one::<i64>("SELECT sum(id) FROM tests.batch_empty")?, Some(6));
+        Ok(())
+    }
+
+    #[pg_test]
+    fn test_spi_prepare() -> Result<(), spi::Error> {
+        let prepared = Spi::prepare("SELECT $1 * 2", Some(vec![PgBuiltInOids::INT4OID.into()]))?;
+        for i in 0..3 {
+            let doubled = Spi::connect(|client| {
+                client.select(&prepared, None, Some(vec![i.into_datum()]))?.first().get::<i32>(1)
+            })?;
+            assert_eq!(doubled, Some(i * 2));
+        }
+        Ok(())
+    }
+
+    #[pg_test]
+    fn test_spi_prepare_cached() -> Result<(), spi::Error> {
+        let query = "SELECT $1 || '!'";
+        let first = Spi::prepare_cached(query, Some(vec![PgBuiltInOids::TEXTOID.into()]))?;
+        let second = Spi::prepare_cached(query, Some(vec![PgBuiltInOids::TEXTOID.into()]))?;
+        assert!(std::rc::Rc::ptr_eq(&first, &second));
+
+        // different argument types are a different statement
+        let other = Spi::prepare_cached(query, Some(vec![PgBuiltInOids::VARCHAROID.into()]))?;
+        assert!(!std::rc::Rc::ptr_eq(&first, &other));
+
+        let result = Spi::connect(|client| {
+            client.select(&*second, None, Some(vec!["hi".into_datum()]))?.first().get::<String>(1)
+        })?;
+        assert_eq!(result.as_deref(), Some("hi!"));
+
+        Spi::clear_plan_cache();
+        let third = Spi::prepare_cached(query, Some(vec![PgBuiltInOids::TEXTOID.into()]))?;
+        assert!(!std::rc::Rc::ptr_eq(&first, &third));
+        Ok(())
+    }
+
+    #[pg_test]
+    fn test_spi_prepare_cached_replans() -> Result<(), spi::Error> {
+        Spi::run("CREATE TABLE tests.replanned (a int); INSERT INTO tests.replanned VALUES (1)")?;
+        let statement = Spi::prepare_cached("SELECT * FROM tests.replanned", None)?;
+        let columns = Spi::connect(|client| client.select(&*statement, None, None)?.columns())?;
+        assert_eq!(columns, 1);
+
+        Spi::run("ALTER TABLE tests.replanned ADD COLUMN b int")?;
+        let statement = Spi::prepare_cached("SELECT * FROM tests.replanned", None)?;
+        let columns = Spi::connect(|client| client.select(&*statement, None, None)?.columns())?;
+        assert_eq!(columns, 2);
+        Ok(())
+    }
+
+    fn memoized_sum(over: i32) -> Result<Option<i64>, spi::Error> {
+        Spi::memoized(
+            "SELECT sum(v) FROM tests.memo WHERE v > $1",
+            Some(vec![(PgBuiltInOids::INT4OID.oid(), over.into_datum())]),
+            |table| table.first().get_one::<i64>(),
+        )
+    }
+
+    #[pg_test]
+    fn test_spi_memoized() -> Result<(), spi::Error> {
+        Spi::run("CREATE TABLE tests.memo (v int); INSERT INTO tests.memo VALUES (1), (2)")?;
+        assert_eq!(Some(3), memoized_sum(0)?);
+        assert_eq!(Some(2), memoized_sum(1)?);
+
+        // the query isn't run again, so the new row isn't seen
+        Spi::run("INSERT INTO tests.memo VALUES (4)")?;
+        assert_eq!(Some(3), memoized_sum(0)?);
+
+        Spi::clear_memoized();
+        assert_eq!(Some(7), memoized_sum(0)?);
+        Ok(())
+    }
+
+    #[pg_test]
+    fn test_spi_memoized_invalidated_by_ddl() -> Result<(), spi::Error> {
+        Spi::run("CREATE TABLE tests.memo (v int); INSERT INTO tests.memo VALUES (1)")?;
+        assert_eq!(Some(1), memoized_sum(0)?);
+
+        Spi::run("INSERT INTO tests.memo VALUES (2); ALTER TABLE tests.memo ADD COLUMN w int")?;
+        assert_eq!(Some(3), memoized_sum(0)?);
+        Ok(())
+    }
+
+    #[pg_test]
+    fn test_spi_cursor_rows() -> Result<(), spi::Error> {
+        let values = Spi::connect(|client| {
+            client
+                .open_cursor("SELECT i, i::text FROM generate_series(1, 25) i", None)
+                .rows(10)
+                .map(|row| Ok((row.get::<i32>(1)?.unwrap(), row.get::<String>(2)?.unwrap())))
+                .collect::<spi::Result<Vec<_>>>()
+        })?;
+        assert_eq!(values.len(), 25);
+        assert_eq!(values[0], (1, "1".to_string()));
+        assert_eq!(values[24], (25, "25".to_string()));
+        Ok(())
+    }
+
+    #[pg_test]
+    fn test_spi_cursor_rows_stops_early() -> Result<(), spi::Error> {
+        Spi::connect(|client| {
+            let mut rows =
+                client.open_cursor("SELECT i FROM generate_series(1, 1000000) i", None).rows(5);
+            assert_eq!(rows.fetch_size(), 5);
+            let first = rows
+                .by_ref()
+                .take(7)
+                .map(|row| row.get::<i32>(1))
+                .collect::<spi::Result<Vec<_>>>()?;
+            assert_eq!(first, (1..=7).map(Some).collect::<Vec<_>>());
+            assert_eq!(rows.next().map(|row| row.get::<i32>(1)).transpose()?, Some(Some(8)));
+            Ok(())
+        })
+    }
+
+    #[pg_test]
+    fn test_spi_cursor_rows_exact_multiple() -> Result<(), spi::Error> {
+        let count = Spi::connect(|client| {
+            client.open_cursor("SELECT * FROM generate_series(1, 20)", None).rows(10).count()
+        });
+        assert_eq!(count, 20);
+        Ok(())
+    }
+
+    #[derive(FromSpiRow, Debug, PartialEq)]
+    struct Dog {
+        name: String,
+        #[spi(rename = "age_in_years")]
+        age: i32,
+        owner: Option<String>,
+    }
+
+    #[pg_test]
+    fn test_spi_decode_rows() -> Result<(), spi::Error> {
+        let dogs = Spi::connect(|client| {
+            client
+                .select(
+                    "SELECT * FROM (VALUES (3, 'Nami', NULL), (7, 'Brandy', 'Eric'))
+                        AS dogs(age_in_years, name, owner)",
+                    None,
+                    None,
+                )?
+                .decode_rows::<Dog>()
+                .collect::<Result<Vec<_>, _>>()
+        })?;
+        assert_eq!(
+            dogs,
+            vec![
+                Dog { name: "Nami".into(), age: 3, owner: None },
+                Dog { name: "Brandy".into(), age: 7, owner: Some("Eric".into()) },
+            ]
+        );
+        Ok(())
+    }
+
+    #[pg_test]
+    fn test_spi_decode_tuple_struct() -> Result<(), spi::Error> {
+        #[derive(FromSpiRow)]
+        struct Pair(i64, Option<String>);
+
+        let pair = Spi::connect(|client| {
+            client.select("SELECT 42::bigint, NULL::text", None, None)?.first().get_heap_tuple()
+        })?
+        .expect("no row")
+        .decode::<Pair>()?;
+        assert_eq!(42, pair.0);
+        assert_eq!(None, pair.1);
+        Ok(())
+    }
+
+    #[pg_test]
+    fn test_spi_decode_unexpected_null() {
+        let dog = Spi::connect(|client| {
+            client
+                .select(
+                    "SELECT 'Nami' AS name, NULL::int AS age_in_years, NULL AS owner",
+                    None,
+                    None,
+                )?
+                .decode_rows::<Dog>()
+                .next()
+                .expect("no row")
+        });
+        assert!(matches!(dog, Err(spi::Error::UnexpectedNull(column)) if column == "age_in_years"));
+    }
+
+    #[pg_test]
+    fn test_spi_decode_missing_column() {
+        let dog = Spi::connect(|client| {
+            client
+                .select("SELECT 'Nami' AS name, 3 AS age", None, None)?
+                .decode_rows::<Dog>()
+                .next()
+                .expect("no row")
+        });
+        assert!(matches!(dog, Err(spi::Error::SpiError(spi::SpiErrorCodes::NoAttribute))));
+    }
+
+    #[pg_test]
+    fn test_spi_transaction_commits() -> Result<(), spi::Error> {
+        Spi::run("CREATE TABLE tests.subxact (v int)")?;
+        let inserted = Spi::transaction(|mut client| {
+            client.update("INSERT INTO tests.subxact VALUES (1)", None, None)
+        })?;
+        assert_eq!(1, inserted.len());
+        assert_eq!(Some(1), Spi::get_one::<i64>("SELECT count(*) FROM tests.subxact")?);
+        Ok(())
+    }
+
+    #[pg_test]
+    fn test_spi_transaction_rolls_back_error() -> Result<(), spi::Error> {
+        Spi::run("CREATE TABLE tests.subxact (v int)")?;
+        let result = Spi::transaction(|mut client| {
+            client.update("INSERT INTO tests.subxact VALUES (1)", None, None)?;
+            client.update("INSERT INTO tests.subxact VALUES (1 / 0)", None, None)
+        });
+        assert_eq!(
+            Err(spi::Error::SubTransactionAborted {
+                code: PgSqlErrorCode::ERRCODE_DIVISION_BY_ZERO,
+                message: "division by zero".into(),
+            }),
+            result.map(|_| ())
+        );
+
+        // the outer transaction carries on
+        Spi::run("INSERT INTO tests.subxact VALUES (2)")?;
+        assert_eq!(Some(2), Spi::get_one::<i32>("SELECT sum(v)::int FROM tests.subxact")?);
+        Ok(())
+    }
+
+    #[pg_test]
+    fn test_spi_transaction_rolls_back_err() -> Result<(), spi::Error> {
+        Spi::run("CREATE TABLE tests.subxact (v int)")?;
+        let result = Spi::transaction(|mut client| {
+            client.update("INSERT INTO tests.subxact VALUES (1)", None, None)?;
+            Err::<(), _>(spi::Error::NoTupleTable)
+        });
+        assert_eq!(Err(spi::Error::NoTupleTable), result);
+        assert_eq!(Some(0), Spi::get_one::<i64>("SELECT count(*) FROM tests.subxact")?);
+        Ok(())
+    }
+
+    #[pg_test]
+    fn test_spi_nested_subtransaction() -> Result<(), spi::Error> {
+        Spi::run("CREATE TABLE tests.subxact (v int)")?;
+        Spi::transaction(|mut client| {
+            client.update("INSERT INTO tests.subxact VALUES (1)", None, None)?;
+            let inner = client.subtransaction(|mut sub| {
+                sub.update("INSERT INTO tests.subxact VALUES (2)", None, None)?;
+                sub.update("SELECT 'not a number'::int", None, None)
+            });
+            assert!(matches!(inner, Err(spi::Error::SubTransactionAborted { .. })));
+            Ok::<_, spi::Error>(())
+        })?;
+        assert_eq!(Some(1), Spi::get_one::<i32>("SELECT sum(v)::int FROM tests.subxact")?);
+        Ok(())
+    }
+
+    #[pg_test(error = "panicked in a subtransaction")]
+    fn test_spi_transaction_panic_continues() -> Result<(), spi::Error> {
+        Spi::transaction(|_| -> Result<(), spi::Error> { panic!("panicked in a subtransaction") })
+    }
+
+    #[pg_test]
+    fn test_query_to_json() -> Result<(), spi::Error> {
+        let json = Spi::query_to_json(
+            "SELECT 1 AS id, 'Bob \"B\"' AS name, true AS ok, NULL::int AS missing, \
+             'NaN'::float8 AS nan, 1.50::numeric AS price, '{\"a\": [1]}'::jsonb AS doc",
+        )?;
+        assert_eq!(
+            json,
+            r#"[{"id":1,"name":"Bob \"B\"","ok":true,"missing":null,"nan":"NaN","price":1.50,"doc":{"a": [1]}}]"#
+        );
+        Ok(())
+    }
+
+    #[pg_test]
+    fn test_query_to_json_empty() -> Result<(), spi::Error> {
+        assert_eq!(Spi::query_to_json("SELECT 1 AS id LIMIT 0")?, "[]");
+        Ok(())
+    }
+
+    #[pg_test]
+    fn test_query_to_json_many_rows() -> Result<(), spi::Error> {
+        let json = Spi::query_to_json("SELECT i FROM generate_series(1, 2500) i")?;
+        let rows: Vec<serde_json::Value> = serde_json::from_str(&json).unwrap();
+        assert_eq!(rows.len(), 2500);
+        assert_eq!(rows[2499]["i"], 2500);
+        Ok(())
+    }
+
+    #[pg_test]
+    fn test_query_to_json_with_args() -> Result<(), spi::Error> {
+        let json = Spi::query_to_json_with_args(
+            "SELECT $1 AS line",
+            Some(vec![(PgBuiltInOids::TEXTOID.oid(), "a\nb\tc".into_datum())]),
+        )?;
+        assert_eq!(json, r#"[{"line":"a\nb\tc"}]"#);
+        Ok(())
+    }
+
+    #[pg_test]
+    fn test_query_to_csv() -> Result<(), spi::Error> {
+        let csv = Spi::query_to_csv(
+            "SELECT * FROM (VALUES (1, 'plain', ''), (2, 'a, b', NULL), (3, 'say \"hi\"', E'x\\ny')) \
+             AS t(id, name, note)",
+        )?;
+        assert_eq!(
+            csv,
+            "id,name,note\r\n1,plain,\"\"\r\n2,\"a, b\",\r\n3,\"say \"\"hi\"\"\",\"x\ny\"\r\n"
+        );
+        Ok(())
+    }
+
+    #[pg_test]
+    fn test_query_to_csv_empty() -> Result<(), spi::Error> {
+        assert_eq!(Spi::query_to_csv("SELECT 1 AS id, 2 AS \"a,b\" LIMIT 0")?, "id,\"a,b\"\r\n");
+        Ok(())
+    }
 }
@@ -8,6 +8,7 @@ Use of this source code is governed by the MIT license that can be found in the
 */
 
 use pgrx::prelude::*;
+use std::sync::atomic::{AtomicUsize, Ordering};
 
 #[pg_extern]
 fn example_generate_series(
@@ -69,16 +70,44 @@ fn return_none_result_setof_iterator(
     Ok(None)
 }
 
-// TODO:  We don't yet support returning Result<Option<TableIterator>> because the code generator
-//        is inscrutable. But when we do, this function will help ensure it works
-//
-// #[pg_extern]
-// fn return_none_result_tableiterator_iterator() -> Result<
-//     Option<TableIterator<'static, (name!(idx, i32), name!(some_value, &'static str))>>,
-//     Box<dyn std::error::Error>,
-// > {
-//     Ok(None)
-// }
+#[pg_extern]
+fn return_none_result_tableiterator_iterator() -> Result<
+    Option<TableIterator<'static, (name!(idx, i32), name!(some_value, &'static str))>>,
+    Box<dyn std::error::Error>,
+> {
+    Ok(None)
+}
+
+#[pg_extern]
+fn return_some_result_tableiterator_iterator() -> Result<
+    Option<TableIterator<'static, (name!(idx, i32), name!(some_value, &'static str))>>,
+    Box<dyn std::error::Error>,
+> {
+    Ok(Some(TableIterator::new(vec![(1, "a"), (2, "b")].into_iter())))
+}
+
+#[pg_extern]
+fn return_result_tableiterator_iterator(
+    fail: bool,
+) -> Result<TableIterator<'static, (name!(idx, i32), name!(some_value, &'static str))>, String> {
+    if fail {
+        Err("tableiterator failed".to_string())
+    } else {
+        Ok(TableIterator::new(vec![(1, "a")].into_iter()))
+    }
+}
+
+#[pg_extern]
+fn return_some_option_result_setof_iterator(
+) -> Option<Result<SetOfIterator<'static, i32>, Box<dyn std::error::Error>>> {
+    Some(Ok(SetOfIterator::new(vec![1, 2, 3].into_iter())))
+}
+
+#[pg_extern]
+fn return_err_option_result_setof_iterator(
+) -> Option<Result<SetOfIterator<'static, i32>, Box<dyn std::error::Error>>> {
+    Some(Err("setof failed".into()))
+}
 
 #[pg_extern]
 fn split_set_with_borrow<'a>(input: &'a str, pattern: &'a str) -> SetOfIterator<'a, &'a str> {
@@ -93,6 +122,40 @@ fn split_table_with_borrow<'a>(
     TableIterator::new(input.split_terminator(pattern).enumerate().map(|(i, s)| (i as i32, s)))
 }
 
+#[pg_extern]
+fn srf_fetch_hint(
+    fcinfo: pg_sys::FunctionCallInfo,
+) -> TableIterator<
+    'static,
+    (name!(value_per_call, bool), name!(scrollable, bool), name!(incremental_fetch, bool)),
+> {
+    let hint = unsafe { pgrx::iter::SrfFetchHint::from_fcinfo(fcinfo) };
+    TableIterator::once((hint.value_per_call(), hint.scrollable(), hint.incremental_fetch()))
+}
+
+static ROWS_PRODUCED: AtomicUsize = AtomicUsize::new(0);
+
+#[pg_extern]
+fn srf_incremental(fcinfo: pg_sys::FunctionCallInfo, total: i32) -> SetOfIterator<'static, i32> {
+    let hint = unsafe { pgrx::iter::SrfFetchHint::from_fcinfo(fcinfo) };
+    ROWS_PRODUCED.store(0, Ordering::Relaxed);
+    let total = total as usize;
+    SetOfIterator::new(pgrx::iter::IncrementalRows::new(
+        hint.batch_size(2, 1000),
+        1000,
+        move |offset, count| {
+            let rows = (offset..(offset + count).min(total)).map(|i| i as i32).collect::<Vec<_>>();
+            ROWS_PRODUCED.fetch_add(rows.len(), Ordering::Relaxed);
+            rows
+        },
+    ))
+}
+
+#[pg_extern]
+fn srf_rows_produced() -> i64 {
+    ROWS_PRODUCED.load(Ordering::Relaxed) as i64
+}
+
 #[cfg(any(test, feature = "pg_test"))]
 #[pgrx::pg_schema]
 mod tests {
@@ -192,6 +255,47 @@ mod tests {
         assert_eq!(cnt, Ok(0))
     }
 
+    #[pg_test]
+    fn test_return_none_result_tableiterator_iterator() {
+        let cnt = Spi::get_one::<i64>(
+            "SELECT count(*) FROM return_none_result_tableiterator_iterator();",
+        );
+        assert_eq!(cnt, Ok(Some(0)))
+    }
+
+    #[pg_test]
+    fn test_return_some_result_tableiterator_iterator() {
+        let sum = Spi::get_one::<i64>(
+            "SELECT sum(idx) FROM return_some_result_tableiterator_iterator();",
+        );
+        assert_eq!(sum, Ok(Some(3)))
+    }
+
+    #[pg_test]
+    fn test_return_result_tableiterator_iterator() {
+        let cnt = Spi::get_one::<i64>(
+            "SELECT count(*) FROM return_result_tableiterator_iterator(false);",
+        );
+        assert_eq!(cnt, Ok(Some(1)))
+    }
+
+    #[pg_test(error = "tableiterator failed")]
+    fn test_return_result_tableiterator_iterator_err() {
+        Spi::run("SELECT * FROM return_result_tableiterator_iterator(true);").unwrap();
+    }
+
+    #[pg_test]
+    fn test_return_some_option_result_setof_iterator() {
+        let cnt =
+            Spi::get_one::<i64>("SELECT count(*) FROM return_some_option_result_setof_iterator();");
+        assert_eq!(cnt, Ok(Some(3)))
+    }
+
+    #[pg_test(error = "setof failed")]
+    fn test_return_err_option_result_setof_iterator() {
+        Spi::run("SELECT * FROM return_err_option_result_setof_iterator();").unwrap();
+    }
+
     #[pg_test]
     fn test_srf_setof_datum_detoasting_with_borrow() {
         let cnt = Spi::connect(|mut client| {
@@ -247,4 +351,57 @@ mod tests {
             Spi::get_one(&format!("SELECT CAUSE_AN_ERROR FROM pg_class WHERE oid = {oid}"))
         }))
     }
+
+    #[pg_test]
+    fn test_srf_fetch_hint_plain_query() -> Result<(), spi::Error> {
+        let (value_per_call, scrollable, incremental) =
+            Spi::get_three::<bool, bool, bool>("SELECT * FROM srf_fetch_hint()")?;
+        assert_eq!(value_per_call, Some(true));
+        assert_eq!(scrollable, Some(false));
+        assert_eq!(incremental, Some(false));
+        Ok(())
+    }
+
+    #[pg_test]
+    fn test_srf_fetch_hint_scroll_cursor() -> Result<(), spi::Error> {
+        Spi::run("DECLARE hinted SCROLL CURSOR FOR SELECT * FROM srf_fetch_hint()")?;
+        Spi::connect(|client| {
+            let row = client.fetch_from_cursor("hinted", spi::FetchDirection::Forward(1))?;
+            let (_, scrollable, incremental) = row.first().get_three::<bool, bool, bool>()?;
+            assert_eq!(scrollable, Some(true));
+            assert_eq!(incremental, Some(true));
+            Ok(())
+        })
+    }
+
+    #[pg_test]
+    fn test_srf_incremental_rows_in_cursor() -> Result<(), spi::Error> {
+        Spi::connect(|client| {
+            let mut cursor = client.open_cursor("SELECT srf_incremental(100000)", None);
+            let rows = cursor.fetch(3)?;
+            assert_eq!(rows.len(), 3);
+            // batches of 2 then 4 rows were enough
+            assert_eq!(Spi::get_one::<i64>("SELECT srf_rows_produced()")?, Some(6));
+            Ok(())
+        })
+    }
+
+    #[pg_test]
+    fn test_srf_incremental_rows_all() -> Result<(), spi::Error> {
+        let sum = Spi::get_one::<i64>("SELECT sum(i) FROM srf_incremental(2500) i")?;
+        assert_eq!(sum, Some((0..2500).sum()));
+        Ok(())
+    }
+
+    #[pg_test]
+    fn test_incremental_rows_batches() {
+        let mut requests = vec![];
+        let rows = pgrx::iter::IncrementalRows::new(1, 4, |offset, count| {
+            requests.push((offset, count));
+            (offset..(offset + count).min(10)).collect::<Vec<_>>()
+        })
+        .collect::<Vec<_>>();
+        assert_eq!(rows, (0..10).collect::<Vec<_>>());
+        assert_eq!(requests, vec![(0, 1), (1, 2), (3, 4), (7, 4)]);
+    }
 }
@@ -0,0 +1,126 @@
+/*
+Portions Copyright 2019-2021 ZomboDB, LLC.
+Portions Copyright 2021-2022 Technology Concepts & Design, Inc. <support@tcdi.com>
+
+All rights reserved.
+
+Use of this source code is governed by the MIT license that can be found in the LICENSE file.
+*/
+
+#[cfg(any(test, feature = "pg_test"))]
+#[pgrx::pg_schema]
+mod tests {
+    #[allow(unused_imports)]
+    use crate as pgrx_tests;
+
+    use pgrx::prelude::*;
+    use pgrx::selectivity::ColumnStatistics;
+    use pgrx::stats_import::{AttributeStats, RelationStats, StatsImportError};
+
+    fn table() -> pg_sys::Oid {
+        Spi::run("CREATE TABLE tests.imported (status text, amount int, ordered date)").unwrap();
+        Spi::get_one::<pg_sys::Oid>("SELECT 'tests.imported'::regclass::oid").unwrap().unwrap()
+    }
+
+    #[pg_test]
+    fn test_relation_stats() {
+        table();
+        RelationStats::new("tests.imported").relpages(100).reltuples(12345.0).set().unwrap();
+        let stats = Spi::get_two::<i32, f32>(
+            "SELECT relpages, reltuples FROM pg_class WHERE oid = 'tests.imported'::regclass",
+        );
+        assert_eq!(stats, Ok((Some(100), Some(12345.0))));
+
+        // values not given are left alone
+        RelationStats::new("tests.imported").relallvisible(40).set().unwrap();
+        let stats = Spi::get_three::<i32, f32, i32>(
+            "SELECT relpages, reltuples, relallvisible FROM pg_class \
+              WHERE oid = 'tests.imported'::regclass",
+        );
+        assert_eq!(stats, Ok((Some(100), Some(12345.0), Some(40))));
+    }
+
+    #[pg_test]
+    fn test_attribute_stats() {
+        let relid = table();
+        AttributeStats::new("tests.imported", "status")
+            .null_frac(0.25)
+            .avg_width(8)
+            .n_distinct(3.0)
+            .most_common_values(&["shipped", "pending"], &[0.6, 0.1])
+            .set()
+            .unwrap();
+        AttributeStats::new("tests.imported", "amount")
+            .n_distinct(-1.0)
+            .histogram_bounds(&["1", "10", "100"])
+            .correlation(0.5)
+            .set()
+            .unwrap();
+
+        let status = ColumnStatistics::lookup_by_name(relid, "status", false).unwrap();
+        assert_eq!(status.null_frac(), 0.25);
+        assert_eq!(status.avg_width(), 8);
+        assert_eq!(status.n_distinct(), 3.0);
+        assert_eq!(
+            status.most_common_values::<String>(),
+            Some(vec![
+                ("shipped".to_string(), 0.6f32 as f64),
+                ("pending".to_string(), 0.1f32 as f64)
+            ])
+        );
+        assert_eq!(status.histogram::<String>(), None);
+
+        let amount = ColumnStatistics::lookup_by_name(relid, "amount", false).unwrap();
+        assert_eq!(amount.histogram::<i32>(), Some(vec![1, 10, 100]));
+        let correlation = Spi::get_one::<f32>(
+            "SELECT correlation FROM pg_stats \
+              WHERE schemaname = 'tests' AND tablename = 'imported' AND attname = 'amount'",
+        );
+        assert_eq!(correlation, Ok(Some(0.5)));
+    }
+
+    #[pg_test]
+    fn test_attribute_stats_replaced() {
+        let relid = table();
+        let stats = AttributeStats::new("tests.imported", "amount").histogram_bounds(&["1", "2"]);
+        stats.set().unwrap();
+        stats.most_common_values(&["7"], &[0.5]).set().unwrap();
+
+        let amount = ColumnStatistics::lookup_by_name(relid, "amount", false).unwrap();
+        assert_eq!(amount.most_common_values::<i32>(), Some(vec![(7, 0.5)]));
+        assert_eq!(amount.histogram::<i32>(), Some(vec![1, 2]));
+        assert_eq!(
+            Spi::get_one::<i64>(
+                "SELECT count(*) FROM pg_statistic WHERE starelid = 'tests.imported'::regclass"
+            ),
+            Ok(Some(1))
+        );
+    }
+
+    #[pg_test]
+    fn test_attribute_stats_errors() {
+        table();
+        assert_eq!(
+            RelationStats::new("tests.missing").relpages(1).set(),
+            Err(StatsImportError::UndefinedTable("tests.missing".into()))
+        );
+        assert_eq!(
+            AttributeStats::new("tests.imported", "missing").set(),
+            Err(StatsImportError::UndefinedColumn("tests.imported".into(), "missing".into()))
+        );
+        assert_eq!(
+            AttributeStats::new("tests.imported", "status")
+                .most_common_values(&["shipped"], &[0.5, 0.5])
+                .set(),
+            Err(StatsImportError::MostCommonMismatch(1, 2))
+        );
+    }
+
+    #[pg_test(error = "invalid input syntax for type date: \"tuesday\"")]
+    fn test_attribute_stats_invalid_value() {
+        table();
+        let _ = AttributeStats::new("tests.imported", "ordered")
+            .histogram_bounds(&["2022-01-01", "tuesday"])
+            .set();
+    }
+}
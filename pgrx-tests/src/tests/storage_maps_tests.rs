@@ -0,0 +1,64 @@
+/*
+Portions Copyright 2019-2021 ZomboDB, LLC.
+Portions Copyright 2021-2022 Technology Concepts & Design, Inc. <support@tcdi.com>
+
+All rights reserved.
+
+Use of this source code is governed by the MIT license that can be found in the LICENSE file.
+*/
+
+#[cfg(any(test, feature = "pg_test"))]
+#[pgrx::pg_schema]
+mod tests {
+    #[allow(unused_imports)]
+    use crate as pgrx_tests;
+
+    use pgrx::prelude::*;
+    use pgrx::storage_maps::{FreeSpaceMap, VisibilityMap, VisibilityStatus};
+    use pgrx::PgRelation;
+
+    fn create_table() -> PgRelation {
+        Spi::run("CREATE TABLE tests.maps (id int, body text)").unwrap();
+        Spi::run(
+            "INSERT INTO tests.maps SELECT i, repeat('x', 100) FROM generate_series(1, 1000) i",
+        )
+        .unwrap();
+        PgRelation::open_with_name_and_share_lock("tests.maps").unwrap()
+    }
+
+    #[pg_test]
+    fn test_visibility_map_of_new_table() {
+        let relation = create_table();
+        let mut vm = VisibilityMap::new(&relation);
+
+        // nothing has vacuumed the table, so no block can be all-visible yet
+        let summary = vm.summary();
+        assert!(summary.blocks > 1);
+        assert_eq!(summary.all_visible, 0);
+        assert_eq!(summary.all_frozen, 0);
+        assert_eq!(summary.all_visible_fraction(), 0.0);
+
+        let statuses = vm.iter().collect::<Vec<_>>();
+        assert_eq!(statuses.len(), summary.blocks as usize);
+        assert!(statuses.iter().all(|(_, status)| *status == VisibilityStatus::default()));
+    }
+
+    #[pg_test]
+    fn test_free_space_map() {
+        let relation = create_table();
+        let fsm = FreeSpaceMap::new(&relation);
+
+        let free = fsm.iter().collect::<Vec<_>>();
+        assert_eq!(free.len(), fsm.blocks() as usize);
+        assert!(free.iter().all(|(_, bytes)| *bytes <= pg_sys::BLCKSZ as usize));
+        assert_eq!(fsm.free_space(fsm.blocks() + 100), 0);
+    }
+
+    #[pg_test(error = "`maps_idx` does not have a visibility map")]
+    fn test_visibility_map_of_index() {
+        let _relation = create_table();
+        Spi::run("CREATE INDEX maps_idx ON tests.maps (id)").unwrap();
+        let index = PgRelation::open_with_name_and_share_lock("tests.maps_idx").unwrap();
+        VisibilityMap::new(&index);
+    }
+}
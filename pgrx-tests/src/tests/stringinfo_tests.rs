@@ -0,0 +1,77 @@
+/*
+Portions Copyright 2019-2021 ZomboDB, LLC.
+Portions Copyright 2021-2022 Technology Concepts & Design, Inc. <support@tcdi.com>
+
+All rights reserved.
+
+Use of this source code is governed by the MIT license that can be found in the LICENSE file.
+*/
+
+#[cfg(any(test, feature = "pg_test"))]
+#[pgrx::pg_schema]
+mod tests {
+    #[allow(unused_imports)]
+    use crate as pgrx_tests;
+
+    use pgrx::prelude::*;
+    use pgrx::StringInfo;
+
+    #[pg_test]
+    fn test_fmt_write() {
+        use std::fmt::Write;
+
+        let mut si = StringInfo::new();
+        write!(si, "{}-{:03}", "abc", 7).unwrap();
+        si.write_char('!').unwrap();
+        assert_eq!(si.as_str(), Ok("abc-007!"));
+    }
+
+    #[pg_test]
+    fn test_io_write() {
+        use std::io::Write;
+
+        let mut si = StringInfo::new();
+        si.write_all(b"\x00\x01binary").unwrap();
+        assert_eq!(si.as_bytes(), b"\x00\x01binary");
+    }
+
+    #[pg_test]
+    fn test_from_raw_parts() {
+        let mut si = unsafe {
+            let data = pg_sys::palloc(8) as *mut std::os::raw::c_char;
+            std::ptr::copy_nonoverlapping(b"abc".as_ptr().cast(), data, 3);
+            StringInfo::from_raw_parts(data, 3, 8)
+        };
+        assert_eq!(si.as_str(), Ok("abc"));
+
+        // grows past the original buffer
+        si.push_str("defghijklmnop");
+        assert_eq!(si.as_str(), Ok("abcdefghijklmnop"));
+    }
+
+    #[pg_test]
+    #[should_panic(expected = "no room for a trailing null byte")]
+    fn test_from_raw_parts_full_buffer() {
+        unsafe {
+            let data = pg_sys::palloc(4) as *mut std::os::raw::c_char;
+            StringInfo::from_raw_parts(data, 4, 4);
+        }
+    }
+
+    #[pg_test]
+    fn test_into_text_datum() {
+        let si = StringInfo::from("hello, world");
+        let text = unsafe { String::from_datum(si.into_text_datum(), false) };
+        assert_eq!(text.as_deref(), Some("hello, world"));
+    }
+
+    #[pg_test]
+    fn test_into_bytea_datum() {
+        let si = StringInfo::from(&b"\x00\xffbytes"[..]);
+        let bytes = unsafe { Vec::<u8>::from_datum(si.into_bytea_datum(), false) };
+        assert_eq!(bytes.as_deref(), Some(&b"\x00\xffbytes"[..]));
+
+        let empty = unsafe { Vec::<u8>::from_datum(StringInfo::new().into_bytea_datum(), false) };
+        assert_eq!(empty, Some(vec![]));
+    }
+}
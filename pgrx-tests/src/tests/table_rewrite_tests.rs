@@ -0,0 +1,95 @@
+/*
+Portions Copyright 2019-2021 ZomboDB, LLC.
+Portions Copyright 2021-2022 Technology Concepts & Design, Inc. <support@tcdi.com>
+
+All rights reserved.
+
+Use of this source code is governed by the MIT license that can be found in the LICENSE file.
+*/
+
+#[cfg(any(test, feature = "pg_test"))]
+#[pgrx::pg_schema]
+mod tests {
+    #[allow(unused_imports)]
+    use crate as pgrx_tests;
+
+    use pgrx::prelude::*;
+    use pgrx::table_rewrite::{RewriteAction, RewritePhase, TableRewrite};
+
+    fn create_table() -> pg_sys::Oid {
+        Spi::run("CREATE TABLE tests.rewrite_me (id int PRIMARY KEY, body text)").unwrap();
+        Spi::run(
+            "INSERT INTO tests.rewrite_me SELECT i, 'row ' || i FROM generate_series(1, 1000) i",
+        )
+        .unwrap();
+        Spi::get_one::<pg_sys::Oid>("SELECT 'tests.rewrite_me'::regclass::oid").unwrap().unwrap()
+    }
+
+    fn relfilenode() -> Option<pg_sys::Oid> {
+        Spi::get_one("SELECT relfilenode FROM pg_class WHERE oid = 'tests.rewrite_me'::regclass")
+            .unwrap()
+    }
+
+    #[pg_test]
+    fn test_table_rewrite() {
+        let relid = create_table();
+        let before = relfilenode();
+
+        let stats = TableRewrite::new(relid).run(|row| {
+            let id = row.get_by_name::<i32>("id")?.unwrap();
+            if id % 2 == 0 {
+                return Ok(RewriteAction::Discard);
+            }
+            let body = row.get_by_name::<String>("body")?;
+            row.set_by_name("body", body.map(|body| body.to_uppercase()))?;
+            Ok(RewriteAction::Keep)
+        });
+
+        assert_eq!(stats.phase, RewritePhase::Done);
+        assert_eq!(stats.tuples_scanned, 1000);
+        assert_eq!(stats.tuples_written, 500);
+        assert_ne!(before, relfilenode());
+
+        assert_eq!(Spi::get_one("SELECT count(*) FROM tests.rewrite_me"), Ok(Some(500i64)));
+        assert_eq!(
+            Spi::get_one("SELECT body FROM tests.rewrite_me WHERE id = 7"),
+            Ok(Some("ROW 7".to_string()))
+        );
+
+        // the primary key index was rebuilt against the new rows
+        Spi::run("SET LOCAL enable_seqscan TO off").unwrap();
+        assert_eq!(
+            Spi::get_one("SELECT body FROM tests.rewrite_me WHERE id = 999"),
+            Ok(Some("ROW 999".to_string()))
+        );
+        assert_eq!(
+            Spi::get_one::<String>("SELECT body FROM tests.rewrite_me WHERE id = 2"),
+            Ok(None)
+        );
+    }
+
+    #[pg_test]
+    fn test_table_rewrite_progress() {
+        let relid = create_table();
+        let mut reports = vec![];
+
+        TableRewrite::new(relid)
+            .on_progress(100, |progress| reports.push(*progress))
+            .run(|_| Ok(RewriteAction::Keep));
+
+        let copying = reports.iter().filter(|p| p.phase == RewritePhase::CopyingRows).count();
+        // once at the start, and then every 100 rows
+        assert_eq!(copying, 11);
+        assert_eq!(reports.last().map(|p| p.phase), Some(RewritePhase::Done));
+        assert_eq!(reports.last().map(|p| p.tuples_written), Some(1000));
+    }
+
+    #[pg_test(error = "`rewrite_me` is not a table")]
+    fn test_table_rewrite_not_a_table() {
+        Spi::run("CREATE VIEW tests.rewrite_me AS SELECT 1").unwrap();
+        let relid = Spi::get_one::<pg_sys::Oid>("SELECT 'tests.rewrite_me'::regclass::oid")
+            .unwrap()
+            .unwrap();
+        TableRewrite::new(relid).run(|_| Ok(RewriteAction::Keep));
+    }
+}
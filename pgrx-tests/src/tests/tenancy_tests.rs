@@ -0,0 +1,98 @@
+/*
+Portions Copyright 2019-2021 ZomboDB, LLC.
+Portions Copyright 2021-2022 Technology Concepts & Design, Inc. <support@tcdi.com>
+
+All rights reserved.
+
+Use of this source code is governed by the MIT license that can be found in the LICENSE file.
+*/
+use pgrx::prelude::*;
+use pgrx::tenancy::TenantSetting;
+
+static TENANT: TenantSetting<i64> =
+    TenantSetting::new("pgrx_tests.tenant_id", "The tenant this session acts for");
+
+pub(crate) fn init() {
+    TENANT.register();
+}
+
+extension_sql!(
+    "CREATE TABLE tenant_documents (tenant_id bigint NOT NULL, body text);",
+    name = "tenant_documents"
+);
+
+pgrx::tenant_policy!(
+    table = "tenant_documents",
+    column = "tenant_id",
+    setting = "pgrx_tests.tenant_id",
+    column_type = "bigint",
+    requires = ["tenant_documents"]
+);
+
+#[cfg(any(test, feature = "pg_test"))]
+#[pgrx::pg_schema]
+mod tests {
+    #[allow(unused_imports)]
+    use crate as pgrx_tests;
+
+    use super::TENANT;
+    use pgrx::prelude::*;
+
+    #[pg_test]
+    fn test_current_tenant() {
+        assert_eq!(TENANT.current(), None);
+        TENANT.set_local("7").unwrap();
+        assert_eq!(TENANT.current(), Some(7));
+        assert_eq!(TENANT.require(), 7);
+        Spi::run("SET LOCAL pgrx_tests.tenant_id = '8'").unwrap();
+        assert_eq!(TENANT.current(), Some(8));
+    }
+
+    #[pg_test(error = "invalid value for parameter \"pgrx_tests.tenant_id\": \"acme\"")]
+    fn test_invalid_tenant() {
+        TENANT.set_local("acme").unwrap();
+        TENANT.current();
+    }
+
+    #[pg_test(error = "no tenant is set in \"pgrx_tests.tenant_id\"")]
+    fn test_require_tenant() {
+        TENANT.require();
+    }
+
+    // policies don't apply to superusers, so queries are run as an ordinary role
+    fn as_tenant_user() {
+        Spi::run(
+            "INSERT INTO tenant_documents VALUES (1, 'a'), (1, 'b'), (2, 'c');
+             CREATE ROLE pgrx_tenant_user;
+             GRANT SELECT, INSERT ON tenant_documents TO pgrx_tenant_user;
+             SET LOCAL ROLE pgrx_tenant_user",
+        )
+        .unwrap();
+    }
+
+    #[pg_test]
+    fn test_tenant_policy() {
+        as_tenant_user();
+        let count = || Spi::get_one::<i64>("SELECT count(*) FROM tenant_documents");
+        assert_eq!(count(), Ok(Some(0)));
+        TENANT.set_local("1").unwrap();
+        assert_eq!(count(), Ok(Some(2)));
+        TENANT.set_local("2").unwrap();
+        assert_eq!(count(), Ok(Some(1)));
+        Spi::run("INSERT INTO tenant_documents VALUES (2, 'd')").unwrap();
+        assert_eq!(count(), Ok(Some(2)));
+
+        // a reset or empty setting matches no rows, rather than failing to cast
+        Spi::run("RESET pgrx_tests.tenant_id").unwrap();
+        assert_eq!(count(), Ok(Some(0)));
+        Spi::run("SET LOCAL pgrx_tests.tenant_id = ''").unwrap();
+        assert_eq!(count(), Ok(Some(0)));
+    }
+
+    #[pg_test(error = "new row violates row-level security policy for table \"tenant_documents\"")]
+    fn test_tenant_policy_check() {
+        as_tenant_user();
+        TENANT.set_local("1").unwrap();
+        Spi::run("INSERT INTO tenant_documents VALUES (2, 'e')").unwrap();
+    }
+}
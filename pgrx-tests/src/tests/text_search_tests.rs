@@ -0,0 +1,98 @@
+/*
+Portions Copyright 2019-2021 ZomboDB, LLC.
+Portions Copyright 2021-2022 Technology Concepts & Design, Inc. <support@tcdi.com>
+
+All rights reserved.
+
+Use of this source code is governed by the MIT license that can be found in the LICENSE file.
+*/
+
+#[cfg(any(test, feature = "pg_test"))]
+#[pgrx::pg_schema]
+mod tests {
+    #[allow(unused_imports)]
+    use crate as pgrx_tests;
+
+    use pgrx::prelude::*;
+    use pgrx::{Lexeme, TsPosition, TsQuery, TsVector, TsWeight};
+
+    #[pg_extern]
+    fn accept_tsvector(vector: TsVector) -> TsVector {
+        vector
+    }
+
+    #[pg_extern]
+    fn accept_tsquery(query: TsQuery) -> TsQuery {
+        query
+    }
+
+    #[pg_test]
+    fn test_tsvector_lexemes() {
+        let vector =
+            Spi::get_one::<TsVector>("SELECT 'fat:2,4 cat a:1A'::tsvector").unwrap().unwrap();
+        assert_eq!(
+            vector.lexemes(),
+            [
+                Lexeme::with_positions("a", vec![TsPosition { position: 1, weight: TsWeight::A }]),
+                Lexeme::new("cat"),
+                Lexeme::with_positions("fat", vec![TsPosition::new(2), TsPosition::new(4)]),
+            ]
+        );
+        assert_eq!(vector.to_string(), "'a':1A 'cat' 'fat':2,4");
+    }
+
+    #[pg_test]
+    fn test_tsvector_round_trip() {
+        let matched = Spi::get_one::<bool>(
+            "SELECT accept_tsvector('a:1A fat:2,4B cat') = 'a:1A fat:2,4B cat'::tsvector",
+        );
+        assert_eq!(matched, Ok(Some(true)));
+
+        let vector = TsVector::new(vec![
+            Lexeme::with_positions("it's", vec![TsPosition { position: 3, weight: TsWeight::C }]),
+            Lexeme::new("back\\slash"),
+            Lexeme::new("it's"),
+        ]);
+        let round_tripped =
+            unsafe { TsVector::from_datum(vector.into_datum().unwrap(), false).unwrap() };
+        assert_eq!(
+            round_tripped.lexemes(),
+            [
+                Lexeme::new("back\\slash"),
+                Lexeme::with_positions(
+                    "it's",
+                    vec![TsPosition { position: 3, weight: TsWeight::C }],
+                ),
+            ]
+        );
+    }
+
+    #[pg_test]
+    fn test_to_tsvector() {
+        let vector = TsVector::to_tsvector("english", "The fat cats sat");
+        assert_eq!(
+            vector.iter().map(|lexeme| lexeme.word.as_str()).collect::<Vec<_>>(),
+            ["cat", "fat", "sat"]
+        );
+        assert_eq!(vector.lexemes()[0].positions, [TsPosition::new(3)]);
+    }
+
+    #[pg_test]
+    fn test_tsquery() {
+        let query = TsQuery::to_tsquery("english", "fat & (rats | cats)");
+        assert_eq!(&*query, "'fat' & ( 'rat' | 'cat' )");
+        assert_eq!(TsQuery::plainto_tsquery("english", "The fat cats").0, "'fat' & 'cat'");
+
+        let matched =
+            Spi::get_one::<bool>("SELECT accept_tsquery('fat & cat') = 'fat & cat'::tsquery");
+        assert_eq!(matched, Ok(Some(true)));
+    }
+
+    #[pg_test]
+    fn test_ts_match() {
+        let vector = TsVector::to_tsvector("english", "The fat cats sat");
+        assert!(vector.matches(&TsQuery::to_tsquery("english", "fat & (rats | cats)")));
+        assert!(!vector.matches(&TsQuery::to_tsquery("english", "fat & rats")));
+        assert!(pgrx::ts_match(&vector, &TsQuery::from("sat:*".to_string())));
+    }
+}
@@ -0,0 +1,60 @@
+/*
+Portions Copyright 2019-2021 ZomboDB, LLC.
+Portions Copyright 2021-2022 Technology Concepts & Design, Inc. <support@tcdi.com>
+
+All rights reserved.
+
+Use of this source code is governed by the MIT license that can be found in the LICENSE file.
+*/
+
+#[cfg(any(test, feature = "pg_test"))]
+#[pgrx::pg_schema]
+mod tests {
+    #[allow(unused_imports)]
+    use crate as pgrx_tests;
+
+    use pgrx::prelude::*;
+    use pgrx::timeout::{sleep, Timeout, TimeoutAction};
+    use std::time::{Duration, Instant};
+
+    #[pg_test]
+    fn test_sleep() {
+        let start = Instant::now();
+        sleep(Duration::from_millis(50));
+        assert!(start.elapsed() >= Duration::from_millis(50));
+    }
+
+    #[pg_test]
+    fn test_flag_timeout() {
+        let timeout = Timeout::register("test_flag", TimeoutAction::Flag);
+        assert_eq!(timeout.name(), "test_flag");
+        assert!(!timeout.fired());
+
+        timeout.enable_after(Duration::from_millis(10));
+        sleep(Duration::from_millis(100));
+        assert!(timeout.fired());
+        assert!(timeout.reset());
+        assert!(!timeout.fired());
+
+        timeout.enable_after(Duration::from_secs(60));
+        timeout.disable();
+        assert!(!timeout.fired());
+    }
+
+    #[pg_test]
+    #[should_panic(expected = "canceling statement due to test_check timeout")]
+    fn test_check_timeout() {
+        let timeout = Timeout::register("test_check", TimeoutAction::Flag);
+        timeout.enable_after(Duration::from_millis(10));
+        sleep(Duration::from_millis(100));
+        timeout.check();
+    }
+
+    #[pg_test]
+    #[should_panic(expected = "canceling statement due to user request")]
+    fn test_cancel_timeout_interrupts_sleep() {
+        let timeout = Timeout::register("test_cancel", TimeoutAction::Cancel);
+        timeout.enable_after(Duration::from_millis(20));
+        sleep(Duration::from_secs(60));
+    }
+}
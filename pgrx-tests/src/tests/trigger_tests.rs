@@ -645,4 +645,112 @@ mod tests {
         let retval = Spi::get_one::<i32>("SELECT id FROM tests.dont_delete;");
         assert_eq!(retval, Ok(Some(1)));
     }
+
+    #[pg_trigger]
+    fn log_transition_tables<'a>(
+        trigger: &'a pgrx::PgTrigger<'a>,
+    ) -> Result<Option<PgHeapTuple<'a, AllocatedByRust>>, Box<dyn Error>> {
+        let sum = |table: Option<pgrx::TransitionTable>| -> Result<Option<i64>, Box<dyn Error>> {
+            match table {
+                Some(table) => {
+                    let mut sum = 0;
+                    for row in table {
+                        sum += row.get_by_name::<i64>("amount")?.unwrap_or_default();
+                    }
+                    Ok(Some(sum))
+                }
+                None => Ok(None),
+            }
+        };
+        let old_count = trigger.old_transition_table().map(|table| table.len() as i64);
+        let new_count = trigger.new_transition_table().map(|table| table.len() as i64);
+        let old_sum = sum(trigger.old_transition_table())?;
+        let new_sum = sum(trigger.new_transition_table())?;
+        Spi::run_with_args(
+            "INSERT INTO tests.transition_log VALUES ($1, $2, $3, $4)",
+            Some(vec![
+                (PgBuiltInOids::INT8OID.oid(), old_count.into_datum()),
+                (PgBuiltInOids::INT8OID.oid(), old_sum.into_datum()),
+                (PgBuiltInOids::INT8OID.oid(), new_count.into_datum()),
+                (PgBuiltInOids::INT8OID.oid(), new_sum.into_datum()),
+            ]),
+        )?;
+        Ok(None)
+    }
+
+    fn create_transition_tables() {
+        Spi::run(
+            r#"
+            CREATE TABLE tests.transition_orders (id int, amount int8);
+            CREATE TABLE tests.transition_log (old_count int8, old_sum int8, new_count int8, new_sum int8);
+        "#,
+        )
+        .expect("SPI failed");
+    }
+
+    #[pg_test]
+    fn after_insert_new_transition_table() {
+        create_transition_tables();
+        Spi::run(
+            r#"
+            CREATE TRIGGER log_inserts
+                AFTER INSERT ON tests.transition_orders
+                REFERENCING NEW TABLE AS inserted
+                FOR EACH STATEMENT
+                EXECUTE PROCEDURE tests.log_transition_tables();
+            INSERT INTO tests.transition_orders VALUES (1, 10), (2, 20), (3, 30);
+        "#,
+        )
+        .expect("SPI failed");
+
+        let retval = Spi::get_three::<i64, i64, i64>(
+            "SELECT new_count, new_sum, old_count FROM tests.transition_log",
+        );
+        assert_eq!(retval, Ok((Some(3), Some(60), None)));
+    }
+
+    #[pg_test]
+    fn after_update_transition_tables() {
+        create_transition_tables();
+        Spi::run(
+            r#"
+            INSERT INTO tests.transition_orders VALUES (1, 10), (2, 20), (3, 30);
+            CREATE TRIGGER log_updates
+                AFTER UPDATE ON tests.transition_orders
+                REFERENCING OLD TABLE AS before NEW TABLE AS after
+                FOR EACH STATEMENT
+                EXECUTE PROCEDURE tests.log_transition_tables();
+            UPDATE tests.transition_orders SET amount = amount * 2 WHERE id > 1;
+        "#,
+        )
+        .expect("SPI failed");
+
+        let retval =
+            Spi::get_two::<i64, i64>("SELECT old_count, old_sum FROM tests.transition_log");
+        assert_eq!(retval, Ok((Some(2), Some(50))));
+        let retval =
+            Spi::get_two::<i64, i64>("SELECT new_count, new_sum FROM tests.transition_log");
+        assert_eq!(retval, Ok((Some(2), Some(100))));
+    }
+
+    #[pg_test]
+    fn after_delete_empty_transition_table() {
+        create_transition_tables();
+        Spi::run(
+            r#"
+            CREATE TRIGGER log_deletes
+                AFTER DELETE ON tests.transition_orders
+                REFERENCING OLD TABLE AS deleted
+                FOR EACH STATEMENT
+                EXECUTE PROCEDURE tests.log_transition_tables();
+            DELETE FROM tests.transition_orders;
+        "#,
+        )
+        .expect("SPI failed");
+
+        let retval = Spi::get_three::<i64, i64, i64>(
+            "SELECT old_count, old_sum, new_count FROM tests.transition_log",
+        );
+        assert_eq!(retval, Ok((Some(0), Some(0), None)));
+    }
 }
@@ -0,0 +1,89 @@
+/*
+Portions Copyright 2019-2021 ZomboDB, LLC.
+Portions Copyright 2021-2022 Technology Concepts & Design, Inc. <support@tcdi.com>
+
+All rights reserved.
+
+Use of this source code is governed by the MIT license that can be found in the LICENSE file.
+*/
+
+#[cfg(any(test, feature = "pg_test"))]
+#[pgrx::pg_schema]
+mod tests {
+    #[allow(unused_imports)]
+    use crate as pgrx_tests;
+
+    use pgrx::prelude::*;
+    use pgrx::{ColumnSpec, TupleDescBuilder, TupleDescriptor};
+
+    #[derive(TupleDescriptor)]
+    #[allow(dead_code)]
+    struct Dog {
+        name: String,
+        age: Option<i32>,
+        r#type: pgrx::Numeric<10, 2>,
+    }
+
+    fn record_out(datum: pg_sys::Datum) -> String {
+        unsafe {
+            let mut output_func = pg_sys::InvalidOid;
+            let mut is_varlena = false;
+            pg_sys::getTypeOutputInfo(pg_sys::RECORDOID, &mut output_func, &mut is_varlena);
+            let cstr = pg_sys::OidOutputFunctionCall(output_func, datum);
+            core::ffi::CStr::from_ptr(cstr).to_str().unwrap().to_string()
+        }
+    }
+
+    #[pg_test]
+    fn test_build_tupdesc() {
+        let tupdesc = TupleDescBuilder::new()
+            .column(ColumnSpec::new::<i64>("id").not_null())
+            .column(ColumnSpec::with_type_oid("label", pg_sys::VARCHAROID).typmod(20 + 4))
+            .build();
+
+        assert_eq!(tupdesc.len(), 2);
+        assert_eq!(tupdesc.oid(), pg_sys::RECORDOID);
+        assert_eq!(tupdesc.typmod(), -1);
+
+        let id = tupdesc.get(0).unwrap();
+        assert_eq!(id.name(), "id");
+        assert_eq!(id.type_oid().value(), pg_sys::INT8OID);
+        assert!(id.attnotnull);
+
+        let label = tupdesc.get(1).unwrap();
+        assert_eq!(label.name(), "label");
+        assert_eq!(label.type_oid().value(), pg_sys::VARCHAROID);
+        assert_eq!(label.type_mod(), 24);
+        assert!(!label.attnotnull);
+    }
+
+    #[pg_test]
+    fn test_blessed_tupdesc_makes_records() {
+        let tupdesc = TupleDescBuilder::new()
+            .column(ColumnSpec::new::<i32>("id"))
+            .column(ColumnSpec::new::<&str>("name"))
+            .bless();
+        assert!(tupdesc.typmod() >= 0);
+
+        let tuple =
+            PgHeapTuple::from_datums(tupdesc, [1i32.into_datum(), "dog".into_datum()]).unwrap();
+        assert_eq!(record_out(tuple.into_composite_datum().unwrap()), "(1,dog)");
+    }
+
+    #[pg_test]
+    fn test_derive_tuple_descriptor() {
+        assert_eq!(
+            Dog::columns(),
+            vec![
+                ColumnSpec::new::<String>("name").not_null(),
+                ColumnSpec::new::<i32>("age"),
+                ColumnSpec::with_type_oid("type", pg_sys::NUMERICOID).not_null(),
+            ]
+        );
+
+        let tupdesc = Dog::tuple_desc();
+        assert_eq!(tupdesc.len(), 3);
+        assert_eq!(tupdesc.get(2).unwrap().name(), "type");
+        assert!(tupdesc.typmod() >= 0);
+    }
+}
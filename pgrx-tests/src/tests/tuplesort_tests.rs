@@ -0,0 +1,154 @@
+/*
+Portions Copyright 2019-2021 ZomboDB, LLC.
+Portions Copyright 2021-2022 Technology Concepts & Design, Inc. <support@tcdi.com>
+
+All rights reserved.
+
+Use of this source code is governed by the MIT license that can be found in the LICENSE file.
+*/
+
+#[cfg(any(test, feature = "pg_test"))]
+#[pgrx::pg_schema]
+mod tests {
+    #[allow(unused_imports)]
+    use crate as pgrx_tests;
+
+    use pgrx::prelude::*;
+    use pgrx::tuplesort::{DatumSorter, SortKey, SortMethod, SortOrder, TupleSorter};
+    use pgrx::PgTupleDesc;
+
+    const WORDS: [&str; 8] = ["pear", "Apple", "fig", "apple", "Banana", "kiwi", "banana", "Fig"];
+
+    #[pg_test]
+    fn test_datum_sorter() {
+        let values = [5, 3, 9, 1, 7];
+        let mut sorter = DatumSorter::<i32>::new(SortOrder::Ascending);
+        values.iter().for_each(|value| sorter.push(Some(*value)));
+        sorter.push(None);
+        assert_eq!(6, sorter.len());
+        let sorted = sorter.sort();
+        assert_eq!(SortMethod::Quicksort, sorted.stats().method);
+        assert!(!sorted.stats().spilled);
+        assert_eq!(
+            vec![Some(1), Some(3), Some(5), Some(7), Some(9), None],
+            sorted.collect::<Vec<_>>()
+        );
+
+        let mut sorter = DatumSorter::<i32>::new(SortOrder::Descending);
+        values.iter().for_each(|value| sorter.push(Some(*value)));
+        sorter.push(None);
+        assert_eq!(
+            vec![None, Some(9), Some(7), Some(5), Some(3), Some(1)],
+            sorter.sort().collect::<Vec<_>>()
+        );
+    }
+
+    #[pg_test]
+    fn test_datum_sorter_sorts_like_sql() -> Result<(), spi::Error> {
+        let mut sorter = DatumSorter::<String>::new(SortOrder::Ascending);
+        WORDS.iter().for_each(|word| sorter.push(Some(word.to_string())));
+        let sorted = sorter.sort().flatten().collect::<Vec<_>>();
+
+        let expected = Spi::get_one_with_args::<Vec<String>>(
+            "SELECT array_agg(w ORDER BY w) FROM unnest($1) w",
+            vec![(PgBuiltInOids::TEXTARRAYOID.oid(), WORDS.to_vec().into_datum())],
+        )?;
+        assert_eq!(expected, Some(sorted));
+        Ok(())
+    }
+
+    #[pg_test]
+    fn test_datum_sorter_bounded() {
+        let mut sorter = DatumSorter::<i64>::new(SortOrder::Descending);
+        sorter.set_bound(3);
+        (0..10_000i64).map(|i| (i * 7919) % 10_007).for_each(|value| sorter.push(Some(value)));
+        let sorted = sorter.sort();
+        assert_eq!(SortMethod::TopNHeapsort, sorted.stats().method);
+        assert_eq!(vec![10_006, 10_005, 10_004], sorted.flatten().collect::<Vec<_>>());
+    }
+
+    #[pg_test]
+    fn test_datum_sorter_spills() -> Result<(), spi::Error> {
+        Spi::run("SET LOCAL work_mem = '64kB'")?;
+        let mut sorter = DatumSorter::<String>::new(SortOrder::Ascending);
+        (0..50_000).rev().for_each(|i| sorter.push(Some(format!("{i:08}"))));
+        let mut sorted = sorter.sort();
+        let stats = sorted.stats();
+        assert!(stats.spilled);
+        assert!(matches!(stats.method, SortMethod::ExternalSort | SortMethod::ExternalMerge));
+        assert!(stats.space_used_kb > 0);
+
+        assert_eq!(Some(Some("00000000".to_string())), sorted.next());
+        assert_eq!(Some(Some("00001001".to_string())), sorted.nth(1000));
+        assert_eq!(48_998, sorted.count());
+        Ok(())
+    }
+
+    #[pg_test(error = "could not identify an ordering operator for type json")]
+    fn test_datum_sorter_unordered_type() {
+        DatumSorter::<pgrx::Json>::new(SortOrder::Ascending);
+    }
+
+    #[pg_test]
+    fn test_tuple_sorter() -> Result<(), spi::Error> {
+        Spi::run("CREATE TYPE tests.fruit AS (name text, count int)")?;
+        let tupdesc = PgTupleDesc::for_composite_type("tests.fruit").unwrap();
+        // by count descending, then by name
+        let mut sorter = TupleSorter::new(
+            &tupdesc,
+            &[SortKey::new(2, SortOrder::Descending), SortKey::new(1, SortOrder::Ascending)],
+        );
+        for (i, word) in WORDS.iter().enumerate() {
+            let count = if i == 5 { None } else { Some(word.len() as i32) };
+            let tuple =
+                PgHeapTuple::from_datums(tupdesc.clone(), [word.into_datum(), count.into_datum()])
+                    .unwrap();
+            sorter.push(&tuple);
+        }
+        let sorted = sorter
+            .sort()
+            .map(|tuple| tuple.get_by_name::<String>("name").unwrap().unwrap())
+            .collect::<Vec<_>>();
+
+        let expected = Spi::get_one_with_args::<Vec<String>>(
+            "SELECT array_agg(name ORDER BY count DESC, name)
+               FROM unnest($1) WITH ORDINALITY AS w(name, i),
+                    LATERAL (SELECT CASE WHEN i = 6 THEN NULL ELSE length(name) END) AS c(count)",
+            vec![(PgBuiltInOids::TEXTARRAYOID.oid(), WORDS.to_vec().into_datum())],
+        )?;
+        assert_eq!(expected, Some(sorted));
+        Ok(())
+    }
+
+    #[pg_test]
+    fn test_tuple_sorter_bounded_nulls_last() -> Result<(), spi::Error> {
+        Spi::run("CREATE TYPE tests.scored AS (id int, score float8)")?;
+        let tupdesc = PgTupleDesc::for_composite_type("tests.scored").unwrap();
+        let mut sorter = TupleSorter::new(
+            &tupdesc,
+            &[SortKey::new(2, SortOrder::Descending).nulls_first(false)],
+        );
+        sorter.set_bound(2);
+        for id in 0..100 {
+            let score = if id % 10 == 0 { None } else { Some(id as f64 / 2.0) };
+            let tuple =
+                PgHeapTuple::from_datums(tupdesc.clone(), [id.into_datum(), score.into_datum()])
+                    .unwrap();
+            sorter.push(&tuple);
+        }
+        let sorted = sorter.sort();
+        assert_eq!(SortMethod::TopNHeapsort, sorted.stats().method);
+        let ids =
+            sorted.map(|tuple| tuple.get_by_index::<i32>(1.try_into().unwrap()).unwrap().unwrap());
+        assert_eq!(vec![99, 98], ids.collect::<Vec<_>>());
+        Ok(())
+    }
+
+    #[pg_test(error = "there's no attribute 3 to sort by")]
+    fn test_tuple_sorter_bad_key() -> Result<(), spi::Error> {
+        Spi::run("CREATE TYPE tests.pair AS (a int, b int)")?;
+        let tupdesc = PgTupleDesc::for_composite_type("tests.pair").unwrap();
+        TupleSorter::new(&tupdesc, &[SortKey::new(3, SortOrder::Ascending)]);
+        Ok(())
+    }
+}
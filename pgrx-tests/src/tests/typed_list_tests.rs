@@ -0,0 +1,63 @@
+/*
+Portions Copyright 2019-2021 ZomboDB, LLC.
+Portions Copyright 2021-2022 Technology Concepts & Design, Inc. <support@tcdi.com>
+
+All rights reserved.
+
+Use of this source code is governed by the MIT license that can be found in the LICENSE file.
+*/
+
+#[cfg(any(test, feature = "pg_test"))]
+#[pgrx::pg_schema]
+mod tests {
+    #[allow(unused_imports)]
+    use crate as pgrx_tests;
+
+    use pgrx::pg_sys::AsPgCStr;
+    use pgrx::prelude::*;
+    use pgrx::typed_list::List;
+
+    #[pg_test]
+    fn test_int_list() {
+        let mut list = List::<i32>::new();
+        assert!(list.is_empty());
+        assert!(list.as_ptr().is_null());
+        assert_eq!(list.first(), None);
+
+        list.extend(1..=5);
+        assert_eq!(list.len(), 5);
+        assert_eq!(list.get(2), Some(3));
+        assert_eq!(list.last(), Some(5));
+        assert_eq!(list.get(5), None);
+        assert_eq!(unsafe { (*list.as_ptr()).type_ }, pg_sys::NodeTag_T_IntList);
+
+        list.concat(vec![6, 7].into());
+        assert_eq!(Vec::from(list), vec![1, 2, 3, 4, 5, 6, 7]);
+    }
+
+    #[pg_test]
+    fn test_oid_list() {
+        let list: List<pg_sys::Oid> = vec![pg_sys::INT4OID, pg_sys::TEXTOID].into();
+        let list = unsafe { List::<pg_sys::Oid>::from_pg(list.into_pg()) };
+        assert_eq!(list.to_vec(), vec![pg_sys::INT4OID, pg_sys::TEXTOID]);
+        assert_eq!(format!("{list:?}"), format!("{:?}", vec![pg_sys::INT4OID, pg_sys::TEXTOID]));
+    }
+
+    #[pg_test]
+    fn test_pointer_list() {
+        let names = ["a", "b", "c"]
+            .map(|name| unsafe { pg_sys::makeString(name.as_pg_cstr()).cast::<pg_sys::Node>() });
+        let list = names.iter().copied().collect::<List<*mut pg_sys::Node>>();
+        assert_eq!(list.len(), 3);
+        for (value, expected) in list.iter().zip(names) {
+            assert_eq!(value, expected);
+        }
+        assert_eq!(unsafe { (*list.as_ptr()).type_ }, pg_sys::NodeTag_T_List);
+    }
+
+    #[pg_test(error = "list has node tag")]
+    fn test_wrong_kind_of_list() {
+        let list: List<i32> = vec![1, 2, 3].into();
+        let _ = unsafe { List::<pg_sys::Oid>::from_pg(list.into_pg()) };
+    }
+}
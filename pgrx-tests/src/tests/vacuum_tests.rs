@@ -0,0 +1,119 @@
+/*
+Portions Copyright 2019-2021 ZomboDB, LLC.
+Portions Copyright 2021-2022 Technology Concepts & Design, Inc. <support@tcdi.com>
+
+All rights reserved.
+
+Use of this source code is governed by the MIT license that can be found in the LICENSE file.
+*/
+use pgrx::prelude::*;
+
+#[pg_guard]
+#[no_mangle]
+/// Creates a table and runs `VACUUM (FREEZE, SKIP_LOCKED)` on it, outside of any transaction
+pub extern "C" fn bgworker_vacuum(_arg: pg_sys::Datum) {
+    use pgrx::bgworkers::*;
+    use pgrx::vacuum::{vacuum_relation, VacuumOptions};
+    use std::time::Duration;
+    BackgroundWorker::attach_signal_handlers(SignalWakeFlags::SIGHUP | SignalWakeFlags::SIGTERM);
+    BackgroundWorker::connect_worker_to_spi(
+        Some(crate::framework::get_pg_dbname()),
+        Some(crate::framework::get_pg_user().as_str()),
+    );
+
+    let relid = BackgroundWorker::transaction(|| {
+        Spi::run("CREATE TABLE tests.vacuum_frozen (id int)")?;
+        Spi::run("INSERT INTO tests.vacuum_frozen SELECT generate_series(1, 500)")?;
+        Spi::get_one::<pg_sys::Oid>("SELECT 'tests.vacuum_frozen'::regclass::oid")
+    })
+    .expect("bgworker transaction failed")
+    .expect("no oid for tests.vacuum_frozen");
+    vacuum_relation(relid, VacuumOptions { freeze: true, skip_locked: true, ..Default::default() });
+    while BackgroundWorker::wait_latch(Some(Duration::from_millis(100))) {}
+}
+
+#[cfg(any(test, feature = "pg_test"))]
+#[pgrx::pg_schema]
+mod tests {
+    #[allow(unused_imports)]
+    use crate as pgrx_tests;
+
+    use pgrx::prelude::*;
+    use pgrx::vacuum::{analyze_relation, vacuum_relation, AnalyzeOptions, VacuumOptions};
+
+    fn create_table() -> pg_sys::Oid {
+        Spi::run("CREATE TABLE tests.vacuum_me (id int, body text)").unwrap();
+        Spi::run(
+            "INSERT INTO tests.vacuum_me SELECT i, 'row ' || i FROM generate_series(1, 500) i",
+        )
+        .unwrap();
+        Spi::get_one::<pg_sys::Oid>("SELECT 'tests.vacuum_me'::regclass::oid").unwrap().unwrap()
+    }
+
+    #[pg_test]
+    fn test_analyze_relation() {
+        let relid = create_table();
+        analyze_relation(relid, AnalyzeOptions::default());
+
+        let reltuples = Spi::get_one::<f32>(
+            "SELECT reltuples FROM pg_class WHERE oid = 'tests.vacuum_me'::regclass",
+        );
+        assert_eq!(reltuples, Ok(Some(500.0)));
+        let stats = Spi::get_one::<i64>(
+            "SELECT count(*) FROM pg_stats WHERE schemaname = 'tests' AND tablename = 'vacuum_me'",
+        );
+        assert_eq!(stats, Ok(Some(2)));
+    }
+
+    #[pg_test]
+    fn test_analyze_relation_skip_locked() {
+        let relid = create_table();
+        analyze_relation(relid, AnalyzeOptions { skip_locked: true, verbose: true });
+    }
+
+    #[pg_test(error = "vacuum_relation() cannot be called while a transaction is in progress")]
+    fn test_vacuum_relation_in_transaction() {
+        let relid = create_table();
+        vacuum_relation(relid, VacuumOptions { freeze: true, ..Default::default() });
+    }
+
+    #[pg_test]
+    fn test_vacuum_relation_freeze_skip_locked() {
+        use pgrx::bgworkers::BackgroundWorkerBuilder;
+        use std::time::{Duration, Instant};
+
+        let (worker, _) = BackgroundWorkerBuilder::new("vacuum_bgworker")
+            .set_library("pgrx_tests")
+            .set_function("bgworker_vacuum")
+            .enable_spi_access()
+            .load_dynamic_and_wait()
+            .expect("the worker didn't start");
+        worker.terminate().wait_for_shutdown().expect("aborted shutdown");
+
+        // vacuuming counted every row, which only VACUUM and ANALYZE do
+        let reltuples = Spi::get_one::<f32>(
+            "SELECT reltuples FROM pg_class WHERE oid = 'tests.vacuum_frozen'::regclass",
+        );
+        assert_eq!(reltuples, Ok(Some(500.0)));
+
+        // and the statistics report it, once they've caught up
+        let deadline = Instant::now() + Duration::from_secs(10);
+        loop {
+            Spi::run("SELECT pg_stat_clear_snapshot()").unwrap();
+            let vacuum_count = Spi::get_one::<i64>(
+                "SELECT vacuum_count FROM pg_stat_all_tables \
+                 WHERE relid = 'tests.vacuum_frozen'::regclass",
+            );
+            if vacuum_count == Ok(Some(1)) {
+                break;
+            }
+            assert!(Instant::now() < deadline, "vacuum_count is {vacuum_count:?}, not 1");
+            std::thread::sleep(Duration::from_millis(100));
+        }
+    }
+
+    #[pg_test(error = "relation with OID 0 does not exist")]
+    fn test_analyze_missing_relation() {
+        analyze_relation(pg_sys::Oid::INVALID, AnalyzeOptions::default());
+    }
+}
@@ -0,0 +1,54 @@
+/*
+Portions Copyright 2019-2021 ZomboDB, LLC.
+Portions Copyright 2021-2022 Technology Concepts & Design, Inc. <support@tcdi.com>
+
+All rights reserved.
+
+Use of this source code is governed by the MIT license that can be found in the LICENSE file.
+*/
+
+#[cfg(any(test, feature = "pg_test"))]
+#[pgrx::pg_schema]
+mod tests {
+    #[allow(unused_imports)]
+    use crate as pgrx_tests;
+
+    use pgrx::prelude::*;
+    use pgrx::Value;
+
+    #[pg_test]
+    fn test_value_from_typed() {
+        assert_eq!(Value::Int4(42), Value::from_typed(42i32));
+        assert_eq!(Value::Text("hello".into()), Value::from_typed("hello"));
+        assert_eq!(Value::Null, Value::from_typed(None::<i64>));
+        assert_eq!(Value::Bytea(vec![1, 2, 3]), Value::from_typed(vec![1u8, 2, 3]));
+    }
+
+    #[pg_test]
+    fn test_value_output_function() {
+        assert_eq!("t", Value::Bool(true).to_string());
+        assert_eq!("1.5", Value::Float8(1.5).to_string());
+        assert_eq!("\\x0102", Value::Bytea(vec![1, 2]).to_string());
+        assert_eq!("NULL", Value::Null.to_string());
+        assert_eq!(None, Value::Null.to_output_string());
+    }
+
+    #[pg_test]
+    fn test_value_other_fallback() -> Result<(), pgrx::spi::Error> {
+        Spi::connect(|client| {
+            let row = client
+                .select("SELECT '1 day'::interval, '(1,2)'::point", None, None)?
+                .next()
+                .expect("no rows");
+
+            let interval = row.get_value(1)?;
+            assert!(matches!(interval, Value::Other { oid: pg_sys::INTERVALOID, .. }));
+            assert_eq!("1 day", interval.to_string());
+
+            let point = row.get_value(2)?;
+            assert_eq!(pg_sys::POINTOID, point.type_oid());
+            assert_eq!(serde_json::json!("(1,2)"), serde_json::to_value(&point).unwrap());
+            Ok(())
+        })
+    }
+}
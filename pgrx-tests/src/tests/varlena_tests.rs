@@ -0,0 +1,90 @@
+/*
+Portions Copyright 2019-2021 ZomboDB, LLC.
+Portions Copyright 2021-2022 Technology Concepts & Design, Inc. <support@tcdi.com>
+
+All rights reserved.
+
+Use of this source code is governed by the MIT license that can be found in the LICENSE file.
+*/
+
+#[cfg(any(test, feature = "pg_test"))]
+#[pgrx::pg_schema]
+mod tests {
+    #[allow(unused_imports)]
+    use crate as pgrx_tests;
+
+    use pgrx::prelude::*;
+    use pgrx::varlena::{
+        detoast_packed, is_packed, rust_str_to_text_p, set_varsize_short, vardata_any,
+        varsize_any_exhdr,
+    };
+
+    /// A one-dimensional `text[]` of the given elements, each packed with a 1-byte header, without
+    /// padding between them, as Postgres stores short elements
+    fn packed_text_array(elements: &[&str]) -> pg_sys::Datum {
+        const HEADER: usize = std::mem::size_of::<pg_sys::ArrayType>() + 2 * 4;
+        let data_offset = (HEADER + 7) & !7;
+        let size = data_offset + elements.iter().map(|e| e.len() + 1).sum::<usize>();
+        unsafe {
+            let array = pg_sys::palloc0(size) as *mut u8;
+            pgrx::set_varsize(array.cast(), size as i32);
+            let header = array.cast::<pg_sys::ArrayType>();
+            (*header).ndim = 1;
+            (*header).dataoffset = 0;
+            (*header).elemtype = pg_sys::TEXTOID;
+            let dims = array.add(std::mem::size_of::<pg_sys::ArrayType>()).cast::<i32>();
+            *dims = elements.len() as i32;
+            *dims.add(1) = 1;
+
+            let mut ptr = array.add(data_offset);
+            for element in elements {
+                set_varsize_short(ptr.cast(), element.len() as i32 + 1);
+                std::ptr::copy_nonoverlapping(element.as_ptr(), ptr.add(1), element.len());
+                ptr = ptr.add(element.len() + 1);
+            }
+            pg_sys::Datum::from(array)
+        }
+    }
+
+    #[pg_test]
+    fn test_is_packed() {
+        let text = rust_str_to_text_p("Nami");
+        unsafe {
+            assert!(!is_packed(text.as_ptr()));
+
+            let packed = pg_sys::palloc0(5) as *mut pg_sys::varlena;
+            set_varsize_short(packed, 5);
+            std::ptr::copy_nonoverlapping(b"Nami".as_ptr(), packed.cast::<u8>().add(1), 4);
+            assert!(is_packed(packed));
+
+            // nothing to detoast, so it's left as it is
+            let detoasted = detoast_packed(packed);
+            assert_eq!(detoasted, packed);
+            assert_eq!(varsize_any_exhdr(detoasted), 4);
+            assert_eq!(*vardata_any(detoasted).cast::<u8>(), b'N');
+
+            let unpacked = pg_sys::pg_detoast_datum(packed);
+            assert!(!is_packed(unpacked));
+            assert_eq!(varsize_any_exhdr(unpacked), 4);
+        }
+    }
+
+    #[pg_test]
+    fn test_packed_array_elements() {
+        let elements = ["a", "bb", "ccc", "", "eeeee"];
+        let datum = packed_text_array(&elements);
+        let array = unsafe { Array::<&str>::from_datum(datum, false) }.unwrap();
+        assert_eq!(array.iter().flatten().collect::<Vec<_>>(), elements);
+        assert_eq!(array.iter_borrowed().flatten().collect::<Vec<_>>(), elements);
+        assert_eq!(array.get(2), Some(Some("ccc")));
+    }
+
+    #[pg_test]
+    fn test_pgvarlena_new_is_aligned() {
+        let varlena = PgVarlena::<i64>::new().into_pg();
+        unsafe {
+            assert!(!is_packed(varlena));
+            assert_eq!(vardata_any(varlena) as usize % std::mem::align_of::<i64>(), 0);
+        }
+    }
+}
@@ -0,0 +1,47 @@
+/*
+Portions Copyright 2019-2021 ZomboDB, LLC.
+Portions Copyright 2021-2022 Technology Concepts & Design, Inc. <support@tcdi.com>
+
+All rights reserved.
+
+Use of this source code is governed by the MIT license that can be found in the LICENSE file.
+*/
+
+#[cfg(any(test, feature = "pg_test"))]
+#[pgrx::pg_schema]
+mod tests {
+    #[allow(unused_imports)]
+    use crate as pgrx_tests;
+
+    use pgrx::prelude::*;
+    use pgrx::version::Feature;
+    use pgrx::{pg_version, PgVersion};
+
+    #[pg_test]
+    fn test_pg_version_is_the_servers() {
+        let num = Spi::get_one::<String>("SHOW server_version_num").unwrap().unwrap();
+        assert_eq!(pg_version().as_num(), num.parse::<u32>().unwrap());
+        assert_eq!(pg_version().major(), PgVersion::compiled().major());
+    }
+
+    #[pg_test]
+    fn test_pg_version_parts() {
+        let version = PgVersion::from_num(150002);
+        assert_eq!(version, PgVersion::new(15, 2));
+        assert_eq!((version.major(), version.minor()), (15, 2));
+        assert_eq!(version.to_string(), "15.2");
+        assert!(version.at_least(15, 2));
+        assert!(version.at_least(14, 9));
+        assert!(!version.at_least(15, 3));
+        assert!(PgVersion::new(11, 19) < version);
+    }
+
+    #[pg_test]
+    fn test_pg_version_supports() {
+        assert!(PgVersion::new(15, 0).supports(Feature::CustomRmgr));
+        assert!(!PgVersion::new(14, 7).supports(Feature::CustomRmgr));
+        assert!(PgVersion::new(14, 0).supports(Feature::Multiranges));
+        assert!(pg_version().supports(Feature::Procedures));
+        assert_eq!(pg_version().supports(Feature::Merge), pg_version().major() >= 15);
+    }
+}
@@ -0,0 +1,133 @@
+/*
+Portions Copyright 2019-2021 ZomboDB, LLC.
+Portions Copyright 2021-2022 Technology Concepts & Design, Inc. <support@tcdi.com>
+
+All rights reserved.
+
+Use of this source code is governed by the MIT license that can be found in the LICENSE file.
+*/
+
+#[cfg(any(test, feature = "pg_test"))]
+#[pgrx::pg_schema]
+mod tests {
+    #[allow(unused_imports)]
+    use crate as pgrx_tests;
+
+    use pgrx::prelude::*;
+    use serde::{Deserialize, Serialize};
+
+    /// How `VersionedDog` was stored before it was `#[versioned]`
+    #[derive(Serialize, Deserialize, PostgresType)]
+    pub struct UnversionedDog {
+        name: String,
+    }
+
+    /// How `VersionedDog` was stored at version 2
+    #[derive(Serialize, Deserialize, PostgresType)]
+    #[versioned]
+    pub struct VersionedDogV2 {
+        name: String,
+        treats: i64,
+    }
+
+    impl VersionedType for VersionedDogV2 {
+        const VERSION: u8 = 2;
+    }
+
+    #[derive(Debug, PartialEq, Serialize, Deserialize, PostgresType)]
+    #[versioned]
+    pub struct VersionedDog {
+        name: String,
+        treats: i64,
+        good: bool,
+    }
+
+    impl From<UnversionedDog> for VersionedDog {
+        fn from(dog: UnversionedDog) -> Self {
+            VersionedDog { name: dog.name, treats: 0, good: true }
+        }
+    }
+
+    impl From<VersionedDogV2> for VersionedDog {
+        fn from(dog: VersionedDogV2) -> Self {
+            VersionedDog { name: dog.name, treats: dog.treats, good: true }
+        }
+    }
+
+    impl VersionedType for VersionedDog {
+        const VERSION: u8 = 3;
+
+        fn migrations() -> Migrations<Self> {
+            Migrations::new().from_version::<UnversionedDog>(0).from_version::<VersionedDogV2>(2)
+        }
+    }
+
+    /// A `VersionedDog` as it was stored by an older version of it
+    fn stored_as<T: IntoDatum>(dog: T) -> Option<VersionedDog> {
+        unsafe { VersionedDog::from_datum(dog.into_datum().unwrap(), false) }
+    }
+
+    #[pg_test]
+    fn test_envelope() {
+        let dog = VersionedDog { name: "Nami".to_string(), treats: 1, good: true };
+        let datum = dog.into_datum().unwrap();
+        unsafe {
+            let varlena = datum.cast_mut_ptr::<pg_sys::varlena>();
+            let data = pgrx::vardata_any(varlena) as *const u8;
+            // the envelope's tag, then its version
+            assert_eq!(*data, 0xff);
+            assert_eq!(*data.add(1), 3);
+        }
+    }
+
+    #[pg_test]
+    fn test_round_trip() {
+        let dog = Spi::get_one::<VersionedDog>(
+            r#"SELECT '{"name": "Brandy", "treats": 2, "good": true}'::VersionedDog"#,
+        );
+        assert_eq!(
+            dog,
+            Ok(Some(VersionedDog { name: "Brandy".to_string(), treats: 2, good: true }))
+        );
+    }
+
+    #[pg_test]
+    fn test_migrate_unversioned() {
+        let dog = stored_as(UnversionedDog { name: "Nami".to_string() });
+        assert_eq!(dog, Some(VersionedDog { name: "Nami".to_string(), treats: 0, good: true }));
+    }
+
+    #[pg_test]
+    fn test_migrate_older_version() {
+        let dog = stored_as(VersionedDogV2 { name: "Nami".to_string(), treats: 5 });
+        assert_eq!(dog, Some(VersionedDog { name: "Nami".to_string(), treats: 5, good: true }));
+    }
+
+    #[pg_test]
+    fn test_migrate_stored() {
+        Spi::run("CREATE TABLE tests.kennel (dog VersionedDogV2)").unwrap();
+        Spi::run(r#"INSERT INTO tests.kennel VALUES ('{"name": "Brandy", "treats": 7}')"#).unwrap();
+        let dog = Spi::connect(|client| {
+            let row = client.select("SELECT dog FROM tests.kennel", None, None)?.first();
+            let datum = row.get_datum_by_ordinal(1)?.unwrap();
+            Ok::<_, pgrx::spi::Error>(unsafe { VersionedDog::from_datum(datum, false) })
+        });
+        assert_eq!(
+            dog,
+            Ok(Some(VersionedDog { name: "Brandy".to_string(), treats: 7, good: true }))
+        );
+    }
+
+    #[pg_test(
+        error = "no migration from version 4 of type `VersionedDog` to its current version 3"
+    )]
+    fn test_newer_version() {
+        let dog = VersionedDog { name: "Nami".to_string(), treats: 1, good: true };
+        let datum = dog.into_datum().unwrap();
+        unsafe {
+            let varlena = datum.cast_mut_ptr::<pg_sys::varlena>();
+            *(pgrx::vardata_any(varlena) as *mut u8).add(1) = 4;
+            VersionedDog::from_datum(datum, false);
+        }
+    }
+}
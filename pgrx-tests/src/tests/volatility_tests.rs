@@ -0,0 +1,46 @@
+/*
+Portions Copyright 2019-2021 ZomboDB, LLC.
+Portions Copyright 2021-2022 Technology Concepts & Design, Inc. <support@tcdi.com>
+
+All rights reserved.
+
+Use of this source code is governed by the MIT license that can be found in the LICENSE file.
+*/
+
+#[cfg(any(test, feature = "pg_test"))]
+#[pgrx::pg_schema]
+mod tests {
+    #[allow(unused_imports)]
+    use crate as pgrx_tests;
+
+    use pgrx::prelude::*;
+    use pgrx::volatility::{set_volatility_lint, volatility_lint, VolatilityLint};
+
+    #[pg_extern(immutable)]
+    fn immutable_with_spi() -> i32 {
+        Spi::get_one::<i32>("SELECT 42").unwrap().unwrap()
+    }
+
+    #[pg_extern(stable)]
+    fn stable_with_spi() -> i32 {
+        Spi::get_one::<i32>("SELECT 42").unwrap().unwrap()
+    }
+
+    #[pg_test]
+    fn test_volatility_lint_off_by_default() {
+        assert_eq!(VolatilityLint::Off, volatility_lint());
+        assert_eq!(Ok(Some(42)), Spi::get_one::<i32>("SELECT tests.immutable_with_spi()"));
+    }
+
+    #[pg_test]
+    fn test_volatility_lint_ignores_stable() {
+        set_volatility_lint(VolatilityLint::Error);
+        assert_eq!(Ok(Some(42)), Spi::get_one::<i32>("SELECT tests.stable_with_spi()"));
+    }
+
+    #[pg_test(error = "IMMUTABLE function `immutable_with_spi` uses SPI")]
+    fn test_volatility_lint_immutable_spi() {
+        set_volatility_lint(VolatilityLint::Error);
+        Spi::get_one::<i32>("SELECT tests.immutable_with_spi()").unwrap();
+    }
+}
@@ -0,0 +1,76 @@
+/*
+Portions Copyright 2019-2021 ZomboDB, LLC.
+Portions Copyright 2021-2022 Technology Concepts & Design, Inc. <support@tcdi.com>
+
+All rights reserved.
+
+Use of this source code is governed by the MIT license that can be found in the LICENSE file.
+*/
+
+#[cfg(any(test, feature = "pg_test"))]
+#[pgrx::pg_schema]
+mod tests {
+    #[allow(unused_imports)]
+    use crate as pgrx_tests;
+
+    use pgrx::prelude::*;
+    use pgrx::wal;
+    use std::time::{Duration, Instant};
+
+    fn sql_lsn(function: &str) -> Option<pg_sys::XLogRecPtr> {
+        Spi::get_one::<i64>(&format!("SELECT (pg_catalog.{}() - '0/0'::pg_lsn)::int8", function))
+            .unwrap()
+            .map(|lsn| lsn as _)
+    }
+
+    #[pg_test]
+    fn test_wal_lsns_on_primary() {
+        assert!(!wal::is_in_recovery());
+        assert_eq!(wal::replay_lsn(), None);
+
+        let flush = wal::flush_lsn().unwrap();
+        let insert = wal::insert_lsn().unwrap();
+        assert!(insert >= wal::write_lsn().unwrap());
+        assert!(wal::write_lsn().unwrap() >= flush);
+        assert!(sql_lsn("pg_current_wal_insert_lsn").unwrap() >= insert);
+        assert!(sql_lsn("pg_current_wal_flush_lsn").unwrap() >= flush);
+        assert!(wal::applied_lsn() >= flush);
+    }
+
+    #[pg_test]
+    fn test_wal_insert_lsn_advances() {
+        let before = wal::insert_lsn().unwrap();
+        Spi::run(
+            "CREATE TABLE tests.wal_advance (v int); INSERT INTO tests.wal_advance VALUES (1)",
+        )
+        .unwrap();
+        assert!(wal::insert_lsn().unwrap() > before);
+    }
+
+    #[pg_test]
+    fn test_lag_bytes() {
+        assert_eq!(wal::lag_bytes(0x2000, 0x1800), 0x800);
+        assert_eq!(wal::lag_bytes(0x1800, 0x2000), 0);
+    }
+
+    #[pg_test]
+    fn test_wait_for_applied_lsn() {
+        let start = Instant::now();
+        assert!(wal::wait_for_lsn(wal::flush_lsn().unwrap(), Some(Duration::ZERO)));
+        assert!(wal::wait_for_lsn(wal::flush_lsn().unwrap(), None));
+        assert!(start.elapsed() < Duration::from_secs(1));
+    }
+
+    #[pg_test]
+    fn test_wait_for_lsn_times_out() {
+        let far_future = wal::insert_lsn().unwrap() + (1 << 40);
+        let start = Instant::now();
+        assert!(!wal::wait_for_lsn(far_future, Some(Duration::from_millis(50))));
+        assert!(start.elapsed() >= Duration::from_millis(50));
+    }
+
+    #[pg_test]
+    fn test_no_standbys() {
+        assert_eq!(wal::standbys().unwrap(), vec![]);
+    }
+}
@@ -0,0 +1,138 @@
+/*
+Portions Copyright 2019-2021 ZomboDB, LLC.
+Portions Copyright 2021-2022 Technology Concepts & Design, Inc. <support@tcdi.com>
+
+All rights reserved.
+
+Use of this source code is governed by the MIT license that can be found in the LICENSE file.
+*/
+use pgrx::prelude::*;
+use pgrx::window::{WindowObject, WindowSeek};
+
+#[pg_extern(window)]
+fn window_row_number(fcinfo: pg_sys::FunctionCallInfo) -> i64 {
+    let window = unsafe { WindowObject::from_fcinfo(fcinfo) };
+    window.current_position() + 1
+}
+
+#[pg_extern(window)]
+fn window_lag(
+    fcinfo: pg_sys::FunctionCallInfo,
+    _value: Option<i32>,
+    _offset: Option<i32>,
+) -> Option<i32> {
+    let window = unsafe { WindowObject::from_fcinfo(fcinfo) };
+    let offset = window.get_func_arg_current::<i32>(1).unwrap_or(1);
+    window.get_func_arg_in_partition::<i32>(0, -offset, WindowSeek::Current, false).flatten()
+}
+
+#[pg_extern(window)]
+fn window_partition_size(fcinfo: pg_sys::FunctionCallInfo) -> i64 {
+    let window = unsafe { WindowObject::from_fcinfo(fcinfo) };
+    window.partition_row_count()
+}
+
+#[pg_extern(window)]
+fn window_first_in_frame(
+    fcinfo: pg_sys::FunctionCallInfo,
+    _value: Option<String>,
+) -> Option<String> {
+    let window = unsafe { WindowObject::from_fcinfo(fcinfo) };
+    window.get_func_arg_in_frame::<String>(0, 0, WindowSeek::Head, false).flatten()
+}
+
+/// A running count of the distinct values, in `ORDER BY` order, that uses partition-local state
+#[pg_extern(window)]
+fn window_dense_rank(fcinfo: pg_sys::FunctionCallInfo) -> i64 {
+    let mut window = unsafe { WindowObject::from_fcinfo(fcinfo) };
+    let position = window.current_position();
+    let new_peer_group = position == 0 || !window.rows_are_peers(position - 1, position);
+    let rank = window.partition_local::<i64>();
+    if new_peer_group {
+        *rank += 1;
+    }
+    *rank
+}
+
+#[pg_extern]
+fn not_a_window_function(fcinfo: pg_sys::FunctionCallInfo) -> i64 {
+    let window = unsafe { WindowObject::from_fcinfo(fcinfo) };
+    window.current_position()
+}
+
+#[cfg(any(test, feature = "pg_test"))]
+#[pgrx::pg_schema]
+mod tests {
+    #[allow(unused_imports)]
+    use crate as pgrx_tests;
+
+    use pgrx::prelude::*;
+
+    /// Does `ours` compute the same column as `theirs`, over the same window?
+    fn same_as(ours: &str, theirs: &str) -> bool {
+        let query = format!(
+            "SELECT bool_and(ours IS NOT DISTINCT FROM theirs) FROM (
+                SELECT {ours} AS ours, {theirs} AS theirs
+                  FROM (VALUES (1, 'a', 1), (1, 'b', 1), (1, 'c', 2), (2, 'd', 3), (2, 'e', 3),
+                               (2, NULL, 4), (2, 'g', 4), (3, 'h', 5)) AS t(p, v, o)
+            ) results"
+        );
+        Spi::get_one::<bool>(&query).unwrap().unwrap()
+    }
+
+    #[pg_test]
+    fn test_window_row_number() {
+        assert!(same_as(
+            "window_row_number() OVER (PARTITION BY p ORDER BY v)",
+            "row_number() OVER (PARTITION BY p ORDER BY v)"
+        ));
+    }
+
+    #[pg_test]
+    fn test_window_lag() {
+        assert!(same_as("window_lag(o, 1) OVER (ORDER BY v)", "lag(o, 1) OVER (ORDER BY v)"));
+        assert!(same_as(
+            "window_lag(o, 2) OVER (PARTITION BY p ORDER BY v)",
+            "lag(o, 2) OVER (PARTITION BY p ORDER BY v)"
+        ));
+    }
+
+    #[pg_test]
+    fn test_window_partition_size() {
+        assert!(same_as(
+            "window_partition_size() OVER (PARTITION BY p)",
+            "count(*) OVER (PARTITION BY p)"
+        ));
+    }
+
+    #[pg_test]
+    fn test_window_first_in_frame() {
+        assert!(same_as(
+            "window_first_in_frame(v) OVER (ORDER BY o ROWS BETWEEN 1 PRECEDING AND CURRENT ROW)",
+            "first_value(v) OVER (ORDER BY o ROWS BETWEEN 1 PRECEDING AND CURRENT ROW)"
+        ));
+    }
+
+    #[pg_test]
+    fn test_window_partition_local() {
+        assert!(same_as(
+            "window_dense_rank() OVER (PARTITION BY p ORDER BY o)",
+            "dense_rank() OVER (PARTITION BY p ORDER BY o)"
+        ));
+    }
+
+    #[pg_test]
+    fn test_window_function_is_declared_window() {
+        assert_eq!(
+            Ok(Some("w".into())),
+            Spi::get_one::<String>(
+                "SELECT prokind::text FROM pg_proc WHERE proname = 'window_row_number'"
+            )
+        );
+    }
+
+    #[pg_test(error = "window function called in non-window context")]
+    fn test_window_object_outside_window() {
+        Spi::get_one::<i64>("SELECT not_a_window_function()").unwrap();
+    }
+}
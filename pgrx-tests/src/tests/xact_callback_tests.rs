@@ -14,7 +14,13 @@ mod tests {
     use crate as pgrx_tests;
 
     use pgrx::prelude::*;
-    use pgrx::{info, register_xact_callback, PgXactCallbackEvent};
+    use pgrx::{
+        info, register_session_subxact_callback, register_session_xact_callback,
+        register_two_phase_callback, register_xact_callback, PgSubXactCallbackEvent,
+        PgTwoPhaseEvent, PgXactCallbackEvent,
+    };
+    use std::cell::RefCell;
+    use std::rc::Rc;
 
     #[test]
     fn make_idea_happy() {}
@@ -23,4 +29,66 @@ mod tests {
     fn test_xact_callback() {
         register_xact_callback(PgXactCallbackEvent::Abort, || info!("TESTMSG: Called on abort"));
     }
+
+    #[pg_test]
+    fn test_session_xact_callback() {
+        let guard = register_session_xact_callback(|event| {
+            if event == PgXactCallbackEvent::Abort {
+                info!("TESTMSG: Called on abort")
+            }
+        });
+        drop(guard);
+    }
+
+    /// start and release an internal subtransaction, restoring our memory context and resource owner
+    fn run_subtransaction() {
+        unsafe {
+            let oldcontext = pg_sys::CurrentMemoryContext;
+            let oldowner = pg_sys::CurrentResourceOwner;
+            pg_sys::BeginInternalSubTransaction(std::ptr::null());
+            pg_sys::ReleaseCurrentSubTransaction();
+            pg_sys::MemoryContextSwitchTo(oldcontext);
+            pg_sys::CurrentResourceOwner = oldowner;
+        }
+    }
+
+    #[pg_test]
+    fn test_session_subxact_callback() {
+        let events = Rc::new(RefCell::new(Vec::new()));
+        let guard = register_session_subxact_callback({
+            let events = Rc::clone(&events);
+            move |event, _, _| events.borrow_mut().push(event)
+        });
+
+        run_subtransaction();
+        assert_eq!(
+            vec![
+                PgSubXactCallbackEvent::StartSub,
+                PgSubXactCallbackEvent::PreCommitSub,
+                PgSubXactCallbackEvent::CommitSub
+            ],
+            *events.borrow()
+        );
+
+        // once the guard is dropped, the callback is no longer called
+        drop(guard);
+        run_subtransaction();
+        assert_eq!(3, events.borrow().len());
+    }
+
+    #[pg_test]
+    fn test_two_phase_callback() {
+        let events = Rc::new(RefCell::new(Vec::new()));
+        let guard = register_two_phase_callback({
+            let events = Rc::clone(&events);
+            move |event: PgTwoPhaseEvent, gid: &str| {
+                events.borrow_mut().push((event, gid.to_string()))
+            }
+        });
+
+        // an ordinary subtransaction is not part of any two-phase commit
+        run_subtransaction();
+        assert!(events.borrow().is_empty());
+        drop(guard);
+    }
 }
@@ -266,12 +266,141 @@ CREATE AGGREGATE DemoSum (
 );
 ```
 
+## Moving-Aggregate Mode
+
+An aggregate used as a window function over a moving frame (eg. `ROWS BETWEEN 1 PRECEDING AND
+CURRENT ROW`) is normally recomputed from scratch for each row.  Implementing
+[`Aggregate::moving_state`] and [`Aggregate::moving_state_inverse`], which removes a value that's
+left the frame, lets Postgres update it instead.  The moving-aggregate state is
+[`Aggregate::MovingState`], which may differ from [`Aggregate::State`], starting from
+[`Aggregate::MOVING_INITIAL_CONDITION`].  [`Aggregate::moving_finalize`] finalizes it, if the
+state isn't already the result.
+
+`moving_state`, `moving_state_inverse` and `MovingState` must be given together.
+
+```rust
+# use pgrx::prelude::*;
+#
+pub struct DemoSum;
+
+#[pg_aggregate]
+impl Aggregate for DemoSum {
+    const INITIAL_CONDITION: Option<&'static str> = Some("0");
+    const MOVING_INITIAL_CONDITION: Option<&'static str> = Some("0");
+    type Args = i32;
+    type State = i32;
+    type MovingState = i32;
+
+    fn state(current: Self::State, arg: Self::Args, _fcinfo: pg_sys::FunctionCallInfo) -> Self::State {
+        current + arg
+    }
+
+    fn moving_state(
+        current: Self::MovingState,
+        arg: Self::Args,
+        _fcinfo: pg_sys::FunctionCallInfo,
+    ) -> Self::MovingState {
+        current + arg
+    }
+
+    fn moving_state_inverse(
+        current: Self::MovingState,
+        arg: Self::Args,
+        _fcinfo: pg_sys::FunctionCallInfo,
+    ) -> Self::MovingState {
+        current - arg
+    }
+}
+```
+
+Creates:
+
+```sql
+CREATE AGGREGATE DemoSum (
+    integer /* i32 */
+)
+(
+    SFUNC = "demo_sum_state", /* aggregate::DemoSum::state */
+    STYPE = integer, /* i32 */
+    INITCOND = '0', /* aggregate::DemoSum::INITIAL_CONDITION */
+    MSFUNC = "demo_sum_moving_state", /* aggregate::DemoSum::moving_state */
+    MINVFUNC = "demo_sum_moving_state_inverse", /* aggregate::DemoSum::moving_state_inverse */
+    MINITCOND = '0', /* aggregate::DemoSum::MOVING_INITIAL_CONDITION */
+    MSTYPE = integer /* aggregate::DemoSum::MovingState = i32 */
+);
+```
+
+## Ordered-Set Aggregates
+
+Setting [`Aggregate::ORDERED_SET`] makes an ordered-set aggregate, called like
+`SELECT agg(direct_args) WITHIN GROUP (ORDER BY args) FROM tab`.  Its direct arguments are
+declared by [`Aggregate::OrderedSetArgs`], and passed to `finalize`.
+
+Postgres doesn't sort the values for it.  An [`OrderedSetSort`] kept in the aggregate's
+[`Internal`](crate::datum::Internal) state sorts them as the `ORDER BY` asks, with Postgres' own
+tuplesort, so they needn't fit in memory:
+
+```rust
+use pgrx::prelude::*;
+use pgrx::aggregate::OrderedSetSort;
+use pgrx::{Aggregate, Internal};
+
+pub struct DemoMedian;
+
+#[pg_aggregate]
+impl Aggregate for DemoMedian {
+    type Args = f64;
+    type State = Internal;
+    type Finalize = Option<f64>;
+    const ORDERED_SET: bool = true;
+
+    fn state(
+        mut current: Self::State,
+        arg: Self::Args,
+        fcinfo: pg_sys::FunctionCallInfo,
+    ) -> Self::State {
+        unsafe { OrderedSetSort::<f64>::from_state(&mut current, fcinfo) }.push(Some(arg));
+        current
+    }
+
+    fn finalize(
+        mut current: Self::State,
+        _direct_args: Self::OrderedSetArgs,
+        fcinfo: pg_sys::FunctionCallInfo,
+    ) -> Self::Finalize {
+        let sort = unsafe { OrderedSetSort::<f64>::from_state(&mut current, fcinfo) };
+        let middle = sort.len().checked_sub(1)? / 2;
+        sort.sorted().nth(middle as usize).flatten()
+    }
+}
+```
+
+Creates:
+
+```sql
+CREATE AGGREGATE DemoMedian (
+    ORDER BY double precision /* f64 */
+)
+(
+    SFUNC = "demo_median_state", /* aggregate::DemoMedian::state */
+    STYPE = internal, /* pgrx::datum::Internal */
+    FINALFUNC = "demo_median_finalize" /* aggregate::DemoMedian::finalize */
+);
+```
+
+Setting [`Aggregate::HYPOTHETICAL`] as well makes a hypothetical-set aggregate, whose last direct
+arguments are a hypothetical row of the aggregated arguments, like `rank()` takes.
+
 */
 
-use crate::error;
+use crate as pgrx; // for #[pg_guard] support from within ourself
+use crate::compat::{list_length, list_nth_ptr};
+use crate::datum::Internal;
 use crate::memcxt::PgMemoryContexts;
 use crate::pg_sys::{AggCheckCallContext, CurrentMemoryContext, FunctionCallInfo, MemoryContext};
 use crate::pgbox::PgBox;
+use crate::{error, pg_guard, pg_sys, FromDatum, IntoDatum};
+use std::marker::PhantomData;
 
 pub use pgrx_sql_entity_graph::{FinalizeModify, ParallelOption};
 
@@ -450,3 +579,162 @@ where
         }
     }
 }
+
+/// The values an ordered-set aggregate is given, sorted as its `WITHIN GROUP (ORDER BY ...)` clause
+/// asks, by Postgres' tuplesort
+///
+/// An `OrderedSetSort` lives in the aggregate's [`Internal`] state, and is started for the
+/// aggregate's `ORDER BY` column when it's first used.  Values are [`push`](OrderedSetSort::push)ed
+/// onto it by `state`, and `finalize` reads them back [`sorted`](OrderedSetSort::sorted).  The
+/// tuplesort spills to disk once the values outgrow `work_mem`, and is ended when Postgres is done
+/// with the group.
+///
+/// Only a single `ORDER BY` column is supported.
+pub struct OrderedSetSort<T> {
+    sortstate: *mut pg_sys::Tuplesortstate,
+    typoid: pg_sys::Oid,
+    len: u64,
+    sorted: bool,
+    __marker: PhantomData<T>,
+}
+
+impl<T: IntoDatum + FromDatum> OrderedSetSort<T> {
+    /// The `OrderedSetSort` kept in an ordered-set aggregate's `state`, started if this is the
+    /// first time it's used.  Call it from either `state` or `finalize`.
+    ///
+    /// # Safety
+    ///
+    /// `state` must only ever have held an `OrderedSetSort<T>`, and `fcinfo` must be the
+    /// `FunctionCallInfo` the aggregate's function was called with.  `T` must be the Rust type of
+    /// the aggregate's `ORDER BY` column.
+    ///
+    /// # Panics
+    ///
+    /// Panics if this isn't an ordered-set aggregate with a single `ORDER BY` column
+    pub unsafe fn from_state(state: &mut Internal, fcinfo: FunctionCallInfo) -> &mut Self {
+        if state.initialized() {
+            return state.get_mut::<Self>().unwrap();
+        }
+
+        let aggref = pg_sys::AggGetAggref(fcinfo);
+        // AGGKIND_NORMAL is 'n', ordered-set aggregates are 'o' or 'h'
+        assert!(
+            !aggref.is_null() && (*aggref).aggkind as u8 != b'n',
+            "OrderedSetSort can only be used by an ordered-set aggregate"
+        );
+        let sortlist = (*aggref).aggorder;
+        assert_eq!(1, list_length(sortlist), "OrderedSetSort can only sort by a single column");
+        let clause = list_nth_ptr(sortlist, 0).unwrap().cast::<pg_sys::SortGroupClause>();
+        let tle = pg_sys::get_sortgroupclause_tle(clause, (*aggref).args);
+        let typoid = pg_sys::exprType((*tle).expr.cast());
+
+        let sortstate = pg_sys::tuplesort_begin_datum(
+            typoid,
+            (*clause).sortop,
+            pg_sys::exprCollation((*tle).expr.cast()),
+            (*clause).nulls_first,
+            pg_sys::work_mem,
+            std::ptr::null_mut(),
+            #[cfg(any(feature = "pg11", feature = "pg12", feature = "pg13", feature = "pg14"))]
+            false,
+            #[cfg(feature = "pg15")]
+            {
+                pg_sys::TUPLESORT_NONE as _
+            },
+        );
+        let sort = state.insert(OrderedSetSort {
+            sortstate,
+            typoid,
+            len: 0,
+            sorted: false,
+            __marker: PhantomData,
+        });
+        pg_sys::AggRegisterCallback(
+            fcinfo,
+            Some(end_sort),
+            pg_sys::Datum::from(&mut sort.sortstate as *mut *mut pg_sys::Tuplesortstate),
+        );
+        sort
+    }
+
+    /// Add a value, or a NULL, to be sorted
+    ///
+    /// # Panics
+    ///
+    /// Panics if the values have already been sorted
+    pub fn push(&mut self, value: Option<T>) {
+        assert!(!self.sorted, "an OrderedSetSort's values have already been sorted");
+        let datum = value.and_then(IntoDatum::into_datum);
+        unsafe {
+            pg_sys::tuplesort_putdatum(
+                self.sortstate,
+                datum.unwrap_or_else(|| pg_sys::Datum::from(0)),
+                datum.is_none(),
+            )
+        }
+        self.len += 1;
+    }
+
+    /// The number of values pushed
+    pub fn len(&self) -> u64 {
+        self.len
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len == 0
+    }
+
+    /// Sort the values, to read them back in order.  `None` is a NULL value.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the values have already been sorted, as they can only be read once
+    pub fn sorted(&mut self) -> SortedValues<'_, T> {
+        assert!(!self.sorted, "an OrderedSetSort's values have already been sorted");
+        self.sorted = true;
+        unsafe { pg_sys::tuplesort_performsort(self.sortstate) }
+        SortedValues { sort: self }
+    }
+}
+
+/// The sorted values of an [`OrderedSetSort`]
+pub struct SortedValues<'a, T> {
+    sort: &'a mut OrderedSetSort<T>,
+}
+
+impl<T: FromDatum> Iterator for SortedValues<'_, T> {
+    type Item = Option<T>;
+
+    fn next(&mut self) -> Option<Option<T>> {
+        let (mut datum, mut is_null) = (pg_sys::Datum::from(0), false);
+        unsafe {
+            if !pg_sys::tuplesort_getdatum(
+                self.sort.sortstate,
+                true,
+                &mut datum,
+                &mut is_null,
+                std::ptr::null_mut(),
+            ) {
+                return None;
+            }
+            Some(T::from_polymorphic_datum(datum, is_null, self.sort.typoid))
+        }
+    }
+
+    fn nth(&mut self, n: usize) -> Option<Option<T>> {
+        if n > 0 && !unsafe { pg_sys::tuplesort_skiptuples(self.sort.sortstate, n as _, true) } {
+            return None;
+        }
+        self.next()
+    }
+}
+
+/// Ends an [`OrderedSetSort`]'s tuplesort, when its aggregate's group is done with
+#[pg_guard]
+unsafe extern "C" fn end_sort(arg: pg_sys::Datum) {
+    let sortstate = arg.cast_mut_ptr::<*mut pg_sys::Tuplesortstate>();
+    if !(*sortstate).is_null() {
+        pg_sys::tuplesort_end(*sortstate);
+        *sortstate = std::ptr::null_mut();
+    }
+}
@@ -0,0 +1,193 @@
+/*
+Portions Copyright 2019-2021 ZomboDB, LLC.
+Portions Copyright 2021-2022 Technology Concepts & Design, Inc. <support@tcdi.com>
+
+All rights reserved.
+
+Use of this source code is governed by the MIT license that can be found in the LICENSE file.
+*/
+
+//! Memory-mapped, read-only assets, like models or dictionaries, shared by every backend
+//!
+//! [`load()`] maps a file read-only, and caches the mapping, so a backend maps each asset only
+//! once however many times it's used.  Mappings are shared, so their pages are in memory once for
+//! the whole host, not once per backend.  An extension in `shared_preload_libraries` can
+//! [`load()`] its assets in `_PG_init()`, so the postmaster maps them and every backend inherits
+//! the mappings as it's forked.
+//!
+//! ```rust,no_run
+//! use pgrx::asset;
+//! use pgrx::data_dir::ExtensionDataDir;
+//!
+//! # fn foo() -> std::io::Result<()> {
+//! let dir = ExtensionDataDir::open("my_extension")?;
+//! let model = asset::load(dir.file_path("model.onnx")?)?;
+//! let header = &model[..16];
+//! # Ok(())
+//! # }
+//! ```
+//!
+//! A cached mapping is replaced when its file changes, which [`load()`] notices by the file having
+//! a different inode, size, or modification time.  Files must be replaced by renaming a new file
+//! over them, as [`ExtensionDataDir::write()`](crate::data_dir::ExtensionDataDir::write) does,
+//! and never modified in place, which would change the contents of the mappings being read.  The
+//! old mapping stays valid until the last [`Arc`] of it is dropped.
+use once_cell::sync::Lazy;
+use std::collections::HashMap;
+use std::fs::{self, File};
+use std::io;
+use std::ops::Deref;
+use std::os::unix::fs::MetadataExt;
+use std::os::unix::io::AsRawFd;
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, Mutex};
+
+static CACHE: Lazy<Mutex<HashMap<PathBuf, Arc<MappedAsset>>>> = Lazy::new(Default::default);
+
+/// What identifies a version of a file:  replacing it changes its inode, and changing it in
+/// place its size or modification time
+#[derive(Debug, Copy, Clone, Eq, PartialEq)]
+struct FileVersion {
+    dev: u64,
+    ino: u64,
+    len: u64,
+    mtime: i64,
+    mtime_nsec: i64,
+}
+
+impl FileVersion {
+    fn of(metadata: &fs::Metadata) -> Self {
+        FileVersion {
+            dev: metadata.dev(),
+            ino: metadata.ino(),
+            len: metadata.len(),
+            mtime: metadata.mtime(),
+            mtime_nsec: metadata.mtime_nsec(),
+        }
+    }
+}
+
+/// A file memory-mapped read-only
+#[derive(Debug)]
+pub struct MappedAsset {
+    ptr: *const u8,
+    len: usize,
+    path: PathBuf,
+    version: FileVersion,
+}
+
+// SAFETY:  the mapping is read-only, and unmapped only when the `MappedAsset` is dropped
+unsafe impl Send for MappedAsset {}
+unsafe impl Sync for MappedAsset {}
+
+impl MappedAsset {
+    /// Map the file at `path`, without caching the mapping
+    pub fn map(path: impl AsRef<Path>) -> io::Result<Self> {
+        let path = path.as_ref();
+        let file = File::open(path)?;
+        let metadata = file.metadata()?;
+        let len = usize::try_from(metadata.len())
+            .map_err(|e| io::Error::new(io::ErrorKind::InvalidData, e))?;
+        let ptr = if len == 0 {
+            // mmap() refuses to map nothing
+            std::ptr::NonNull::dangling().as_ptr()
+        } else {
+            // SAFETY:  `file` is open for reading, and is `len` bytes long
+            let ptr = unsafe {
+                libc::mmap(
+                    std::ptr::null_mut(),
+                    len,
+                    libc::PROT_READ,
+                    libc::MAP_SHARED,
+                    file.as_raw_fd(),
+                    0,
+                )
+            };
+            if ptr == libc::MAP_FAILED {
+                return Err(io::Error::last_os_error());
+            }
+            ptr as *const u8
+        };
+        Ok(MappedAsset { ptr, len, path: path.to_path_buf(), version: FileVersion::of(&metadata) })
+    }
+
+    /// The mapped file's contents
+    pub fn as_bytes(&self) -> &[u8] {
+        // SAFETY:  we mapped `len` bytes at `ptr`, which stay mapped until we're dropped
+        unsafe { std::slice::from_raw_parts(self.ptr, self.len) }
+    }
+
+    /// The path of the mapped file
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+
+    /// Has the file at our path changed, or been removed, since it was mapped?
+    pub fn is_stale(&self) -> bool {
+        match fs::metadata(&self.path) {
+            Ok(metadata) => FileVersion::of(&metadata) != self.version,
+            Err(_) => true,
+        }
+    }
+}
+
+impl Deref for MappedAsset {
+    type Target = [u8];
+
+    fn deref(&self) -> &[u8] {
+        self.as_bytes()
+    }
+}
+
+impl AsRef<[u8]> for MappedAsset {
+    fn as_ref(&self) -> &[u8] {
+        self.as_bytes()
+    }
+}
+
+impl Drop for MappedAsset {
+    fn drop(&mut self) {
+        if self.len > 0 {
+            // SAFETY:  we mapped `len` bytes at `ptr`, and nothing borrows them anymore
+            unsafe {
+                libc::munmap(self.ptr as *mut _, self.len);
+            }
+        }
+    }
+}
+
+/// The file at `path`, mapped by this backend, or by the postmaster before it was forked, unless
+/// it's changed since.  Otherwise it's mapped now, and the mapping cached
+pub fn load(path: impl AsRef<Path>) -> io::Result<Arc<MappedAsset>> {
+    let path = path.as_ref();
+    let mut cache = CACHE.lock().unwrap();
+    if let Some(asset) = cache.get(path) {
+        if !asset.is_stale() {
+            return Ok(Arc::clone(asset));
+        }
+    }
+    let asset = match MappedAsset::map(path) {
+        Ok(asset) => Arc::new(asset),
+        Err(e) => {
+            cache.remove(path);
+            return Err(e);
+        }
+    };
+    cache.insert(path.to_path_buf(), Arc::clone(&asset));
+    Ok(asset)
+}
+
+/// Drop this backend's cached mapping of the file at `path`, so the next [`load()`] maps it again
+pub fn invalidate(path: impl AsRef<Path>) {
+    CACHE.lock().unwrap().remove(path.as_ref());
+}
+
+/// Drop all of this backend's cached mappings
+pub fn invalidate_all() {
+    CACHE.lock().unwrap().clear();
+}
+
+/// Is the file at `path` mapped in this backend's cache?
+pub fn is_cached(path: impl AsRef<Path>) -> bool {
+    CACHE.lock().unwrap().contains_key(path.as_ref())
+}
@@ -7,14 +7,27 @@ All rights reserved.
 Use of this source code is governed by the MIT license that can be found in the LICENSE file.
 */
 use once_cell::sync::OnceCell;
+use uuid::Uuid;
 
 pub struct PgAtomic<T> {
     inner: OnceCell<*mut T>,
+    name: OnceCell<&'static str>,
 }
 
 impl<T> PgAtomic<T> {
     pub const fn new() -> Self {
-        Self { inner: OnceCell::new() }
+        Self { inner: OnceCell::new(), name: OnceCell::new() }
+    }
+
+    /// Create an empty atomic whose shared memory is found by `name`, the same in every backend.
+    /// See [`pg_shmem!`](crate::pg_shmem)
+    pub const fn new_named(name: &'static str) -> Self {
+        Self { inner: OnceCell::new(), name: OnceCell::with_value(name) }
+    }
+
+    /// Get the name of the PgAtomic's shared memory
+    pub fn get_name(&self) -> &'static str {
+        self.name.get_or_init(|| Box::leak(Uuid::new_v4().to_string().into_boxed_str()))
     }
 }
 
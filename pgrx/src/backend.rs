@@ -0,0 +1,151 @@
+/*
+Portions Copyright 2019-2021 ZomboDB, LLC.
+Portions Copyright 2021-2022 Technology Concepts & Design, Inc. <support@tcdi.com>
+
+All rights reserved.
+
+Use of this source code is governed by the MIT license that can be found in the LICENSE file.
+*/
+
+//! Callbacks for when a backend starts and exits, to set up and tear down per-session state
+//!
+//! Rust `static`s are never dropped when a backend exits:  Postgres ends the process with
+//! `exit()`, after running its own exit callbacks.  Per-session resources that need tearing down,
+//! like connections to external services or files to flush, should be torn down from
+//! [`before_shmem_exit()`] or [`on_proc_exit()`] callbacks.
+//!
+//! Postgres runs a backend's exit callbacks in two phases:
+//!
+//! 1. [`before_shmem_exit()`] callbacks run first, while the backend is still attached to shared
+//!    memory.  Locks, shared memory, and (once any open transaction is aborted) the catalogs can
+//!    still be used.
+//! 2. [`on_proc_exit()`] callbacks run after the backend has detached from shared memory, so they
+//!    can only clean up process-local state.
+//!
+//! Within each phase, the callbacks registered through this module run in the reverse of the
+//! order they were registered in, like Postgres' own, and all of them run at the point the first
+//! of them was registered, relative to callbacks registered directly with Postgres.  A callback
+//! that panics is reported as a WARNING, and the remaining callbacks still run.  Postgres turns
+//! an ERROR raised while the backend is exiting into a FATAL, so callbacks should avoid them.
+//!
+//! ## Examples
+//!
+//! ```rust,no_run
+//! use pgrx::backend::{on_backend_startup, on_proc_exit};
+//! use pgrx::prelude::*;
+//!
+//! #[pg_guard]
+//! pub extern "C" fn _PG_init() {
+//!     on_backend_startup(|| {
+//!         // open this backend's connection to the cache service
+//!     });
+//!     on_proc_exit(|| {
+//!         // and close it
+//!     });
+//! }
+//! ```
+use crate as pgrx; // for #[pg_guard] support from within ourself
+use crate::prelude::*;
+use std::cell::{Cell, RefCell};
+use std::panic::AssertUnwindSafe;
+use std::thread::LocalKey;
+
+type Callback = Box<dyn FnOnce()>;
+
+// a backend is forked from the postmaster's one thread, so it inherits what `_PG_init()`
+// registered there
+thread_local! {
+    static STARTUP_CALLBACKS: RefCell<Vec<Callback>> = const { RefCell::new(Vec::new()) };
+    static STARTED: Cell<bool> = const { Cell::new(false) };
+    static BEFORE_SHMEM_EXIT_CALLBACKS: RefCell<Option<Vec<Callback>>> = const { RefCell::new(None) };
+    static PROC_EXIT_CALLBACKS: RefCell<Option<Vec<Callback>>> = const { RefCell::new(None) };
+}
+
+static mut PREV_CLIENT_AUTHENTICATION_HOOK: pg_sys::ClientAuthentication_hook_type = None;
+
+/// Run `callback` once in each backend, when it starts.
+///
+/// When an extension is loaded by `shared_preload_libraries`, its `_PG_init()` runs in the
+/// postmaster, before any backend exists.  `callback` then runs in each client backend, once the
+/// client has authenticated, before it runs any queries.  Background workers don't authenticate,
+/// so they should do their own setup.
+///
+/// Otherwise the extension was loaded into an already running backend, and `callback` runs
+/// immediately.
+pub fn on_backend_startup<F: FnOnce() + 'static>(callback: F) {
+    // SAFETY:  `IsUnderPostmaster` is set once, when the process starts
+    if STARTED.get() || unsafe { pg_sys::IsUnderPostmaster } {
+        STARTED.set(true);
+        callback();
+        return;
+    }
+
+    if STARTUP_CALLBACKS.with(|callbacks| callbacks.borrow().is_empty()) {
+        // SAFETY:  this is the first registration, so our hook isn't installed yet, and the
+        // previous one is only ever read by it
+        unsafe {
+            PREV_CLIENT_AUTHENTICATION_HOOK = pg_sys::ClientAuthentication_hook;
+            pg_sys::ClientAuthentication_hook = Some(client_authentication_hook);
+        }
+    }
+    STARTUP_CALLBACKS.with(|callbacks| callbacks.borrow_mut().push(Box::new(callback)));
+}
+
+#[pg_guard]
+unsafe extern "C" fn client_authentication_hook(port: *mut pg_sys::Port, status: i32) {
+    if let Some(prev) = PREV_CLIENT_AUTHENTICATION_HOOK {
+        prev(port, status);
+    }
+
+    // an unsuccessful authentication has already raised a FATAL error, so this backend is ours
+    STARTED.set(true);
+    for callback in STARTUP_CALLBACKS.with(|callbacks| callbacks.take()) {
+        callback();
+    }
+}
+
+/// Run `callback` when the backend exits, while it can still use shared memory, with
+/// `before_shmem_exit()`
+pub fn before_shmem_exit<F: FnOnce() + 'static>(callback: F) {
+    register(&BEFORE_SHMEM_EXIT_CALLBACKS, callback, |trampoline| unsafe {
+        pg_sys::before_shmem_exit(Some(trampoline), pg_sys::Datum::from(0));
+    });
+}
+
+/// Run `callback` when the backend exits, after it has detached from shared memory, with
+/// `on_proc_exit()`
+pub fn on_proc_exit<F: FnOnce() + 'static>(callback: F) {
+    register(&PROC_EXIT_CALLBACKS, callback, |trampoline| unsafe {
+        pg_sys::on_proc_exit(Some(trampoline), pg_sys::Datum::from(1));
+    });
+}
+
+type Trampoline = unsafe extern "C" fn(i32, pg_sys::Datum);
+
+fn register<F: FnOnce() + 'static>(
+    callbacks: &'static LocalKey<RefCell<Option<Vec<Callback>>>>,
+    callback: F,
+    install: impl FnOnce(Trampoline),
+) {
+    if callbacks.with(|callbacks| callbacks.borrow().is_none()) {
+        install(run_exit_callbacks);
+    }
+    callbacks.with(|callbacks| {
+        callbacks.borrow_mut().get_or_insert_with(Vec::new).push(Box::new(callback))
+    });
+}
+
+/// Our one exit callback for each phase.  Its argument says which phase it is
+unsafe extern "C" fn run_exit_callbacks(_code: i32, phase: pg_sys::Datum) {
+    let callbacks = if phase.value() == 0 {
+        BEFORE_SHMEM_EXIT_CALLBACKS.with(|callbacks| callbacks.take())
+    } else {
+        PROC_EXIT_CALLBACKS.with(|callbacks| callbacks.take())
+    };
+
+    for callback in callbacks.unwrap_or_default().into_iter().rev() {
+        if std::panic::catch_unwind(AssertUnwindSafe(callback)).is_err() {
+            warning!("a backend exit callback panicked");
+        }
+    }
+}
@@ -12,8 +12,11 @@ Use of this source code is governed by the MIT license that can be found in the
 //! See: [https://www.postgresql.org/docs/current/bgworker.html](https://www.postgresql.org/docs/current/bgworker.html)
 use crate::pg_sys;
 use pgrx_pg_sys::PgTryBuilder;
+use serde::de::DeserializeOwned;
+use serde::Serialize;
 use std::convert::TryInto;
 use std::ffi::{CStr, CString};
+use std::ops::ControlFlow;
 use std::os::raw::c_char;
 use std::ptr::null_mut;
 use std::sync::atomic::{AtomicBool, Ordering};
@@ -61,6 +64,17 @@ pub enum BgWorkerStartTime {
     RecoveryFinished = pg_sys::BgWorkerStartTime_BgWorkerStart_RecoveryFinished as isize,
 }
 
+/// What woke a background worker in [`BackgroundWorker::wait_latch_loop()`]
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum LatchEvent {
+    /// The timeout passed
+    Timeout,
+    /// The worker's latch was set, by another process or a signal other than SIGHUP or SIGTERM
+    LatchSet,
+    /// The worker received a SIGHUP, and the configuration file has been reloaded
+    ConfigReloaded,
+}
+
 /// Static interface into a running Background Worker
 ///
 /// It also provides a few helper functions as wrappers around the global `pgrx::pg_sys::MyBgworkerEntry`
@@ -102,6 +116,12 @@ impl BackgroundWorker {
         .expect("'extra' is not valid UTF8")
     }
 
+    /// Retrieve the `extra` data provided to the `BackgroundWorkerBuilder` with
+    /// [`BackgroundWorkerBuilder::set_extra_data()`], deserialized
+    pub fn get_extra_data<T: DeserializeOwned>() -> serde_json::Result<T> {
+        serde_json::from_str(BackgroundWorker::get_extra())
+    }
+
     /// Have we received a SIGUP?
     pub fn sighup_received() -> bool {
         unsafe {
@@ -137,6 +157,66 @@ impl BackgroundWorker {
         !BackgroundWorker::sigterm_received()
     }
 
+    /// Run the worker's main loop:  wait on its latch, for at most `timeout` at a time, and call
+    /// `body` with what woke it, until `body` breaks out of the loop or the worker receives a
+    /// SIGTERM.
+    ///
+    /// Returns what `body` broke out of the loop with, or `None` after a SIGTERM.  The loop also
+    /// checks for interrupts after each wait, and exits the process if the postmaster has died.
+    /// The signal handlers must have been attached with
+    /// [`BackgroundWorker::attach_signal_handlers()`] for SIGHUP and SIGTERM to be seen.
+    ///
+    /// ```rust,no_run
+    /// use pgrx::bgworkers::{BackgroundWorker, LatchEvent, SignalWakeFlags};
+    /// use std::ops::ControlFlow;
+    /// use std::time::Duration;
+    ///
+    /// # fn do_some_work() {}
+    /// BackgroundWorker::attach_signal_handlers(SignalWakeFlags::SIGHUP | SignalWakeFlags::SIGTERM);
+    /// BackgroundWorker::wait_latch_loop(Some(Duration::from_secs(10)), |event| {
+    ///     if event != LatchEvent::ConfigReloaded {
+    ///         do_some_work();
+    ///     }
+    ///     ControlFlow::<()>::Continue(())
+    /// });
+    /// ```
+    pub fn wait_latch_loop<R, F: FnMut(LatchEvent) -> ControlFlow<R>>(
+        timeout: Option<Duration>,
+        mut body: F,
+    ) -> Option<R> {
+        unsafe {
+            assert!(!pg_sys::MyBgworkerEntry.is_null(), "BackgroundWorker associated functions can only be called from a registered background worker");
+        }
+        loop {
+            let events = match timeout {
+                Some(t) => wait_latch(
+                    t.as_millis().try_into().unwrap(),
+                    WLflags::WL_LATCH_SET | WLflags::WL_TIMEOUT | WLflags::WL_POSTMASTER_DEATH,
+                ),
+                None => wait_latch(0, WLflags::WL_LATCH_SET | WLflags::WL_POSTMASTER_DEATH),
+            };
+            let events = WLflags::from_bits_truncate(events);
+            if events.contains(WLflags::WL_POSTMASTER_DEATH) {
+                unsafe { pg_sys::proc_exit(1) };
+            }
+            // unlike `sigterm_received()`, leave the flag set for the worker to see after the loop
+            if GOT_SIGTERM.load(Ordering::SeqCst) {
+                return None;
+            }
+
+            let event = if GOT_SIGHUP.swap(false, Ordering::SeqCst) {
+                LatchEvent::ConfigReloaded
+            } else if events.contains(WLflags::WL_TIMEOUT) {
+                LatchEvent::Timeout
+            } else {
+                LatchEvent::LatchSet
+            };
+            if let ControlFlow::Break(result) = body(event) {
+                return Some(result);
+            }
+        }
+    }
+
     /// Is this `BackgroundWorker` allowed to continue?
     pub fn worker_continue() -> bool {
         unsafe {
@@ -191,6 +271,35 @@ impl BackgroundWorker {
         }
     }
 
+    /// Apply the `nice` value and CPU affinity the worker was registered with, by
+    /// [`BackgroundWorkerBuilder::set_nice()`] and [`BackgroundWorkerBuilder::set_cpu_affinity()`].
+    /// A worker that was registered with either should call this first thing.
+    ///
+    /// # Errors
+    ///
+    /// Returns the operating system's error if either can't be applied, such as when the worker
+    /// isn't allowed to lower its `nice` value, or none of its CPUs exist
+    pub fn apply_resource_controls() -> std::io::Result<()> {
+        let controls = unsafe {
+            assert!(!pg_sys::MyBgworkerEntry.is_null(), "BackgroundWorker associated functions can only be called from a registered background worker");
+            ResourceControls::decode_from_extra(&(*pg_sys::MyBgworkerEntry).bgw_extra)
+        };
+        controls.apply()
+    }
+
+    /// Exit, asking the postmaster to restart the worker once its restart time,
+    /// set with [`BackgroundWorkerBuilder::set_restart_time()`], has passed.
+    ///
+    /// A worker that returns from its main function exits successfully instead, and is
+    /// unregistered rather than restarted.  A worker without a restart time is never restarted.
+    pub fn exit_and_restart() -> ! {
+        unsafe {
+            assert!(!pg_sys::MyBgworkerEntry.is_null(), "BackgroundWorker associated functions can only be called from a registered background worker");
+            pg_sys::proc_exit(1);
+        }
+        unreachable!("proc_exit() returned")
+    }
+
     /// Once connected to SPI via `connect_worker_to_spi()`, begin a transaction to
     /// use the `pgrx::Spi` interface. Returns the return value of the `F` function.
     pub fn transaction<F: FnOnce() -> R + std::panic::UnwindSafe + std::panic::RefUnwindSafe, R>(
@@ -379,6 +488,7 @@ pub struct BackgroundWorkerBuilder {
     bgw_extra: String,
     bgw_notify_pid: pg_sys::pid_t,
     shared_memory_startup_fn: Option<unsafe extern "C" fn()>,
+    resource_controls: ResourceControls,
 }
 
 impl BackgroundWorkerBuilder {
@@ -401,6 +511,7 @@ impl BackgroundWorkerBuilder {
             bgw_extra: "".to_string(),
             bgw_notify_pid: 0,
             shared_memory_startup_fn: None,
+            resource_controls: ResourceControls::default(),
         }
     }
 
@@ -441,6 +552,9 @@ impl BackgroundWorkerBuilder {
     /// the interval, in seconds, that postgres should wait before restarting the process,
     /// in case it crashes. It can be `Some(any positive duration value), or
     /// `None`, indicating not to restart the process in case of a crash.
+    ///
+    /// A worker is also restarted after this interval when it exits with
+    /// [`BackgroundWorker::exit_and_restart()`], but not when its main function returns.
     pub fn set_restart_time(mut self: Self, input: Option<Duration>) -> Self {
         self.bgw_restart_time = input;
         self
@@ -520,6 +634,43 @@ impl BackgroundWorkerBuilder {
         self
     }
 
+    /// Typed extra data to be passed to the background worker, which it retrieves with
+    /// [`BackgroundWorker::get_extra_data()`].  It's passed as JSON, in place of the string set
+    /// with [`BackgroundWorkerBuilder::set_extra()`].
+    ///
+    /// ## Example
+    ///
+    /// ```rust,no_run
+    /// use pgrx::bgworkers::BackgroundWorkerBuilder;
+    /// use serde::{Deserialize, Serialize};
+    ///
+    /// #[derive(Serialize, Deserialize)]
+    /// struct Job {
+    ///     table: String,
+    ///     batch_size: u32,
+    /// }
+    ///
+    /// let job = Job { table: "events".into(), batch_size: 1000 };
+    /// BackgroundWorkerBuilder::new("Example")
+    ///     .set_function("background_worker_main")
+    ///     .set_library("example")
+    ///     .set_extra_data(&job)
+    ///     .load_dynamic();
+    /// ```
+    ///
+    /// # Panics
+    ///
+    /// Panics if `data` can't be serialized, or if it serializes to 128 bytes or more
+    pub fn set_extra_data<T: Serialize + ?Sized>(self: Self, data: &T) -> Self {
+        let json = serde_json::to_string(data).expect("failed to serialize extra data");
+        assert!(
+            json.len() < 128,
+            "extra data must serialize to less than 128 bytes, not {}",
+            json.len()
+        );
+        self.set_extra(&json)
+    }
+
     /// PID of a PostgreSQL backend process to which the postmaster should send SIGUSR1
     /// when the process is started or exits. It should be 0 for workers registered at
     /// postmaster startup time, or when the backend registering the worker does not wish
@@ -530,6 +681,35 @@ impl BackgroundWorkerBuilder {
         self
     }
 
+    /// The `nice` value the worker should run with, from -20 (the highest priority) to 19 (the
+    /// lowest).  Without the privilege to, a worker can only raise its `nice` value, which lowers
+    /// its priority.
+    ///
+    /// The worker applies this itself, when it calls
+    /// [`BackgroundWorker::apply_resource_controls()`].
+    #[cfg(unix)]
+    pub fn set_nice(mut self: Self, nice: i32) -> Self {
+        assert!((-20..=19).contains(&nice), "nice must be between -20 and 19");
+        self.resource_controls.nice = Some(nice as i8);
+        self
+    }
+
+    /// The CPUs the worker should run on, such as those of one NUMA node.  CPUs are numbered from
+    /// zero, like `taskset` numbers them.
+    ///
+    /// The worker applies this itself, when it calls
+    /// [`BackgroundWorker::apply_resource_controls()`].
+    ///
+    /// # Panics
+    ///
+    /// Panics if `cpus` is empty
+    #[cfg(target_os = "linux")]
+    pub fn set_cpu_affinity(mut self: Self, cpus: &[usize]) -> Self {
+        assert!(!cpus.is_empty(), "a worker must be allowed to run on at least one CPU");
+        self.resource_controls.cpus = cpus.to_vec();
+        self
+    }
+
     /// Once properly configured, call `load()` to get the BackgroundWorker registered and
     /// started at the proper time by Postgres.
     pub fn load(self: Self) {
@@ -547,15 +727,42 @@ impl BackgroundWorkerBuilder {
     }
 
     /// Once properly configured, call `load_dynamic()` to get the BackgroundWorker registered and started dynamically.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the worker can't be registered, see [`BackgroundWorkerBuilder::try_load_dynamic()`]
     pub fn load_dynamic(self: Self) -> DynamicBackgroundWorker {
+        self.try_load_dynamic().expect(
+            "could not register background worker, consider increasing max_worker_processes",
+        )
+    }
+
+    /// Like [`BackgroundWorkerBuilder::load_dynamic()`], but returns `None` if the worker can't
+    /// be registered, such as when `max_worker_processes` workers already are
+    pub fn try_load_dynamic(self: Self) -> Option<DynamicBackgroundWorker> {
         let mut bgw: pg_sys::BackgroundWorker = (&self).into();
         let mut handle: *mut pg_sys::BackgroundWorkerHandle = null_mut();
 
-        unsafe {
-            pg_sys::RegisterDynamicBackgroundWorker(&mut bgw, &mut handle);
-        };
+        let registered = unsafe { pg_sys::RegisterDynamicBackgroundWorker(&mut bgw, &mut handle) };
 
-        DynamicBackgroundWorker { handle, notify_pid: bgw.bgw_notify_pid }
+        registered.then(|| DynamicBackgroundWorker { handle, notify_pid: bgw.bgw_notify_pid })
+    }
+
+    /// Register the BackgroundWorker dynamically, as with [`BackgroundWorkerBuilder::load_dynamic()`],
+    /// and block until it has started.  The current process is made the worker's notify PID, so it
+    /// can also wait for the worker to shut down.
+    ///
+    /// Returns the worker and its PID, or the worker's status as an error if it didn't start.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the worker can't be registered
+    pub fn load_dynamic_and_wait(
+        self: Self,
+    ) -> Result<(DynamicBackgroundWorker, Pid), BackgroundWorkerStatus> {
+        let worker = self.set_notify_pid(unsafe { pg_sys::MyProcPid }).load_dynamic();
+        let pid = worker.wait_for_startup()?;
+        Ok((worker, pid))
     }
 }
 
@@ -583,7 +790,7 @@ impl<'a> Into<pg_sys::BackgroundWorker> for &'a BackgroundWorkerBuilder {
             bgw_library_name: RpgffiChar::from(&self.bgw_library_name[..]).0,
             bgw_function_name: RpgffiChar::from(&self.bgw_function_name[..]).0,
             bgw_main_arg: self.bgw_main_arg,
-            bgw_extra: RpgffiChar128::from(&self.bgw_extra[..]).0,
+            bgw_extra: self.resource_controls.encode_into_extra(&self.bgw_extra),
             bgw_notify_pid: self.bgw_notify_pid,
         };
 
@@ -591,6 +798,173 @@ impl<'a> Into<pg_sys::BackgroundWorker> for &'a BackgroundWorkerBuilder {
     }
 }
 
+/// Identifies the resource controls that are stored in `bgw_extra`, after its string
+const RESOURCE_CONTROLS_MAGIC: [u8; 2] = [0xfe, 0x01];
+
+/// The `nice` value and CPU affinity a worker applies to itself, which are passed to it after the
+/// string in its `bgw_extra`, so [`BackgroundWorker::get_extra()`] is unaffected
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+struct ResourceControls {
+    nice: Option<i8>,
+    cpus: Vec<usize>,
+}
+
+impl ResourceControls {
+    /// # Panics
+    ///
+    /// Panics if `extra` doesn't leave enough room for the controls
+    fn encode_into_extra(&self, extra: &str) -> [c_char; 128] {
+        let mut encoded = RpgffiChar128::from(extra).0;
+        if *self == ResourceControls::default() {
+            return encoded;
+        }
+
+        let mut mask = vec![0u8; self.cpus.iter().max().map_or(0, |max| max / 8 + 1)];
+        for cpu in &self.cpus {
+            mask[cpu / 8] |= 1 << (cpu % 8);
+        }
+        let mut controls = RESOURCE_CONTROLS_MAGIC.to_vec();
+        controls.extend([self.nice.is_some() as u8, self.nice.unwrap_or(0) as u8]);
+        controls.push(mask.len().try_into().expect("too many CPUs for a background worker"));
+        controls.extend(mask);
+
+        // after the string's terminating NUL
+        let start = extra.len() + 1;
+        assert!(
+            start + controls.len() <= encoded.len(),
+            "`extra` is too long to leave room for the background worker's resource controls"
+        );
+        for (dest, src) in encoded[start..].iter_mut().zip(controls) {
+            *dest = src as c_char;
+        }
+        encoded
+    }
+
+    fn decode_from_extra(extra: &[c_char; 128]) -> Self {
+        let extra = extra.map(|c| c as u8);
+        let Some(nul) = extra.iter().position(|&c| c == 0) else {
+            return ResourceControls::default();
+        };
+        let controls = &extra[nul + 1..];
+        if controls.len() < 5 || controls[..2] != RESOURCE_CONTROLS_MAGIC {
+            return ResourceControls::default();
+        }
+
+        let nice = (controls[2] != 0).then_some(controls[3] as i8);
+        let mask = &controls[5..(5 + controls[4] as usize).min(controls.len())];
+        let cpus =
+            (0..mask.len() * 8).filter(|cpu| mask[cpu / 8] & (1 << (cpu % 8)) != 0).collect();
+        ResourceControls { nice, cpus }
+    }
+
+    fn apply(&self) -> std::io::Result<()> {
+        #[cfg(unix)]
+        if let Some(nice) = self.nice {
+            // SAFETY:  this only changes the priority of this process
+            if unsafe { libc::setpriority(libc::PRIO_PROCESS, 0, nice.into()) } != 0 {
+                return Err(std::io::Error::last_os_error());
+            }
+        }
+
+        #[cfg(target_os = "linux")]
+        if !self.cpus.is_empty() {
+            unsafe {
+                // SAFETY:  `set` is a properly initialized cpu_set_t, and this only changes the
+                // affinity of this process
+                let mut set = std::mem::zeroed::<libc::cpu_set_t>();
+                libc::CPU_ZERO(&mut set);
+                for &cpu in &self.cpus {
+                    libc::CPU_SET(cpu, &mut set);
+                }
+                if libc::sched_setaffinity(0, std::mem::size_of::<libc::cpu_set_t>(), &set) != 0 {
+                    return Err(std::io::Error::last_os_error());
+                }
+            }
+        }
+        Ok(())
+    }
+}
+
+/// A cooperative limit on the share of a CPU a background worker uses
+///
+/// A worker doing heavy computation calls [`CpuBudget::throttle()`] between units of work.  Once
+/// the CPU time it has used since the budget was made exceeds its share of the time that has
+/// passed, `throttle()` sleeps on the worker's latch until it no longer does.  That spreads the
+/// worker's work out over time, leaving CPU for the rest of a shared database host.
+///
+/// ```rust,no_run
+/// use pgrx::bgworkers::{BackgroundWorker, CpuBudget};
+///
+/// # fn do_some_work() {}
+/// // use at most a quarter of a CPU
+/// let mut budget = CpuBudget::new(0.25);
+/// while budget.throttle() {
+///     do_some_work();
+/// }
+/// ```
+#[cfg(unix)]
+#[derive(Debug)]
+pub struct CpuBudget {
+    share: f64,
+    wall_start: std::time::Instant,
+    cpu_start: Duration,
+}
+
+#[cfg(unix)]
+impl CpuBudget {
+    /// A budget of `share` of one CPU, from now on
+    ///
+    /// # Panics
+    ///
+    /// Panics unless `share` is greater than zero and at most one
+    pub fn new(share: f64) -> Self {
+        assert!(share > 0.0 && share <= 1.0, "a CPU budget's share must be in (0, 1]");
+        CpuBudget { share, wall_start: std::time::Instant::now(), cpu_start: process_cpu_time() }
+    }
+
+    /// The CPU time this process has used since the budget was made, or last reset
+    pub fn used(&self) -> Duration {
+        process_cpu_time().saturating_sub(self.cpu_start)
+    }
+
+    /// Start the budget over from now
+    pub fn reset(&mut self) {
+        *self = CpuBudget::new(self.share);
+    }
+
+    /// How long to sleep for, right now, to be within budget
+    pub fn overage(&self) -> Duration {
+        // the wall-clock time it takes to have earned the CPU time that's been used
+        let earned_by = self.used().div_f64(self.share);
+        earned_by.saturating_sub(self.wall_start.elapsed())
+    }
+
+    /// Sleep on the worker's latch until it's within its budget.  Like
+    /// [`BackgroundWorker::wait_latch()`], returns false if the worker has received a SIGTERM and
+    /// should stop.
+    pub fn throttle(&mut self) -> bool {
+        loop {
+            let overage = self.overage();
+            if overage.is_zero() {
+                return true;
+            }
+            if !BackgroundWorker::wait_latch(Some(overage)) {
+                return false;
+            }
+        }
+    }
+}
+
+#[cfg(unix)]
+fn process_cpu_time() -> Duration {
+    unsafe {
+        // SAFETY:  `time` is valid to be written to
+        let mut time = std::mem::zeroed::<libc::timespec>();
+        libc::clock_gettime(libc::CLOCK_PROCESS_CPUTIME_ID, &mut time);
+        Duration::new(time.tv_sec as u64, time.tv_nsec as u32)
+    }
+}
+
 fn wait_latch(timeout: libc::c_long, wakeup_flags: WLflags) -> i32 {
     unsafe {
         let latch = pg_sys::WaitLatch(
@@ -0,0 +1,290 @@
+/*
+Portions Copyright 2019-2021 ZomboDB, LLC.
+Portions Copyright 2021-2022 Technology Concepts & Design, Inc. <support@tcdi.com>
+
+All rights reserved.
+
+Use of this source code is governed by the MIT license that can be found in the LICENSE file.
+*/
+
+//! Reading and writing the binary format of `COPY ... (FORMAT binary)`
+//!
+//! A binary `COPY` stream is a header, rows of length-prefixed fields in each column type's binary
+//! ("send"/"receive") representation, and a trailer.  [`BinaryCopyWriter`] produces one, and
+//! [`BinaryCopyReader`] consumes one, so an extension can make a file `COPY ... FROM` loads
+//! quickly, or read one `COPY ... TO` wrote:
+//!
+//! ```rust,no_run
+//! use pgrx::binary_copy::BinaryCopyWriter;
+//! use pgrx::prelude::*;
+//!
+//! # fn foo() -> std::io::Result<()> {
+//! let file = std::fs::File::create("/tmp/points.copy")?;
+//! let mut writer = BinaryCopyWriter::new(std::io::BufWriter::new(file))?;
+//! for i in 0..1000 {
+//!     writer.write_row(&[
+//!         (PgBuiltInOids::INT4OID.oid(), i.into_datum()),
+//!         (PgBuiltInOids::TEXTOID.oid(), format!("point {}", i).into_datum()),
+//!     ])?;
+//! }
+//! writer.finish()?;
+//! Spi::run("COPY points FROM '/tmp/points.copy' (FORMAT binary)").unwrap();
+//! # Ok(())
+//! # }
+//! ```
+//!
+//! The raw field bytes can also be written and read with [`BinaryCopyWriter::write_raw_row()`]
+//! and [`BinaryCopyReader::read_raw_row()`], without converting them to or from Datums.
+use crate::{pg_sys, varlena, FromDatum, IntoDatum, PgOid};
+use std::io::{self, Read, Write};
+
+/// The signature every binary `COPY` stream starts with
+pub const SIGNATURE: &[u8; 11] = b"PGCOPY\n\xff\r\n\0";
+
+// the header flag saying each row has an OID, which no longer exists as of Postgres 12
+const FLAG_HAS_OIDS: u32 = 1 << 16;
+// flags in the upper half of the header's flags are critical:  a reader must understand them
+const CRITICAL_FLAGS: u32 = 0xffff_0000;
+
+fn invalid_data(message: impl Into<String>) -> io::Error {
+    io::Error::new(io::ErrorKind::InvalidData, message.into())
+}
+
+/// Writes a binary `COPY` stream
+pub struct BinaryCopyWriter<W: Write> {
+    out: W,
+}
+
+impl<W: Write> BinaryCopyWriter<W> {
+    /// Start a stream written to `out`, writing its header
+    pub fn new(mut out: W) -> io::Result<Self> {
+        out.write_all(SIGNATURE)?;
+        out.write_all(&0u32.to_be_bytes())?; // flags
+        out.write_all(&0u32.to_be_bytes())?; // header extension length
+        Ok(BinaryCopyWriter { out })
+    }
+
+    /// Write a row of fields in their binary representations, or `None` for nulls
+    ///
+    /// # Panics
+    ///
+    /// If there are more than `i16::MAX` fields, or a field is longer than `i32::MAX` bytes
+    pub fn write_raw_row(&mut self, fields: &[Option<&[u8]>]) -> io::Result<()> {
+        let count = i16::try_from(fields.len()).expect("too many fields for a COPY row");
+        self.out.write_all(&count.to_be_bytes())?;
+        for field in fields {
+            match field {
+                Some(bytes) => {
+                    let len = i32::try_from(bytes.len()).expect("field too long for COPY");
+                    self.out.write_all(&len.to_be_bytes())?;
+                    self.out.write_all(bytes)?;
+                }
+                None => self.out.write_all(&(-1i32).to_be_bytes())?,
+            }
+        }
+        Ok(())
+    }
+
+    /// Write a row of Datums, each of the type it's paired with, or `None` for nulls.  Each is
+    /// converted with its type's send function
+    ///
+    /// # Panics
+    ///
+    /// If there are more than `i16::MAX` fields.  A type without a send function raises an error
+    pub fn write_row(&mut self, fields: &[(PgOid, Option<pg_sys::Datum>)]) -> io::Result<()> {
+        let sent = fields
+            .iter()
+            .map(|(oid, datum)| datum.map(|datum| unsafe { send(oid.value(), datum) }))
+            .collect::<Vec<_>>();
+        self.write_raw_row(&sent.iter().map(|field| field.as_deref()).collect::<Vec<_>>())
+    }
+
+    /// Write the stream's trailer, returning what it was written to
+    pub fn finish(mut self) -> io::Result<W> {
+        self.out.write_all(&(-1i16).to_be_bytes())?;
+        self.out.flush()?;
+        Ok(self.out)
+    }
+}
+
+/// The binary representation of `datum`, of type `type_oid`
+unsafe fn send(type_oid: pg_sys::Oid, datum: pg_sys::Datum) -> Vec<u8> {
+    let mut send_func = pg_sys::InvalidOid;
+    let mut is_varlena = false;
+    pg_sys::getTypeBinaryOutputInfo(type_oid, &mut send_func, &mut is_varlena);
+    let bytea = pg_sys::OidSendFunctionCall(send_func, datum);
+    let bytes = varlena::varlena_to_byte_slice(bytea).to_vec();
+    pg_sys::pfree(bytea.cast());
+    bytes
+}
+
+/// Reads a binary `COPY` stream
+pub struct BinaryCopyReader<R: Read> {
+    input: R,
+    finished: bool,
+}
+
+impl<R: Read> BinaryCopyReader<R> {
+    /// Start reading the stream `input`, reading its header
+    pub fn new(mut input: R) -> io::Result<Self> {
+        let mut signature = [0u8; 11];
+        input.read_exact(&mut signature)?;
+        if &signature != SIGNATURE {
+            return Err(invalid_data("COPY file signature not recognized"));
+        }
+
+        let flags = read_u32(&mut input)?;
+        if flags & FLAG_HAS_OIDS != 0 {
+            return Err(invalid_data("COPY files with OIDs are not supported"));
+        }
+        if flags & CRITICAL_FLAGS != 0 {
+            return Err(invalid_data("unrecognized critical flags in COPY file header"));
+        }
+
+        // skip the header extension, which has nothing we understand
+        let extension = read_u32(&mut input)?;
+        io::copy(&mut (&mut input).take(extension.into()), &mut io::sink())?;
+
+        Ok(BinaryCopyReader { input, finished: false })
+    }
+
+    /// Read the next row's fields in their binary representations, or `None` for nulls.  Returns
+    /// `None` once the trailer has been read
+    pub fn read_raw_row(&mut self) -> io::Result<Option<Vec<Option<Vec<u8>>>>> {
+        if self.finished {
+            return Ok(None);
+        }
+
+        let mut count = [0u8; 2];
+        self.input.read_exact(&mut count)?;
+        let count = i16::from_be_bytes(count);
+        if count == -1 {
+            self.finished = true;
+            return Ok(None);
+        }
+        if count < 0 {
+            return Err(invalid_data(format!("invalid field count {} in COPY row", count)));
+        }
+
+        (0..count)
+            .map(|_| {
+                let len = read_u32(&mut self.input)? as i32;
+                match len {
+                    -1 => Ok(None),
+                    len if len < 0 => {
+                        Err(invalid_data(format!("invalid field length {} in COPY row", len)))
+                    }
+                    len => {
+                        let mut bytes = vec![0; len as usize];
+                        self.input.read_exact(&mut bytes)?;
+                        Ok(Some(bytes))
+                    }
+                }
+            })
+            .collect::<io::Result<Vec<_>>>()
+            .map(Some)
+    }
+
+    /// Read the next row, converting its fields, one for each of `types`, to Datums with their
+    /// type's receive function.  Returns `None` once the trailer has been read.
+    ///
+    /// The Datums are allocated in the `CurrentMemoryContext`.  A type without a receive function,
+    /// or a field that isn't a valid binary representation of its type, raises an error
+    pub fn read_row(&mut self, types: &[PgOid]) -> io::Result<Option<BinaryCopyRow>> {
+        let fields = match self.read_raw_row()? {
+            Some(fields) => fields,
+            None => return Ok(None),
+        };
+        if fields.len() != types.len() {
+            return Err(invalid_data(format!(
+                "COPY row has {} fields, expected {}",
+                fields.len(),
+                types.len()
+            )));
+        }
+
+        let types = types.iter().map(|oid| oid.value()).collect::<Vec<_>>();
+        let datums = fields
+            .iter()
+            .zip(&types)
+            .map(|(field, &type_oid)| {
+                field.as_ref().map(|bytes| unsafe { receive(type_oid, bytes) }).transpose()
+            })
+            .collect::<io::Result<Vec<_>>>()?;
+        Ok(Some(BinaryCopyRow { datums, types }))
+    }
+
+    /// What the stream is read from
+    pub fn into_inner(self) -> R {
+        self.input
+    }
+}
+
+fn read_u32(input: &mut impl Read) -> io::Result<u32> {
+    let mut bytes = [0u8; 4];
+    input.read_exact(&mut bytes)?;
+    Ok(u32::from_be_bytes(bytes))
+}
+
+/// The Datum of type `type_oid` whose binary representation is `bytes`
+unsafe fn receive(type_oid: pg_sys::Oid, bytes: &[u8]) -> io::Result<pg_sys::Datum> {
+    let mut receive_func = pg_sys::InvalidOid;
+    let mut io_param = pg_sys::InvalidOid;
+    pg_sys::getTypeBinaryInputInfo(type_oid, &mut receive_func, &mut io_param);
+
+    let buf = pg_sys::makeStringInfo();
+    pg_sys::appendBinaryStringInfo(buf, bytes.as_ptr().cast(), bytes.len() as _);
+    let datum = pg_sys::OidReceiveFunctionCall(receive_func, buf, io_param, -1);
+    // like Postgres' own COPY, insist the receive function used every byte
+    let consumed = (*buf).cursor == (*buf).len;
+    pg_sys::pfree((*buf).data.cast());
+    pg_sys::pfree(buf.cast());
+
+    if consumed {
+        Ok(datum)
+    } else {
+        Err(invalid_data("incorrect binary data format in COPY field"))
+    }
+}
+
+/// A row read by [`BinaryCopyReader::read_row()`]
+pub struct BinaryCopyRow {
+    datums: Vec<Option<pg_sys::Datum>>,
+    types: Vec<pg_sys::Oid>,
+}
+
+impl BinaryCopyRow {
+    /// The number of fields
+    pub fn len(&self) -> usize {
+        self.datums.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.datums.is_empty()
+    }
+
+    /// The Datum of field `ordinal`, starting at one, or `None` if it's null
+    ///
+    /// # Panics
+    ///
+    /// If there's no such field
+    pub fn get_datum(&self, ordinal: usize) -> Option<pg_sys::Datum> {
+        self.datums[ordinal - 1]
+    }
+
+    /// The value of field `ordinal`, starting at one, or `None` if it's null
+    ///
+    /// # Panics
+    ///
+    /// If there's no such field, or its type isn't compatible with `T`
+    pub fn get<T: FromDatum + IntoDatum>(&self, ordinal: usize) -> Option<T> {
+        let type_oid = self.types[ordinal - 1];
+        assert!(
+            T::is_compatible_with(type_oid),
+            "COPY field {} is not compatible with {}",
+            ordinal,
+            std::any::type_name::<T>()
+        );
+        unsafe { T::from_polymorphic_datum(self.datums[ordinal - 1]?, false, type_oid) }
+    }
+}
@@ -0,0 +1,306 @@
+/*
+Portions Copyright 2019-2021 ZomboDB, LLC.
+Portions Copyright 2021-2022 Technology Concepts & Design, Inc. <support@tcdi.com>
+
+All rights reserved.
+
+Use of this source code is governed by the MIT license that can be found in the LICENSE file.
+*/
+
+//! A safe wrapper around Postgres' [`Bitmapset`][crate::pg_sys::Bitmapset], a set of small
+//! non-negative integers
+//!
+//! The planner uses bitmapsets everywhere it needs a set of range table indexes (`relids`) or
+//! attribute numbers, so they show up in almost every planner hook.  [`Bitmapset`] does its set
+//! operations with Postgres' own `bms_*()` functions, and converts to and from Rust collections.
+//!
+//! As in Postgres, the empty set is the null pointer.  Sets are allocated in the
+//! `CurrentMemoryContext`, and are never freed by `Bitmapset`.
+//!
+//! ## Examples
+//!
+//! ```rust,no_run
+//! use pgrx::bitmapset::Bitmapset;
+//! use pgrx::prelude::*;
+//!
+//! # unsafe fn example(rel: *mut pg_sys::RelOptInfo, root: *mut pg_sys::PlannerInfo) {
+//! let relids = Bitmapset::from_pg((*rel).relids);
+//! for rti in relids.iter() {
+//!     // ...
+//! }
+//!
+//! let outer = Bitmapset::from_pg((*root).all_baserels);
+//! let others = outer.difference(&relids);
+//! # }
+//! ```
+use crate::pg_sys;
+use std::collections::BTreeSet;
+use std::fmt::{Debug, Formatter};
+use std::os::raw::c_int;
+
+/// A set of non-negative integers, stored as a Postgres `Bitmapset`
+pub struct Bitmapset {
+    bms: *mut pg_sys::Bitmapset,
+}
+
+impl Bitmapset {
+    /// The empty set, which is the null pointer until something is inserted into it
+    pub fn new() -> Self {
+        Bitmapset { bms: std::ptr::null_mut() }
+    }
+
+    /// A set of just `member`, like `bms_make_singleton()`
+    ///
+    /// # Panics
+    ///
+    /// Panics if `member` is negative
+    pub fn singleton(member: i32) -> Self {
+        Bitmapset { bms: unsafe { pg_sys::bms_make_singleton(member as c_int) } }
+    }
+
+    /// Wrap a set from Postgres.  Changes made through the wrapper may reallocate the set, so
+    /// [`Bitmapset::into_pg()`] should be used to get the result back
+    ///
+    /// # Safety
+    ///
+    /// `bms` must be a valid `Bitmapset`, or null
+    pub unsafe fn from_pg(bms: *mut pg_sys::Bitmapset) -> Self {
+        Bitmapset { bms }
+    }
+
+    /// The underlying set, which may be null
+    pub fn as_ptr(&self) -> *mut pg_sys::Bitmapset {
+        self.bms
+    }
+
+    /// Give the underlying set, which may be null, back to Postgres
+    pub fn into_pg(self) -> *mut pg_sys::Bitmapset {
+        self.bms
+    }
+
+    pub fn is_empty(&self) -> bool {
+        unsafe { pg_sys::bms_is_empty(self.bms) }
+    }
+
+    /// The number of members
+    pub fn len(&self) -> usize {
+        unsafe { pg_sys::bms_num_members(self.bms) as usize }
+    }
+
+    /// Is `member` in the set?
+    ///
+    /// # Panics
+    ///
+    /// Panics if `member` is negative
+    pub fn contains(&self, member: i32) -> bool {
+        unsafe { pg_sys::bms_is_member(member as c_int, self.bms) }
+    }
+
+    /// Add `member` to the set, returning whether it was newly added
+    ///
+    /// # Panics
+    ///
+    /// Panics if `member` is negative
+    pub fn insert(&mut self, member: i32) -> bool {
+        let added = !self.contains(member);
+        self.bms = unsafe { pg_sys::bms_add_member(self.bms, member as c_int) };
+        added
+    }
+
+    /// Remove `member` from the set, returning whether it was there
+    ///
+    /// # Panics
+    ///
+    /// Panics if `member` is negative
+    pub fn remove(&mut self, member: i32) -> bool {
+        let removed = self.contains(member);
+        self.bms = unsafe { pg_sys::bms_del_member(self.bms, member as c_int) };
+        removed
+    }
+
+    /// The smallest member
+    pub fn min(&self) -> Option<i32> {
+        member(unsafe { pg_sys::bms_next_member(self.bms, -1) })
+    }
+
+    /// The largest member
+    pub fn max(&self) -> Option<i32> {
+        member(unsafe { pg_sys::bms_prev_member(self.bms, -1) })
+    }
+
+    /// The only member, if the set has exactly one, like `bms_get_singleton_member()`
+    pub fn singleton_member(&self) -> Option<i32> {
+        let mut member = 0;
+        unsafe { pg_sys::bms_get_singleton_member(self.bms, &mut member) }.then_some(member)
+    }
+
+    /// A new set of the members in either set, like `bms_union()`
+    pub fn union(&self, other: &Bitmapset) -> Bitmapset {
+        Bitmapset { bms: unsafe { pg_sys::bms_union(self.bms, other.bms) } }
+    }
+
+    /// A new set of the members in both sets, like `bms_intersect()`
+    pub fn intersection(&self, other: &Bitmapset) -> Bitmapset {
+        Bitmapset { bms: unsafe { pg_sys::bms_intersect(self.bms, other.bms) } }
+    }
+
+    /// A new set of the members in this set but not in `other`, like `bms_difference()`
+    pub fn difference(&self, other: &Bitmapset) -> Bitmapset {
+        Bitmapset { bms: unsafe { pg_sys::bms_difference(self.bms, other.bms) } }
+    }
+
+    /// Add every member of `other` to this set, like `bms_add_members()`
+    pub fn union_with(&mut self, other: &Bitmapset) {
+        self.bms = unsafe { pg_sys::bms_add_members(self.bms, other.bms) };
+    }
+
+    /// Keep only the members also in `other`, like `bms_int_members()`
+    pub fn intersect_with(&mut self, other: &Bitmapset) {
+        self.bms = unsafe { pg_sys::bms_int_members(self.bms, other.bms) };
+    }
+
+    /// Remove every member of `other` from this set, like `bms_del_members()`
+    pub fn difference_with(&mut self, other: &Bitmapset) {
+        self.bms = unsafe { pg_sys::bms_del_members(self.bms, other.bms) };
+    }
+
+    /// Is every member of this set also in `other`?
+    pub fn is_subset(&self, other: &Bitmapset) -> bool {
+        unsafe { pg_sys::bms_is_subset(self.bms, other.bms) }
+    }
+
+    /// Do the sets have any member in common?
+    pub fn overlaps(&self, other: &Bitmapset) -> bool {
+        unsafe { pg_sys::bms_overlap(self.bms, other.bms) }
+    }
+
+    /// Iterate over the members, in increasing order
+    pub fn iter(&self) -> BitmapsetIter<'_> {
+        BitmapsetIter { set: self, prev: -1 }
+    }
+
+    /// Copy the members into a `Vec`, in increasing order
+    pub fn to_vec(&self) -> Vec<i32> {
+        self.iter().collect()
+    }
+
+    /// The set as 64-bit words, least significant bit first, so that `member` is bit
+    /// `member % 64` of word `member / 64`.  Postgres' own word size varies by version, but this
+    /// layout doesn't
+    pub fn to_words(&self) -> Vec<u64> {
+        let mut words = vec![0u64; self.max().map(|max| max as usize / 64 + 1).unwrap_or(0)];
+        for member in self.iter() {
+            words[member as usize / 64] |= 1 << (member % 64);
+        }
+        words
+    }
+
+    /// The set of bits set in `words`, laid out as [`Bitmapset::to_words()`] makes them
+    pub fn from_words(words: &[u64]) -> Self {
+        let mut set = Bitmapset::new();
+        for (i, word) in words.iter().enumerate() {
+            for bit in 0..64 {
+                if word & (1 << bit) != 0 {
+                    set.insert((i * 64 + bit) as i32);
+                }
+            }
+        }
+        set
+    }
+}
+
+fn member(bit: c_int) -> Option<i32> {
+    // the `bms_*_member()` functions return a negative number when there are no more members
+    (bit >= 0).then_some(bit as i32)
+}
+
+impl Default for Bitmapset {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Clone for Bitmapset {
+    fn clone(&self) -> Self {
+        Bitmapset { bms: unsafe { pg_sys::bms_copy(self.bms) } }
+    }
+}
+
+impl PartialEq for Bitmapset {
+    fn eq(&self, other: &Self) -> bool {
+        unsafe { pg_sys::bms_equal(self.bms, other.bms) }
+    }
+}
+
+impl Eq for Bitmapset {}
+
+impl Debug for Bitmapset {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        f.debug_set().entries(self.iter()).finish()
+    }
+}
+
+impl FromIterator<i32> for Bitmapset {
+    fn from_iter<I: IntoIterator<Item = i32>>(iter: I) -> Self {
+        let mut set = Bitmapset::new();
+        set.extend(iter);
+        set
+    }
+}
+
+impl Extend<i32> for Bitmapset {
+    fn extend<I: IntoIterator<Item = i32>>(&mut self, iter: I) {
+        for member in iter {
+            self.insert(member);
+        }
+    }
+}
+
+impl From<&[i32]> for Bitmapset {
+    fn from(members: &[i32]) -> Self {
+        members.iter().copied().collect()
+    }
+}
+
+impl From<&BTreeSet<i32>> for Bitmapset {
+    fn from(members: &BTreeSet<i32>) -> Self {
+        members.iter().copied().collect()
+    }
+}
+
+impl From<&Bitmapset> for BTreeSet<i32> {
+    fn from(set: &Bitmapset) -> Self {
+        set.iter().collect()
+    }
+}
+
+impl From<&Bitmapset> for Vec<i32> {
+    fn from(set: &Bitmapset) -> Self {
+        set.to_vec()
+    }
+}
+
+impl<'a> IntoIterator for &'a Bitmapset {
+    type Item = i32;
+    type IntoIter = BitmapsetIter<'a>;
+
+    fn into_iter(self) -> Self::IntoIter {
+        self.iter()
+    }
+}
+
+/// An iterator over the members of a [`Bitmapset`], in increasing order
+pub struct BitmapsetIter<'a> {
+    set: &'a Bitmapset,
+    prev: c_int,
+}
+
+impl Iterator for BitmapsetIter<'_> {
+    type Item = i32;
+
+    fn next(&mut self) -> Option<i32> {
+        let next = member(unsafe { pg_sys::bms_next_member(self.set.bms, self.prev) })?;
+        self.prev = next as c_int;
+        Some(next)
+    }
+}
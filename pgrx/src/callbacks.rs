@@ -13,7 +13,7 @@ use crate as pgrx; // for #[pg_guard] support from within ourself
 use crate::pg_sys;
 use crate::prelude::*;
 use enum_map::{Enum, EnumMap};
-use std::cell::RefCell;
+use std::cell::{Cell, RefCell};
 use std::collections::HashMap;
 use std::rc::Rc;
 
@@ -49,10 +49,18 @@ pub enum PgXactCallbackEvent {
     /// Same as `::PreCommit`, but for parallel workers
     ParallelPreCommit,
 
-    /// Same as `::Commit`, but for committing a prepared transaction
+    /// Fired once the transaction has been prepared by `PREPARE TRANSACTION`.  It is mutually
+    /// exclusive with `PgXactCallbackEvent::Commit` and `PgXactCallbackEvent::Abort`
+    ///
+    /// ## Safety
+    ///
+    /// Any kind of Rust `panic!()` or Postgres `ereport(ERROR)` while this event is firing will
+    /// cause the Postgres backend to abort.
     Prepare,
 
-    /// Same as `::PreCommit`, but for committing a prepared transaction
+    /// Fired immediately before the transaction is prepared by `PREPARE TRANSACTION`.  This is
+    /// your last chance to cleanly abort the current transaction via a Rust `panic!()` or Postgres
+    /// `ereport(ERROR)`
     PrePrepare,
 }
 
@@ -113,8 +121,9 @@ type CallbackMap =
 /// closures can be registered per event (one at a time), and they are called in the order in which
 /// they were registered.
 ///
-/// Registered callbacks only remain registered for the life of a single transaction.  Registration
-/// of permanet callbacks should be done through the unsafe `pg_sys::RegisterXactCallback()` function.
+/// Registered callbacks only remain registered for the life of a single transaction.  Callbacks
+/// that should remain registered across transactions can be registered with
+/// [`register_session_xact_callback()`].
 ///
 ///
 /// ## Examples
@@ -377,3 +386,507 @@ where
 
     SubXactCallbackReceipt(wrapped_func)
 }
+
+/// An internal wrapper for a session-level transaction callback closure
+type SessionXactCallbackFn = Box<dyn FnMut(PgXactCallbackEvent) + 'static>;
+
+/// An internal wrapper for a session-level sub-transaction callback closure
+type SessionSubXactCallbackFn = Box<
+    dyn FnMut(PgSubXactCallbackEvent, pg_sys::SubTransactionId, pg_sys::SubTransactionId) + 'static,
+>;
+
+thread_local! {
+    static SESSION_XACT_HOOKS: RefCell<Vec<(u64, Rc<RefCell<SessionXactCallbackFn>>)>> =
+        const { RefCell::new(Vec::new()) };
+    static SESSION_SUBXACT_HOOKS: RefCell<Vec<(u64, Rc<RefCell<SessionSubXactCallbackFn>>)>> =
+        const { RefCell::new(Vec::new()) };
+    static NEXT_SESSION_HOOK_ID: Cell<u64> = const { Cell::new(0) };
+}
+
+/// Registering a session-level transaction callback returns a `SessionXactCallbackGuard`.  The
+/// callback stays registered across transactions until the guard is dropped.
+///
+/// Use [`SessionXactCallbackGuard::leak()`] to keep the callback registered for the remaining
+/// life of the backend.
+#[must_use = "the callback is unregistered as soon as its guard is dropped"]
+pub struct SessionXactCallbackGuard(u64);
+
+impl SessionXactCallbackGuard {
+    /// Consumes this guard without unregistering the callback it represents, leaving it
+    /// registered until the backend exits
+    pub fn leak(self) {
+        std::mem::forget(self)
+    }
+}
+
+impl Drop for SessionXactCallbackGuard {
+    fn drop(&mut self) {
+        SESSION_XACT_HOOKS.with(|hooks| hooks.borrow_mut().retain(|(id, _)| *id != self.0));
+    }
+}
+
+/// Registering a session-level sub-transaction callback returns a `SessionSubXactCallbackGuard`.
+/// The callback stays registered across transactions until the guard is dropped.
+///
+/// Use [`SessionSubXactCallbackGuard::leak()`] to keep the callback registered for the remaining
+/// life of the backend.
+#[must_use = "the callback is unregistered as soon as its guard is dropped"]
+pub struct SessionSubXactCallbackGuard(u64);
+
+impl SessionSubXactCallbackGuard {
+    /// Consumes this guard without unregistering the callback it represents, leaving it
+    /// registered until the backend exits
+    pub fn leak(self) {
+        std::mem::forget(self)
+    }
+}
+
+impl Drop for SessionSubXactCallbackGuard {
+    fn drop(&mut self) {
+        SESSION_SUBXACT_HOOKS.with(|hooks| hooks.borrow_mut().retain(|(id, _)| *id != self.0));
+    }
+}
+
+fn next_session_hook_id() -> u64 {
+    NEXT_SESSION_HOOK_ID.with(|next| {
+        next.set(next.get() + 1);
+        next.get()
+    })
+}
+
+thread_local! {
+    // the parent of every session callback's memory context
+    static SESSION_CALLBACK_CONTEXTS: Cell<pg_sys::MemoryContext> =
+        const { Cell::new(std::ptr::null_mut()) };
+}
+
+/// Run `f` in a memory context of its own, private to session-level callbacks.  It's deleted as
+/// soon as `f` returns or unwinds, so nothing `f` pallocs can outlive it, and `f` never allocates
+/// into a transaction context that Postgres is in the middle of tearing down.  Each call gets a
+/// new context, so a callback fired while another is running can't free the other's memory.
+unsafe fn in_session_callback_context<F: FnOnce()>(f: F) {
+    struct Delete {
+        context: pg_sys::MemoryContext,
+        previous: pg_sys::MemoryContext,
+    }
+    impl Drop for Delete {
+        fn drop(&mut self) {
+            unsafe {
+                pg_sys::CurrentMemoryContext = self.previous;
+                pg_sys::MemoryContextDelete(self.context);
+            }
+        }
+    }
+
+    let parent = SESSION_CALLBACK_CONTEXTS.with(|parent| {
+        if parent.get().is_null() {
+            parent.set(pg_sys::AllocSetContextCreateExtended(
+                pg_sys::TopMemoryContext,
+                b"pgrx session xact callbacks\0".as_ptr().cast(),
+                pg_sys::ALLOCSET_SMALL_MINSIZE as usize,
+                pg_sys::ALLOCSET_SMALL_INITSIZE as usize,
+                pg_sys::ALLOCSET_SMALL_MAXSIZE as usize,
+            ));
+        }
+        parent.get()
+    });
+    let context = pg_sys::AllocSetContextCreateExtended(
+        parent,
+        b"pgrx session xact callback\0".as_ptr().cast(),
+        pg_sys::ALLOCSET_SMALL_MINSIZE as usize,
+        pg_sys::ALLOCSET_SMALL_INITSIZE as usize,
+        pg_sys::ALLOCSET_SMALL_MAXSIZE as usize,
+    );
+
+    let _delete = Delete { context, previous: pg_sys::CurrentMemoryContext };
+    pg_sys::CurrentMemoryContext = context;
+    f()
+}
+
+/// Register a closure to be called for every `PgXactCallbackEvent` of every transaction for the
+/// life of the session (ie, the backend serving the current connection), until the returned
+/// [`SessionXactCallbackGuard`] is dropped.
+///
+/// This is the counterpart to [`register_xact_callback()`], whose callbacks only live for the
+/// current transaction.  It's useful for flushing buffered, extension-owned state on
+/// `PreCommit` and discarding it on `Abort`.
+///
+/// Callbacks are called in the order in which they were registered, and run in a private memory
+/// context that is freed after every event.  Anything that must survive the event needs to be
+/// owned by Rust or explicitly copied into a longer-lived memory context.
+///
+/// ## Examples
+///
+/// ```rust,no_run
+/// use pgrx::*;
+///
+/// let mut buffered = Vec::<String>::new();
+/// register_session_xact_callback(move |event| match event {
+///     PgXactCallbackEvent::PreCommit => { /* flush `buffered` somewhere */ }
+///     PgXactCallbackEvent::Abort => buffered.clear(),
+///     _ => {}
+/// })
+/// .leak();
+/// ```
+///
+/// ## Safety
+///
+/// Any kind of Rust `panic!()` or Postgres `ereport(ERROR)` while executing a `PgXactCallbackEvent::Commit`
+/// or `PgXactCallbackEvent::Abort` event will immediately cause the Postgres backend to abort and
+/// the entire cluster to restart.
+pub fn register_session_xact_callback<F>(f: F) -> SessionXactCallbackGuard
+where
+    F: FnMut(PgXactCallbackEvent) + 'static,
+{
+    thread_local! {
+        static REGISTERED: Cell<bool> = const { Cell::new(false) };
+    }
+
+    #[pg_guard]
+    unsafe extern "C" fn callback(event: pg_sys::XactEvent, _arg: *mut ::std::os::raw::c_void) {
+        let which_event = PgXactCallbackEvent::translate_pg_event(event);
+
+        // clone the list so callbacks are free to register and unregister other callbacks
+        let hooks = SESSION_XACT_HOOKS.with(|hooks| {
+            hooks.borrow().iter().map(|(_, hook)| Rc::clone(hook)).collect::<Vec<_>>()
+        });
+
+        if !hooks.is_empty() {
+            in_session_callback_context(|| {
+                for hook in hooks {
+                    (hook.borrow_mut())(which_event);
+                }
+            });
+        }
+    }
+
+    let id = next_session_hook_id();
+    if !REGISTERED.with(|registered| registered.replace(true)) {
+        unsafe {
+            pg_sys::RegisterXactCallback(Some(callback), std::ptr::null_mut());
+        }
+    }
+    SESSION_XACT_HOOKS
+        .with(|hooks| hooks.borrow_mut().push((id, Rc::new(RefCell::new(Box::new(f))))));
+    SessionXactCallbackGuard(id)
+}
+
+/// Register a closure to be called for every `PgSubXactCallbackEvent` of every sub-transaction
+/// for the life of the session, until the returned [`SessionSubXactCallbackGuard`] is dropped.
+///
+/// The closure is given the event along with the current and parent `SubTransactionId`s.  As
+/// with [`register_session_xact_callback()`], callbacks run in a private memory context that is
+/// freed after every event.
+///
+/// ## Examples
+///
+/// ```rust,no_run
+/// use pgrx::*;
+///
+/// let guard = register_session_subxact_callback(|event, my_subid, parent_subid| {
+///     if event == PgSubXactCallbackEvent::AbortSub {
+///         // discard anything buffered by `my_subid`
+///     }
+/// });
+/// ```
+pub fn register_session_subxact_callback<F>(f: F) -> SessionSubXactCallbackGuard
+where
+    F: FnMut(PgSubXactCallbackEvent, pg_sys::SubTransactionId, pg_sys::SubTransactionId) + 'static,
+{
+    thread_local! {
+        static REGISTERED: Cell<bool> = const { Cell::new(false) };
+    }
+
+    #[pg_guard]
+    unsafe extern "C" fn callback(
+        event: pg_sys::SubXactEvent,
+        my_subid: pg_sys::SubTransactionId,
+        parent_subid: pg_sys::SubTransactionId,
+        _arg: *mut ::std::os::raw::c_void,
+    ) {
+        let which_event = PgSubXactCallbackEvent::translate_pg_event(event);
+
+        // clone the list so callbacks are free to register and unregister other callbacks
+        let hooks = SESSION_SUBXACT_HOOKS.with(|hooks| {
+            hooks.borrow().iter().map(|(_, hook)| Rc::clone(hook)).collect::<Vec<_>>()
+        });
+
+        if !hooks.is_empty() {
+            in_session_callback_context(|| {
+                for hook in hooks {
+                    (hook.borrow_mut())(which_event.clone(), my_subid, parent_subid);
+                }
+            });
+        }
+    }
+
+    let id = next_session_hook_id();
+    if !REGISTERED.with(|registered| registered.replace(true)) {
+        unsafe {
+            pg_sys::RegisterSubXactCallback(Some(callback), std::ptr::null_mut());
+        }
+    }
+    SESSION_SUBXACT_HOOKS
+        .with(|hooks| hooks.borrow_mut().push((id, Rc::new(RefCell::new(Box::new(f))))));
+    SessionSubXactCallbackGuard(id)
+}
+
+/// Two-phase commit events, as seen by a participant holding resources outside of Postgres
+#[derive(Hash, Eq, PartialEq, Clone, Copy, Debug)]
+pub enum PgTwoPhaseEvent {
+    /// Fired immediately before the transaction is prepared by `PREPARE TRANSACTION`.  This is the
+    /// participant's chance to prepare its own external resources, and the last chance to cleanly
+    /// abort the transaction via a Rust `panic!()` or Postgres `ereport(ERROR)`
+    PrePrepare,
+
+    /// Fired once the transaction has been prepared by `PREPARE TRANSACTION`
+    ///
+    /// ## Safety
+    ///
+    /// Any kind of Rust `panic!()` or Postgres `ereport(ERROR)` while this event is firing will
+    /// cause the Postgres backend to abort.
+    Prepare,
+
+    /// Fired after `COMMIT PREPARED` successfully committed the prepared transaction.  This is
+    /// usually a different backend than the one that prepared it.
+    CommitPrepared,
+
+    /// Fired after `ROLLBACK PREPARED` successfully rolled back the prepared transaction.  This is
+    /// usually a different backend than the one that prepared it.
+    RollbackPrepared,
+}
+
+/// An internal wrapper for a two-phase commit callback closure
+type TwoPhaseCallbackFn = Box<dyn FnMut(PgTwoPhaseEvent, &str) + 'static>;
+
+static mut PREV_PROCESS_UTILITY_HOOK: pg_sys::ProcessUtility_hook_type = None;
+
+thread_local! {
+    static TWO_PHASE_HOOKS: RefCell<Vec<(u64, Rc<RefCell<TwoPhaseCallbackFn>>)>> =
+        const { RefCell::new(Vec::new()) };
+
+    /// The global transaction identifier given to the `PREPARE TRANSACTION` statement currently
+    /// being processed, remembered until the transaction is actually prepared (or aborted)
+    static PENDING_PREPARE_GID: RefCell<Option<String>> = const { RefCell::new(None) };
+}
+
+/// Registering a two-phase commit callback returns a `TwoPhaseCallbackGuard`.  The callback stays
+/// registered across transactions until the guard is dropped.
+///
+/// Use [`TwoPhaseCallbackGuard::leak()`] to keep the callback registered for the remaining life of
+/// the backend.
+#[must_use = "the callback is unregistered as soon as its guard is dropped"]
+pub struct TwoPhaseCallbackGuard(u64);
+
+impl TwoPhaseCallbackGuard {
+    /// Consumes this guard without unregistering the callback it represents, leaving it
+    /// registered until the backend exits
+    pub fn leak(self) {
+        std::mem::forget(self)
+    }
+}
+
+impl Drop for TwoPhaseCallbackGuard {
+    fn drop(&mut self) {
+        TWO_PHASE_HOOKS.with(|hooks| hooks.borrow_mut().retain(|(id, _)| *id != self.0));
+    }
+}
+
+/// Register a closure to participate in two-phase commit for the life of the session, until the
+/// returned [`TwoPhaseCallbackGuard`] is dropped.
+///
+/// The closure is given the [`PgTwoPhaseEvent`] along with the global transaction identifier
+/// (the "gid") of the prepared transaction.  Extensions holding external resources, such as
+/// message queue publishes or remote server transactions, should prepare them on `PrePrepare`,
+/// keyed by the gid, and then finish them on `CommitPrepared` or `RollbackPrepared`.
+///
+/// Note that `CommitPrepared` and `RollbackPrepared` are only fired in the backend that runs the
+/// `COMMIT PREPARED`/`ROLLBACK PREPARED` statement, so the callback needs to be registered in
+/// every backend, typically from the extension's `_PG_init()`.  The events are detected through
+/// Postgres' `ProcessUtility_hook`, so prepared transactions finished by other means (such as
+/// during crash recovery) aren't reported.
+///
+/// Like [`register_session_xact_callback()`], callbacks are called in the order in which they were
+/// registered, and run in a private memory context that is freed after every event.
+///
+/// ## Examples
+///
+/// ```rust,no_run
+/// use pgrx::*;
+///
+/// register_two_phase_callback(|event, gid| match event {
+///     PgTwoPhaseEvent::PrePrepare => { /* durably prepare external work under `gid` */ }
+///     PgTwoPhaseEvent::CommitPrepared => { /* commit external work prepared under `gid` */ }
+///     PgTwoPhaseEvent::RollbackPrepared => { /* discard external work prepared under `gid` */ }
+///     PgTwoPhaseEvent::Prepare => {}
+/// })
+/// .leak();
+/// ```
+///
+/// ## Safety
+///
+/// Any kind of Rust `panic!()` or Postgres `ereport(ERROR)` while executing a
+/// `PgTwoPhaseEvent::Prepare` event will immediately cause the Postgres backend to abort and the
+/// entire cluster to restart.
+pub fn register_two_phase_callback<F>(f: F) -> TwoPhaseCallbackGuard
+where
+    F: FnMut(PgTwoPhaseEvent, &str) + 'static,
+{
+    thread_local! {
+        static REGISTERED: Cell<bool> = const { Cell::new(false) };
+    }
+
+    let id = next_session_hook_id();
+    if !REGISTERED.with(|registered| registered.replace(true)) {
+        unsafe {
+            // SAFETY:  `REGISTERED` makes this the only write to `PREV_PROCESS_UTILITY_HOOK`, and
+            // it's made before our hook is installed, so before the hook can read it
+            PREV_PROCESS_UTILITY_HOOK = pg_sys::ProcessUtility_hook;
+            pg_sys::ProcessUtility_hook = Some(two_phase_process_utility);
+        }
+        register_session_xact_callback(|event| match event {
+            PgXactCallbackEvent::PrePrepare => {
+                // clone the gid, rather than borrowing it, as the callbacks can run statements
+                if let Some(gid) = PENDING_PREPARE_GID.with(|gid| gid.borrow().clone()) {
+                    fire_two_phase_callbacks(PgTwoPhaseEvent::PrePrepare, &gid);
+                }
+            }
+            PgXactCallbackEvent::Prepare => {
+                if let Some(gid) = PENDING_PREPARE_GID.with(|gid| gid.borrow_mut().take()) {
+                    fire_two_phase_callbacks(PgTwoPhaseEvent::Prepare, &gid);
+                }
+            }
+            PgXactCallbackEvent::Commit | PgXactCallbackEvent::Abort => {
+                PENDING_PREPARE_GID.with(|gid| gid.borrow_mut().take());
+            }
+            _ => {}
+        })
+        .leak();
+    }
+    TWO_PHASE_HOOKS.with(|hooks| hooks.borrow_mut().push((id, Rc::new(RefCell::new(Box::new(f))))));
+    TwoPhaseCallbackGuard(id)
+}
+
+fn fire_two_phase_callbacks(event: PgTwoPhaseEvent, gid: &str) {
+    // clone the list so callbacks are free to register and unregister other callbacks
+    let hooks = TWO_PHASE_HOOKS
+        .with(|hooks| hooks.borrow().iter().map(|(_, hook)| Rc::clone(hook)).collect::<Vec<_>>());
+
+    for hook in hooks {
+        (hook.borrow_mut())(event, gid);
+    }
+}
+
+/// If `pstmt` is one of `PREPARE TRANSACTION`, `COMMIT PREPARED`, or `ROLLBACK PREPARED`, returns
+/// its kind and global transaction identifier
+unsafe fn two_phase_statement(
+    pstmt: *mut pg_sys::PlannedStmt,
+) -> Option<(pg_sys::TransactionStmtKind, String)> {
+    let node = (*pstmt).utilityStmt;
+    if !crate::is_a(node, pg_sys::NodeTag_T_TransactionStmt) {
+        return None;
+    }
+
+    let stmt = node.cast::<pg_sys::TransactionStmt>();
+    match (*stmt).kind {
+        pg_sys::TransactionStmtKind_TRANS_STMT_PREPARE
+        | pg_sys::TransactionStmtKind_TRANS_STMT_COMMIT_PREPARED
+        | pg_sys::TransactionStmtKind_TRANS_STMT_ROLLBACK_PREPARED
+            if !(*stmt).gid.is_null() =>
+        {
+            let gid = core::ffi::CStr::from_ptr((*stmt).gid).to_string_lossy().to_string();
+            Some(((*stmt).kind, gid))
+        }
+        _ => None,
+    }
+}
+
+/// Remember the gid of a `PREPARE TRANSACTION` before Postgres processes it.  The transaction
+/// isn't actually prepared until the statement finishes.
+unsafe fn two_phase_before_utility(stmt: &Option<(pg_sys::TransactionStmtKind, String)>) {
+    if let Some((pg_sys::TransactionStmtKind_TRANS_STMT_PREPARE, gid)) = stmt {
+        PENDING_PREPARE_GID.with(|pending| *pending.borrow_mut() = Some(gid.clone()));
+    }
+}
+
+/// Report a `COMMIT PREPARED` or `ROLLBACK PREPARED` once Postgres has successfully processed it
+unsafe fn two_phase_after_utility(stmt: Option<(pg_sys::TransactionStmtKind, String)>) {
+    let event = match &stmt {
+        Some((pg_sys::TransactionStmtKind_TRANS_STMT_COMMIT_PREPARED, _)) => {
+            PgTwoPhaseEvent::CommitPrepared
+        }
+        Some((pg_sys::TransactionStmtKind_TRANS_STMT_ROLLBACK_PREPARED, _)) => {
+            PgTwoPhaseEvent::RollbackPrepared
+        }
+        _ => return,
+    };
+    let (_, gid) = stmt.unwrap();
+    in_session_callback_context(|| fire_two_phase_callbacks(event, &gid));
+}
+
+#[cfg(any(feature = "pg11", feature = "pg12", feature = "pg13"))]
+#[pg_guard]
+unsafe extern "C" fn two_phase_process_utility(
+    pstmt: *mut pg_sys::PlannedStmt,
+    query_string: *const ::std::os::raw::c_char,
+    context: pg_sys::ProcessUtilityContext,
+    params: pg_sys::ParamListInfo,
+    query_env: *mut pg_sys::QueryEnvironment,
+    dest: *mut pg_sys::DestReceiver,
+    completion_tag: *mut pg_sys::QueryCompletion,
+) {
+    let stmt = two_phase_statement(pstmt);
+    two_phase_before_utility(&stmt);
+    match PREV_PROCESS_UTILITY_HOOK {
+        Some(prev) => prev(pstmt, query_string, context, params, query_env, dest, completion_tag),
+        None => pg_sys::standard_ProcessUtility(
+            pstmt,
+            query_string,
+            context,
+            params,
+            query_env,
+            dest,
+            completion_tag,
+        ),
+    }
+    two_phase_after_utility(stmt);
+}
+
+#[cfg(any(feature = "pg14", feature = "pg15"))]
+#[pg_guard]
+unsafe extern "C" fn two_phase_process_utility(
+    pstmt: *mut pg_sys::PlannedStmt,
+    query_string: *const ::std::os::raw::c_char,
+    read_only_tree: bool,
+    context: pg_sys::ProcessUtilityContext,
+    params: pg_sys::ParamListInfo,
+    query_env: *mut pg_sys::QueryEnvironment,
+    dest: *mut pg_sys::DestReceiver,
+    completion_tag: *mut pg_sys::QueryCompletion,
+) {
+    let stmt = two_phase_statement(pstmt);
+    two_phase_before_utility(&stmt);
+    match PREV_PROCESS_UTILITY_HOOK {
+        Some(prev) => prev(
+            pstmt,
+            query_string,
+            read_only_tree,
+            context,
+            params,
+            query_env,
+            dest,
+            completion_tag,
+        ),
+        None => pg_sys::standard_ProcessUtility(
+            pstmt,
+            query_string,
+            read_only_tree,
+            context,
+            params,
+            query_env,
+            dest,
+            completion_tag,
+        ),
+    }
+    two_phase_after_utility(stmt);
+}
@@ -0,0 +1,204 @@
+/*
+Portions Copyright 2019-2021 ZomboDB, LLC.
+Portions Copyright 2021-2022 Technology Concepts & Design, Inc. <support@tcdi.com>
+
+All rights reserved.
+
+Use of this source code is governed by the MIT license that can be found in the LICENSE file.
+*/
+
+//! Checking for optional catalog objects, so an extension can degrade gracefully without them
+//!
+//! An extension that can make use of another one, such as PostGIS, but doesn't require it, has to
+//! check whether its types and functions are there before using them.  [`type_exists()`],
+//! [`function_exists()`], and [`extension_installed()`] answer that, and cache their answers
+//! until the relevant system catalogs change.  [`Alternatives`] picks between code paths based on
+//! those answers.
+//!
+//! ## Examples
+//!
+//! ```rust,no_run
+//! use pgrx::catalog::exists::{Alternatives, Requirement};
+//!
+//! fn distance_with_postgis(points: &str) -> f64 {
+//!     // ... use ST_Distance() through SPI
+//!     # unimplemented!()
+//! }
+//!
+//! fn distance_in_rust(points: &str) -> f64 {
+//!     // ... a slower, less accurate version
+//!     # unimplemented!()
+//! }
+//!
+//! let distance = Alternatives::new(distance_in_rust)
+//!     .when(&[Requirement::Extension("postgis")], distance_with_postgis);
+//! let d = distance.call("...");
+//! ```
+use crate::pg_sys::AsPgCStr;
+use crate::{direct_function_call, pg_sys, IntoDatum};
+use std::cell::RefCell;
+use std::collections::HashMap;
+
+/// A catalog object that has to exist for a code path to be usable
+#[derive(Debug, Copy, Clone, Eq, PartialEq, Hash)]
+pub enum Requirement {
+    /// A type, as accepted by [`type_exists()`]
+    Type(&'static str),
+    /// A function, as accepted by [`function_exists()`]
+    Function(&'static str),
+    /// An installed extension, as accepted by [`extension_installed()`]
+    Extension(&'static str),
+}
+
+impl Requirement {
+    /// Does the required object exist?
+    pub fn is_met(&self) -> bool {
+        match self {
+            Requirement::Type(name) => type_exists(name),
+            Requirement::Function(name) => function_exists(name),
+            Requirement::Extension(name) => extension_installed(name),
+        }
+    }
+}
+
+/// Does the type named `name` exist?  The name is resolved like a `::type` cast would, so may be
+/// schema-qualified, or found through the `search_path`, and may be written like `int4[]` or
+/// `varchar(10)`
+pub fn type_exists(name: &str) -> bool {
+    cached(Kind::Type, name, || unsafe {
+        direct_function_call::<pg_sys::Oid>(pg_sys::to_regtype, &[name.into_datum()]).is_some()
+    })
+}
+
+/// Does the function described by `name` exist?  Like a `::regprocedure` cast, it can be given
+/// with its argument types, like `st_distance(geometry, geometry)`.  Without them, like a
+/// `::regproc` cast, only a function that isn't overloaded is found.  Either way, the name may be
+/// schema-qualified, or found through the `search_path`
+pub fn function_exists(name: &str) -> bool {
+    cached(Kind::Function, name, || unsafe {
+        let lookup = if name.contains('(') { pg_sys::to_regprocedure } else { pg_sys::to_regproc };
+        direct_function_call::<pg_sys::Oid>(lookup, &[name.into_datum()]).is_some()
+    })
+}
+
+/// Is the extension named `name` installed in the current database?
+///
+/// Extensions aren't covered by Postgres' catalog caches, so this relies on the types, functions,
+/// and schemas an extension creates (or drops) to notice it being installed (or removed)
+pub fn extension_installed(name: &str) -> bool {
+    cached(Kind::Extension, name, || unsafe {
+        pg_sys::get_extension_oid(name.as_pg_cstr(), true) != pg_sys::InvalidOid
+    })
+}
+
+/// Forget every cached answer.  This happens automatically whenever a type, function, or schema
+/// is created, altered, or dropped, in any backend
+pub fn reset_cache() {
+    CACHE.with(|cache| {
+        if let Some(cache) = cache.borrow_mut().as_mut() {
+            cache.clear();
+        }
+    })
+}
+
+/// A choice of code paths, each usable only when some optional catalog objects exist
+///
+/// The paths are tried in the order they're added with [`Alternatives::when()`], and the first
+/// whose requirements are all met is used.  If none are, the fallback given to
+/// [`Alternatives::new()`] is
+pub struct Alternatives<A, R> {
+    paths: Vec<(Vec<Requirement>, fn(A) -> R)>,
+    fallback: fn(A) -> R,
+}
+
+impl<A, R> Alternatives<A, R> {
+    /// Start with the code path to use when none of the others can be
+    pub fn new(fallback: fn(A) -> R) -> Self {
+        Alternatives { paths: Vec::new(), fallback }
+    }
+
+    /// Add a code path that can be used when every one of `requirements` is met
+    pub fn when(mut self, requirements: &[Requirement], path: fn(A) -> R) -> Self {
+        self.paths.push((requirements.to_vec(), path));
+        self
+    }
+
+    /// The code path that would be used right now
+    pub fn choose(&self) -> fn(A) -> R {
+        self.paths
+            .iter()
+            .find(|(requirements, _)| requirements.iter().all(Requirement::is_met))
+            .map(|(_, path)| *path)
+            .unwrap_or(self.fallback)
+    }
+
+    /// Run the first usable code path with `args`
+    pub fn call(&self, args: A) -> R {
+        (self.choose())(args)
+    }
+}
+
+#[derive(Debug, Copy, Clone, Eq, PartialEq, Hash)]
+enum Kind {
+    Type,
+    Function,
+    Extension,
+}
+
+thread_local! {
+    /// Answers, keyed by the kind of object, its name, and the `search_path` it was resolved with.
+    /// `None` until the invalidation callbacks are registered
+    static CACHE: RefCell<Option<HashMap<(Kind, String, String), bool>>> = const { RefCell::new(None) };
+}
+
+fn cached(kind: Kind, name: &str, lookup: impl FnOnce() -> bool) -> bool {
+    let key = (kind, name.to_string(), search_path());
+    let (registered, cached) = CACHE.with(|cache| match cache.borrow().as_ref() {
+        Some(cache) => (true, cache.get(&key).copied()),
+        None => (false, None),
+    });
+    if let Some(exists) = cached {
+        return exists;
+    }
+    if !registered {
+        unsafe {
+            register_invalidation_callbacks();
+        }
+        CACHE.with(|cache| *cache.borrow_mut() = Some(HashMap::new()));
+    }
+
+    // the lookup may process invalidations, which clear the cache, so it mustn't be borrowed
+    // meanwhile.  The answer is the current one either way
+    let exists = lookup();
+    CACHE.with(|cache| cache.borrow_mut().as_mut().map(|cache| cache.insert(key, exists)));
+    exists
+}
+
+fn search_path() -> String {
+    unsafe {
+        let search_path = pg_sys::namespace_search_path;
+        if search_path.is_null() {
+            String::new()
+        } else {
+            core::ffi::CStr::from_ptr(search_path).to_string_lossy().into_owned()
+        }
+    }
+}
+
+unsafe fn register_invalidation_callbacks() {
+    for cacheid in [
+        pg_sys::SysCacheIdentifier_TYPEOID,
+        pg_sys::SysCacheIdentifier_PROCOID,
+        pg_sys::SysCacheIdentifier_NAMESPACEOID,
+    ] {
+        pg_sys::CacheRegisterSyscacheCallback(
+            cacheid as _,
+            Some(invalidate),
+            pg_sys::Datum::from(0),
+        );
+    }
+}
+
+unsafe extern "C" fn invalidate(_arg: pg_sys::Datum, _cacheid: i32, _hashvalue: u32) {
+    reset_cache();
+}
@@ -0,0 +1,220 @@
+/*
+Portions Copyright 2019-2021 ZomboDB, LLC.
+Portions Copyright 2021-2022 Technology Concepts & Design, Inc. <support@tcdi.com>
+
+All rights reserved.
+
+Use of this source code is governed by the MIT license that can be found in the LICENSE file.
+*/
+
+//! Change data capture into shadow tables
+//!
+//! [`cdc_table!`](macro@crate::cdc_table) creates a shadow table for a table, and triggers
+//! recording each row inserted, updated, deleted, or truncated from the table into it:
+//!
+//! ```rust,no_run
+//! use pgrx::prelude::*;
+//!
+//! extension_sql!("CREATE TABLE orders (id int PRIMARY KEY, total numeric);", name = "orders");
+//!
+//! pgrx::cdc_table!(table = "orders", shadow = "orders_changes", requires = ["orders"]);
+//! ```
+//!
+//! Each change is a row of the shadow table:
+//!
+//! ```sql
+//! CREATE TABLE orders_changes (
+//!     change_id bigserial PRIMARY KEY,
+//!     txid bigint NOT NULL DEFAULT txid_current(),
+//!     changed_at timestamptz NOT NULL DEFAULT now(),
+//!     operation text NOT NULL,  -- INSERT, UPDATE, DELETE, or TRUNCATE
+//!     old_row jsonb,            -- the row before an UPDATE or DELETE
+//!     new_row jsonb             -- the row after an INSERT or UPDATE
+//! );
+//! ```
+//!
+//! Changes are kept until they're [`purge()`]d, which a [`CdcRetention`] background worker can do
+//! periodically.
+use crate::bgworkers::{
+    BackgroundWorker, BackgroundWorkerBuilder, BgWorkerStartTime, LatchEvent, SignalWakeFlags,
+};
+use crate::prelude::*;
+use crate::{spi, AllocatedByPostgres, PgOid};
+use std::ops::ControlFlow;
+use std::time::Duration;
+
+/// Why a change couldn't be captured
+#[derive(thiserror::Error, Debug)]
+pub enum CdcError {
+    #[error(
+        "change capture triggers must be AFTER row triggers, or AFTER TRUNCATE statement triggers"
+    )]
+    NotAfterTrigger,
+    #[error("change capture trigger \"{0}\" isn't given the name of its shadow table")]
+    MissingShadowTable(String),
+    #[error("shadow table \"{0}\" does not exist")]
+    UnknownShadowTable(String),
+    #[error("{0}")]
+    Trigger(#[from] PgTriggerError),
+    #[error("{0}")]
+    Spi(#[from] spi::Error),
+}
+
+/// Record the change `trigger` fired for in the shadow table named by the trigger's first argument.
+///
+/// This is the body of the trigger functions [`cdc_table!`](macro@crate::cdc_table) generates.
+/// The trigger must be an `AFTER ... FOR EACH ROW` trigger, or an `AFTER TRUNCATE ... FOR EACH
+/// STATEMENT` trigger
+pub fn capture<'a>(
+    trigger: &'a PgTrigger<'a>,
+) -> Result<Option<PgHeapTuple<'a, AllocatedByPostgres>>, CdcError> {
+    let op = trigger.op()?;
+    let row_level = matches!(trigger.level(), PgTriggerLevel::Row);
+    let truncate = matches!(op, PgTriggerOperation::Truncate);
+    if !matches!(trigger.when()?, PgTriggerWhen::After) || row_level == truncate {
+        return Err(CdcError::NotAfterTrigger);
+    }
+    let shadow = match trigger.extra_args()?.into_iter().next() {
+        Some(shadow) => shadow_table(&shadow)?,
+        None => return Err(CdcError::MissingShadowTable(trigger.name()?.to_string())),
+    };
+
+    // the rows are passed as the table's row type, for `to_jsonb()` to name their columns
+    let rowtype = PgOid::from(unsafe { (*(*trigger.trigger_data().tg_relation).rd_rel).reltype });
+    let old = trigger.old().and_then(PgHeapTuple::into_composite_datum);
+    let new = trigger.new().and_then(PgHeapTuple::into_composite_datum);
+    Spi::run_with_args(
+        &format!(
+            "INSERT INTO {} (operation, old_row, new_row) \
+             VALUES ($1, pg_catalog.to_jsonb($2), pg_catalog.to_jsonb($3))",
+            shadow
+        ),
+        Some(vec![
+            (PgBuiltInOids::TEXTOID.oid(), op.to_string().into_datum()),
+            (rowtype, old),
+            (rowtype, new),
+        ]),
+    )?;
+    Ok(None)
+}
+
+/// The shadow table named `shadow`, which may be schema-qualified, quoted to splice into a query
+fn shadow_table(shadow: &str) -> Result<String, CdcError> {
+    Spi::get_one_with_args::<String>(
+        "SELECT pg_catalog.to_regclass($1)::pg_catalog.text",
+        vec![(PgBuiltInOids::TEXTOID.oid(), shadow.into_datum())],
+    )?
+    .ok_or_else(|| CdcError::UnknownShadowTable(shadow.to_string()))
+}
+
+/// Delete the changes in the shadow table `shadow` made more than `retain` ago.  Returns how many
+/// were deleted
+pub fn purge(shadow: &str, retain: Duration) -> Result<u64, CdcError> {
+    let shadow = shadow_table(shadow)?;
+    Spi::connect(|mut client| {
+        let deleted = client.update(
+            &format!(
+                "DELETE FROM {} WHERE changed_at < pg_catalog.now() - $1::pg_catalog.interval",
+                shadow
+            ),
+            None,
+            Some(vec![(
+                PgBuiltInOids::TEXTOID.oid(),
+                format!("{} microseconds", retain.as_micros()).into_datum(),
+            )]),
+        )?;
+        Ok(deleted.len() as u64)
+    })
+}
+
+/// How long to keep the changes of shadow tables, for a background worker to [`purge()`] them
+///
+/// Register a worker from `_PG_init()` with [`CdcRetention::register_worker()`], naming a
+/// function of the extension that runs the retention:
+///
+/// ```rust,no_run
+/// use pgrx::cdc::CdcRetention;
+/// use pgrx::prelude::*;
+/// use std::time::Duration;
+///
+/// #[pg_guard]
+/// pub extern "C" fn _PG_init() {
+///     CdcRetention::register_worker("my_extension", "cdc_retention_main");
+/// }
+///
+/// #[pg_guard]
+/// #[no_mangle]
+/// pub extern "C" fn cdc_retention_main(_arg: pg_sys::Datum) {
+///     CdcRetention::new("postgres")
+///         .retain("orders_changes", Duration::from_secs(7 * 24 * 60 * 60))
+///         .run();
+/// }
+/// ```
+#[derive(Debug, Clone)]
+pub struct CdcRetention {
+    database: String,
+    interval: Duration,
+    tables: Vec<(String, Duration)>,
+}
+
+impl CdcRetention {
+    /// Retention for shadow tables in `database`, purged every minute
+    pub fn new(database: &str) -> Self {
+        CdcRetention {
+            database: database.to_string(),
+            interval: Duration::from_secs(60),
+            tables: Vec::new(),
+        }
+    }
+
+    /// Keep the changes of the shadow table `shadow` for `retain`
+    pub fn retain(mut self, shadow: &str, retain: Duration) -> Self {
+        self.tables.push((shadow.to_string(), retain));
+        self
+    }
+
+    /// Purge the shadow tables every `interval`
+    pub fn every(mut self, interval: Duration) -> Self {
+        self.interval = interval;
+        self
+    }
+
+    /// Purge each shadow table once.  Returns how many changes were deleted
+    pub fn purge(&self) -> Result<u64, CdcError> {
+        self.tables.iter().map(|(shadow, retain)| purge(shadow, *retain)).sum()
+    }
+
+    /// Register a background worker, started with the server, that calls `function` of the
+    /// extension library `library`.  This must be called from `_PG_init()` of an extension
+    /// loaded by `shared_preload_libraries`.  `function` should call [`CdcRetention::run()`]
+    pub fn register_worker(library: &str, function: &str) {
+        BackgroundWorkerBuilder::new("pgrx cdc retention")
+            .set_type("pgrx cdc retention")
+            .set_library(library)
+            .set_function(function)
+            .set_start_time(BgWorkerStartTime::RecoveryFinished)
+            .set_restart_time(Some(Duration::from_secs(60)))
+            .enable_spi_access()
+            .load();
+    }
+
+    /// Run as a background worker's main loop:  purge the shadow tables every `interval`, until
+    /// the worker is told to stop.  Failing to purge a table is logged, and retried next time
+    pub fn run(&self) {
+        BackgroundWorker::attach_signal_handlers(
+            SignalWakeFlags::SIGHUP | SignalWakeFlags::SIGTERM,
+        );
+        BackgroundWorker::connect_worker_to_spi(Some(&self.database), None);
+        BackgroundWorker::wait_latch_loop(Some(self.interval), |event| {
+            if event == LatchEvent::Timeout {
+                for (shadow, retain) in &self.tables {
+                    let result = BackgroundWorker::transaction(|| purge(shadow, *retain));
+                    if let Err(e) = result {
+                        warning!("could not purge changes from {}: {}", shadow, e);
+                    }
+                }
+            }
+            ControlFlow::<()>::Continue(())
+        });
+    }
+}
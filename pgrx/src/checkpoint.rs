@@ -0,0 +1,132 @@
+/*
+Portions Copyright 2019-2021 ZomboDB, LLC.
+Portions Copyright 2021-2022 Technology Concepts & Design, Inc. <support@tcdi.com>
+
+All rights reserved.
+
+Use of this source code is governed by the MIT license that can be found in the LICENSE file.
+*/
+
+//! Coordinating with the checkpointer and background writer, for extensions that manage their
+//! own storage
+//!
+//! Anything written through shared buffers becomes durable at the next checkpoint:  writing a
+//! dirty buffer out also asks the checkpointer to `fsync()` the file it belongs to before the
+//! checkpoint completes.  Relation data written some other way, directly through the storage
+//! manager for instance, has to make that request itself, with [`register_relation_sync()`].
+//!
+//! The checkpointer only knows how to sync relation files.  Files an extension keeps elsewhere
+//! in the data directory are synced immediately, with [`fsync_file()`].
+use crate::pg_sys;
+use crate::rel::PgRelation;
+use bitflags::bitflags;
+use pg_sys::AsPgCStr;
+
+bitflags! {
+    /// How [`request_checkpoint()`] should checkpoint
+    pub struct CheckpointFlags: i32 {
+        /// Checkpoint as fast as possible, instead of spreading the writes out over time
+        const IMMEDIATE = pg_sys::CHECKPOINT_IMMEDIATE as i32;
+        /// Checkpoint even if nothing has happened since the last checkpoint
+        const FORCE = pg_sys::CHECKPOINT_FORCE as i32;
+        /// Also write out the buffers of unlogged relations
+        const FLUSH_ALL = pg_sys::CHECKPOINT_FLUSH_ALL as i32;
+        /// Wait for the checkpoint to complete before returning
+        const WAIT = pg_sys::CHECKPOINT_WAIT as i32;
+    }
+}
+
+/// Ask the checkpointer to run a checkpoint, like the `CHECKPOINT` command does with
+/// `IMMEDIATE | FORCE | WAIT`.
+///
+/// Without [`CheckpointFlags::WAIT`], this returns once the checkpoint has been requested, and the
+/// checkpoint may not even have started.
+pub fn request_checkpoint(flags: CheckpointFlags) {
+    unsafe {
+        // SAFETY:  RequestCheckpoint() raises an ERROR if the checkpoint fails
+        pg_sys::RequestCheckpoint(flags.bits());
+    }
+}
+
+/// Write every dirty shared buffer of `relation` out to the kernel, with `FlushRelationBuffers()`.
+/// Each write asks the checkpointer to sync the file it went to, so the writes are durable once
+/// the next checkpoint completes
+pub fn flush_relation_buffers(relation: &PgRelation) {
+    unsafe {
+        // SAFETY:  a PgRelation is an open relation
+        pg_sys::FlushRelationBuffers(relation.as_ptr());
+    }
+}
+
+/// Write every dirty shared buffer of the database `database` out to the kernel, with
+/// `FlushDatabaseBuffers()`
+pub fn flush_database_buffers(database: pg_sys::Oid) {
+    unsafe {
+        // SAFETY:  FlushDatabaseBuffers() skips buffers that aren't in `database`
+        pg_sys::FlushDatabaseBuffers(database);
+    }
+}
+
+/// Ask the checkpointer to `fsync()` every segment file of the `fork` of `relation` before the
+/// next checkpoint completes.  This is what writing a buffer out does for its segment, and what
+/// anything writing to a relation's files without going through shared buffers has to do
+/// instead.
+///
+/// The fork must exist.  Temporary relations are never synced, so nothing is done for them.
+pub fn register_relation_sync(relation: &PgRelation, fork: pg_sys::ForkNumber) {
+    if relation.is_temp() {
+        return;
+    }
+
+    unsafe {
+        // SAFETY:  a PgRelation is an open relation
+        let nblocks = pg_sys::RelationGetNumberOfBlocksInFork(relation.as_ptr(), fork);
+        let segments = (nblocks.max(1) + pg_sys::RELSEG_SIZE - 1) / pg_sys::RELSEG_SIZE;
+        for segno in 0..segments {
+            register_segment_sync(relation, fork, segno);
+        }
+    }
+}
+
+#[cfg(feature = "pg11")]
+unsafe fn register_segment_sync(relation: &PgRelation, fork: pg_sys::ForkNumber, segno: u32) {
+    if !pg_sys::ForwardFsyncRequest(relation.rd_node, fork, segno) {
+        // the checkpointer's request queue is full, so sync the fork ourselves, as md.c does
+        let smgr = pg_sys::smgropen(relation.rd_node, relation.rd_backend);
+        pg_sys::smgrimmedsync(smgr, fork);
+    }
+}
+
+#[cfg(not(feature = "pg11"))]
+unsafe fn register_segment_sync(relation: &PgRelation, fork: pg_sys::ForkNumber, segno: u32) {
+    let tag = pg_sys::FileTag {
+        handler: pg_sys::SyncRequestHandler_SYNC_HANDLER_MD as i16,
+        forknum: fork as i16,
+        rnode: relation.rd_node,
+        segno,
+    };
+    // with `retryOnError`, this waits for room in the checkpointer's request queue
+    pg_sys::RegisterSyncRequest(&tag, pg_sys::SyncRequestType_SYNC_REQUEST, true);
+}
+
+/// `fsync()` the file or directory at `path`, which is relative to the data directory unless it's
+/// absolute, with `fsync_fname()`.
+///
+/// As with Postgres' own files, a failure, including the file not existing, raises a PANIC unless
+/// the `data_sync_retry` setting is on, in which case it raises an ERROR.  After a failed
+/// `fsync()`, the kernel may have thrown the dirty data away, so retrying can't be trusted
+pub fn fsync_file(path: &str, is_directory: bool) {
+    unsafe {
+        // SAFETY:  fsync_fname() reports errors with ereport()
+        pg_sys::fsync_fname(path.as_pg_cstr(), is_directory);
+    }
+}
+
+/// Rename the file at `from` to `to`, so that after a crash one or the other exists in full, with
+/// `durable_rename()`.  This is the way to atomically replace a file the extension manages
+pub fn durable_rename(from: &str, to: &str) {
+    unsafe {
+        // SAFETY:  with an `elevel` of ERROR, durable_rename() raises an ERROR when it fails
+        pg_sys::durable_rename(from.as_pg_cstr(), to.as_pg_cstr(), pg_sys::ERROR as i32);
+    }
+}
@@ -0,0 +1,308 @@
+/*
+Portions Copyright 2019-2021 ZomboDB, LLC.
+Portions Copyright 2021-2022 Technology Concepts & Design, Inc. <support@tcdi.com>
+
+All rights reserved.
+
+Use of this source code is governed by the MIT license that can be found in the LICENSE file.
+*/
+
+//! The same operations on every supported Postgres version
+//!
+//! Some of Postgres' internal APIs change shape between major versions:  functions gain or lose
+//! arguments, get renamed, or become `static inline` and disappear from `pg_sys`.  Extensions
+//! that call them directly end up with a `#[cfg(feature = "pgXX")]` on every other line.  The
+//! functions here do the version-specific dance once, behind a single signature.
+//!
+//! They are thin, and as unsafe as the functions they wrap.  None of them need the `cshim`
+//! feature.
+use crate::pg_sys;
+use std::os::raw::c_int;
+
+/// Create a tuple descriptor with room for `natts` attributes, to be filled in with
+/// `TupleDescInitEntry()`.  Like `CreateTemplateTupleDesc()`, which took a `hasoid` argument
+/// before Postgres 12
+pub fn create_template_tuple_desc(natts: usize) -> pg_sys::TupleDesc {
+    unsafe {
+        #[cfg(feature = "pg11")]
+        let tupdesc = pg_sys::CreateTemplateTupleDesc(natts as c_int, false);
+        #[cfg(not(feature = "pg11"))]
+        let tupdesc = pg_sys::CreateTemplateTupleDesc(natts as c_int);
+        tupdesc
+    }
+}
+
+/// Open a table (or any relation with storage) by oid, taking `lockmode`.  Like `table_open()`,
+/// which was `heap_open()` before Postgres 12
+///
+/// # Safety
+///
+/// The relation must be closed with [`table_close()`] before the end of the transaction
+pub unsafe fn table_open(relid: pg_sys::Oid, lockmode: pg_sys::LOCKMODE) -> pg_sys::Relation {
+    #[cfg(feature = "pg11")]
+    let rel = pg_sys::heap_open(relid, lockmode);
+    #[cfg(not(feature = "pg11"))]
+    let rel = pg_sys::table_open(relid, lockmode);
+    rel
+}
+
+/// Close a relation opened with [`table_open()`], releasing `lockmode` unless it's `NoLock`
+///
+/// # Safety
+///
+/// `rel` must be a relation opened by [`table_open()`] that hasn't been closed already
+pub unsafe fn table_close(rel: pg_sys::Relation, lockmode: pg_sys::LOCKMODE) {
+    #[cfg(feature = "pg11")]
+    pg_sys::relation_close(rel, lockmode);
+    #[cfg(not(feature = "pg11"))]
+    pg_sys::table_close(rel, lockmode);
+}
+
+/// Make a standalone slot for heap tuples of the shape `tupdesc` describes.  Like
+/// `MakeSingleTupleTableSlot()`, which takes the slot's type since Postgres 12
+///
+/// # Safety
+///
+/// `tupdesc` must be a valid tuple descriptor that outlives the slot.  The slot should be freed
+/// with `ExecDropSingleTupleTableSlot()`
+pub unsafe fn make_heap_tuple_slot(tupdesc: pg_sys::TupleDesc) -> *mut pg_sys::TupleTableSlot {
+    #[cfg(feature = "pg11")]
+    let slot = pg_sys::MakeSingleTupleTableSlot(tupdesc);
+    #[cfg(not(feature = "pg11"))]
+    let slot = pg_sys::MakeSingleTupleTableSlot(tupdesc, &pg_sys::TTSOpsHeapTuple);
+    slot
+}
+
+/// Make a standalone slot for minimal tuples of the shape `tupdesc` describes, such as a
+/// tuplesort returns.  Like [`make_heap_tuple_slot()`], but on Postgres 11 the two are the same
+///
+/// # Safety
+///
+/// `tupdesc` must be a valid tuple descriptor that outlives the slot.  The slot should be freed
+/// with `ExecDropSingleTupleTableSlot()`
+pub unsafe fn make_minimal_tuple_slot(tupdesc: pg_sys::TupleDesc) -> *mut pg_sys::TupleTableSlot {
+    #[cfg(feature = "pg11")]
+    let slot = pg_sys::MakeSingleTupleTableSlot(tupdesc);
+    #[cfg(not(feature = "pg11"))]
+    let slot = pg_sys::MakeSingleTupleTableSlot(tupdesc, &pg_sys::TTSOpsMinimalTuple);
+    slot
+}
+
+/// Store a heap tuple in a slot, which frees it when cleared if `should_free` is true.  Like
+/// `ExecStoreHeapTuple()`, which was `ExecStoreTuple()` before Postgres 12
+///
+/// # Safety
+///
+/// `tuple` must be a valid heap tuple, not from a shared buffer, and `slot` a valid slot that
+/// can hold heap tuples, such as one from [`make_heap_tuple_slot()`]
+pub unsafe fn store_heap_tuple(
+    tuple: pg_sys::HeapTuple,
+    slot: *mut pg_sys::TupleTableSlot,
+    should_free: bool,
+) -> *mut pg_sys::TupleTableSlot {
+    #[cfg(feature = "pg11")]
+    let slot = pg_sys::ExecStoreTuple(tuple, slot, pg_sys::InvalidBuffer as _, should_free);
+    #[cfg(not(feature = "pg11"))]
+    let slot = pg_sys::ExecStoreHeapTuple(tuple, slot, should_free);
+    slot
+}
+
+/// The contents of a slot as a heap tuple, materializing one if need be.  The tuple belongs to
+/// the slot.  Like `ExecFetchSlotHeapTuple()`, which was `ExecMaterializeSlot()` before
+/// Postgres 12
+///
+/// # Safety
+///
+/// `slot` must be a valid, non-empty, slot
+pub unsafe fn fetch_heap_tuple(slot: *mut pg_sys::TupleTableSlot) -> pg_sys::HeapTuple {
+    #[cfg(feature = "pg11")]
+    let tuple = pg_sys::ExecMaterializeSlot(slot);
+    #[cfg(not(feature = "pg11"))]
+    let tuple = pg_sys::ExecFetchSlotHeapTuple(slot, true, std::ptr::null_mut());
+    tuple
+}
+
+/// The value of the attribute numbered `attnum` (starting at one) of the tuple in `slot`, or
+/// `None` if it's null.  Like `slot_getattr()`, which is `static inline` since Postgres 12
+///
+/// # Safety
+///
+/// `slot` must be a valid, non-empty, slot, and `attnum` a valid attribute number of its tuple
+/// descriptor
+pub unsafe fn slot_getattr(
+    slot: *mut pg_sys::TupleTableSlot,
+    attnum: usize,
+) -> Option<pg_sys::Datum> {
+    #[cfg(feature = "pg11")]
+    let (datum, isnull) = {
+        let mut isnull = false;
+        let datum = pg_sys::slot_getattr(slot, attnum as c_int, &mut isnull);
+        (datum, isnull)
+    };
+
+    #[cfg(not(feature = "pg11"))]
+    let (datum, isnull) = {
+        assert!(attnum >= 1, "invalid attribute number {attnum}");
+        if (attnum as c_int) > (*slot).tts_nvalid as c_int {
+            pg_sys::slot_getsomeattrs_int(slot, attnum as c_int);
+        }
+        (*(*slot).tts_values.add(attnum - 1), *(*slot).tts_isnull.add(attnum - 1))
+    };
+
+    if isnull {
+        None
+    } else {
+        Some(datum)
+    }
+}
+
+/// Empty a slot, freeing its tuple if the slot owns it.  Like `ExecClearTuple()`, which is
+/// `static inline` since Postgres 12
+///
+/// # Safety
+///
+/// `slot` must be a valid slot
+pub unsafe fn clear_slot(slot: *mut pg_sys::TupleTableSlot) {
+    #[cfg(feature = "pg11")]
+    pg_sys::ExecClearTuple(slot);
+    #[cfg(not(feature = "pg11"))]
+    if let Some(clear) = (*(*slot).tts_ops).clear {
+        clear(slot);
+    }
+}
+
+/// A copy of `tuple` with the attributes in `replacements`, numbered from one, set to new values
+/// (`None` for null).  Like `heap_modify_tuple_by_cols()`
+///
+/// # Safety
+///
+/// `tuple` must be a valid heap tuple described by `tupdesc`, and the replacement datums must be
+/// of the right types for their attributes
+pub unsafe fn modify_tuple(
+    tuple: pg_sys::HeapTuple,
+    tupdesc: pg_sys::TupleDesc,
+    replacements: &[(usize, Option<pg_sys::Datum>)],
+) -> pg_sys::HeapTuple {
+    let mut columns = replacements.iter().map(|(attnum, _)| *attnum as c_int).collect::<Vec<_>>();
+    let mut values = replacements
+        .iter()
+        .map(|(_, value)| value.unwrap_or(pg_sys::Datum::from(0)))
+        .collect::<Vec<_>>();
+    let mut nulls = replacements.iter().map(|(_, value)| value.is_none()).collect::<Vec<_>>();
+    pg_sys::heap_modify_tuple_by_cols(
+        tuple,
+        tupdesc,
+        replacements.len() as c_int,
+        columns.as_mut_ptr(),
+        values.as_mut_ptr(),
+        nulls.as_mut_ptr(),
+    )
+}
+
+/// The number of elements of a `List`.  `NIL`, the null pointer, is the empty list
+///
+/// # Safety
+///
+/// `list` must be a valid `List` or null
+pub unsafe fn list_length(list: *const pg_sys::List) -> usize {
+    list.as_ref().map(|list| list.length as usize).unwrap_or(0)
+}
+
+/// The cell at position `n` (from zero) of a `List`.  Until Postgres 13, lists were linked lists,
+/// so this takes time proportional to `n` there, rather than constant time
+///
+/// # Safety
+///
+/// `list` must be a valid `List` or null
+pub unsafe fn list_nth_cell(list: *const pg_sys::List, n: usize) -> Option<*mut pg_sys::ListCell> {
+    if n >= list_length(list) {
+        return None;
+    }
+
+    #[cfg(any(feature = "pg11", feature = "pg12"))]
+    let cell = {
+        let mut cell = (*list).head;
+        for _ in 0..n {
+            cell = (*cell).next;
+        }
+        cell
+    };
+    #[cfg(not(any(feature = "pg11", feature = "pg12")))]
+    let cell = (*list).elements.add(n);
+
+    Some(cell)
+}
+
+/// The pointer at position `n` (from zero) of a `List` of pointers
+///
+/// # Safety
+///
+/// `list` must be a valid `List` of pointers (a `T_List`) or null
+pub unsafe fn list_nth_ptr(list: *const pg_sys::List, n: usize) -> Option<*mut std::ffi::c_void> {
+    list_nth_cell(list, n).map(|cell| cell_data(cell).ptr_value)
+}
+
+/// The integer at position `n` (from zero) of an integer `List`
+///
+/// # Safety
+///
+/// `list` must be a valid `List` of integers (a `T_IntList`) or null
+pub unsafe fn list_nth_int(list: *const pg_sys::List, n: usize) -> Option<i32> {
+    list_nth_cell(list, n).map(|cell| cell_data(cell).int_value)
+}
+
+/// The oid at position `n` (from zero) of an oid `List`
+///
+/// # Safety
+///
+/// `list` must be a valid `List` of oids (a `T_OidList`) or null
+pub unsafe fn list_nth_oid(list: *const pg_sys::List, n: usize) -> Option<pg_sys::Oid> {
+    list_nth_cell(list, n).map(|cell| cell_data(cell).oid_value)
+}
+
+/// The value stored in a list cell, whose layout changed in Postgres 13
+#[cfg(any(feature = "pg11", feature = "pg12"))]
+pub(crate) unsafe fn cell_data<'a>(
+    cell: *mut pg_sys::ListCell,
+) -> &'a pg_sys::ListCell__bindgen_ty_1 {
+    &(*cell).data
+}
+
+/// The value stored in a list cell, whose layout changed in Postgres 13
+#[cfg(not(any(feature = "pg11", feature = "pg12")))]
+pub(crate) unsafe fn cell_data<'a>(cell: *mut pg_sys::ListCell) -> &'a pg_sys::ListCell {
+    &*cell
+}
+
+/// Append `list2` to `list1`, returning the combined list.  Before Postgres 13 this reused the
+/// cells of `list2`, and since then it copies them, so on every version `list2` must not be used
+/// afterwards.  Like `list_concat()`
+///
+/// # Safety
+///
+/// Both lists must be valid `List`s of the same kind, or null
+pub unsafe fn list_concat(list1: *mut pg_sys::List, list2: *mut pg_sys::List) -> *mut pg_sys::List {
+    pg_sys::list_concat(list1, list2)
+}
+
+/// Plan a query, producing a `PlannedStmt`.  Like `pg_plan_query()`, which takes the query's
+/// source text since Postgres 13
+///
+/// # Safety
+///
+/// `query` must be a valid, rewritten, `Query`, and `params` valid or null
+pub unsafe fn plan_query(
+    query: *mut pg_sys::Query,
+    query_string: *const std::os::raw::c_char,
+    cursor_options: c_int,
+    params: pg_sys::ParamListInfo,
+) -> *mut pg_sys::PlannedStmt {
+    #[cfg(any(feature = "pg11", feature = "pg12"))]
+    let plan = {
+        let _ = query_string;
+        pg_sys::pg_plan_query(query, cursor_options, params)
+    };
+    #[cfg(not(any(feature = "pg11", feature = "pg12")))]
+    let plan = pg_sys::pg_plan_query(query, query_string, cursor_options, params);
+    plan
+}
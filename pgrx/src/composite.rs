@@ -0,0 +1,126 @@
+/*
+Portions Copyright 2019-2021 ZomboDB, LLC.
+Portions Copyright 2021-2022 Technology Concepts & Design, Inc. <support@tcdi.com>
+
+All rights reserved.
+
+Use of this source code is governed by the MIT license that can be found in the LICENSE file.
+*/
+
+//! Rust structs as named composite types
+//!
+//! `#[derive(PostgresComposite)]` maps a struct with named fields to a composite type of the same
+//! name, with one attribute per field:
+//!
+//! ```rust,no_run
+//! use pgrx::prelude::*;
+//!
+//! #[derive(PostgresComposite)]
+//! struct Dog {
+//!     name: String,
+//!     #[pgrx(rename = "scritches")]
+//!     scritch_count: i32,
+//!     owner: Option<String>,
+//! }
+//! ```
+//!
+//! creates the type with
+//!
+//! ```sql
+//! CREATE TYPE Dog AS (
+//!     name text,
+//!     scritches integer,
+//!     owner text
+//! );
+//! ```
+//!
+//! Values are packed into, and unpacked from, heap tuples by attribute name rather than position,
+//! using the type's row descriptor at the time.  Attributes since dropped from the type are left
+//! out when packing, and read as `None` when unpacking, as are attributes added to the type after
+//! the struct was written.  An attribute a non-`Option` field is read from can't be missing or NULL.
+use crate::heap_tuple::{PgHeapTuple, PgHeapTupleError};
+use crate::{AllocatedByRust, FromDatum, IntoDatum, TryFromDatumError, WhoAllocated};
+use pgrx_sql_entity_graph::metadata::{ArgumentError, SqlMapping};
+
+/// Why a value couldn't be converted to or from its composite type
+#[derive(thiserror::Error, Debug, Clone, PartialEq, Eq)]
+pub enum CompositeError {
+    #[error("{0}")]
+    HeapTuple(#[from] PgHeapTupleError),
+    #[error("{0}")]
+    Attribute(#[from] TryFromDatumError),
+    #[error("attribute `{attribute}` of composite type `{type_name}` is missing or NULL")]
+    MissingAttribute { type_name: &'static str, attribute: &'static str },
+}
+
+/// A Rust struct stored as a named composite type
+///
+/// Implemented by `#[derive(PostgresComposite)]`.
+pub trait PostgresComposite: Sized {
+    /// The composite type's name
+    const TYPE_NAME: &'static str;
+
+    /// Unpacks a value of the composite type, attribute by attribute
+    fn from_heap_tuple<A: WhoAllocated>(tuple: &PgHeapTuple<'_, A>)
+        -> Result<Self, CompositeError>;
+
+    /// Packs this value into a new tuple of the composite type
+    fn into_heap_tuple(self) -> Result<PgHeapTuple<'static, AllocatedByRust>, CompositeError>;
+}
+
+/// Reads the named attribute, or `None` if the type doesn't have one by that name
+#[doc(hidden)]
+pub fn get_attribute<T: FromDatum + IntoDatum + 'static, A: WhoAllocated>(
+    tuple: &PgHeapTuple<'_, A>,
+    name: &str,
+) -> Result<Option<T>, CompositeError> {
+    match tuple.get_by_name::<T>(name) {
+        Err(TryFromDatumError::NoSuchAttributeName(_)) => Ok(None),
+        other => Ok(other?),
+    }
+}
+
+/// Writes the named attribute, unless the type doesn't have one by that name
+#[doc(hidden)]
+pub fn set_attribute<T: IntoDatum>(
+    tuple: &mut PgHeapTuple<'_, AllocatedByRust>,
+    name: &str,
+    value: T,
+) -> Result<(), CompositeError> {
+    match tuple.set_by_name(name, value) {
+        Err(TryFromDatumError::NoSuchAttributeName(_)) => Ok(()),
+        other => Ok(other?),
+    }
+}
+
+/// The `CREATE TYPE` statement for a composite type of the given `(name, sql type)` attributes
+#[doc(hidden)]
+pub fn create_type_sql(type_name: &str, attributes: &[(&str, String)]) -> &'static str {
+    let attributes = attributes
+        .iter()
+        .map(|(name, sql)| format!("    {} {}", name, sql))
+        .collect::<Vec<_>>()
+        .join(",\n");
+    Box::leak(format!("CREATE TYPE {} AS (\n{}\n);", type_name, attributes).into_boxed_str())
+}
+
+/// The SQL type of an attribute, from its Rust type's argument mapping.  Types without an explicit
+/// SQL name, such as `#[derive(PostgresType)]`s, are named after the Rust type.
+#[doc(hidden)]
+pub fn attribute_sql(
+    type_name: &str,
+    attribute: &str,
+    mapping: Result<SqlMapping, ArgumentError>,
+    rust_name: &str,
+) -> String {
+    match mapping {
+        Ok(SqlMapping::As(sql)) => sql,
+        Ok(SqlMapping::Composite { array_brackets } | SqlMapping::Source { array_brackets }) => {
+            format!("{}{}", rust_name, if array_brackets { "[]" } else { "" })
+        }
+        Ok(SqlMapping::Skip) => {
+            panic!("attribute `{}` of composite type `{}` has no SQL type", attribute, type_name)
+        }
+        Err(e) => panic!("attribute `{}` of composite type `{}`: {}", attribute, type_name, e),
+    }
+}
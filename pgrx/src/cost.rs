@@ -0,0 +1,249 @@
+/*
+Portions Copyright 2019-2021 ZomboDB, LLC.
+Portions Copyright 2021-2022 Technology Concepts & Design, Inc. <support@tcdi.com>
+
+All rights reserved.
+
+Use of this source code is governed by the MIT license that can be found in the LICENSE file.
+*/
+
+//! The planner's cost parameters, and path cost estimates built from them
+//!
+//! Foreign data wrappers and custom scans have to tell the planner what their paths cost.
+//! Estimating that with the same parameters and formulas Postgres uses for its own sequential and
+//! index scans lets those paths compete fairly with the built-in ones, and respects whatever the
+//! DBA has tuned `seq_page_cost` and friends to.
+//!
+//! ## Examples
+//!
+//! ```rust,no_run
+//! use pgrx::cost::{CostParameters, RelationSize};
+//! use pgrx::prelude::*;
+//!
+//! # unsafe fn example(path: *mut pg_sys::Path, relid: pg_sys::Oid) {
+//! let params = CostParameters::for_relation(relid);
+//! let cost = params.seq_scan(RelationSize::estimate(relid), 0.0).with_selectivity(0.1);
+//! cost.apply_to(path);
+//! # }
+//! ```
+use crate::pg_sys;
+
+/// The planner's cost parameters, as configured by the `*_cost` and `effective_cache_size` GUCs
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub struct CostParameters {
+    /// `seq_page_cost`:  the cost of a sequentially-fetched disk page
+    pub seq_page_cost: f64,
+    /// `random_page_cost`:  the cost of a non-sequentially-fetched disk page
+    pub random_page_cost: f64,
+    /// `cpu_tuple_cost`:  the cost of processing each row
+    pub cpu_tuple_cost: f64,
+    /// `cpu_index_tuple_cost`:  the cost of processing each index entry
+    pub cpu_index_tuple_cost: f64,
+    /// `cpu_operator_cost`:  the cost of evaluating each operator or function
+    pub cpu_operator_cost: f64,
+    /// `parallel_tuple_cost`:  the cost of passing each row from a parallel worker to the leader
+    pub parallel_tuple_cost: f64,
+    /// `parallel_setup_cost`:  the cost of starting parallel workers
+    pub parallel_setup_cost: f64,
+    /// `effective_cache_size`, in pages
+    pub effective_cache_size: f64,
+}
+
+impl CostParameters {
+    /// The cost parameters currently in effect
+    pub fn current() -> Self {
+        unsafe {
+            // SAFETY:  these are the planner's GUC variables, which are plain values that Postgres
+            // only assigns while processing `SET` or a configuration reload, never during this read
+            CostParameters {
+                seq_page_cost: pg_sys::seq_page_cost,
+                random_page_cost: pg_sys::random_page_cost,
+                cpu_tuple_cost: pg_sys::cpu_tuple_cost,
+                cpu_index_tuple_cost: pg_sys::cpu_index_tuple_cost,
+                cpu_operator_cost: pg_sys::cpu_operator_cost,
+                parallel_tuple_cost: pg_sys::parallel_tuple_cost,
+                parallel_setup_cost: pg_sys::parallel_setup_cost,
+                effective_cache_size: pg_sys::effective_cache_size as f64,
+            }
+        }
+    }
+
+    /// The cost parameters currently in effect, with the page costs overridden by those of the
+    /// tablespace the relation with the specified oid lives in, if it sets any
+    pub fn for_relation(relid: pg_sys::Oid) -> Self {
+        let spcid = unsafe { pg_sys::get_rel_tablespace(relid) };
+        Self::for_tablespace(spcid)
+    }
+
+    /// The cost parameters currently in effect, with the page costs overridden by those of the
+    /// specified tablespace, if it sets any.  `InvalidOid` means the database's default tablespace
+    pub fn for_tablespace(spcid: pg_sys::Oid) -> Self {
+        let mut params = Self::current();
+        unsafe {
+            pg_sys::get_tablespace_page_costs(
+                spcid,
+                &mut params.random_page_cost,
+                &mut params.seq_page_cost,
+            );
+        }
+        params
+    }
+
+    /// Estimate a full sequential scan of a relation of the specified `size`, evaluating quals that
+    /// cost `qual_cost_per_tuple` for each row.  Like `cost_seqscan()`
+    pub fn seq_scan(&self, size: RelationSize, qual_cost_per_tuple: f64) -> PathCost {
+        let disk_cost = self.seq_page_cost * size.pages;
+        let cpu_cost = (self.cpu_tuple_cost + qual_cost_per_tuple) * size.tuples;
+        PathCost { startup: 0.0, total: disk_cost + cpu_cost, rows: clamp_row_est(size.tuples) }
+    }
+
+    /// Estimate a scan of `table` through `index` that returns the fraction `selectivity` of the
+    /// table's rows, evaluating quals that cost `qual_cost_per_tuple` for each one.
+    ///
+    /// `correlation` is the correlation between the index order and the table's physical order,
+    /// between `-1.0` and `1.0`, as found in `pg_stats.correlation`.  Like `cost_index()` combined
+    /// with `genericcostestimate()`
+    pub fn index_scan(
+        &self,
+        table: RelationSize,
+        index: RelationSize,
+        selectivity: f64,
+        correlation: f64,
+        qual_cost_per_tuple: f64,
+    ) -> PathCost {
+        let selectivity = selectivity.clamp(0.0, 1.0);
+
+        // the part of the index we have to read, in no particular order
+        let index_tuples = clamp_row_est(selectivity * index.tuples);
+        let index_pages = if index.tuples > 1.0 {
+            (index_tuples * index.pages / index.tuples).ceil()
+        } else {
+            index.pages.min(1.0)
+        };
+        let index_cost = index_pages * self.random_page_cost
+            + index_tuples * (self.cpu_index_tuple_cost + self.cpu_operator_cost);
+
+        // the heap pages we visit:  each one at random if the index order is unrelated to the
+        // table's, or a contiguous run if it's perfectly correlated
+        let tuples_fetched = clamp_row_est(selectivity * table.tuples);
+        let max_io =
+            self.pages_fetched(tuples_fetched, table.pages, index.pages) * self.random_page_cost;
+        let pages_fetched = (selectivity * table.pages).ceil().max(1.0);
+        let min_io = self.random_page_cost + (pages_fetched - 1.0) * self.seq_page_cost;
+        let csquared = correlation.clamp(-1.0, 1.0).powi(2);
+        let io_cost = max_io + csquared * (min_io - max_io);
+
+        let cpu_cost = (self.cpu_tuple_cost + qual_cost_per_tuple) * tuples_fetched;
+        PathCost { startup: 0.0, total: index_cost + io_cost + cpu_cost, rows: tuples_fetched }
+    }
+
+    /// The Mackert and Lohman approximation of the number of distinct pages fetched when fetching
+    /// `tuples` tuples from a table of `pages` pages, accounting for caching.  Like
+    /// `index_pages_fetched()`
+    pub fn pages_fetched(&self, tuples: f64, pages: f64, index_pages: f64) -> f64 {
+        let t = pages.max(1.0);
+        let total_pages = (pages + index_pages).max(1.0);
+        let b = (self.effective_cache_size * t / total_pages).max(1.0).ceil();
+
+        let fetched = if t <= b {
+            ((2.0 * t * tuples) / (2.0 * t + tuples)).min(t)
+        } else {
+            let lim = (2.0 * t * b) / (2.0 * t - b);
+            if tuples <= lim {
+                (2.0 * t * tuples) / (2.0 * t + tuples)
+            } else {
+                b + (tuples - lim) * (t - b) / t
+            }
+        };
+        fetched.ceil()
+    }
+}
+
+/// The size of a relation, in pages and tuples
+#[derive(Debug, Default, Copy, Clone, PartialEq)]
+pub struct RelationSize {
+    pub pages: f64,
+    pub tuples: f64,
+}
+
+impl RelationSize {
+    /// The planner's estimate of the current size of the relation with the specified oid, which
+    /// the caller should hold at least `AccessShareLock` on.  Like `estimate_rel_size()`, this
+    /// scales `pg_class.reltuples` by the relation's actual number of pages
+    pub fn estimate(relid: pg_sys::Oid) -> Self {
+        let mut pages: pg_sys::BlockNumber = 0;
+        let mut tuples = 0.0;
+        let mut allvisfrac = 0.0;
+        unsafe {
+            let rel = pg_sys::RelationIdGetRelation(relid);
+            if rel.is_null() {
+                panic!("could not open relation with OID {}", relid.as_u32());
+            }
+            pg_sys::estimate_rel_size(
+                rel,
+                std::ptr::null_mut(),
+                &mut pages,
+                &mut tuples,
+                &mut allvisfrac,
+            );
+            pg_sys::RelationClose(rel);
+        }
+        RelationSize { pages: pages as f64, tuples }
+    }
+}
+
+/// An estimated cost, in the planner's arbitrary units, and row count for a path
+#[derive(Debug, Default, Copy, Clone, PartialEq)]
+pub struct PathCost {
+    /// The cost spent before the first row is returned
+    pub startup: f64,
+    /// The cost of returning every row
+    pub total: f64,
+    /// The number of rows returned
+    pub rows: f64,
+}
+
+impl PathCost {
+    /// Scale the number of rows by a qual's `selectivity`.  The cost is unchanged, as every row
+    /// still has to be looked at
+    pub fn with_selectivity(self, selectivity: f64) -> Self {
+        PathCost { rows: clamp_row_est(self.rows * selectivity.clamp(0.0, 1.0)), ..self }
+    }
+
+    /// Add the cost of another step, such as a projection, that runs once per row
+    pub fn plus_per_row(self, cost_per_row: f64) -> Self {
+        PathCost { total: self.total + cost_per_row * self.rows, ..self }
+    }
+
+    /// Set the `startup_cost`, `total_cost`, and `rows` of a `Path`
+    ///
+    /// # Safety
+    ///
+    /// `path` must point to a valid `Path` (or a node that begins with one)
+    pub unsafe fn apply_to(&self, path: *mut pg_sys::Path) {
+        (*path).startup_cost = self.startup;
+        (*path).total_cost = self.total;
+        (*path).rows = self.rows;
+    }
+}
+
+/// The cost of evaluating `quals`, a list of qual expressions, as `(startup, per_tuple)`.  Like
+/// `cost_qual_eval()`, which also honors the costs of the functions involved
+///
+/// # Safety
+///
+/// `quals` must be a valid `List` of expression nodes, and `root` a valid `PlannerInfo` or null
+pub unsafe fn qual_cost(quals: *mut pg_sys::List, root: *mut pg_sys::PlannerInfo) -> (f64, f64) {
+    let mut cost = pg_sys::QualCost::default();
+    pg_sys::cost_qual_eval(&mut cost, quals, root);
+    (cost.startup, cost.per_tuple)
+}
+
+/// Round a row estimate to an integer, but never less than one.  Like `clamp_row_est()`
+pub fn clamp_row_est(rows: f64) -> f64 {
+    if rows.is_nan() || rows <= 1.0 {
+        1.0
+    } else {
+        rows.round()
+    }
+}
@@ -0,0 +1,430 @@
+/*
+Portions Copyright 2019-2021 ZomboDB, LLC.
+Portions Copyright 2021-2022 Technology Concepts & Design, Inc. <support@tcdi.com>
+
+All rights reserved.
+
+Use of this source code is governed by the MIT license that can be found in the LICENSE file.
+*/
+
+//! Custom scans, which let an extension offer the planner its own way of scanning a table
+//!
+//! A custom scan provider is described by three traits, after the three tables of callbacks
+//! Postgres asks for:
+//!
+//! - [`CustomPathMethods`] offers paths for scanning a table, with their estimated costs
+//! - [`CustomScanMethods`] creates the state of a scan chosen by the planner
+//! - [`CustomExecMethods`] is that state, producing the table's rows
+//!
+//! [`register_custom_scan()`] registers the provider with Postgres, and installs a
+//! [`set_rel_pathlist`](crate::hooks::PgHooks::set_rel_pathlist) hook that offers its paths.  It
+//! must be called once, from `_PG_init()`:
+//!
+//! ```rust,no_run
+//! use pgrx::custom_scan::*;
+//! use pgrx::fdw::FdwRow;
+//! use pgrx::pathlist::PathEstimates;
+//! use pgrx::prelude::*;
+//!
+//! struct Answer;
+//!
+//! impl CustomScanMethods for Answer {
+//!     const NAME: &'static str = "Answer";
+//!     type Exec = AnswerScan;
+//!
+//!     fn create_scan_state(_private: &[String]) -> AnswerScan {
+//!         AnswerScan { done: false }
+//!     }
+//! }
+//!
+//! impl CustomPathMethods for Answer {
+//!     fn create_paths(rel: &CustomScanRel) -> Vec<CustomPathInfo> {
+//!         let estimates = PathEstimates { startup_cost: 0.0, total_cost: 0.01, rows: 1.0 };
+//!         match rel.name().as_str() {
+//!             "the_answer" => vec![CustomPathInfo::new(estimates)],
+//!             _ => vec![],
+//!         }
+//!     }
+//! }
+//!
+//! struct AnswerScan {
+//!     done: bool,
+//! }
+//!
+//! impl CustomExecMethods for AnswerScan {
+//!     fn exec_scan(&mut self, row: &mut FdwRow) -> bool {
+//!         row.set("answer", Some(42));
+//!         !std::mem::replace(&mut self.done, true)
+//!     }
+//!
+//!     fn rescan(&mut self) {
+//!         self.done = false;
+//!     }
+//! }
+//!
+//! #[pg_guard]
+//! pub extern "C" fn _PG_init() {
+//!     unsafe { register_custom_scan::<Answer>() };
+//! }
+//! ```
+//!
+//! Like a foreign table's rows, the rows a custom scan returns are checked against the query's
+//! `WHERE` clause by Postgres, so a scan need not apply it itself.
+use crate as pgrx; // for #[pg_guard] support from within ourself
+use crate::explain::Explain;
+use crate::fdw::{FdwColumn, FdwRow};
+use crate::hooks::{register_hook, HookResult, PgHooks};
+use crate::pathlist::{PathEstimates, RelPathList};
+use crate::prelude::*;
+use crate::{compat, PgList, PgMemoryContexts};
+use pg_sys::AsPgCStr;
+use std::marker::PhantomData;
+use std::os::raw::c_int;
+
+/// The planning half of a custom scan provider:  offers the planner paths for scanning tables
+pub trait CustomPathMethods: CustomScanMethods {
+    /// The paths to offer for scanning `rel`, if any.  The planner only uses one if it's the
+    /// cheapest way to scan the table
+    fn create_paths(rel: &CustomScanRel) -> Vec<CustomPathInfo>;
+}
+
+/// Creates the state of a custom scan the planner chose
+pub trait CustomScanMethods: 'static {
+    /// The provider's name, which `EXPLAIN` shows as `Custom Scan (NAME)`.  It must be unique
+    /// among all the custom scan providers loaded into a backend
+    const NAME: &'static str;
+
+    /// The state of a scan
+    type Exec: CustomExecMethods;
+
+    /// Create the state of a scan, given the private data of the path it was planned from
+    fn create_scan_state(private: &[String]) -> Self::Exec;
+}
+
+/// The state of a custom scan being executed
+pub trait CustomExecMethods: Sized + 'static {
+    /// Start the scan of a table with `columns`.  Not called for `EXPLAIN` without `ANALYZE`
+    fn begin_scan(&mut self, _columns: &[FdwColumn]) {}
+
+    /// Fill in the next row, returning false if there are no more
+    fn exec_scan(&mut self, row: &mut FdwRow) -> bool;
+
+    /// Restart the scan from its first row.  Raises an error by default
+    fn rescan(&mut self) {
+        ereport!(
+            PgLogLevel::ERROR,
+            PgSqlErrorCode::ERRCODE_FEATURE_NOT_SUPPORTED,
+            format!("{} does not support rescanning", std::any::type_name::<Self>())
+        );
+    }
+
+    /// End the scan.  Not called if the scan ends in an error, but the state is dropped either way
+    fn end_scan(&mut self) {}
+
+    /// Add to the scan's `EXPLAIN` output
+    fn explain(&self, _explain: &mut Explain) {}
+}
+
+/// A table being planned, for [`CustomPathMethods::create_paths()`]
+pub struct CustomScanRel {
+    rel: *mut pg_sys::RelOptInfo,
+    relid: pg_sys::Oid,
+}
+
+impl CustomScanRel {
+    /// The table's oid
+    pub fn oid(&self) -> pg_sys::Oid {
+        self.relid
+    }
+
+    /// The table's name
+    pub fn name(&self) -> String {
+        unsafe {
+            let name = pg_sys::get_rel_name(self.relid);
+            std::ffi::CStr::from_ptr(name).to_string_lossy().into_owned()
+        }
+    }
+
+    /// The planner's estimate of the rows the scan returns, after the query's `WHERE` clause
+    pub fn rows(&self) -> f64 {
+        unsafe { (*self.rel).rows }
+    }
+
+    /// The planner's estimate of the number of pages in the table
+    pub fn pages(&self) -> u32 {
+        unsafe { (*self.rel).pages }
+    }
+
+    /// The planner's estimate of the number of rows in the table
+    pub fn tuples(&self) -> f64 {
+        unsafe { (*self.rel).tuples }
+    }
+}
+
+/// A path offered by [`CustomPathMethods::create_paths()`]
+#[derive(Debug, Clone, PartialEq)]
+pub struct CustomPathInfo {
+    pub estimates: PathEstimates,
+    /// Passed to [`CustomScanMethods::create_scan_state()`] if the path is chosen
+    pub private: Vec<String>,
+}
+
+impl CustomPathInfo {
+    pub fn new(estimates: PathEstimates) -> Self {
+        CustomPathInfo { estimates, private: Vec::new() }
+    }
+
+    pub fn with_private(mut self, private: Vec<String>) -> Self {
+        self.private = private;
+        self
+    }
+}
+
+// a scan's methods, laid out so the exec methods can be found from the scan methods Postgres
+// hands back
+#[repr(C)]
+struct ScanMethods {
+    scan: pg_sys::CustomScanMethods,
+    exec: pg_sys::CustomExecMethods,
+}
+
+/// Register the custom scan provider `T`, and a hook offering its paths
+///
+/// # Safety
+///
+/// Must be called once per backend, from `_PG_init()`.  Postgres raises an error if a provider
+/// with the same [`NAME`](CustomScanMethods::NAME) was already registered
+pub unsafe fn register_custom_scan<T: CustomPathMethods>() {
+    // the methods live as long as the backend does
+    let name = PgMemoryContexts::TopMemoryContext.switch_to(|_| T::NAME.as_pg_cstr());
+    let scan_methods: &'static ScanMethods = Box::leak(Box::new(ScanMethods {
+        scan: pg_sys::CustomScanMethods {
+            CustomName: name,
+            CreateCustomScanState: Some(create_custom_scan_state::<T>),
+        },
+        exec: pg_sys::CustomExecMethods {
+            CustomName: name,
+            BeginCustomScan: Some(begin_custom_scan::<T>),
+            ExecCustomScan: Some(exec_custom_scan::<T>),
+            EndCustomScan: Some(end_custom_scan::<T>),
+            ReScanCustomScan: Some(rescan_custom_scan::<T>),
+            ExplainCustomScan: Some(explain_custom_scan::<T>),
+            ..Default::default()
+        },
+    }));
+    let path_methods: &'static pg_sys::CustomPathMethods =
+        Box::leak(Box::new(pg_sys::CustomPathMethods {
+            CustomName: name,
+            PlanCustomPath: Some(plan_custom_path),
+            ..Default::default()
+        }));
+
+    pg_sys::RegisterCustomScanMethods(&scan_methods.scan);
+    register_hook(Box::leak(Box::new(CustomScanPaths::<T> { path_methods, _marker: PhantomData })));
+}
+
+struct CustomScanPaths<T> {
+    path_methods: &'static pg_sys::CustomPathMethods,
+    _marker: PhantomData<T>,
+}
+
+impl<T: CustomPathMethods> PgHooks for CustomScanPaths<T> {
+    fn set_rel_pathlist(
+        &mut self,
+        root: PgBox<pg_sys::PlannerInfo>,
+        rel: PgBox<pg_sys::RelOptInfo>,
+        rti: pg_sys::Index,
+        rte: PgBox<pg_sys::RangeTblEntry>,
+        prev_hook: fn(
+            root: PgBox<pg_sys::PlannerInfo>,
+            rel: PgBox<pg_sys::RelOptInfo>,
+            rti: pg_sys::Index,
+            rte: PgBox<pg_sys::RangeTblEntry>,
+        ) -> HookResult<()>,
+    ) -> HookResult<()> {
+        let (root_ptr, rel_ptr, rte_ptr) = (root.as_ptr(), rel.as_ptr(), rte.as_ptr());
+        let result = prev_hook(root, rel, rti, rte);
+        unsafe {
+            // only plain scans of tables, not of an inheritance tree's parent
+            let scannable = (*rel_ptr).reloptkind == pg_sys::RelOptKind_RELOPT_BASEREL
+                && (*rte_ptr).rtekind == pg_sys::RTEKind_RTE_RELATION
+                && !(*rte_ptr).inh
+                && [pg_sys::RELKIND_RELATION, pg_sys::RELKIND_MATVIEW]
+                    .contains(&((*rte_ptr).relkind as u8));
+            if scannable {
+                let scan_rel = CustomScanRel { rel: rel_ptr, relid: (*rte_ptr).relid };
+                let mut paths = RelPathList::from_pg(rel_ptr);
+                for info in T::create_paths(&scan_rel) {
+                    paths.add_path(self.make_path(root_ptr, rel_ptr, &info).cast());
+                }
+            }
+        }
+        result
+    }
+}
+
+impl<T> CustomScanPaths<T> {
+    unsafe fn make_path(
+        &self,
+        root: *mut pg_sys::PlannerInfo,
+        rel: *mut pg_sys::RelOptInfo,
+        info: &CustomPathInfo,
+    ) -> *mut pg_sys::CustomPath {
+        let mut path = PgBox::<pg_sys::CustomPath>::alloc_node(pg_sys::NodeTag_T_CustomPath);
+        path.path.pathtype = pg_sys::NodeTag_T_CustomScan;
+        path.path.parent = rel;
+        path.path.pathtarget = (*rel).reltarget;
+        path.path.param_info = pg_sys::get_baserel_parampathinfo(root, rel, (*rel).lateral_relids);
+        path.path.rows = info.estimates.rows;
+        path.path.startup_cost = info.estimates.startup_cost;
+        path.path.total_cost = info.estimates.total_cost;
+        let mut private = PgList::<pg_sys::Node>::new();
+        for value in &info.private {
+            private.push(pg_sys::makeString(value.as_pg_cstr()).cast());
+        }
+        path.custom_private = private.into_pg();
+        path.methods = self.path_methods;
+        path.into_pg()
+    }
+}
+
+#[pg_guard]
+unsafe extern "C" fn plan_custom_path(
+    _root: *mut pg_sys::PlannerInfo,
+    rel: *mut pg_sys::RelOptInfo,
+    best_path: *mut pg_sys::CustomPath,
+    tlist: *mut pg_sys::List,
+    clauses: *mut pg_sys::List,
+    _custom_plans: *mut pg_sys::List,
+) -> *mut pg_sys::Plan {
+    let mut scan = PgBox::<pg_sys::CustomScan>::alloc_node(pg_sys::NodeTag_T_CustomScan);
+    scan.scan.plan.targetlist = tlist;
+    // the executor checks every clause against the rows the scan returns
+    scan.scan.plan.qual = pg_sys::extract_actual_clauses(clauses, false);
+    scan.scan.scanrelid = (*rel).relid;
+    scan.flags = (*best_path).flags;
+    scan.custom_private = (*best_path).custom_private;
+    // the scan methods were registered under the same name as the path methods
+    scan.methods = pg_sys::GetCustomScanMethods((*(*best_path).methods).CustomName, false);
+    scan.into_pg().cast()
+}
+
+// a CustomScanState, followed by the Rust state of the scan
+#[repr(C)]
+struct ScanState<E> {
+    css: pg_sys::CustomScanState,
+    exec: *mut ExecState<E>,
+}
+
+struct ExecState<E> {
+    exec: E,
+    columns: Vec<FdwColumn>,
+    begun: bool,
+}
+
+unsafe fn private_strings(list: *mut pg_sys::List) -> Vec<String> {
+    PgList::<pg_sys::Node>::from_pg(list)
+        .iter_ptr()
+        .map(|node| {
+            #[cfg(any(feature = "pg11", feature = "pg12", feature = "pg13", feature = "pg14"))]
+            let value = (*(node as *mut pg_sys::Value)).val.str_;
+            #[cfg(feature = "pg15")]
+            let value = (*(node as *mut pg_sys::String)).sval;
+            std::ffi::CStr::from_ptr(value).to_string_lossy().into_owned()
+        })
+        .collect()
+}
+
+#[pg_guard]
+unsafe extern "C" fn create_custom_scan_state<T: CustomScanMethods>(
+    cscan: *mut pg_sys::CustomScan,
+) -> *mut pg_sys::Node {
+    let scan_methods = (*cscan).methods as *const ScanMethods;
+    let exec = T::create_scan_state(&private_strings((*cscan).custom_private));
+    let exec = ExecState { exec, columns: Vec::new(), begun: false };
+
+    let state =
+        pg_sys::palloc0(std::mem::size_of::<ScanState<T::Exec>>()) as *mut ScanState<T::Exec>;
+    (*state).css.ss.ps.type_ = pg_sys::NodeTag_T_CustomScanState;
+    (*state).css.methods = &(*scan_methods).exec;
+    // dropped with the executor's memory, even if the scan ends in an error
+    (*state).exec = PgMemoryContexts::CurrentMemoryContext.leak_and_drop_on_delete(exec);
+    state.cast()
+}
+
+unsafe fn exec_state<'a, T: CustomScanMethods>(
+    node: *mut pg_sys::CustomScanState,
+) -> &'a mut ExecState<T::Exec> {
+    &mut *(*(node as *mut ScanState<T::Exec>)).exec
+}
+
+#[pg_guard]
+unsafe extern "C" fn begin_custom_scan<T: CustomScanMethods>(
+    node: *mut pg_sys::CustomScanState,
+    _estate: *mut pg_sys::EState,
+    eflags: c_int,
+) {
+    if eflags & pg_sys::EXEC_FLAG_EXPLAIN_ONLY as c_int != 0 {
+        return;
+    }
+    let state = exec_state::<T>(node);
+    state.columns = crate::fdw::table_columns((*node).ss.ss_currentRelation);
+    state.exec.begin_scan(&state.columns);
+    state.begun = true;
+}
+
+#[pg_guard]
+unsafe extern "C" fn exec_custom_scan<T: CustomScanMethods>(
+    node: *mut pg_sys::CustomScanState,
+) -> *mut pg_sys::TupleTableSlot {
+    // ExecScan() evaluates the query's quals and projects the scanned rows
+    pg_sys::ExecScan(&mut (*node).ss, Some(next_row::<T>), Some(recheck_row))
+}
+
+#[pg_guard]
+unsafe extern "C" fn next_row<T: CustomScanMethods>(
+    node: *mut pg_sys::ScanState,
+) -> *mut pg_sys::TupleTableSlot {
+    let state = exec_state::<T>(node.cast());
+    let slot = (*node).ss_ScanTupleSlot;
+    compat::clear_slot(slot);
+    let natts = (*(*slot).tts_tupleDescriptor).natts as usize;
+    std::slice::from_raw_parts_mut((*slot).tts_isnull, natts).fill(true);
+
+    let mut row = FdwRow::new(slot, &state.columns);
+    if state.exec.exec_scan(&mut row) {
+        pg_sys::ExecStoreVirtualTuple(slot);
+    }
+    // an empty slot ends the scan
+    slot
+}
+
+#[pg_guard]
+unsafe extern "C" fn recheck_row(
+    _node: *mut pg_sys::ScanState,
+    _slot: *mut pg_sys::TupleTableSlot,
+) -> bool {
+    true
+}
+
+#[pg_guard]
+unsafe extern "C" fn end_custom_scan<T: CustomScanMethods>(node: *mut pg_sys::CustomScanState) {
+    let state = exec_state::<T>(node);
+    if state.begun {
+        state.exec.end_scan();
+    }
+}
+
+#[pg_guard]
+unsafe extern "C" fn rescan_custom_scan<T: CustomScanMethods>(node: *mut pg_sys::CustomScanState) {
+    exec_state::<T>(node).exec.rescan();
+}
+
+#[pg_guard]
+unsafe extern "C" fn explain_custom_scan<T: CustomScanMethods>(
+    node: *mut pg_sys::CustomScanState,
+    _ancestors: *mut pg_sys::List,
+    es: *mut pg_sys::ExplainState,
+) {
+    exec_state::<T>(node).exec.explain(&mut Explain::from_pg(es));
+}
@@ -0,0 +1,227 @@
+/*
+Portions Copyright 2019-2021 ZomboDB, LLC.
+Portions Copyright 2021-2022 Technology Concepts & Design, Inc. <support@tcdi.com>
+
+All rights reserved.
+
+Use of this source code is governed by the MIT license that can be found in the LICENSE file.
+*/
+
+//! A per-extension data directory under `$PGDATA`, for auxiliary files like models or dictionaries
+//!
+//! An [`ExtensionDataDir`] is `$PGDATA/pgrx_data/<extension>`.  Its files are written crash-safely:
+//! each is written to a temporary file, which is `fsync`'d and renamed over the old one before the
+//! directory is `fsync`'d, so after a crash a file has either its old or its new contents, never a
+//! mix.
+//!
+//! ```rust,no_run
+//! use pgrx::data_dir::ExtensionDataDir;
+//!
+//! # fn foo() -> std::io::Result<()> {
+//! let dir = ExtensionDataDir::open("my_extension")?;
+//! dir.write("stopwords.txt", "a\nan\nthe\n")?;
+//! assert_eq!(dir.read("stopwords.txt")?, b"a\nan\nthe\n");
+//! # Ok(())
+//! # }
+//! ```
+//!
+//! The directory is left behind by `DROP EXTENSION` unless the extension removes it, with an
+//! event trigger that calls [`remove_on_drop_extension()`]:
+//!
+//! ```rust,no_run
+//! use pgrx::event_trigger::EventTriggerData;
+//! use pgrx::prelude::*;
+//!
+//! #[pg_event_trigger(event = "ddl_command_start", tags = ["DROP EXTENSION"])]
+//! fn my_extension_drop(event: &EventTriggerData) {
+//!     pgrx::data_dir::remove_on_drop_extension(event, "my_extension");
+//! }
+//! ```
+use crate::event_trigger::EventTriggerData;
+use crate::{pg_sys, register_xact_callback, warning, PgXactCallbackEvent};
+use std::ffi::{CStr, CString};
+use std::fs::{self, File};
+use std::io::{self, BufWriter, Write};
+use std::path::{Path, PathBuf};
+
+/// The directory under `$PGDATA` every extension's data directory is in
+pub const DATA_DIR_NAME: &str = "pgrx_data";
+
+// the suffix of the temporary files being written, which are named `.<name>.<pid>.tmp`
+const TEMP_SUFFIX: &str = ".tmp";
+
+fn invalid_name(name: &str) -> io::Error {
+    io::Error::new(io::ErrorKind::InvalidInput, format!("invalid file name `{}`", name))
+}
+
+/// Is `name` a single path component that isn't a hidden or temporary file?
+fn is_valid_name(name: &str) -> bool {
+    !name.is_empty()
+        && !name.starts_with('.')
+        && !name.contains(|c| c == '/' || c == '\\' || c == '\0')
+}
+
+/// `fsync` a directory, so the entries created, renamed, or removed in it are durable
+fn fsync_dir(path: &Path) -> io::Result<()> {
+    File::open(path)?.sync_all()
+}
+
+/// Create the directory `path`, if it doesn't exist, and make its entry in its parent durable
+fn create_dir(path: &Path) -> io::Result<()> {
+    match fs::create_dir(path) {
+        Ok(()) => fsync_dir(path.parent().expect("a data directory has a parent")),
+        Err(e) if e.kind() == io::ErrorKind::AlreadyExists && path.is_dir() => Ok(()),
+        Err(e) => Err(e),
+    }
+}
+
+/// The path of `extension`'s data directory, `$PGDATA/pgrx_data/<extension>`
+pub fn extension_data_path(extension: &str) -> io::Result<PathBuf> {
+    if !is_valid_name(extension) {
+        return Err(invalid_name(extension));
+    }
+    // SAFETY:  `DataDir` is set by the postmaster before any backend starts
+    let data_dir = unsafe { CStr::from_ptr(pg_sys::DataDir) };
+    let data_dir = data_dir.to_str().map_err(|e| io::Error::new(io::ErrorKind::InvalidData, e))?;
+    Ok(Path::new(data_dir).join(DATA_DIR_NAME).join(extension))
+}
+
+/// An extension's data directory, `$PGDATA/pgrx_data/<extension>`
+#[derive(Debug, Clone)]
+pub struct ExtensionDataDir {
+    path: PathBuf,
+}
+
+impl ExtensionDataDir {
+    /// Open `extension`'s data directory, creating it if it doesn't exist
+    pub fn open(extension: &str) -> io::Result<Self> {
+        let path = extension_data_path(extension)?;
+        create_dir(path.parent().unwrap())?;
+        create_dir(&path)?;
+        Ok(ExtensionDataDir { path })
+    }
+
+    /// The directory's path
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+
+    /// The path of the file `name` in the directory.  `name` can't contain a path separator, nor
+    /// start with a `.`, which temporary files do
+    pub fn file_path(&self, name: &str) -> io::Result<PathBuf> {
+        if is_valid_name(name) {
+            Ok(self.path.join(name))
+        } else {
+            Err(invalid_name(name))
+        }
+    }
+
+    /// Does the file `name` exist?
+    pub fn exists(&self, name: &str) -> io::Result<bool> {
+        Ok(self.file_path(name)?.exists())
+    }
+
+    /// Read all of the file `name`
+    pub fn read(&self, name: &str) -> io::Result<Vec<u8>> {
+        fs::read(self.file_path(name)?)
+    }
+
+    /// Crash-safely replace the file `name` with `contents`
+    pub fn write(&self, name: &str, contents: impl AsRef<[u8]>) -> io::Result<()> {
+        self.write_with(name, |out| out.write_all(contents.as_ref()))
+    }
+
+    /// Crash-safely replace the file `name` with what `write` writes, for files too large to
+    /// build in memory first.  If `write` fails, the file is left as it was
+    pub fn write_with<F>(&self, name: &str, write: F) -> io::Result<()>
+    where
+        F: FnOnce(&mut BufWriter<File>) -> io::Result<()>,
+    {
+        let path = self.file_path(name)?;
+        let temp = self.path.join(format!(".{}.{}{}", name, std::process::id(), TEMP_SUFFIX));
+        let result = (|| {
+            let mut out = BufWriter::new(File::create(&temp)?);
+            write(&mut out)?;
+            out.into_inner().map_err(|e| e.into_error())?.sync_all()?;
+            fs::rename(&temp, &path)?;
+            fsync_dir(&self.path)
+        })();
+        if result.is_err() {
+            let _ = fs::remove_file(&temp);
+        }
+        result
+    }
+
+    /// Durably remove the file `name`
+    pub fn remove(&self, name: &str) -> io::Result<()> {
+        fs::remove_file(self.file_path(name)?)?;
+        fsync_dir(&self.path)
+    }
+
+    /// The names of the files in the directory, not including temporary files, sorted
+    pub fn list(&self) -> io::Result<Vec<String>> {
+        let mut names = Vec::new();
+        for entry in fs::read_dir(&self.path)? {
+            let name = entry?.file_name().to_string_lossy().into_owned();
+            if is_valid_name(&name) {
+                names.push(name);
+            }
+        }
+        names.sort();
+        Ok(names)
+    }
+
+    /// Remove the temporary files left behind by writes a crash interrupted.  This must only be
+    /// called when no other backend can be writing to the directory, such as from a background
+    /// worker as the server starts
+    pub fn remove_temp_files(&self) -> io::Result<()> {
+        for entry in fs::read_dir(&self.path)? {
+            let entry = entry?;
+            let name = entry.file_name().to_string_lossy().into_owned();
+            if name.starts_with('.') && name.ends_with(TEMP_SUFFIX) {
+                fs::remove_file(entry.path())?;
+            }
+        }
+        fsync_dir(&self.path)
+    }
+
+    /// Durably remove the directory and everything in it
+    pub fn remove_all(self) -> io::Result<()> {
+        fs::remove_dir_all(&self.path)?;
+        fsync_dir(self.path.parent().unwrap())
+    }
+}
+
+/// Remove `extension`'s data directory when the transaction commits, if a `DROP EXTENSION` the
+/// event trigger `event` fired for drops `extension`.
+///
+/// Call this from a `ddl_command_start` event trigger for `DROP EXTENSION`: the extension's own
+/// event trigger is dropped with it, so it doesn't fire at `ddl_command_end` or `sql_drop`.
+/// Whether `extension` was dropped is checked just before the transaction commits, so the
+/// directory is kept when the `DROP EXTENSION` fails, is rolled back, or drops other extensions.
+pub fn remove_on_drop_extension(event: &EventTriggerData, extension: &str) {
+    if event.tag() != "DROP EXTENSION" {
+        return;
+    }
+    let extension = extension.to_string();
+    register_xact_callback(PgXactCallbackEvent::PreCommit, move || {
+        let name = CString::new(extension.as_str()).expect("extension name contains a nul byte");
+        // SAFETY:  we're still in the transaction, so can look up the extension
+        let oid = unsafe { pg_sys::get_extension_oid(name.as_ptr(), true) };
+        if oid != pg_sys::InvalidOid {
+            return;
+        }
+        register_xact_callback(PgXactCallbackEvent::Commit, move || {
+            let result = extension_data_path(&extension).and_then(|path| {
+                if path.exists() {
+                    ExtensionDataDir { path }.remove_all()
+                } else {
+                    Ok(())
+                }
+            });
+            if let Err(e) = result {
+                warning!("could not remove the data directory of extension `{}`: {}", extension, e);
+            }
+        });
+    });
+}
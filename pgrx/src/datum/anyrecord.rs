@@ -0,0 +1,139 @@
+/*
+Portions Copyright 2019-2021 ZomboDB, LLC.
+Portions Copyright 2021-2022 Technology Concepts & Design, Inc. <support@tcdi.com>
+
+All rights reserved.
+
+Use of this source code is governed by the MIT license that can be found in the LICENSE file.
+*/
+
+use crate::heap_tuple::PgHeapTuple;
+use crate::{pg_sys, AllocatedByRust, FromDatum, IntoDatum, TryFromDatumError};
+use pgrx_sql_entity_graph::metadata::{
+    ArgumentError, Returns, ReturnsError, SqlMapping, SqlTranslatable,
+};
+use std::num::NonZeroUsize;
+
+/// The [`record` pseudo-type][record]:  a row of any composite type, including anonymous ones
+/// like `ROW(1, 'a')` and PL/pgSQL `RECORD` variables.
+///
+/// Unlike [`composite_type!()`][crate::composite_type], the row's type isn't known until it's
+/// received.  Its tuple descriptor is looked up from the type and typmod stored in the row itself,
+/// so its fields can be accessed by name.
+///
+/// [record]: https://www.postgresql.org/docs/current/datatype-pseudo.html
+pub struct AnyRecord {
+    tuple: PgHeapTuple<'static, AllocatedByRust>,
+}
+
+impl AnyRecord {
+    /// The row's composite type, which is `RECORDOID` for an anonymous row type
+    pub fn oid(&self) -> pg_sys::Oid {
+        self.tuple.composite_type_oid().unwrap_or(pg_sys::RECORDOID)
+    }
+
+    /// The name of the row's composite type, which is `record` for an anonymous row type
+    pub fn type_name(&self) -> String {
+        crate::datum::lookup_type_name(self.oid())
+    }
+
+    /// The number of fields
+    pub fn len(&self) -> usize {
+        self.tuple.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    /// The names of the fields, in order.  Fields of anonymous rows are named `f1`, `f2`, and so on
+    pub fn field_names(&self) -> Vec<String> {
+        self.tuple.attributes().map(|(_, att)| att.name().to_string()).collect()
+    }
+
+    /// The type of the field named `name`
+    pub fn field_type_oid(&self, name: &str) -> Option<pg_sys::Oid> {
+        self.tuple.get_attribute_by_name(name).map(|(_, att)| att.type_oid().value())
+    }
+
+    /// Retrieve the value of the field named `name`
+    ///
+    /// ## Errors
+    /// - return [`TryFromDatumError::NoSuchAttributeName`] if the field does not exist
+    /// - return [`TryFromDatumError::IncompatibleTypes`] if the Rust type is not compatible with
+    /// the field's Postgres type
+    pub fn get_by_name<T: FromDatum + IntoDatum + 'static>(
+        &self,
+        name: &str,
+    ) -> Result<Option<T>, TryFromDatumError> {
+        self.tuple.get_by_name(name)
+    }
+
+    /// Retrieve the value of the field at `index`, which starts at 1
+    ///
+    /// ## Errors
+    /// - return [`TryFromDatumError::NoSuchAttributeNumber`] if the field does not exist
+    /// - return [`TryFromDatumError::IncompatibleTypes`] if the Rust type is not compatible with
+    /// the field's Postgres type
+    pub fn get_by_index<T: FromDatum + IntoDatum + 'static>(
+        &self,
+        index: NonZeroUsize,
+    ) -> Result<Option<T>, TryFromDatumError> {
+        self.tuple.get_by_index(index)
+    }
+
+    /// The row as a [`PgHeapTuple`], to modify it or to use its attribute metadata
+    pub fn into_heap_tuple(self) -> PgHeapTuple<'static, AllocatedByRust> {
+        self.tuple
+    }
+}
+
+impl From<PgHeapTuple<'static, AllocatedByRust>> for AnyRecord {
+    fn from(tuple: PgHeapTuple<'static, AllocatedByRust>) -> Self {
+        AnyRecord { tuple }
+    }
+}
+
+impl FromDatum for AnyRecord {
+    #[inline]
+    unsafe fn from_polymorphic_datum(
+        datum: pg_sys::Datum,
+        is_null: bool,
+        _typoid: pg_sys::Oid,
+    ) -> Option<AnyRecord> {
+        if is_null {
+            None
+        } else {
+            // the row's type and typmod are in its header, so its own type is what counts
+            Some(AnyRecord { tuple: PgHeapTuple::from_composite_datum(datum) })
+        }
+    }
+}
+
+impl IntoDatum for AnyRecord {
+    #[inline]
+    fn into_datum(self) -> Option<pg_sys::Datum> {
+        self.tuple.into_composite_datum()
+    }
+
+    fn type_oid() -> pg_sys::Oid {
+        pg_sys::RECORDOID
+    }
+
+    fn composite_type_oid(&self) -> Option<pg_sys::Oid> {
+        Some(self.oid())
+    }
+
+    fn is_compatible_with(other: pg_sys::Oid) -> bool {
+        PgHeapTuple::<'static, AllocatedByRust>::is_compatible_with(other)
+    }
+}
+
+unsafe impl SqlTranslatable for AnyRecord {
+    fn argument_sql() -> Result<SqlMapping, ArgumentError> {
+        Ok(SqlMapping::literal("record"))
+    }
+    fn return_sql() -> Result<Returns, ReturnsError> {
+        Ok(Returns::One(SqlMapping::literal("record")))
+    }
+}
@@ -12,7 +12,7 @@ use crate::layout::*;
 use crate::slice::PallocSlice;
 use crate::toast::Toast;
 use crate::varlena;
-use crate::{pg_sys, FromDatum, IntoDatum, PgMemoryContexts};
+use crate::{ereport, pg_sys, FromDatum, IntoDatum, PgMemoryContexts, PgSqlErrorCode};
 use bitvec::slice::BitSlice;
 use core::ffi::CStr;
 use core::ops::DerefMut;
@@ -23,7 +23,10 @@ use pgrx_sql_entity_graph::metadata::{
     ArgumentError, Returns, ReturnsError, SqlMapping, SqlTranslatable,
 };
 use serde::Serializer;
+use std::cell::RefCell;
+use std::cmp::Ordering;
 use std::marker::PhantomData;
+use std::ops::{Bound, RangeBounds};
 
 /** An array of some type (eg. `TEXT[]`, `int[]`)
 
@@ -63,6 +66,8 @@ pub struct Array<'a, T: FromDatum> {
     null_slice: NullKind<'a>,
     elem_layout: Layout,
     _datum_slice: OnceCell<PallocSlice<pg_sys::Datum>>,
+    // Where each element starts, for elements that aren't fixed-size, as far as `get()` has looked
+    offsets: RefCell<Vec<*const u8>>,
     // Rust drops in FIFO order, drop this last
     raw: Toast<RawArray>,
     _marker: PhantomData<T>,
@@ -128,16 +133,14 @@ impl<'a, T: FromDatum> Array<'a, T> {
             panic!("oh no, the debug code exploded!")
         };
 
-        // The array-walking code assumes this is always the case, is it?
-        if let Layout { size: Size::Fixed(n), align, .. } = elem_layout {
-            let n: usize = n.into();
-            assert!(
-                n % (align.as_usize()) == 0,
-                "typlen does NOT include padding for fixed-width layouts!"
-            );
+        Array {
+            raw,
+            _datum_slice,
+            offsets: RefCell::new(Vec::new()),
+            null_slice,
+            elem_layout,
+            _marker: PhantomData,
         }
-
-        Array { raw, _datum_slice, null_slice, elem_layout, _marker: PhantomData }
     }
 
     /// Rips out the underlying `pg_sys::ArrayType` pointer.
@@ -174,6 +177,102 @@ impl<'a, T: FromDatum> Array<'a, T> {
         ArrayTypedIterator { array: self, curr: 0, ptr }
     }
 
+    /// Return an iterator of `Option<&str>` or `Option<&[u8]>`, borrowing each element in place
+    /// instead of copying it the way [`Array::iter()`] does for `Array<String>` and
+    /// `Array<Vec<u8>>`.
+    ///
+    /// The elements stay valid for as long as the array is borrowed.
+    ///
+    /// ```rust,no_run
+    /// use pgrx::prelude::*;
+    ///
+    /// #[pg_extern]
+    /// fn longest(words: Array<String>) -> i32 {
+    ///     words.iter_borrowed().flatten().map(|word| word.len() as i32).max().unwrap_or(0)
+    /// }
+    /// ```
+    pub fn iter_borrowed(&self) -> ArrayBorrowedIterator<'_, T>
+    where
+        T: BorrowDatum,
+    {
+        assert!(
+            matches!(self.elem_layout.size, Size::Varlena),
+            "only arrays of varlena types can be borrowed from"
+        );
+        let ptr = self.raw.data_ptr();
+        ArrayBorrowedIterator { array: self, curr: 0, ptr }
+    }
+
+    /// A view of the elements in `range`, which doesn't copy them.
+    ///
+    /// ```rust,no_run
+    /// use pgrx::prelude::*;
+    ///
+    /// #[pg_extern]
+    /// fn moving_sums(values: Array<i64>, window: i32) -> Vec<i64> {
+    ///     let window = window as usize;
+    ///     (0..=values.len().saturating_sub(window))
+    ///         .map(|start| values.slice(start..start + window).iter().flatten().sum())
+    ///         .collect()
+    /// }
+    /// ```
+    ///
+    /// # Panics
+    ///
+    /// Panics if `range` isn't within the array, like slicing a Rust slice does
+    pub fn slice<R: RangeBounds<usize>>(&self, range: R) -> ArraySlice<'_, T> {
+        ArraySlice::new(self, 0, self.len()).slice(range)
+    }
+
+    /// Copy the elements, in order, into owned chunks of at most `chunk_size` elements.
+    ///
+    /// Postgres can't be called from any thread but the backend's own, and an `Array` points
+    /// into Postgres-allocated memory, so it can't be shared with other threads.  Its chunks can:
+    /// they're plain `Vec`s of owned values, which can be handed to a thread pool such as
+    /// [rayon](https://docs.rs/rayon)'s (`chunks.into_par_iter().map(...)`) for CPU-heavy work.
+    /// The results can then be put back together with [`merge_array_chunks()`] and returned, as
+    /// long as no Postgres functions are called until then.
+    ///
+    /// ```rust,no_run
+    /// use pgrx::prelude::*;
+    /// use pgrx::merge_array_chunks;
+    ///
+    /// #[pg_extern]
+    /// fn expensive_transform(values: Array<i64>) -> Vec<Option<i64>> {
+    ///     let chunks = values.par_chunks(10_000);
+    ///     let results = std::thread::scope(|scope| {
+    ///         let handles = chunks
+    ///             .into_iter()
+    ///             .map(|chunk| {
+    ///                 scope.spawn(move || chunk.into_iter().map(|v| v.map(|v| v * v)).collect())
+    ///             })
+    ///             .collect::<Vec<_>>();
+    ///         handles.into_iter().map(|handle| handle.join().unwrap()).collect::<Vec<Vec<_>>>()
+    ///     });
+    ///     merge_array_chunks(results)
+    /// }
+    /// ```
+    ///
+    /// # Panics
+    ///
+    /// Panics if `chunk_size` is zero
+    pub fn par_chunks(&self, chunk_size: usize) -> Vec<Vec<Option<T>>>
+    where
+        T: Send + 'static,
+    {
+        assert!(chunk_size > 0, "chunk_size must be greater than zero");
+        let mut chunks = Vec::with_capacity((self.len() + chunk_size - 1) / chunk_size);
+        let mut iter = self.iter();
+        loop {
+            let chunk = iter.by_ref().take(chunk_size).collect::<Vec<_>>();
+            if chunk.is_empty() {
+                break;
+            }
+            chunks.push(chunk);
+        }
+        chunks
+    }
+
     #[inline]
     pub fn len(&self) -> usize {
         self.raw.len()
@@ -192,48 +291,76 @@ impl<'a, T: FromDatum> Array<'a, T> {
             return Some(None);
         }
 
-        // This pointer is what's walked over the entire array's data buffer.
-        // If the array has varlena or cstr elements, we can't index into the array.
-        // If the elements are fixed size, we could, but we do not exploit that optimization yet
-        // as it would significantly complicate the code and impact debugging it.
-        // Such improvements should wait until a later version (today's: 0.7.4, preparing 0.8.0).
-        let mut at_byte = self.raw.data_ptr();
-        for i in 0..index {
-            match self.null_slice.get(i) {
-                None => unreachable!("array was exceeded while walking to known non-null index???"),
-                // Skip nulls: the data buffer has no placeholders for them!
-                Some(true) => continue,
-                Some(false) => {
-                    #[cfg(debug_assertions)]
-                    if let PassBy::Ref = self.elem_layout.pass {
-                        assert_eq!(
-                            Some(pg_sys::Datum::from(at_byte)),
-                            self._datum_slice.get().and_then(|s| unsafe { s.get(i) }).copied()
-                        );
-                    }
-                    // SAFETY: Note this entire function has to be correct,
-                    // not just this one call, for this to be correct!
-                    at_byte = unsafe { self.one_hop_this_time(at_byte, self.elem_layout) };
-                }
-            }
+        let at_byte = unsafe { self.element_ptr(index) };
+
+        #[cfg(debug_assertions)]
+        if let PassBy::Ref = self.elem_layout.pass {
+            assert_eq!(
+                Some(pg_sys::Datum::from(at_byte)),
+                self._datum_slice.get().and_then(|s| unsafe { s.get(index) }).copied()
+            );
         }
 
-        // If this has gotten this far, it is known to be non-null,
-        // all the null values in the array up to this index were skipped,
-        // and the only offsets were via our hopping function.
         Some(unsafe { self.bring_it_back_now(at_byte, index, is_null) })
     }
 
+    /// Where the non-null element at `index` starts in the array's data buffer
+    ///
+    /// # Safety
+    /// `index` must be in bounds
+    unsafe fn element_ptr(&self, index: usize) -> *const u8 {
+        let data = self.raw.data_ptr();
+        if let Size::Fixed(n) = self.elem_layout.size {
+            // the data buffer has no placeholders for nulls, so skip the non-null elements before
+            // this one.  Note Postgres nullbitmaps are 1 for "valid" and 0 for "null"
+            let before = match &self.null_slice {
+                NullKind::Bits(bits) => bits[..index].count_ones(),
+                NullKind::Strict(_) => index,
+            };
+            return unsafe { data.add(before * fixed_stride(n, self.elem_layout.align)) };
+        }
+
+        // other elements have to be walked over, but each only once:  where each element starts
+        // is remembered for the next call
+        let mut offsets = self.offsets.borrow_mut();
+        if offsets.is_empty() {
+            offsets.push(data);
+        }
+        while offsets.len() <= index {
+            let prev = offsets.len() - 1;
+            let ptr = offsets[prev];
+            let next = match self.null_slice.get(prev) {
+                // Skip nulls: the data buffer has no placeholders for them!
+                Some(true) => ptr,
+                // SAFETY: `prev` is before `index`, so it's in bounds, and non-null
+                Some(false) => unsafe { self.one_hop_this_time(ptr, self.elem_layout) },
+                None => unreachable!("array was exceeded while walking to an in-bounds index???"),
+            };
+            offsets.push(next);
+        }
+        offsets[index]
+    }
+
     /// Extracts an element from a Postgres Array's data buffer
     ///
     /// # Safety
     /// This assumes the pointer is to a valid element of that type.
     #[inline]
-    unsafe fn bring_it_back_now(&self, ptr: *const u8, _index: usize, is_null: bool) -> Option<T> {
+    unsafe fn bring_it_back_now(&self, ptr: *const u8, index: usize, is_null: bool) -> Option<T> {
         if is_null {
             return None;
         }
 
+        let datum = unsafe { self.element_datum(ptr, index) };
+        unsafe { T::from_polymorphic_datum(datum, false, self.raw.oid()) }
+    }
+
+    /// The Datum of a non-null element in a Postgres Array's data buffer
+    ///
+    /// # Safety
+    /// This assumes the pointer is to a valid element of that type.
+    #[inline]
+    unsafe fn element_datum(&self, ptr: *const u8, _index: usize) -> Datum {
         match self.elem_layout.pass {
             PassBy::Value => match self.elem_layout.size {
                 //
@@ -279,8 +406,7 @@ impl<'a, T: FromDatum> Array<'a, T> {
                         Datum::from(usize::from_ne_bytes(buf))
                     }
 
-                    let datum = bytes_to_datum(ptr, size as usize);
-                    unsafe { T::from_polymorphic_datum(datum, false, self.raw.oid()) }
+                    bytes_to_datum(ptr, size as usize)
                 }
 
                 other => {
@@ -294,7 +420,7 @@ impl<'a, T: FromDatum> Array<'a, T> {
                     Some(datum),
                     self._datum_slice.get().and_then(|s| unsafe { s.get(_index) }).copied()
                 );
-                unsafe { T::from_polymorphic_datum(datum, false, self.raw.oid()) }
+                datum
             }
         }
     }
@@ -314,36 +440,335 @@ impl<'a, T: FromDatum> Array<'a, T> {
     #[inline]
     unsafe fn one_hop_this_time(&self, ptr: *const u8, layout: Layout) -> *const u8 {
         unsafe {
-            let offset = match layout {
-                Layout { size: Size::Fixed(n), .. } => n.into(),
+            let end = self.raw.end_ptr();
+            let next = match layout {
+                Layout { size: Size::Fixed(n), align, .. } => ptr.add(fixed_stride(n, align)),
                 Layout { size: Size::Varlena, align, .. } => {
                     // SAFETY: This uses the varsize_any function to be safe,
                     // and the caller was informed of pointer requirements.
-                    let varsize = varlena::varsize_any(ptr.cast());
-
-                    // the Postgres realignment code may seem different in form,
-                    // but it's the same in function, just micro-optimized
-                    let align = align.as_usize();
-                    let align_mask = varsize & (align - 1);
-                    let align_offset = if align_mask != 0 { align - align_mask } else { 0 };
-
-                    varsize + align_offset
+                    let next = ptr.add(varlena::varsize_any(ptr.cast()));
+
+                    // like Postgres' att_align_pointer(), an element with a 1-byte header is packed
+                    // right after the last, as it's not aligned, and a pad byte can't begin one
+                    if next < end && varlena::varatt_not_pad_byte(next.cast()) {
+                        next
+                    } else {
+                        align_pointer(next, align.as_usize())
+                    }
                 }
-                Layout { size: Size::CStr, .. } => {
+                Layout { size: Size::CStr, align, .. } => {
                     // TODO: this code is dangerously under-exercised in the test suite
                     // SAFETY: The caller was informed of pointer requirements.
                     let strlen = CStr::from_ptr(ptr.cast()).to_bytes().len();
 
                     // Skip over the null and into the next cstr!
-                    strlen + 2
+                    align_pointer(ptr.add(strlen + 1), align.as_usize())
                 }
             };
 
             // SAFETY: ptr stops at 1-past-end of the array's varlena
-            debug_assert!(ptr.wrapping_add(offset) <= self.raw.end_ptr());
-            ptr.add(offset)
+            debug_assert!(next <= end);
+            next
+        }
+    }
+}
+
+/// Rounds `ptr` up to a multiple of `align`, as Postgres' `att_align_nominal()` does
+#[inline]
+/// How far apart fixed-size elements are:  like Postgres' att_align_nominal(), each one starts
+/// aligned, so a 6-byte `macaddr` with int alignment takes up 8 bytes
+fn fixed_stride(size: u16, align: Align) -> usize {
+    let align = align.as_usize();
+    (usize::from(size) + align - 1) & !(align - 1)
+}
+
+fn align_pointer(ptr: *const u8, align: usize) -> *const u8 {
+    let misalignment = ptr as usize & (align - 1);
+    if misalignment != 0 {
+        ptr.wrapping_add(align - misalignment)
+    } else {
+        ptr
+    }
+}
+
+/// Searching, sorting, and deduplicating, with the element type's own equality and comparison
+/// functions:  those of its default btree operator class, and the collation it has by default.
+/// These are what Postgres' own array functions and operators use, so the results agree with them.
+impl<'a, T: FromDatum> Array<'a, T> {
+    /// Does this array contain every element of `other`, like Postgres' `@>` operator?
+    ///
+    /// As with `@>`, a `NULL` in `other` is never contained, and duplicates don't matter.
+    ///
+    /// # Errors
+    ///
+    /// Raises a Postgres `ERROR` if the element type has no equality operator
+    pub fn contains(&self, other: &Array<'_, T>) -> bool {
+        let ops = ElementOps::lookup(self.raw.oid(), pg_sys::TYPECACHE_EQ_OPR_FINFO);
+        let mine = self.datums();
+        other.datums().into_iter().all(|theirs| match theirs {
+            Some(theirs) => mine.iter().flatten().any(|&mine| ops.eq(mine, theirs)),
+            None => false,
+        })
+    }
+
+    /// Do this array and `other` have any element in common, like Postgres' `&&` operator?
+    ///
+    /// `NULL`s are never in common.
+    ///
+    /// # Errors
+    ///
+    /// Raises a Postgres `ERROR` if the element type has no equality operator
+    pub fn overlaps(&self, other: &Array<'_, T>) -> bool {
+        let ops = ElementOps::lookup(self.raw.oid(), pg_sys::TYPECACHE_EQ_OPR_FINFO);
+        let mine = self.datums();
+        other
+            .datums()
+            .into_iter()
+            .flatten()
+            .any(|theirs| mine.iter().flatten().any(|&mine| ops.eq(mine, theirs)))
+    }
+
+    /// The index of the first element equal to `value`, like Postgres' `array_position()`.  Unlike
+    /// `array_position()`, indexes start at zero.  A `None` value finds the first `NULL`.
+    ///
+    /// # Errors
+    ///
+    /// Raises a Postgres `ERROR` if the element type has no equality operator
+    pub fn position(&self, value: Option<T>) -> Option<usize>
+    where
+        T: IntoDatum,
+    {
+        self.positions(value).into_iter().next()
+    }
+
+    /// The indexes of every element equal to `value`, like Postgres' `array_positions()`.  Unlike
+    /// `array_positions()`, indexes start at zero.  A `None` value finds the `NULL`s.
+    ///
+    /// # Errors
+    ///
+    /// Raises a Postgres `ERROR` if the element type has no equality operator
+    pub fn positions(&self, value: Option<T>) -> Vec<usize>
+    where
+        T: IntoDatum,
+    {
+        let datums = self.datums();
+        let Some(value) = value.and_then(IntoDatum::into_datum) else {
+            return datums
+                .iter()
+                .enumerate()
+                .filter(|(_, d)| d.is_none())
+                .map(|(i, _)| i)
+                .collect();
+        };
+        let ops = ElementOps::lookup(self.raw.oid(), pg_sys::TYPECACHE_EQ_OPR_FINFO);
+        datums
+            .iter()
+            .enumerate()
+            .filter(|(_, datum)| matches!(datum, Some(datum) if ops.eq(*datum, value)))
+            .map(|(i, _)| i)
+            .collect()
+    }
+
+    /// The elements in ascending order, with `NULL`s last, like `ORDER BY` sorts them
+    ///
+    /// # Errors
+    ///
+    /// Raises a Postgres `ERROR` if the element type has no btree comparison function
+    pub fn sorted(&self) -> Vec<Option<T>> {
+        let ops = ElementOps::lookup(self.raw.oid(), pg_sys::TYPECACHE_CMP_PROC_FINFO);
+        let mut datums = self.datums();
+        datums.sort_by(|a, b| ops.cmp_nulls_last(*a, *b));
+        self.elements_of(datums)
+    }
+
+    /// The elements without any duplicates, each where it first appears.  `NULL`s are duplicates
+    /// of each other, like `DISTINCT` treats them.
+    ///
+    /// # Errors
+    ///
+    /// Raises a Postgres `ERROR` if the element type has no btree comparison function
+    pub fn dedup(&self) -> Vec<Option<T>> {
+        let ops = ElementOps::lookup(self.raw.oid(), pg_sys::TYPECACHE_CMP_PROC_FINFO);
+        let datums = self.datums();
+
+        // a stable sort of the indexes puts each element's first appearance first among its equals
+        let mut order = (0..datums.len()).collect::<Vec<_>>();
+        order.sort_by(|&a, &b| ops.cmp_nulls_last(datums[a], datums[b]));
+        let mut keep = vec![false; datums.len()];
+        for (i, &index) in order.iter().enumerate() {
+            keep[index] = i == 0
+                || ops.cmp_nulls_last(datums[order[i - 1]], datums[index]) != Ordering::Equal;
+        }
+
+        let datums = datums.into_iter().zip(keep).filter(|(_, keep)| *keep).map(|(d, _)| d);
+        self.elements_of(datums)
+    }
+
+    /// The Datum of every element, in order, with `None` for `NULL`s.  They point into this array.
+    fn datums(&self) -> Vec<Option<Datum>> {
+        let mut datums = Vec::with_capacity(self.len());
+        let mut ptr = self.raw.data_ptr();
+        for index in 0..self.len() {
+            if let Some(false) = self.null_slice.get(index) {
+                // SAFETY:  `ptr` has been walked to this non-null element
+                unsafe {
+                    datums.push(Some(self.element_datum(ptr, index)));
+                    ptr = self.one_hop_this_time(ptr, self.elem_layout);
+                }
+            } else {
+                // Skip nulls: the data buffer has no placeholders for them!
+                datums.push(None);
+            }
+        }
+        datums
+    }
+
+    fn elements_of(&self, datums: impl IntoIterator<Item = Option<Datum>>) -> Vec<Option<T>> {
+        datums
+            .into_iter()
+            .map(|datum| {
+                // SAFETY:  the datums were all elements of this array
+                datum.and_then(|datum| unsafe {
+                    T::from_polymorphic_datum(datum, false, self.raw.oid())
+                })
+            })
+            .collect()
+    }
+}
+
+/// Making a new array from this one's elements.  Each builds the new array in a single allocation,
+/// straight from this array's data, instead of collecting the elements one at a time the way
+/// [`ArrayBuilder`] does, and returns it as a one-dimensional array's Datum.
+impl<'a, T: FromDatum + IntoDatum> Array<'a, T> {
+    /// A new array of this array's elements followed by `value`, like Postgres' `array_append()`
+    pub fn push_returning_datum<V: Into<Option<T>>>(&self, value: V) -> pg_sys::Datum {
+        let mut datums = self.datums();
+        datums.push(value.into().and_then(IntoDatum::into_datum));
+        self.construct(&datums)
+    }
+
+    /// A new array of this array's elements followed by `other`'s, like Postgres' `array_cat()`
+    pub fn concat(&self, other: &Array<'_, T>) -> pg_sys::Datum {
+        let mut datums = self.datums();
+        datums.extend(other.datums());
+        self.construct(&datums)
+    }
+
+    /// A new array of this array's elements, with the one at `index` replaced by `value`.  Like
+    /// assigning past the end of an array in SQL, an `index` past the end adds `NULL`s up to it.
+    pub fn set_element<V: Into<Option<T>>>(&self, index: usize, value: V) -> pg_sys::Datum {
+        let mut datums = self.datums();
+        if index >= datums.len() {
+            datums.resize(index + 1, None);
+        }
+        datums[index] = value.into().and_then(IntoDatum::into_datum);
+        self.construct(&datums)
+    }
+
+    fn construct(&self, datums: &[Option<Datum>]) -> pg_sys::Datum {
+        let mut elems =
+            datums.iter().map(|datum| datum.unwrap_or(Datum::from(0))).collect::<Vec<_>>();
+        let mut nulls = datums.iter().map(Option::is_none).collect::<Vec<_>>();
+        let mut dims = [datums.len() as libc::c_int];
+        let mut lbs = [1];
+        let Layout { size, align, pass } = self.elem_layout;
+        unsafe {
+            // SAFETY:  the elements are all of this array's type, and the non-null ones are either
+            // in this array, still borrowed, or were just made by `IntoDatum`
+            let array = pg_sys::construct_md_array(
+                elems.as_mut_ptr(),
+                if nulls.contains(&true) { nulls.as_mut_ptr() } else { std::ptr::null_mut() },
+                if datums.is_empty() { 0 } else { 1 },
+                dims.as_mut_ptr(),
+                lbs.as_mut_ptr(),
+                self.raw.oid(),
+                size.as_typlen().into(),
+                matches!(pass, PassBy::Value),
+                align.as_typalign(),
+            );
+            pg_sys::Datum::from(array)
+        }
+    }
+}
+
+/// The support functions for comparing values of a type, from the type cache
+struct ElementOps {
+    entry: *mut pg_sys::TypeCacheEntry,
+    collation: pg_sys::Oid,
+}
+
+impl ElementOps {
+    fn lookup(typoid: pg_sys::Oid, flags: u32) -> Self {
+        let (entry, collation) = unsafe {
+            (pg_sys::lookup_type_cache(typoid, flags as _), pg_sys::get_typcollation(typoid))
+        };
+        let (has_eq, has_cmp) = unsafe {
+            (
+                (*entry).eq_opr_finfo.fn_oid != pg_sys::InvalidOid,
+                (*entry).cmp_proc_finfo.fn_oid != pg_sys::InvalidOid,
+            )
+        };
+        if flags & pg_sys::TYPECACHE_EQ_OPR_FINFO != 0 && !has_eq {
+            ereport!(
+                ERROR,
+                PgSqlErrorCode::ERRCODE_UNDEFINED_FUNCTION,
+                format!(
+                    "could not identify an equality operator for type {}",
+                    super::lookup_type_name(typoid)
+                )
+            );
+        }
+        if flags & pg_sys::TYPECACHE_CMP_PROC_FINFO != 0 && !has_cmp {
+            ereport!(
+                ERROR,
+                PgSqlErrorCode::ERRCODE_UNDEFINED_FUNCTION,
+                format!(
+                    "could not identify a comparison function for type {}",
+                    super::lookup_type_name(typoid)
+                )
+            );
         }
+        ElementOps { entry, collation }
+    }
+
+    fn eq(&self, a: Datum, b: Datum) -> bool {
+        unsafe {
+            // SAFETY:  the type cache entry is never freed, and its FmgrInfo is set up
+            pg_sys::FunctionCall2Coll(&mut (*self.entry).eq_opr_finfo, self.collation, a, b).value()
+                != 0
+        }
+    }
+
+    fn cmp_nulls_last(&self, a: Option<Datum>, b: Option<Datum>) -> Ordering {
+        match (a, b) {
+            (Some(a), Some(b)) => {
+                let cmp = unsafe {
+                    // SAFETY:  the type cache entry is never freed, and its FmgrInfo is set up
+                    pg_sys::FunctionCall2Coll(
+                        &mut (*self.entry).cmp_proc_finfo,
+                        self.collation,
+                        a,
+                        b,
+                    )
+                };
+                (cmp.value() as i32).cmp(&0)
+            }
+            (Some(_), None) => Ordering::Less,
+            (None, Some(_)) => Ordering::Greater,
+            (None, None) => Ordering::Equal,
+        }
+    }
+}
+
+/// Concatenate the per-chunk results of processing [`Array::par_chunks()`], in chunk order, into
+/// one `Vec` that can be returned as an array
+pub fn merge_array_chunks<R>(chunks: impl IntoIterator<Item = Vec<R>>) -> Vec<R> {
+    let chunks = chunks.into_iter().collect::<Vec<_>>();
+    let mut merged = Vec::with_capacity(chunks.iter().map(Vec::len).sum());
+    for chunk in chunks {
+        merged.extend(chunk);
     }
+    merged
 }
 
 pub struct VariadicArray<'a, T: FromDatum>(Array<'a, T>);
@@ -392,6 +817,129 @@ impl<'a, T: FromDatum> VariadicArray<'a, T> {
     }
 }
 
+/// A view of a contiguous range of an [`Array`]'s elements, made by [`Array::slice()`]
+pub struct ArraySlice<'a, T: FromDatum> {
+    array: &'a Array<'a, T>,
+    start: usize,
+    end: usize,
+}
+
+impl<'a, T: FromDatum> ArraySlice<'a, T> {
+    fn new(array: &'a Array<'a, T>, start: usize, end: usize) -> Self {
+        ArraySlice { array, start, end }
+    }
+
+    /// The index in the whole array of this slice's first element
+    #[inline]
+    pub fn start(&self) -> usize {
+        self.start
+    }
+
+    #[inline]
+    pub fn len(&self) -> usize {
+        self.end - self.start
+    }
+
+    #[inline]
+    pub fn is_empty(&self) -> bool {
+        self.start == self.end
+    }
+
+    /// The element at `index`, counting from the start of this slice
+    #[allow(clippy::option_option)]
+    #[inline]
+    pub fn get(&self, index: usize) -> Option<Option<T>> {
+        if index >= self.len() {
+            return None;
+        }
+        self.array.get(self.start + index)
+    }
+
+    /// A view of the elements in `range`, counting from the start of this slice
+    ///
+    /// # Panics
+    ///
+    /// Panics if `range` isn't within this slice
+    pub fn slice<R: RangeBounds<usize>>(&self, range: R) -> ArraySlice<'a, T> {
+        let start = match range.start_bound() {
+            Bound::Included(&start) => start,
+            Bound::Excluded(&start) => start.checked_add(1).expect("slice start overflowed"),
+            Bound::Unbounded => 0,
+        };
+        let end = match range.end_bound() {
+            Bound::Included(&end) => end.checked_add(1).expect("slice end overflowed"),
+            Bound::Excluded(&end) => end,
+            Bound::Unbounded => self.len(),
+        };
+        assert!(start <= end, "slice index starts at {start} but ends at {end}");
+        assert!(
+            end <= self.len(),
+            "range end index {end} out of range for slice of length {}",
+            self.len()
+        );
+        ArraySlice::new(self.array, self.start + start, self.start + end)
+    }
+
+    /// Return an iterator of `Option<T>` over the slice's elements
+    pub fn iter(&self) -> ArraySliceIterator<'a, T> {
+        let ptr = if self.is_empty() {
+            std::ptr::null()
+        } else {
+            // SAFETY:  `start` is in bounds, since the slice isn't empty
+            unsafe { self.array.element_ptr(self.start) }
+        };
+        ArraySliceIterator { array: self.array, curr: self.start, end: self.end, ptr }
+    }
+
+    /// Copy the slice's elements into a `Vec`
+    pub fn to_vec(&self) -> Vec<Option<T>> {
+        self.iter().collect()
+    }
+}
+
+impl<'a, T: FromDatum> IntoIterator for ArraySlice<'a, T> {
+    type Item = Option<T>;
+    type IntoIter = ArraySliceIterator<'a, T>;
+
+    fn into_iter(self) -> Self::IntoIter {
+        self.iter()
+    }
+}
+
+pub struct ArraySliceIterator<'a, T: 'a + FromDatum> {
+    array: &'a Array<'a, T>,
+    curr: usize,
+    end: usize,
+    ptr: *const u8,
+}
+
+impl<'a, T: FromDatum> Iterator for ArraySliceIterator<'a, T> {
+    type Item = Option<T>;
+
+    #[inline]
+    fn next(&mut self) -> Option<Self::Item> {
+        let Self { array, curr, end, ptr } = self;
+        if *curr >= *end {
+            return None;
+        }
+        let Some(is_null) = array.null_slice.get(*curr) else { return None };
+        let element = unsafe { array.bring_it_back_now(*ptr, *curr, is_null) };
+        *curr += 1;
+        // Skip nulls: the data buffer has no placeholders for them!
+        if !is_null && *curr < *end {
+            *ptr = unsafe { array.one_hop_this_time(*ptr, array.elem_layout) };
+        }
+        Some(element)
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        let remaining = self.end - self.curr;
+        (remaining, Some(remaining))
+    }
+}
+
+impl<'a, T: FromDatum> ExactSizeIterator for ArraySliceIterator<'a, T> {}
+
 pub struct ArrayTypedIterator<'a, T: 'a + FromDatum> {
     array: &'a Array<'a, T>,
     curr: usize,
@@ -426,6 +974,79 @@ impl<'a, T: FromDatum + serde::Serialize> serde::Serialize for ArrayTypedIterato
     }
 }
 
+/// Types whose array elements can be borrowed in place, by [`Array::iter_borrowed()`]
+///
+/// # Safety
+///
+/// Implementors must be varlena types, and `borrow_varlena()` must only read within the varlena
+pub unsafe trait BorrowDatum: FromDatum {
+    /// What the element is borrowed as
+    type Borrowed: ?Sized;
+
+    /// Borrow the value of an uncompressed, inline varlena, which may have a short header
+    ///
+    /// # Safety
+    ///
+    /// `varlena` must point to a valid varlena of this type, which outlives `'a`
+    unsafe fn borrow_varlena<'a>(varlena: *const pg_sys::varlena) -> &'a Self::Borrowed;
+}
+
+unsafe impl BorrowDatum for String {
+    type Borrowed = str;
+
+    unsafe fn borrow_varlena<'a>(varlena: *const pg_sys::varlena) -> &'a str {
+        super::from::convert_varlena_to_str_memoized(varlena)
+    }
+}
+
+unsafe impl<'s> BorrowDatum for &'s str {
+    type Borrowed = str;
+
+    unsafe fn borrow_varlena<'a>(varlena: *const pg_sys::varlena) -> &'a str {
+        super::from::convert_varlena_to_str_memoized(varlena)
+    }
+}
+
+unsafe impl BorrowDatum for Vec<u8> {
+    type Borrowed = [u8];
+
+    unsafe fn borrow_varlena<'a>(varlena: *const pg_sys::varlena) -> &'a [u8] {
+        varlena::varlena_to_byte_slice(varlena)
+    }
+}
+
+unsafe impl<'s> BorrowDatum for &'s [u8] {
+    type Borrowed = [u8];
+
+    unsafe fn borrow_varlena<'a>(varlena: *const pg_sys::varlena) -> &'a [u8] {
+        varlena::varlena_to_byte_slice(varlena)
+    }
+}
+
+pub struct ArrayBorrowedIterator<'a, T: 'a + BorrowDatum> {
+    array: &'a Array<'a, T>,
+    curr: usize,
+    ptr: *const u8,
+}
+
+impl<'a, T: BorrowDatum> Iterator for ArrayBorrowedIterator<'a, T> {
+    type Item = Option<&'a T::Borrowed>;
+
+    #[inline]
+    fn next(&mut self) -> Option<Self::Item> {
+        let Self { array, curr, ptr } = self;
+        let Some(is_null) = array.null_slice.get(*curr) else { return None };
+        // SAFETY:  array elements are never compressed or toasted, and `ptr` is at the element
+        let element = (!is_null).then(|| unsafe { T::borrow_varlena(ptr.cast()) });
+        *curr += 1;
+        // the data buffer has no placeholders for nulls, so only a non-null element is hopped over
+        if !is_null {
+            *ptr = unsafe { array.one_hop_this_time(*ptr, array.elem_layout) };
+        }
+        Some(element)
+    }
+}
+
 pub struct ArrayIterator<'a, T: 'a + FromDatum> {
     array: &'a Array<'a, T>,
     curr: usize,
@@ -702,6 +1323,176 @@ where
     }
 }
 
+/// Builds a Postgres array one element at a time, with `accumArrayResult()`.
+///
+/// Unlike collecting into a [`Vec<T>`] and converting that with [`IntoDatum`], the elements are
+/// converted to datums and copied into the array's own storage as they're pushed, so they're
+/// only ever held once.
+///
+/// ```rust,no_run
+/// use pgrx::prelude::*;
+/// use pgrx::ArrayBuilder;
+///
+/// #[pg_extern]
+/// fn evens(limit: i32) -> ArrayBuilder<i32> {
+///     let mut builder = ArrayBuilder::with_capacity(limit as usize / 2);
+///     for i in (0..limit).step_by(2) {
+///         builder.push(i);
+///     }
+///     builder.push(None); // a SQL NULL
+///     builder
+/// }
+/// ```
+pub struct ArrayBuilder<T: IntoDatum> {
+    state: *mut pg_sys::ArrayBuildState,
+    _marker: PhantomData<T>,
+}
+
+impl<T: IntoDatum> ArrayBuilder<T> {
+    /// An empty builder, which builds its array in its own memory context, a child of the
+    /// `CurrentMemoryContext`
+    pub fn new() -> Self {
+        let state = unsafe {
+            pg_sys::initArrayResult(
+                T::type_oid(),
+                PgMemoryContexts::CurrentMemoryContext.value(),
+                true,
+            )
+        };
+        ArrayBuilder { state, _marker: PhantomData }
+    }
+
+    /// An empty builder with room for at least `capacity` elements
+    pub fn with_capacity(capacity: usize) -> Self {
+        let mut builder = Self::new();
+        builder.reserve(capacity);
+        builder
+    }
+
+    /// Make room for at least `additional` more elements
+    pub fn reserve(&mut self, additional: usize) {
+        unsafe {
+            // SAFETY:  `self.state` is always a valid ArrayBuildState from initArrayResult()
+            let state = &mut *self.state;
+            let needed = state.nelems as usize + additional;
+            if needed > state.alen as usize {
+                let alen = i32::try_from(needed).expect("too many array elements");
+                // repalloc() keeps the allocations in the builder's memory context
+                state.dvalues = pg_sys::repalloc(
+                    state.dvalues.cast(),
+                    needed * std::mem::size_of::<pg_sys::Datum>(),
+                )
+                .cast();
+                state.dnulls =
+                    pg_sys::repalloc(state.dnulls.cast(), needed * std::mem::size_of::<bool>())
+                        .cast();
+                state.alen = alen;
+            }
+        }
+    }
+
+    /// Append an element, which is a SQL NULL if it's `None`
+    pub fn push<V: Into<Option<T>>>(&mut self, value: V) {
+        let datum = value.into().and_then(IntoDatum::into_datum);
+        unsafe {
+            // SAFETY:  `self.state` is always a valid ArrayBuildState.  accumArrayResult() copies
+            // the datum into the builder's memory context
+            self.state = pg_sys::accumArrayResult(
+                self.state,
+                datum.unwrap_or(0.into()),
+                datum.is_none(),
+                T::type_oid(),
+                PgMemoryContexts::CurrentMemoryContext.value(),
+            );
+        }
+    }
+
+    /// The number of elements pushed so far
+    pub fn len(&self) -> usize {
+        unsafe { (*self.state).nelems as usize }
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    /// Finish the array, allocated in the `CurrentMemoryContext`, and free the builder's memory
+    pub fn finish(self) -> pg_sys::Datum {
+        let state = std::mem::ManuallyDrop::new(self).state;
+        unsafe {
+            // SAFETY:  makeArrayResult() frees a state that has its own memory context
+            pg_sys::makeArrayResult(state, PgMemoryContexts::CurrentMemoryContext.value())
+        }
+    }
+
+    /// Finish the array, as an [`Array`]
+    pub fn into_array(self) -> Array<'static, T>
+    where
+        T: FromDatum,
+    {
+        unsafe {
+            // SAFETY:  we just made the array, and it isn't null
+            Array::from_datum(self.finish(), false).unwrap()
+        }
+    }
+}
+
+impl<T: IntoDatum> Default for ArrayBuilder<T> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<T: IntoDatum> Drop for ArrayBuilder<T> {
+    fn drop(&mut self) {
+        unsafe {
+            // SAFETY:  the builder owns its memory context, which holds the state itself
+            pg_sys::MemoryContextDelete((*self.state).mcontext);
+        }
+    }
+}
+
+impl<T: IntoDatum> Extend<Option<T>> for ArrayBuilder<T> {
+    fn extend<I: IntoIterator<Item = Option<T>>>(&mut self, iter: I) {
+        let iter = iter.into_iter();
+        self.reserve(iter.size_hint().0);
+        for value in iter {
+            self.push(value);
+        }
+    }
+}
+
+impl<T: IntoDatum> FromIterator<Option<T>> for ArrayBuilder<T> {
+    fn from_iter<I: IntoIterator<Item = Option<T>>>(iter: I) -> Self {
+        let mut builder = Self::new();
+        builder.extend(iter);
+        builder
+    }
+}
+
+impl<T: IntoDatum> IntoDatum for ArrayBuilder<T> {
+    fn into_datum(self) -> Option<pg_sys::Datum> {
+        Some(self.finish())
+    }
+
+    fn type_oid() -> pg_sys::Oid {
+        T::array_type_oid()
+    }
+}
+
+unsafe impl<T> SqlTranslatable for ArrayBuilder<T>
+where
+    T: SqlTranslatable + IntoDatum,
+{
+    fn argument_sql() -> Result<SqlMapping, ArgumentError> {
+        Vec::<T>::argument_sql()
+    }
+
+    fn return_sql() -> Result<Returns, ReturnsError> {
+        Vec::<T>::return_sql()
+    }
+}
+
 unsafe impl<'a, T> SqlTranslatable for Array<'a, T>
 where
     T: SqlTranslatable + FromDatum,
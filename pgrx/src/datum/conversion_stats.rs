@@ -0,0 +1,162 @@
+/*
+Portions Copyright 2019-2021 ZomboDB, LLC.
+Portions Copyright 2021-2022 Technology Concepts & Design, Inc. <support@tcdi.com>
+
+All rights reserved.
+
+Use of this source code is governed by the MIT license that can be found in the LICENSE file.
+*/
+
+//! Counts and times of Datum conversions, per Rust type, to find unexpected per-row conversion
+//! hot spots
+//!
+//! With the `conversion-stats` feature, pgrx counts and times every conversion it makes on an
+//! extension's behalf:  `#[pg_extern]` arguments and return values, the rows of set-returning
+//! functions, and values read with [`Spi`](crate::Spi) and [`PgHeapTuple`](crate::PgHeapTuple).
+//! Conversions an extension makes itself, by calling [`FromDatum`](crate::FromDatum) or
+//! [`IntoDatum`](crate::IntoDatum) directly, aren't counted.
+//!
+//! The stats are kept per backend.  [`conversion_stats_functions!`](crate::conversion_stats_functions)
+//! adds SQL functions to read and reset them:
+//!
+//! ```rust,no_run
+//! pgrx::conversion_stats_functions!();
+//! ```
+//!
+//! ```sql
+//! SELECT * FROM datum_conversion_stats() ORDER BY total_time_us DESC;
+//! ```
+//!
+//! Timing every conversion has a cost of its own, so the feature is meant for debug builds.
+use std::cell::RefCell;
+use std::collections::HashMap;
+use std::time::{Duration, Instant};
+
+/// Which way a Datum conversion went
+#[derive(Debug, Copy, Clone, Eq, PartialEq, Hash, Ord, PartialOrd)]
+pub enum ConversionDirection {
+    /// From a Datum to a Rust value, with [`FromDatum`](crate::FromDatum)
+    FromDatum,
+    /// From a Rust value to a Datum, with [`IntoDatum`](crate::IntoDatum)
+    IntoDatum,
+}
+
+impl ConversionDirection {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            ConversionDirection::FromDatum => "from_datum",
+            ConversionDirection::IntoDatum => "into_datum",
+        }
+    }
+}
+
+/// The conversions of one Rust type, in one direction, this backend has made
+#[derive(Debug, Clone, Eq, PartialEq)]
+pub struct ConversionStats {
+    /// The Rust type, as [`std::any::type_name()`] names it
+    pub type_name: &'static str,
+    pub direction: ConversionDirection,
+    pub count: u64,
+    pub total_time: Duration,
+}
+
+impl ConversionStats {
+    /// The average time a conversion took
+    pub fn mean_time(&self) -> Duration {
+        if self.count == 0 {
+            Duration::ZERO
+        } else {
+            Duration::from_nanos((self.total_time.as_nanos() / self.count as u128) as u64)
+        }
+    }
+}
+
+thread_local! {
+    static STATS: RefCell<HashMap<(&'static str, ConversionDirection), (u64, Duration)>> =
+        RefCell::new(HashMap::new());
+}
+
+/// Time `convert`, and count it as a conversion of `T` in `direction`
+pub(crate) fn record<T: ?Sized, R>(
+    direction: ConversionDirection,
+    convert: impl FnOnce() -> R,
+) -> R {
+    let start = Instant::now();
+    let result = convert();
+    let elapsed = start.elapsed();
+    // not borrowed while converting, as a conversion can make others
+    STATS.with(|stats| {
+        let mut stats = stats.borrow_mut();
+        let (count, total_time) = stats.entry((std::any::type_name::<T>(), direction)).or_default();
+        *count += 1;
+        *total_time += elapsed;
+    });
+    result
+}
+
+/// The conversions this backend has made, the most total time first
+pub fn conversion_stats() -> Vec<ConversionStats> {
+    let mut stats = STATS.with(|stats| {
+        stats
+            .borrow()
+            .iter()
+            .map(|(&(type_name, direction), &(count, total_time))| ConversionStats {
+                type_name,
+                direction,
+                count,
+                total_time,
+            })
+            .collect::<Vec<_>>()
+    });
+    stats.sort_by(|a, b| {
+        b.total_time
+            .cmp(&a.total_time)
+            .then(a.type_name.cmp(b.type_name))
+            .then(a.direction.cmp(&b.direction))
+    });
+    stats
+}
+
+/// Forget the conversions this backend has made so far
+pub fn reset_conversion_stats() {
+    STATS.with(|stats| stats.borrow_mut().clear())
+}
+
+/// Add a `datum_conversion_stats()` function to the extension's schema, listing the Datum
+/// conversions this backend has made, and a `reset_datum_conversion_stats()` function to forget
+/// them.  Requires the `conversion-stats` feature
+#[macro_export]
+macro_rules! conversion_stats_functions {
+    () => {
+        #[::pgrx::pg_extern(volatile)]
+        fn datum_conversion_stats() -> ::pgrx::iter::TableIterator<
+            'static,
+            (
+                ::pgrx::name!(type_name, String),
+                ::pgrx::name!(direction, String),
+                ::pgrx::name!(count, i64),
+                ::pgrx::name!(total_time_us, i64),
+                ::pgrx::name!(mean_time_ns, i64),
+            ),
+        > {
+            let stats = $crate::datum::conversion_stats::conversion_stats()
+                .into_iter()
+                .map(|stats| {
+                    (
+                        stats.type_name.to_string(),
+                        stats.direction.as_str().to_string(),
+                        stats.count as i64,
+                        stats.total_time.as_micros() as i64,
+                        stats.mean_time().as_nanos() as i64,
+                    )
+                })
+                .collect::<Vec<_>>();
+            $crate::iter::TableIterator::new(stats.into_iter())
+        }
+
+        #[::pgrx::pg_extern(volatile)]
+        fn reset_datum_conversion_stats() {
+            $crate::datum::conversion_stats::reset_conversion_stats()
+        }
+    };
+}
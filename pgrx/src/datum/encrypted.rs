@@ -0,0 +1,388 @@
+/*
+Portions Copyright 2019-2021 ZomboDB, LLC.
+Portions Copyright 2021-2022 Technology Concepts & Design, Inc. <support@tcdi.com>
+
+All rights reserved.
+
+Use of this source code is governed by the MIT license that can be found in the LICENSE file.
+*/
+
+//! Values encrypted at rest, for column encryption
+//!
+//! An [`Encrypted<T>`] is a `T` that is encrypted when it's converted to a `bytea` Datum, and
+//! decrypted when it's converted back.  pgrx doesn't implement any cryptography itself:  the
+//! extension sets the [`Cipher`] to encrypt with, such as an AEAD from the `aes-gcm` or
+//! `chacha20poly1305` crates, and the [`KeyProvider`] handing out keys, with [`set_encryption()`].
+//!
+//! Each value is encrypted for a [`KeyScope`], usually the table and column it's stored in, so a
+//! key provider can use a different key per column.  The scope, the id of the key, and a random
+//! nonce are stored with the value, and authenticated as the cipher's associated data, so values
+//! stay readable after their scope's key is rotated, and their scope can't be altered.  A value
+//! copied whole to another column still decrypts, though, with the scope it was encrypted for:
+//! decrypt it with [`Encrypted::into_inner_in()`] to require the scope it should have.
+//!
+//! ```rust,no_run
+//! use pgrx::encrypted::{Encrypted, KeyScope};
+//! use pgrx::prelude::*;
+//!
+//! #[pg_extern]
+//! fn encrypt_card(number: String) -> Encrypted<String> {
+//!     Encrypted::in_scope(number, KeyScope::column("payments", "card_number"))
+//! }
+//!
+//! #[pg_extern]
+//! fn decrypt_card(card: Encrypted<String>) -> String {
+//!     card.into_inner_in(&KeyScope::column("payments", "card_number"))
+//! }
+//! ```
+//!
+//! An optional audit hook, set with [`set_audit_hook()`], is told of every value encrypted or
+//! decrypted, and of every failure to.
+use crate::{pg_sys, FromDatum, IntoDatum, PgLogLevel, PgSqlErrorCode};
+use pgrx_sql_entity_graph::metadata::{
+    ArgumentError, Returns, ReturnsError, SqlMapping, SqlTranslatable,
+};
+use serde::de::DeserializeOwned;
+use serde::Serialize;
+use std::cell::RefCell;
+use std::fmt::{Display, Formatter};
+use std::ops::Deref;
+
+// the version of the format values are stored in
+const FORMAT_VERSION: u8 = 1;
+
+/// Where an encrypted value is stored, which decides the key it's encrypted with
+#[derive(Debug, Clone, Default, Eq, PartialEq, Hash)]
+pub struct KeyScope {
+    pub table: Option<String>,
+    pub column: Option<String>,
+}
+
+impl KeyScope {
+    /// The scope of values stored in `column` of `table`
+    pub fn column(table: &str, column: &str) -> Self {
+        KeyScope { table: Some(table.to_string()), column: Some(column.to_string()) }
+    }
+
+    /// The scope of values stored anywhere in `table`
+    pub fn table(table: &str) -> Self {
+        KeyScope { table: Some(table.to_string()), column: None }
+    }
+}
+
+impl Display for KeyScope {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match (&self.table, &self.column) {
+            (Some(table), Some(column)) => write!(f, "{}.{}", table, column),
+            (Some(table), None) => write!(f, "{}", table),
+            (None, Some(column)) => write!(f, "{}", column),
+            (None, None) => write!(f, "the default scope"),
+        }
+    }
+}
+
+/// Hands out the keys values are encrypted with
+pub trait KeyProvider {
+    /// The id of the key to encrypt new values in `scope` with.  It's stored with each value, to
+    /// find its key again when it's decrypted
+    fn current_key_id(&self, scope: &KeyScope) -> String;
+
+    /// The key `key_id` of `scope`, or `None` if there's no such key
+    fn key(&self, scope: &KeyScope, key_id: &str) -> Option<Vec<u8>>;
+}
+
+/// An authenticated cipher, such as AES-GCM
+pub trait Cipher {
+    /// The length of the nonces the cipher takes, in bytes
+    fn nonce_len(&self) -> usize;
+
+    /// Encrypt `plaintext` with `key` and `nonce`, authenticating it and `aad`
+    fn encrypt(&self, key: &[u8], nonce: &[u8], aad: &[u8], plaintext: &[u8]) -> Vec<u8>;
+
+    /// Decrypt `ciphertext` with `key` and `nonce`, or `None` if it, or `aad`, isn't what was
+    /// encrypted
+    fn decrypt(&self, key: &[u8], nonce: &[u8], aad: &[u8], ciphertext: &[u8]) -> Option<Vec<u8>>;
+}
+
+/// Whether a value was being encrypted or decrypted
+#[derive(Debug, Copy, Clone, Eq, PartialEq)]
+pub enum EncryptionOp {
+    Encrypt,
+    Decrypt,
+}
+
+/// What the audit hook is told of each value encrypted or decrypted
+#[derive(Debug)]
+pub struct AuditEvent<'a> {
+    pub op: EncryptionOp,
+    pub scope: &'a KeyScope,
+    pub key_id: &'a str,
+    /// Whether the value was encrypted or decrypted.  Errors are raised after the hook returns
+    pub succeeded: bool,
+}
+
+struct Encryption {
+    cipher: Option<Box<dyn Cipher>>,
+    keys: Option<Box<dyn KeyProvider>>,
+    audit_hook: Option<fn(&AuditEvent)>,
+}
+
+// a backend is forked from the postmaster's one thread, so it inherits what `_PG_init()` set
+thread_local! {
+    static ENCRYPTION: RefCell<Encryption> =
+        const { RefCell::new(Encryption { cipher: None, keys: None, audit_hook: None }) };
+}
+
+fn with_encryption<R>(f: impl FnOnce(&Encryption) -> R) -> R {
+    ENCRYPTION.with(|encryption| f(&encryption.borrow()))
+}
+
+/// Encrypt and decrypt [`Encrypted`] values with `cipher`, using the keys of `keys`.  This should
+/// be called from `_PG_init()`
+pub fn set_encryption(cipher: impl Cipher + 'static, keys: impl KeyProvider + 'static) {
+    ENCRYPTION.with(|encryption| {
+        let mut encryption = encryption.borrow_mut();
+        encryption.cipher = Some(Box::new(cipher));
+        encryption.keys = Some(Box::new(keys));
+    });
+}
+
+/// Call `hook` for every value encrypted or decrypted, or `None` to stop
+pub fn set_audit_hook(hook: Option<fn(&AuditEvent)>) {
+    ENCRYPTION.with(|encryption| encryption.borrow_mut().audit_hook = hook);
+}
+
+/// A `T` stored encrypted, as a `bytea`
+///
+/// `T` is serialized with `serde`, like a [`PostgresType`](macro@crate::PostgresType)
+#[derive(Debug, Clone, Eq, PartialEq)]
+pub struct Encrypted<T> {
+    value: T,
+    scope: KeyScope,
+}
+
+impl<T> Encrypted<T> {
+    /// `value`, to be encrypted with the keys of the default, empty, scope
+    pub fn new(value: T) -> Self {
+        Encrypted { value, scope: KeyScope::default() }
+    }
+
+    /// `value`, to be encrypted with the keys of `scope`
+    pub fn in_scope(value: T, scope: KeyScope) -> Self {
+        Encrypted { value, scope }
+    }
+
+    /// The scope the value is encrypted for
+    pub fn scope(&self) -> &KeyScope {
+        &self.scope
+    }
+
+    /// The value, whatever scope it was encrypted for.  See [`Encrypted::into_inner_in()`]
+    pub fn into_inner(self) -> T {
+        self.value
+    }
+
+    /// The value, which must have been encrypted for `scope`.  Raises an error if it was
+    /// encrypted for another scope, as when it was copied from another column
+    pub fn into_inner_in(self, scope: &KeyScope) -> T {
+        if &self.scope != scope {
+            crate::ereport!(
+                PgLogLevel::ERROR,
+                PgSqlErrorCode::ERRCODE_DATA_CORRUPTED,
+                format!("encrypted value belongs to {}, not {}", self.scope, scope)
+            );
+        }
+        self.value
+    }
+}
+
+impl<T> Deref for Encrypted<T> {
+    type Target = T;
+
+    fn deref(&self) -> &T {
+        &self.value
+    }
+}
+
+impl<T: Serialize> IntoDatum for Encrypted<T> {
+    fn into_datum(self) -> Option<pg_sys::Datum> {
+        let mut plaintext =
+            serde_cbor::to_vec(&self.value).expect("failed to serialize an Encrypted value");
+        let stored = with_encryption(|encryption| {
+            let (cipher, keys) = configured(encryption);
+
+            let key_id = keys.current_key_id(&self.scope);
+            let mut key = match keys.key(&self.scope, &key_id) {
+                Some(key) => key,
+                None => {
+                    audit(encryption, EncryptionOp::Encrypt, &self.scope, &key_id, false);
+                    unknown_key(&self.scope, &key_id)
+                }
+            };
+            let mut nonce = vec![0u8; cipher.nonce_len()];
+            if !unsafe { pg_sys::pg_strong_random(nonce.as_mut_ptr().cast(), nonce.len()) } {
+                crate::ereport!(
+                    PgLogLevel::ERROR,
+                    PgSqlErrorCode::ERRCODE_INTERNAL_ERROR,
+                    "could not generate a random nonce"
+                );
+            }
+
+            let mut stored = header(&self.scope, &key_id, &nonce);
+            let ciphertext = cipher.encrypt(&key, &nonce, &stored, &plaintext);
+            wipe(&mut key);
+            wipe(&mut plaintext);
+            audit(encryption, EncryptionOp::Encrypt, &self.scope, &key_id, true);
+
+            stored.extend_from_slice(&ciphertext);
+            stored
+        });
+        stored.as_slice().into_datum()
+    }
+
+    fn type_oid() -> pg_sys::Oid {
+        pg_sys::BYTEAOID
+    }
+}
+
+impl<T: DeserializeOwned> FromDatum for Encrypted<T> {
+    unsafe fn from_polymorphic_datum(
+        datum: pg_sys::Datum,
+        is_null: bool,
+        typoid: pg_sys::Oid,
+    ) -> Option<Self> {
+        let stored = <&[u8]>::from_polymorphic_datum(datum, is_null, typoid)?;
+        let (scope, key_id, nonce, header_len) = parse_header(stored).unwrap_or_else(|| {
+            crate::ereport!(
+                PgLogLevel::ERROR,
+                PgSqlErrorCode::ERRCODE_DATA_CORRUPTED,
+                "invalid encrypted value"
+            );
+            unreachable!()
+        });
+        let plaintext = with_encryption(|encryption| {
+            let (cipher, keys) = configured(encryption);
+
+            let mut key = match keys.key(&scope, &key_id) {
+                Some(key) => key,
+                None => {
+                    audit(encryption, EncryptionOp::Decrypt, &scope, &key_id, false);
+                    unknown_key(&scope, &key_id)
+                }
+            };
+            let (aad, ciphertext) = stored.split_at(header_len);
+            let plaintext = cipher.decrypt(&key, nonce, aad, ciphertext);
+            wipe(&mut key);
+            audit(encryption, EncryptionOp::Decrypt, &scope, &key_id, plaintext.is_some());
+            plaintext
+        });
+
+        let mut plaintext = plaintext.unwrap_or_else(|| {
+            crate::ereport!(
+                PgLogLevel::ERROR,
+                PgSqlErrorCode::ERRCODE_DATA_CORRUPTED,
+                "could not decrypt an encrypted value",
+                format!("It was encrypted with key \"{}\".", key_id)
+            );
+            unreachable!()
+        });
+        let value = serde_cbor::from_slice(&plaintext);
+        wipe(&mut plaintext);
+        let value = value.expect("failed to deserialize a decrypted Encrypted value");
+        Some(Encrypted { value, scope })
+    }
+}
+
+unsafe impl<T> SqlTranslatable for Encrypted<T> {
+    fn argument_sql() -> Result<SqlMapping, ArgumentError> {
+        Ok(SqlMapping::literal("bytea"))
+    }
+    fn return_sql() -> Result<Returns, ReturnsError> {
+        Ok(Returns::One(SqlMapping::literal("bytea")))
+    }
+}
+
+fn configured(encryption: &Encryption) -> (&dyn Cipher, &dyn KeyProvider) {
+    match (&encryption.cipher, &encryption.keys) {
+        (Some(cipher), Some(keys)) => (cipher.as_ref(), keys.as_ref()),
+        _ => {
+            crate::ereport!(
+                PgLogLevel::ERROR,
+                PgSqlErrorCode::ERRCODE_OBJECT_NOT_IN_PREREQUISITE_STATE,
+                "no encryption is set for encrypted values"
+            );
+            unreachable!()
+        }
+    }
+}
+
+fn audit(encryption: &Encryption, op: EncryptionOp, scope: &KeyScope, key_id: &str, ok: bool) {
+    if let Some(hook) = encryption.audit_hook {
+        hook(&AuditEvent { op, scope, key_id, succeeded: ok })
+    }
+}
+
+fn unknown_key(scope: &KeyScope, key_id: &str) -> ! {
+    crate::ereport!(
+        PgLogLevel::ERROR,
+        PgSqlErrorCode::ERRCODE_UNDEFINED_OBJECT,
+        format!("encryption key \"{}\" does not exist for {}", key_id, scope)
+    );
+    unreachable!()
+}
+
+// the version, then the table, column, key id, and nonce, each prefixed by its length, with
+// `u16::MAX` for a `None` table or column
+fn header(scope: &KeyScope, key_id: &str, nonce: &[u8]) -> Vec<u8> {
+    let mut header = vec![FORMAT_VERSION];
+    let mut field = |bytes: Option<&[u8]>| match bytes {
+        Some(bytes) => {
+            let len = u16::try_from(bytes.len()).expect("encryption header field is too long");
+            header.extend_from_slice(&len.to_le_bytes());
+            header.extend_from_slice(bytes);
+        }
+        None => header.extend_from_slice(&u16::MAX.to_le_bytes()),
+    };
+    field(scope.table.as_deref().map(str::as_bytes));
+    field(scope.column.as_deref().map(str::as_bytes));
+    field(Some(key_id.as_bytes()));
+    field(Some(nonce));
+    header
+}
+
+fn parse_header(stored: &[u8]) -> Option<(KeyScope, String, &[u8], usize)> {
+    let (&version, mut rest) = stored.split_first()?;
+    if version != FORMAT_VERSION {
+        return None;
+    }
+    let table = take_string(&mut rest)?;
+    let column = take_string(&mut rest)?;
+    let key_id = take_string(&mut rest)??;
+    let nonce = take_field(&mut rest)??;
+    Some((KeyScope { table, column }, key_id, nonce, stored.len() - rest.len()))
+}
+
+fn take_field<'a>(rest: &mut &'a [u8]) -> Option<Option<&'a [u8]>> {
+    let len = u16::from_le_bytes(rest.get(..2)?.try_into().ok()?);
+    if len == u16::MAX {
+        *rest = &rest[2..];
+        return Some(None);
+    }
+    let bytes = rest.get(2..2 + len as usize)?;
+    *rest = &rest[2 + len as usize..];
+    Some(Some(bytes))
+}
+
+fn take_string(rest: &mut &[u8]) -> Option<Option<String>> {
+    match take_field(rest)? {
+        Some(bytes) => Some(Some(String::from_utf8(bytes.to_vec()).ok()?)),
+        None => Some(None),
+    }
+}
+
+// overwrite key material and plaintexts once they're no longer needed
+fn wipe(bytes: &mut [u8]) {
+    for byte in bytes.iter_mut() {
+        unsafe { std::ptr::write_volatile(byte, 0) };
+    }
+}
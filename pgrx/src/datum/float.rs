@@ -0,0 +1,133 @@
+/*
+Portions Copyright 2019-2021 ZomboDB, LLC.
+Portions Copyright 2021-2022 Technology Concepts & Design, Inc. <support@tcdi.com>
+
+All rights reserved.
+
+Use of this source code is governed by the MIT license that can be found in the LICENSE file.
+*/
+
+//! Formatting and parsing `real` and `double precision` values exactly as Postgres does
+//!
+//! Rust's own formatting of floats doesn't match Postgres':  Postgres writes `1e+20` where Rust
+//! writes `100000000000000000000`, spells infinity `Infinity`, and, depending on the version and
+//! the `extra_float_digits` setting, may not write the shortest representation that round-trips.
+//! A type with an embedded float that formats it with Rust won't print the way the same float
+//! does as a column of its own.
+//!
+//! These functions use Postgres' own `float4out()`/`float8out()` and `float4in()`/`float8in()`,
+//! which don't depend on the locale, so a custom type's input and output functions can match
+//! core's exactly.
+use crate::{direct_function_call, direct_function_call_as_datum, pg_sys, IntoDatum};
+use core::ffi::CStr;
+use pgrx_pg_sys::errcodes::PgSqlErrorCode;
+use pgrx_pg_sys::panic::CaughtError;
+use pgrx_pg_sys::{AsPgCStr, PgTryBuilder};
+use std::fmt::{self, Display, Formatter};
+
+/// Why a string couldn't be parsed as a float
+#[derive(Debug, Clone, Eq, PartialEq)]
+pub enum FloatParseError {
+    /// It isn't a number
+    Invalid(String),
+    /// It's a number too large or too small, other than zero, for the type
+    OutOfRange(String),
+}
+
+impl Display for FloatParseError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        match self {
+            FloatParseError::Invalid(s) | FloatParseError::OutOfRange(s) => write!(f, "{s}"),
+        }
+    }
+}
+
+impl std::error::Error for FloatParseError {}
+
+/// Format `value` as a `double precision`, as it's output under the current `extra_float_digits`
+pub fn format_float8(value: f64) -> String {
+    unsafe {
+        // SAFETY:  float8out_internal() returns a palloc'd string
+        let cstr = pg_sys::float8out_internal(value);
+        let formatted = CStr::from_ptr(cstr).to_string_lossy().into_owned();
+        pg_sys::pfree(cstr.cast());
+        formatted
+    }
+}
+
+/// Format `value` as a `real`, as it's output under the current `extra_float_digits`
+pub fn format_float4(value: f32) -> String {
+    unsafe {
+        // SAFETY:  float4out() returns a palloc'd string
+        let cstr = direct_function_call_as_datum(pg_sys::float4out, &[value.into_datum()])
+            .expect("float4out returned NULL")
+            .cast_mut_ptr::<std::os::raw::c_char>();
+        let formatted = CStr::from_ptr(cstr).to_string_lossy().into_owned();
+        pg_sys::pfree(cstr.cast());
+        formatted
+    }
+}
+
+/// Format `value` as a `double precision`, as it's output with `extra_float_digits` set to
+/// `extra_float_digits`.  Since Postgres 12, any positive value gives the shortest
+/// representation that round-trips
+pub fn format_float8_with_extra_digits(value: f64, extra_float_digits: i32) -> String {
+    with_extra_float_digits(extra_float_digits, || format_float8(value))
+}
+
+/// Format `value` as a `real`, as it's output with `extra_float_digits` set to
+/// `extra_float_digits`.  Since Postgres 12, any positive value gives the shortest
+/// representation that round-trips
+pub fn format_float4_with_extra_digits(value: f32, extra_float_digits: i32) -> String {
+    with_extra_float_digits(extra_float_digits, || format_float4(value))
+}
+
+fn with_extra_float_digits<R>(extra_float_digits: i32, f: impl FnOnce() -> R) -> R {
+    unsafe {
+        // SAFETY:  `extra_float_digits` is the GUC's own variable, which Postgres only assigns
+        // while processing a `SET`.  `f` only formats one float, which never does that, and
+        // never raises an error, so the setting is always put back
+        let saved = pg_sys::extra_float_digits;
+        pg_sys::extra_float_digits = extra_float_digits.clamp(-15, 3);
+        let result = f();
+        pg_sys::extra_float_digits = saved;
+        result
+    }
+}
+
+/// Parse `s` as a `double precision`, as `float8in()` does.  Leading and trailing whitespace is
+/// allowed, as are `NaN`, `Infinity`, `-Infinity`, `inf`, and `-inf`, in any case
+pub fn parse_float8(s: &str) -> Result<f64, FloatParseError> {
+    parse(|| unsafe { direct_function_call::<f64>(pg_sys::float8in, &[cstring(s)]) })
+}
+
+/// Parse `s` as a `real`, as `float4in()` does.  Leading and trailing whitespace is allowed, as
+/// are `NaN`, `Infinity`, `-Infinity`, `inf`, and `-inf`, in any case
+pub fn parse_float4(s: &str) -> Result<f32, FloatParseError> {
+    parse(|| unsafe { direct_function_call::<f32>(pg_sys::float4in, &[cstring(s)]) })
+}
+
+fn cstring(s: &str) -> Option<pg_sys::Datum> {
+    Some(pg_sys::Datum::from(s.as_pg_cstr()))
+}
+
+fn parse<F>(
+    parse: impl FnOnce() -> Option<F> + std::panic::UnwindSafe,
+) -> Result<F, FloatParseError> {
+    PgTryBuilder::new(|| Ok(parse().expect("float input function returned NULL")))
+        .catch_when(PgSqlErrorCode::ERRCODE_INVALID_TEXT_REPRESENTATION, |e| {
+            if let CaughtError::PostgresError(ref ereport) = e {
+                Err(FloatParseError::Invalid(ereport.message().to_string()))
+            } else {
+                e.rethrow()
+            }
+        })
+        .catch_when(PgSqlErrorCode::ERRCODE_NUMERIC_VALUE_OUT_OF_RANGE, |e| {
+            if let CaughtError::PostgresError(ref ereport) = e {
+                Err(FloatParseError::OutOfRange(ereport.message().to_string()))
+            } else {
+                e.rethrow()
+            }
+        })
+        .execute()
+}
@@ -11,8 +11,13 @@ Use of this source code is governed by the MIT license that can be found in the
 //! and converting Rust types into their corresponding Postgres types
 mod anyarray;
 mod anyelement;
+mod anyrecord;
 mod array;
+#[cfg(feature = "conversion-stats")]
+pub mod conversion_stats;
 mod date;
+pub mod encrypted;
+pub mod float;
 mod from;
 mod geo;
 mod inet;
@@ -21,24 +26,32 @@ mod interval;
 mod into;
 mod item_pointer_data;
 mod json;
+#[cfg(any(feature = "pg14", feature = "pg15"))]
+mod multirange;
 pub mod numeric;
 pub mod numeric_support;
+mod owned;
 #[deny(unsafe_op_in_unsafe_fn)]
 mod range;
+mod text_search;
 mod time;
 mod time_stamp;
 mod time_stamp_with_timezone;
 mod time_with_timezone;
 mod tuples;
 mod uuid;
+mod value;
 mod varlena;
+mod versioned;
 
 pub use self::time::*;
 pub use self::uuid::*;
 pub use anyarray::*;
 pub use anyelement::*;
+pub use anyrecord::*;
 pub use array::*;
 pub use date::*;
+pub use encrypted::Encrypted;
 pub use from::*;
 pub use geo::*;
 pub use inet::*;
@@ -47,22 +60,76 @@ pub use interval::*;
 pub use into::*;
 pub use item_pointer_data::*;
 pub use json::*;
+#[cfg(any(feature = "pg14", feature = "pg15"))]
+pub use multirange::MultiRange;
 pub use numeric::{AnyNumeric, Numeric};
 use once_cell::sync::Lazy;
+pub use owned::OwnedDatum;
 pub use range::*;
 use std::any::TypeId;
+pub use text_search::*;
 pub use time_stamp::*;
 pub use time_stamp_with_timezone::*;
 pub use time_with_timezone::*;
 pub use tuples::*;
+pub use value::*;
 pub use varlena::*;
+pub use versioned::{Migrations, VersionedType};
 
 use crate::PgBox;
 use pgrx_sql_entity_graph::RustSqlMapping;
 
+/// Make the conversion to a `T` that `convert` makes, counting it with the `conversion-stats`
+/// feature
+#[inline(always)]
+pub(crate) fn counted_from_datum<T: ?Sized, R>(convert: impl FnOnce() -> R) -> R {
+    #[cfg(feature = "conversion-stats")]
+    {
+        conversion_stats::record::<T, R>(conversion_stats::ConversionDirection::FromDatum, convert)
+    }
+    #[cfg(not(feature = "conversion-stats"))]
+    {
+        convert()
+    }
+}
+
+/// [`IntoDatum::into_datum()`], counting the conversion with the `conversion-stats` feature
+#[doc(hidden)]
+#[inline(always)]
+pub fn counted_into_datum<T: IntoDatum>(value: T) -> Option<crate::pg_sys::Datum> {
+    #[cfg(feature = "conversion-stats")]
+    {
+        conversion_stats::record::<T, _>(conversion_stats::ConversionDirection::IntoDatum, || {
+            value.into_datum()
+        })
+    }
+    #[cfg(not(feature = "conversion-stats"))]
+    {
+        value.into_datum()
+    }
+}
+
 /// A tagging trait to indicate a user type is also meant to be used by Postgres
 /// Implemented automatically by `#[derive(PostgresType)]`
-pub trait PostgresType {}
+pub trait PostgresType {
+    /// The version values are stored with, for `#[versioned]` types
+    #[doc(hidden)]
+    fn storage_version() -> Option<u8>
+    where
+        Self: Sized,
+    {
+        None
+    }
+
+    /// How to decode values stored by older versions, for `#[versioned]` types
+    #[doc(hidden)]
+    fn storage_migrations() -> Migrations<Self>
+    where
+        Self: Sized,
+    {
+        Migrations::new()
+    }
+}
 
 /// A type which can have it's [`core::any::TypeId`]s registered for Rust to SQL mapping.
 ///
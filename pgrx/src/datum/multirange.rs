@@ -0,0 +1,286 @@
+/*
+Portions Copyright 2019-2021 ZomboDB, LLC.
+Portions Copyright 2021-2022 Technology Concepts & Design, Inc. <support@tcdi.com>
+
+All rights reserved.
+
+Use of this source code is governed by the MIT license that can be found in the LICENSE file.
+*/
+
+//! Utility functions for working with Postgres 14+ `pg_sys::MultirangeType` structs
+use crate::{
+    pg_sys, AnyNumeric, Date, FromDatum, IntoDatum, Numeric, Range, RangeSubType, Timestamp,
+    TimestampWithTimeZone,
+};
+use pgrx_sql_entity_graph::metadata::{
+    ArgumentError, Returns, ReturnsError, SqlMapping, SqlTranslatable,
+};
+
+/// A safe deconstruction of a Postgres `pg_sys::MultirangeType` struct, the ranges of a
+/// multirange such as `int4multirange`.
+///
+/// Postgres keeps a multirange's ranges sorted, and merges any that overlap or are adjacent, when
+/// a [`MultiRange`] is converted into a Datum.  A [`MultiRange`] made from a Datum has its ranges in
+/// that order, and never any empty ones.
+///
+/// ```rust,no_run
+/// use pgrx::{MultiRange, Range};
+/// let m: MultiRange<i32> = vec![Range::from(1..5), Range::from(10..20)].into();
+/// assert_eq!(m.len(), 2);
+/// ```
+#[derive(Debug, Clone, Hash, Eq, PartialEq)]
+pub struct MultiRange<T: RangeSubType> {
+    ranges: Vec<Range<T>>,
+}
+
+impl<T> MultiRange<T>
+where
+    T: RangeSubType,
+{
+    /// Builds a new [`MultiRange`] of `ranges`
+    #[inline]
+    pub fn new(ranges: Vec<Range<T>>) -> Self {
+        Self { ranges }
+    }
+
+    /// Builds an empty [`MultiRange`], one without any ranges
+    #[inline]
+    pub fn empty() -> Self {
+        Self { ranges: Vec::new() }
+    }
+
+    /// Returns the number of ranges
+    #[inline]
+    pub fn len(&self) -> usize {
+        self.ranges.len()
+    }
+
+    /// Returns `true` if there aren't any ranges
+    #[inline]
+    pub fn is_empty(&self) -> bool {
+        self.ranges.is_empty()
+    }
+
+    /// Returns an iterator over the ranges
+    #[inline]
+    pub fn iter(&self) -> std::slice::Iter<'_, Range<T>> {
+        self.ranges.iter()
+    }
+
+    /// Returns the ranges as a slice
+    #[inline]
+    pub fn as_slice(&self) -> &[Range<T>] {
+        &self.ranges
+    }
+
+    /// Consumes `self` and returns its ranges
+    #[inline]
+    pub fn into_inner(self) -> Vec<Range<T>> {
+        self.ranges
+    }
+}
+
+impl<T> From<Vec<Range<T>>> for MultiRange<T>
+where
+    T: RangeSubType,
+{
+    #[inline]
+    fn from(ranges: Vec<Range<T>>) -> Self {
+        Self::new(ranges)
+    }
+}
+
+impl<T> FromIterator<Range<T>> for MultiRange<T>
+where
+    T: RangeSubType,
+{
+    fn from_iter<I: IntoIterator<Item = Range<T>>>(iter: I) -> Self {
+        Self::new(iter.into_iter().collect())
+    }
+}
+
+impl<T> IntoIterator for MultiRange<T>
+where
+    T: RangeSubType,
+{
+    type Item = Range<T>;
+    type IntoIter = std::vec::IntoIter<Range<T>>;
+
+    #[inline]
+    fn into_iter(self) -> Self::IntoIter {
+        self.ranges.into_iter()
+    }
+}
+
+impl<'a, T> IntoIterator for &'a MultiRange<T>
+where
+    T: RangeSubType,
+{
+    type Item = &'a Range<T>;
+    type IntoIter = std::slice::Iter<'a, Range<T>>;
+
+    #[inline]
+    fn into_iter(self) -> Self::IntoIter {
+        self.ranges.iter()
+    }
+}
+
+impl<T> FromDatum for MultiRange<T>
+where
+    T: RangeSubType,
+{
+    /// ## Safety
+    /// function requires that
+    /// - is_null is true OR datum represents a PG MultirangeType datum
+    unsafe fn from_polymorphic_datum(
+        datum: pg_sys::Datum,
+        is_null: bool,
+        _: pg_sys::Oid,
+    ) -> Option<Self>
+    where
+        Self: Sized,
+    {
+        if is_null || datum.is_null() {
+            return None;
+        }
+
+        let ptr: *mut pg_sys::varlena = datum.cast_mut_ptr();
+        // Datum should be non-null and point to PG MultirangeType
+        let multirange = pg_sys::pg_detoast_datum(ptr) as *mut pg_sys::MultirangeType;
+
+        // SAFETY: multirange came from PG, so assume its multirangetypid is valid
+        let typecache = pg_sys::lookup_type_cache(
+            (*multirange).multirangetypid,
+            pg_sys::TYPECACHE_MULTIRANGE_INFO as i32,
+        );
+
+        // SAFETY: PG will deserialize into an array of palloc'd RangeTypes
+        let mut count = 0;
+        let mut ranges = std::ptr::null_mut();
+        pg_sys::multirange_deserialize((*typecache).rngtype, multirange, &mut count, &mut ranges);
+
+        let values = (0..count as usize)
+            .map(|i| {
+                let range = *ranges.add(i);
+                let value = Range::<T>::from_datum(pg_sys::Datum::from(range), false).unwrap();
+                pg_sys::pfree(range.cast());
+                value
+            })
+            .collect();
+        if !ranges.is_null() {
+            pg_sys::pfree(ranges.cast());
+        }
+
+        if !std::ptr::eq(ptr, multirange.cast()) {
+            // SAFETY: multirange was allocated by Postgres in the call to pg_detoast_datum above,
+            // so we know it's a valid pointer and needs to be freed
+            pg_sys::pfree(multirange.cast());
+        }
+
+        Some(MultiRange { ranges: values })
+    }
+}
+
+impl<T> IntoDatum for MultiRange<T>
+where
+    T: RangeSubType,
+{
+    fn into_datum(self) -> Option<pg_sys::Datum> {
+        unsafe {
+            // T must have a valid registered "Multirange" Type ex. int4 -> int4multirange
+            let typecache = pg_sys::lookup_type_cache(
+                T::multirange_type_oid(),
+                pg_sys::TYPECACHE_MULTIRANGE_INFO as i32,
+            );
+
+            let mut ranges = self
+                .ranges
+                .into_iter()
+                .map(|range| range.into_datum().unwrap().cast_mut_ptr::<pg_sys::RangeType>())
+                .collect::<Vec<_>>();
+
+            // PG will sort, merge, and serialize these ranges to a *MultirangeType ptr/datum
+            let multirange = pg_sys::make_multirange(
+                T::multirange_type_oid(),
+                (*typecache).rngtype,
+                ranges.len() as i32,
+                ranges.as_mut_ptr(),
+            );
+
+            for range in ranges {
+                pg_sys::pfree(range.cast());
+            }
+
+            // *MultirangeType into Datum
+            Some(pg_sys::Datum::from(multirange))
+        }
+    }
+
+    #[inline]
+    fn type_oid() -> pg_sys::Oid {
+        T::multirange_type_oid()
+    }
+}
+
+unsafe impl SqlTranslatable for MultiRange<i32> {
+    fn argument_sql() -> Result<SqlMapping, ArgumentError> {
+        Ok(SqlMapping::literal("int4multirange"))
+    }
+    fn return_sql() -> Result<Returns, ReturnsError> {
+        Ok(Returns::One(SqlMapping::literal("int4multirange")))
+    }
+}
+
+unsafe impl SqlTranslatable for MultiRange<i64> {
+    fn argument_sql() -> Result<SqlMapping, ArgumentError> {
+        Ok(SqlMapping::literal("int8multirange"))
+    }
+    fn return_sql() -> Result<Returns, ReturnsError> {
+        Ok(Returns::One(SqlMapping::literal("int8multirange")))
+    }
+}
+
+unsafe impl SqlTranslatable for MultiRange<AnyNumeric> {
+    fn argument_sql() -> Result<SqlMapping, ArgumentError> {
+        Ok(SqlMapping::literal("nummultirange"))
+    }
+    fn return_sql() -> Result<Returns, ReturnsError> {
+        Ok(Returns::One(SqlMapping::literal("nummultirange")))
+    }
+}
+
+unsafe impl<const P: u32, const S: u32> SqlTranslatable for MultiRange<Numeric<P, S>> {
+    fn argument_sql() -> Result<SqlMapping, ArgumentError> {
+        Ok(SqlMapping::literal("nummultirange"))
+    }
+    fn return_sql() -> Result<Returns, ReturnsError> {
+        Ok(Returns::One(SqlMapping::literal("nummultirange")))
+    }
+}
+
+unsafe impl SqlTranslatable for MultiRange<Date> {
+    fn argument_sql() -> Result<SqlMapping, ArgumentError> {
+        Ok(SqlMapping::literal("datemultirange"))
+    }
+    fn return_sql() -> Result<Returns, ReturnsError> {
+        Ok(Returns::One(SqlMapping::literal("datemultirange")))
+    }
+}
+
+unsafe impl SqlTranslatable for MultiRange<TimestampWithTimeZone> {
+    fn argument_sql() -> Result<SqlMapping, ArgumentError> {
+        Ok(SqlMapping::literal("tstzmultirange"))
+    }
+    fn return_sql() -> Result<Returns, ReturnsError> {
+        Ok(Returns::One(SqlMapping::literal("tstzmultirange")))
+    }
+}
+
+unsafe impl SqlTranslatable for MultiRange<Timestamp> {
+    fn argument_sql() -> Result<SqlMapping, ArgumentError> {
+        Ok(SqlMapping::literal("tsmultirange"))
+    }
+    fn return_sql() -> Result<Returns, ReturnsError> {
+        Ok(Returns::One(SqlMapping::literal("tsmultirange")))
+    }
+}
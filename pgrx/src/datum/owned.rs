@@ -0,0 +1,160 @@
+/*
+Portions Copyright 2019-2021 ZomboDB, LLC.
+Portions Copyright 2021-2022 Technology Concepts & Design, Inc. <support@tcdi.com>
+
+All rights reserved.
+
+Use of this source code is governed by the MIT license that can be found in the LICENSE file.
+*/
+
+//! A Datum that owns a copy of its value, for keeping values of any type in Rust collections
+use crate::datum::lookup_type_name;
+use crate::{memcxt_tracking, pg_sys, FromDatum, IntoDatum, PgMemoryContexts, TryFromDatumError};
+
+/// A non-NULL Datum of any type, that owns a detoasted copy of its value in a chosen
+/// `MemoryContext` and remembers its type oid.
+///
+/// Unlike a bare [`pg_sys::Datum`], an `OwnedDatum` stays valid after whatever it was copied from
+/// is freed, so values whose types are only known at runtime can be kept across calls, such as in
+/// a `Vec<OwnedDatum>` an aggregate or SRF accumulates.  It's converted back into a typed value
+/// with [`OwnedDatum::get`].
+///
+/// By-reference values are `pfree`'d when the `OwnedDatum` is dropped, so it must not outlive the
+/// `MemoryContext` it was copied into.
+///
+/// ```rust,no_run
+/// use pgrx::prelude::*;
+/// use pgrx::OwnedDatum;
+///
+/// let values = vec![OwnedDatum::new(42i32).unwrap(), OwnedDatum::new("Nami").unwrap()];
+/// assert_eq!(values[0].type_oid(), pg_sys::INT4OID);
+/// assert_eq!(values[1].get::<String>(), Ok("Nami".to_string()));
+/// ```
+pub struct OwnedDatum {
+    datum: pg_sys::Datum,
+    type_oid: pg_sys::Oid,
+    typlen: i16,
+    typbyval: bool,
+    memory_context: pg_sys::MemoryContext,
+}
+
+impl OwnedDatum {
+    /// Copies `value` into `CurrentMemoryContext`, or returns `None` if it's NULL
+    pub fn new<T: IntoDatum>(value: T) -> Option<Self> {
+        Self::new_in(&PgMemoryContexts::CurrentMemoryContext, value)
+    }
+
+    /// Copies `value` into `memory_context`, or returns `None` if it's NULL
+    pub fn new_in<T: IntoDatum>(memory_context: &PgMemoryContexts, value: T) -> Option<Self> {
+        let datum = value.into_datum()?;
+        // SAFETY:  `T`'s IntoDatum impl just made us a valid Datum of type `T::type_oid()`
+        unsafe { Some(Self::copy_from(memory_context, datum, T::type_oid())) }
+    }
+
+    /// Copies a non-NULL `datum` of the type `type_oid` into `memory_context`.  A toasted value is
+    /// decompressed or fetched, and an expanded one flattened.
+    ///
+    /// ## Safety
+    ///
+    /// `datum` must be a valid, non-NULL, Datum of the type `type_oid`
+    pub unsafe fn copy_from(
+        memory_context: &PgMemoryContexts,
+        datum: pg_sys::Datum,
+        type_oid: pg_sys::Oid,
+    ) -> Self {
+        let (mut typlen, mut typbyval) = (0, false);
+        pg_sys::get_typlenbyval(type_oid, &mut typlen, &mut typbyval);
+        let memory_context = memory_context.value();
+        let datum = copy_datum(memory_context, datum, typlen, typbyval);
+        Self { datum, type_oid, typlen, typbyval, memory_context }
+    }
+
+    /// The oid of the value's type
+    #[inline]
+    pub fn type_oid(&self) -> pg_sys::Oid {
+        self.type_oid
+    }
+
+    /// The Datum, which points into this `OwnedDatum`'s copy if the type is by-reference
+    #[inline]
+    pub fn as_datum(&self) -> pg_sys::Datum {
+        if !self.typbyval {
+            memcxt_tracking::assert_allocation_live(self.datum.cast_mut_ptr(), "OwnedDatum");
+        }
+        self.datum
+    }
+
+    /// Gives up ownership of the copy, to be freed with its `MemoryContext`, and returns the Datum
+    pub fn into_pg(self) -> pg_sys::Datum {
+        let datum = self.as_datum();
+        memcxt_tracking::forget_allocation(datum.cast_mut_ptr());
+        std::mem::forget(self);
+        datum
+    }
+
+    /// Converts the value back into the Rust type `T`.  Borrowed types, such as `&str`, borrow
+    /// from this `OwnedDatum`, and mustn't outlive it.
+    ///
+    /// ## Errors
+    /// - return [`TryFromDatumError::IncompatibleTypes`] if `T` isn't compatible with the value's type
+    pub fn get<T: FromDatum + IntoDatum>(&self) -> Result<T, TryFromDatumError> {
+        // SAFETY:  we own a valid Datum of the type `self.type_oid`
+        unsafe { T::try_from_datum(self.as_datum(), false, self.type_oid) }
+            .map(|value| value.expect("a non-NULL Datum was converted to NULL"))
+    }
+}
+
+/// Copies `datum` into `memory_context`, unless it's by-value
+unsafe fn copy_datum(
+    memory_context: pg_sys::MemoryContext,
+    datum: pg_sys::Datum,
+    typlen: i16,
+    typbyval: bool,
+) -> pg_sys::Datum {
+    if typbyval {
+        return datum;
+    }
+    let copy = PgMemoryContexts::For(memory_context).switch_to(|_| {
+        if typlen == -1 {
+            pg_sys::Datum::from(pg_sys::pg_detoast_datum_copy(datum.cast_mut_ptr()))
+        } else {
+            pg_sys::datumCopy(datum, false, typlen as _)
+        }
+    });
+    memcxt_tracking::track_allocation(copy.cast_mut_ptr(), memory_context);
+    copy
+}
+
+impl Clone for OwnedDatum {
+    /// Copies the value into the same `MemoryContext` as this one
+    fn clone(&self) -> Self {
+        // SAFETY:  we own a valid Datum of our type
+        let datum =
+            unsafe { copy_datum(self.memory_context, self.as_datum(), self.typlen, self.typbyval) };
+        Self { datum, ..*self }
+    }
+}
+
+impl Drop for OwnedDatum {
+    fn drop(&mut self) {
+        if self.typbyval {
+            return;
+        }
+        let ptr = self.datum.cast_mut_ptr::<std::ffi::c_void>();
+        if memcxt_tracking::is_allocation_live(ptr) {
+            // SAFETY:  we palloc'd our copy, and its MemoryContext hasn't been reset
+            unsafe { pg_sys::pfree(ptr) }
+        }
+        memcxt_tracking::forget_allocation(ptr);
+    }
+}
+
+impl std::fmt::Debug for OwnedDatum {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("OwnedDatum")
+            .field("type", &lookup_type_name(self.type_oid))
+            .field("type_oid", &self.type_oid)
+            .field("datum", &self.datum)
+            .finish()
+    }
+}
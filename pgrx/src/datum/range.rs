@@ -252,6 +252,58 @@ where
         }
     }
 
+    /// Does this range contain `value`, like Postgres' `@>` operator?
+    ///
+    /// This, [`Range::contains_range`], and [`Range::overlaps`] are answered by Postgres, with the
+    /// range type's own comparison and canonicalization, so that, for example, the `int4range`
+    /// `[1,3]` contains the same values as `[1,4)`.
+    pub fn contains(&self, value: &T) -> bool {
+        let range = self.to_range_type();
+        let value = value.clone().into_datum().expect("a range's element can't be NULL");
+        unsafe {
+            // SAFETY:  `range` is a valid range of `T::range_type_oid()`, and `value` a valid `T`
+            let contains = pg_sys::range_contains_elem_internal(Self::type_cache(), range, value);
+            pg_sys::pfree(range.cast());
+            contains
+        }
+    }
+
+    /// Does this range contain all of `other`, like Postgres' `@>` operator?
+    pub fn contains_range(&self, other: &Range<T>) -> bool {
+        let (range, other) = (self.to_range_type(), other.to_range_type());
+        unsafe {
+            // SAFETY:  both are valid ranges of `T::range_type_oid()`
+            let contains = pg_sys::range_contains_internal(Self::type_cache(), range, other);
+            pg_sys::pfree(range.cast());
+            pg_sys::pfree(other.cast());
+            contains
+        }
+    }
+
+    /// Do this range and `other` have any values in common, like Postgres' `&&` operator?
+    pub fn overlaps(&self, other: &Range<T>) -> bool {
+        let (range, other) = (self.to_range_type(), other.to_range_type());
+        unsafe {
+            // SAFETY:  both are valid ranges of `T::range_type_oid()`
+            let overlaps = pg_sys::range_overlaps_internal(Self::type_cache(), range, other);
+            pg_sys::pfree(range.cast());
+            pg_sys::pfree(other.cast());
+            overlaps
+        }
+    }
+
+    fn type_cache() -> *mut pg_sys::TypeCacheEntry {
+        unsafe {
+            // SAFETY:  T must have a valid registered "Range" Type
+            pg_sys::lookup_type_cache(T::range_type_oid(), pg_sys::TYPECACHE_RANGE_INFO as i32)
+        }
+    }
+
+    /// A palloc'd copy of this range as a Postgres `RangeType`
+    fn to_range_type(&self) -> *mut pg_sys::RangeType {
+        self.clone().into_datum().unwrap().cast_mut_ptr()
+    }
+
     /// Consumes `self` and returns the internal representation, which can be easily mapped or
     /// unwrapped.
     ///
@@ -465,6 +517,13 @@ where
 /// This trait allows a struct to be a valid subtype for a RangeType
 pub unsafe trait RangeSubType: Clone + FromDatum + IntoDatum {
     fn range_type_oid() -> pg_sys::Oid;
+
+    /// The oid of the multirange type over [`RangeSubType::range_type_oid`]
+    #[cfg(any(feature = "pg14", feature = "pg15"))]
+    fn multirange_type_oid() -> pg_sys::Oid {
+        // SAFETY:  the range type is valid, and Postgres 14+ makes a multirange type for each
+        unsafe { pg_sys::get_range_multirange(Self::range_type_oid()) }
+    }
 }
 
 /// for int/int4range
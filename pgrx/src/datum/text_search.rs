@@ -0,0 +1,358 @@
+/*
+Portions Copyright 2019-2021 ZomboDB, LLC.
+Portions Copyright 2021-2022 Technology Concepts & Design, Inc. <support@tcdi.com>
+
+All rights reserved.
+
+Use of this source code is governed by the MIT license that can be found in the LICENSE file.
+*/
+
+//! The full text search types, `tsvector` and `tsquery`
+use crate::{direct_function_call, direct_function_call_as_datum, pg_sys, FromDatum, IntoDatum};
+use core::ffi::CStr;
+use pgrx_sql_entity_graph::metadata::{
+    ArgumentError, Returns, ReturnsError, SqlMapping, SqlTranslatable,
+};
+use std::ffi::CString;
+use std::fmt::{self, Display, Formatter};
+use std::ops::Deref;
+
+/// The weight of a lexeme's position, from `A`, the highest, to `D`, the default
+#[derive(Debug, Copy, Clone, Eq, PartialEq, Ord, PartialOrd, Hash)]
+pub enum TsWeight {
+    D = 0,
+    C = 1,
+    B = 2,
+    A = 3,
+}
+
+impl TsWeight {
+    fn from_bits(bits: u16) -> Self {
+        match bits & 3 {
+            3 => TsWeight::A,
+            2 => TsWeight::B,
+            1 => TsWeight::C,
+            _ => TsWeight::D,
+        }
+    }
+}
+
+/// Where a lexeme appears in a document, counting words from `1`
+#[derive(Debug, Copy, Clone, Eq, PartialEq, Ord, PartialOrd, Hash)]
+pub struct TsPosition {
+    pub position: u16,
+    pub weight: TsWeight,
+}
+
+impl TsPosition {
+    /// A position with the default weight, `D`
+    pub fn new(position: u16) -> Self {
+        TsPosition { position, weight: TsWeight::D }
+    }
+}
+
+/// A lexeme of a [`TsVector`], and its positions, if it has any
+#[derive(Debug, Clone, Eq, PartialEq, Hash)]
+pub struct Lexeme {
+    pub word: String,
+    pub positions: Vec<TsPosition>,
+}
+
+impl Lexeme {
+    /// A lexeme without positions
+    pub fn new(word: impl Into<String>) -> Self {
+        Lexeme { word: word.into(), positions: Vec::new() }
+    }
+
+    /// A lexeme at `positions`
+    pub fn with_positions(word: impl Into<String>, positions: Vec<TsPosition>) -> Self {
+        Lexeme { word: word.into(), positions }
+    }
+}
+
+/// A `tsvector`, a document's lexemes
+///
+/// Postgres sorts a `tsvector`'s lexemes, and merges duplicates, when a [`TsVector`] is converted
+/// into a Datum.  A [`TsVector`] made from a Datum has its lexemes in that order.
+///
+/// ```rust,no_run
+/// use pgrx::{TsQuery, TsVector};
+/// let document = TsVector::to_tsvector("english", "The quick brown fox");
+/// assert!(document.lexemes().iter().any(|lexeme| lexeme.word == "quick"));
+/// assert!(document.matches(&TsQuery::to_tsquery("english", "quick & fox")));
+/// ```
+#[derive(Debug, Clone, Eq, PartialEq, Hash)]
+pub struct TsVector {
+    lexemes: Vec<Lexeme>,
+}
+
+impl TsVector {
+    /// A `tsvector` of `lexemes`
+    pub fn new(lexemes: Vec<Lexeme>) -> Self {
+        TsVector { lexemes }
+    }
+
+    /// Parse and normalize `document` into lexemes with the text search configuration `config`,
+    /// like Postgres' `to_tsvector(config, document)`
+    pub fn to_tsvector(config: &str, document: &str) -> Self {
+        unsafe {
+            direct_function_call::<TsVector>(
+                pg_sys::to_tsvector_byid,
+                &[regconfig(config).into_datum(), document.into_datum()],
+            )
+            .expect("to_tsvector() returned NULL")
+        }
+    }
+
+    /// The lexemes
+    pub fn lexemes(&self) -> &[Lexeme] {
+        &self.lexemes
+    }
+
+    /// Returns an iterator over the lexemes
+    pub fn iter(&self) -> std::slice::Iter<'_, Lexeme> {
+        self.lexemes.iter()
+    }
+
+    /// Returns the number of lexemes
+    pub fn len(&self) -> usize {
+        self.lexemes.len()
+    }
+
+    /// Returns `true` if there aren't any lexemes
+    pub fn is_empty(&self) -> bool {
+        self.lexemes.is_empty()
+    }
+
+    /// Consumes `self` and returns its lexemes
+    pub fn into_inner(self) -> Vec<Lexeme> {
+        self.lexemes
+    }
+
+    /// Does this document match `query`, like Postgres' `@@` operator?
+    pub fn matches(&self, query: &TsQuery) -> bool {
+        ts_match(self, query)
+    }
+}
+
+impl From<Vec<Lexeme>> for TsVector {
+    fn from(lexemes: Vec<Lexeme>) -> Self {
+        TsVector::new(lexemes)
+    }
+}
+
+impl<'a> IntoIterator for &'a TsVector {
+    type Item = &'a Lexeme;
+    type IntoIter = std::slice::Iter<'a, Lexeme>;
+
+    fn into_iter(self) -> Self::IntoIter {
+        self.lexemes.iter()
+    }
+}
+
+impl Display for TsVector {
+    /// Follows Postgres' format for displaying a `tsvector`
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        for (i, lexeme) in self.lexemes.iter().enumerate() {
+            if i > 0 {
+                f.write_str(" ")?;
+            }
+            f.write_str("'")?;
+            for c in lexeme.word.chars() {
+                match c {
+                    '\'' => f.write_str("''")?,
+                    '\\' => f.write_str("\\\\")?,
+                    c => write!(f, "{}", c)?,
+                }
+            }
+            f.write_str("'")?;
+            for (j, position) in lexeme.positions.iter().enumerate() {
+                f.write_str(if j == 0 { ":" } else { "," })?;
+                write!(f, "{}", position.position)?;
+                match position.weight {
+                    TsWeight::A => f.write_str("A")?,
+                    TsWeight::B => f.write_str("B")?,
+                    TsWeight::C => f.write_str("C")?,
+                    TsWeight::D => {}
+                }
+            }
+        }
+        Ok(())
+    }
+}
+
+impl FromDatum for TsVector {
+    unsafe fn from_polymorphic_datum(
+        datum: pg_sys::Datum,
+        is_null: bool,
+        _typoid: pg_sys::Oid,
+    ) -> Option<TsVector> {
+        if is_null {
+            return None;
+        }
+
+        let ptr: *mut pg_sys::varlena = datum.cast_mut_ptr();
+        let vector = pg_sys::pg_detoast_datum(ptr) as *mut pg_sys::TSVectorData;
+        let size = (*vector).size as usize;
+        let entries = (*vector).entries.as_ptr();
+        // the lexemes, and their positions, follow the entries
+        let strings = entries.add(size) as *const u8;
+
+        let mut lexemes = Vec::with_capacity(size);
+        for i in 0..size {
+            let entry = *entries.add(i);
+            let (pos, len) = (entry.pos() as usize, entry.len() as usize);
+            let word = std::slice::from_raw_parts(strings.add(pos), len);
+            let word = String::from_utf8_lossy(word).into_owned();
+
+            let mut positions = Vec::new();
+            if entry.haspos() != 0 {
+                // a lexeme's positions are a count, then the positions, at the next 2-byte boundary
+                let offset = (pos + len + 1) & !1;
+                let npos = (strings.add(offset) as *const u16).read_unaligned();
+                let list = strings.add(offset + 2) as *const u16;
+                for j in 0..npos as usize {
+                    let position = list.add(j).read_unaligned();
+                    positions.push(TsPosition {
+                        position: position & 0x3fff,
+                        weight: TsWeight::from_bits(position >> 14),
+                    });
+                }
+            }
+            lexemes.push(Lexeme { word, positions });
+        }
+
+        if !std::ptr::eq(ptr, vector.cast()) {
+            pg_sys::pfree(vector.cast());
+        }
+
+        Some(TsVector { lexemes })
+    }
+}
+
+impl IntoDatum for TsVector {
+    fn into_datum(self) -> Option<pg_sys::Datum> {
+        let cstr = CString::new(self.to_string()).expect("a tsvector can't contain a nul byte");
+        unsafe {
+            direct_function_call_as_datum(pg_sys::tsvectorin, &[cstr.as_c_str().into_datum()])
+        }
+    }
+
+    fn type_oid() -> pg_sys::Oid {
+        pg_sys::TSVECTOROID
+    }
+}
+
+unsafe impl SqlTranslatable for TsVector {
+    fn argument_sql() -> Result<SqlMapping, ArgumentError> {
+        Ok(SqlMapping::literal("tsvector"))
+    }
+    fn return_sql() -> Result<Returns, ReturnsError> {
+        Ok(Returns::One(SqlMapping::literal("tsvector")))
+    }
+}
+
+/// A `tsquery`, in its text form, such as `'fat' & ( 'rat' | 'cat' )`
+#[derive(Debug, Clone, Eq, PartialEq, Hash)]
+pub struct TsQuery(pub String);
+
+impl TsQuery {
+    /// Parse and normalize `query` with the text search configuration `config`, like Postgres'
+    /// `to_tsquery(config, query)`
+    pub fn to_tsquery(config: &str, query: &str) -> Self {
+        unsafe {
+            direct_function_call::<TsQuery>(
+                pg_sys::to_tsquery_byid,
+                &[regconfig(config).into_datum(), query.into_datum()],
+            )
+            .expect("to_tsquery() returned NULL")
+        }
+    }
+
+    /// Normalize the words of `text` with the text search configuration `config`, and query for
+    /// all of them, like Postgres' `plainto_tsquery(config, text)`
+    pub fn plainto_tsquery(config: &str, text: &str) -> Self {
+        unsafe {
+            direct_function_call::<TsQuery>(
+                pg_sys::plainto_tsquery_byid,
+                &[regconfig(config).into_datum(), text.into_datum()],
+            )
+            .expect("plainto_tsquery() returned NULL")
+        }
+    }
+}
+
+impl Deref for TsQuery {
+    type Target = str;
+
+    fn deref(&self) -> &Self::Target {
+        &self.0
+    }
+}
+
+impl From<String> for TsQuery {
+    fn from(val: String) -> Self {
+        TsQuery(val)
+    }
+}
+
+impl Display for TsQuery {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        f.write_str(&self.0)
+    }
+}
+
+impl FromDatum for TsQuery {
+    unsafe fn from_polymorphic_datum(
+        datum: pg_sys::Datum,
+        is_null: bool,
+        _typoid: pg_sys::Oid,
+    ) -> Option<TsQuery> {
+        if is_null {
+            None
+        } else {
+            let cstr = direct_function_call::<&CStr>(pg_sys::tsqueryout, &[Some(datum)]);
+            Some(TsQuery(cstr.unwrap().to_string_lossy().into_owned()))
+        }
+    }
+}
+
+impl IntoDatum for TsQuery {
+    fn into_datum(self) -> Option<pg_sys::Datum> {
+        let cstr = CString::new(self.0).expect("a tsquery can't contain a nul byte");
+        unsafe { direct_function_call_as_datum(pg_sys::tsqueryin, &[cstr.as_c_str().into_datum()]) }
+    }
+
+    fn type_oid() -> pg_sys::Oid {
+        pg_sys::TSQUERYOID
+    }
+}
+
+unsafe impl SqlTranslatable for TsQuery {
+    fn argument_sql() -> Result<SqlMapping, ArgumentError> {
+        Ok(SqlMapping::literal("tsquery"))
+    }
+    fn return_sql() -> Result<Returns, ReturnsError> {
+        Ok(Returns::One(SqlMapping::literal("tsquery")))
+    }
+}
+
+/// Does `vector` match `query`, like Postgres' `vector @@ query`?
+pub fn ts_match(vector: &TsVector, query: &TsQuery) -> bool {
+    unsafe {
+        direct_function_call::<bool>(
+            pg_sys::ts_match_vq,
+            &[vector.clone().into_datum(), query.clone().into_datum()],
+        )
+        .unwrap_or(false)
+    }
+}
+
+/// The oid of the text search configuration named `config`
+fn regconfig(config: &str) -> pg_sys::Oid {
+    let cstr = CString::new(config).expect("a text search configuration name can't contain a nul");
+    unsafe {
+        direct_function_call::<pg_sys::Oid>(pg_sys::regconfigin, &[cstr.as_c_str().into_datum()])
+            .expect("regconfigin() returned NULL")
+    }
+}
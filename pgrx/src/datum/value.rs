@@ -0,0 +1,208 @@
+/*
+Portions Copyright 2019-2021 ZomboDB, LLC.
+Portions Copyright 2021-2022 Technology Concepts & Design, Inc. <support@tcdi.com>
+
+All rights reserved.
+
+Use of this source code is governed by the MIT license that can be found in the LICENSE file.
+*/
+
+//! A dynamically-typed Datum value, for processing values whose types are only known at runtime
+use crate::{
+    pg_sys, AnyNumeric, Date, FromDatum, IntoDatum, Json, JsonB, Timestamp, TimestampWithTimeZone,
+    Uuid,
+};
+use core::ffi::CStr;
+use serde::{Serialize, Serializer};
+use std::fmt::{Display, Formatter};
+
+/// A Datum decoded into a Rust value based on its runtime type oid.
+///
+/// This is useful for generic tools, such as loggers, auditors, or converting the output of an
+/// arbitrary SPI query to JSON, where the types of the values aren't known until runtime.
+///
+/// Types without a dedicated variant are kept as [`Value::Other`], which can still be rendered
+/// as text through the type's output function.
+#[derive(Debug, Clone, PartialEq)]
+pub enum Value {
+    Null,
+    Bool(bool),
+    Int2(i16),
+    Int4(i32),
+    Int8(i64),
+    Float4(f32),
+    Float8(f64),
+    Numeric(AnyNumeric),
+    Text(String),
+    Bytea(Vec<u8>),
+    Json(serde_json::Value),
+    Uuid(Uuid),
+    Date(Date),
+    Timestamp(Timestamp),
+    TimestampWithTimeZone(TimestampWithTimeZone),
+    /// A value of any other type, as its raw (unowned) Datum and type oid.  If the type is
+    /// pass-by-reference, the Datum is only valid for as long as the memory it points to.
+    Other {
+        oid: pg_sys::Oid,
+        datum: pg_sys::Datum,
+    },
+}
+
+impl Value {
+    /// Decode a `(datum, is_null)` pair of the Postgres type `typoid` into a [`Value`].
+    ///
+    /// # Safety
+    ///
+    /// Same caveats as [`FromDatum::from_datum`]: `datum` must be a valid Datum of type `typoid`.
+    pub unsafe fn from_datum(datum: pg_sys::Datum, is_null: bool, typoid: pg_sys::Oid) -> Value {
+        if is_null {
+            return Value::Null;
+        }
+
+        let value = match typoid {
+            pg_sys::BOOLOID => bool::from_datum(datum, false).map(Value::Bool),
+            pg_sys::INT2OID => i16::from_datum(datum, false).map(Value::Int2),
+            pg_sys::INT4OID => i32::from_datum(datum, false).map(Value::Int4),
+            pg_sys::INT8OID => i64::from_datum(datum, false).map(Value::Int8),
+            pg_sys::FLOAT4OID => f32::from_datum(datum, false).map(Value::Float4),
+            pg_sys::FLOAT8OID => f64::from_datum(datum, false).map(Value::Float8),
+            pg_sys::NUMERICOID => AnyNumeric::from_datum(datum, false).map(Value::Numeric),
+            pg_sys::TEXTOID | pg_sys::VARCHAROID | pg_sys::BPCHAROID => {
+                String::from_datum(datum, false).map(Value::Text)
+            }
+            pg_sys::BYTEAOID => Vec::<u8>::from_datum(datum, false).map(Value::Bytea),
+            pg_sys::JSONOID => Json::from_datum(datum, false).map(|json| Value::Json(json.0)),
+            pg_sys::JSONBOID => JsonB::from_datum(datum, false).map(|jsonb| Value::Json(jsonb.0)),
+            pg_sys::UUIDOID => Uuid::from_datum(datum, false).map(Value::Uuid),
+            pg_sys::DATEOID => Date::from_datum(datum, false).map(Value::Date),
+            pg_sys::TIMESTAMPOID => Timestamp::from_datum(datum, false).map(Value::Timestamp),
+            pg_sys::TIMESTAMPTZOID => {
+                TimestampWithTimeZone::from_datum(datum, false).map(Value::TimestampWithTimeZone)
+            }
+            _ => Some(Value::Other { oid: typoid, datum }),
+        };
+
+        value.unwrap_or(Value::Null)
+    }
+
+    /// Convert any typed Rust value into a [`Value`] by way of its Datum representation
+    pub fn from_typed<T: IntoDatum>(value: T) -> Value {
+        match value.into_datum() {
+            // SAFETY:  `T`'s IntoDatum impl just made us a valid Datum of type `T::type_oid()`
+            Some(datum) => unsafe { Value::from_datum(datum, false, T::type_oid()) },
+            None => Value::Null,
+        }
+    }
+
+    /// Is this the SQL `NULL` value?
+    pub fn is_null(&self) -> bool {
+        matches!(self, Value::Null)
+    }
+
+    /// The Postgres type oid this value converts back into.  [`Value::Null`] is untyped and
+    /// returns [`pg_sys::InvalidOid`]
+    pub fn type_oid(&self) -> pg_sys::Oid {
+        match self {
+            Value::Null => pg_sys::InvalidOid,
+            Value::Bool(_) => pg_sys::BOOLOID,
+            Value::Int2(_) => pg_sys::INT2OID,
+            Value::Int4(_) => pg_sys::INT4OID,
+            Value::Int8(_) => pg_sys::INT8OID,
+            Value::Float4(_) => pg_sys::FLOAT4OID,
+            Value::Float8(_) => pg_sys::FLOAT8OID,
+            Value::Numeric(_) => pg_sys::NUMERICOID,
+            Value::Text(_) => pg_sys::TEXTOID,
+            Value::Bytea(_) => pg_sys::BYTEAOID,
+            Value::Json(_) => pg_sys::JSONOID,
+            Value::Uuid(_) => pg_sys::UUIDOID,
+            Value::Date(_) => pg_sys::DATEOID,
+            Value::Timestamp(_) => pg_sys::TIMESTAMPOID,
+            Value::TimestampWithTimeZone(_) => pg_sys::TIMESTAMPTZOID,
+            Value::Other { oid, .. } => *oid,
+        }
+    }
+
+    /// Convert this value back into a Datum of [`Value::type_oid()`]
+    pub fn to_datum(&self) -> Option<pg_sys::Datum> {
+        match self.clone() {
+            Value::Null => None,
+            Value::Bool(v) => v.into_datum(),
+            Value::Int2(v) => v.into_datum(),
+            Value::Int4(v) => v.into_datum(),
+            Value::Int8(v) => v.into_datum(),
+            Value::Float4(v) => v.into_datum(),
+            Value::Float8(v) => v.into_datum(),
+            Value::Numeric(v) => v.into_datum(),
+            Value::Text(v) => v.into_datum(),
+            Value::Bytea(v) => v.into_datum(),
+            Value::Json(v) => Json(v).into_datum(),
+            Value::Uuid(v) => v.into_datum(),
+            Value::Date(v) => v.into_datum(),
+            Value::Timestamp(v) => v.into_datum(),
+            Value::TimestampWithTimeZone(v) => v.into_datum(),
+            Value::Other { datum, .. } => Some(datum),
+        }
+    }
+
+    /// Render this value as text using its type's output function, exactly as Postgres would.
+    /// Returns `None` for [`Value::Null`].
+    pub fn to_output_string(&self) -> Option<String> {
+        // SAFETY:  `to_datum()` gives us a Datum of type `type_oid()`
+        self.to_datum().map(|datum| unsafe { output_function_call(datum, self.type_oid()) })
+    }
+}
+
+/// Call the output function of the type `typoid` for `datum`, returning its text representation
+///
+/// # Safety
+///
+/// `datum` must be a valid, non-null Datum of type `typoid`
+pub(crate) unsafe fn output_function_call(datum: pg_sys::Datum, typoid: pg_sys::Oid) -> String {
+    let mut output_func = pg_sys::InvalidOid;
+    let mut is_varlena = false;
+    pg_sys::getTypeOutputInfo(typoid, &mut output_func, &mut is_varlena);
+
+    let cstr = pg_sys::OidOutputFunctionCall(output_func, datum);
+    let string = CStr::from_ptr(cstr).to_string_lossy().to_string();
+    pg_sys::pfree(cstr.cast());
+    string
+}
+
+/// Formats the value using its type's output function, and `NULL` for [`Value::Null`]
+impl Display for Value {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self.to_output_string() {
+            Some(s) => f.write_str(&s),
+            None => f.write_str("NULL"),
+        }
+    }
+}
+
+/// Serializes into the natural JSON representation of each type.  `numeric` values are serialized
+/// as strings so as to not lose precision, just like [`AnyNumeric`]'s own `Serialize` impl.
+/// [`Value::Other`] is serialized as the text from its type's output function.
+impl Serialize for Value {
+    fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
+    where
+        S: Serializer,
+    {
+        match self {
+            Value::Null => serializer.serialize_none(),
+            Value::Bool(v) => serializer.serialize_bool(*v),
+            Value::Int2(v) => serializer.serialize_i16(*v),
+            Value::Int4(v) => serializer.serialize_i32(*v),
+            Value::Int8(v) => serializer.serialize_i64(*v),
+            Value::Float4(v) => serializer.serialize_f32(*v),
+            Value::Float8(v) => serializer.serialize_f64(*v),
+            Value::Numeric(v) => v.serialize(serializer),
+            Value::Text(v) => serializer.serialize_str(v),
+            Value::Bytea(v) => serializer.serialize_bytes(v),
+            Value::Json(v) => v.serialize(serializer),
+            Value::Uuid(v) => serializer.serialize_str(&v.to_string()),
+            Value::Date(v) => v.serialize(serializer),
+            Value::Timestamp(v) => v.serialize(serializer),
+            Value::TimestampWithTimeZone(v) => v.serialize(serializer),
+            Value::Other { .. } => serializer.serialize_str(&self.to_string()),
+        }
+    }
+}
@@ -7,11 +7,11 @@ All rights reserved.
 Use of this source code is governed by the MIT license that can be found in the LICENSE file.
 */
 //! Wrapper for Postgres 'varlena' type, over Rust types of a fixed size (ie, `impl Copy`)
-use crate::pg_sys::{VARATT_SHORT_MAX, VARHDRSZ_SHORT};
+use crate::datum::versioned::ENVELOPE_TAG;
 use crate::{
-    pg_sys, rust_regtypein, set_varsize, set_varsize_short, vardata_any, varsize_any,
-    varsize_any_exhdr, void_mut_ptr, FromDatum, IntoDatum, PgMemoryContexts, PostgresType,
-    StringInfo,
+    ereport, memcxt_tracking, pg_sys, rust_regtypein, set_varsize, vardata_any, varsize_any,
+    varsize_any_exhdr, void_mut_ptr, FromDatum, IntoDatum, PgMemoryContexts, PgSqlErrorCode,
+    PostgresType, StringInfo,
 };
 use pgrx_pg_sys::varlena;
 use pgrx_sql_entity_graph::metadata::{
@@ -34,9 +34,11 @@ impl Clone for PallocdVarlena {
         // SAFETY:  we know that `self.ptr` is valid as the only way we could have gotten one
         // is internally via Postgres
         let ptr = unsafe {
-            PgMemoryContexts::of(self.ptr as void_mut_ptr)
-                .expect("could not determine owning memory context")
-                .copy_ptr_into(self.ptr as void_mut_ptr, len) as *mut pg_sys::varlena
+            let mut context = PgMemoryContexts::of(self.ptr as void_mut_ptr)
+                .expect("could not determine owning memory context");
+            let ptr = context.copy_ptr_into(self.ptr as void_mut_ptr, len) as *mut pg_sys::varlena;
+            memcxt_tracking::track_allocation(ptr.cast(), context.value());
+            ptr
         };
 
         PallocdVarlena { ptr, len }
@@ -130,16 +132,15 @@ where
         let size_of = std::mem::size_of::<T>();
 
         let ptr = unsafe { pg_sys::palloc0(pg_sys::VARHDRSZ + size_of) as *mut pg_sys::varlena };
+        unsafe {
+            memcxt_tracking::track_allocation(ptr.cast(), pg_sys::CurrentMemoryContext);
+        }
 
         // safe: ptr will halready be allocated
+        // always the full 4-byte header:  with a 1-byte header, the data wouldn't be aligned for `T`.
+        // Postgres packs it itself if it's stored
         unsafe {
-            if size_of + VARHDRSZ_SHORT() <= VARATT_SHORT_MAX as usize {
-                // we can use the short header size
-                set_varsize_short(ptr, (size_of + VARHDRSZ_SHORT()) as i32);
-            } else {
-                // gotta use the full 4-byte header
-                set_varsize(ptr, (size_of + pg_sys::VARHDRSZ) as i32);
-            }
+            set_varsize(ptr, (size_of + pg_sys::VARHDRSZ) as i32);
         }
 
         PgVarlena {
@@ -168,6 +169,7 @@ where
 
         if ptr == datum.cast_mut_ptr() {
             // no detoasting happened so we're using borrowed memory
+            memcxt_tracking::forget_allocation(ptr.cast());
             let leaked = Box::leak(Box::new(PallocdVarlena { ptr, len }));
             PgVarlena {
                 leaked: Some(leaked),
@@ -177,6 +179,7 @@ where
             }
         } else {
             // datum was detoasted so we own and need to free it
+            memcxt_tracking::track_allocation(ptr.cast(), pg_sys::CurrentMemoryContext);
             PgVarlena {
                 leaked: None,
                 varlena: Cow::Owned(PallocdVarlena { ptr, len }),
@@ -193,6 +196,7 @@ where
     pub fn into_pg(mut self) -> *mut pg_sys::varlena {
         // we don't want our varlena to be pfree'd
         self.need_free = false;
+        memcxt_tracking::forget_allocation(self.varlena.ptr.cast());
         self.varlena.ptr
     }
 }
@@ -203,11 +207,20 @@ where
     T: Copy + Sized,
 {
     fn drop(&mut self) {
+        if self.need_free && !memcxt_tracking::is_allocation_live(self.varlena.ptr.cast()) {
+            // the memory is already gone.  Complain, unless we're already unwinding from a panic
+            self.need_free = false;
+            if !std::thread::panicking() {
+                memcxt_tracking::assert_allocation_live(self.varlena.ptr.cast(), "PgVarlena");
+            }
+        }
+
         if self.need_free {
             unsafe {
                 // safe: self.varlena.ptr will never be null
                 pg_sys::pfree(self.varlena.ptr as void_mut_ptr);
             }
+            memcxt_tracking::forget_allocation(self.varlena.ptr.cast());
         }
 
         if let Some(leaked) = self.leaked {
@@ -240,7 +253,9 @@ impl<T> AsRef<T> for PgVarlena<T>
 where
     T: Copy + Sized,
 {
+    #[track_caller]
     fn as_ref(&self) -> &T {
+        memcxt_tracking::assert_allocation_live(self.varlena.ptr.cast(), "PgVarlena");
         unsafe {
             // safe: ptr will never be null
             let ptr = vardata_any(self.varlena.ptr) as *const T;
@@ -265,7 +280,9 @@ impl<T> AsMut<T> for PgVarlena<T>
 where
     T: Copy + Sized,
 {
+    #[track_caller]
     fn as_mut(&mut self) -> &mut T {
+        memcxt_tracking::assert_allocation_live(self.varlena.ptr.cast(), "PgVarlena");
         unsafe {
             // safe: ptr will never be null
             let ptr = vardata_any(self.varlena.to_mut().ptr) as *mut T;
@@ -340,7 +357,7 @@ where
     T: PostgresType + Serialize,
 {
     fn into_datum(self) -> Option<pg_sys::Datum> {
-        Some(cbor_encode(&self).into())
+        Some(cbor_encode(&self, T::storage_version()).into())
     }
 
     fn type_oid() -> pg_sys::Oid {
@@ -360,12 +377,12 @@ where
         if is_null {
             None
         } else {
-            cbor_decode(datum.cast_mut_ptr())
+            cbor_decode_stored(datum.cast_mut_ptr())
         }
     }
 
     unsafe fn from_datum_in_memory_context(
-        memory_context: PgMemoryContexts,
+        mut memory_context: PgMemoryContexts,
         datum: pg_sys::Datum,
         is_null: bool,
         _typoid: pg_sys::Oid,
@@ -373,18 +390,26 @@ where
         if is_null {
             None
         } else {
-            cbor_decode_into_context(memory_context, datum.cast_mut_ptr())
+            memory_context.switch_to(|_| {
+                // this gets the varlena Datum copied into this memory context
+                let varlena = pg_sys::pg_detoast_datum_copy(datum.cast_mut_ptr());
+                cbor_decode_stored(varlena)
+            })
         }
     }
 }
 
-fn cbor_encode<T>(input: T) -> *const pg_sys::varlena
+/// Encodes `input` as CBOR, in a versioned envelope if it has a `version`
+fn cbor_encode<T>(input: T, version: Option<u8>) -> *const pg_sys::varlena
 where
     T: Serialize,
 {
     let mut serialized = StringInfo::new();
 
     serialized.push_bytes(&[0u8; pg_sys::VARHDRSZ]); // reserve space for the header
+    if let Some(version) = version {
+        serialized.push_bytes(&[ENVELOPE_TAG, version]);
+    }
     serde_cbor::to_writer(&mut serialized, &input).expect("failed to encode as CBOR");
 
     let size = serialized.len() as usize;
@@ -421,6 +446,46 @@ where
     })
 }
 
+/// Decodes a `T` written by any version of it, migrating values written by older versions
+unsafe fn cbor_decode_stored<'de, T>(input: *mut pg_sys::varlena) -> Option<T>
+where
+    T: PostgresType + Deserialize<'de>,
+{
+    let current = match T::storage_version() {
+        Some(current) => current,
+        None => return cbor_decode(input),
+    };
+
+    let varlena = pg_sys::pg_detoast_datum_packed(input);
+    let len = varsize_any_exhdr(varlena);
+    let data = vardata_any(varlena);
+    let slice = std::slice::from_raw_parts(data as *const u8, len);
+    let (version, payload) = match slice {
+        [ENVELOPE_TAG, version, payload @ ..] => (*version, payload),
+        _ => (0, slice),
+    };
+    if version == current {
+        return serde_cbor::from_slice(payload).expect("failed to decode CBOR");
+    }
+
+    match T::storage_migrations().decode(version, payload) {
+        Some(migrated) => Some(migrated.expect("failed to decode CBOR")),
+        None if version == 0 => serde_cbor::from_slice(payload).expect("failed to decode CBOR"),
+        None => {
+            let type_name = std::any::type_name::<T>();
+            let type_name = type_name.rsplit("::").next().unwrap_or(type_name);
+            ereport!(
+                ERROR,
+                PgSqlErrorCode::ERRCODE_DATA_CORRUPTED,
+                format!(
+                    "no migration from version {} of type `{}` to its current version {}",
+                    version, type_name, current
+                )
+            );
+        }
+    }
+}
+
 #[allow(dead_code)]
 fn json_encode<T>(input: T) -> *const varlena
 where
@@ -0,0 +1,114 @@
+/*
+Portions Copyright 2019-2021 ZomboDB, LLC.
+Portions Copyright 2021-2022 Technology Concepts & Design, Inc. <support@tcdi.com>
+
+All rights reserved.
+
+Use of this source code is governed by the MIT license that can be found in the LICENSE file.
+*/
+
+//! Versioned storage for `#[derive(Serialize, Deserialize, PostgresType)]` types, so values written
+//! by older versions of an extension can still be read after the type's definition changes
+use serde::de::DeserializeOwned;
+use std::collections::BTreeMap;
+
+/// The first byte of a versioned value, followed by its version byte and then its CBOR.  `0xff` is
+/// CBOR's "break" code, which can't begin a CBOR data item, so versioned values can't be confused
+/// with values written before their type was versioned.
+pub(crate) const ENVELOPE_TAG: u8 = 0xff;
+
+/// `#[derive(Serialize, Deserialize, PostgresType)]` types with the `#[versioned]` attribute
+/// implement this trait to store their values with a version, and to decode values written by
+/// older versions of the type.
+///
+/// Values written before the type was `#[versioned]` are version `0`.  Unless there's a migration
+/// from version `0`, they're decoded as the current version.
+///
+/// ```rust,no_run
+/// use pgrx::prelude::*;
+/// use serde::{Deserialize, Serialize};
+///
+/// #[derive(Serialize, Deserialize)]
+/// struct DogV1 {
+///     name: String,
+/// }
+///
+/// #[derive(Serialize, Deserialize, PostgresType)]
+/// #[versioned]
+/// struct Dog {
+///     name: String,
+///     treats: i64,
+/// }
+///
+/// impl From<DogV1> for Dog {
+///     fn from(dog: DogV1) -> Self {
+///         Dog { name: dog.name, treats: 0 }
+///     }
+/// }
+///
+/// impl VersionedType for Dog {
+///     const VERSION: u8 = 2;
+///
+///     fn migrations() -> Migrations<Self> {
+///         Migrations::new().from_version::<DogV1>(1)
+///     }
+/// }
+/// ```
+pub trait VersionedType: Sized {
+    /// The version values are written with.  Bump it, and add a migration from the old version,
+    /// whenever the type's serialized form changes.
+    const VERSION: u8;
+
+    /// How to decode values written by older versions of the type
+    fn migrations() -> Migrations<Self> {
+        Migrations::new()
+    }
+}
+
+/// A registry of decoders for the older versions of a [`VersionedType`], each of which
+/// deserializes a value as it was then and converts it into the current type.
+///
+/// A migration across several versions can be written by converting through the versions between,
+/// such as with `impl From<DogV1> for DogV3 { fn from(dog: DogV1) -> Self { DogV2::from(dog).into() } }`.
+pub struct Migrations<T> {
+    decoders: BTreeMap<u8, fn(&[u8]) -> Result<T, serde_cbor::Error>>,
+}
+
+impl<T> Migrations<T> {
+    /// A registry without any migrations
+    pub fn new() -> Self {
+        Self { decoders: BTreeMap::new() }
+    }
+
+    /// Decodes values written by `version` as an `Old`, and converts them into a `T`
+    pub fn from_version<Old>(mut self, version: u8) -> Self
+    where
+        Old: DeserializeOwned + Into<T>,
+    {
+        self.decoders.insert(version, decode_as::<Old, T>);
+        self
+    }
+
+    /// Is there a migration from `version`?
+    pub fn contains(&self, version: u8) -> bool {
+        self.decoders.contains_key(&version)
+    }
+
+    /// Decodes `payload` written by `version`, or returns `None` if there's no migration from it
+    pub fn decode(&self, version: u8, payload: &[u8]) -> Option<Result<T, serde_cbor::Error>> {
+        self.decoders.get(&version).map(|decode| decode(payload))
+    }
+}
+
+impl<T> Default for Migrations<T> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+fn decode_as<Old, T>(payload: &[u8]) -> Result<T, serde_cbor::Error>
+where
+    Old: DeserializeOwned + Into<T>,
+{
+    serde_cbor::from_slice::<Old>(payload).map(Into::into)
+}
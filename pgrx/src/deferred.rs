@@ -0,0 +1,289 @@
+/*
+Portions Copyright 2019-2021 ZomboDB, LLC.
+Portions Copyright 2021-2022 Technology Concepts & Design, Inc. <support@tcdi.com>
+
+All rights reserved.
+
+Use of this source code is governed by the MIT license that can be found in the LICENSE file.
+*/
+
+//! Validations of custom type values deferred to the end of the statement or transaction, like
+//! deferred constraints
+//!
+//! Some checks of a value are too expensive to make one value at a time, as each is created, but
+//! cheap to make for a batch of them, such as checking that a set of values doesn't overlap, or
+//! looking them up in a remote service.  A [`DeferredValidation`] queues values as they're created
+//! and validates all those queued when the statement, or the transaction, ends:
+//!
+//! ```rust,no_run
+//! use pgrx::deferred::{DeferredValidation, ValidateAt};
+//!
+//! static ROUTE_EXISTS: DeferredValidation<String> =
+//!     DeferredValidation::new("route_exists", ValidateAt::TransactionEnd, |routes| {
+//!         // look all of `routes` up at once
+//!         Ok(())
+//!     });
+//!
+//! #[pgrx::pg_guard]
+//! pub extern "C" fn _PG_init() {
+//!     pgrx::deferred::init();
+//! }
+//!
+//! fn make_route(name: String) -> String {
+//!     ROUTE_EXISTS.defer(name.clone());
+//!     name
+//! }
+//! ```
+//!
+//! A failed validation raises an `ERROR`, aborting the transaction.  Queued values are copied into
+//! the `TopTransactionContext`, and forgotten when the (sub)transaction they were queued in
+//! aborts.
+use crate::callbacks::{
+    register_session_subxact_callback, register_session_xact_callback, PgSubXactCallbackEvent,
+    PgXactCallbackEvent,
+};
+use crate::hooks::{register_hook, HookResult, PgHooks};
+use crate::prelude::*;
+use crate::PgMemoryContexts;
+use std::cell::{Cell, RefCell};
+
+/// When a [`DeferredValidation`] validates the values queued for it
+#[derive(Debug, Copy, Clone, Eq, PartialEq)]
+pub enum ValidateAt {
+    /// At the end of the top-level statement, once its `AFTER` triggers have run
+    StatementEnd,
+    /// Immediately before the transaction commits or is prepared
+    TransactionEnd,
+}
+
+/// A validation of values of `T`, made in batches at the end of the statement or transaction the
+/// values were queued in.  Declare one as a `static`, and queue values for it with
+/// [`DeferredValidation::defer()`]
+pub struct DeferredValidation<T> {
+    name: &'static str,
+    at: ValidateAt,
+    validate: fn(Vec<T>) -> Result<(), String>,
+}
+
+impl<T: FromDatum + IntoDatum> DeferredValidation<T> {
+    /// A validation named `name` in error messages, which calls `validate` with the values queued
+    /// for it when `at` comes.  `validate` returns an error message for a value it rejects
+    pub const fn new(
+        name: &'static str,
+        at: ValidateAt,
+        validate: fn(Vec<T>) -> Result<(), String>,
+    ) -> Self {
+        DeferredValidation { name, at, validate }
+    }
+
+    pub fn name(&self) -> &'static str {
+        self.name
+    }
+
+    pub fn at(&self) -> ValidateAt {
+        self.at
+    }
+
+    /// Queue `value` to be validated.  A `NULL` value is never validated
+    ///
+    /// # Panics
+    ///
+    /// If [`init()`] wasn't called from the extension's `_PG_init()`
+    pub fn defer(&'static self, value: T) {
+        assert!(
+            INITIALIZED.with(Cell::get),
+            "pgrx::deferred::init() must be called from _PG_init() before deferring validations"
+        );
+        let type_oid = T::type_oid();
+        let datum = match value.into_datum() {
+            Some(datum) => datum,
+            None => return,
+        };
+        let (mut typlen, mut typbyval) = (0, false);
+        let datum = unsafe {
+            pg_sys::get_typlenbyval(type_oid, &mut typlen, &mut typbyval);
+            PgMemoryContexts::TopTransactionContext
+                .switch_to(|_| pg_sys::datumCopy(datum, typbyval, typlen.into()))
+        };
+
+        QUEUE.with(|queue| {
+            queue.borrow_mut().push(Queued {
+                validation: self as *const Self as *const (),
+                name: self.name,
+                at: self.at,
+                subxact: unsafe { pg_sys::GetCurrentSubTransactionId() },
+                type_oid,
+                datum,
+                validate: validate_queued::<T>,
+            })
+        })
+    }
+
+    /// The number of values queued for this validation, not yet validated
+    pub fn pending(&'static self) -> usize {
+        let validation = self as *const Self as *const ();
+        QUEUE.with(|queue| {
+            queue.borrow().iter().filter(|queued| queued.validation == validation).count()
+        })
+    }
+}
+
+// `validation` is `&'static DeferredValidation<T>` for the `T` of `validate_queued::<T>`
+unsafe fn validate_queued<T: FromDatum + IntoDatum>(
+    validation: *const (),
+    type_oid: pg_sys::Oid,
+    datums: Vec<pg_sys::Datum>,
+) -> Result<(), String> {
+    let validation = &*(validation as *const DeferredValidation<T>);
+    let values = datums
+        .into_iter()
+        .filter_map(|datum| T::from_polymorphic_datum(datum, false, type_oid))
+        .collect();
+    (validation.validate)(values)
+}
+
+struct Queued {
+    validation: *const (),
+    name: &'static str,
+    at: ValidateAt,
+    // the subtransaction it was queued in
+    subxact: pg_sys::SubTransactionId,
+    type_oid: pg_sys::Oid,
+    // in the TopTransactionContext
+    datum: pg_sys::Datum,
+    validate: unsafe fn(*const (), pg_sys::Oid, Vec<pg_sys::Datum>) -> Result<(), String>,
+}
+
+thread_local! {
+    static QUEUE: RefCell<Vec<Queued>> = RefCell::new(Vec::new());
+    static INITIALIZED: Cell<bool> = const { Cell::new(false) };
+}
+
+/// Install the hooks and callbacks deferred validations run from.  Call it from `_PG_init()`
+pub fn init() {
+    if INITIALIZED.with(|initialized| initialized.replace(true)) {
+        return;
+    }
+
+    // SAFETY:  `register_hook()` changes the hooks Postgres runs, and `_PG_init()` isn't called
+    // from one of them
+    unsafe { register_hook(Box::leak(Box::new(StatementEnd { nesting: 0 }))) };
+
+    register_session_xact_callback(|event| match event {
+        PgXactCallbackEvent::PreCommit | PgXactCallbackEvent::PrePrepare => {
+            validate_pending(|_| true)
+        }
+        PgXactCallbackEvent::Abort => QUEUE.with(|queue| queue.borrow_mut().clear()),
+        _ => {}
+    })
+    .leak();
+
+    register_session_subxact_callback(|event, subxact, _parent| {
+        if event == PgSubXactCallbackEvent::AbortSub {
+            // subtransactions started later than this one were within it
+            QUEUE.with(|queue| queue.borrow_mut().retain(|queued| queued.subxact < subxact))
+        }
+    })
+    .leak();
+}
+
+/// Validate every value queued so far now, rather than waiting for the end of the statement or
+/// transaction, like `SET CONSTRAINTS ALL IMMEDIATE`
+pub fn validate_all_pending() {
+    validate_pending(|_| true)
+}
+
+/// The number of values queued for any validation, not yet validated
+pub fn pending_count() -> usize {
+    QUEUE.with(|queue| queue.borrow().len())
+}
+
+fn validate_pending(which: impl Fn(&Queued) -> bool) {
+    // taken from the queue first, so a validation can itself queue more values
+    let queued = QUEUE.with(|queue| {
+        let mut queue = queue.borrow_mut();
+        let (taken, kept) = queue.drain(..).partition::<Vec<_>, _>(|queued| which(queued));
+        *queue = kept;
+        taken
+    });
+
+    // a batch for each validation, in the order each first had a value queued
+    let mut batches: Vec<(Queued, Vec<pg_sys::Datum>)> = Vec::new();
+    for queued in queued {
+        match batches.iter_mut().find(|(first, _)| first.validation == queued.validation) {
+            Some((_, datums)) => datums.push(queued.datum),
+            None => {
+                let datums = vec![queued.datum];
+                batches.push((queued, datums));
+            }
+        }
+    }
+
+    for (first, datums) in batches {
+        if let Err(message) = unsafe { (first.validate)(first.validation, first.type_oid, datums) }
+        {
+            ereport!(
+                PgLogLevel::ERROR,
+                PgSqlErrorCode::ERRCODE_CHECK_VIOLATION,
+                format!("deferred validation \"{}\" failed: {}", first.name, message)
+            );
+        }
+    }
+}
+
+/// Validates values queued for [`ValidateAt::StatementEnd`] when the top-level statement finishes
+struct StatementEnd {
+    // how many executor runs and finishes are in progress
+    nesting: usize,
+}
+
+impl StatementEnd {
+    fn nested<R>(&mut self, f: impl FnOnce() -> R) -> R {
+        struct Unnest(*mut usize);
+
+        impl Drop for Unnest {
+            fn drop(&mut self) {
+                unsafe { *self.0 = (*self.0).saturating_sub(1) }
+            }
+        }
+
+        self.nesting += 1;
+        let _unnest = Unnest(&mut self.nesting);
+        f()
+    }
+}
+
+impl PgHooks for StatementEnd {
+    fn executor_run(
+        &mut self,
+        query_desc: PgBox<pg_sys::QueryDesc>,
+        direction: pg_sys::ScanDirection,
+        count: u64,
+        execute_once: bool,
+        prev_hook: fn(
+            query_desc: PgBox<pg_sys::QueryDesc>,
+            direction: pg_sys::ScanDirection,
+            count: u64,
+            execute_once: bool,
+        ) -> HookResult<()>,
+    ) -> HookResult<()> {
+        self.nested(|| prev_hook(query_desc, direction, count, execute_once))
+    }
+
+    fn executor_finish(
+        &mut self,
+        query_desc: PgBox<pg_sys::QueryDesc>,
+        prev_hook: fn(query_desc: PgBox<pg_sys::QueryDesc>) -> HookResult<()>,
+    ) -> HookResult<()> {
+        // finishing runs the statement's AFTER triggers, which may queue values too
+        let result = self.nested(|| prev_hook(query_desc));
+        if self.nesting == 0 {
+            validate_pending(|queued| queued.at == ValidateAt::StatementEnd);
+        }
+        result
+    }
+
+    fn abort(&mut self) {
+        self.nesting = 0;
+    }
+}
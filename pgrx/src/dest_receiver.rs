@@ -0,0 +1,206 @@
+/*
+Portions Copyright 2019-2021 ZomboDB, LLC.
+Portions Copyright 2021-2022 Technology Concepts & Design, Inc. <support@tcdi.com>
+
+All rights reserved.
+
+Use of this source code is governed by the MIT license that can be found in the LICENSE file.
+*/
+
+//! Receiving query results a row at a time, with a `DestReceiver` implemented in Rust
+//!
+//! The executor hands each row it produces to a `DestReceiver`, which might send it to the
+//! client, store it in a tuplestore, or, for SPI, collect it into an `SPITupleTable`.
+//! [`RowReceiver`] instead calls a Rust closure with each row, as it's produced, so results can
+//! be streamed into an extension's own sink without ever being materialized.
+//!
+//! [`SpiClient::select_into()`](crate::spi::SpiClient::select_into) runs a query into a
+//! `RowReceiver`.  [`RowReceiver::as_dest_receiver()`] can be given to anything else that takes a
+//! `DestReceiver`, like `ExecutorRun()`.
+//!
+//! ## Examples
+//!
+//! ```rust,no_run
+//! use pgrx::dest_receiver::RowReceiver;
+//! use pgrx::prelude::*;
+//!
+//! # fn foo() -> spi::Result<()> {
+//! let mut total = 0i64;
+//! let mut receiver = RowReceiver::new(|row| {
+//!     total += row.get::<i64>(1).unwrap().unwrap_or_default();
+//!     true // keep going
+//! });
+//! Spi::connect(|client| client.select_into("SELECT i FROM generate_series(1, 1000000) i", None, &mut receiver))?;
+//! # Ok(())
+//! # }
+//! ```
+use crate as pgrx; // for #[pg_guard] support from within ourself
+use crate::memcxt::PgMemoryContexts;
+use crate::prelude::*;
+use crate::spi::{self, SpiErrorCodes};
+use std::marker::PhantomData;
+
+/// A row being handed to a [`RowReceiver`].  It's only valid during the call
+pub struct ReceivedRow<'a> {
+    slot: *mut pg_sys::TupleTableSlot,
+    tupdesc: pg_sys::TupleDesc,
+    __marker: PhantomData<&'a pg_sys::TupleTableSlot>,
+}
+
+impl ReceivedRow<'_> {
+    /// The number of columns
+    pub fn columns(&self) -> usize {
+        unsafe { (*self.tupdesc).natts as usize }
+    }
+
+    fn attribute(&self, ordinal: usize) -> spi::Result<&pg_sys::FormData_pg_attribute> {
+        if ordinal < 1 || ordinal > self.columns() {
+            return Err(spi::Error::SpiError(SpiErrorCodes::NoAttribute));
+        }
+        unsafe { Ok(&(*self.tupdesc).attrs.as_slice(self.columns())[ordinal - 1]) }
+    }
+
+    /// The name of the column at the 1-based `ordinal` position
+    pub fn column_name(&self, ordinal: usize) -> spi::Result<&str> {
+        Ok(pg_sys::name_data_to_str(&self.attribute(ordinal)?.attname))
+    }
+
+    /// The type of the column at the 1-based `ordinal` position
+    pub fn column_type_oid(&self, ordinal: usize) -> spi::Result<pg_sys::Oid> {
+        Ok(self.attribute(ordinal)?.atttypid)
+    }
+
+    /// The 1-based ordinal position of the column named `name`
+    pub fn column_ordinal<S: AsRef<str>>(&self, name: S) -> spi::Result<usize> {
+        (1..=self.columns())
+            .find(|&ordinal| self.column_name(ordinal).ok() == Some(name.as_ref()))
+            .ok_or(spi::Error::SpiError(SpiErrorCodes::NoAttribute))
+    }
+
+    /// The raw value of the column at the 1-based `ordinal` position
+    pub fn get_datum(&self, ordinal: usize) -> spi::Result<Option<pg_sys::Datum>> {
+        self.attribute(ordinal)?;
+        unsafe { Ok(crate::compat::slot_getattr(self.slot, ordinal)) }
+    }
+
+    /// The value of the column at the 1-based `ordinal` position
+    ///
+    /// # Errors
+    ///
+    /// Returns a [`spi::Error::DatumError`] if the desired Rust type is incompatible with the
+    /// column's type
+    pub fn get<T: IntoDatum + FromDatum>(&self, ordinal: usize) -> spi::Result<Option<T>> {
+        let type_oid = self.column_type_oid(ordinal)?;
+        match self.get_datum(ordinal)? {
+            Some(datum) => unsafe {
+                T::try_from_datum(datum, false, type_oid).map_err(spi::Error::DatumError)
+            },
+            None => Ok(None),
+        }
+    }
+
+    /// The value of the column named `name`
+    pub fn get_by_name<T: IntoDatum + FromDatum, S: AsRef<str>>(
+        &self,
+        name: S,
+    ) -> spi::Result<Option<T>> {
+        self.get(self.column_ordinal(name)?)
+    }
+}
+
+/// A `DestReceiver` that calls a closure with each row it receives.  The closure returns whether
+/// it wants more rows:  returning `false` stops the query's execution early.
+///
+/// The closure runs in a memory context that's reset after each row, so anything it allocates
+/// with `palloc()` doesn't accumulate over a large result set.
+pub struct RowReceiver<'f> {
+    // boxed, so the `DestReceiver` handed to Postgres doesn't move
+    state: Box<ReceiverState<'f>>,
+}
+
+#[repr(C)]
+struct ReceiverState<'f> {
+    // must be the first field, so a pointer to it is a pointer to the whole state
+    dest: pg_sys::DestReceiver,
+    tupdesc: pg_sys::TupleDesc,
+    rows: u64,
+    stopped: bool,
+    row_context: PgMemoryContexts,
+    on_row: Box<dyn FnMut(&ReceivedRow) -> bool + 'f>,
+}
+
+impl<'f> RowReceiver<'f> {
+    /// A receiver that calls `on_row` with each row
+    pub fn new<F: FnMut(&ReceivedRow) -> bool + 'f>(on_row: F) -> Self {
+        let dest = pg_sys::DestReceiver {
+            receiveSlot: Some(receive_slot),
+            rStartup: Some(startup),
+            rShutdown: Some(shutdown),
+            rDestroy: Some(shutdown),
+            mydest: pg_sys::CommandDest_DestNone,
+        };
+        RowReceiver {
+            state: Box::new(ReceiverState {
+                dest,
+                tupdesc: std::ptr::null_mut(),
+                rows: 0,
+                stopped: false,
+                row_context: PgMemoryContexts::new("RowReceiver"),
+                on_row: Box::new(on_row),
+            }),
+        }
+    }
+
+    /// The `DestReceiver` to give to Postgres.  It belongs to this `RowReceiver`, so Postgres
+    /// must be done with it before the `RowReceiver` is dropped
+    pub fn as_dest_receiver(&mut self) -> *mut pg_sys::DestReceiver {
+        &mut self.state.dest
+    }
+
+    /// How many rows were received by the most recent query
+    pub fn rows(&self) -> u64 {
+        self.state.rows
+    }
+
+    /// Did the closure stop the most recent query by returning `false`?
+    pub fn stopped(&self) -> bool {
+        self.state.stopped
+    }
+}
+
+unsafe fn state<'a>(dest: *mut pg_sys::DestReceiver) -> &'a mut ReceiverState<'a> {
+    // SAFETY:  the only DestReceivers with our callbacks are the first field of a ReceiverState
+    &mut *dest.cast()
+}
+
+#[pg_guard]
+unsafe extern "C" fn startup(
+    dest: *mut pg_sys::DestReceiver,
+    _operation: std::os::raw::c_int,
+    tupdesc: pg_sys::TupleDesc,
+) {
+    let state = state(dest);
+    state.tupdesc = tupdesc;
+    state.rows = 0;
+    state.stopped = false;
+}
+
+#[pg_guard]
+unsafe extern "C" fn receive_slot(
+    slot: *mut pg_sys::TupleTableSlot,
+    dest: *mut pg_sys::DestReceiver,
+) -> bool {
+    let state = state(dest);
+    let row = ReceivedRow { slot, tupdesc: state.tupdesc, __marker: PhantomData };
+    let on_row = &mut state.on_row;
+    let more = state.row_context.switch_to(|_| on_row(&row));
+    state.row_context.reset();
+    state.rows += 1;
+    state.stopped = !more;
+    more
+}
+
+#[pg_guard]
+unsafe extern "C" fn shutdown(_dest: *mut pg_sys::DestReceiver) {
+    // the receiver belongs to Rust, and is freed when it's dropped
+}
@@ -0,0 +1,369 @@
+/*
+Portions Copyright 2019-2021 ZomboDB, LLC.
+Portions Copyright 2021-2022 Technology Concepts & Design, Inc. <support@tcdi.com>
+
+All rights reserved.
+
+Use of this source code is governed by the MIT license that can be found in the LICENSE file.
+*/
+
+//! Dynamic shared memory, created at runtime rather than when Postgres starts
+//!
+//! A [`DsmSegment`] is a single block of memory of a fixed size.  A [`DsaArea`] is an allocator
+//! over as many segments as it needs, handing out [`DsaPointer`]s that are meaningful in every
+//! process attached to the area, unlike addresses.
+//!
+//! Either is shared by passing its handle to another backend, which attaches to it, typically
+//! through a [`PgLwLock`](crate::PgLwLock)-protected value in fixed shared memory or a background
+//! worker's `bgw_extra`.  Both are detached when dropped, not at the end of the transaction.
+//!
+//! ## Examples
+//!
+//! ```rust,no_run
+//! use pgrx::dsm::{DsaArea, DsaPointer};
+//! use pgrx::pg_sys;
+//!
+//! // in one backend
+//! fn publish(values: &[i64]) -> (pg_sys::dsa_handle, DsaPointer<[i64]>) {
+//!     let area = DsaArea::create();
+//!     let values = area.allocate_slice(values);
+//!     // keep the area alive after we detach from it, until it's unpinned
+//!     area.pin();
+//!     (area.handle(), values)
+//! }
+//!
+//! // in another
+//! fn sum(handle: pg_sys::dsa_handle, values: DsaPointer<[i64]>) -> i64 {
+//!     let area = DsaArea::attach(handle);
+//!     let sum = unsafe { area.get_slice(values) }.iter().sum();
+//!     unsafe { area.free(values) };
+//!     area.unpin();
+//!     sum
+//! }
+//! ```
+use crate::{pg_sys, PGRXSharedMemory, PgMemoryContexts};
+use std::marker::PhantomData;
+
+/// A dynamic shared memory segment, which this process is attached to until it's dropped
+pub struct DsmSegment {
+    seg: *mut pg_sys::dsm_segment,
+}
+
+impl DsmSegment {
+    /// Create a new segment of `size` bytes, and attach to it
+    ///
+    /// # Errors
+    ///
+    /// Raises a Postgres `ERROR` if the segment can't be created, as when `max_connections`
+    /// limits how many may exist
+    pub fn create(size: usize) -> Self {
+        unsafe { Self::attached(pg_sys::dsm_create(size, 0)) }
+    }
+
+    /// Attach to the segment with the specified handle, if it still exists.  A process may only
+    /// attach to a segment once at a time
+    pub fn attach(handle: pg_sys::dsm_handle) -> Option<Self> {
+        unsafe {
+            let seg = pg_sys::dsm_attach(handle);
+            (!seg.is_null()).then(|| Self::attached(seg))
+        }
+    }
+
+    unsafe fn attached(seg: *mut pg_sys::dsm_segment) -> Self {
+        // we detach when dropped, rather than leave it to the current resource owner
+        pg_sys::dsm_pin_mapping(seg);
+        DsmSegment { seg }
+    }
+
+    /// The handle other processes attach to the segment with
+    pub fn handle(&self) -> pg_sys::dsm_handle {
+        unsafe { pg_sys::dsm_segment_handle(self.seg) }
+    }
+
+    /// The size of the segment in bytes
+    pub fn len(&self) -> usize {
+        unsafe { pg_sys::dsm_segment_map_length(self.seg) }
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    /// The address the segment is mapped at in this process
+    pub fn as_ptr(&self) -> *mut u8 {
+        unsafe { pg_sys::dsm_segment_address(self.seg).cast() }
+    }
+
+    /// The contents of the segment
+    ///
+    /// # Safety
+    ///
+    /// No other process may write to the segment while the slice is in use
+    pub unsafe fn as_slice(&self) -> &[u8] {
+        std::slice::from_raw_parts(self.as_ptr(), self.len())
+    }
+
+    /// The contents of the segment, to write to
+    ///
+    /// # Safety
+    ///
+    /// No other process may read or write the segment while the slice is in use
+    pub unsafe fn as_mut_slice(&mut self) -> &mut [u8] {
+        std::slice::from_raw_parts_mut(self.as_ptr(), self.len())
+    }
+
+    /// Keep the segment in existence after every process has detached from it, until
+    /// [`DsmSegment::unpin()`] or the server restarts.  A segment can only be pinned once
+    pub fn pin(&self) {
+        unsafe { pg_sys::dsm_pin_segment(self.seg) }
+    }
+
+    /// Let the pinned segment with the specified handle be destroyed once every process has
+    /// detached from it
+    pub fn unpin(handle: pg_sys::dsm_handle) {
+        unsafe { pg_sys::dsm_unpin_segment(handle) }
+    }
+}
+
+impl Drop for DsmSegment {
+    fn drop(&mut self) {
+        unsafe { pg_sys::dsm_detach(self.seg) }
+    }
+}
+
+/// A pointer to a `T` allocated in a [`DsaArea`].  Unlike an address, it means the same thing in
+/// every process attached to the area, so it may itself be put in shared memory
+pub struct DsaPointer<T: ?Sized> {
+    dp: pg_sys::dsa_pointer,
+    len: usize,
+    __marker: PhantomData<fn() -> *const T>,
+}
+
+impl<T: ?Sized> DsaPointer<T> {
+    /// The raw `dsa_pointer`
+    pub fn as_raw(&self) -> pg_sys::dsa_pointer {
+        self.dp
+    }
+
+    /// The number of elements, for a pointer to a slice, or `1`
+    pub fn len(&self) -> usize {
+        self.len
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len == 0
+    }
+}
+
+impl<T> DsaPointer<T> {
+    /// A `DsaPointer` from a raw `dsa_pointer`
+    ///
+    /// # Safety
+    ///
+    /// `dp` must have been allocated for a `T`
+    pub unsafe fn from_raw(dp: pg_sys::dsa_pointer) -> Self {
+        DsaPointer { dp, len: 1, __marker: PhantomData }
+    }
+}
+
+impl<T> DsaPointer<[T]> {
+    /// A `DsaPointer` from a raw `dsa_pointer` to `len` `T`s
+    ///
+    /// # Safety
+    ///
+    /// `dp` must have been allocated for `len` `T`s
+    pub unsafe fn from_raw_parts(dp: pg_sys::dsa_pointer, len: usize) -> Self {
+        DsaPointer { dp, len, __marker: PhantomData }
+    }
+}
+
+impl<T: ?Sized> Copy for DsaPointer<T> {}
+
+impl<T: ?Sized> Clone for DsaPointer<T> {
+    fn clone(&self) -> Self {
+        *self
+    }
+}
+
+impl<T: ?Sized> std::fmt::Debug for DsaPointer<T> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("DsaPointer").field("dp", &self.dp).field("len", &self.len).finish()
+    }
+}
+
+impl<T: ?Sized> PartialEq for DsaPointer<T> {
+    fn eq(&self, other: &Self) -> bool {
+        self.dp == other.dp && self.len == other.len
+    }
+}
+
+impl<T: ?Sized> Eq for DsaPointer<T> {}
+
+unsafe impl<T: ?Sized> PGRXSharedMemory for DsaPointer<T> {}
+
+/// A dynamic shared memory area, which this process is attached to until it's dropped
+pub struct DsaArea {
+    area: *mut pg_sys::dsa_area,
+}
+
+impl DsaArea {
+    /// Create a new area, and attach to it
+    pub fn create() -> Self {
+        unsafe {
+            let tranche_id = pg_sys::LWLockNewTrancheId();
+            pg_sys::LWLockRegisterTranche(tranche_id, b"pgrx_dsa\0".as_ptr().cast());
+            // the area lives until it's dropped, not until the end of the current transaction
+            let area =
+                PgMemoryContexts::TopMemoryContext.switch_to(|_| pg_sys::dsa_create(tranche_id));
+            Self::attached(area)
+        }
+    }
+
+    /// Attach to the area with the specified handle.  A process may only attach to an area once
+    /// at a time
+    ///
+    /// # Errors
+    ///
+    /// Raises a Postgres `ERROR` if the area no longer exists
+    pub fn attach(handle: pg_sys::dsa_handle) -> Self {
+        unsafe {
+            let area = PgMemoryContexts::TopMemoryContext.switch_to(|_| pg_sys::dsa_attach(handle));
+            Self::attached(area)
+        }
+    }
+
+    unsafe fn attached(area: *mut pg_sys::dsa_area) -> Self {
+        // we detach when dropped, rather than leave it to the current resource owner
+        pg_sys::dsa_pin_mapping(area);
+        DsaArea { area }
+    }
+
+    /// The handle other processes attach to the area with
+    pub fn handle(&self) -> pg_sys::dsa_handle {
+        unsafe { pg_sys::dsa_get_handle(self.area) }
+    }
+
+    /// Keep the area in existence after every process has detached from it, until it's unpinned.
+    /// An area can only be pinned once
+    pub fn pin(&self) {
+        unsafe { pg_sys::dsa_pin(self.area) }
+    }
+
+    /// Let the pinned area be destroyed once every process has detached from it
+    pub fn unpin(&self) {
+        unsafe { pg_sys::dsa_unpin(self.area) }
+    }
+
+    /// Limit the total size of the segments the area may use, in bytes
+    pub fn set_size_limit(&self, limit: usize) {
+        unsafe { pg_sys::dsa_set_size_limit(self.area, limit) }
+    }
+
+    /// Move `value` into the area
+    ///
+    /// # Panics
+    ///
+    /// Panics if `T` needs to be aligned more strictly than Postgres aligns its allocations
+    ///
+    /// # Errors
+    ///
+    /// Raises a Postgres `ERROR` if the area is out of memory
+    pub fn allocate<T: PGRXSharedMemory>(&self, value: T) -> DsaPointer<T> {
+        unsafe {
+            let dp = self.allocate_raw::<T>(1);
+            (self.address(dp) as *mut T).write(value);
+            DsaPointer::from_raw(dp)
+        }
+    }
+
+    /// Copy `values` into the area
+    ///
+    /// # Panics
+    ///
+    /// Panics if `T` needs to be aligned more strictly than Postgres aligns its allocations
+    ///
+    /// # Errors
+    ///
+    /// Raises a Postgres `ERROR` if the area is out of memory
+    pub fn allocate_slice<T: PGRXSharedMemory + Copy>(&self, values: &[T]) -> DsaPointer<[T]> {
+        unsafe {
+            let dp = self.allocate_raw::<T>(values.len());
+            let ptr = self.address(dp) as *mut T;
+            ptr.copy_from_nonoverlapping(values.as_ptr(), values.len());
+            DsaPointer::from_raw_parts(dp, values.len())
+        }
+    }
+
+    unsafe fn allocate_raw<T>(&self, len: usize) -> pg_sys::dsa_pointer {
+        assert!(
+            std::mem::align_of::<T>() <= pg_sys::MAXIMUM_ALIGNOF as usize,
+            "type is too strictly aligned for dynamic shared memory"
+        );
+        let size = std::mem::size_of::<T>().checked_mul(len).expect("allocation size overflow");
+        // Postgres refuses zero-sized allocations
+        pg_sys::dsa_allocate_extended(self.area, size.max(1), pg_sys::DSA_ALLOC_HUGE as _)
+    }
+
+    fn address(&self, dp: pg_sys::dsa_pointer) -> *mut u8 {
+        unsafe { pg_sys::dsa_get_address(self.area, dp).cast() }
+    }
+
+    /// The address of `ptr`'s value in this process
+    pub fn as_ptr<T>(&self, ptr: DsaPointer<T>) -> *mut T {
+        self.address(ptr.dp).cast()
+    }
+
+    /// The value `ptr` points to
+    ///
+    /// # Safety
+    ///
+    /// `ptr` must have been allocated in this area and not freed, and no other process may write
+    /// to it while the reference is in use
+    pub unsafe fn get<T>(&self, ptr: DsaPointer<T>) -> &T {
+        &*self.as_ptr(ptr)
+    }
+
+    /// The value `ptr` points to, to write to
+    ///
+    /// # Safety
+    ///
+    /// `ptr` must have been allocated in this area and not freed, and no other process may read or
+    /// write it while the reference is in use
+    pub unsafe fn get_mut<T>(&self, ptr: DsaPointer<T>) -> &mut T {
+        &mut *self.as_ptr(ptr)
+    }
+
+    /// The values `ptr` points to
+    ///
+    /// # Safety
+    ///
+    /// As for [`DsaArea::get()`]
+    pub unsafe fn get_slice<T>(&self, ptr: DsaPointer<[T]>) -> &[T] {
+        std::slice::from_raw_parts(self.address(ptr.dp).cast(), ptr.len)
+    }
+
+    /// The values `ptr` points to, to write to
+    ///
+    /// # Safety
+    ///
+    /// As for [`DsaArea::get_mut()`]
+    pub unsafe fn get_slice_mut<T>(&self, ptr: DsaPointer<[T]>) -> &mut [T] {
+        std::slice::from_raw_parts_mut(self.address(ptr.dp).cast(), ptr.len)
+    }
+
+    /// Return the memory `ptr` points to to the area, without dropping its value
+    ///
+    /// # Safety
+    ///
+    /// `ptr` must have been allocated in this area and not freed, and mustn't be used again by
+    /// any process
+    pub unsafe fn free<T: ?Sized>(&self, ptr: DsaPointer<T>) {
+        pg_sys::dsa_free(self.area, ptr.dp)
+    }
+}
+
+impl Drop for DsaArea {
+    fn drop(&mut self) {
+        unsafe { pg_sys::dsa_detach(self.area) }
+    }
+}
@@ -0,0 +1,248 @@
+/*
+Portions Copyright 2019-2021 ZomboDB, LLC.
+Portions Copyright 2021-2022 Technology Concepts & Design, Inc. <support@tcdi.com>
+
+All rights reserved.
+
+Use of this source code is governed by the MIT license that can be found in the LICENSE file.
+*/
+
+//! Event triggers, which run on DDL commands rather than on changes to rows
+//!
+//! Declare a function taking an [`EventTriggerData`] with
+//! [`#[pg_event_trigger]`](macro@crate::pg_event_trigger), which generates its `RETURNS
+//! event_trigger` SQL function, and, given an `event`, the `CREATE EVENT TRIGGER` statement that
+//! fires it:
+//!
+//! ```rust,no_run
+//! use pgrx::event_trigger::EventTriggerData;
+//! use pgrx::prelude::*;
+//!
+//! #[pg_event_trigger(event = "ddl_command_end", tags = ["CREATE TABLE", "ALTER TABLE"])]
+//! fn log_table_changes(event: &EventTriggerData) {
+//!     for command in event.ddl_commands().unwrap() {
+//!         notice!("{} {}", command.command_tag, command.object_identity);
+//!     }
+//! }
+//! ```
+//!
+//! Without an `event`, only the function is created, to be used in a `CREATE EVENT TRIGGER` of
+//! the extension's users' own.
+use crate::prelude::*;
+use crate::{is_a, spi};
+use std::ffi::CStr;
+
+/// The event an event trigger fired on
+#[derive(Debug, Copy, Clone, Eq, PartialEq)]
+pub enum EventTriggerEvent {
+    /// Before a DDL command runs
+    DdlCommandStart,
+    /// After a DDL command ran
+    DdlCommandEnd,
+    /// After a DDL command dropped objects, just before `ddl_command_end`
+    SqlDrop,
+    /// Before a table is rewritten by `ALTER TABLE` or `ALTER TYPE`
+    TableRewrite,
+}
+
+impl EventTriggerEvent {
+    /// The event's name, as `CREATE EVENT TRIGGER` knows it
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            EventTriggerEvent::DdlCommandStart => "ddl_command_start",
+            EventTriggerEvent::DdlCommandEnd => "ddl_command_end",
+            EventTriggerEvent::SqlDrop => "sql_drop",
+            EventTriggerEvent::TableRewrite => "table_rewrite",
+        }
+    }
+
+    fn from_str(name: &str) -> Option<Self> {
+        match name {
+            "ddl_command_start" => Some(EventTriggerEvent::DdlCommandStart),
+            "ddl_command_end" => Some(EventTriggerEvent::DdlCommandEnd),
+            "sql_drop" => Some(EventTriggerEvent::SqlDrop),
+            "table_rewrite" => Some(EventTriggerEvent::TableRewrite),
+            _ => None,
+        }
+    }
+}
+
+/// What an event trigger was fired for
+pub struct EventTriggerData {
+    data: *mut pg_sys::EventTriggerData,
+}
+
+impl EventTriggerData {
+    /// Wrap the `EventTriggerData` Postgres passes to an event trigger function
+    ///
+    /// # Safety
+    ///
+    /// `data` must be a valid `EventTriggerData` for the event trigger currently running
+    pub unsafe fn from_pg(data: *mut pg_sys::EventTriggerData) -> Self {
+        EventTriggerData { data }
+    }
+
+    /// The underlying `EventTriggerData`
+    pub fn as_ptr(&self) -> *mut pg_sys::EventTriggerData {
+        self.data
+    }
+
+    /// The event the trigger fired on
+    pub fn event(&self) -> EventTriggerEvent {
+        let name = unsafe { CStr::from_ptr((*self.data).event) }.to_string_lossy();
+        EventTriggerEvent::from_str(&name)
+            .unwrap_or_else(|| panic!("unrecognized event trigger event `{}`", name))
+    }
+
+    /// The command tag of the command that fired the trigger, like `CREATE TABLE`
+    pub fn tag(&self) -> String {
+        unsafe {
+            #[cfg(any(feature = "pg11", feature = "pg12"))]
+            let tag = (*self.data).tag;
+            #[cfg(any(feature = "pg13", feature = "pg14", feature = "pg15"))]
+            let tag = pg_sys::GetCommandTagName((*self.data).tag);
+            CStr::from_ptr(tag).to_string_lossy().into_owned()
+        }
+    }
+
+    /// The parse tree of the command that fired the trigger
+    pub fn parsetree(&self) -> *mut pg_sys::Node {
+        unsafe { (*self.data).parsetree }
+    }
+
+    /// The commands a `ddl_command_end` trigger fired for, from `pg_event_trigger_ddl_commands()`.
+    /// Raises an error for other events
+    pub fn ddl_commands(&self) -> spi::Result<Vec<DdlCommand>> {
+        Spi::connect(|client| {
+            client
+                .select(
+                    "SELECT classid, objid, objsubid, command_tag, object_type, schema_name, \
+                     object_identity, in_extension FROM pg_event_trigger_ddl_commands()",
+                    None,
+                    None,
+                )?
+                .map(|row| {
+                    Ok(DdlCommand {
+                        classid: row.get(1)?.unwrap_or(pg_sys::InvalidOid),
+                        objid: row.get(2)?.unwrap_or(pg_sys::InvalidOid),
+                        objsubid: row.get(3)?.unwrap_or_default(),
+                        command_tag: row.get(4)?.unwrap_or_default(),
+                        object_type: row.get(5)?.unwrap_or_default(),
+                        schema_name: row.get(6)?,
+                        object_identity: row.get(7)?.unwrap_or_default(),
+                        in_extension: row.get(8)?.unwrap_or_default(),
+                    })
+                })
+                .collect()
+        })
+    }
+
+    /// The objects a `sql_drop` trigger fired for, from `pg_event_trigger_dropped_objects()`.
+    /// Raises an error for other events
+    pub fn dropped_objects(&self) -> spi::Result<Vec<DroppedObject>> {
+        Spi::connect(|client| {
+            client
+                .select(
+                    "SELECT classid, objid, objsubid, original, normal, is_temporary, \
+                     object_type, schema_name, object_name, object_identity \
+                     FROM pg_event_trigger_dropped_objects()",
+                    None,
+                    None,
+                )?
+                .map(|row| {
+                    Ok(DroppedObject {
+                        classid: row.get(1)?.unwrap_or(pg_sys::InvalidOid),
+                        objid: row.get(2)?.unwrap_or(pg_sys::InvalidOid),
+                        objsubid: row.get(3)?.unwrap_or_default(),
+                        original: row.get(4)?.unwrap_or_default(),
+                        normal: row.get(5)?.unwrap_or_default(),
+                        is_temporary: row.get(6)?.unwrap_or_default(),
+                        object_type: row.get(7)?.unwrap_or_default(),
+                        schema_name: row.get(8)?,
+                        object_name: row.get(9)?,
+                        object_identity: row.get(10)?.unwrap_or_default(),
+                    })
+                })
+                .collect()
+        })
+    }
+
+    /// The oid of the table a `table_rewrite` trigger fired for, and the reason it's being
+    /// rewritten, from `pg_event_trigger_table_rewrite_oid()` and
+    /// `pg_event_trigger_table_rewrite_reason()`.  Raises an error for other events
+    pub fn table_rewrite(&self) -> spi::Result<(pg_sys::Oid, i32)> {
+        Spi::connect(|client| {
+            let row = client
+                .select(
+                    "SELECT pg_event_trigger_table_rewrite_oid(), \
+                     pg_event_trigger_table_rewrite_reason()",
+                    None,
+                    None,
+                )?
+                .first();
+            Ok((row.get(1)?.unwrap_or(pg_sys::InvalidOid), row.get(2)?.unwrap_or_default()))
+        })
+    }
+}
+
+/// A command reported by [`EventTriggerData::ddl_commands()`]
+#[derive(Debug, Clone, Eq, PartialEq)]
+pub struct DdlCommand {
+    /// The oid of the catalog the object is in
+    pub classid: pg_sys::Oid,
+    /// The oid of the object
+    pub objid: pg_sys::Oid,
+    /// The column number, for a column
+    pub objsubid: i32,
+    pub command_tag: String,
+    pub object_type: String,
+    /// The schema the object is in, if it's in one
+    pub schema_name: Option<String>,
+    /// A schema-qualified text rendering of the object's identity
+    pub object_identity: String,
+    /// Whether the command is part of an extension's script
+    pub in_extension: bool,
+}
+
+/// An object reported by [`EventTriggerData::dropped_objects()`]
+#[derive(Debug, Clone, Eq, PartialEq)]
+pub struct DroppedObject {
+    /// The oid of the catalog the object was in
+    pub classid: pg_sys::Oid,
+    /// The oid of the object
+    pub objid: pg_sys::Oid,
+    /// The column number, for a column
+    pub objsubid: i32,
+    /// Whether the object was one of those the `DROP` named
+    pub original: bool,
+    /// Whether the object was dropped through a normal dependency on one of those
+    pub normal: bool,
+    /// Whether the object was temporary
+    pub is_temporary: bool,
+    pub object_type: String,
+    /// The schema the object was in, if it was in one
+    pub schema_name: Option<String>,
+    /// The object's name, if it had a unique one
+    pub object_name: Option<String>,
+    /// A schema-qualified text rendering of the object's identity
+    pub object_identity: String,
+}
+
+/// Run `f` for the event trigger function `name`, whose `fcinfo` Postgres passed
+#[doc(hidden)]
+pub unsafe fn call_event_trigger(
+    fcinfo: pg_sys::FunctionCallInfo,
+    name: &str,
+    f: fn(&EventTriggerData),
+) -> pg_sys::Datum {
+    let context = (*fcinfo).context;
+    if !is_a(context, pg_sys::NodeTag_T_EventTriggerData) {
+        ereport!(
+            PgLogLevel::ERROR,
+            PgSqlErrorCode::ERRCODE_E_R_I_E_TRIGGER_PROTOCOL_VIOLATED,
+            format!("function \"{}\" was not called by event trigger manager", name)
+        );
+    }
+    f(&EventTriggerData::from_pg(context.cast()));
+    pg_sys::Datum::from(0)
+}
@@ -0,0 +1,277 @@
+/*
+Portions Copyright 2019-2021 ZomboDB, LLC.
+Portions Copyright 2021-2022 Technology Concepts & Design, Inc. <support@tcdi.com>
+
+All rights reserved.
+
+Use of this source code is governed by the MIT license that can be found in the LICENSE file.
+*/
+
+//! Adding extension-specific output to `EXPLAIN`
+//!
+//! [`Explain`] wraps Postgres' `ExplainState` and its `ExplainProperty*()` functions, which emit
+//! a labeled value in whichever of `EXPLAIN`'s output formats was asked for.  Custom scan
+//! providers are handed an `ExplainState` directly.  Anything else, like an extension that
+//! rewrites queries from a hook, can [`register_explain_annotator()`] to add its own output after
+//! each query's plan.
+//!
+//! Postgres versions up to 15 have no way for an extension to add its own `EXPLAIN` options, so
+//! annotators should use [`Explain::verbose()`] and friends to decide how much to say.
+//!
+//! ## Examples
+//!
+//! ```rust,no_run
+//! use pgrx::explain::{register_explain_annotator, Explain};
+//! use pgrx::prelude::*;
+//!
+//! static mut QUERIES_REWRITTEN: i64 = 0;
+//!
+//! fn annotate(explain: &mut Explain, _query: &pg_sys::Query) {
+//!     explain.property_integer("Rewritten Queries", None, unsafe { QUERIES_REWRITTEN });
+//! }
+//!
+//! #[pg_guard]
+//! pub extern "C" fn _PG_init() {
+//!     register_explain_annotator("my_extension", annotate);
+//! }
+//! ```
+use crate as pgrx; // for #[pg_guard] support from within ourself
+use crate::prelude::*;
+use pg_sys::AsPgCStr;
+use std::cell::RefCell;
+
+/// The output format `EXPLAIN` was asked for
+#[derive(Debug, Copy, Clone, Eq, PartialEq)]
+pub enum ExplainFormat {
+    Text,
+    Xml,
+    Json,
+    Yaml,
+}
+
+/// An `EXPLAIN` in progress
+pub struct Explain {
+    es: *mut pg_sys::ExplainState,
+}
+
+impl Explain {
+    /// Wrap an `ExplainState` provided by Postgres
+    ///
+    /// # Safety
+    ///
+    /// `es` must be a valid `ExplainState` that's currently being used to produce output
+    pub unsafe fn from_pg(es: *mut pg_sys::ExplainState) -> Self {
+        assert!(!es.is_null(), "ExplainState is NULL");
+        Explain { es }
+    }
+
+    /// The underlying `ExplainState`
+    pub fn as_ptr(&self) -> *mut pg_sys::ExplainState {
+        self.es
+    }
+
+    fn state(&self) -> &pg_sys::ExplainState {
+        unsafe { &*self.es }
+    }
+
+    /// The output format
+    pub fn format(&self) -> ExplainFormat {
+        match self.state().format {
+            pg_sys::ExplainFormat_EXPLAIN_FORMAT_XML => ExplainFormat::Xml,
+            pg_sys::ExplainFormat_EXPLAIN_FORMAT_JSON => ExplainFormat::Json,
+            pg_sys::ExplainFormat_EXPLAIN_FORMAT_YAML => ExplainFormat::Yaml,
+            _ => ExplainFormat::Text,
+        }
+    }
+
+    /// Was `ANALYZE` given, so the query was actually executed?
+    pub fn analyze(&self) -> bool {
+        self.state().analyze
+    }
+
+    /// Was `VERBOSE` given?
+    pub fn verbose(&self) -> bool {
+        self.state().verbose
+    }
+
+    /// Are costs to be printed?  On by default
+    pub fn costs(&self) -> bool {
+        self.state().costs
+    }
+
+    /// Was `BUFFERS` given?
+    pub fn buffers(&self) -> bool {
+        self.state().buffers
+    }
+
+    /// Are timings to be printed?  Only meaningful with `ANALYZE`
+    pub fn timing(&self) -> bool {
+        self.state().timing
+    }
+
+    /// Is the summary, with planning and execution times, to be printed?
+    pub fn summary(&self) -> bool {
+        self.state().summary
+    }
+
+    /// Emit a text property, like `Label: value`
+    pub fn property_text(&mut self, label: &str, value: &str) {
+        unsafe {
+            pg_sys::ExplainPropertyText(label.as_pg_cstr(), value.as_pg_cstr(), self.es);
+        }
+    }
+
+    /// Emit an integer property, with an optional unit that only appears in text format
+    pub fn property_integer(&mut self, label: &str, unit: Option<&str>, value: i64) {
+        unsafe {
+            pg_sys::ExplainPropertyInteger(label.as_pg_cstr(), unit_cstr(unit), value, self.es);
+        }
+    }
+
+    /// Emit a floating-point property with `ndigits` digits after the decimal point, and an
+    /// optional unit that only appears in text format
+    pub fn property_float(&mut self, label: &str, unit: Option<&str>, value: f64, ndigits: i32) {
+        unsafe {
+            pg_sys::ExplainPropertyFloat(
+                label.as_pg_cstr(),
+                unit_cstr(unit),
+                value,
+                ndigits,
+                self.es,
+            );
+        }
+    }
+
+    /// Emit a boolean property
+    pub fn property_bool(&mut self, label: &str, value: bool) {
+        unsafe {
+            pg_sys::ExplainPropertyBool(label.as_pg_cstr(), value, self.es);
+        }
+    }
+
+    /// Emit the properties `f` emits nested under `label`.  In text format the group itself is
+    /// invisible
+    pub fn group<R>(&mut self, label: &str, f: impl FnOnce(&mut Self) -> R) -> R {
+        unsafe {
+            pg_sys::ExplainOpenGroup(label.as_pg_cstr(), label.as_pg_cstr(), true, self.es);
+        }
+        let result = f(self);
+        unsafe {
+            pg_sys::ExplainCloseGroup(label.as_pg_cstr(), label.as_pg_cstr(), true, self.es);
+        }
+        result
+    }
+}
+
+fn unit_cstr(unit: Option<&str>) -> *const std::os::raw::c_char {
+    unit.map(|unit| unit.as_pg_cstr() as *const _).unwrap_or(std::ptr::null())
+}
+
+/// A function that adds output to `EXPLAIN`, after the plan of each query
+pub type ExplainAnnotator = fn(explain: &mut Explain, query: &pg_sys::Query);
+
+thread_local! {
+    static ANNOTATORS: RefCell<Vec<(&'static str, ExplainAnnotator)>> = const { RefCell::new(Vec::new()) };
+}
+static mut PREV_EXPLAIN_ONE_QUERY_HOOK: pg_sys::ExplainOneQuery_hook_type = None;
+
+/// Call `annotator` whenever a query is `EXPLAIN`ed, after its plan has been printed, so it can
+/// emit properties describing what the extension did to or for that query.
+///
+/// In the structured formats, each annotator's output appears in its own group labeled with
+/// `name`, inside an `Extensions` element that follows the plan.  This installs an `ExplainOneQuery_hook` and should be called from `_PG_init()`.
+pub fn register_explain_annotator(name: &'static str, annotator: ExplainAnnotator) {
+    let first = ANNOTATORS.with(|annotators| {
+        let mut annotators = annotators.borrow_mut();
+        annotators.push((name, annotator));
+        annotators.len() == 1
+    });
+    if first {
+        unsafe {
+            // SAFETY:  only the first registration writes `PREV_EXPLAIN_ONE_QUERY_HOOK`, and it
+            // does so before installing the hook that reads it
+            PREV_EXPLAIN_ONE_QUERY_HOOK = pg_sys::ExplainOneQuery_hook;
+            pg_sys::ExplainOneQuery_hook = Some(pgrx_explain_one_query);
+        }
+    }
+}
+
+#[pg_guard]
+unsafe extern "C" fn pgrx_explain_one_query(
+    query: *mut pg_sys::Query,
+    cursor_options: i32,
+    into: *mut pg_sys::IntoClause,
+    es: *mut pg_sys::ExplainState,
+    query_string: *const std::os::raw::c_char,
+    params: pg_sys::ParamListInfo,
+    query_env: *mut pg_sys::QueryEnvironment,
+) {
+    match PREV_EXPLAIN_ONE_QUERY_HOOK {
+        Some(prev) => prev(query, cursor_options, into, es, query_string, params, query_env),
+        None => standard_explain_one_query(
+            query,
+            cursor_options,
+            into,
+            es,
+            query_string,
+            params,
+            query_env,
+        ),
+    }
+
+    // `ExplainOnePlan()` has closed the query's group, so in the structured formats our output
+    // becomes another element of the top-level list, after the plan
+    pg_sys::ExplainOpenGroup("Extensions".as_pg_cstr(), std::ptr::null(), false, es);
+    let mut explain = Explain::from_pg(es);
+    // copy the list, so annotators are free to register other annotators
+    let annotators = ANNOTATORS.with(|annotators| annotators.borrow().clone());
+    for (name, annotator) in annotators {
+        explain.group(name, |explain| annotator(explain, &*query));
+    }
+    pg_sys::ExplainCloseGroup("Extensions".as_pg_cstr(), std::ptr::null(), false, es);
+}
+
+/// What `ExplainOneQuery()` does when there's no hook:  plan the query and explain the plan
+unsafe fn standard_explain_one_query(
+    query: *mut pg_sys::Query,
+    cursor_options: i32,
+    into: *mut pg_sys::IntoClause,
+    es: *mut pg_sys::ExplainState,
+    query_string: *const std::os::raw::c_char,
+    params: pg_sys::ParamListInfo,
+    query_env: *mut pg_sys::QueryEnvironment,
+) {
+    #[cfg(not(any(feature = "pg11", feature = "pg12")))]
+    let bufusage_start = pg_sys::pgBufferUsage;
+    let start = std::time::Instant::now();
+
+    let plan = crate::compat::plan_query(query, query_string, cursor_options, params);
+
+    let elapsed = start.elapsed();
+    let planduration =
+        pg_sys::instr_time { tv_sec: elapsed.as_secs() as _, tv_nsec: elapsed.subsec_nanos() as _ };
+
+    #[cfg(any(feature = "pg11", feature = "pg12"))]
+    pg_sys::ExplainOnePlan(plan, into, es, query_string, params, query_env, &planduration);
+
+    #[cfg(not(any(feature = "pg11", feature = "pg12")))]
+    {
+        let mut bufusage = pg_sys::BufferUsage::default();
+        pg_sys::BufferUsageAccumDiff(
+            &mut bufusage,
+            std::ptr::addr_of!(pg_sys::pgBufferUsage),
+            &bufusage_start,
+        );
+        let bufusage = if (*es).buffers { &bufusage as *const _ } else { std::ptr::null() };
+        pg_sys::ExplainOnePlan(
+            plan,
+            into,
+            es,
+            query_string,
+            params,
+            query_env,
+            &planduration,
+            bufusage,
+        );
+    }
+}
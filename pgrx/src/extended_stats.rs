@@ -0,0 +1,289 @@
+/*
+Portions Copyright 2019-2021 ZomboDB, LLC.
+Portions Copyright 2021-2022 Technology Concepts & Design, Inc. <support@tcdi.com>
+
+All rights reserved.
+
+Use of this source code is governed by the MIT license that can be found in the LICENSE file.
+*/
+
+//! Create extended statistics objects and read back what `ANALYZE` computed for them
+//!
+//! Extended statistics (see `CREATE STATISTICS`) teach the planner about correlations between
+//! columns of a table.  [`ExtendedStatistics`] creates them, and [`read_extended_statistics()`]
+//! decodes the contents of one after the table has been analyzed.
+//!
+//! ## Examples
+//!
+//! ```rust,no_run
+//! use pgrx::extended_stats::{read_extended_statistics, ExtendedStatistics, StatisticsKind};
+//! use pgrx::prelude::*;
+//!
+//! let stxoid = ExtendedStatistics::new("public.city_zip", "public.addresses")
+//!     .column("city")
+//!     .column("zip")
+//!     .kind(StatisticsKind::Dependencies)
+//!     .create()
+//!     .unwrap();
+//!
+//! Spi::run("ANALYZE public.addresses").unwrap();
+//! let contents = read_extended_statistics(stxoid).unwrap();
+//! for dependency in contents.dependencies.unwrap_or_default() {
+//!     info!("{:?} => {} ({})", dependency.determinants, dependency.dependent, dependency.degree);
+//! }
+//! ```
+use crate::prelude::*;
+use crate::spi::{self, quote_identifier};
+
+/// A kind of extended statistics, as listed in `CREATE STATISTICS`
+#[derive(Debug, Copy, Clone, Eq, PartialEq, Hash)]
+pub enum StatisticsKind {
+    /// `ndistinct`:  the number of distinct values of each combination of the columns
+    NDistinct,
+    /// `dependencies`:  functional dependencies between the columns
+    Dependencies,
+    /// `mcv`:  the most common combinations of values of the columns
+    #[cfg(not(feature = "pg11"))]
+    Mcv,
+}
+
+impl StatisticsKind {
+    fn keyword(&self) -> &'static str {
+        match self {
+            StatisticsKind::NDistinct => "ndistinct",
+            StatisticsKind::Dependencies => "dependencies",
+            #[cfg(not(feature = "pg11"))]
+            StatisticsKind::Mcv => "mcv",
+        }
+    }
+}
+
+/// Builder for a `CREATE STATISTICS` command
+#[derive(Debug, Clone)]
+pub struct ExtendedStatistics {
+    name: String,
+    table: String,
+    columns: Vec<String>,
+    kinds: Vec<StatisticsKind>,
+    if_not_exists: bool,
+}
+
+impl ExtendedStatistics {
+    /// Describe a statistics object named `name` on `table`.  Both may be schema-qualified, and
+    /// are used as-is, so must already be quoted where necessary
+    pub fn new(name: &str, table: &str) -> Self {
+        ExtendedStatistics {
+            name: name.to_string(),
+            table: table.to_string(),
+            columns: Vec::new(),
+            kinds: Vec::new(),
+            if_not_exists: false,
+        }
+    }
+
+    /// Add a column of the table to the statistics object.  At least two are required
+    pub fn column(mut self, column: &str) -> Self {
+        self.columns.push(column.to_string());
+        self
+    }
+
+    /// Add several columns of the table to the statistics object
+    pub fn columns<I: IntoIterator<Item = S>, S: AsRef<str>>(mut self, columns: I) -> Self {
+        self.columns.extend(columns.into_iter().map(|column| column.as_ref().to_string()));
+        self
+    }
+
+    /// Compute this kind of statistics.  If no kind is given, Postgres computes all of them
+    pub fn kind(mut self, kind: StatisticsKind) -> Self {
+        if !self.kinds.contains(&kind) {
+            self.kinds.push(kind);
+        }
+        self
+    }
+
+    /// Do nothing, rather than raise an error, if a statistics object by the same name exists
+    pub fn if_not_exists(mut self) -> Self {
+        self.if_not_exists = true;
+        self
+    }
+
+    /// The `CREATE STATISTICS` command this describes
+    pub fn to_sql(&self) -> String {
+        let mut sql = String::from("CREATE STATISTICS ");
+        if self.if_not_exists {
+            sql.push_str("IF NOT EXISTS ");
+        }
+        sql.push_str(&self.name);
+        if !self.kinds.is_empty() {
+            let kinds = self.kinds.iter().map(StatisticsKind::keyword).collect::<Vec<_>>();
+            sql.push_str(&format!(" ({})", kinds.join(", ")));
+        }
+        let columns = self.columns.iter().map(quote_identifier).collect::<Vec<_>>();
+        sql.push_str(&format!(" ON {} FROM {}", columns.join(", "), self.table));
+        sql
+    }
+
+    /// Create the statistics object, returning its oid.  Its contents are computed the next time
+    /// the table is analyzed
+    pub fn create(&self) -> spi::Result<pg_sys::Oid> {
+        Spi::run(&self.to_sql())?;
+        Spi::get_one::<pg_sys::Oid>(&format!(
+            "SELECT oid FROM pg_catalog.pg_statistic_ext \
+              WHERE stxrelid = {}::regclass AND stxname = {} \
+              ORDER BY oid DESC LIMIT 1",
+            spi::quote_literal(&self.table),
+            spi::quote_literal(unqualified(&self.name)),
+        ))
+        .map(|oid| oid.expect("statistics object was not created"))
+    }
+}
+
+/// The final, unquoted, component of a possibly schema-qualified name
+fn unqualified(name: &str) -> String {
+    let last = name.rsplit('.').next().unwrap_or(name);
+    match last.strip_prefix('"').and_then(|last| last.strip_suffix('"')) {
+        Some(quoted) => quoted.replace("\"\"", "\""),
+        None => last.to_lowercase(),
+    }
+}
+
+/// The number of distinct values of a combination of columns
+#[derive(Debug, Clone, PartialEq)]
+pub struct NDistinctItem {
+    /// The attribute numbers of the columns
+    pub attnums: Vec<i16>,
+    /// The estimated number of distinct combinations of their values
+    pub ndistinct: f64,
+}
+
+/// A functional dependency between columns
+#[derive(Debug, Clone, PartialEq)]
+pub struct FunctionalDependency {
+    /// The attribute numbers of the determining columns
+    pub determinants: Vec<i16>,
+    /// The attribute number of the column they determine
+    pub dependent: i16,
+    /// The fraction of rows for which the dependency holds, between `0.0` and `1.0`
+    pub degree: f64,
+}
+
+/// One of the most common combinations of values
+#[derive(Debug, Clone, PartialEq)]
+pub struct McvItem {
+    /// The values, in column order, as text
+    pub values: Vec<Option<String>>,
+    /// The fraction of rows with this combination of values
+    pub frequency: f64,
+    /// The frequency the combination would have if the columns were independent
+    pub base_frequency: f64,
+}
+
+/// What `ANALYZE` computed for an extended statistics object.  Each kind is `None` if it wasn't
+/// requested or hasn't been computed yet
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct ExtendedStatisticsContents {
+    pub ndistinct: Option<Vec<NDistinctItem>>,
+    pub dependencies: Option<Vec<FunctionalDependency>>,
+    pub mcv: Option<Vec<McvItem>>,
+}
+
+/// Read the contents of the extended statistics object with the specified oid
+///
+/// The statistics themselves contain samples of table data, so on Postgres 12 and later they are
+/// only readable by superusers.
+pub fn read_extended_statistics(stxoid: pg_sys::Oid) -> spi::Result<ExtendedStatisticsContents> {
+    #[cfg(feature = "pg11")]
+    const QUERY: &str = "SELECT stxndistinct::text, stxdependencies::text \
+                           FROM pg_catalog.pg_statistic_ext WHERE oid = $1";
+    #[cfg(any(feature = "pg12", feature = "pg13", feature = "pg14"))]
+    const QUERY: &str = "SELECT stxdndistinct::text, stxddependencies::text \
+                           FROM pg_catalog.pg_statistic_ext_data WHERE stxoid = $1";
+    #[cfg(feature = "pg15")]
+    const QUERY: &str = "SELECT stxdndistinct::text, stxddependencies::text \
+                           FROM pg_catalog.pg_statistic_ext_data \
+                          WHERE stxoid = $1 AND NOT stxdinherit";
+
+    let args = || vec![(PgBuiltInOids::OIDOID.oid(), stxoid.into_datum())];
+    let (ndistinct, dependencies) = match Spi::get_two_with_args::<String, String>(QUERY, args()) {
+        Ok(found) => found,
+        // never analyzed
+        Err(spi::Error::InvalidPosition) => (None, None),
+        Err(e) => return Err(e),
+    };
+
+    #[cfg(feature = "pg11")]
+    let mcv = None;
+    #[cfg(not(feature = "pg11"))]
+    let mcv = {
+        #[cfg(not(feature = "pg15"))]
+        const MCV_QUERY: &str = "SELECT m.values, m.frequency, m.base_frequency \
+                                   FROM pg_catalog.pg_statistic_ext_data d, \
+                                        pg_catalog.pg_mcv_list_items(d.stxdmcv) m \
+                                  WHERE d.stxoid = $1 ORDER BY m.index";
+        #[cfg(feature = "pg15")]
+        const MCV_QUERY: &str = "SELECT m.values, m.frequency, m.base_frequency \
+                                   FROM pg_catalog.pg_statistic_ext_data d, \
+                                        pg_catalog.pg_mcv_list_items(d.stxdmcv) m \
+                                  WHERE d.stxoid = $1 AND NOT d.stxdinherit ORDER BY m.index";
+
+        let items = Spi::connect(|client| {
+            client
+                .select(MCV_QUERY, None, Some(args()))?
+                .map(|row| {
+                    Ok(McvItem {
+                        values: row.get_by_name("values")?.unwrap_or_default(),
+                        frequency: row.get_by_name("frequency")?.unwrap_or_default(),
+                        base_frequency: row.get_by_name("base_frequency")?.unwrap_or_default(),
+                    })
+                })
+                .collect::<spi::Result<Vec<_>>>()
+        })?;
+        Some(items).filter(|items| !items.is_empty())
+    };
+
+    Ok(ExtendedStatisticsContents {
+        ndistinct: ndistinct.map(|text| parse_ndistinct(&text)).transpose()?,
+        dependencies: dependencies.map(|text| parse_dependencies(&text)).transpose()?,
+        mcv,
+    })
+}
+
+/// `pg_ndistinct` values print as a JSON object like `{"1, 2": 11, "1, 3": 5}`
+fn parse_ndistinct(text: &str) -> spi::Result<Vec<NDistinctItem>> {
+    parse_object(text)?
+        .into_iter()
+        .map(|(key, ndistinct)| Ok(NDistinctItem { attnums: parse_attnums(&key)?, ndistinct }))
+        .collect()
+}
+
+/// `pg_dependencies` values print as a JSON object like `{"1 => 2": 1.000000, "1, 2 => 3": 0.5}`
+fn parse_dependencies(text: &str) -> spi::Result<Vec<FunctionalDependency>> {
+    parse_object(text)?
+        .into_iter()
+        .map(|(key, degree)| {
+            let (determinants, dependent) =
+                key.split_once(" => ").ok_or_else(|| bad_format(&key))?;
+            Ok(FunctionalDependency {
+                determinants: parse_attnums(determinants)?,
+                dependent: dependent.trim().parse().map_err(|_| bad_format(&key))?,
+                degree,
+            })
+        })
+        .collect()
+}
+
+fn parse_object(text: &str) -> spi::Result<Vec<(String, f64)>> {
+    let map: serde_json::Map<String, serde_json::Value> =
+        serde_json::from_str(text).map_err(|_| bad_format(text))?;
+    map.into_iter()
+        .map(|(key, value)| Ok((key, value.as_f64().ok_or_else(|| bad_format(text))?)))
+        .collect()
+}
+
+fn parse_attnums(text: &str) -> spi::Result<Vec<i16>> {
+    text.split(',').map(|attnum| attnum.trim().parse().map_err(|_| bad_format(text))).collect()
+}
+
+fn bad_format(text: &str) -> spi::Error {
+    spi::Error::DeserializeError(format!("unrecognized extended statistics format: {text}"))
+}
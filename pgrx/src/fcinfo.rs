@@ -108,7 +108,7 @@ mod pg_11 {
         let datum = unsafe { fcinfo.as_ref() }.unwrap().arg[num];
         let isnull = pg_arg_is_null(fcinfo, num);
 
-        unsafe {
+        crate::datum::counted_from_datum::<T, _>(|| unsafe {
             // SAFETY:  User has asserted that the desired Rust type `T` is compatible with the
             // underlying Datum, and has asserted that `fcinfo` is valid
             if T::GET_TYPOID {
@@ -116,7 +116,7 @@ mod pg_11 {
             } else {
                 T::from_datum(datum, isnull)
             }
-        }
+        })
     }
 
     /// Is the specified argument for a `PG_FUNCTION_INFO_V1` function NULL?
@@ -211,7 +211,7 @@ mod pg_12_13_14_15 {
         num: usize,
     ) -> Option<T> {
         let datum = get_nullable_datum(fcinfo, num);
-        unsafe {
+        crate::datum::counted_from_datum::<T, _>(|| unsafe {
             if T::GET_TYPOID {
                 T::from_polymorphic_datum(
                     datum.value,
@@ -221,7 +221,7 @@ mod pg_12_13_14_15 {
             } else {
                 T::from_datum(datum.value, datum.isnull)
             }
-        }
+        })
     }
 
     /// Is the specified argument for a `PG_FUNCTION_INFO_V1` function NULL?
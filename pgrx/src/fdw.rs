@@ -0,0 +1,644 @@
+/*
+Portions Copyright 2019-2021 ZomboDB, LLC.
+Portions Copyright 2021-2022 Technology Concepts & Design, Inc. <support@tcdi.com>
+
+All rights reserved.
+
+Use of this source code is governed by the MIT license that can be found in the LICENSE file.
+*/
+
+//! Foreign data wrappers, which make data from outside of Postgres readable, and optionally
+//! writable, as foreign tables
+//!
+//! Implement [`ForeignDataWrapper`] for a type holding the state of a scan, and declare it with
+//! [`#[pg_fdw]`](macro@crate::pg_fdw), which generates the wrapper's handler and validator
+//! functions and its `CREATE FOREIGN DATA WRAPPER` statement:
+//!
+//! ```rust,no_run
+//! use pgrx::fdw::{FdwColumn, FdwOptions, FdwRow, ForeignDataWrapper};
+//! use pgrx::prelude::*;
+//!
+//! struct Numbers {
+//!     next: i64,
+//!     last: i64,
+//! }
+//!
+//! #[pg_fdw(name = "numbers_fdw")]
+//! impl ForeignDataWrapper for Numbers {
+//!     fn begin_scan(options: &FdwOptions, _columns: &[FdwColumn]) -> Self {
+//!         let last = options.get("last").map_or(10, |last| last.parse().unwrap());
+//!         Numbers { next: 1, last }
+//!     }
+//!
+//!     fn iterate_scan(&mut self, row: &mut FdwRow) -> bool {
+//!         if self.next > self.last {
+//!             return false;
+//!         }
+//!         row.set("n", Some(self.next));
+//!         self.next += 1;
+//!         true
+//!     }
+//!
+//!     fn re_scan(&mut self) {
+//!         self.next = 1;
+//!     }
+//! }
+//! ```
+//!
+//! ```sql
+//! CREATE SERVER numbers FOREIGN DATA WRAPPER numbers_fdw;
+//! CREATE FOREIGN TABLE one_to_five (n bigint) SERVER numbers OPTIONS (last '5');
+//! ```
+//!
+//! Postgres applies a query's `WHERE` clause to the rows a scan returns, so a wrapper need not.
+//!
+//! A wrapper supporting `UPDATE` or `DELETE` identifies the rows to change by the column named by
+//! the foreign table's `rowid_column` option.
+use crate as pgrx; // for #[pg_guard] support from within ourself
+use crate::prelude::*;
+use crate::{compat, PgMemoryContexts, PgRelation, PgTupleDesc};
+use std::collections::HashMap;
+use std::ffi::CStr;
+use std::os::raw::c_int;
+
+/// The options of a foreign data wrapper, server, or table, by name
+pub type FdwOptions = HashMap<String, String>;
+
+/// The table option naming the column that identifies the rows to `UPDATE` or `DELETE`
+pub const ROWID_COLUMN_OPTION: &str = "rowid_column";
+
+/// The catalog an option list being validated belongs to
+#[derive(Debug, Copy, Clone, Eq, PartialEq)]
+pub enum FdwOptionsCatalog {
+    /// `CREATE FOREIGN DATA WRAPPER ... OPTIONS`
+    Wrapper,
+    /// `CREATE SERVER ... OPTIONS`
+    Server,
+    /// `CREATE USER MAPPING ... OPTIONS`
+    UserMapping,
+    /// `CREATE FOREIGN TABLE ... OPTIONS`
+    Table,
+    /// The options of a foreign table's column
+    Column,
+}
+
+impl FdwOptionsCatalog {
+    fn from_oid(catalog: pg_sys::Oid) -> Option<Self> {
+        match catalog {
+            pg_sys::ForeignDataWrapperRelationId => Some(FdwOptionsCatalog::Wrapper),
+            pg_sys::ForeignServerRelationId => Some(FdwOptionsCatalog::Server),
+            pg_sys::UserMappingRelationId => Some(FdwOptionsCatalog::UserMapping),
+            pg_sys::ForeignTableRelationId => Some(FdwOptionsCatalog::Table),
+            pg_sys::AttributeRelationId => Some(FdwOptionsCatalog::Column),
+            _ => None,
+        }
+    }
+}
+
+/// A column of a foreign table
+#[derive(Debug, Clone, Eq, PartialEq)]
+pub struct FdwColumn {
+    pub name: String,
+    /// Its attribute number, starting at one
+    pub attnum: usize,
+    pub type_oid: pg_sys::Oid,
+}
+
+/// A row of a foreign table:  one for a scan to fill in, or one being inserted or updated.  Custom
+/// scans fill in rows of the tables they scan the same way
+pub struct FdwRow<'a> {
+    slot: *mut pg_sys::TupleTableSlot,
+    columns: &'a [FdwColumn],
+}
+
+impl<'a> FdwRow<'a> {
+    #[cfg(feature = "cshim")]
+    pub(crate) fn new(slot: *mut pg_sys::TupleTableSlot, columns: &'a [FdwColumn]) -> Self {
+        FdwRow { slot, columns }
+    }
+
+    /// The columns of the foreign table
+    pub fn columns(&self) -> &'a [FdwColumn] {
+        self.columns
+    }
+
+    fn column(&self, name: &str) -> &'a FdwColumn {
+        self.columns
+            .iter()
+            .find(|column| column.name == name)
+            .unwrap_or_else(|| panic!("the table has no column named `{}`", name))
+    }
+
+    /// The value of the column named `name`, or `None` if it's null
+    ///
+    /// # Panics
+    ///
+    /// If there's no such column, or its type isn't compatible with `T`
+    pub fn get<T: FromDatum + IntoDatum>(&self, name: &str) -> Option<T> {
+        let column = self.column(name);
+        assert!(
+            T::is_compatible_with(column.type_oid),
+            "column `{}` is not compatible with {}",
+            name,
+            std::any::type_name::<T>()
+        );
+        unsafe {
+            let datum = compat::slot_getattr(self.slot, column.attnum)?;
+            T::from_polymorphic_datum(datum, false, column.type_oid)
+        }
+    }
+
+    /// Set the column named `name` to `value`, or to null if it's `None`.  A scan leaves columns it
+    /// doesn't set null
+    ///
+    /// # Panics
+    ///
+    /// If there's no such column, or its type isn't compatible with `T`
+    pub fn set<T: IntoDatum>(&mut self, name: &str, value: Option<T>) {
+        let column = self.column(name);
+        assert!(
+            T::is_compatible_with(column.type_oid),
+            "column `{}` is not compatible with {}",
+            name,
+            std::any::type_name::<T>()
+        );
+        let datum = value.and_then(IntoDatum::into_datum);
+        unsafe {
+            *(*self.slot).tts_values.add(column.attnum - 1) =
+                datum.unwrap_or(pg_sys::Datum::from(0));
+            *(*self.slot).tts_isnull.add(column.attnum - 1) = datum.is_none();
+        }
+    }
+}
+
+/// The value of the `rowid_column` of a row being updated or deleted
+pub struct FdwRowId {
+    datum: Option<pg_sys::Datum>,
+    type_oid: pg_sys::Oid,
+}
+
+impl FdwRowId {
+    /// The value, or `None` if it's null
+    ///
+    /// # Panics
+    ///
+    /// If the `rowid_column`'s type isn't compatible with `T`
+    pub fn value<T: FromDatum + IntoDatum>(&self) -> Option<T> {
+        assert!(
+            T::is_compatible_with(self.type_oid),
+            "the rowid column is not compatible with {}",
+            std::any::type_name::<T>()
+        );
+        unsafe { T::from_polymorphic_datum(self.datum?, false, self.type_oid) }
+    }
+}
+
+/// A foreign data wrapper.  An instance is created for each scan of a foreign table, and for each
+/// statement modifying one
+///
+/// Declare an implementation with [`#[pg_fdw]`](macro@crate::pg_fdw).
+pub trait ForeignDataWrapper: Sized {
+    /// Whether the wrapper supports `INSERT`.  `false` by default
+    const INSERT: bool = false;
+    /// Whether the wrapper supports `UPDATE`.  `false` by default
+    const UPDATE: bool = false;
+    /// Whether the wrapper supports `DELETE`.  `false` by default
+    const DELETE: bool = false;
+
+    /// The number of rows a scan of a foreign table with `options` is expected to return, for the
+    /// planner
+    fn estimate_rows(_options: &FdwOptions) -> f64 {
+        1000.0
+    }
+
+    /// Start a scan of a foreign table with `options`, the options of its wrapper, server and
+    /// table, the latter overriding the former.  `columns` are the table's columns
+    fn begin_scan(options: &FdwOptions, columns: &[FdwColumn]) -> Self;
+
+    /// Fill in the next row of the scan, returning `false` instead once there are no more
+    fn iterate_scan(&mut self, row: &mut FdwRow) -> bool;
+
+    /// Restart the scan from its first row.  Raises an error by default
+    fn re_scan(&mut self) {
+        ereport!(
+            PgLogLevel::ERROR,
+            PgSqlErrorCode::ERRCODE_FEATURE_NOT_SUPPORTED,
+            format!("{} does not support rescanning", std::any::type_name::<Self>())
+        );
+    }
+
+    /// Finish the scan
+    fn end_scan(&mut self) {}
+
+    /// Start modifying a foreign table with `options`, like [`ForeignDataWrapper::begin_scan()`].
+    /// Called when [`ForeignDataWrapper::INSERT`], [`ForeignDataWrapper::UPDATE`] or
+    /// [`ForeignDataWrapper::DELETE`] are `true`
+    fn begin_modify(options: &FdwOptions, columns: &[FdwColumn]) -> Self {
+        Self::begin_scan(options, columns)
+    }
+
+    /// Insert `row`.  Called when [`ForeignDataWrapper::INSERT`] is `true`
+    fn insert(&mut self, _row: &FdwRow) {
+        unreachable!("ForeignDataWrapper::insert() called without ForeignDataWrapper::INSERT")
+    }
+
+    /// Replace the row identified by `rowid` with `row`.  Called when
+    /// [`ForeignDataWrapper::UPDATE`] is `true`
+    fn update(&mut self, _rowid: &FdwRowId, _row: &FdwRow) {
+        unreachable!("ForeignDataWrapper::update() called without ForeignDataWrapper::UPDATE")
+    }
+
+    /// Delete the row identified by `rowid`.  Called when [`ForeignDataWrapper::DELETE`] is `true`
+    fn delete(&mut self, _rowid: &FdwRowId) {
+        unreachable!("ForeignDataWrapper::delete() called without ForeignDataWrapper::DELETE")
+    }
+
+    /// Finish modifying the foreign table
+    fn end_modify(&mut self) {}
+
+    /// Check the options given to the wrapper, or to a server, user mapping, table or column of it,
+    /// returning a message for those it rejects.  Accepts any by default
+    fn validate_options(_options: &FdwOptions, _catalog: FdwOptionsCatalog) -> Result<(), String> {
+        Ok(())
+    }
+}
+
+/// The `FdwRoutine` for `T`, as its handler function returns.  Used by
+/// [`#[pg_fdw]`](macro@crate::pg_fdw)
+#[doc(hidden)]
+pub fn fdw_routine<T: ForeignDataWrapper>() -> pg_sys::Datum {
+    unsafe {
+        let mut routine = PgBox::<pg_sys::FdwRoutine>::alloc_node(pg_sys::NodeTag_T_FdwRoutine);
+        routine.GetForeignRelSize = Some(get_foreign_rel_size::<T>);
+        routine.GetForeignPaths = Some(get_foreign_paths);
+        routine.GetForeignPlan = Some(get_foreign_plan);
+        routine.BeginForeignScan = Some(begin_foreign_scan::<T>);
+        routine.IterateForeignScan = Some(iterate_foreign_scan::<T>);
+        routine.ReScanForeignScan = Some(re_scan_foreign_scan::<T>);
+        routine.EndForeignScan = Some(end_foreign_scan::<T>);
+        if T::INSERT || T::UPDATE || T::DELETE {
+            routine.IsForeignRelUpdatable = Some(is_foreign_rel_updatable::<T>);
+            routine.AddForeignUpdateTargets = Some(add_foreign_update_targets);
+            routine.BeginForeignModify = Some(begin_foreign_modify::<T>);
+            routine.ExecForeignInsert = Some(exec_foreign_insert::<T>);
+            routine.ExecForeignUpdate = Some(exec_foreign_update::<T>);
+            routine.ExecForeignDelete = Some(exec_foreign_delete::<T>);
+            routine.EndForeignModify = Some(end_foreign_modify::<T>);
+        }
+        pg_sys::Datum::from(routine.into_pg())
+    }
+}
+
+/// Check the options passed to `T`'s validator function.  Used by
+/// [`#[pg_fdw]`](macro@crate::pg_fdw)
+///
+/// # Safety
+///
+/// `options` must be a `text[]` of `name=value` options, as Postgres passes to a validator, or null
+#[doc(hidden)]
+pub unsafe fn validate_options<T: ForeignDataWrapper>(
+    options: pg_sys::Datum,
+    catalog: pg_sys::Oid,
+) {
+    let catalog = match FdwOptionsCatalog::from_oid(catalog) {
+        Some(catalog) => catalog,
+        None => return,
+    };
+    let options = options_from_list(pg_sys::untransformRelOptions(options));
+    if let Err(message) = T::validate_options(&options, catalog) {
+        ereport!(PgLogLevel::ERROR, PgSqlErrorCode::ERRCODE_FDW_INVALID_OPTION_NAME, message);
+    }
+}
+
+unsafe fn options_from_list(list: *mut pg_sys::List) -> FdwOptions {
+    (0..compat::list_length(list))
+        .filter_map(|i| compat::list_nth_ptr(list, i))
+        .map(|def| {
+            let def = def as *mut pg_sys::DefElem;
+            let name = CStr::from_ptr((*def).defname).to_string_lossy().into_owned();
+            let value = CStr::from_ptr(pg_sys::defGetString(def)).to_string_lossy().into_owned();
+            (name, value)
+        })
+        .collect()
+}
+
+/// The options of the foreign table, and of its server and wrapper
+unsafe fn table_options(relid: pg_sys::Oid) -> FdwOptions {
+    let table = pg_sys::GetForeignTable(relid);
+    let server = pg_sys::GetForeignServer((*table).serverid);
+    let wrapper = pg_sys::GetForeignDataWrapper((*server).fdwid);
+    let mut options = options_from_list((*wrapper).options);
+    options.extend(options_from_list((*server).options));
+    options.extend(options_from_list((*table).options));
+    options
+}
+
+pub(crate) unsafe fn table_columns(relation: pg_sys::Relation) -> Vec<FdwColumn> {
+    PgTupleDesc::from_pg_unchecked((*relation).rd_att)
+        .iter()
+        .enumerate()
+        .filter(|(_, attribute)| !attribute.attisdropped)
+        .map(|(i, attribute)| FdwColumn {
+            name: pg_sys::name_data_to_str(&attribute.attname).to_string(),
+            attnum: i + 1,
+            type_oid: attribute.atttypid,
+        })
+        .collect()
+}
+
+struct FdwState<T> {
+    fdw: T,
+    columns: Vec<FdwColumn>,
+    // for modifies, the attribute number of the rowid in the plan's tuples, and its type
+    rowid: Option<(pg_sys::AttrNumber, pg_sys::Oid)>,
+}
+
+const ROWID_RESNAME: &[u8] = b"pgrx_fdw_rowid\0";
+
+#[pg_guard]
+unsafe extern "C" fn get_foreign_rel_size<T: ForeignDataWrapper>(
+    _root: *mut pg_sys::PlannerInfo,
+    baserel: *mut pg_sys::RelOptInfo,
+    foreigntableid: pg_sys::Oid,
+) {
+    (*baserel).rows = T::estimate_rows(&table_options(foreigntableid));
+}
+
+#[pg_guard]
+unsafe extern "C" fn get_foreign_paths(
+    root: *mut pg_sys::PlannerInfo,
+    baserel: *mut pg_sys::RelOptInfo,
+    _foreigntableid: pg_sys::Oid,
+) {
+    // a scan starts up like a sequential scan, and costs about as much per row
+    let startup_cost = 10.0;
+    let total_cost = startup_cost + (*baserel).rows;
+    let path = pg_sys::create_foreignscan_path(
+        root,
+        baserel,
+        std::ptr::null_mut(),
+        (*baserel).rows,
+        startup_cost,
+        total_cost,
+        std::ptr::null_mut(),
+        std::ptr::null_mut(),
+        std::ptr::null_mut(),
+        std::ptr::null_mut(),
+    );
+    pg_sys::add_path(baserel, path.cast());
+}
+
+#[pg_guard]
+unsafe extern "C" fn get_foreign_plan(
+    _root: *mut pg_sys::PlannerInfo,
+    baserel: *mut pg_sys::RelOptInfo,
+    _foreigntableid: pg_sys::Oid,
+    _best_path: *mut pg_sys::ForeignPath,
+    tlist: *mut pg_sys::List,
+    scan_clauses: *mut pg_sys::List,
+    outer_plan: *mut pg_sys::Plan,
+) -> *mut pg_sys::ForeignScan {
+    // the executor checks every clause against the rows the scan returns
+    let scan_clauses = pg_sys::extract_actual_clauses(scan_clauses, false);
+    pg_sys::make_foreignscan(
+        tlist,
+        scan_clauses,
+        (*baserel).relid,
+        std::ptr::null_mut(),
+        std::ptr::null_mut(),
+        std::ptr::null_mut(),
+        std::ptr::null_mut(),
+        outer_plan,
+    )
+}
+
+#[pg_guard]
+unsafe extern "C" fn begin_foreign_scan<T: ForeignDataWrapper>(
+    node: *mut pg_sys::ForeignScanState,
+    eflags: c_int,
+) {
+    if eflags & pg_sys::EXEC_FLAG_EXPLAIN_ONLY as c_int != 0 {
+        return;
+    }
+    let relation = (*node).ss.ss_currentRelation;
+    let options = table_options((*relation).rd_id);
+    let columns = table_columns(relation);
+    let fdw = T::begin_scan(&options, &columns);
+    // dropped with the executor's memory, even if the scan ends in an error
+    (*node).fdw_state = PgMemoryContexts::For((*(*node).ss.ps.state).es_query_cxt)
+        .leak_and_drop_on_delete(FdwState { fdw, columns, rowid: None })
+        .cast();
+}
+
+#[pg_guard]
+unsafe extern "C" fn iterate_foreign_scan<T: ForeignDataWrapper>(
+    node: *mut pg_sys::ForeignScanState,
+) -> *mut pg_sys::TupleTableSlot {
+    let state = &mut *((*node).fdw_state as *mut FdwState<T>);
+    let slot = (*node).ss.ss_ScanTupleSlot;
+    compat::clear_slot(slot);
+    let natts = (*(*slot).tts_tupleDescriptor).natts as usize;
+    std::slice::from_raw_parts_mut((*slot).tts_isnull, natts).fill(true);
+
+    let mut row = FdwRow { slot, columns: &state.columns };
+    if state.fdw.iterate_scan(&mut row) {
+        pg_sys::ExecStoreVirtualTuple(slot);
+    }
+    // an empty slot ends the scan
+    slot
+}
+
+#[pg_guard]
+unsafe extern "C" fn re_scan_foreign_scan<T: ForeignDataWrapper>(
+    node: *mut pg_sys::ForeignScanState,
+) {
+    let state = &mut *((*node).fdw_state as *mut FdwState<T>);
+    state.fdw.re_scan();
+}
+
+#[pg_guard]
+unsafe extern "C" fn end_foreign_scan<T: ForeignDataWrapper>(node: *mut pg_sys::ForeignScanState) {
+    if let Some(state) = ((*node).fdw_state as *mut FdwState<T>).as_mut() {
+        state.fdw.end_scan();
+    }
+}
+
+#[pg_guard]
+unsafe extern "C" fn is_foreign_rel_updatable<T: ForeignDataWrapper>(
+    _rel: pg_sys::Relation,
+) -> c_int {
+    let mut operations = 0;
+    if T::INSERT {
+        operations |= 1 << pg_sys::CmdType_CMD_INSERT;
+    }
+    if T::UPDATE {
+        operations |= 1 << pg_sys::CmdType_CMD_UPDATE;
+    }
+    if T::DELETE {
+        operations |= 1 << pg_sys::CmdType_CMD_DELETE;
+    }
+    operations
+}
+
+/// The `rowid_column` of `relation`, as a `Var` of the range table entry `rtindex`
+unsafe fn rowid_var(relation: pg_sys::Relation, rtindex: pg_sys::Index) -> *mut pg_sys::Var {
+    let options = table_options((*relation).rd_id);
+    let name = match options.get(ROWID_COLUMN_OPTION) {
+        Some(name) => name,
+        None => {
+            ereport!(
+                ERROR,
+                PgSqlErrorCode::ERRCODE_FDW_OPTION_NAME_NOT_FOUND,
+                format!(
+                    "foreign table \"{}\" needs a {} option to be updated or deleted from",
+                    PgRelation::from_pg(relation).name(),
+                    ROWID_COLUMN_OPTION
+                )
+            );
+        }
+    };
+    let column = table_columns(relation).into_iter().find(|column| column.name == *name);
+    let column = match column {
+        Some(column) => column,
+        None => {
+            ereport!(
+                ERROR,
+                PgSqlErrorCode::ERRCODE_UNDEFINED_COLUMN,
+                format!(
+                    "{} \"{}\" is not a column of the foreign table",
+                    ROWID_COLUMN_OPTION, name
+                )
+            );
+        }
+    };
+    let attribute = &*(*(*relation).rd_att).attrs.as_ptr().add(column.attnum - 1);
+    pg_sys::makeVar(
+        rtindex as _,
+        column.attnum as _,
+        attribute.atttypid,
+        attribute.atttypmod,
+        attribute.attcollation,
+        0,
+    )
+}
+
+#[cfg(any(feature = "pg11", feature = "pg12", feature = "pg13"))]
+#[pg_guard]
+unsafe extern "C" fn add_foreign_update_targets(
+    parsetree: *mut pg_sys::Query,
+    _target_rte: *mut pg_sys::RangeTblEntry,
+    target_relation: pg_sys::Relation,
+) {
+    let var = rowid_var(target_relation, (*parsetree).resultRelation as _);
+    let entry = pg_sys::makeTargetEntry(
+        var.cast(),
+        (compat::list_length((*parsetree).targetList) + 1) as _,
+        pg_sys::pstrdup(ROWID_RESNAME.as_ptr().cast()),
+        true,
+    );
+    (*parsetree).targetList = pg_sys::lappend((*parsetree).targetList, entry.cast());
+}
+
+#[cfg(any(feature = "pg14", feature = "pg15"))]
+#[pg_guard]
+unsafe extern "C" fn add_foreign_update_targets(
+    root: *mut pg_sys::PlannerInfo,
+    rtindex: pg_sys::Index,
+    _target_rte: *mut pg_sys::RangeTblEntry,
+    target_relation: pg_sys::Relation,
+) {
+    let var = rowid_var(target_relation, rtindex);
+    pg_sys::add_row_identity_var(root, var, rtindex, ROWID_RESNAME.as_ptr().cast());
+}
+
+#[pg_guard]
+unsafe extern "C" fn begin_foreign_modify<T: ForeignDataWrapper>(
+    mtstate: *mut pg_sys::ModifyTableState,
+    rinfo: *mut pg_sys::ResultRelInfo,
+    _fdw_private: *mut pg_sys::List,
+    _subplan_index: c_int,
+    eflags: c_int,
+) {
+    if eflags & pg_sys::EXEC_FLAG_EXPLAIN_ONLY as c_int != 0 {
+        return;
+    }
+    let relation = (*rinfo).ri_RelationDesc;
+    let options = table_options((*relation).rd_id);
+    let columns = table_columns(relation);
+
+    let operation = (*mtstate).operation;
+    let rowid =
+        if operation == pg_sys::CmdType_CMD_UPDATE || operation == pg_sys::CmdType_CMD_DELETE {
+            #[cfg(any(feature = "pg11", feature = "pg12", feature = "pg13"))]
+            let subplan = (**(*mtstate).mt_plans.add(_subplan_index as usize)).plan;
+            #[cfg(any(feature = "pg14", feature = "pg15"))]
+            let subplan = (*(*mtstate).ps.lefttree).plan;
+
+            let attno = pg_sys::ExecFindJunkAttributeInTlist(
+                (*subplan).targetlist,
+                ROWID_RESNAME.as_ptr().cast(),
+            );
+            let type_oid = (*rowid_var(relation, 1)).vartype;
+            Some((attno, type_oid))
+        } else {
+            None
+        };
+
+    let fdw = T::begin_modify(&options, &columns);
+    (*rinfo).ri_FdwState = PgMemoryContexts::For((*(*mtstate).ps.state).es_query_cxt)
+        .leak_and_drop_on_delete(FdwState { fdw, columns, rowid })
+        .cast();
+}
+
+unsafe fn row_id<T>(state: &FdwState<T>, plan_slot: *mut pg_sys::TupleTableSlot) -> FdwRowId {
+    let (attno, type_oid) = state.rowid.expect("the modify has no rowid");
+    FdwRowId { datum: compat::slot_getattr(plan_slot, attno as usize), type_oid }
+}
+
+#[pg_guard]
+unsafe extern "C" fn exec_foreign_insert<T: ForeignDataWrapper>(
+    _estate: *mut pg_sys::EState,
+    rinfo: *mut pg_sys::ResultRelInfo,
+    slot: *mut pg_sys::TupleTableSlot,
+    _plan_slot: *mut pg_sys::TupleTableSlot,
+) -> *mut pg_sys::TupleTableSlot {
+    let state = &mut *((*rinfo).ri_FdwState as *mut FdwState<T>);
+    state.fdw.insert(&FdwRow { slot, columns: &state.columns });
+    slot
+}
+
+#[pg_guard]
+unsafe extern "C" fn exec_foreign_update<T: ForeignDataWrapper>(
+    _estate: *mut pg_sys::EState,
+    rinfo: *mut pg_sys::ResultRelInfo,
+    slot: *mut pg_sys::TupleTableSlot,
+    plan_slot: *mut pg_sys::TupleTableSlot,
+) -> *mut pg_sys::TupleTableSlot {
+    let state = &mut *((*rinfo).ri_FdwState as *mut FdwState<T>);
+    let rowid = row_id(state, plan_slot);
+    state.fdw.update(&rowid, &FdwRow { slot, columns: &state.columns });
+    slot
+}
+
+#[pg_guard]
+unsafe extern "C" fn exec_foreign_delete<T: ForeignDataWrapper>(
+    _estate: *mut pg_sys::EState,
+    rinfo: *mut pg_sys::ResultRelInfo,
+    slot: *mut pg_sys::TupleTableSlot,
+    plan_slot: *mut pg_sys::TupleTableSlot,
+) -> *mut pg_sys::TupleTableSlot {
+    let state = &mut *((*rinfo).ri_FdwState as *mut FdwState<T>);
+    let rowid = row_id(state, plan_slot);
+    state.fdw.delete(&rowid);
+    slot
+}
+
+#[pg_guard]
+unsafe extern "C" fn end_foreign_modify<T: ForeignDataWrapper>(
+    _estate: *mut pg_sys::EState,
+    rinfo: *mut pg_sys::ResultRelInfo,
+) {
+    if let Some(state) = ((*rinfo).ri_FdwState as *mut FdwState<T>).as_mut() {
+        state.fdw.end_modify();
+    }
+}
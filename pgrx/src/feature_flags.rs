@@ -0,0 +1,191 @@
+/*
+Portions Copyright 2019-2021 ZomboDB, LLC.
+Portions Copyright 2021-2022 Technology Concepts & Design, Inc. <support@tcdi.com>
+
+All rights reserved.
+
+Use of this source code is governed by the MIT license that can be found in the LICENSE file.
+*/
+
+//! Named feature flags, backed by boolean GUCs, for staged rollouts of extension behavior
+//!
+//! A [`FeatureFlag`] is declared as a `static` with [`feature_flag!`](crate::feature_flag) and
+//! registered from `_PG_init()`.  From then on it can be turned on and off like any other GUC,
+//! with `SET`, `ALTER ROLE ... SET`, or `postgresql.conf`.  Postgres writes the GUC's value
+//! straight into the flag, so [`FeatureFlag::is_enabled()`] is just a memory read.
+//!
+//! Each flag also counts, per backend, how often it has been checked and how often it was on, so
+//! it's possible to tell whether a flagged code path is actually being exercised.
+//! [`feature_flags_view!`](crate::feature_flags_view) adds a `feature_flags` view to the
+//! extension's schema listing all of this.
+//!
+//! ## Examples
+//!
+//! ```rust,no_run
+//! use pgrx::prelude::*;
+//!
+//! pgrx::feature_flag!(NEW_PLANNER, "my_extension.new_planner", "Use the new planner", false);
+//! pgrx::feature_flags_view!();
+//!
+//! #[pg_guard]
+//! pub extern "C" fn _PG_init() {
+//!     NEW_PLANNER.register();
+//! }
+//!
+//! fn plan() {
+//!     if NEW_PLANNER.is_enabled() {
+//!         // ...
+//!     }
+//! }
+//! ```
+use crate::guc::{GucContext, GucFlags, GucRegistry, GucSetting};
+use std::cell::RefCell;
+use std::sync::atomic::{AtomicU64, Ordering};
+
+/// A feature that can be turned on and off at runtime through a boolean GUC
+pub struct FeatureFlag {
+    name: &'static str,
+    description: &'static str,
+    setting: GucSetting<bool>,
+    // the counters are per backend, as a `FeatureFlag` is in each backend's own memory.  They're
+    // atomics only so a flag can be a `static`, and are never contended
+    checks: AtomicU64,
+    enabled_checks: AtomicU64,
+}
+
+thread_local! {
+    static REGISTERED: RefCell<Vec<&'static FeatureFlag>> = const { RefCell::new(Vec::new()) };
+}
+
+impl FeatureFlag {
+    /// A feature flag controlled by the GUC named `name`, which should be prefixed with the
+    /// extension's name, like `my_extension.new_planner`
+    pub const fn new(name: &'static str, description: &'static str, default: bool) -> Self {
+        FeatureFlag {
+            name,
+            description,
+            setting: GucSetting::new(default),
+            checks: AtomicU64::new(0),
+            enabled_checks: AtomicU64::new(0),
+        }
+    }
+
+    /// Define the flag's GUC, settable by any user, and add it to [`registered_feature_flags()`].
+    /// This should be called from `_PG_init()`
+    pub fn register(&'static self) {
+        self.register_with_context(GucContext::Userset)
+    }
+
+    /// Like [`FeatureFlag::register()`], but only allow the flag to be set as `context` allows,
+    /// such as only by superusers with [`GucContext::Suset`]
+    pub fn register_with_context(&'static self, context: GucContext) {
+        GucRegistry::define_bool_guc(
+            self.name,
+            self.description,
+            self.description,
+            &self.setting,
+            context,
+            GucFlags::default(),
+        );
+        REGISTERED.with(|registered| registered.borrow_mut().push(self));
+    }
+
+    /// Is the feature currently turned on?
+    #[inline]
+    pub fn is_enabled(&self) -> bool {
+        let enabled = self.setting.get();
+        self.checks.fetch_add(1, Ordering::Relaxed);
+        if enabled {
+            self.enabled_checks.fetch_add(1, Ordering::Relaxed);
+        }
+        enabled
+    }
+
+    /// Is the feature currently turned on?  Unlike [`FeatureFlag::is_enabled()`], this isn't
+    /// counted as a check
+    pub fn current_value(&self) -> bool {
+        self.setting.get()
+    }
+
+    /// The name of the flag's GUC
+    pub fn name(&self) -> &'static str {
+        self.name
+    }
+
+    pub fn description(&self) -> &'static str {
+        self.description
+    }
+
+    /// How many times [`FeatureFlag::is_enabled()`] has been called in this backend
+    pub fn checks(&self) -> u64 {
+        self.checks.load(Ordering::Relaxed)
+    }
+
+    /// How many of those calls found the feature turned on
+    pub fn enabled_checks(&self) -> u64 {
+        self.enabled_checks.load(Ordering::Relaxed)
+    }
+
+    /// Reset this backend's counters to zero
+    pub fn reset_counters(&self) {
+        self.checks.store(0, Ordering::Relaxed);
+        self.enabled_checks.store(0, Ordering::Relaxed);
+    }
+}
+
+/// Every feature flag registered in this backend, in the order they were registered
+pub fn registered_feature_flags() -> impl Iterator<Item = &'static FeatureFlag> {
+    REGISTERED.with(|registered| registered.borrow().clone()).into_iter()
+}
+
+/// Declare a `static` [`FeatureFlag`](crate::feature_flags::FeatureFlag)
+///
+/// ```rust,no_run
+/// pgrx::feature_flag!(pub FAST_PATH, "my_extension.fast_path", "Take the fast path", true);
+/// ```
+#[macro_export]
+macro_rules! feature_flag {
+    ($vis:vis $ident:ident, $name:literal, $description:literal, $default:expr) => {
+        $vis static $ident: $crate::feature_flags::FeatureFlag =
+            $crate::feature_flags::FeatureFlag::new($name, $description, $default);
+    };
+}
+
+/// Add a `feature_flag_states()` function, and a `feature_flags` view over it, to the extension's
+/// schema.  They list each registered feature flag, whether it's on, its description, and this
+/// backend's counters
+#[macro_export]
+macro_rules! feature_flags_view {
+    () => {
+        #[::pgrx::pg_extern(volatile)]
+        fn feature_flag_states() -> ::pgrx::iter::TableIterator<
+            'static,
+            (
+                ::pgrx::name!(name, String),
+                ::pgrx::name!(enabled, bool),
+                ::pgrx::name!(description, String),
+                ::pgrx::name!(checks, i64),
+                ::pgrx::name!(enabled_checks, i64),
+            ),
+        > {
+            let states = $crate::feature_flags::registered_feature_flags()
+                .map(|flag| {
+                    (
+                        flag.name().to_string(),
+                        flag.current_value(),
+                        flag.description().to_string(),
+                        flag.checks() as i64,
+                        flag.enabled_checks() as i64,
+                    )
+                })
+                .collect::<Vec<_>>();
+            $crate::iter::TableIterator::new(states.into_iter())
+        }
+
+        ::pgrx::extension_sql!(
+            "CREATE VIEW feature_flags AS SELECT * FROM feature_flag_states();",
+            name = "feature_flags_view",
+            requires = [feature_flag_states]
+        );
+    };
+}
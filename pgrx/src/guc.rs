@@ -8,7 +8,9 @@ Use of this source code is governed by the MIT license that can be found in the
 */
 
 //! Provides a safe interface into Postgres' Configuration System (GUC)
-use crate::{pg_sys, PgMemoryContexts};
+use crate::pg_sys::AsPgCStr;
+use crate::prelude::*;
+use crate::{pg_sys, spi, PgMemoryContexts};
 use core::ffi::CStr;
 pub use pgrx_macros::PostgresGucEnum;
 use std::cell::Cell;
@@ -132,6 +134,7 @@ impl<T> GucSetting<T> {
 unsafe impl Sync for GucSetting<bool> {}
 impl GucSetting<bool> {
     pub fn get(&self) -> bool {
+        crate::volatility::check_volatility("reads a GUC");
         self.value.get()
     }
 
@@ -143,6 +146,7 @@ impl GucSetting<bool> {
 unsafe impl Sync for GucSetting<i32> {}
 impl GucSetting<i32> {
     pub fn get(&self) -> i32 {
+        crate::volatility::check_volatility("reads a GUC");
         self.value.get()
     }
 
@@ -154,6 +158,7 @@ impl GucSetting<i32> {
 unsafe impl Sync for GucSetting<f64> {}
 impl GucSetting<f64> {
     pub fn get(&self) -> f64 {
+        crate::volatility::check_volatility("reads a GUC");
         self.value.get()
     }
 
@@ -165,6 +170,7 @@ impl GucSetting<f64> {
 unsafe impl Sync for GucSetting<Option<&'static str>> {}
 impl GucSetting<Option<&'static str>> {
     pub fn get(&self) -> Option<String> {
+        crate::volatility::check_volatility("reads a GUC");
         let ptr = self.get_char_ptr();
         if ptr.is_null() {
             None
@@ -189,6 +195,7 @@ where
     T: GucEnum<T> + Copy,
 {
     pub fn get(&self) -> T {
+        crate::volatility::check_volatility("reads a GUC");
         T::from_ordinal(self.enum_o.get())
     }
 
@@ -336,3 +343,568 @@ impl GucRegistry {
         }
     }
 }
+
+/// Check and assign hooks for a GUC whose values are given to the hooks as `T`.  [`define_gucs!`]
+/// implements this for the hooks it's given
+pub trait GucHooks<T> {
+    /// Whether `value` is a valid value for the GUC.  The `Err` is the detail of the error (or
+    /// log message, for a value from `postgresql.conf`) rejecting it.  This must not raise errors
+    fn check(_value: &T) -> Result<(), String> {
+        Ok(())
+    }
+
+    /// Called after the GUC is set to `value`, which has passed the check hook.  This must not
+    /// raise errors
+    fn assign(_value: &T) {}
+}
+
+/// [`GucHooks`] that don't do anything
+pub struct NoGucHooks;
+impl<T> GucHooks<T> for NoGucHooks {}
+
+/// The types of GUC [`define_gucs!`] can define:  `bool`, `i32`, `f64`, `Option<&'static str>`,
+/// and enums deriving [`PostgresGucEnum`]
+pub trait GucValue: Sized + 'static {
+    /// The type of the values given to the GUC's hooks
+    type Hooked;
+
+    /// Define the GUC `name` for `setting`, with its hooks `H`.  `min` and `max` only apply to
+    /// `i32` and `f64` GUCs, and default to the type's limits
+    fn define<H: GucHooks<Self::Hooked>>(
+        setting: &'static GucSetting<Self>,
+        name: &str,
+        short_description: &str,
+        long_description: &str,
+        min: Option<Self>,
+        max: Option<Self>,
+        context: GucContext,
+        flags: GucFlags,
+    );
+}
+
+fn no_range<T: GucValue>(name: &str, min: Option<T>, max: Option<T>) {
+    if min.is_some() || max.is_some() {
+        panic!("only int and float GUCs have a range, but `{}` is given one", name);
+    }
+}
+
+impl GucValue for bool {
+    type Hooked = bool;
+
+    fn define<H: GucHooks<bool>>(
+        setting: &'static GucSetting<bool>,
+        name: &str,
+        short_description: &str,
+        long_description: &str,
+        min: Option<bool>,
+        max: Option<bool>,
+        context: GucContext,
+        flags: GucFlags,
+    ) {
+        unsafe extern "C" fn check<H: GucHooks<bool>>(
+            newval: *mut bool,
+            _extra: *mut *mut std::os::raw::c_void,
+            _source: pg_sys::GucSource,
+        ) -> bool {
+            run_check_hook(|| H::check(&*newval))
+        }
+        unsafe extern "C" fn assign<H: GucHooks<bool>>(
+            newval: bool,
+            _extra: *mut std::os::raw::c_void,
+        ) {
+            run_assign_hook(|| H::assign(&newval))
+        }
+
+        no_range(name, min, max);
+        unsafe {
+            pg_sys::DefineCustomBoolVariable(
+                PgMemoryContexts::TopMemoryContext.pstrdup(name),
+                PgMemoryContexts::TopMemoryContext.pstrdup(short_description),
+                PgMemoryContexts::TopMemoryContext.pstrdup(long_description),
+                setting.as_ptr(),
+                setting.value.get(),
+                context as isize as u32,
+                flags.bits(),
+                Some(check::<H>),
+                Some(assign::<H>),
+                None,
+            )
+        }
+    }
+}
+
+impl GucValue for i32 {
+    type Hooked = i32;
+
+    fn define<H: GucHooks<i32>>(
+        setting: &'static GucSetting<i32>,
+        name: &str,
+        short_description: &str,
+        long_description: &str,
+        min: Option<i32>,
+        max: Option<i32>,
+        context: GucContext,
+        flags: GucFlags,
+    ) {
+        unsafe extern "C" fn check<H: GucHooks<i32>>(
+            newval: *mut i32,
+            _extra: *mut *mut std::os::raw::c_void,
+            _source: pg_sys::GucSource,
+        ) -> bool {
+            run_check_hook(|| H::check(&*newval))
+        }
+        unsafe extern "C" fn assign<H: GucHooks<i32>>(
+            newval: i32,
+            _extra: *mut std::os::raw::c_void,
+        ) {
+            run_assign_hook(|| H::assign(&newval))
+        }
+
+        unsafe {
+            pg_sys::DefineCustomIntVariable(
+                PgMemoryContexts::TopMemoryContext.pstrdup(name),
+                PgMemoryContexts::TopMemoryContext.pstrdup(short_description),
+                PgMemoryContexts::TopMemoryContext.pstrdup(long_description),
+                setting.as_ptr(),
+                setting.value.get(),
+                min.unwrap_or(i32::MIN),
+                max.unwrap_or(i32::MAX),
+                context as isize as u32,
+                flags.bits(),
+                Some(check::<H>),
+                Some(assign::<H>),
+                None,
+            )
+        }
+    }
+}
+
+impl GucValue for f64 {
+    type Hooked = f64;
+
+    fn define<H: GucHooks<f64>>(
+        setting: &'static GucSetting<f64>,
+        name: &str,
+        short_description: &str,
+        long_description: &str,
+        min: Option<f64>,
+        max: Option<f64>,
+        context: GucContext,
+        flags: GucFlags,
+    ) {
+        unsafe extern "C" fn check<H: GucHooks<f64>>(
+            newval: *mut f64,
+            _extra: *mut *mut std::os::raw::c_void,
+            _source: pg_sys::GucSource,
+        ) -> bool {
+            run_check_hook(|| H::check(&*newval))
+        }
+        unsafe extern "C" fn assign<H: GucHooks<f64>>(
+            newval: f64,
+            _extra: *mut std::os::raw::c_void,
+        ) {
+            run_assign_hook(|| H::assign(&newval))
+        }
+
+        unsafe {
+            pg_sys::DefineCustomRealVariable(
+                PgMemoryContexts::TopMemoryContext.pstrdup(name),
+                PgMemoryContexts::TopMemoryContext.pstrdup(short_description),
+                PgMemoryContexts::TopMemoryContext.pstrdup(long_description),
+                setting.as_ptr(),
+                setting.value.get(),
+                min.unwrap_or(f64::MIN),
+                max.unwrap_or(f64::MAX),
+                context as isize as u32,
+                flags.bits(),
+                Some(check::<H>),
+                Some(assign::<H>),
+                None,
+            )
+        }
+    }
+}
+
+impl GucValue for Option<&'static str> {
+    type Hooked = Option<String>;
+
+    fn define<H: GucHooks<Option<String>>>(
+        setting: &'static GucSetting<Option<&'static str>>,
+        name: &str,
+        short_description: &str,
+        long_description: &str,
+        min: Option<Option<&'static str>>,
+        max: Option<Option<&'static str>>,
+        context: GucContext,
+        flags: GucFlags,
+    ) {
+        unsafe fn string(value: *const std::os::raw::c_char) -> Option<String> {
+            (!value.is_null()).then(|| CStr::from_ptr(value).to_string_lossy().into_owned())
+        }
+        unsafe extern "C" fn check<H: GucHooks<Option<String>>>(
+            newval: *mut *mut std::os::raw::c_char,
+            _extra: *mut *mut std::os::raw::c_void,
+            _source: pg_sys::GucSource,
+        ) -> bool {
+            run_check_hook(|| H::check(&string(*newval)))
+        }
+        unsafe extern "C" fn assign<H: GucHooks<Option<String>>>(
+            newval: *const std::os::raw::c_char,
+            _extra: *mut std::os::raw::c_void,
+        ) {
+            run_assign_hook(|| H::assign(&string(newval)))
+        }
+
+        no_range(name, min, max);
+        unsafe {
+            let boot_value = match setting.value.get() {
+                Some(s) => PgMemoryContexts::TopMemoryContext.pstrdup(s),
+                None => std::ptr::null_mut(),
+            };
+
+            pg_sys::DefineCustomStringVariable(
+                PgMemoryContexts::TopMemoryContext.pstrdup(name),
+                PgMemoryContexts::TopMemoryContext.pstrdup(short_description),
+                PgMemoryContexts::TopMemoryContext.pstrdup(long_description),
+                setting.as_ptr(),
+                boot_value,
+                context as isize as u32,
+                flags.bits(),
+                Some(check::<H>),
+                Some(assign::<H>),
+                None,
+            )
+        }
+    }
+}
+
+impl<T> GucValue for T
+where
+    T: GucEnum<T> + Copy + 'static,
+{
+    type Hooked = T;
+
+    fn define<H: GucHooks<T>>(
+        setting: &'static GucSetting<T>,
+        name: &str,
+        short_description: &str,
+        long_description: &str,
+        min: Option<T>,
+        max: Option<T>,
+        context: GucContext,
+        flags: GucFlags,
+    ) {
+        unsafe extern "C" fn check<T: GucEnum<T> + Copy, H: GucHooks<T>>(
+            newval: *mut i32,
+            _extra: *mut *mut std::os::raw::c_void,
+            _source: pg_sys::GucSource,
+        ) -> bool {
+            run_check_hook(|| H::check(&T::from_ordinal(*newval)))
+        }
+        unsafe extern "C" fn assign<T: GucEnum<T> + Copy, H: GucHooks<T>>(
+            newval: i32,
+            _extra: *mut std::os::raw::c_void,
+        ) {
+            run_assign_hook(|| H::assign(&T::from_ordinal(newval)))
+        }
+
+        no_range(name, min, max);
+        unsafe {
+            pg_sys::DefineCustomEnumVariable(
+                PgMemoryContexts::TopMemoryContext.pstrdup(name),
+                PgMemoryContexts::TopMemoryContext.pstrdup(short_description),
+                PgMemoryContexts::TopMemoryContext.pstrdup(long_description),
+                setting.as_ptr(),
+                setting.value.get().to_ordinal(),
+                setting.value.get().config_matrix(),
+                context as isize as u32,
+                flags.bits(),
+                Some(check::<T, H>),
+                Some(assign::<T, H>),
+                None,
+            )
+        }
+    }
+}
+
+// hooks can't raise errors, or unwind into Postgres
+fn run_check_hook(check: impl FnOnce() -> Result<(), String>) -> bool {
+    let detail = match std::panic::catch_unwind(std::panic::AssertUnwindSafe(check)) {
+        Ok(Ok(())) => return true,
+        Ok(Err(detail)) => detail,
+        Err(e) => format!("The check hook panicked: {}", panic_message(&e)),
+    };
+    unsafe {
+        pg_sys::GUC_check_errdetail_string = detail.as_pg_cstr();
+    }
+    false
+}
+
+fn run_assign_hook(assign: impl FnOnce()) {
+    if let Err(e) = std::panic::catch_unwind(std::panic::AssertUnwindSafe(assign)) {
+        warning!("a GUC assign hook panicked: {}", panic_message(&e));
+    }
+}
+
+fn panic_message(e: &Box<dyn std::any::Any + Send>) -> &str {
+    e.downcast_ref::<&str>()
+        .copied()
+        .or_else(|| e.downcast_ref::<String>().map(String::as_str))
+        .unwrap_or("unknown panic")
+}
+
+/// The short and long descriptions of a GUC, from the lines of its doc comment:  the first
+/// paragraph, and the rest
+#[doc(hidden)]
+pub fn __guc_descriptions(name: &str, doc: &[&str]) -> (String, String) {
+    let mut paragraphs = doc
+        .split(|line| line.trim().is_empty())
+        .filter(|p| !p.is_empty())
+        .map(|paragraph| paragraph.iter().map(|line| line.trim()).collect::<Vec<_>>().join(" "));
+    let short = paragraphs.next().unwrap_or_else(|| name.to_string());
+    let long = paragraphs.collect::<Vec<_>>().join("\n\n");
+    (short, long)
+}
+
+/// Declare GUCs, and a function defining them all, to call from `_PG_init()`
+///
+/// Each GUC is a `static` [`GucSetting`] of a type implementing [`GucValue`], with its default
+/// value and its name.  Its doc comment is the GUC's description:  its first paragraph is the
+/// short description, and the rest is the long one.  These optional settings may follow, in this
+/// order:
+///
+/// * `min` and `max`, for `i32` and `f64` GUCs, which otherwise accept any value of their type
+/// * `context`, a [`GucContext`], which is [`GucContext::Userset`] by default
+/// * `flags`, such as a unit like [`GucFlags::UNIT_MS`]
+/// * `check`, a closure given a reference to a new value, returning `Err(detail)` if it isn't
+///   valid.  String GUCs' values are given as an `Option<String>`
+/// * `assign`, a closure given a reference to the GUC's new value once it's set
+///
+/// ```rust,no_run
+/// use pgrx::guc::{GucContext, GucFlags};
+/// use pgrx::prelude::*;
+///
+/// pgrx::define_gucs! {
+///     pub fn register_gucs();
+///
+///     /// Whether widgets are enabled
+///     pub static ENABLED: bool = true, "widgets.enabled";
+///
+///     /// How long to wait for a widget
+///     ///
+///     /// Zero waits forever.
+///     pub static TIMEOUT: i32 = 1000, "widgets.timeout",
+///         min = 0,
+///         max = 60_000,
+///         context = GucContext::Suset,
+///         flags = GucFlags::UNIT_MS;
+///
+///     /// The name of the widget factory
+///     pub static FACTORY: Option<&'static str> = None, "widgets.factory",
+///         check = |name| match name {
+///             Some(name) if name.len() > 32 => Err(format!("\"{}\" is too long", name)),
+///             _ => Ok(()),
+///         },
+///         assign = |name| notice!("widgets now come from {:?}", name);
+/// }
+///
+/// #[pg_guard]
+/// pub extern "C" fn _PG_init() {
+///     register_gucs();
+/// }
+/// ```
+#[macro_export]
+macro_rules! define_gucs {
+    (
+        $fvis:vis fn $register:ident();
+        $(
+            $(#[doc = $doc:expr])*
+            $vis:vis static $ident:ident: $ty:ty = $default:expr, $name:expr
+            $(, min = $min:expr)?
+            $(, max = $max:expr)?
+            $(, context = $context:expr)?
+            $(, flags = $flags:expr)?
+            $(, check = $check:expr)?
+            $(, assign = $assign:expr)?
+            ;
+        )*
+    ) => {
+        $(
+            $(#[doc = $doc])*
+            $vis static $ident: $crate::guc::GucSetting<$ty> = $crate::guc::GucSetting::new($default);
+        )*
+
+        /// Define the GUCs of this `define_gucs!`.  This should be called from `_PG_init()`
+        $fvis fn $register() {
+            $({
+                struct Hooks;
+                impl $crate::guc::GucHooks<<$ty as $crate::guc::GucValue>::Hooked> for Hooks {
+                    $(
+                        fn check(
+                            value: &<$ty as $crate::guc::GucValue>::Hooked,
+                        ) -> ::core::result::Result<(), ::std::string::String> {
+                            ($check)(value)
+                        }
+                    )?
+                    $(
+                        fn assign(value: &<$ty as $crate::guc::GucValue>::Hooked) {
+                            ($assign)(value)
+                        }
+                    )?
+                }
+
+                let (short_description, long_description) =
+                    $crate::guc::__guc_descriptions($name, &[$($doc),*]);
+                #[allow(unused_mut)]
+                let mut min: ::core::option::Option<$ty> = None;
+                $(min = Some($min);)?
+                #[allow(unused_mut)]
+                let mut max: ::core::option::Option<$ty> = None;
+                $(max = Some($max);)?
+                #[allow(unused_mut)]
+                let mut context = $crate::guc::GucContext::Userset;
+                $(context = $context;)?
+                #[allow(unused_mut)]
+                let mut flags = $crate::guc::GucFlags::default();
+                $(flags = $flags;)?
+                <$ty as $crate::guc::GucValue>::define::<Hooks>(
+                    &$ident,
+                    $name,
+                    &short_description,
+                    &long_description,
+                    min,
+                    max,
+                    context,
+                    flags,
+                );
+            })*
+        }
+    };
+}
+
+/// Where a per-database or per-role setting is stored in `pg_db_role_setting`.  These settings
+/// are applied when a session starts, and don't affect sessions that are already running
+#[derive(Debug, Copy, Clone, Eq, PartialEq)]
+pub enum SettingScope<'a> {
+    /// `ALTER DATABASE database SET ...`
+    Database(&'a str),
+    /// `ALTER ROLE role SET ...`
+    Role(&'a str),
+    /// `ALTER ROLE role IN DATABASE database SET ...`
+    RoleInDatabase { role: &'a str, database: &'a str },
+    /// `ALTER ROLE ALL SET ...`
+    AllRoles,
+}
+
+impl<'a> SettingScope<'a> {
+    /// The value of the GUC named `name` stored for exactly this scope, if any
+    pub fn get(&self, name: &str) -> spi::Result<Option<String>> {
+        let (database, role) = self.oids();
+        let config = Spi::get_one_with_args::<Vec<Option<String>>>(
+            "SELECT setconfig FROM pg_catalog.pg_db_role_setting \
+              WHERE setdatabase = $1 AND setrole = $2",
+            vec![
+                (PgBuiltInOids::OIDOID.oid(), database.into_datum()),
+                (PgBuiltInOids::OIDOID.oid(), role.into_datum()),
+            ],
+        );
+        let config = match config {
+            Ok(config) => config.unwrap_or_default(),
+            // nothing is set for this scope
+            Err(spi::Error::InvalidPosition) => return Ok(None),
+            Err(e) => return Err(e),
+        };
+        Ok(config.into_iter().flatten().find_map(|entry| {
+            let (key, value) = entry.split_once('=')?;
+            key.eq_ignore_ascii_case(name).then(|| value.to_string())
+        }))
+    }
+
+    /// Store `value` for the GUC named `name` in this scope, with `ALTER DATABASE` or
+    /// `ALTER ROLE`, so it's checked and permissions are enforced the same way.
+    ///
+    /// The value is passed as a single string literal, so list-valued GUCs like `search_path`
+    /// need [`SettingScope::set_list()`] instead
+    pub fn set(&self, name: &str, value: &str) -> spi::Result<()> {
+        Spi::run(&format!(
+            "{} SET {} = {}",
+            self.alter_command(),
+            spi::quote_identifier(name),
+            spi::quote_literal(value)
+        ))
+    }
+
+    /// Like [`SettingScope::set()`], for a GUC whose value is a list, such as `search_path`
+    pub fn set_list<S: AsRef<str>>(&self, name: &str, values: &[S]) -> spi::Result<()> {
+        let values =
+            values.iter().map(|value| spi::quote_literal(value.as_ref())).collect::<Vec<_>>();
+        Spi::run(&format!(
+            "{} SET {} = {}",
+            self.alter_command(),
+            spi::quote_identifier(name),
+            values.join(", ")
+        ))
+    }
+
+    /// Remove the value stored for the GUC named `name` in this scope, if any
+    pub fn reset(&self, name: &str) -> spi::Result<()> {
+        Spi::run(&format!("{} RESET {}", self.alter_command(), spi::quote_identifier(name)))
+    }
+
+    /// The value a new session of `role` connected to `database` would start with for the GUC
+    /// named `name`, and the scope it comes from.  Like Postgres, this prefers the most specific
+    /// scope:  the role in that database, then the role, then the database, then all roles.
+    ///
+    /// `None` means no scope sets it, so the server-wide value from `postgresql.conf` applies
+    pub fn effective(
+        role: &'a str,
+        database: &'a str,
+        name: &str,
+    ) -> spi::Result<Option<(String, SettingScope<'a>)>> {
+        for scope in [
+            SettingScope::RoleInDatabase { role, database },
+            SettingScope::Role(role),
+            SettingScope::Database(database),
+            SettingScope::AllRoles,
+        ] {
+            if let Some(value) = scope.get(name)? {
+                return Ok(Some((value, scope)));
+            }
+        }
+        Ok(None)
+    }
+
+    /// The `(setdatabase, setrole)` key of this scope in `pg_db_role_setting`
+    fn oids(&self) -> (pg_sys::Oid, pg_sys::Oid) {
+        unsafe {
+            match self {
+                SettingScope::Database(database) => {
+                    (pg_sys::get_database_oid(database.as_pg_cstr(), false), pg_sys::InvalidOid)
+                }
+                SettingScope::Role(role) => {
+                    (pg_sys::InvalidOid, pg_sys::get_role_oid(role.as_pg_cstr(), false))
+                }
+                SettingScope::RoleInDatabase { role, database } => (
+                    pg_sys::get_database_oid(database.as_pg_cstr(), false),
+                    pg_sys::get_role_oid(role.as_pg_cstr(), false),
+                ),
+                SettingScope::AllRoles => (pg_sys::InvalidOid, pg_sys::InvalidOid),
+            }
+        }
+    }
+
+    fn alter_command(&self) -> String {
+        match self {
+            SettingScope::Database(database) => {
+                format!("ALTER DATABASE {}", spi::quote_identifier(database))
+            }
+            SettingScope::Role(role) => format!("ALTER ROLE {}", spi::quote_identifier(role)),
+            SettingScope::RoleInDatabase { role, database } => format!(
+                "ALTER ROLE {} IN DATABASE {}",
+                spi::quote_identifier(role),
+                spi::quote_identifier(database)
+            ),
+            SettingScope::AllRoles => String::from("ALTER ROLE ALL"),
+        }
+    }
+}
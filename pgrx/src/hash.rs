@@ -0,0 +1,124 @@
+/*
+Portions Copyright 2019-2021 ZomboDB, LLC.
+Portions Copyright 2021-2022 Technology Concepts & Design, Inc. <support@tcdi.com>
+
+All rights reserved.
+
+Use of this source code is governed by the MIT license that can be found in the LICENSE file.
+*/
+
+//! Hashing Datums with the same functions Postgres uses
+//!
+//! Hash indexes, hash joins, hash aggregation, and hash partitioning all hash values with the
+//! support functions of the type's default hash operator class.  [`hash_datum()`] and
+//! [`hash_datum_extended()`] call those same functions, so an extension assigning rows to buckets
+//! or computing dedup keys agrees exactly with Postgres.  [`hash_partition_bucket()`] goes one step
+//! further and computes which partition of a `PARTITION BY HASH` table a row belongs in.
+//!
+//! ```rust,no_run
+//! use pgrx::hash::hash_partition_bucket;
+//! use pgrx::prelude::*;
+//!
+//! #[pg_extern]
+//! fn which_partition(id: i64, modulus: i64) -> i64 {
+//!     let key = (id.into_datum(), pg_sys::INT8OID);
+//!     hash_partition_bucket(&[key], modulus as u64) as i64
+//! }
+//! ```
+use crate::datum::lookup_type_name;
+use crate::{ereport, pg_sys, PgSqlErrorCode};
+
+/// The seed Postgres uses when hashing partition keys
+pub const HASH_PARTITION_SEED: u64 = 0x7A5B22367996DCFD;
+
+/// The 32-bit hash of `datum`, a value of type `typoid`, as computed by the hash function of the
+/// type's default hash operator class
+///
+/// # Errors
+///
+/// Raises a Postgres `ERROR` if the type has no default hash operator class
+pub fn hash_datum(datum: pg_sys::Datum, typoid: pg_sys::Oid) -> u32 {
+    unsafe {
+        let entry = lookup_hash_support(typoid, pg_sys::TYPECACHE_HASH_PROC_FINFO);
+        if (*entry).hash_proc == pg_sys::InvalidOid {
+            no_hash_function(typoid);
+        }
+        pg_sys::FunctionCall1Coll(
+            &mut (*entry).hash_proc_finfo,
+            pg_sys::get_typcollation(typoid),
+            datum,
+        )
+        .value() as u32
+    }
+}
+
+/// The 64-bit hash of `datum`, a value of type `typoid`, as computed with `seed` by the extended
+/// hash function of the type's default hash operator class.  With a `seed` of zero, the low 32
+/// bits are the same as [`hash_datum()`]'s.
+///
+/// # Errors
+///
+/// Raises a Postgres `ERROR` if the type has no default hash operator class, or it has no
+/// extended hash function
+pub fn hash_datum_extended(datum: pg_sys::Datum, typoid: pg_sys::Oid, seed: u64) -> u64 {
+    unsafe {
+        let entry = lookup_hash_support(typoid, pg_sys::TYPECACHE_HASH_EXTENDED_PROC_FINFO);
+        if (*entry).hash_extended_proc == pg_sys::InvalidOid {
+            no_hash_function(typoid);
+        }
+        pg_sys::FunctionCall2Coll(
+            &mut (*entry).hash_extended_proc_finfo,
+            pg_sys::get_typcollation(typoid),
+            datum,
+            pg_sys::Datum::from(seed),
+        )
+        .value() as u64
+    }
+}
+
+/// Combine two 32-bit hashes, like Postgres' `hash_combine()`
+#[inline]
+pub fn hash_combine(a: u32, b: u32) -> u32 {
+    a ^ b.wrapping_add(0x9e3779b9).wrapping_add(a << 6).wrapping_add(a >> 2)
+}
+
+/// Combine two 64-bit hashes, like Postgres' `hash_combine64()`
+#[inline]
+pub fn hash_combine64(a: u64, b: u64) -> u64 {
+    a ^ b.wrapping_add(0x49a0f4dd15e5a8e3).wrapping_add(a << 54).wrapping_add(a >> 7)
+}
+
+/// The remainder, for a `PARTITION BY HASH` table with `modulus` partitions, of a row whose
+/// partition key columns have the values in `keys`, each paired with its type.  `None` is a
+/// SQL `NULL`.
+///
+/// The row belongs in the partition created `FOR VALUES WITH (MODULUS modulus, REMAINDER r)`.
+/// The keys are hashed with the types' default hash operator classes and collations, which is
+/// what a partition key uses unless it names another.
+///
+/// # Panics
+///
+/// Panics if `modulus` is zero
+pub fn hash_partition_bucket(keys: &[(Option<pg_sys::Datum>, pg_sys::Oid)], modulus: u64) -> u64 {
+    assert!(modulus > 0, "modulus must be greater than zero");
+    let hash = keys.iter().fold(0, |hash, &(datum, typoid)| match datum {
+        Some(datum) => {
+            hash_combine64(hash, hash_datum_extended(datum, typoid, HASH_PARTITION_SEED))
+        }
+        // nulls don't contribute to the hash
+        None => hash,
+    });
+    hash % modulus
+}
+
+unsafe fn lookup_hash_support(typoid: pg_sys::Oid, flags: u32) -> *mut pg_sys::TypeCacheEntry {
+    pg_sys::lookup_type_cache(typoid, flags as _)
+}
+
+fn no_hash_function(typoid: pg_sys::Oid) -> ! {
+    ereport!(
+        ERROR,
+        PgSqlErrorCode::ERRCODE_UNDEFINED_FUNCTION,
+        format!("could not identify a hash function for type {}", lookup_type_name(typoid))
+    );
+}
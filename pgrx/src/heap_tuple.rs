@@ -170,6 +170,21 @@ impl<'a> PgHeapTuple<'a, AllocatedByPostgres> {
     }
 }
 
+impl<'a> PgHeapTuple<'a, AllocatedByRust> {
+    /// Wraps a [pg_sys::HeapTuple] that Rust now owns, and will `pfree()` when it's dropped.
+    ///
+    /// ## Safety
+    ///
+    /// `heap_tuple` must be a valid, palloc'd, heap tuple nothing else will free, of the shape
+    /// `tupdesc` describes
+    pub(crate) unsafe fn from_owned_heap_tuple(
+        tupdesc: PgTupleDesc<'a>,
+        heap_tuple: pg_sys::HeapTuple,
+    ) -> Self {
+        Self { tuple: PgBox::<_, AllocatedByRust>::from_rust(heap_tuple), tupdesc }
+    }
+}
+
 impl<'a> PgHeapTuple<'a, AllocatedByRust> {
     /** Create a new heap tuple in the shape of a defined composite type
 
@@ -310,20 +325,7 @@ impl<'a> PgHeapTuple<'a, AllocatedByRust> {
         unsafe {
             match self.get_attribute_by_index(attno) {
                 None => return Err(TryFromDatumError::NoSuchAttributeNumber(attno)),
-                Some(att) => {
-                    let type_oid = T::type_oid();
-                    let composite_type_oid = value.composite_type_oid();
-                    let is_compatible_composite_types =
-                        type_oid == pg_sys::RECORDOID && composite_type_oid == Some(att.atttypid);
-                    if !is_compatible_composite_types && !T::is_compatible_with(att.atttypid) {
-                        return Err(TryFromDatumError::IncompatibleTypes {
-                            rust_type: std::any::type_name::<T>(),
-                            rust_oid: att.atttypid,
-                            datum_type: lookup_type_name(type_oid),
-                            datum_oid: type_oid,
-                        });
-                    }
-                }
+                Some(att) => check_compatible(att, &value)?,
             }
 
             let mut datums =
@@ -354,6 +356,122 @@ impl<'a> PgHeapTuple<'a, AllocatedByRust> {
     }
 }
 
+/// Can `value` be stored in the attribute `att`?
+fn check_compatible<T: IntoDatum>(
+    att: &pg_sys::FormData_pg_attribute,
+    value: &T,
+) -> Result<(), TryFromDatumError> {
+    let type_oid = T::type_oid();
+    let composite_type_oid = value.composite_type_oid();
+    let is_compatible_composite_types =
+        type_oid == pg_sys::RECORDOID && composite_type_oid == Some(att.atttypid);
+    if !is_compatible_composite_types && !T::is_compatible_with(att.atttypid) {
+        return Err(TryFromDatumError::IncompatibleTypes {
+            rust_type: std::any::type_name::<T>(),
+            rust_oid: att.atttypid,
+            datum_type: lookup_type_name(type_oid),
+            datum_oid: type_oid,
+        });
+    }
+    Ok(())
+}
+
+/** Builds a [`PgHeapTuple`] attribute by attribute
+
+Unlike [`PgHeapTuple::set_by_name`], which forms a new tuple for every attribute set, the builder
+collects the attributes' Datums and forms the tuple once, in [`PgHeapTupleBuilder::build`].
+Attributes that aren't set are NULL.
+
+```rust,no_run
+use pgrx::heap_tuple::PgHeapTupleBuilder;
+use pgrx::prelude::*;
+
+Spi::run("CREATE TYPE dog AS (name text, age int);");
+let mut builder = PgHeapTupleBuilder::for_composite_type("dog").unwrap();
+builder.set_by_name("name", "Brandy").unwrap().set_by_name("age", 42).unwrap();
+let heap_tuple = builder.build();
+
+assert_eq!(heap_tuple.get_by_name("name").unwrap(), Some("Brandy".to_string()));
+```
+*/
+pub struct PgHeapTupleBuilder<'a> {
+    tupdesc: PgTupleDesc<'a>,
+    datums: Vec<pg_sys::Datum>,
+    nulls: Vec<bool>,
+}
+
+impl<'a> PgHeapTupleBuilder<'a> {
+    /// Starts a tuple of the shape `tupdesc` describes, such as a trigger's relation's or an SRF's
+    /// result type's
+    pub fn new(tupdesc: PgTupleDesc<'a>) -> Self {
+        let natts = tupdesc.len();
+        Self { tupdesc, datums: vec![pg_sys::Datum::from(0); natts], nulls: vec![true; natts] }
+    }
+
+    /// Starts a tuple in the shape of a defined composite type
+    ///
+    /// ## Errors
+    /// - return [`PgHeapTupleError::NoSuchType`] if the type doesn't exist
+    pub fn for_composite_type(type_name: &str) -> Result<Self, PgHeapTupleError> {
+        PgTupleDesc::for_composite_type(type_name)
+            .map(Self::new)
+            .ok_or_else(|| PgHeapTupleError::NoSuchType(type_name.to_string()))
+    }
+
+    /// Sets the named attribute.  Attribute names are case sensitive.
+    ///
+    /// ## Errors
+    /// - return [`TryFromDatumError::NoSuchAttributeName`] if the attribute does not exist
+    /// - return [`TryFromDatumError::IncompatibleTypes`] if the Rust type of the `value` is not
+    /// compatible with the attribute's Postgres type
+    pub fn set_by_name<T: IntoDatum>(
+        &mut self,
+        attname: &str,
+        value: T,
+    ) -> Result<&mut Self, TryFromDatumError> {
+        let attno = self
+            .tupdesc
+            .iter()
+            .position(|att| !att.is_dropped() && att.name() == attname)
+            .ok_or_else(|| TryFromDatumError::NoSuchAttributeName(attname.to_string()))?;
+        self.set_by_index(NonZeroUsize::new(attno + 1).unwrap(), value)
+    }
+
+    /// Sets the attribute at `attno`.  Attribute numbers start at 1, not 0.
+    ///
+    /// ## Errors
+    /// - return [`TryFromDatumError::NoSuchAttributeNumber`] if the attribute does not exist
+    /// - return [`TryFromDatumError::IncompatibleTypes`] if the Rust type of the `value` is not
+    /// compatible with the attribute's Postgres type
+    pub fn set_by_index<T: IntoDatum>(
+        &mut self,
+        attno: NonZeroUsize,
+        value: T,
+    ) -> Result<&mut Self, TryFromDatumError> {
+        match self.tupdesc.get(attno.get() - 1) {
+            Some(att) if !att.is_dropped() => check_compatible(att, &value)?,
+            _ => return Err(TryFromDatumError::NoSuchAttributeNumber(attno)),
+        }
+
+        let datum = value.into_datum();
+        self.nulls[attno.get() - 1] = datum.is_none();
+        self.datums[attno.get() - 1] = datum.unwrap_or(0.into());
+        Ok(self)
+    }
+
+    /// Forms the tuple from the attributes set so far
+    pub fn build(mut self) -> PgHeapTuple<'a, AllocatedByRust> {
+        unsafe {
+            let heap_tuple = pg_sys::heap_form_tuple(
+                self.tupdesc.as_ptr(),
+                self.datums.as_mut_ptr(),
+                self.nulls.as_mut_ptr(),
+            );
+            PgHeapTuple::from_owned_heap_tuple(self.tupdesc, heap_tuple)
+        }
+    }
+}
+
 impl<'a, AllocatedBy: WhoAllocated> IntoDatum for PgHeapTuple<'a, AllocatedBy> {
     // Delegate to `into_composite_datum()` as this will normally be used with composite types.
     // See `into_trigger_datum()` if using as a trigger.
@@ -403,6 +521,12 @@ impl<'a, AllocatedBy: WhoAllocated> PgHeapTuple<'a, AllocatedBy> {
         self.tuple.into_pg()
     }
 
+    /// The underlying [`pg_sys::HeapTupleData`], which this [`PgHeapTuple`] still owns
+    #[inline]
+    pub(crate) fn as_ptr(&self) -> *mut pg_sys::HeapTupleData {
+        self.tuple.as_ptr()
+    }
+
     /// Returns the number of attributes in this [`PgHeapTuple`].
     #[inline]
     pub fn len(&self) -> usize {
@@ -495,12 +619,12 @@ impl<'a, AllocatedBy: WhoAllocated> PgHeapTuple<'a, AllocatedBy> {
                     if datum.is_none() {
                         return Ok(None);
                     }
-                    match T::type_oid() {
+                    crate::datum::counted_from_datum::<T, _>(|| match T::type_oid() {
                         record @ pg_sys::RECORDOID => {
                             T::try_from_datum(datum.unwrap(), false, record)
                         }
                         _ => T::try_from_datum(datum.unwrap(), false, att.type_oid().value()),
-                    }
+                    })
                 }
             }
         }
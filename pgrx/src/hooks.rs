@@ -160,6 +160,24 @@ pub trait PgHooks {
         prev_hook(parse, query_string, cursor_options, bound_params)
     }
 
+    /// Hook for plugins to edit the paths generated for a base relation, before the planner picks
+    /// the cheapest.  See [`RelPathList`](crate::pathlist::RelPathList) for doing so safely
+    fn set_rel_pathlist(
+        &mut self,
+        root: PgBox<pg_sys::PlannerInfo>,
+        rel: PgBox<pg_sys::RelOptInfo>,
+        rti: pg_sys::Index,
+        rte: PgBox<pg_sys::RangeTblEntry>,
+        prev_hook: fn(
+            root: PgBox<pg_sys::PlannerInfo>,
+            rel: PgBox<pg_sys::RelOptInfo>,
+            rti: pg_sys::Index,
+            rte: PgBox<pg_sys::RangeTblEntry>,
+        ) -> HookResult<()>,
+    ) -> HookResult<()> {
+        prev_hook(root, rel, rti, rte)
+    }
+
     fn post_parse_analyze(
         &mut self,
         pstate: PgBox<pg_sys::ParseState>,
@@ -182,7 +200,10 @@ pub trait PgHooks {
 }
 
 struct Hooks {
-    current_hook: Box<&'static mut (dyn PgHooks)>,
+    // in the order they were registered
+    registered: Vec<&'static mut dyn PgHooks>,
+    // for each hook point, the position in `registered`, plus one, of the hook running there
+    running: [usize; HOOK_POINTS],
     prev_emit_log_hook: pg_sys::emit_log_hook_type,
     prev_executor_start_hook: pg_sys::ExecutorStart_hook_type,
     prev_executor_run_hook: pg_sys::ExecutorRun_hook_type,
@@ -191,18 +212,85 @@ struct Hooks {
     prev_executor_check_perms_hook: pg_sys::ExecutorCheckPerms_hook_type,
     prev_process_utility_hook: pg_sys::ProcessUtility_hook_type,
     prev_planner_hook: pg_sys::planner_hook_type,
+    prev_set_rel_pathlist_hook: pg_sys::set_rel_pathlist_hook_type,
     prev_post_parse_analyze_hook: pg_sys::post_parse_analyze_hook_type,
 }
 
 static mut HOOKS: Option<Hooks> = None;
 
+const HOOK_POINTS: usize = 10;
+
+#[derive(Copy, Clone)]
+enum HookPoint {
+    EmitLog,
+    ExecutorStart,
+    ExecutorRun,
+    ExecutorFinish,
+    ExecutorEnd,
+    ExecutorCheckPerms,
+    ProcessUtility,
+    Planner,
+    SetRelPathlist,
+    PostParseAnalyze,
+}
+
+/// A registered `PgHooks` instance, about to run at a hook point
+struct HookCall {
+    point: HookPoint,
+    // its position in `Hooks::registered`, plus one
+    position: usize,
+}
+
+impl HookCall {
+    /// The most recently registered instance, which runs first
+    unsafe fn first(point: HookPoint) -> HookCall {
+        HookCall { point, position: HOOKS.as_ref().unwrap().registered.len() }
+    }
+
+    /// The instance registered before the one running at `point`, which its `prev_hook` runs, or
+    /// `None` if that's the hook Postgres had before any were registered
+    unsafe fn next(point: HookPoint) -> Option<HookCall> {
+        let running = HOOKS.as_ref().unwrap().running[point as usize];
+        (running > 1).then(|| HookCall { point, position: running - 1 })
+    }
+
+    unsafe fn run<R>(self, f: impl FnOnce(&mut dyn PgHooks) -> R) -> R {
+        struct Restore {
+            point: HookPoint,
+            running: usize,
+        }
+
+        impl Drop for Restore {
+            fn drop(&mut self) {
+                unsafe { HOOKS.as_mut().unwrap().running[self.point as usize] = self.running }
+            }
+        }
+
+        let hooks = HOOKS.as_mut().unwrap();
+        // hooks can be reentered, by a query the hook itself runs, so put back what was running
+        // here before, even if the hook raises an error
+        let _restore = Restore {
+            point: self.point,
+            running: std::mem::replace(&mut hooks.running[self.point as usize], self.position),
+        };
+        let hook: *mut dyn PgHooks = &mut *hooks.registered[self.position - 1];
+        f(&mut *hook)
+    }
+}
+
 /// Register a `PgHook` instance to respond to the various hook points
+///
+/// More than one instance can be registered.  The most recently registered one runs first, and its
+/// `prev_hook` runs the one registered before it, down to the hooks Postgres had installed before
+/// the first was registered, such as those of other extensions.
 pub unsafe fn register_hook(hook: &'static mut (dyn PgHooks)) {
-    if HOOKS.is_some() {
-        panic!("PgHook instance already registered");
+    if let Some(hooks) = HOOKS.as_mut() {
+        hooks.registered.push(hook);
+        return;
     }
     HOOKS = Some(Hooks {
-        current_hook: Box::new(hook),
+        registered: vec![hook],
+        running: [0; HOOK_POINTS],
         prev_executor_start_hook: pg_sys::ExecutorStart_hook
             .replace(pgrx_executor_start)
             .or(Some(pgrx_standard_executor_start_wrapper)),
@@ -224,6 +312,7 @@ pub unsafe fn register_hook(hook: &'static mut (dyn PgHooks)) {
         prev_planner_hook: pg_sys::planner_hook
             .replace(pgrx_planner)
             .or(Some(pgrx_standard_planner_wrapper)),
+        prev_set_rel_pathlist_hook: pg_sys::set_rel_pathlist_hook.replace(pgrx_set_rel_pathlist),
         prev_post_parse_analyze_hook: pg_sys::post_parse_analyze_hook
             .replace(pgrx_post_parse_analyze),
         prev_emit_log_hook: pg_sys::emit_log_hook.replace(pgrx_emit_log),
@@ -232,9 +321,11 @@ pub unsafe fn register_hook(hook: &'static mut (dyn PgHooks)) {
     #[pg_guard]
     unsafe extern "C" fn xact_callback(event: pg_sys::XactEvent, _data: void_mut_ptr) {
         match event {
-            pg_sys::XactEvent_XACT_EVENT_ABORT => HOOKS.as_mut().unwrap().current_hook.abort(),
+            pg_sys::XactEvent_XACT_EVENT_ABORT => {
+                HOOKS.as_mut().unwrap().registered.iter_mut().rev().for_each(|hook| hook.abort())
+            }
             pg_sys::XactEvent_XACT_EVENT_PRE_COMMIT => {
-                HOOKS.as_mut().unwrap().current_hook.commit()
+                HOOKS.as_mut().unwrap().registered.iter_mut().rev().for_each(|hook| hook.commit())
             }
             _ => { /* noop */ }
         }
@@ -246,6 +337,9 @@ pub unsafe fn register_hook(hook: &'static mut (dyn PgHooks)) {
 #[pg_guard]
 unsafe extern "C" fn pgrx_executor_start(query_desc: *mut pg_sys::QueryDesc, eflags: i32) {
     fn prev(query_desc: PgBox<pg_sys::QueryDesc>, eflags: i32) -> HookResult<()> {
+        if let Some(hook) = unsafe { HookCall::next(HookPoint::ExecutorStart) } {
+            return unsafe { hook.run(|hook| hook.executor_start(query_desc, eflags, prev)) };
+        }
         unsafe {
             (HOOKS.as_mut().unwrap().prev_executor_start_hook.as_ref().unwrap())(
                 query_desc.into_pg(),
@@ -254,8 +348,8 @@ unsafe extern "C" fn pgrx_executor_start(query_desc: *mut pg_sys::QueryDesc, efl
         }
         HookResult::new(())
     }
-    let hook = &mut HOOKS.as_mut().unwrap().current_hook;
-    hook.executor_start(PgBox::from_pg(query_desc), eflags, prev);
+    HookCall::first(HookPoint::ExecutorStart)
+        .run(|hook| hook.executor_start(PgBox::from_pg(query_desc), eflags, prev));
 }
 
 #[pg_guard]
@@ -271,6 +365,11 @@ unsafe extern "C" fn pgrx_executor_run(
         count: u64,
         execute_once: bool,
     ) -> HookResult<()> {
+        if let Some(hook) = unsafe { HookCall::next(HookPoint::ExecutorRun) } {
+            return unsafe {
+                hook.run(|hook| hook.executor_run(query_desc, direction, count, execute_once, prev))
+            };
+        }
         unsafe {
             (HOOKS.as_mut().unwrap().prev_executor_run_hook.as_ref().unwrap())(
                 query_desc.into_pg(),
@@ -281,13 +380,17 @@ unsafe extern "C" fn pgrx_executor_run(
         }
         HookResult::new(())
     }
-    let hook = &mut HOOKS.as_mut().unwrap().current_hook;
-    hook.executor_run(PgBox::from_pg(query_desc), direction, count, execute_once, prev);
+    HookCall::first(HookPoint::ExecutorRun).run(|hook| {
+        hook.executor_run(PgBox::from_pg(query_desc), direction, count, execute_once, prev)
+    });
 }
 
 #[pg_guard]
 unsafe extern "C" fn pgrx_executor_finish(query_desc: *mut pg_sys::QueryDesc) {
     fn prev(query_desc: PgBox<pg_sys::QueryDesc>) -> HookResult<()> {
+        if let Some(hook) = unsafe { HookCall::next(HookPoint::ExecutorFinish) } {
+            return unsafe { hook.run(|hook| hook.executor_finish(query_desc, prev)) };
+        }
         unsafe {
             (HOOKS.as_mut().unwrap().prev_executor_finish_hook.as_ref().unwrap())(
                 query_desc.into_pg(),
@@ -295,20 +398,23 @@ unsafe extern "C" fn pgrx_executor_finish(query_desc: *mut pg_sys::QueryDesc) {
         }
         HookResult::new(())
     }
-    let hook = &mut HOOKS.as_mut().unwrap().current_hook;
-    hook.executor_finish(PgBox::from_pg(query_desc), prev);
+    HookCall::first(HookPoint::ExecutorFinish)
+        .run(|hook| hook.executor_finish(PgBox::from_pg(query_desc), prev));
 }
 
 #[pg_guard]
 unsafe extern "C" fn pgrx_executor_end(query_desc: *mut pg_sys::QueryDesc) {
     fn prev(query_desc: PgBox<pg_sys::QueryDesc>) -> HookResult<()> {
+        if let Some(hook) = unsafe { HookCall::next(HookPoint::ExecutorEnd) } {
+            return unsafe { hook.run(|hook| hook.executor_end(query_desc, prev)) };
+        }
         unsafe {
             (HOOKS.as_mut().unwrap().prev_executor_end_hook.as_ref().unwrap())(query_desc.into_pg())
         }
         HookResult::new(())
     }
-    let hook = &mut HOOKS.as_mut().unwrap().current_hook;
-    hook.executor_end(PgBox::from_pg(query_desc), prev);
+    HookCall::first(HookPoint::ExecutorEnd)
+        .run(|hook| hook.executor_end(PgBox::from_pg(query_desc), prev));
 }
 
 #[pg_guard]
@@ -320,6 +426,11 @@ unsafe extern "C" fn pgrx_executor_check_perms(
         range_table: PgList<*mut pg_sys::RangeTblEntry>,
         ereport_on_violation: bool,
     ) -> HookResult<bool> {
+        if let Some(hook) = unsafe { HookCall::next(HookPoint::ExecutorCheckPerms) } {
+            return unsafe {
+                hook.run(|hook| hook.executor_check_perms(range_table, ereport_on_violation, prev))
+            };
+        }
         HookResult::new(unsafe {
             (HOOKS.as_mut().unwrap().prev_executor_check_perms_hook.as_ref().unwrap())(
                 range_table.into_pg(),
@@ -327,8 +438,11 @@ unsafe extern "C" fn pgrx_executor_check_perms(
             )
         })
     }
-    let hook = &mut HOOKS.as_mut().unwrap().current_hook;
-    hook.executor_check_perms(PgList::from_pg(range_table), ereport_on_violation, prev).inner
+    HookCall::first(HookPoint::ExecutorCheckPerms)
+        .run(|hook| {
+            hook.executor_check_perms(PgList::from_pg(range_table), ereport_on_violation, prev)
+        })
+        .inner
 }
 
 #[cfg(any(feature = "pg11", feature = "pg12", feature = "pg13"))]
@@ -345,13 +459,30 @@ unsafe extern "C" fn pgrx_process_utility(
     fn prev(
         pstmt: PgBox<pg_sys::PlannedStmt>,
         query_string: &core::ffi::CStr,
-        _read_only_tree: Option<bool>,
+        read_only_tree: Option<bool>,
         context: pg_sys::ProcessUtilityContext,
         params: PgBox<pg_sys::ParamListInfoData>,
         query_env: PgBox<pg_sys::QueryEnvironment>,
         dest: PgBox<pg_sys::DestReceiver>,
         completion_tag: *mut pg_sys::QueryCompletion,
     ) -> HookResult<()> {
+        if let Some(hook) = unsafe { HookCall::next(HookPoint::ProcessUtility) } {
+            return unsafe {
+                hook.run(|hook| {
+                    hook.process_utility_hook(
+                        pstmt,
+                        query_string,
+                        read_only_tree,
+                        context,
+                        params,
+                        query_env,
+                        dest,
+                        completion_tag,
+                        prev,
+                    )
+                })
+            };
+        }
         HookResult::new(unsafe {
             (HOOKS.as_mut().unwrap().prev_process_utility_hook.as_ref().unwrap())(
                 pstmt.into_pg(),
@@ -365,19 +496,21 @@ unsafe extern "C" fn pgrx_process_utility(
         })
     }
 
-    let hook = &mut HOOKS.as_mut().unwrap().current_hook;
-    hook.process_utility_hook(
-        PgBox::from_pg(pstmt),
-        core::ffi::CStr::from_ptr(query_string),
-        None,
-        context,
-        PgBox::from_pg(params),
-        PgBox::from_pg(query_env),
-        PgBox::from_pg(dest),
-        completion_tag,
-        prev,
-    )
-    .inner
+    HookCall::first(HookPoint::ProcessUtility)
+        .run(|hook| {
+            hook.process_utility_hook(
+                PgBox::from_pg(pstmt),
+                core::ffi::CStr::from_ptr(query_string),
+                None,
+                context,
+                PgBox::from_pg(params),
+                PgBox::from_pg(query_env),
+                PgBox::from_pg(dest),
+                completion_tag,
+                prev,
+            )
+        })
+        .inner
 }
 #[cfg(any(feature = "pg14", feature = "pg15"))]
 #[pg_guard]
@@ -401,6 +534,23 @@ unsafe extern "C" fn pgrx_process_utility(
         dest: PgBox<pg_sys::DestReceiver>,
         completion_tag: *mut pg_sys::QueryCompletion,
     ) -> HookResult<()> {
+        if let Some(hook) = unsafe { HookCall::next(HookPoint::ProcessUtility) } {
+            return unsafe {
+                hook.run(|hook| {
+                    hook.process_utility_hook(
+                        pstmt,
+                        query_string,
+                        read_only_tree,
+                        context,
+                        params,
+                        query_env,
+                        dest,
+                        completion_tag,
+                        prev,
+                    )
+                })
+            };
+        }
         HookResult::new(unsafe {
             (HOOKS.as_mut().unwrap().prev_process_utility_hook.as_ref().unwrap())(
                 pstmt.into_pg(),
@@ -415,19 +565,21 @@ unsafe extern "C" fn pgrx_process_utility(
         })
     }
 
-    let hook = &mut HOOKS.as_mut().unwrap().current_hook;
-    hook.process_utility_hook(
-        PgBox::from_pg(pstmt),
-        core::ffi::CStr::from_ptr(query_string),
-        Some(read_only_tree),
-        context,
-        PgBox::from_pg(params),
-        PgBox::from_pg(query_env),
-        PgBox::from_pg(dest),
-        completion_tag,
-        prev,
-    )
-    .inner
+    HookCall::first(HookPoint::ProcessUtility)
+        .run(|hook| {
+            hook.process_utility_hook(
+                PgBox::from_pg(pstmt),
+                core::ffi::CStr::from_ptr(query_string),
+                Some(read_only_tree),
+                context,
+                PgBox::from_pg(params),
+                PgBox::from_pg(query_env),
+                PgBox::from_pg(dest),
+                completion_tag,
+                prev,
+            )
+        })
+        .inner
 }
 
 #[cfg(any(feature = "pg11", feature = "pg12"))]
@@ -460,10 +612,17 @@ unsafe extern "C" fn pgrx_planner_impl(
 ) -> *mut pg_sys::PlannedStmt {
     fn prev(
         parse: PgBox<pg_sys::Query>,
-        #[allow(unused_variables)] query_string: *const ::std::os::raw::c_char,
+        query_string: *const ::std::os::raw::c_char,
         cursor_options: i32,
         bound_params: PgBox<pg_sys::ParamListInfoData>,
     ) -> HookResult<*mut pg_sys::PlannedStmt> {
+        if let Some(hook) = unsafe { HookCall::next(HookPoint::Planner) } {
+            return unsafe {
+                hook.run(|hook| {
+                    hook.planner(parse, query_string, cursor_options, bound_params, prev)
+                })
+            };
+        }
         HookResult::new(unsafe {
             #[cfg(any(feature = "pg11", feature = "pg12"))]
             {
@@ -485,15 +644,54 @@ unsafe extern "C" fn pgrx_planner_impl(
             }
         })
     }
-    let hook = &mut HOOKS.as_mut().unwrap().current_hook;
-    hook.planner(
-        PgBox::from_pg(parse),
-        query_string,
-        cursor_options,
-        PgBox::from_pg(bound_params),
-        prev,
-    )
-    .inner
+    HookCall::first(HookPoint::Planner)
+        .run(|hook| {
+            hook.planner(
+                PgBox::from_pg(parse),
+                query_string,
+                cursor_options,
+                PgBox::from_pg(bound_params),
+                prev,
+            )
+        })
+        .inner
+}
+
+#[pg_guard]
+unsafe extern "C" fn pgrx_set_rel_pathlist(
+    root: *mut pg_sys::PlannerInfo,
+    rel: *mut pg_sys::RelOptInfo,
+    rti: pg_sys::Index,
+    rte: *mut pg_sys::RangeTblEntry,
+) {
+    fn prev(
+        root: PgBox<pg_sys::PlannerInfo>,
+        rel: PgBox<pg_sys::RelOptInfo>,
+        rti: pg_sys::Index,
+        rte: PgBox<pg_sys::RangeTblEntry>,
+    ) -> HookResult<()> {
+        if let Some(hook) = unsafe { HookCall::next(HookPoint::SetRelPathlist) } {
+            return unsafe { hook.run(|hook| hook.set_rel_pathlist(root, rel, rti, rte, prev)) };
+        }
+        HookResult::new(unsafe {
+            match HOOKS.as_mut().unwrap().prev_set_rel_pathlist_hook.as_ref() {
+                None => (),
+                Some(f) => (f)(root.as_ptr(), rel.as_ptr(), rti, rte.as_ptr()),
+            }
+        })
+    }
+
+    HookCall::first(HookPoint::SetRelPathlist)
+        .run(|hook| {
+            hook.set_rel_pathlist(
+                PgBox::from_pg(root),
+                PgBox::from_pg(rel),
+                rti,
+                PgBox::from_pg(rte),
+                prev,
+            )
+        })
+        .inner
 }
 
 #[cfg(any(feature = "pg10", feature = "pg11", feature = "pg12", feature = "pg13"))]
@@ -505,8 +703,13 @@ unsafe extern "C" fn pgrx_post_parse_analyze(
     fn prev(
         parse_state: PgBox<pg_sys::ParseState>,
         query: PgBox<pg_sys::Query>,
-        _jumble_state: Option<PgBox<JumbleState>>,
+        jumble_state: Option<PgBox<JumbleState>>,
     ) -> HookResult<()> {
+        if let Some(hook) = unsafe { HookCall::next(HookPoint::PostParseAnalyze) } {
+            return unsafe {
+                hook.run(|hook| hook.post_parse_analyze(parse_state, query, jumble_state, prev))
+            };
+        }
         HookResult::new(unsafe {
             match HOOKS.as_mut().unwrap().prev_post_parse_analyze_hook.as_ref() {
                 None => (),
@@ -515,8 +718,11 @@ unsafe extern "C" fn pgrx_post_parse_analyze(
         })
     }
 
-    let hook = &mut HOOKS.as_mut().unwrap().current_hook;
-    hook.post_parse_analyze(PgBox::from_pg(parse_state), PgBox::from_pg(query), None, prev).inner
+    HookCall::first(HookPoint::PostParseAnalyze)
+        .run(|hook| {
+            hook.post_parse_analyze(PgBox::from_pg(parse_state), PgBox::from_pg(query), None, prev)
+        })
+        .inner
 }
 
 #[cfg(any(feature = "pg14", feature = "pg15"))]
@@ -531,29 +737,41 @@ unsafe extern "C" fn pgrx_post_parse_analyze(
         query: PgBox<pg_sys::Query>,
         jumble_state: Option<PgBox<JumbleState>>,
     ) -> HookResult<()> {
+        if let Some(hook) = unsafe { HookCall::next(HookPoint::PostParseAnalyze) } {
+            return unsafe {
+                hook.run(|hook| hook.post_parse_analyze(parse_state, query, jumble_state, prev))
+            };
+        }
         HookResult::new(unsafe {
             match HOOKS.as_mut().unwrap().prev_post_parse_analyze_hook.as_ref() {
                 None => (),
-                Some(f) => {
-                    (f)(parse_state.as_ptr(), query.as_ptr(), jumble_state.unwrap().as_ptr())
-                }
+                Some(f) => (f)(
+                    parse_state.as_ptr(),
+                    query.as_ptr(),
+                    jumble_state.map_or(std::ptr::null_mut(), |jumble_state| jumble_state.as_ptr()),
+                ),
             }
         })
     }
 
-    let hook = &mut HOOKS.as_mut().unwrap().current_hook;
-    hook.post_parse_analyze(
-        PgBox::from_pg(parse_state),
-        PgBox::from_pg(query),
-        Some(PgBox::from_pg(jumble_state)),
-        prev,
-    )
-    .inner
+    HookCall::first(HookPoint::PostParseAnalyze)
+        .run(|hook| {
+            hook.post_parse_analyze(
+                PgBox::from_pg(parse_state),
+                PgBox::from_pg(query),
+                (!jumble_state.is_null()).then(|| PgBox::from_pg(jumble_state)),
+                prev,
+            )
+        })
+        .inner
 }
 
 #[pg_guard]
 unsafe extern "C" fn pgrx_emit_log(error_data: *mut pg_sys::ErrorData) {
     fn prev(error_data: PgBox<pg_sys::ErrorData>) -> HookResult<()> {
+        if let Some(hook) = unsafe { HookCall::next(HookPoint::EmitLog) } {
+            return unsafe { hook.run(|hook| hook.emit_log(error_data, prev)) };
+        }
         HookResult::new(unsafe {
             match HOOKS.as_mut().unwrap().prev_emit_log_hook.as_ref() {
                 None => (),
@@ -562,8 +780,9 @@ unsafe extern "C" fn pgrx_emit_log(error_data: *mut pg_sys::ErrorData) {
         })
     }
 
-    let hook = &mut HOOKS.as_mut().unwrap().current_hook;
-    hook.emit_log(PgBox::from_pg(error_data), prev).inner
+    HookCall::first(HookPoint::EmitLog)
+        .run(|hook| hook.emit_log(PgBox::from_pg(error_data), prev))
+        .inner
 }
 
 #[pg_guard]
@@ -197,7 +197,7 @@ seq!(I in 0..32 {
                     let mut nulls = [false; I];
 
                     #(
-                        match self.N.into_datum() {
+                        match crate::datum::counted_into_datum(self.N) {
                             Some(datum) => datums[N] = datum,
                             None => nulls[N] = true,
                         }
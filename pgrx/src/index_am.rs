@@ -0,0 +1,559 @@
+/*
+Portions Copyright 2019-2021 ZomboDB, LLC.
+Portions Copyright 2021-2022 Technology Concepts & Design, Inc. <support@tcdi.com>
+
+All rights reserved.
+
+Use of this source code is governed by the MIT license that can be found in the LICENSE file.
+*/
+
+//! Index access methods, the kinds of index `CREATE INDEX ... USING` can build
+//!
+//! Implement [`IndexAm`] for a type holding the state of an index scan, and declare it with
+//! [`#[pg_index_am]`](macro@crate::pg_index_am), which generates the access method's handler
+//! function and its `CREATE ACCESS METHOD` statement:
+//!
+//! ```rust,no_run
+//! use pgrx::index_am::{IndexAm, IndexEntry, IndexMatch, IndexScanKey};
+//! use pgrx::prelude::*;
+//! use pgrx::PgRelation;
+//!
+//! struct Exact {
+//!     matches: Vec<pg_sys::ItemPointerData>,
+//! }
+//!
+//! #[pg_index_am(name = "exact")]
+//! impl IndexAm for Exact {
+//!     const STRATEGIES: u16 = 1;
+//!
+//!     fn insert(index: &PgRelation, entry: &IndexEntry) {
+//!         // store `entry.tid()` under `entry.get::<i32>(1)`
+//!     }
+//!
+//!     fn bulk_delete(
+//!         index: &PgRelation,
+//!         is_dead: &mut dyn FnMut(pg_sys::ItemPointerData) -> bool,
+//!     ) -> usize {
+//!         // remove the stored tids `is_dead()`, returning how many
+//!         0
+//!     }
+//!
+//!     fn begin_scan(_index: &PgRelation) -> Self {
+//!         Exact { matches: Vec::new() }
+//!     }
+//!
+//!     fn rescan(&mut self, keys: &[IndexScanKey]) {
+//!         // look up the tids stored under `keys[0].argument::<i32>()`
+//!     }
+//!
+//!     fn get_tuple(&mut self) -> Option<IndexMatch> {
+//!         self.matches.pop().map(|tid| IndexMatch { tid, recheck: false })
+//!     }
+//! }
+//! ```
+//!
+//! ```sql
+//! CREATE OPERATOR CLASS int4_exact_ops DEFAULT FOR TYPE int4 USING exact AS OPERATOR 1 =;
+//! CREATE INDEX ON things USING exact (id);
+//! ```
+//!
+//! pgrx doesn't manage the index's pages:  where an access method keeps its entries is up to it.
+use crate as pgrx; // for #[pg_guard] support from within ourself
+use crate::prelude::*;
+use crate::{PgMemoryContexts, PgRelation, PgTupleDesc};
+use std::os::raw::c_int;
+
+/// An entry to add to an index:  the values of its columns for a row of the table
+pub struct IndexEntry<'a> {
+    tid: pg_sys::ItemPointerData,
+    values: &'a [pg_sys::Datum],
+    isnull: &'a [bool],
+    tupdesc: &'a PgTupleDesc<'a>,
+}
+
+impl<'a> IndexEntry<'a> {
+    /// The table row the entry is for
+    pub fn tid(&self) -> pg_sys::ItemPointerData {
+        self.tid
+    }
+
+    /// The number of indexed columns
+    pub fn natts(&self) -> usize {
+        self.values.len()
+    }
+
+    /// The value of indexed column `attno`, starting at one, or `None` if it's null
+    ///
+    /// # Panics
+    ///
+    /// If there's no such column, or its type isn't compatible with `T`
+    pub fn get<T: FromDatum + IntoDatum>(&self, attno: usize) -> Option<T> {
+        let type_oid = self
+            .tupdesc
+            .get(attno.wrapping_sub(1))
+            .unwrap_or_else(|| panic!("the index has no column {}", attno))
+            .atttypid;
+        assert!(
+            T::is_compatible_with(type_oid),
+            "index column {} is not compatible with {}",
+            attno,
+            std::any::type_name::<T>()
+        );
+        unsafe {
+            T::from_polymorphic_datum(self.values[attno - 1], self.isnull[attno - 1], type_oid)
+        }
+    }
+}
+
+/// A condition of an index scan:  that indexed column [`IndexScanKey::attno()`] is related to
+/// [`IndexScanKey::argument()`] by the operator of the operator class's strategy
+/// [`IndexScanKey::strategy()`]
+pub struct IndexScanKey {
+    attno: usize,
+    strategy: u16,
+    argument: Option<pg_sys::Datum>,
+    type_oid: pg_sys::Oid,
+}
+
+impl IndexScanKey {
+    /// The indexed column compared, starting at one
+    pub fn attno(&self) -> usize {
+        self.attno
+    }
+
+    /// The strategy number of the operator compared with, as in `CREATE OPERATOR CLASS`
+    pub fn strategy(&self) -> u16 {
+        self.strategy
+    }
+
+    /// The type of the value compared with
+    pub fn type_oid(&self) -> pg_sys::Oid {
+        self.type_oid
+    }
+
+    /// The value compared with, or `None` if it's null
+    ///
+    /// # Panics
+    ///
+    /// If its type isn't compatible with `T`
+    pub fn argument<T: FromDatum + IntoDatum>(&self) -> Option<T> {
+        assert!(
+            T::is_compatible_with(self.type_oid),
+            "the scan key's argument is not compatible with {}",
+            std::any::type_name::<T>()
+        );
+        unsafe { T::from_polymorphic_datum(self.argument?, false, self.type_oid) }
+    }
+}
+
+/// A table row an index scan found
+#[derive(Debug, Copy, Clone)]
+pub struct IndexMatch {
+    pub tid: pg_sys::ItemPointerData,
+    /// Whether the scan's conditions must be checked against the row, as the index only
+    /// approximates them
+    pub recheck: bool,
+}
+
+/// The planner's estimate of the cost of an index scan
+#[derive(Debug, Copy, Clone)]
+pub struct IndexCosts {
+    pub startup_cost: f64,
+    pub total_cost: f64,
+    /// The fraction of the table's rows the scan returns
+    pub selectivity: f64,
+    /// How well the order of the index matches the order of the table, from -1 to 1
+    pub correlation: f64,
+    /// The number of pages of the index the scan reads
+    pub pages: f64,
+}
+
+/// An index access method.  An instance is created for each scan of an index
+///
+/// Declare an implementation with [`#[pg_index_am]`](macro@crate::pg_index_am).
+pub trait IndexAm: Sized {
+    /// The number of operator strategies the operator classes of the access method have.  `0`,
+    /// for none in particular, by default
+    const STRATEGIES: u16 = 0;
+    /// The number of support functions the operator classes of the access method have.  `0` by
+    /// default
+    const SUPPORT_FUNCTIONS: u16 = 0;
+    /// Whether an index can have more than one column.  `false` by default
+    const MULTI_COLUMN: bool = false;
+    /// Whether an index can be scanned without a condition on its first column.  `false` by
+    /// default
+    const OPTIONAL_KEY: bool = false;
+
+    /// Prepare the storage of a new index, before the rows of its table are inserted.  Also called
+    /// for the initial, empty, state of an index of an unlogged table
+    fn build_empty(_index: &PgRelation) {}
+
+    /// Add `entry` to `index`.  Called for each row of the table when the index is built, and for
+    /// each row inserted later
+    fn insert(index: &PgRelation, entry: &IndexEntry);
+
+    /// Remove from `index` the entries of the table rows `is_dead()`, which `VACUUM` has removed,
+    /// returning how many were removed
+    fn bulk_delete(
+        index: &PgRelation,
+        is_dead: &mut dyn FnMut(pg_sys::ItemPointerData) -> bool,
+    ) -> usize;
+
+    /// Tidy `index` up at the end of a `VACUUM`, returning the number of entries it has, if known
+    fn vacuum_cleanup(_index: &PgRelation) -> Option<usize> {
+        None
+    }
+
+    /// Adjust the planner's estimate of the cost of scanning the index, which `costs` holds.  By
+    /// default, the estimate for an index with nothing special about it
+    fn cost_estimate(_costs: &mut IndexCosts) {}
+
+    /// Start a scan of `index`
+    fn begin_scan(index: &PgRelation) -> Self;
+
+    /// Start, or restart, the scan, looking for the entries meeting all of `keys`
+    fn rescan(&mut self, keys: &[IndexScanKey]);
+
+    /// The next row the scan finds, or `None` once there are no more
+    fn get_tuple(&mut self) -> Option<IndexMatch>;
+
+    /// Finish the scan
+    fn end_scan(&mut self) {}
+}
+
+/// The `IndexAmRoutine` for `T`, as its handler function returns.  Used by
+/// [`#[pg_index_am]`](macro@crate::pg_index_am)
+#[doc(hidden)]
+pub fn index_am_routine<T: IndexAm>() -> pg_sys::Datum {
+    unsafe {
+        let mut routine =
+            PgBox::<pg_sys::IndexAmRoutine>::alloc_node(pg_sys::NodeTag_T_IndexAmRoutine);
+        routine.amstrategies = T::STRATEGIES;
+        routine.amsupport = T::SUPPORT_FUNCTIONS;
+        routine.amcanmulticol = T::MULTI_COLUMN;
+        routine.amoptionalkey = T::OPTIONAL_KEY;
+        routine.amkeytype = pg_sys::InvalidOid;
+
+        routine.ambuild = Some(ambuild::<T>);
+        routine.ambuildempty = Some(ambuildempty::<T>);
+        routine.aminsert = Some(aminsert::<T>);
+        routine.ambulkdelete = Some(ambulkdelete::<T>);
+        routine.amvacuumcleanup = Some(amvacuumcleanup::<T>);
+        routine.amcostestimate = Some(amcostestimate::<T>);
+        routine.amoptions = Some(amoptions);
+        routine.amvalidate = Some(amvalidate);
+        routine.ambeginscan = Some(ambeginscan::<T>);
+        routine.amrescan = Some(amrescan::<T>);
+        routine.amgettuple = Some(amgettuple::<T>);
+        routine.amendscan = Some(amendscan::<T>);
+        pg_sys::Datum::from(routine.into_pg())
+    }
+}
+
+struct BuildState {
+    tupdesc: PgTupleDesc<'static>,
+    insert: fn(&PgRelation, &IndexEntry),
+    count: f64,
+}
+
+unsafe fn build_insert(
+    index: pg_sys::Relation,
+    tid: pg_sys::ItemPointerData,
+    values: *mut pg_sys::Datum,
+    isnull: *mut bool,
+    state: *mut std::os::raw::c_void,
+) {
+    let state = &mut *(state as *mut BuildState);
+    let natts = state.tupdesc.len();
+    let entry = IndexEntry {
+        tid,
+        values: std::slice::from_raw_parts(values, natts),
+        isnull: std::slice::from_raw_parts(isnull, natts),
+        tupdesc: &state.tupdesc,
+    };
+    (state.insert)(&PgRelation::from_pg(index), &entry);
+    state.count += 1.0;
+}
+
+#[cfg(any(feature = "pg11", feature = "pg12"))]
+#[pg_guard]
+unsafe extern "C" fn build_callback(
+    index: pg_sys::Relation,
+    htup: pg_sys::HeapTuple,
+    values: *mut pg_sys::Datum,
+    isnull: *mut bool,
+    _tuple_is_alive: bool,
+    state: *mut std::os::raw::c_void,
+) {
+    build_insert(index, (*htup).t_self, values, isnull, state)
+}
+
+#[cfg(any(feature = "pg13", feature = "pg14", feature = "pg15"))]
+#[pg_guard]
+unsafe extern "C" fn build_callback(
+    index: pg_sys::Relation,
+    tid: pg_sys::ItemPointer,
+    values: *mut pg_sys::Datum,
+    isnull: *mut bool,
+    _tuple_is_alive: bool,
+    state: *mut std::os::raw::c_void,
+) {
+    build_insert(index, *tid, values, isnull, state)
+}
+
+#[pg_guard]
+unsafe extern "C" fn ambuild<T: IndexAm>(
+    heap_relation: pg_sys::Relation,
+    index_relation: pg_sys::Relation,
+    index_info: *mut pg_sys::IndexInfo,
+) -> *mut pg_sys::IndexBuildResult {
+    T::build_empty(&PgRelation::from_pg(index_relation));
+
+    let mut state = BuildState {
+        tupdesc: PgTupleDesc::from_pg_unchecked((*index_relation).rd_att),
+        insert: T::insert,
+        count: 0.0,
+    };
+    pg_sys::IndexBuildHeapScan(
+        heap_relation,
+        index_relation,
+        index_info,
+        Some(build_callback),
+        &mut state,
+    );
+
+    let mut result = PgBox::<pg_sys::IndexBuildResult>::alloc0();
+    result.heap_tuples = state.count;
+    result.index_tuples = state.count;
+    result.into_pg()
+}
+
+#[pg_guard]
+unsafe extern "C" fn ambuildempty<T: IndexAm>(index_relation: pg_sys::Relation) {
+    T::build_empty(&PgRelation::from_pg(index_relation));
+}
+
+unsafe fn insert<T: IndexAm>(
+    index_relation: pg_sys::Relation,
+    values: *mut pg_sys::Datum,
+    isnull: *mut bool,
+    heap_tid: pg_sys::ItemPointer,
+) -> bool {
+    let tupdesc = PgTupleDesc::from_pg_unchecked((*index_relation).rd_att);
+    let natts = tupdesc.len();
+    let entry = IndexEntry {
+        tid: *heap_tid,
+        values: std::slice::from_raw_parts(values, natts),
+        isnull: std::slice::from_raw_parts(isnull, natts),
+        tupdesc: &tupdesc,
+    };
+    T::insert(&PgRelation::from_pg(index_relation), &entry);
+    // the result only matters to unique indexes
+    false
+}
+
+#[cfg(any(feature = "pg11", feature = "pg12", feature = "pg13"))]
+#[pg_guard]
+unsafe extern "C" fn aminsert<T: IndexAm>(
+    index_relation: pg_sys::Relation,
+    values: *mut pg_sys::Datum,
+    isnull: *mut bool,
+    heap_tid: pg_sys::ItemPointer,
+    _heap_relation: pg_sys::Relation,
+    _check_unique: pg_sys::IndexUniqueCheck,
+    _index_info: *mut pg_sys::IndexInfo,
+) -> bool {
+    insert::<T>(index_relation, values, isnull, heap_tid)
+}
+
+#[cfg(any(feature = "pg14", feature = "pg15"))]
+#[pg_guard]
+unsafe extern "C" fn aminsert<T: IndexAm>(
+    index_relation: pg_sys::Relation,
+    values: *mut pg_sys::Datum,
+    isnull: *mut bool,
+    heap_tid: pg_sys::ItemPointer,
+    _heap_relation: pg_sys::Relation,
+    _check_unique: pg_sys::IndexUniqueCheck,
+    _index_unchanged: bool,
+    _index_info: *mut pg_sys::IndexInfo,
+) -> bool {
+    insert::<T>(index_relation, values, isnull, heap_tid)
+}
+
+#[pg_guard]
+unsafe extern "C" fn ambulkdelete<T: IndexAm>(
+    info: *mut pg_sys::IndexVacuumInfo,
+    stats: *mut pg_sys::IndexBulkDeleteResult,
+    callback: pg_sys::IndexBulkDeleteCallback,
+    callback_state: *mut std::os::raw::c_void,
+) -> *mut pg_sys::IndexBulkDeleteResult {
+    let stats = if stats.is_null() {
+        PgBox::<pg_sys::IndexBulkDeleteResult>::alloc0().into_pg()
+    } else {
+        stats
+    };
+    let callback = callback.expect("VACUUM passed no IndexBulkDeleteCallback");
+    let removed = T::bulk_delete(&PgRelation::from_pg((*info).index), &mut |mut tid| {
+        callback(&mut tid, callback_state)
+    });
+    (*stats).tuples_removed += removed as f64;
+    stats
+}
+
+#[pg_guard]
+unsafe extern "C" fn amvacuumcleanup<T: IndexAm>(
+    info: *mut pg_sys::IndexVacuumInfo,
+    stats: *mut pg_sys::IndexBulkDeleteResult,
+) -> *mut pg_sys::IndexBulkDeleteResult {
+    if (*info).analyze_only {
+        return stats;
+    }
+    match T::vacuum_cleanup(&PgRelation::from_pg((*info).index)) {
+        Some(count) => {
+            let stats = if stats.is_null() {
+                PgBox::<pg_sys::IndexBulkDeleteResult>::alloc0().into_pg()
+            } else {
+                stats
+            };
+            (*stats).num_index_tuples = count as f64;
+            stats
+        }
+        None => stats,
+    }
+}
+
+#[pg_guard]
+unsafe extern "C" fn amcostestimate<T: IndexAm>(
+    root: *mut pg_sys::PlannerInfo,
+    path: *mut pg_sys::IndexPath,
+    loop_count: f64,
+    index_startup_cost: *mut pg_sys::Cost,
+    index_total_cost: *mut pg_sys::Cost,
+    index_selectivity: *mut pg_sys::Selectivity,
+    index_correlation: *mut f64,
+    index_pages: *mut f64,
+) {
+    let mut generic = pg_sys::GenericCosts::default();
+    #[cfg(feature = "pg11")]
+    pg_sys::genericcostestimate(
+        root,
+        path,
+        loop_count,
+        pg_sys::deconstruct_indexquals(path),
+        &mut generic,
+    );
+    #[cfg(any(feature = "pg12", feature = "pg13", feature = "pg14", feature = "pg15"))]
+    pg_sys::genericcostestimate(root, path, loop_count, &mut generic);
+
+    let mut costs = IndexCosts {
+        startup_cost: generic.indexStartupCost,
+        total_cost: generic.indexTotalCost,
+        selectivity: generic.indexSelectivity,
+        correlation: generic.indexCorrelation,
+        pages: generic.numIndexPages,
+    };
+    T::cost_estimate(&mut costs);
+
+    *index_startup_cost = costs.startup_cost;
+    *index_total_cost = costs.total_cost;
+    *index_selectivity = costs.selectivity;
+    *index_correlation = costs.correlation;
+    *index_pages = costs.pages;
+}
+
+#[pg_guard]
+unsafe extern "C" fn amoptions(_reloptions: pg_sys::Datum, validate: bool) -> *mut pg_sys::bytea {
+    // only called when there are options
+    if validate {
+        ereport!(
+            PgLogLevel::ERROR,
+            PgSqlErrorCode::ERRCODE_INVALID_PARAMETER_VALUE,
+            "the index access method does not support options"
+        );
+    }
+    std::ptr::null_mut()
+}
+
+#[pg_guard]
+unsafe extern "C" fn amvalidate(_opclassoid: pg_sys::Oid) -> bool {
+    true
+}
+
+#[pg_guard]
+unsafe extern "C" fn ambeginscan<T: IndexAm>(
+    index_relation: pg_sys::Relation,
+    nkeys: c_int,
+    norderbys: c_int,
+) -> pg_sys::IndexScanDesc {
+    let scan = pg_sys::RelationGetIndexScan(index_relation, nkeys, norderbys);
+    let am = T::begin_scan(&PgRelation::from_pg(index_relation));
+    // dropped with the scan's memory, even if the scan ends in an error
+    (*scan).opaque = PgMemoryContexts::CurrentMemoryContext.leak_and_drop_on_delete(am).cast();
+    scan
+}
+
+#[pg_guard]
+unsafe extern "C" fn amrescan<T: IndexAm>(
+    scan: pg_sys::IndexScanDesc,
+    keys: pg_sys::ScanKey,
+    nkeys: c_int,
+    _orderbys: pg_sys::ScanKey,
+    _norderbys: c_int,
+) {
+    if !keys.is_null() && nkeys > 0 && keys != (*scan).keyData {
+        std::ptr::copy(keys, (*scan).keyData, nkeys as usize);
+    }
+
+    let tupdesc = PgTupleDesc::from_pg_unchecked((*(*scan).indexRelation).rd_att);
+    let keys = (0..(*scan).numberOfKeys as usize)
+        .map(|i| {
+            let key = &*(*scan).keyData.add(i);
+            let attno = key.sk_attno as usize;
+            let type_oid = if key.sk_subtype != pg_sys::InvalidOid {
+                key.sk_subtype
+            } else {
+                tupdesc.get(attno - 1).expect("a scan key of no index column").atttypid
+            };
+            IndexScanKey {
+                attno,
+                strategy: key.sk_strategy,
+                argument: (key.sk_flags & pg_sys::SK_ISNULL as c_int == 0).then(|| key.sk_argument),
+                type_oid,
+            }
+        })
+        .collect::<Vec<_>>();
+
+    let am = &mut *((*scan).opaque as *mut T);
+    am.rescan(&keys);
+}
+
+#[pg_guard]
+unsafe extern "C" fn amgettuple<T: IndexAm>(
+    scan: pg_sys::IndexScanDesc,
+    _direction: pg_sys::ScanDirection,
+) -> bool {
+    let am = &mut *((*scan).opaque as *mut T);
+    match am.get_tuple() {
+        Some(found) => {
+            #[cfg(feature = "pg11")]
+            {
+                (*scan).xs_ctup.t_self = found.tid;
+            }
+            #[cfg(any(feature = "pg12", feature = "pg13", feature = "pg14", feature = "pg15"))]
+            {
+                (*scan).xs_heaptid = found.tid;
+            }
+            (*scan).xs_recheck = found.recheck;
+            true
+        }
+        None => false,
+    }
+}
+
+#[pg_guard]
+unsafe extern "C" fn amendscan<T: IndexAm>(scan: pg_sys::IndexScanDesc) {
+    if let Some(am) = ((*scan).opaque as *mut T).as_mut() {
+        am.end_scan();
+    }
+}
@@ -11,7 +11,8 @@ Use of this source code is governed by the MIT license that can be found in the
 //! input/output functions.
 //!
 //! The default implementations use `serde_json` to serialize a custom type to human-readable strings,
-//! and `serde_cbor` to serialize internally as a `varlena *` for storage on disk.
+//! and `serde_cbor` to serialize internally as a `varlena *` for storage on disk.  Types with the
+//! `#[fmt_inoutfuncs]` attribute use their `Display` and `FromStr` impls as their text form instead.
 
 use crate::*;
 
@@ -72,3 +73,42 @@ pub trait JsonInOutFuncs<'de>: serde::de::Deserialize<'de> + serde::ser::Seriali
     /// error message should be generated?
     const NULL_ERROR_MESSAGE: Option<&'static str> = None;
 }
+
+/// Automatically implemented for `#[derive(PostgresType)]` types with the `#[fmt_inoutfuncs]`
+/// attribute, which use their `Display` and `FromStr` impls as their text representation
+pub trait FmtInOutFuncs: core::fmt::Display + core::str::FromStr {
+    /// Parses the input with `FromStr`, raising an `invalid_text_representation` error if it fails
+    fn input(input: &core::ffi::CStr) -> Self
+    where
+        <Self as core::str::FromStr>::Err: core::fmt::Display,
+    {
+        let input = input.to_str().expect("text input is not valid UTF8");
+        match input.parse() {
+            Ok(value) => value,
+            Err(e) => {
+                let type_name = core::any::type_name::<Self>();
+                let type_name = type_name.rsplit("::").next().unwrap_or(type_name);
+                ereport!(
+                    ERROR,
+                    PgSqlErrorCode::ERRCODE_INVALID_TEXT_REPRESENTATION,
+                    format!(
+                        "invalid input syntax for type {}: \"{}\"",
+                        type_name.to_lowercase(),
+                        input
+                    );
+                    set_detail(e.to_string())
+                );
+            }
+        }
+    }
+
+    /// Uses `Display` to write `Self` as text
+    fn output(&self, buffer: &mut StringInfo) {
+        use core::fmt::Write;
+        write!(buffer, "{}", self).expect("failed to format as text")
+    }
+
+    /// If PostgreSQL calls the conversion function with NULL as an argument, what
+    /// error message should be generated?
+    const NULL_ERROR_MESSAGE: Option<&'static str> = None;
+}
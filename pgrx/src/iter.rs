@@ -1,6 +1,6 @@
 use std::iter::once;
 
-use crate::IntoHeapTuple;
+use crate::{pg_sys, IntoHeapTuple};
 use pgrx_sql_entity_graph::metadata::{
     ArgumentError, Returns, ReturnsError, SqlMapping, SqlTranslatable,
 };
@@ -145,6 +145,162 @@ impl<'a, T> Iterator for TableIterator<'a, T> {
     }
 }
 
+/// What a set-returning function can tell, when it's first called, about how its rows will be
+/// consumed
+///
+/// Postgres asks for a set-returning function's rows one at a time, but doesn't say how many it
+/// will ask for.  Sometimes it's apparent that it might not ask for many, such as when the query
+/// is being run through a cursor that the client fetches a few rows at a time from.  A function
+/// that computes its rows in batches can start with a small one then.
+///
+/// Note that when the function is called in a query's `FROM` clause, Postgres collects all of its
+/// rows before returning any of them, no matter how many are fetched.  Calling it in the `SELECT`
+/// list instead (`SELECT my_srf()`) lets rows be produced only as they're fetched.
+///
+/// ```rust,no_run
+/// use pgrx::iter::{IncrementalRows, SrfFetchHint};
+/// use pgrx::prelude::*;
+///
+/// #[pg_extern]
+/// fn expensive_rows(fcinfo: pg_sys::FunctionCallInfo) -> SetOfIterator<'static, i64> {
+///     let hint = unsafe { SrfFetchHint::from_fcinfo(fcinfo) };
+///     let first_batch = hint.batch_size(10, 10_000);
+///     SetOfIterator::new(IncrementalRows::new(first_batch, 10_000, |offset, count| {
+///         // compute rows offset..offset + count, which is fewer than `count` once there are no more
+///         (offset as i64..(offset + count).min(1_000_000) as i64).collect()
+///     }))
+/// }
+/// ```
+#[derive(Debug, Copy, Clone, Eq, PartialEq, Default)]
+pub struct SrfFetchHint {
+    value_per_call: bool,
+    scrollable: bool,
+    incremental_fetch: bool,
+}
+
+impl SrfFetchHint {
+    /// Examine how the function behind `fcinfo` was called
+    ///
+    /// # Safety
+    ///
+    /// `fcinfo` must be the valid `FunctionCallInfo` of the currently running function
+    pub unsafe fn from_fcinfo(fcinfo: pg_sys::FunctionCallInfo) -> Self {
+        let mut hint = SrfFetchHint::default();
+
+        let rsinfo = (*fcinfo).resultinfo.cast::<pg_sys::ReturnSetInfo>();
+        if !rsinfo.is_null() && (*rsinfo).type_ == pg_sys::NodeTag_T_ReturnSetInfo {
+            hint.value_per_call = (*rsinfo).allowedModes
+                & pg_sys::SetFunctionReturnMode_SFRM_ValuePerCall as i32
+                != 0;
+            let econtext = (*rsinfo).econtext;
+            if !econtext.is_null() && !(*econtext).ecxt_estate.is_null() {
+                hint.scrollable = (*(*econtext).ecxt_estate).es_top_eflags
+                    & pg_sys::EXEC_FLAG_BACKWARD as i32
+                    != 0;
+            }
+        }
+
+        // cursors, whether `DECLARE`d, opened by SPI, or named portals of the extended query
+        // protocol (which is how clients with a fetch size fetch rows in batches), have names.
+        // The portal a query is simply run in doesn't
+        let portal = pg_sys::ActivePortal;
+        if !portal.is_null() && !(*portal).name.is_null() {
+            hint.incremental_fetch = *(*portal).name != 0;
+        }
+        hint
+    }
+
+    /// Can the function return its rows one at a time?  If not, it's being called in a way that
+    /// requires all of them at once
+    pub fn value_per_call(&self) -> bool {
+        self.value_per_call
+    }
+
+    /// Is the query being run by a scrollable cursor, which might fetch rows backwards?
+    pub fn scrollable(&self) -> bool {
+        self.scrollable
+    }
+
+    /// Is the query being run by a cursor, or a named portal, which its rows might be fetched from
+    /// a few at a time?
+    pub fn incremental_fetch(&self) -> bool {
+        self.incremental_fetch
+    }
+
+    /// `small` if the rows might be fetched a few at a time, otherwise `large`
+    pub fn batch_size(&self, small: usize, large: usize) -> usize {
+        if self.incremental_fetch && self.value_per_call {
+            small
+        } else {
+            large
+        }
+    }
+}
+
+/// An iterator over rows that are computed in batches, only as they're needed
+///
+/// The first batch has `first_batch` rows, and each batch after it twice as many as the one
+/// before, up to `max_batch`.  That way a query that fetches only a few rows causes only a few to
+/// be computed, while one that fetches all of them needs only a few batches.
+///
+/// `produce` is called with the offset of the first row of a batch and the number of rows
+/// wanted.  Returning fewer rows than that ends the iteration after them.
+///
+/// See [`SrfFetchHint`] for an example.
+pub struct IncrementalRows<T, F> {
+    produce: F,
+    batch: std::vec::IntoIter<T>,
+    offset: usize,
+    next_batch: usize,
+    max_batch: usize,
+    done: bool,
+}
+
+impl<T, F: FnMut(usize, usize) -> Vec<T>> IncrementalRows<T, F> {
+    /// # Panics
+    ///
+    /// Panics if `first_batch` is zero, or greater than `max_batch`
+    pub fn new(first_batch: usize, max_batch: usize, produce: F) -> Self {
+        assert!(first_batch > 0, "first_batch must be greater than zero");
+        assert!(first_batch <= max_batch, "first_batch must not be greater than max_batch");
+        IncrementalRows {
+            produce,
+            batch: Vec::new().into_iter(),
+            offset: 0,
+            next_batch: first_batch,
+            max_batch,
+            done: false,
+        }
+    }
+
+    /// How many rows have been computed so far
+    pub fn produced(&self) -> usize {
+        self.offset
+    }
+}
+
+impl<T, F: FnMut(usize, usize) -> Vec<T>> Iterator for IncrementalRows<T, F> {
+    type Item = T;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        loop {
+            if let Some(row) = self.batch.next() {
+                return Some(row);
+            }
+            if self.done {
+                return None;
+            }
+
+            let wanted = self.next_batch;
+            let batch = (self.produce)(self.offset, wanted);
+            self.done = batch.len() < wanted;
+            self.offset += batch.len();
+            self.next_batch = wanted.saturating_mul(2).min(self.max_batch);
+            self.batch = batch.into_iter();
+        }
+    }
+}
+
 seq_macro::seq!(I in 0..=32 {
     #(
         seq_macro::seq!(N in 0..=I {
@@ -38,40 +38,90 @@ pub mod prelude;
 
 pub mod aggregate;
 pub mod array;
+pub mod asset;
 pub mod atomics;
+pub mod backend;
 pub mod bgworkers;
+pub mod binary_copy;
+pub mod bitmapset;
 pub mod callbacks;
+pub mod catalog;
+pub mod cdc;
+pub mod checkpoint;
+pub mod compat;
+pub mod composite;
+pub mod cost;
+#[cfg(feature = "cshim")]
+pub mod custom_scan;
+pub mod data_dir;
 pub mod datum;
+#[cfg(feature = "cshim")]
+pub mod deferred;
+pub mod dest_receiver;
+pub mod dsm;
 pub mod enum_helper;
+pub mod event_trigger;
+pub mod explain;
+pub mod extended_stats;
 pub mod fcinfo;
+pub mod fdw;
+pub mod feature_flags;
 pub mod ffi;
 pub mod guc;
+pub mod hash;
 pub mod heap_tuple;
 #[cfg(feature = "cshim")]
 pub mod hooks;
 pub mod htup;
+pub mod index_am;
 pub mod inoutfuncs;
 pub mod itemptr;
 pub mod iter;
 #[cfg(feature = "cshim")]
 pub mod list;
+pub mod lock;
+pub mod logical_replication;
 pub mod lwlock;
+pub mod matview;
 pub mod memcxt;
+pub mod memcxt_tracking;
+pub mod metrics;
 pub mod misc;
 #[cfg(feature = "cshim")]
 pub mod namespace;
 pub mod nodes;
+#[cfg(feature = "cshim")]
+pub mod pathlist;
 pub mod pgbox;
+pub mod portal;
+pub mod rate_limit;
 pub mod rel;
+pub mod relfork;
+pub mod roaring;
+pub mod selectivity;
+pub mod session;
 pub mod shmem;
+pub mod sketch;
 pub mod spi;
 #[cfg(feature = "cshim")]
 pub mod spinlock;
 pub mod srf;
+pub mod stats_import;
+pub mod storage_maps;
 pub mod stringinfo;
+pub mod table_rewrite;
+pub mod tenancy;
+pub mod timeout;
 pub mod trigger_support;
 pub mod tupdesc;
+pub mod tuplesort;
+pub mod typed_list;
+pub mod vacuum;
 pub mod varlena;
+pub mod version;
+pub mod volatility;
+pub mod wal;
+pub mod window;
 pub mod wrappers;
 pub mod xid;
 
@@ -97,6 +147,7 @@ pub use inoutfuncs::*;
 pub use itemptr::*;
 #[cfg(feature = "cshim")]
 pub use list::*;
+pub use lock::*;
 pub use lwlock::*;
 pub use memcxt::*;
 #[cfg(feature = "cshim")]
@@ -110,6 +161,7 @@ pub use stringinfo::*;
 pub use trigger_support::*;
 pub use tupdesc::*;
 pub use varlena::*;
+pub use version::{pg_version, PgVersion};
 pub use wrappers::*;
 pub use xid::*;
 
@@ -120,7 +172,7 @@ pub use pg_sys::elog::PgLogLevel;
 pub use pg_sys::errcodes::PgSqlErrorCode;
 pub use pg_sys::oids::PgOid;
 pub use pg_sys::panic::pgrx_extern_c_guard;
-pub use pg_sys::pg_try::PgTryBuilder;
+pub use pg_sys::pg_try::{pg_try, PgTryBuilder};
 pub use pg_sys::utils::name_data_to_str;
 pub use pg_sys::PgBuiltInOids;
 pub use pg_sys::{
@@ -0,0 +1,344 @@
+/*
+Portions Copyright 2019-2021 ZomboDB, LLC.
+Portions Copyright 2021-2022 Technology Concepts & Design, Inc. <support@tcdi.com>
+
+All rights reserved.
+
+Use of this source code is governed by the MIT license that can be found in the LICENSE file.
+*/
+
+//! Safe wrappers around Postgres' heavyweight lock manager
+use crate::pg_sys;
+use std::time::{Duration, Instant};
+
+/// The heavyweight lock modes, as documented in
+/// <https://www.postgresql.org/docs/current/explicit-locking.html#LOCKING-TABLES>
+///
+/// Modes are ordered from weakest to strongest.
+#[derive(Debug, Copy, Clone, Eq, PartialEq, Ord, PartialOrd, Hash)]
+#[repr(i32)]
+pub enum LockMode {
+    /// Don't acquire any lock at all
+    NoLock = pg_sys::NoLock as i32,
+    /// Taken by `SELECT`
+    AccessShare = pg_sys::AccessShareLock as i32,
+    /// Taken by `SELECT ... FOR UPDATE/SHARE`
+    RowShare = pg_sys::RowShareLock as i32,
+    /// Taken by `INSERT`, `UPDATE`, and `DELETE`
+    RowExclusive = pg_sys::RowExclusiveLock as i32,
+    /// Taken by `VACUUM`, `ANALYZE`, and `CREATE INDEX CONCURRENTLY`
+    ShareUpdateExclusive = pg_sys::ShareUpdateExclusiveLock as i32,
+    /// Taken by `CREATE INDEX`
+    Share = pg_sys::ShareLock as i32,
+    /// Taken by `CREATE TRIGGER` and some forms of `ALTER TABLE`
+    ShareRowExclusive = pg_sys::ShareRowExclusiveLock as i32,
+    /// Taken by `REFRESH MATERIALIZED VIEW CONCURRENTLY`
+    Exclusive = pg_sys::ExclusiveLock as i32,
+    /// Taken by `DROP TABLE`, `TRUNCATE`, `VACUUM FULL`, and most forms of `ALTER TABLE`
+    AccessExclusive = pg_sys::AccessExclusiveLock as i32,
+}
+
+impl LockMode {
+    /// This mode as a Postgres `LOCKMODE`
+    pub fn as_pg(self) -> pg_sys::LOCKMODE {
+        self as pg_sys::LOCKMODE
+    }
+
+    /// Would a lock of this mode conflict with a lock of the `other` mode held by a different
+    /// transaction?  This follows Postgres' own lock conflict table.
+    pub fn conflicts_with(self, other: LockMode) -> bool {
+        use LockMode::*;
+        match (self, other) {
+            (NoLock, _) | (_, NoLock) => false,
+            (AccessExclusive, _) | (_, AccessExclusive) => true,
+            (AccessShare, _) | (_, AccessShare) => false,
+            (Exclusive, _) | (_, Exclusive) => true,
+            (RowShare, _) | (_, RowShare) => false,
+            (RowExclusive, RowExclusive) => false,
+            (ShareUpdateExclusive, RowExclusive) | (RowExclusive, ShareUpdateExclusive) => false,
+            (Share, Share) => false,
+            _ => true,
+        }
+    }
+}
+
+impl From<LockMode> for pg_sys::LOCKMODE {
+    fn from(mode: LockMode) -> Self {
+        mode.as_pg()
+    }
+}
+
+/// A lock on a relation, held by the current transaction.
+///
+/// Dropping the guard releases the lock immediately.  Postgres' usual convention, however, is to
+/// hold relation locks until the end of the transaction, which [`RelationLock::hold_until_xact_end()`]
+/// does.  Either way, Postgres releases any relation locks still held when the transaction ends.
+///
+/// ## Deadlocks
+///
+/// [`RelationLock::lock()`] waits in the lock queue and, should that wait deadlock with another
+/// backend, Postgres' deadlock detector raises an `ERROR` with
+/// [`PgSqlErrorCode::ERRCODE_T_R_DEADLOCK_DETECTED`](crate::PgSqlErrorCode::ERRCODE_T_R_DEADLOCK_DETECTED),
+/// aborting the transaction.  [`RelationLock::try_lock()`] and [`RelationLock::try_lock_for()`]
+/// never wait in the lock queue, so they can't deadlock and instead report that the lock isn't
+/// available.
+///
+/// ## Examples
+///
+/// ```rust,no_run
+/// use pgrx::prelude::*;
+/// use pgrx::{LockMode, RelationLock};
+///
+/// # let relid = pg_sys::InvalidOid;
+/// match RelationLock::try_lock(relid, LockMode::ShareUpdateExclusive) {
+///     Some(lock) => lock.hold_until_xact_end(),
+///     None => warning!("relation is busy, skipping"),
+/// }
+/// ```
+#[must_use = "a relation lock is released as soon as its guard is dropped"]
+#[derive(Debug)]
+pub struct RelationLock {
+    relid: pg_sys::Oid,
+    mode: LockMode,
+}
+
+impl RelationLock {
+    /// Lock the relation with the given oid, waiting for as long as necessary
+    pub fn lock(relid: pg_sys::Oid, mode: LockMode) -> RelationLock {
+        // SAFETY:  Postgres raises an ERROR if it can't lock the relation
+        unsafe {
+            pg_sys::LockRelationOid(relid, mode.as_pg());
+        }
+        RelationLock { relid, mode }
+    }
+
+    /// Lock the relation with the given oid only if the lock is immediately available
+    pub fn try_lock(relid: pg_sys::Oid, mode: LockMode) -> Option<RelationLock> {
+        // SAFETY:  a conditional lock never waits and simply reports if it was acquired
+        if unsafe { pg_sys::ConditionalLockRelationOid(relid, mode.as_pg()) } {
+            Some(RelationLock { relid, mode })
+        } else {
+            None
+        }
+    }
+
+    /// Repeatedly try to lock the relation with the given oid until the lock is acquired or
+    /// `timeout` elapses.  Unlike [`RelationLock::lock()`], this never waits in the lock queue
+    /// and so can't be chosen as a deadlock victim, but it also doesn't get in line in front of
+    /// later lockers.
+    pub fn try_lock_for(
+        relid: pg_sys::Oid,
+        mode: LockMode,
+        timeout: Duration,
+    ) -> Option<RelationLock> {
+        let start = Instant::now();
+        loop {
+            if let Some(lock) = Self::try_lock(relid, mode) {
+                return Some(lock);
+            } else if start.elapsed() >= timeout {
+                return None;
+            }
+
+            pg_sys::check_for_interrupts!();
+            // SAFETY:  pg_usleep() is always safe to call
+            unsafe {
+                pg_sys::pg_usleep(1000);
+            }
+        }
+    }
+
+    /// The oid of the locked relation
+    pub fn relid(&self) -> pg_sys::Oid {
+        self.relid
+    }
+
+    /// The mode the relation is locked with
+    pub fn mode(&self) -> LockMode {
+        self.mode
+    }
+
+    /// Consume this guard without releasing the lock, leaving it to Postgres to release at the
+    /// end of the current transaction
+    pub fn hold_until_xact_end(self) {
+        std::mem::forget(self)
+    }
+}
+
+impl Drop for RelationLock {
+    fn drop(&mut self) {
+        // SAFETY:  we hold this exact lock
+        unsafe {
+            pg_sys::UnlockRelationOid(self.relid, self.mode.as_pg());
+        }
+    }
+}
+
+/// The key of an advisory lock.  Just like the SQL-level `pg_advisory_*` functions, a lock can
+/// be keyed either by a single `bigint` or by a pair of `integer`s, and the two key spaces never
+/// overlap.
+#[derive(Debug, Copy, Clone, Eq, PartialEq, Hash)]
+pub enum AdvisoryLockKey {
+    /// A single 64-bit key, as used by `pg_advisory_lock(bigint)`
+    Int8(i64),
+
+    /// A composite key of two 32-bit values, as used by `pg_advisory_lock(int, int)`
+    Pair(i32, i32),
+}
+
+impl From<i64> for AdvisoryLockKey {
+    fn from(key: i64) -> Self {
+        AdvisoryLockKey::Int8(key)
+    }
+}
+
+impl From<(i32, i32)> for AdvisoryLockKey {
+    fn from((key1, key2): (i32, i32)) -> Self {
+        AdvisoryLockKey::Pair(key1, key2)
+    }
+}
+
+impl AdvisoryLockKey {
+    /// Build the same `LOCKTAG` as Postgres' `SET_LOCKTAG_ADVISORY()` does for this key in the
+    /// current database
+    fn locktag(&self) -> pg_sys::LOCKTAG {
+        let (field2, field3, field4) = match *self {
+            AdvisoryLockKey::Int8(key) => ((key >> 32) as u32, key as u32, 1),
+            AdvisoryLockKey::Pair(key1, key2) => (key1 as u32, key2 as u32, 2),
+        };
+
+        pg_sys::LOCKTAG {
+            // SAFETY:  MyDatabaseId is set once the backend has connected to a database
+            locktag_field1: unsafe { pg_sys::MyDatabaseId.as_u32() },
+            locktag_field2: field2,
+            locktag_field3: field3,
+            locktag_field4: field4,
+            locktag_type: pg_sys::LockTagType_LOCKTAG_ADVISORY as u8,
+            locktag_lockmethodid: pg_sys::USER_LOCKMETHOD as u8,
+        }
+    }
+}
+
+/// A held Postgres advisory lock.
+///
+/// Session-level locks, from [`AdvisoryLock::session()`] and friends, are released when this
+/// guard is dropped.  Transaction-level locks, from [`AdvisoryLock::xact()`] and friends, can't be
+/// released early and are instead released by Postgres at the end of the current transaction, so
+/// dropping their guard does nothing.
+///
+/// Locks are acquired through the lock manager directly, without going through SPI, and are
+/// visible in `pg_locks` exactly like those taken by the SQL-level `pg_advisory_*` functions.
+///
+/// ## Examples
+///
+/// ```rust,no_run
+/// use pgrx::AdvisoryLock;
+///
+/// // blocks until the lock is available, and releases it at the end of this scope
+/// let _lock = AdvisoryLock::session(42);
+///
+/// // doesn't wait, returning `None` if another backend already holds the lock
+/// if let Some(_lock) = AdvisoryLock::try_xact((1, 2)) {
+///     // ...
+/// }
+/// ```
+#[must_use = "a session-level advisory lock is released as soon as its guard is dropped"]
+#[derive(Debug)]
+pub struct AdvisoryLock {
+    key: AdvisoryLockKey,
+    mode: LockMode,
+    session: bool,
+}
+
+impl AdvisoryLock {
+    /// Obtain an exclusive session-level advisory lock, waiting if necessary.  Equivalent to
+    /// `pg_advisory_lock()`
+    pub fn session<K: Into<AdvisoryLockKey>>(key: K) -> AdvisoryLock {
+        Self::acquire(key.into(), LockMode::Exclusive, true, false).unwrap()
+    }
+
+    /// Obtain a shared session-level advisory lock, waiting if necessary.  Equivalent to
+    /// `pg_advisory_lock_shared()`
+    pub fn session_shared<K: Into<AdvisoryLockKey>>(key: K) -> AdvisoryLock {
+        Self::acquire(key.into(), LockMode::Share, true, false).unwrap()
+    }
+
+    /// Obtain an exclusive session-level advisory lock if it's immediately available.
+    /// Equivalent to `pg_try_advisory_lock()`
+    pub fn try_session<K: Into<AdvisoryLockKey>>(key: K) -> Option<AdvisoryLock> {
+        Self::acquire(key.into(), LockMode::Exclusive, true, true)
+    }
+
+    /// Obtain a shared session-level advisory lock if it's immediately available.  Equivalent to
+    /// `pg_try_advisory_lock_shared()`
+    pub fn try_session_shared<K: Into<AdvisoryLockKey>>(key: K) -> Option<AdvisoryLock> {
+        Self::acquire(key.into(), LockMode::Share, true, true)
+    }
+
+    /// Obtain an exclusive transaction-level advisory lock, waiting if necessary.  Equivalent to
+    /// `pg_advisory_xact_lock()`
+    pub fn xact<K: Into<AdvisoryLockKey>>(key: K) -> AdvisoryLock {
+        Self::acquire(key.into(), LockMode::Exclusive, false, false).unwrap()
+    }
+
+    /// Obtain a shared transaction-level advisory lock, waiting if necessary.  Equivalent to
+    /// `pg_advisory_xact_lock_shared()`
+    pub fn xact_shared<K: Into<AdvisoryLockKey>>(key: K) -> AdvisoryLock {
+        Self::acquire(key.into(), LockMode::Share, false, false).unwrap()
+    }
+
+    /// Obtain an exclusive transaction-level advisory lock if it's immediately available.
+    /// Equivalent to `pg_try_advisory_xact_lock()`
+    pub fn try_xact<K: Into<AdvisoryLockKey>>(key: K) -> Option<AdvisoryLock> {
+        Self::acquire(key.into(), LockMode::Exclusive, false, true)
+    }
+
+    /// Obtain a shared transaction-level advisory lock if it's immediately available.
+    /// Equivalent to `pg_try_advisory_xact_lock_shared()`
+    pub fn try_xact_shared<K: Into<AdvisoryLockKey>>(key: K) -> Option<AdvisoryLock> {
+        Self::acquire(key.into(), LockMode::Share, false, true)
+    }
+
+    fn acquire(
+        key: AdvisoryLockKey,
+        mode: LockMode,
+        session: bool,
+        dont_wait: bool,
+    ) -> Option<AdvisoryLock> {
+        let locktag = key.locktag();
+
+        // SAFETY:  `locktag` is a fully initialized advisory LOCKTAG and if we're asked to wait,
+        // a deadlock is reported as a normal Postgres ERROR
+        let result = unsafe { pg_sys::LockAcquire(&locktag, mode.as_pg(), session, dont_wait) };
+        if result == pg_sys::LockAcquireResult_LOCKACQUIRE_NOT_AVAIL {
+            None
+        } else {
+            Some(AdvisoryLock { key, mode, session })
+        }
+    }
+
+    /// The key this lock was acquired with
+    pub fn key(&self) -> AdvisoryLockKey {
+        self.key
+    }
+
+    /// Is this a session-level lock, as opposed to a transaction-level lock?
+    pub fn is_session(&self) -> bool {
+        self.session
+    }
+
+    /// Is this a shared lock, as opposed to an exclusive lock?
+    pub fn is_shared(&self) -> bool {
+        self.mode == LockMode::Share
+    }
+}
+
+impl Drop for AdvisoryLock {
+    fn drop(&mut self) {
+        if self.session {
+            // SAFETY:  we acquired this exact lock in `AdvisoryLock::acquire()`
+            unsafe {
+                pg_sys::LockRelease(&self.key.locktag(), self.mode.as_pg(), true);
+            }
+        }
+    }
+}
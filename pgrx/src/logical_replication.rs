@@ -0,0 +1,399 @@
+/*
+Portions Copyright 2019-2021 ZomboDB, LLC.
+Portions Copyright 2021-2022 Technology Concepts & Design, Inc. <support@tcdi.com>
+
+All rights reserved.
+
+Use of this source code is governed by the MIT license that can be found in the LICENSE file.
+*/
+
+//! Manage logical replication publications, and inspect subscriptions
+//!
+//! [`CreatePublication`] and the methods of [`Publication`] build the `CREATE PUBLICATION` and
+//! `ALTER PUBLICATION` commands from table oids, quoting every name, and [`subscriptions()`]
+//! reports on the subscriptions of the current database and their workers.
+//!
+//! ## Examples
+//!
+//! ```rust,no_run
+//! use pgrx::logical_replication::{subscriptions, CreatePublication, PublishActions};
+//! use pgrx::prelude::*;
+//!
+//! let orders = Spi::get_one::<pg_sys::Oid>("SELECT 'public.orders'::regclass::oid")
+//!     .unwrap()
+//!     .unwrap();
+//! let publication = CreatePublication::new("orders_pub")
+//!     .table(orders)
+//!     .publish(PublishActions { delete: false, ..Default::default() })
+//!     .create()
+//!     .unwrap();
+//! assert_eq!(publication.tables().unwrap(), vec![orders]);
+//!
+//! for subscription in subscriptions().unwrap() {
+//!     info!("{}: worker {:?} at {:?}", subscription.name, subscription.pid, subscription.received_lsn);
+//! }
+//! ```
+use crate::prelude::*;
+use crate::spi::{self, quote_identifier, quote_literal, quote_qualified_identifier};
+
+/// The changes a publication publishes, as listed in its `publish` option
+#[derive(Debug, Copy, Clone, Eq, PartialEq)]
+pub struct PublishActions {
+    pub insert: bool,
+    pub update: bool,
+    pub delete: bool,
+    pub truncate: bool,
+}
+
+/// Everything, as Postgres publishes by default
+impl Default for PublishActions {
+    fn default() -> Self {
+        PublishActions { insert: true, update: true, delete: true, truncate: true }
+    }
+}
+
+impl PublishActions {
+    /// The `publish = '...'` option for these actions
+    fn to_sql(&self) -> String {
+        let actions = [
+            (self.insert, "insert"),
+            (self.update, "update"),
+            (self.delete, "delete"),
+            (self.truncate, "truncate"),
+        ];
+        let actions = actions
+            .iter()
+            .filter(|(enabled, _)| *enabled)
+            .map(|(_, name)| *name)
+            .collect::<Vec<_>>();
+        format!("publish = {}", quote_literal(actions.join(", ")))
+    }
+}
+
+/// Builder for a `CREATE PUBLICATION` command
+#[derive(Debug, Clone)]
+pub struct CreatePublication {
+    name: String,
+    tables: Vec<pg_sys::Oid>,
+    all_tables: bool,
+    publish: PublishActions,
+}
+
+impl CreatePublication {
+    /// Describe a publication named `name`, which isn't quoted yet.  Without tables, it publishes
+    /// nothing until some are added
+    pub fn new(name: &str) -> Self {
+        CreatePublication {
+            name: name.to_string(),
+            tables: Vec::new(),
+            all_tables: false,
+            publish: PublishActions::default(),
+        }
+    }
+
+    /// Publish changes to the table with the specified oid
+    pub fn table(mut self, relid: pg_sys::Oid) -> Self {
+        if !self.tables.contains(&relid) {
+            self.tables.push(relid);
+        }
+        self
+    }
+
+    /// Publish changes to each of the tables with the specified oids
+    pub fn tables<I: IntoIterator<Item = pg_sys::Oid>>(self, relids: I) -> Self {
+        relids.into_iter().fold(self, |publication, relid| publication.table(relid))
+    }
+
+    /// `FOR ALL TABLES`:  publish changes to every table in the database, including those created
+    /// later.  Only superusers may create such a publication
+    pub fn all_tables(mut self) -> Self {
+        self.all_tables = true;
+        self
+    }
+
+    /// Publish only these changes
+    pub fn publish(mut self, publish: PublishActions) -> Self {
+        self.publish = publish;
+        self
+    }
+
+    /// The `CREATE PUBLICATION` command this describes
+    ///
+    /// # Errors
+    ///
+    /// Raises a Postgres `ERROR` if one of the tables doesn't exist
+    pub fn to_sql(&self) -> String {
+        let mut sql = format!("CREATE PUBLICATION {}", quote_identifier(&self.name));
+        if self.all_tables {
+            sql.push_str(" FOR ALL TABLES");
+        } else if !self.tables.is_empty() {
+            sql.push_str(&format!(" FOR TABLE {}", table_list(&self.tables)));
+        }
+        sql.push_str(&format!(" WITH ({})", self.publish.to_sql()));
+        sql
+    }
+
+    /// Create the publication
+    pub fn create(&self) -> spi::Result<Publication> {
+        Spi::run(&self.to_sql())?;
+        Publication::open(&self.name)
+            .map(|publication| publication.expect("publication was not created"))
+    }
+}
+
+/// A publication in the current database
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Publication {
+    oid: pg_sys::Oid,
+    name: String,
+}
+
+impl Publication {
+    /// The publication named `name`, if there is one
+    pub fn open(name: &str) -> spi::Result<Option<Publication>> {
+        let oid = Spi::get_one_with_args::<pg_sys::Oid>(
+            "SELECT oid FROM pg_catalog.pg_publication WHERE pubname = $1",
+            vec![(PgBuiltInOids::TEXTOID.oid(), name.into_datum())],
+        );
+        match oid {
+            Ok(oid) => Ok(oid.map(|oid| Publication { oid, name: name.to_string() })),
+            Err(spi::Error::InvalidPosition) => Ok(None),
+            Err(e) => Err(e),
+        }
+    }
+
+    pub fn oid(&self) -> pg_sys::Oid {
+        self.oid
+    }
+
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+
+    /// Is this a `FOR ALL TABLES` publication?
+    pub fn all_tables(&self) -> spi::Result<bool> {
+        Spi::get_one_with_args::<bool>(
+            "SELECT puballtables FROM pg_catalog.pg_publication WHERE oid = $1",
+            self.args(),
+        )
+        .map(|all_tables| all_tables.unwrap_or_default())
+    }
+
+    /// The changes the publication publishes
+    pub fn publish(&self) -> spi::Result<PublishActions> {
+        Spi::connect(|client| {
+            let row = client
+                .select(
+                    "SELECT pubinsert, pubupdate, pubdelete, pubtruncate \
+                       FROM pg_catalog.pg_publication WHERE oid = $1",
+                    Some(1),
+                    Some(self.args()),
+                )?
+                .first();
+            Ok(PublishActions {
+                insert: row.get_by_name("pubinsert")?.unwrap_or_default(),
+                update: row.get_by_name("pubupdate")?.unwrap_or_default(),
+                delete: row.get_by_name("pubdelete")?.unwrap_or_default(),
+                truncate: row.get_by_name("pubtruncate")?.unwrap_or_default(),
+            })
+        })
+    }
+
+    /// The oids of the tables the publication publishes changes to, in oid order.  For a
+    /// `FOR ALL TABLES` publication, that's every table it currently covers
+    pub fn tables(&self) -> spi::Result<Vec<pg_sys::Oid>> {
+        Spi::connect(|client| {
+            client
+                .select(
+                    "SELECT relid FROM pg_catalog.pg_get_publication_tables($1) ORDER BY relid",
+                    None,
+                    Some(vec![(PgBuiltInOids::TEXTOID.oid(), self.name.as_str().into_datum())]),
+                )?
+                .map(|row| row.get::<pg_sys::Oid>(1).map(Option::unwrap_or_default))
+                .collect()
+        })
+    }
+
+    /// `ALTER PUBLICATION ... ADD TABLE`:  also publish changes to the tables with the specified
+    /// oids
+    pub fn add_tables(&self, relids: &[pg_sys::Oid]) -> spi::Result<()> {
+        self.alter_tables("ADD", relids)
+    }
+
+    /// `ALTER PUBLICATION ... DROP TABLE`:  stop publishing changes to the tables with the
+    /// specified oids
+    pub fn drop_tables(&self, relids: &[pg_sys::Oid]) -> spi::Result<()> {
+        self.alter_tables("DROP", relids)
+    }
+
+    /// `ALTER PUBLICATION ... SET TABLE`:  publish changes to exactly the tables with the specified
+    /// oids
+    pub fn set_tables(&self, relids: &[pg_sys::Oid]) -> spi::Result<()> {
+        self.alter_tables("SET", relids)
+    }
+
+    /// `ALTER PUBLICATION ... SET (publish = ...)`:  publish only these changes
+    pub fn set_publish(&self, publish: PublishActions) -> spi::Result<()> {
+        Spi::run(&format!(
+            "ALTER PUBLICATION {} SET ({})",
+            quote_identifier(&self.name),
+            publish.to_sql()
+        ))
+    }
+
+    /// `DROP PUBLICATION`
+    pub fn drop(self) -> spi::Result<()> {
+        Spi::run(&format!("DROP PUBLICATION {}", quote_identifier(&self.name)))
+    }
+
+    fn alter_tables(&self, action: &str, relids: &[pg_sys::Oid]) -> spi::Result<()> {
+        if relids.is_empty() {
+            return Ok(());
+        }
+        Spi::run(&format!(
+            "ALTER PUBLICATION {} {} TABLE {}",
+            quote_identifier(&self.name),
+            action,
+            table_list(relids)
+        ))
+    }
+
+    fn args(&self) -> Vec<(PgOid, Option<pg_sys::Datum>)> {
+        vec![(PgBuiltInOids::OIDOID.oid(), self.oid.into_datum())]
+    }
+}
+
+/// The quoted, schema-qualified names of the tables with the specified oids
+fn table_list(relids: &[pg_sys::Oid]) -> String {
+    let names = relids
+        .iter()
+        .map(|&relid| unsafe {
+            let relname = pg_sys::get_rel_name(relid);
+            if relname.is_null() {
+                ereport!(
+                    PgLogLevel::ERROR,
+                    PgSqlErrorCode::ERRCODE_UNDEFINED_TABLE,
+                    format!("relation with OID {} does not exist", relid.as_u32())
+                );
+            }
+            let nspname = pg_sys::get_namespace_name(pg_sys::get_rel_namespace(relid));
+            quote_qualified_identifier(
+                core::ffi::CStr::from_ptr(nspname).to_string_lossy(),
+                core::ffi::CStr::from_ptr(relname).to_string_lossy(),
+            )
+        })
+        .collect::<Vec<_>>();
+    names.join(", ")
+}
+
+/// Where a subscription is in synchronizing one of its tables, from `pg_subscription_rel`
+#[derive(Debug, Copy, Clone, Eq, PartialEq)]
+pub enum SubscriptionTableState {
+    /// `i`:  waiting to start copying the table's data
+    Init,
+    /// `d`:  copying the table's data
+    DataSync,
+    /// `f`:  copied the table's data, and waiting to catch up with the apply worker
+    #[cfg(any(feature = "pg14", feature = "pg15"))]
+    FinishedCopy,
+    /// `s`:  synchronized with the apply worker
+    SyncDone,
+    /// `r`:  replicating normally
+    Ready,
+}
+
+impl SubscriptionTableState {
+    fn from_code(code: &str) -> Option<Self> {
+        match code {
+            "i" => Some(SubscriptionTableState::Init),
+            "d" => Some(SubscriptionTableState::DataSync),
+            #[cfg(any(feature = "pg14", feature = "pg15"))]
+            "f" => Some(SubscriptionTableState::FinishedCopy),
+            "s" => Some(SubscriptionTableState::SyncDone),
+            "r" => Some(SubscriptionTableState::Ready),
+            _ => None,
+        }
+    }
+}
+
+/// A subscription in the current database, and the state of its apply worker
+#[derive(Debug, Clone, PartialEq)]
+pub struct SubscriptionStatus {
+    pub oid: pg_sys::Oid,
+    pub name: String,
+    pub enabled: bool,
+    /// The publications it subscribes to
+    pub publications: Vec<String>,
+    /// The process id of its apply worker, if one is running
+    pub pid: Option<i32>,
+    /// The last write-ahead log location received from the publisher
+    pub received_lsn: Option<pg_sys::XLogRecPtr>,
+    /// The last write-ahead log location reported back to the publisher
+    pub latest_end_lsn: Option<pg_sys::XLogRecPtr>,
+    /// When the last message from the publisher was received
+    pub last_msg_receipt_time: Option<TimestampWithTimeZone>,
+}
+
+impl SubscriptionStatus {
+    /// The oids of the tables the subscription replicates into, in oid order, and where it is in
+    /// synchronizing each
+    pub fn tables(&self) -> spi::Result<Vec<(pg_sys::Oid, SubscriptionTableState)>> {
+        Spi::connect(|client| {
+            client
+                .select(
+                    "SELECT srrelid, srsubstate::text FROM pg_catalog.pg_subscription_rel \
+                      WHERE srsubid = $1 ORDER BY srrelid",
+                    None,
+                    Some(vec![(PgBuiltInOids::OIDOID.oid(), self.oid.into_datum())]),
+                )?
+                .filter_map(|row| {
+                    let relid = row.get::<pg_sys::Oid>(1).map(Option::unwrap_or_default);
+                    let state = row
+                        .get::<String>(2)
+                        .map(|state| state.as_deref().and_then(SubscriptionTableState::from_code));
+                    match (relid, state) {
+                        (Ok(relid), Ok(Some(state))) => Some(Ok((relid, state))),
+                        (Ok(_), Ok(None)) => None,
+                        (Err(e), _) | (_, Err(e)) => Some(Err(e)),
+                    }
+                })
+                .collect()
+        })
+    }
+}
+
+/// The subscriptions in the current database, in name order
+pub fn subscriptions() -> spi::Result<Vec<SubscriptionStatus>> {
+    // only the apply worker, not table synchronization workers, has no `relid`
+    const QUERY: &str = "SELECT s.oid, s.subname::text, s.subenabled, s.subpublications, \
+                                w.pid, \
+                                (w.received_lsn - '0/0'::pg_lsn)::int8 AS received_lsn, \
+                                (w.latest_end_lsn - '0/0'::pg_lsn)::int8 AS latest_end_lsn, \
+                                w.last_msg_receipt_time \
+                           FROM pg_catalog.pg_subscription s \
+                           LEFT JOIN pg_catalog.pg_stat_subscription w \
+                             ON w.subid = s.oid AND w.relid IS NULL \
+                          WHERE s.subdbid = (SELECT oid FROM pg_catalog.pg_database \
+                                              WHERE datname = pg_catalog.current_database()) \
+                          ORDER BY s.subname";
+
+    Spi::connect(|client| {
+        client
+            .select(QUERY, None, None)?
+            .map(|row| {
+                Ok(SubscriptionStatus {
+                    oid: row.get_by_name("oid")?.unwrap_or_default(),
+                    name: row.get_by_name("subname")?.unwrap_or_default(),
+                    enabled: row.get_by_name("subenabled")?.unwrap_or_default(),
+                    publications: row.get_by_name("subpublications")?.unwrap_or_default(),
+                    pid: row.get_by_name("pid")?,
+                    received_lsn: row.get_by_name::<i64, _>("received_lsn")?.map(|lsn| lsn as _),
+                    latest_end_lsn: row
+                        .get_by_name::<i64, _>("latest_end_lsn")?
+                        .map(|lsn| lsn as _),
+                    last_msg_receipt_time: row.get_by_name("last_msg_receipt_time")?,
+                })
+            })
+            .collect()
+    })
+}
@@ -44,6 +44,13 @@ impl<T> PgLwLock<T> {
         PgLwLock { inner: OnceCell::new(), name: OnceCell::new() }
     }
 
+    /// Create an empty lock whose LWLock and shared memory are found by `name`.  Unlike the random
+    /// name [`PgLwLock::new()`] gives it, this names it the same in every backend, even if each one
+    /// must attach to shared memory itself, as on Windows.  See [`pg_shmem!`](crate::pg_shmem)
+    pub const fn new_named(name: &'static str) -> Self {
+        PgLwLock { inner: OnceCell::new(), name: OnceCell::with_value(name) }
+    }
+
     /// Create a new lock for T by attaching a LWLock, which is looked up by name
     pub fn from_named(input_name: &'static str, value: *mut T) -> Self {
         let inner = OnceCell::new();
@@ -97,7 +104,7 @@ impl<T> fmt::Debug for PgLwLockInner<T> {
 impl<'a, T> PgLwLockInner<T> {
     fn new(name: &'static str, data: *mut T) -> Self {
         unsafe {
-            let lock = alloc::ffi::CString::new(name).expect("CString::new failed");
+            let lock = crate::shmem::shmem_key(name);
             PgLwLockInner {
                 lock_ptr: &mut (*pg_sys::GetNamedLWLockTranche(lock.as_ptr())).lock,
                 data,
@@ -0,0 +1,263 @@
+/*
+Portions Copyright 2019-2021 ZomboDB, LLC.
+Portions Copyright 2021-2022 Technology Concepts & Design, Inc. <support@tcdi.com>
+
+All rights reserved.
+
+Use of this source code is governed by the MIT license that can be found in the LICENSE file.
+*/
+
+//! Refresh materialized views and inspect their state, without going through SPI
+//!
+//! An extension keeping a graph of materialized views up to date can find what each one reads
+//! with [`dependencies()`], and refresh them in order with [`refresh_materialized_view()`]:
+//!
+//! ```rust,no_run
+//! use pgrx::matview::{self, RefreshOptions};
+//! use pgrx::pg_sys;
+//!
+//! fn refresh_with_dependencies(relid: pg_sys::Oid) {
+//!     for dependency in matview::dependencies(relid) {
+//!         if matview::is_materialized_view(dependency) {
+//!             refresh_with_dependencies(dependency);
+//!         }
+//!     }
+//!     let concurrently = matview::can_refresh_concurrently(relid);
+//!     matview::refresh_materialized_view(relid, RefreshOptions { concurrently, ..Default::default() });
+//! }
+//! ```
+use crate::compat::{list_length, list_nth_oid, table_close, table_open};
+use crate::{ereport, pg_sys, PgBox, PgLogLevel, PgSqlErrorCode};
+
+/// Options for [`refresh_materialized_view()`], equivalent to those of the SQL
+/// `REFRESH MATERIALIZED VIEW` command
+#[derive(Debug, Default, Copy, Clone, Eq, PartialEq)]
+pub struct RefreshOptions {
+    /// `REFRESH MATERIALIZED VIEW CONCURRENTLY`:  refresh without locking out concurrent reads.
+    /// The view must be populated and have a unique index, see [`can_refresh_concurrently()`]
+    pub concurrently: bool,
+    /// `REFRESH MATERIALIZED VIEW ... WITH NO DATA`:  empty the view, leaving it unpopulated
+    pub with_no_data: bool,
+}
+
+/// `REFRESH MATERIALIZED VIEW` the materialized view with the specified oid
+///
+/// The calling user must own the view.  If there's no active snapshot, as in a background worker's
+/// transaction, the refresh runs under a new transaction snapshot.
+///
+/// # Errors
+///
+/// Raises a Postgres `ERROR` if no transaction is in progress, if the relation doesn't exist or
+/// isn't a materialized view, or for any of the reasons `REFRESH MATERIALIZED VIEW` itself would.
+pub fn refresh_materialized_view(relid: pg_sys::Oid, options: RefreshOptions) {
+    if unsafe { !pg_sys::IsTransactionState() } {
+        ereport!(
+            PgLogLevel::ERROR,
+            PgSqlErrorCode::ERRCODE_NO_ACTIVE_SQL_TRANSACTION,
+            "refresh_materialized_view() must be called within a transaction"
+        );
+    }
+
+    unsafe {
+        let mut stmt =
+            PgBox::<pg_sys::RefreshMatViewStmt>::alloc_node(pg_sys::NodeTag_T_RefreshMatViewStmt);
+        stmt.concurrent = options.concurrently;
+        stmt.skipData = options.with_no_data;
+        stmt.relation = range_var(relid);
+
+        let push_snapshot = !pg_sys::ActiveSnapshotSet();
+        if push_snapshot {
+            pg_sys::PushActiveSnapshot(pg_sys::GetTransactionSnapshot());
+        }
+
+        let query_string = b"REFRESH MATERIALIZED VIEW\0".as_ptr().cast();
+        #[cfg(any(feature = "pg11", feature = "pg12"))]
+        {
+            let mut completion_tag = [0; pg_sys::COMPLETION_TAG_BUFSIZE as usize];
+            pg_sys::ExecRefreshMatView(
+                stmt.as_ptr(),
+                query_string,
+                std::ptr::null_mut(),
+                completion_tag.as_mut_ptr(),
+            );
+        }
+        #[cfg(not(any(feature = "pg11", feature = "pg12")))]
+        {
+            let mut qc = pg_sys::QueryCompletion::default();
+            pg_sys::ExecRefreshMatView(stmt.as_ptr(), query_string, std::ptr::null_mut(), &mut qc);
+        }
+
+        if push_snapshot {
+            pg_sys::PopActiveSnapshot();
+        }
+    }
+}
+
+/// Is the relation with the specified oid a materialized view?  Returns false if there's no such
+/// relation
+pub fn is_materialized_view(relid: pg_sys::Oid) -> bool {
+    unsafe { pg_sys::get_rel_relkind(relid) as u8 == pg_sys::RELKIND_MATVIEW }
+}
+
+/// Has the materialized view with the specified oid been populated, so that it can be read?  It
+/// isn't after `CREATE MATERIALIZED VIEW ... WITH NO DATA` or a refresh `WITH NO DATA`, until it's
+/// next refreshed
+///
+/// # Errors
+///
+/// Raises a Postgres `ERROR` if the relation doesn't exist or isn't a materialized view
+pub fn is_populated(relid: pg_sys::Oid) -> bool {
+    unsafe {
+        let rel = open_matview(relid);
+        let populated = (*(*rel).rd_rel).relispopulated;
+        table_close(rel, pg_sys::AccessShareLock as _);
+        populated
+    }
+}
+
+/// Can the materialized view with the specified oid be refreshed with
+/// [`RefreshOptions::concurrently`]?  Postgres requires it to be populated, and to have a valid
+/// unique index on plain columns, without a `WHERE` clause
+///
+/// # Errors
+///
+/// Raises a Postgres `ERROR` if the relation doesn't exist or isn't a materialized view
+pub fn can_refresh_concurrently(relid: pg_sys::Oid) -> bool {
+    unsafe {
+        let rel = open_matview(relid);
+        let usable = (*(*rel).rd_rel).relispopulated && {
+            let indexes = pg_sys::RelationGetIndexList(rel);
+            (0..list_length(indexes)).filter_map(|i| list_nth_oid(indexes, i)).any(|indexid| {
+                let index = pg_sys::index_open(indexid, pg_sys::AccessShareLock as _);
+                let form = &*(*index).rd_index;
+                let usable = form.indisunique
+                    && form.indimmediate
+                    && form.indisvalid
+                    && pg_sys::RelationGetIndexPredicate(index).is_null()
+                    && pg_sys::RelationGetIndexExpressions(index).is_null();
+                pg_sys::index_close(index, pg_sys::AccessShareLock as _);
+                usable
+            })
+        };
+        table_close(rel, pg_sys::AccessShareLock as _);
+        usable
+    }
+}
+
+/// The relations (tables, views, other materialized views, ...) the query of the materialized view
+/// with the specified oid reads directly, in oid order
+///
+/// # Errors
+///
+/// Raises a Postgres `ERROR` if the relation doesn't exist or isn't a materialized view
+pub fn dependencies(relid: pg_sys::Oid) -> Vec<pg_sys::Oid> {
+    unsafe {
+        // the view's query is its `_RETURN` rule, and it's the rule that records what it reads
+        let rel = open_matview(relid);
+        let rules = (*rel).rd_rules;
+        let rule_oid = if rules.is_null() || (*rules).numLocks < 1 {
+            None
+        } else {
+            Some((**(*rules).rules).ruleId)
+        };
+        table_close(rel, pg_sys::AccessShareLock as _);
+
+        let mut dependencies = match rule_oid {
+            Some(rule_oid) => rule_dependencies(rule_oid),
+            None => Vec::new(),
+        };
+        dependencies.retain(|oid| *oid != relid);
+        dependencies.sort_unstable_by_key(|oid| oid.as_u32());
+        dependencies.dedup();
+        dependencies
+    }
+}
+
+/// Scan `pg_depend` for the relations the rule with oid `rule_oid` depends on
+unsafe fn rule_dependencies(rule_oid: pg_sys::Oid) -> Vec<pg_sys::Oid> {
+    let depend = table_open(pg_sys::DependRelationId, pg_sys::AccessShareLock as _);
+    let mut keys = [pg_sys::ScanKeyData::default(), pg_sys::ScanKeyData::default()];
+    let oideq = pg_sys::Oid::from_u32_unchecked(pg_sys::F_OIDEQ);
+    pg_sys::ScanKeyInit(
+        &mut keys[0],
+        pg_sys::Anum_pg_depend_classid as _,
+        pg_sys::BTEqualStrategyNumber as _,
+        oideq,
+        pg_sys::RewriteRelationId.into(),
+    );
+    pg_sys::ScanKeyInit(
+        &mut keys[1],
+        pg_sys::Anum_pg_depend_objid as _,
+        pg_sys::BTEqualStrategyNumber as _,
+        oideq,
+        rule_oid.into(),
+    );
+    let scan = pg_sys::systable_beginscan(
+        depend,
+        pg_sys::Oid::from_u32_unchecked(pg_sys::DependDependerIndexId),
+        true,
+        std::ptr::null_mut(),
+        keys.len() as _,
+        keys.as_mut_ptr(),
+    );
+
+    let mut dependencies = Vec::new();
+    loop {
+        let tuple = pg_sys::systable_getnext(scan);
+        if tuple.is_null() {
+            break;
+        }
+        let form = &*pg_sys::heap_tuple_get_struct::<pg_sys::FormData_pg_depend>(tuple);
+        if form.refclassid == pg_sys::RelationRelationId
+            && form.deptype as u8 == pg_sys::DependencyType_DEPENDENCY_NORMAL as u8
+        {
+            dependencies.push(form.refobjid);
+        }
+    }
+
+    pg_sys::systable_endscan(scan);
+    table_close(depend, pg_sys::AccessShareLock as _);
+    dependencies
+}
+
+/// Open the materialized view `relid` with an `AccessShareLock`, or raise an `ERROR` if it isn't one
+unsafe fn open_matview(relid: pg_sys::Oid) -> pg_sys::Relation {
+    if !is_materialized_view(relid) {
+        not_a_matview(relid);
+    }
+    let rel = table_open(relid, pg_sys::AccessShareLock as _);
+    // it may have been dropped and its oid reused while we waited for the lock
+    if (*(*rel).rd_rel).relkind as u8 != pg_sys::RELKIND_MATVIEW {
+        table_close(rel, pg_sys::AccessShareLock as _);
+        not_a_matview(relid);
+    }
+    rel
+}
+
+/// A `RangeVar` naming the materialized view `relid`, or raise an `ERROR` if it isn't one
+unsafe fn range_var(relid: pg_sys::Oid) -> *mut pg_sys::RangeVar {
+    if !is_materialized_view(relid) {
+        not_a_matview(relid);
+    }
+    let relname = pg_sys::get_rel_name(relid);
+    let nspname = pg_sys::get_namespace_name(pg_sys::get_rel_namespace(relid));
+    pg_sys::makeRangeVar(nspname, relname, -1)
+}
+
+fn not_a_matview(relid: pg_sys::Oid) -> ! {
+    let relname = unsafe { pg_sys::get_rel_name(relid) };
+    if relname.is_null() {
+        ereport!(
+            PgLogLevel::ERROR,
+            PgSqlErrorCode::ERRCODE_UNDEFINED_TABLE,
+            format!("relation with OID {} does not exist", relid.as_u32())
+        );
+    }
+    let relname = unsafe { std::ffi::CStr::from_ptr(relname) }.to_string_lossy();
+    ereport!(
+        PgLogLevel::ERROR,
+        PgSqlErrorCode::ERRCODE_WRONG_OBJECT_TYPE,
+        format!("\"{}\" is not a materialized view", relname)
+    );
+    unreachable!()
+}
@@ -599,3 +599,86 @@ impl PgMemoryContexts {
         result
     }
 }
+
+thread_local! {
+    // the contexts of `run_in_per_tuple_context()`, one for each level it's nested to, reused by
+    // every call at that level
+    static PER_TUPLE_CONTEXTS: std::cell::RefCell<Vec<pg_sys::MemoryContext>> =
+        std::cell::RefCell::new(Vec::new());
+    static PER_TUPLE_DEPTH: std::cell::Cell<usize> = std::cell::Cell::new(0);
+}
+
+/// Run `f` in a short-lived memory context that's reset as soon as `f` returns, freeing everything
+/// Postgres allocated while processing one row.
+///
+/// The body of a trigger, or the code producing the rows of a set-returning function, often runs
+/// in a context that lasts as long as the query does, such as the SRF's multi-call memory context,
+/// so that what each row allocates accumulates until the query ends:
+///
+/// ```rust,no_run
+/// use pgrx::memcxt::run_in_per_tuple_context;
+/// use pgrx::prelude::*;
+///
+/// #[pg_extern]
+/// fn word_lengths(words: Vec<String>) -> SetOfIterator<'static, i64> {
+///     let lengths = words
+///         .iter()
+///         .map(|word| {
+///             run_in_per_tuple_context(|| {
+///                 Spi::get_one_with_args::<i64>(
+///                     "SELECT length($1)",
+///                     vec![(PgBuiltInOids::TEXTOID.oid(), word.as_str().into_datum())],
+///                 )
+///                 .unwrap()
+///                 .unwrap_or(0)
+///             })
+///         })
+///         .collect::<Vec<_>>();
+///     SetOfIterator::new(lengths)
+/// }
+/// ```
+///
+/// What `f` returns must not point into memory allocated while it ran:  return owned Rust values,
+/// not datums or `PgBox`es.  Calls may be nested, and Postgres errors raised by `f` are propagated
+/// as usual.
+pub fn run_in_per_tuple_context<R>(f: impl FnOnce() -> R) -> R {
+    struct Restore {
+        depth: usize,
+        context: pg_sys::MemoryContext,
+        previous: pg_sys::MemoryContext,
+    }
+
+    impl Drop for Restore {
+        fn drop(&mut self) {
+            unsafe {
+                pg_sys::CurrentMemoryContext = self.previous;
+                pg_sys::MemoryContextReset(self.context);
+            }
+            PER_TUPLE_DEPTH.with(|depth| depth.set(self.depth));
+        }
+    }
+
+    let depth = PER_TUPLE_DEPTH.with(|depth| depth.replace(depth.get() + 1));
+    let context = PER_TUPLE_CONTEXTS.with(|contexts| {
+        let mut contexts = contexts.borrow_mut();
+        if contexts.len() <= depth {
+            contexts.push(unsafe {
+                pg_sys::AllocSetContextCreateExtended(
+                    pg_sys::TopMemoryContext,
+                    // the context outlives the caller's, so its name can't be allocated in it
+                    b"pgrx per-tuple context\0".as_ptr().cast(),
+                    pg_sys::ALLOCSET_DEFAULT_MINSIZE as usize,
+                    pg_sys::ALLOCSET_DEFAULT_INITSIZE as usize,
+                    pg_sys::ALLOCSET_DEFAULT_MAXSIZE as usize,
+                )
+            });
+        }
+        contexts[depth]
+    });
+
+    let _restore = Restore { depth, context, previous: unsafe { pg_sys::CurrentMemoryContext } };
+    unsafe {
+        pg_sys::CurrentMemoryContext = context;
+    }
+    f()
+}
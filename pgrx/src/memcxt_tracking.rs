@@ -0,0 +1,236 @@
+/*
+Portions Copyright 2019-2021 ZomboDB, LLC.
+Portions Copyright 2021-2022 Technology Concepts & Design, Inc. <support@tcdi.com>
+
+All rights reserved.
+
+Use of this source code is governed by the MIT license that can be found in the LICENSE file.
+*/
+
+//! Debug-build detection of pointers used after their `MemoryContext` was reset
+//!
+//! Holding onto a [`PgBox`](crate::PgBox) or [`PgVarlena`](crate::PgVarlena) longer than the
+//! `MemoryContext` it was allocated in (say, stashing a value allocated in a per-tuple context
+//! into a `static`) is a use-after-free.  Usually nothing crashes right away and the damage shows
+//! up much later, somewhere unrelated.
+//!
+//! When the extension is compiled with `debug_assertions`, pgrx records the `MemoryContext` of
+//! every `PgBox` and `PgVarlena` it allocates and registers a reset callback on that context.
+//! Dereferencing one of those pointers after its context was reset or deleted panics immediately,
+//! naming the context.  A reset stops tracking the allocations it freed, remembering only the
+//! most recent few thousand to catch them being used.  In release builds every function here
+//! compiles to nothing.
+//!
+//! Code that manages its own `palloc`'d pointers can opt in with [`track_allocation()`] and
+//! [`assert_allocation_live()`].
+use crate::pg_sys;
+
+#[cfg(debug_assertions)]
+mod imp {
+    use crate::pg_sys;
+    use crate::void_mut_ptr;
+    use std::cell::RefCell;
+    use std::collections::{HashMap, HashSet, VecDeque};
+    use std::rc::Rc;
+
+    /// How many of the allocations freed by resets are remembered, to catch them being used
+    const FREED_CAPACITY: usize = 4096;
+
+    /// A generation of a context:  the allocations tracked in it between two resets
+    struct ContextState {
+        name: String,
+        allocations: RefCell<HashSet<usize>>,
+    }
+
+    #[derive(Default)]
+    struct Registry {
+        /// Contexts we've registered a reset callback with
+        contexts: HashMap<usize, Rc<ContextState>>,
+        /// Allocations in contexts that haven't been reset since
+        allocations: HashMap<usize, Rc<ContextState>>,
+        /// The most recent allocations freed by a reset, and the order they were freed in, oldest
+        /// first
+        freed: HashMap<usize, Rc<ContextState>>,
+        freed_order: VecDeque<(usize, Rc<ContextState>)>,
+    }
+
+    impl Registry {
+        fn remember_freed(&mut self, ptr: usize, state: &Rc<ContextState>) {
+            if self.freed_order.len() == FREED_CAPACITY {
+                let (oldest, oldest_state) = self.freed_order.pop_front().unwrap();
+                // the address may have been freed again since, by a later generation
+                if self.freed.get(&oldest).map_or(false, |s| Rc::ptr_eq(s, &oldest_state)) {
+                    self.freed.remove(&oldest);
+                }
+            }
+            self.freed.insert(ptr, state.clone());
+            self.freed_order.push_back((ptr, state.clone()));
+        }
+    }
+
+    thread_local! {
+        static REGISTRY: RefCell<Registry> = RefCell::new(Registry::default());
+    }
+
+    /// Run `f` with the registry.  `f` mustn't call into Postgres, which could reset a context and
+    /// re-enter the registry from `context_reset()`
+    fn with_registry<R>(f: impl FnOnce(&mut Registry) -> R) -> R {
+        REGISTRY.with(|registry| f(&mut registry.borrow_mut()))
+    }
+
+    unsafe extern "C" fn context_reset(arg: void_mut_ptr) {
+        // SAFETY:  `arg` came from `Rc::into_raw()` in `context_state()` and this callback only
+        // ever fires once
+        let state = Rc::from_raw(arg as *const ContextState);
+        let freed = state.allocations.take();
+
+        // the next allocation in this context starts a new generation.  This generation's
+        // allocations are no longer tracked, but the most recent are remembered so we can still
+        // catch them being used
+        with_registry(|registry| {
+            registry.contexts.retain(|_, current| !Rc::ptr_eq(current, &state));
+            for ptr in freed {
+                registry.allocations.remove(&ptr);
+                registry.remember_freed(ptr, &state);
+            }
+        });
+    }
+
+    /// Find, or start tracking, the current generation of `context`
+    unsafe fn context_state(context: pg_sys::MemoryContext) -> Rc<ContextState> {
+        if let Some(state) =
+            with_registry(|registry| registry.contexts.get(&(context as usize)).cloned())
+        {
+            return state;
+        }
+
+        let name = if context.is_null() || (*context).name.is_null() {
+            String::from("<unnamed>")
+        } else {
+            std::ffi::CStr::from_ptr((*context).name).to_string_lossy().into_owned()
+        };
+        let state = Rc::new(ContextState { name, allocations: Default::default() });
+
+        // SAFETY:  the callback struct lives in `context` itself, which is exactly as long as
+        // Postgres needs it.  Postgres unregisters reset callbacks as it fires them, so we register
+        // a new one each time we see the context after a reset
+        let callback = pg_sys::MemoryContextAlloc(
+            context,
+            std::mem::size_of::<pg_sys::MemoryContextCallback>(),
+        ) as *mut pg_sys::MemoryContextCallback;
+        (*callback).func = Some(context_reset);
+        (*callback).arg = Rc::into_raw(state.clone()) as void_mut_ptr;
+        pg_sys::MemoryContextRegisterResetCallback(context, callback);
+
+        with_registry(|registry| registry.contexts.insert(context as usize, state.clone()));
+        state
+    }
+
+    pub(super) unsafe fn track_allocation(
+        ptr: *const std::ffi::c_void,
+        context: pg_sys::MemoryContext,
+    ) {
+        if ptr.is_null() || context.is_null() {
+            return;
+        }
+        let state = context_state(context);
+        let ptr = ptr as usize;
+        with_registry(|registry| {
+            registry.freed.remove(&ptr);
+            if let Some(previous) = registry.allocations.insert(ptr, state.clone()) {
+                previous.allocations.borrow_mut().remove(&ptr);
+            }
+        });
+        state.allocations.borrow_mut().insert(ptr);
+    }
+
+    pub(super) fn forget_allocation(ptr: *const std::ffi::c_void) {
+        let ptr = ptr as usize;
+        with_registry(|registry| {
+            registry.freed.remove(&ptr);
+            if let Some(state) = registry.allocations.remove(&ptr) {
+                state.allocations.borrow_mut().remove(&ptr);
+            }
+        });
+    }
+
+    /// The name of the `MemoryContext` `ptr` was allocated in, if that context has since been reset
+    pub(super) fn reset_context_of(ptr: *const std::ffi::c_void) -> Option<String> {
+        with_registry(|registry| {
+            registry.freed.get(&(ptr as usize)).map(|state| state.name.clone())
+        })
+    }
+
+    pub(super) fn tracked_allocations() -> usize {
+        with_registry(|registry| registry.allocations.len())
+    }
+}
+
+/// Record that `ptr` was allocated in `context`, so that later calls to
+/// [`assert_allocation_live()`] can detect it being used after `context` is reset.
+///
+/// Does nothing unless compiled with `debug_assertions`.
+///
+/// # Safety
+///
+/// `context` must be a valid `MemoryContext`, and `ptr`, if not null, must have been allocated
+/// in it.
+#[inline]
+pub unsafe fn track_allocation(ptr: *const std::ffi::c_void, context: pg_sys::MemoryContext) {
+    #[cfg(debug_assertions)]
+    imp::track_allocation(ptr, context);
+    #[cfg(not(debug_assertions))]
+    let _ = (ptr, context);
+}
+
+/// Stop tracking `ptr`, typically because it was just `pfree`'d or handed back to Postgres.
+///
+/// Does nothing unless compiled with `debug_assertions`.
+#[inline]
+pub fn forget_allocation(ptr: *const std::ffi::c_void) {
+    #[cfg(debug_assertions)]
+    imp::forget_allocation(ptr);
+    #[cfg(not(debug_assertions))]
+    let _ = ptr;
+}
+
+/// Is `ptr` still safe to use, as far as [`track_allocation()`] knows?  Pointers that were never
+/// tracked are assumed to be live.
+///
+/// Always `true` unless compiled with `debug_assertions`.
+#[inline]
+pub fn is_allocation_live(ptr: *const std::ffi::c_void) -> bool {
+    #[cfg(debug_assertions)]
+    return imp::reset_context_of(ptr).is_none();
+    #[cfg(not(debug_assertions))]
+    {
+        let _ = ptr;
+        true
+    }
+}
+
+/// The number of allocations being tracked in contexts that haven't been reset or deleted since.
+/// A reset stops tracking the allocations it freed.
+///
+/// Always `0` unless compiled with `debug_assertions`.
+pub fn tracked_allocations() -> usize {
+    #[cfg(debug_assertions)]
+    return imp::tracked_allocations();
+    #[cfg(not(debug_assertions))]
+    0
+}
+
+/// Panic if `ptr` was recorded by [`track_allocation()`] and its `MemoryContext` has since been
+/// reset or deleted.  `what` describes the pointer in the panic message.
+///
+/// Does nothing unless compiled with `debug_assertions`.
+#[inline]
+#[track_caller]
+pub fn assert_allocation_live(ptr: *const std::ffi::c_void, what: &str) {
+    #[cfg(debug_assertions)]
+    if let Some(name) = imp::reset_context_of(ptr) {
+        panic!("{what} used after its MemoryContext `{name}` was reset or deleted");
+    }
+    #[cfg(not(debug_assertions))]
+    let _ = (ptr, what);
+}
@@ -0,0 +1,506 @@
+/*
+Portions Copyright 2019-2021 ZomboDB, LLC.
+Portions Copyright 2021-2022 Technology Concepts & Design, Inc. <support@tcdi.com>
+
+All rights reserved.
+
+Use of this source code is governed by the MIT license that can be found in the LICENSE file.
+*/
+
+//! Operational metrics, kept in shared memory so every backend adds to the same ones
+//!
+//! A [`MetricsRegistry`] owns a block of shared memory holding [`Counter`]s, [`Gauge`]s and
+//! [`Histogram`]s.  Each metric is declared as a `static`, registered with the registry from
+//! `_PG_init()`, and the registry itself is then passed to [`pg_shmem_init!()`](crate::pg_shmem_init).
+//! Updating a metric is a lock-free atomic operation.
+//!
+//! [`metrics_functions!`](crate::metrics_functions) adds a function listing every metric's
+//! current value to the extension's schema, and optionally one returning them all in
+//! Prometheus' text exposition format, for a scraper to collect.
+//!
+//! ## Examples
+//!
+//! ```rust,no_run
+//! use pgrx::metrics::{Counter, Histogram, MetricsRegistry};
+//! use pgrx::prelude::*;
+//! use pgrx::{pg_shmem_init, PgSharedMemoryInitialization};
+//!
+//! static METRICS: MetricsRegistry = MetricsRegistry::new("my_extension");
+//! static SEARCHES: Counter = Counter::new("my_extension_searches_total", "Searches run");
+//! static SEARCH_SECONDS: Histogram = Histogram::new(
+//!     "my_extension_search_seconds",
+//!     "How long searches took",
+//!     &[0.001, 0.01, 0.1, 1.0],
+//! );
+//!
+//! pgrx::metrics_functions!(METRICS, fn my_extension_metrics, fn my_extension_prometheus);
+//!
+//! #[pg_guard]
+//! pub extern "C" fn _PG_init() {
+//!     METRICS.register(&SEARCHES);
+//!     METRICS.register(&SEARCH_SECONDS);
+//!     pg_shmem_init!(METRICS);
+//! }
+//!
+//! #[pg_extern]
+//! fn search(query: &str) -> i64 {
+//!     let start = std::time::Instant::now();
+//!     SEARCHES.inc();
+//!     let found = query.len() as i64;
+//!     SEARCH_SECONDS.observe(start.elapsed().as_secs_f64());
+//!     found
+//! }
+//! ```
+//!
+//! As with any shared memory, the extension must be in `shared_preload_libraries`.  If it isn't,
+//! updating a metric does nothing, and listing them raises an error.
+use crate::shmem::{shmem_init_zeroed, PgSharedMemoryInitialization};
+use crate::{pg_sys, PgLogLevel, PgSqlErrorCode};
+use std::fmt::Write;
+use std::sync::atomic::{AtomicBool, AtomicPtr, AtomicU64, Ordering};
+use std::sync::{Mutex, MutexGuard, PoisonError};
+
+/// The kind of a metric
+#[derive(Debug, Copy, Clone, Eq, PartialEq)]
+pub enum MetricKind {
+    /// A count that only goes up
+    Counter,
+    /// A value that can go up and down
+    Gauge,
+    /// A distribution of observed values, counted into buckets
+    Histogram,
+}
+
+impl MetricKind {
+    /// The kind's name, as Prometheus knows it
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            MetricKind::Counter => "counter",
+            MetricKind::Gauge => "gauge",
+            MetricKind::Histogram => "histogram",
+        }
+    }
+}
+
+// the shared memory a metric's values live in, once it's been attached
+struct Slots(AtomicPtr<AtomicU64>);
+
+impl Slots {
+    const fn new() -> Self {
+        Slots(AtomicPtr::new(std::ptr::null_mut()))
+    }
+
+    fn attach(&self, slots: *mut AtomicU64) {
+        self.0.store(slots, Ordering::Release);
+    }
+
+    fn get(&self, i: usize) -> Option<&AtomicU64> {
+        let slots = self.0.load(Ordering::Acquire);
+        // SAFETY:  once attached, `slots` points at the metric's `slot_count()` values, in shared
+        // memory that's never unmapped
+        unsafe { slots.as_ref().map(|_| &*slots.add(i)) }
+    }
+
+    fn load(&self, i: usize) -> u64 {
+        self.get(i).map_or(0, |slot| slot.load(Ordering::Relaxed))
+    }
+}
+
+// a float, stored as its bits
+fn add_f64(slot: &AtomicU64, value: f64) {
+    let _ = slot.fetch_update(Ordering::Relaxed, Ordering::Relaxed, |bits| {
+        Some((f64::from_bits(bits) + value).to_bits())
+    });
+}
+
+/// A count that only goes up, like the number of requests served
+pub struct Counter {
+    name: &'static str,
+    help: &'static str,
+    slots: Slots,
+}
+
+impl Counter {
+    pub const fn new(name: &'static str, help: &'static str) -> Self {
+        Counter { name, help, slots: Slots::new() }
+    }
+
+    /// Add one
+    #[inline]
+    pub fn inc(&self) {
+        self.inc_by(1)
+    }
+
+    /// Add `n`
+    #[inline]
+    pub fn inc_by(&self, n: u64) {
+        if let Some(slot) = self.slots.get(0) {
+            slot.fetch_add(n, Ordering::Relaxed);
+        }
+    }
+
+    /// The current count
+    pub fn get(&self) -> u64 {
+        self.slots.load(0)
+    }
+}
+
+/// A value that can go up and down, like the size of a queue
+pub struct Gauge {
+    name: &'static str,
+    help: &'static str,
+    slots: Slots,
+}
+
+impl Gauge {
+    pub const fn new(name: &'static str, help: &'static str) -> Self {
+        Gauge { name, help, slots: Slots::new() }
+    }
+
+    pub fn set(&self, value: f64) {
+        if let Some(slot) = self.slots.get(0) {
+            slot.store(value.to_bits(), Ordering::Relaxed);
+        }
+    }
+
+    /// Add `value`, which may be negative
+    pub fn add(&self, value: f64) {
+        if let Some(slot) = self.slots.get(0) {
+            add_f64(slot, value);
+        }
+    }
+
+    /// The current value
+    pub fn get(&self) -> f64 {
+        f64::from_bits(self.slots.load(0))
+    }
+}
+
+/// A distribution of observed values, like how long requests took, counted into buckets
+///
+/// Each bucket counts the observations less than or equal to its upper bound, and there's an
+/// implicit last bucket, `+Inf`, counting every observation.  The sum of the observations is
+/// also kept.
+pub struct Histogram {
+    name: &'static str,
+    help: &'static str,
+    bounds: &'static [f64],
+    // a count for each bound, then the count above the last bound, then the sum
+    slots: Slots,
+}
+
+impl Histogram {
+    /// A histogram with buckets up to each of `bounds`, which must be in increasing order
+    pub const fn new(name: &'static str, help: &'static str, bounds: &'static [f64]) -> Self {
+        Histogram { name, help, bounds, slots: Slots::new() }
+    }
+
+    fn sum_slot(&self) -> usize {
+        self.bounds.len() + 1
+    }
+
+    /// Count `value` into its bucket
+    pub fn observe(&self, value: f64) {
+        let bucket =
+            self.bounds.iter().position(|&bound| value <= bound).unwrap_or(self.bounds.len());
+        if let (Some(count), Some(sum)) = (self.slots.get(bucket), self.slots.get(self.sum_slot()))
+        {
+            count.fetch_add(1, Ordering::Relaxed);
+            add_f64(sum, value);
+        }
+    }
+
+    /// The upper bounds of the buckets, not counting `+Inf`
+    pub fn bounds(&self) -> &'static [f64] {
+        self.bounds
+    }
+
+    /// The number of observations in each bucket, cumulatively, ending with `+Inf`
+    pub fn buckets(&self) -> Vec<u64> {
+        (0..=self.bounds.len())
+            .scan(0, |total, i| {
+                *total += self.slots.load(i);
+                Some(*total)
+            })
+            .collect()
+    }
+
+    /// The number of observations
+    pub fn count(&self) -> u64 {
+        (0..=self.bounds.len()).map(|i| self.slots.load(i)).sum()
+    }
+
+    /// The sum of the observations
+    pub fn sum(&self) -> f64 {
+        f64::from_bits(self.slots.load(self.sum_slot()))
+    }
+}
+
+/// A metric registered with a [`MetricsRegistry`]
+#[derive(Copy, Clone)]
+pub enum Metric {
+    Counter(&'static Counter),
+    Gauge(&'static Gauge),
+    Histogram(&'static Histogram),
+}
+
+impl From<&'static Counter> for Metric {
+    fn from(counter: &'static Counter) -> Self {
+        Metric::Counter(counter)
+    }
+}
+
+impl From<&'static Gauge> for Metric {
+    fn from(gauge: &'static Gauge) -> Self {
+        Metric::Gauge(gauge)
+    }
+}
+
+impl From<&'static Histogram> for Metric {
+    fn from(histogram: &'static Histogram) -> Self {
+        Metric::Histogram(histogram)
+    }
+}
+
+impl Metric {
+    pub fn name(&self) -> &'static str {
+        match self {
+            Metric::Counter(counter) => counter.name,
+            Metric::Gauge(gauge) => gauge.name,
+            Metric::Histogram(histogram) => histogram.name,
+        }
+    }
+
+    pub fn help(&self) -> &'static str {
+        match self {
+            Metric::Counter(counter) => counter.help,
+            Metric::Gauge(gauge) => gauge.help,
+            Metric::Histogram(histogram) => histogram.help,
+        }
+    }
+
+    pub fn kind(&self) -> MetricKind {
+        match self {
+            Metric::Counter(_) => MetricKind::Counter,
+            Metric::Gauge(_) => MetricKind::Gauge,
+            Metric::Histogram(_) => MetricKind::Histogram,
+        }
+    }
+
+    fn slots(&self) -> &Slots {
+        match self {
+            Metric::Counter(counter) => &counter.slots,
+            Metric::Gauge(gauge) => &gauge.slots,
+            Metric::Histogram(histogram) => &histogram.slots,
+        }
+    }
+
+    fn slot_count(&self) -> usize {
+        match self {
+            Metric::Counter(_) | Metric::Gauge(_) => 1,
+            Metric::Histogram(histogram) => histogram.bounds.len() + 2,
+        }
+    }
+
+    /// The metric's current values, as Prometheus would name them:  one for a counter or gauge,
+    /// and for a histogram, one for each bucket, then the sum and the count
+    pub fn samples(&self) -> Vec<MetricSample> {
+        let sample = |suffix: &str, le: Option<f64>, value: f64| MetricSample {
+            name: format!("{}{}", self.name(), suffix),
+            kind: self.kind(),
+            le,
+            value,
+        };
+        match self {
+            Metric::Counter(counter) => vec![sample("", None, counter.get() as f64)],
+            Metric::Gauge(gauge) => vec![sample("", None, gauge.get())],
+            Metric::Histogram(histogram) => {
+                let bounds = histogram.bounds.iter().copied().chain(Some(f64::INFINITY));
+                let mut samples = bounds
+                    .zip(histogram.buckets())
+                    .map(|(le, count)| sample("_bucket", Some(le), count as f64))
+                    .collect::<Vec<_>>();
+                samples.push(sample("_sum", None, histogram.sum()));
+                samples.push(sample("_count", None, histogram.count() as f64));
+                samples
+            }
+        }
+    }
+}
+
+/// A value of a metric, from [`Metric::samples()`]
+#[derive(Debug, Clone, PartialEq)]
+pub struct MetricSample {
+    pub name: String,
+    pub kind: MetricKind,
+    /// For a histogram's buckets, the bucket's upper bound
+    pub le: Option<f64>,
+    pub value: f64,
+}
+
+/// The shared memory holding an extension's metrics
+pub struct MetricsRegistry {
+    name: &'static str,
+    metrics: Mutex<Vec<Metric>>,
+    attached: AtomicBool,
+}
+
+impl MetricsRegistry {
+    /// A registry whose shared memory is named after `name`, which should be the extension's name
+    pub const fn new(name: &'static str) -> Self {
+        MetricsRegistry { name, metrics: Mutex::new(Vec::new()), attached: AtomicBool::new(false) }
+    }
+
+    // a registration that panics adds nothing, so a poisoned lock still holds every metric
+    fn lock(&self) -> MutexGuard<'_, Vec<Metric>> {
+        self.metrics.lock().unwrap_or_else(PoisonError::into_inner)
+    }
+
+    /// Add `metric` to the registry.  This must be called from `_PG_init()`, before the registry
+    /// is passed to [`pg_shmem_init!()`](crate::pg_shmem_init)
+    ///
+    /// # Panics
+    ///
+    /// If a metric with the same name was already registered
+    pub fn register(&'static self, metric: impl Into<Metric>) {
+        let metric = metric.into();
+        let mut metrics = self.lock();
+        assert!(
+            metrics.iter().all(|registered| registered.name() != metric.name()),
+            "metric `{}` is already registered",
+            metric.name()
+        );
+        metrics.push(metric);
+    }
+
+    /// The registered metrics, in the order they were registered
+    pub fn metrics(&self) -> Vec<Metric> {
+        self.lock().clone()
+    }
+
+    fn size(&self) -> usize {
+        let slots = self.lock().iter().map(Metric::slot_count).sum::<usize>();
+        slots * std::mem::size_of::<AtomicU64>()
+    }
+
+    fn check_attached(&self) {
+        if !self.attached.load(Ordering::Acquire) {
+            crate::ereport!(
+                PgLogLevel::ERROR,
+                PgSqlErrorCode::ERRCODE_OBJECT_NOT_IN_PREREQUISITE_STATE,
+                format!("the metrics of `{}` are not in shared memory", self.name),
+                "The extension must be loaded through shared_preload_libraries."
+            );
+        }
+    }
+
+    /// Every registered metric's current values
+    pub fn samples(&self) -> Vec<MetricSample> {
+        self.check_attached();
+        self.lock().iter().flat_map(Metric::samples).collect()
+    }
+
+    /// Every registered metric's current values, in Prometheus' text exposition format
+    pub fn prometheus_text(&self) -> String {
+        self.check_attached();
+        let mut text = String::new();
+        for metric in self.metrics().iter() {
+            let help = metric.help().replace('\\', "\\\\").replace('\n', "\\n");
+            writeln!(text, "# HELP {} {}", metric.name(), help).unwrap();
+            writeln!(text, "# TYPE {} {}", metric.name(), metric.kind().as_str()).unwrap();
+            for sample in metric.samples() {
+                match sample.le {
+                    Some(le) => writeln!(
+                        text,
+                        "{}{{le=\"{}\"}} {}",
+                        sample.name,
+                        prometheus_float(le),
+                        prometheus_float(sample.value)
+                    ),
+                    None => writeln!(text, "{} {}", sample.name, prometheus_float(sample.value)),
+                }
+                .unwrap();
+            }
+        }
+        text
+    }
+}
+
+fn prometheus_float(value: f64) -> String {
+    if value == f64::INFINITY {
+        "+Inf".into()
+    } else if value == f64::NEG_INFINITY {
+        "-Inf".into()
+    } else if value.is_nan() {
+        "NaN".into()
+    } else {
+        value.to_string()
+    }
+}
+
+impl PgSharedMemoryInitialization for MetricsRegistry {
+    fn pg_init(&'static self) {
+        unsafe {
+            pg_sys::RequestAddinShmemSpace(self.size());
+        }
+    }
+
+    fn shmem_init(&'static self) {
+        unsafe {
+            let name = format!("pgrx metrics of {}", self.name);
+            let shmem = shmem_init_zeroed(&name, self.size());
+
+            let mut next = shmem.cast::<AtomicU64>();
+            for metric in self.lock().iter() {
+                metric.slots().attach(next);
+                next = next.add(metric.slot_count());
+            }
+            self.attached.store(true, Ordering::Release);
+        }
+    }
+}
+
+/// Add functions listing the metrics of a [`MetricsRegistry`](crate::metrics::MetricsRegistry) to
+/// the extension's schema
+///
+/// The first function returns a row for each of the metrics' values, with the value's name, the
+/// metric's kind, the bucket's upper bound for a histogram bucket, and the value.  The second,
+/// if given, returns them all as Prometheus' text exposition format.
+///
+/// ```rust,no_run
+/// use pgrx::metrics::MetricsRegistry;
+///
+/// static METRICS: MetricsRegistry = MetricsRegistry::new("my_extension");
+/// pgrx::metrics_functions!(METRICS, fn my_extension_metrics, fn my_extension_prometheus);
+/// ```
+#[macro_export]
+macro_rules! metrics_functions {
+    ($registry:expr, fn $metrics:ident $(, fn $prometheus:ident)? $(,)?) => {
+        #[::pgrx::pg_extern(volatile)]
+        fn $metrics() -> ::pgrx::iter::TableIterator<
+            'static,
+            (
+                ::pgrx::name!(name, String),
+                ::pgrx::name!(kind, String),
+                ::pgrx::name!(le, Option<f64>),
+                ::pgrx::name!(value, f64),
+            ),
+        > {
+            let samples = $registry
+                .samples()
+                .into_iter()
+                .map(|sample| {
+                    (sample.name, sample.kind.as_str().to_string(), sample.le, sample.value)
+                })
+                .collect::<Vec<_>>();
+            $crate::iter::TableIterator::new(samples.into_iter())
+        }
+
+        $(
+            #[::pgrx::pg_extern(volatile)]
+            fn $prometheus() -> String {
+                $registry.prometheus_text()
+            }
+        )?
+    };
+}
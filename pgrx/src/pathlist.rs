@@ -0,0 +1,247 @@
+/*
+Portions Copyright 2019-2021 ZomboDB, LLC.
+Portions Copyright 2021-2022 Technology Concepts & Design, Inc. <support@tcdi.com>
+
+All rights reserved.
+
+Use of this source code is governed by the MIT license that can be found in the LICENSE file.
+*/
+
+//! Safe editing of a relation's candidate paths, for `set_rel_pathlist_hook` implementations
+//!
+//! By the time [`PgHooks::set_rel_pathlist()`](crate::hooks::PgHooks::set_rel_pathlist) runs, the
+//! planner has generated every path it knows of for a base relation, and is about to pick the
+//! cheapest of them.  [`RelPathList`] lets a hook remove, add, penalize or re-cost those paths
+//! while keeping the invariants the rest of the planner relies on:  there is always at least one
+//! path left, every path belongs to the relation, costs are sane, and the path list stays sorted
+//! by total cost.
+//!
+//! ## Examples
+//!
+//! Force the use of a particular index, the way `pg_hint_plan`'s `IndexScan()` hint would:
+//!
+//! ```rust,no_run
+//! use pgrx::pathlist::{index_oid, RelPathList};
+//! use pgrx::prelude::*;
+//!
+//! # unsafe fn example(rel: *mut pg_sys::RelOptInfo, wanted: pg_sys::Oid) {
+//! let mut paths = RelPathList::from_pg(rel);
+//! if paths.iter().any(|path| index_oid(path) == Some(wanted)) {
+//!     paths.retain(|path| index_oid(path) == Some(wanted));
+//! }
+//! # }
+//! ```
+use crate::{pg_sys, PgList};
+
+/// The paths of a relation being planned
+pub struct RelPathList {
+    rel: *mut pg_sys::RelOptInfo,
+}
+
+impl RelPathList {
+    /// Wrap the `RelOptInfo` given to a `set_rel_pathlist_hook`
+    ///
+    /// # Safety
+    ///
+    /// `rel` must be a valid `RelOptInfo` whose paths have not yet been chosen from by
+    /// `set_cheapest()`
+    pub unsafe fn from_pg(rel: *mut pg_sys::RelOptInfo) -> Self {
+        assert!(!rel.is_null(), "RelOptInfo is NULL");
+        RelPathList { rel }
+    }
+
+    /// The range table index of the relation, or zero if it is a join relation
+    pub fn relid(&self) -> pg_sys::Index {
+        unsafe { (*self.rel).relid }
+    }
+
+    /// The number of (non-partial) paths
+    pub fn len(&self) -> usize {
+        self.pathlist().len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    /// The paths, cheapest total cost first
+    pub fn iter(&self) -> impl Iterator<Item = &pg_sys::Path> + '_ {
+        self.pathlist().iter_ptr().map(|path| unsafe { &*path }).collect::<Vec<_>>().into_iter()
+    }
+
+    /// The partial paths, which a parallel plan can gather from, cheapest total cost first
+    pub fn iter_partial(&self) -> impl Iterator<Item = &pg_sys::Path> + '_ {
+        self.partial_pathlist()
+            .iter_ptr()
+            .map(|path| unsafe { &*path })
+            .collect::<Vec<_>>()
+            .into_iter()
+    }
+
+    /// Keep only the paths for which `f` returns true
+    ///
+    /// # Panics
+    ///
+    /// Panics if that would remove every path, as the planner has to have something to choose
+    pub fn retain<F: FnMut(&pg_sys::Path) -> bool>(&mut self, mut f: F) {
+        let kept = self.pathlist().iter_ptr().filter(|path| f(unsafe { &**path })).collect();
+        let kept = sorted_list(kept);
+        if kept.is_null() {
+            panic!("cannot remove every path for a relation");
+        }
+        unsafe { (*self.rel).pathlist = kept }
+    }
+
+    /// Keep only the partial paths for which `f` returns true.  Removing every one of them is
+    /// allowed, and rules out a parallel scan of the relation
+    pub fn retain_partial<F: FnMut(&pg_sys::Path) -> bool>(&mut self, mut f: F) {
+        let kept =
+            self.partial_pathlist().iter_ptr().filter(|path| f(unsafe { &**path })).collect();
+        unsafe { (*self.rel).partial_pathlist = sorted_list(kept) }
+    }
+
+    /// Offer a new path for the relation, through `add_path()`.  It may be discarded, or cause
+    /// existing paths to be discarded, if it is dominated by or dominates them.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the path belongs to a different relation or its costs are invalid
+    ///
+    /// # Safety
+    ///
+    /// `path` must be a valid, palloc'd path node
+    pub unsafe fn add_path(&mut self, path: *mut pg_sys::Path) {
+        self.check_path(path);
+        pg_sys::add_path(self.rel, path);
+    }
+
+    /// Offer a new partial path for the relation, through `add_partial_path()`
+    ///
+    /// # Panics
+    ///
+    /// Panics if the path belongs to a different relation, its costs are invalid, or it isn't
+    /// parallel-safe
+    ///
+    /// # Safety
+    ///
+    /// `path` must be a valid, palloc'd path node
+    pub unsafe fn add_partial_path(&mut self, path: *mut pg_sys::Path) {
+        self.check_path(path);
+        assert!((*path).parallel_safe, "partial paths must be parallel-safe");
+        pg_sys::add_partial_path(self.rel, path);
+    }
+
+    /// Change the estimates of existing paths (both regular and partial) by calling `f` with each
+    /// path and its estimates.  The paths are re-sorted by total cost afterwards.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `f` leaves a path with invalid costs
+    pub fn adjust_costs<F: FnMut(&pg_sys::Path, &mut PathEstimates)>(&mut self, mut f: F) {
+        unsafe {
+            for list in [(*self.rel).pathlist, (*self.rel).partial_pathlist] {
+                for path in PgList::<pg_sys::Path>::from_pg(list).iter_ptr() {
+                    let mut estimates = PathEstimates {
+                        startup_cost: (*path).startup_cost,
+                        total_cost: (*path).total_cost,
+                        rows: (*path).rows,
+                    };
+                    f(&*path, &mut estimates);
+                    estimates.check();
+                    (*path).startup_cost = estimates.startup_cost;
+                    (*path).total_cost = estimates.total_cost;
+                    (*path).rows = estimates.rows;
+                }
+            }
+            (*self.rel).pathlist = sorted_list(self.pathlist().iter_ptr().collect());
+            (*self.rel).partial_pathlist =
+                sorted_list(self.partial_pathlist().iter_ptr().collect());
+        }
+    }
+
+    /// Make the paths for which `f` returns true look prohibitively expensive, like setting an
+    /// `enable_*` GUC to off does.  Unlike [`RelPathList::retain()`], they stay available in case
+    /// nothing else can be used
+    pub fn disable<F: FnMut(&pg_sys::Path) -> bool>(&mut self, mut f: F) {
+        let disable_cost = unsafe { pg_sys::disable_cost };
+        self.adjust_costs(|path, estimates| {
+            if f(path) {
+                estimates.startup_cost += disable_cost;
+                estimates.total_cost += disable_cost;
+            }
+        })
+    }
+
+    fn pathlist(&self) -> PgList<pg_sys::Path> {
+        unsafe { PgList::from_pg((*self.rel).pathlist) }
+    }
+
+    fn partial_pathlist(&self) -> PgList<pg_sys::Path> {
+        unsafe { PgList::from_pg((*self.rel).partial_pathlist) }
+    }
+
+    unsafe fn check_path(&self, path: *mut pg_sys::Path) {
+        assert!(!path.is_null(), "path is NULL");
+        assert!((*path).parent == self.rel, "path belongs to a different relation");
+        PathEstimates {
+            startup_cost: (*path).startup_cost,
+            total_cost: (*path).total_cost,
+            rows: (*path).rows,
+        }
+        .check();
+    }
+}
+
+/// The planner's estimates for a path, as seen by [`RelPathList::adjust_costs()`]
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub struct PathEstimates {
+    pub startup_cost: f64,
+    pub total_cost: f64,
+    pub rows: f64,
+}
+
+impl PathEstimates {
+    fn check(&self) {
+        assert!(
+            self.startup_cost.is_finite() && self.startup_cost >= 0.0,
+            "invalid startup cost: {}",
+            self.startup_cost
+        );
+        assert!(
+            self.total_cost.is_finite() && self.total_cost >= self.startup_cost,
+            "invalid total cost: {} (startup cost {})",
+            self.total_cost,
+            self.startup_cost
+        );
+        assert!(self.rows.is_finite() && self.rows >= 0.0, "invalid row estimate: {}", self.rows);
+    }
+}
+
+/// Build a new `List` of `paths`, sorted by total cost like `add_path()` keeps them
+fn sorted_list(mut paths: Vec<*mut pg_sys::Path>) -> *mut pg_sys::List {
+    unsafe {
+        paths.sort_by(|a, b| (**a).total_cost.total_cmp(&(**b).total_cost));
+        let mut list = PgList::<pg_sys::Path>::new();
+        for path in paths {
+            list.push(path);
+        }
+        list.into_pg()
+    }
+}
+
+/// The kind of scan a path would produce, such as `NodeTag_T_SeqScan` or `NodeTag_T_IndexScan`
+pub fn scan_type(path: &pg_sys::Path) -> pg_sys::NodeTag {
+    path.pathtype
+}
+
+/// The oid of the index an index or index-only scan path would use
+pub fn index_oid(path: &pg_sys::Path) -> Option<pg_sys::Oid> {
+    if path.type_ != pg_sys::NodeTag_T_IndexPath {
+        return None;
+    }
+    unsafe {
+        // SAFETY:  the node tag says this is an IndexPath
+        let index_path = path as *const pg_sys::Path as *const pg_sys::IndexPath;
+        (*index_path).indexinfo.as_ref().map(|info| info.indexoid)
+    }
+}
@@ -8,7 +8,7 @@ Use of this source code is governed by the MIT license that can be found in the
 */
 
 /// Similar to Rust's `Box<T>` type, `PgBox<T>` also represents heap-allocated memory.
-use crate::{pg_sys, PgMemoryContexts};
+use crate::{memcxt_tracking, pg_sys, PgMemoryContexts};
 //use std::fmt::{Debug, Error, Formatter};
 use pgrx_sql_entity_graph::metadata::{
     ArgumentError, Returns, ReturnsError, SqlMapping, SqlTranslatable,
@@ -122,6 +122,13 @@ impl WhoAllocated for AllocatedByRust {
     /// Uses [`pg_sys::pfree`] to free the specified pointer
     #[inline]
     unsafe fn maybe_pfree(ptr: *mut std::os::raw::c_void) {
+        if !memcxt_tracking::is_allocation_live(ptr) {
+            // the memory is already gone.  Complain, unless we're already unwinding from a panic
+            if !std::thread::panicking() {
+                memcxt_tracking::assert_allocation_live(ptr, "PgBox");
+            }
+            return;
+        }
         pg_sys::pfree(ptr.cast());
     }
 }
@@ -133,6 +140,8 @@ impl<T> PgBox<T, AllocatedByPostgres> {
     /// allocated it, Postgres is responsible for freeing it.
     #[inline]
     pub unsafe fn from_pg(ptr: *mut T) -> PgBox<T, AllocatedByPostgres> {
+        // whatever we knew about this address no longer applies
+        memcxt_tracking::forget_allocation(ptr.cast());
         PgBox::<T, AllocatedByPostgres> { ptr: NonNull::new(ptr), __marker: PhantomData }
     }
 }
@@ -146,6 +155,8 @@ impl<T, AllocatedBy: WhoAllocated> PgBox<T, AllocatedBy> {
     /// If you need to give the boxed pointer to Postgres, call [`.into_pg()`][PgBox::into_pg]
     #[inline]
     pub unsafe fn from_rust(ptr: *mut T) -> PgBox<T, AllocatedByRust> {
+        // a fresh allocation can land where a reset context freed a tracked one
+        memcxt_tracking::forget_allocation(ptr.cast());
         PgBox::<T, AllocatedByRust> { ptr: NonNull::new(ptr), __marker: PhantomData }
     }
 
@@ -171,10 +182,10 @@ impl<T, AllocatedBy: WhoAllocated> PgBox<T, AllocatedBy> {
     /// be a valid state for `T`.
     #[inline]
     pub unsafe fn alloc() -> PgBox<T, AllocatedByRust> {
+        let ptr = pg_sys::palloc(std::mem::size_of::<T>());
+        memcxt_tracking::track_allocation(ptr, pg_sys::CurrentMemoryContext);
         PgBox::<T, AllocatedByRust> {
-            ptr: Some(unsafe {
-                NonNull::new_unchecked(pg_sys::palloc(std::mem::size_of::<T>()) as *mut T)
-            }),
+            ptr: Some(unsafe { NonNull::new_unchecked(ptr as *mut T) }),
             __marker: PhantomData,
         }
     }
@@ -201,10 +212,10 @@ impl<T, AllocatedBy: WhoAllocated> PgBox<T, AllocatedBy> {
     /// be a valid state for `T`.
     #[inline]
     pub unsafe fn alloc0() -> PgBox<T, AllocatedByRust> {
+        let ptr = pg_sys::palloc0(std::mem::size_of::<T>());
+        memcxt_tracking::track_allocation(ptr, pg_sys::CurrentMemoryContext);
         PgBox::<T, AllocatedByRust> {
-            ptr: Some(unsafe {
-                NonNull::new_unchecked(pg_sys::palloc0(std::mem::size_of::<T>()) as *mut T)
-            }),
+            ptr: Some(unsafe { NonNull::new_unchecked(ptr as *mut T) }),
             __marker: PhantomData,
         }
     }
@@ -231,13 +242,11 @@ impl<T, AllocatedBy: WhoAllocated> PgBox<T, AllocatedBy> {
     /// be a valid state for `T`.
     #[inline]
     pub unsafe fn alloc_in_context(memory_context: PgMemoryContexts) -> PgBox<T, AllocatedByRust> {
+        let context = memory_context.value();
+        let ptr = pg_sys::MemoryContextAlloc(context, std::mem::size_of::<T>());
+        memcxt_tracking::track_allocation(ptr, context);
         PgBox::<T, AllocatedByRust> {
-            ptr: Some(unsafe {
-                NonNull::new_unchecked(pg_sys::MemoryContextAlloc(
-                    memory_context.value(),
-                    std::mem::size_of::<T>(),
-                ) as *mut T)
-            }),
+            ptr: Some(unsafe { NonNull::new_unchecked(ptr as *mut T) }),
             __marker: PhantomData,
         }
     }
@@ -264,13 +273,11 @@ impl<T, AllocatedBy: WhoAllocated> PgBox<T, AllocatedBy> {
     /// be a valid state for `T`.
     #[inline]
     pub unsafe fn alloc0_in_context(memory_context: PgMemoryContexts) -> PgBox<T, AllocatedByRust> {
+        let context = memory_context.value();
+        let ptr = pg_sys::MemoryContextAllocZero(context, std::mem::size_of::<T>());
+        memcxt_tracking::track_allocation(ptr, context);
         PgBox::<T, AllocatedByRust> {
-            ptr: Some(unsafe {
-                NonNull::new_unchecked(pg_sys::MemoryContextAllocZero(
-                    memory_context.value(),
-                    std::mem::size_of::<T>(),
-                ) as *mut T)
-            }),
+            ptr: Some(unsafe { NonNull::new_unchecked(ptr as *mut T) }),
             __marker: PhantomData,
         }
     }
@@ -337,7 +344,10 @@ impl<T, AllocatedBy: WhoAllocated> PgBox<T, AllocatedBy> {
     #[inline]
     pub fn into_pg(mut self) -> *mut T {
         match self.ptr.take() {
-            Some(ptr) => ptr.as_ptr(),
+            Some(ptr) => {
+                memcxt_tracking::forget_allocation(ptr.as_ptr().cast());
+                ptr.as_ptr()
+            }
             None => std::ptr::null_mut(),
         }
     }
@@ -347,13 +357,9 @@ impl<T, AllocatedBy: WhoAllocated> PgBox<T, AllocatedBy> {
     /// The boxed pointer is **not** free'd by Rust
     #[inline]
     pub fn into_pg_boxed(mut self) -> PgBox<T, AllocatedByPostgres> {
-        // SAFETY:  we know our internal pointer is good so we can now make it owned by Postgres
-        unsafe {
-            PgBox::from_pg(match self.ptr.take() {
-                Some(ptr) => ptr.as_ptr(),
-                None => std::ptr::null_mut(),
-            })
-        }
+        // we know our internal pointer is good so we can now make it owned by Postgres.  We don't
+        // go through `::from_pg()` so the pointer stays tracked in debug builds
+        PgBox::<T, AllocatedByPostgres> { ptr: self.ptr.take(), __marker: PhantomData }
     }
 
     /// Execute a closure with a mutable, `PgBox`'d form of the specified `ptr`
@@ -374,7 +380,10 @@ impl<T, AllocatedBy: WhoAllocated> Deref for PgBox<T, AllocatedBy> {
     #[track_caller]
     fn deref(&self) -> &Self::Target {
         match self.ptr.as_ref() {
-            Some(ptr) => unsafe { ptr.as_ref() },
+            Some(ptr) => unsafe {
+                memcxt_tracking::assert_allocation_live(ptr.as_ptr().cast(), "PgBox");
+                ptr.as_ref()
+            },
             None => panic!("Attempt to dereference null pointer during Deref of PgBox"),
         }
     }
@@ -384,7 +393,10 @@ impl<T, AllocatedBy: WhoAllocated> DerefMut for PgBox<T, AllocatedBy> {
     #[track_caller]
     fn deref_mut(&mut self) -> &mut T {
         match self.ptr.as_mut() {
-            Some(ptr) => unsafe { ptr.as_mut() },
+            Some(ptr) => unsafe {
+                memcxt_tracking::assert_allocation_live(ptr.as_ptr().cast(), "PgBox");
+                ptr.as_mut()
+            },
             None => panic!("Attempt to dereference null pointer during DerefMut of PgBox"),
         }
     }
@@ -397,6 +409,7 @@ impl<T, AllocatedBy: WhoAllocated> Drop for PgBox<T, AllocatedBy> {
                 // SAFETY:  we know ptr is a valid, non-null, Postgres allocated pointer
                 AllocatedBy::maybe_pfree(ptr.as_ptr().cast());
             }
+            memcxt_tracking::forget_allocation(ptr.as_ptr().cast());
         }
     }
 }
@@ -0,0 +1,126 @@
+/*
+Portions Copyright 2019-2021 ZomboDB, LLC.
+Portions Copyright 2021-2022 Technology Concepts & Design, Inc. <support@tcdi.com>
+
+All rights reserved.
+
+Use of this source code is governed by the MIT license that can be found in the LICENSE file.
+*/
+
+//! Introspection of the current backend's portals, which is how Postgres represents open cursors
+//!
+//! [`active_portals()`] lists every cursor visible to SQL, whether it was `DECLARE`d by the
+//! client, opened by a PL/pgSQL function, or opened through [`Spi`](crate::spi::Spi).  Unlike the
+//! `pg_cursors` view, it also reports where each cursor is positioned and the state of its
+//! execution.  Rows can be fetched from any of them, by name, with
+//! [`SpiClient::fetch_from_cursor()`](crate::spi::SpiClient::fetch_from_cursor).
+use crate::prelude::*;
+use pg_sys::AsPgCStr;
+use std::ffi::CStr;
+
+/// The state of a portal's execution
+#[derive(Debug, Copy, Clone, Eq, PartialEq)]
+pub enum PortalStatus {
+    /// Freshly created
+    New,
+    /// Its query has been planned
+    Defined,
+    /// Ready to run, or to fetch more rows from
+    Ready,
+    /// Currently running
+    Active,
+    /// Finished running, and can't be run again
+    Done,
+    /// An error occurred while it was running
+    Failed,
+}
+
+impl PortalStatus {
+    fn from_pg(status: pg_sys::PortalStatus) -> Self {
+        match status {
+            pg_sys::PortalStatus_PORTAL_NEW => PortalStatus::New,
+            pg_sys::PortalStatus_PORTAL_DEFINED => PortalStatus::Defined,
+            pg_sys::PortalStatus_PORTAL_READY => PortalStatus::Ready,
+            pg_sys::PortalStatus_PORTAL_ACTIVE => PortalStatus::Active,
+            pg_sys::PortalStatus_PORTAL_DONE => PortalStatus::Done,
+            _ => PortalStatus::Failed,
+        }
+    }
+}
+
+/// A description of a portal, as of when it was looked up
+#[derive(Debug, Clone, PartialEq)]
+pub struct PortalInfo {
+    /// The portal's name, which is the cursor's name
+    pub name: String,
+    /// The text of the statement that created the portal, such as the `DECLARE` command
+    pub statement: Option<String>,
+    pub status: PortalStatus,
+    /// Was it created `WITH HOLD`, so it outlives its transaction?
+    pub holdable: bool,
+    /// Can it be fetched from backwards?
+    pub scrollable: bool,
+    /// Does it return rows in binary format?
+    pub binary: bool,
+    /// Is it positioned before its first row?
+    pub at_start: bool,
+    /// Is it positioned after its last row?
+    pub at_end: bool,
+    /// The number of the row it's positioned on, counting from one.  Zero is before the first
+    pub position: u64,
+    pub created: TimestampWithTimeZone,
+}
+
+impl PortalInfo {
+    /// Describe a portal
+    ///
+    /// # Safety
+    ///
+    /// `portal` must be a valid portal
+    pub unsafe fn from_pg(portal: &pg_sys::PortalData) -> Self {
+        let cstr = |ptr: *const std::os::raw::c_char| {
+            (!ptr.is_null()).then(|| CStr::from_ptr(ptr).to_string_lossy().into_owned())
+        };
+        let option = |option: u32| portal.cursorOptions & option as i32 != 0;
+
+        PortalInfo {
+            name: cstr(portal.name).unwrap_or_default(),
+            statement: cstr(portal.sourceText),
+            status: PortalStatus::from_pg(portal.status),
+            holdable: option(pg_sys::CURSOR_OPT_HOLD),
+            scrollable: option(pg_sys::CURSOR_OPT_SCROLL),
+            binary: option(pg_sys::CURSOR_OPT_BINARY),
+            at_start: portal.atStart,
+            at_end: portal.atEnd,
+            position: portal.portalPos,
+            created: portal.creation_time.try_into().unwrap_or(TimestampWithTimeZone::INFINITY),
+        }
+    }
+}
+
+/// The portal named `name`, if there is one
+pub fn find_portal(name: &str) -> Option<PortalInfo> {
+    unsafe {
+        // SAFETY:  GetPortalByName() returns a valid portal or null
+        pg_sys::GetPortalByName(name.as_pg_cstr())
+            .as_ref()
+            .map(|portal| PortalInfo::from_pg(portal))
+    }
+}
+
+/// Every portal visible to SQL, in the order they were created.  These are the cursors listed in
+/// the `pg_cursors` view.  The unnamed portals used to run protocol-level statements aren't
+/// included
+pub fn active_portals() -> spi::Result<Vec<PortalInfo>> {
+    let names = Spi::connect(|client| {
+        client
+            .select(
+                "SELECT name FROM pg_catalog.pg_cursors ORDER BY creation_time, name",
+                None,
+                None,
+            )?
+            .map(|row| row.get::<String>(1))
+            .collect::<spi::Result<Vec<_>>>()
+    })?;
+    Ok(names.into_iter().flatten().filter_map(|name| find_portal(&name)).collect())
+}
@@ -12,18 +12,21 @@ pub use crate::{default, name};
 pub use crate::iter::{SetOfIterator, TableIterator};
 
 // Needed for complex returns and Triggers
+pub use crate::composite::PostgresComposite;
 pub use crate::heap_tuple::{PgHeapTuple, PgHeapTupleError};
 pub use crate::pgbox::{AllocatedByPostgres, AllocatedByRust, PgBox, WhoAllocated};
 
 // These could be factored into a temporal type module that could be easily imported for code which works with them.
 // However, reexporting them seems fine for now.
 
+#[cfg(any(feature = "pg14", feature = "pg15"))]
+pub use crate::datum::MultiRange;
 pub use crate::datum::{
-    AnyNumeric, Array, Date, FromDatum, Interval, IntoDatum, Numeric, PgVarlena, PostgresType,
-    Range, RangeBound, RangeSubType, Time, TimeWithTimeZone, Timestamp, TimestampWithTimeZone,
-    VariadicArray,
+    AnyNumeric, Array, Date, FromDatum, Interval, IntoDatum, Migrations, Numeric, PgVarlena,
+    PostgresType, Range, RangeBound, RangeSubType, Time, TimeWithTimeZone, Timestamp,
+    TimestampWithTimeZone, VariadicArray, VersionedType,
 };
-pub use crate::inoutfuncs::{InOutFuncs, JsonInOutFuncs, PgVarlenaInOutFuncs};
+pub use crate::inoutfuncs::{FmtInOutFuncs, InOutFuncs, JsonInOutFuncs, PgVarlenaInOutFuncs};
 
 // Trigger support
 pub use crate::trigger_support::{
@@ -34,7 +37,7 @@ pub use crate::trigger_support::{
 pub use crate::aggregate::{Aggregate, FinalizeModify, ParallelOption};
 
 pub use crate::pg_sys::oids::PgOid;
-pub use crate::pg_sys::pg_try::PgTryBuilder;
+pub use crate::pg_sys::pg_try::{pg_try, PgTryBuilder};
 pub use crate::pg_sys::utils::name_data_to_str;
 pub use crate::pg_sys::PgBuiltInOids;
 
@@ -0,0 +1,390 @@
+/*
+Portions Copyright 2019-2021 ZomboDB, LLC.
+Portions Copyright 2021-2022 Technology Concepts & Design, Inc. <support@tcdi.com>
+
+All rights reserved.
+
+Use of this source code is governed by the MIT license that can be found in the LICENSE file.
+*/
+
+//! Rate limiters and circuit breakers shared by every backend
+//!
+//! A [`RateLimiter`] is a token bucket:  it allows bursts of up to a number of calls, refilled at
+//! a steady rate.  A [`CircuitBreaker`] stops calls to something that keeps failing, like an
+//! external service that's down, for a cooldown period, then lets a single trial call through to
+//! see whether it has recovered.
+//!
+//! Both keep their state in shared memory, so the limits hold across all backends rather than
+//! per connection.  Each is declared as a `static` and passed to
+//! [`pg_shmem_init!()`](crate::pg_shmem_init) from `_PG_init()`, and updated with lock-free
+//! atomic operations.
+//!
+//! ## Examples
+//!
+//! ```rust,no_run
+//! use pgrx::prelude::*;
+//! use pgrx::rate_limit::{CircuitBreaker, RateLimiter};
+//! use pgrx::{pg_shmem_init, PgSharedMemoryInitialization};
+//! use std::time::Duration;
+//!
+//! // ten calls a second, in bursts of up to twenty
+//! static GEOCODER_RATE: RateLimiter = RateLimiter::new("my_extension.geocoder", 10.0, 20);
+//! // stop calling after five failures in a row, for thirty seconds
+//! static GEOCODER: CircuitBreaker =
+//!     CircuitBreaker::new("my_extension.geocoder", 5, Duration::from_secs(30));
+//!
+//! #[pg_guard]
+//! pub extern "C" fn _PG_init() {
+//!     pg_shmem_init!(GEOCODER_RATE);
+//!     pg_shmem_init!(GEOCODER);
+//! }
+//!
+//! # fn call_geocoder(_address: &str) -> Result<String, String> { todo!() }
+//! #[pg_extern]
+//! fn geocode(address: &str) -> Option<String> {
+//!     GEOCODER_RATE.acquire();
+//!     GEOCODER.call(|| call_geocoder(address)).ok()
+//! }
+//! ```
+//!
+//! As with any shared memory, the extension must be in `shared_preload_libraries`.  If it isn't,
+//! using a rate limiter or circuit breaker raises an error.
+use crate::shmem::{shmem_init_zeroed, PgSharedMemoryInitialization};
+use crate::{check_for_interrupts, pg_sys, PgLogLevel, PgSqlErrorCode};
+use std::sync::atomic::{AtomicI64, AtomicPtr, AtomicU64, Ordering};
+use std::time::Duration;
+
+/// The time, in microseconds
+fn now() -> i64 {
+    unsafe { pg_sys::GetCurrentTimestamp() }
+}
+
+/// Attach to the shared memory named after `name`, zeroing it if this is the first to
+unsafe fn attach<T>(kind: &str, name: &str) -> *mut T {
+    shmem_init_zeroed(&format!("pgrx {} {}", kind, name), std::mem::size_of::<T>()).cast()
+}
+
+fn not_attached(kind: &str, name: &str) -> ! {
+    crate::ereport!(
+        PgLogLevel::ERROR,
+        PgSqlErrorCode::ERRCODE_OBJECT_NOT_IN_PREREQUISITE_STATE,
+        format!("{} `{}` is not in shared memory", kind, name),
+        "The extension must be loaded through shared_preload_libraries."
+    );
+    unreachable!()
+}
+
+/// A token bucket rate limiter, shared by every backend
+///
+/// The bucket holds up to `burst` tokens, and is refilled at `per_second` tokens a second.  Each
+/// call takes a token, so after a quiet period up to `burst` calls can be made at once, and on
+/// average no more than `per_second` a second.
+pub struct RateLimiter {
+    name: &'static str,
+    per_second: f64,
+    burst: u32,
+    // the time the bucket would be full again, in microseconds:  the "theoretical arrival time"
+    // of the generic cell rate algorithm, an equivalent of a token bucket that needs only one
+    // atomic
+    full_at: AtomicPtr<AtomicI64>,
+}
+
+impl RateLimiter {
+    /// A rate limiter whose shared memory is named after `name`, which should be prefixed with the
+    /// extension's name
+    ///
+    /// # Panics
+    ///
+    /// If `per_second` isn't greater than zero, as the bucket would never be refilled
+    pub const fn new(name: &'static str, per_second: f64, burst: u32) -> Self {
+        assert!(per_second > 0.0, "a rate limiter's per_second must be greater than zero");
+        RateLimiter { name, per_second, burst, full_at: AtomicPtr::new(std::ptr::null_mut()) }
+    }
+
+    pub fn name(&self) -> &'static str {
+        self.name
+    }
+
+    fn full_at(&self) -> &AtomicI64 {
+        unsafe { self.full_at.load(Ordering::Acquire).as_ref() }
+            .unwrap_or_else(|| not_attached("rate limiter", self.name))
+    }
+
+    // the microseconds it takes to refill one token
+    fn interval(&self) -> i64 {
+        (1_000_000.0 / self.per_second).ceil() as i64
+    }
+
+    /// Take `tokens` from the bucket, if it holds that many, or else how long until it will
+    fn take(&self, tokens: u32) -> Result<(), Duration> {
+        let interval = self.interval();
+        let capacity = interval * self.burst as i64;
+        let cost = interval * tokens as i64;
+        let full_at = self.full_at();
+        let mut current = full_at.load(Ordering::Relaxed);
+        loop {
+            let now = now();
+            let next = current.max(now) + cost;
+            // the bucket can't owe more than its capacity
+            let excess = next - now - capacity;
+            if excess > 0 {
+                return Err(Duration::from_micros(excess as u64));
+            }
+            match full_at.compare_exchange_weak(current, next, Ordering::Relaxed, Ordering::Relaxed)
+            {
+                Ok(_) => return Ok(()),
+                Err(actual) => current = actual,
+            }
+        }
+    }
+
+    /// Take a token, returning false if there are none
+    pub fn try_acquire(&self) -> bool {
+        self.try_acquire_n(1)
+    }
+
+    /// Take `tokens` tokens, returning false if there aren't that many.  Asking for more than
+    /// `burst` tokens always fails
+    pub fn try_acquire_n(&self, tokens: u32) -> bool {
+        self.take(tokens).is_ok()
+    }
+
+    /// Take a token, sleeping until there is one.  The wait can be cancelled like any query
+    pub fn acquire(&self) {
+        self.acquire_n(1)
+    }
+
+    /// Take `tokens` tokens, sleeping until there are that many
+    ///
+    /// # Panics
+    ///
+    /// If `tokens` is more than `burst`, as there will never be that many
+    pub fn acquire_n(&self, tokens: u32) {
+        assert!(tokens <= self.burst, "cannot acquire more tokens than the burst size");
+        while let Err(wait) = self.take(tokens) {
+            // wake up at least once a second, to notice cancellation promptly
+            unsafe { pg_sys::pg_usleep(wait.min(Duration::from_secs(1)).as_micros() as _) };
+            check_for_interrupts!();
+        }
+    }
+
+    /// The number of whole tokens in the bucket
+    pub fn available(&self) -> u32 {
+        let interval = self.interval();
+        let owed = (self.full_at().load(Ordering::Relaxed) - now()).max(0);
+        self.burst.saturating_sub(((owed + interval - 1) / interval) as u32)
+    }
+
+    /// Fill the bucket
+    pub fn reset(&self) {
+        self.full_at().store(0, Ordering::Relaxed);
+    }
+}
+
+impl PgSharedMemoryInitialization for RateLimiter {
+    fn pg_init(&'static self) {
+        unsafe {
+            pg_sys::RequestAddinShmemSpace(std::mem::size_of::<AtomicI64>());
+        }
+    }
+
+    fn shmem_init(&'static self) {
+        self.full_at.store(unsafe { attach("rate limiter", self.name) }, Ordering::Release);
+    }
+}
+
+/// The state of a [`CircuitBreaker`]
+#[derive(Debug, Copy, Clone, Eq, PartialEq)]
+pub enum CircuitState {
+    /// Calls are allowed
+    Closed,
+    /// Calls have failed too often, and are refused until the cooldown has passed
+    Open,
+    /// The cooldown has passed, and a single trial call has been allowed through
+    HalfOpen,
+}
+
+impl CircuitState {
+    fn from_bits(bits: u64) -> Self {
+        match bits >> 32 {
+            0 => CircuitState::Closed,
+            1 => CircuitState::Open,
+            _ => CircuitState::HalfOpen,
+        }
+    }
+
+    fn to_bits(self, failures: u32) -> u64 {
+        let state = match self {
+            CircuitState::Closed => 0u64,
+            CircuitState::Open => 1,
+            CircuitState::HalfOpen => 2,
+        };
+        state << 32 | failures as u64
+    }
+}
+
+#[repr(C)]
+struct BreakerState {
+    // the state, in the upper half, and the number of failures in a row, in the lower
+    state: AtomicU64,
+    // when the circuit last opened, or the trial call was let through, in microseconds
+    changed_at: AtomicI64,
+}
+
+/// The error of a call made through [`CircuitBreaker::call()`]
+#[derive(Debug, Clone, Eq, PartialEq)]
+pub enum CircuitBreakerError<E> {
+    /// The circuit is open, so the call wasn't made
+    Open,
+    /// The call was made, and failed
+    Failed(E),
+}
+
+impl<E: std::fmt::Display> std::fmt::Display for CircuitBreakerError<E> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            CircuitBreakerError::Open => write!(f, "the circuit breaker is open"),
+            CircuitBreakerError::Failed(e) => e.fmt(f),
+        }
+    }
+}
+
+impl<E: std::fmt::Debug + std::fmt::Display> std::error::Error for CircuitBreakerError<E> {}
+
+/// A circuit breaker, shared by every backend
+///
+/// The circuit starts closed, letting calls through.  After `failure_threshold` failures in a
+/// row it opens, and refuses calls for `cooldown`.  Then it lets one trial call through:  if that
+/// succeeds the circuit closes again, and if it fails it stays open for another `cooldown`.  A
+/// trial call that never reports back, because its transaction errored, say, is given up on
+/// after `cooldown` too.
+pub struct CircuitBreaker {
+    name: &'static str,
+    failure_threshold: u32,
+    cooldown: Duration,
+    state: AtomicPtr<BreakerState>,
+}
+
+impl CircuitBreaker {
+    /// A circuit breaker whose shared memory is named after `name`, which should be prefixed with
+    /// the extension's name
+    pub const fn new(name: &'static str, failure_threshold: u32, cooldown: Duration) -> Self {
+        CircuitBreaker {
+            name,
+            failure_threshold,
+            cooldown,
+            state: AtomicPtr::new(std::ptr::null_mut()),
+        }
+    }
+
+    pub fn name(&self) -> &'static str {
+        self.name
+    }
+
+    fn shared(&self) -> &BreakerState {
+        unsafe { self.state.load(Ordering::Acquire).as_ref() }
+            .unwrap_or_else(|| not_attached("circuit breaker", self.name))
+    }
+
+    fn cooled_down(&self, shared: &BreakerState) -> bool {
+        now() - shared.changed_at.load(Ordering::Relaxed) >= self.cooldown.as_micros() as i64
+    }
+
+    /// The circuit's state
+    pub fn state(&self) -> CircuitState {
+        CircuitState::from_bits(self.shared().state.load(Ordering::Relaxed))
+    }
+
+    /// The number of failures in a row since the circuit last closed
+    pub fn failures(&self) -> u32 {
+        self.shared().state.load(Ordering::Relaxed) as u32
+    }
+
+    /// May a call be made now?  If this returns true, the call's outcome must be reported with
+    /// [`record_success()`](Self::record_success) or [`record_failure()`](Self::record_failure)
+    pub fn allow(&self) -> bool {
+        let shared = self.shared();
+        let current = shared.state.load(Ordering::Relaxed);
+        match CircuitState::from_bits(current) {
+            CircuitState::Closed => true,
+            CircuitState::Open | CircuitState::HalfOpen if self.cooled_down(shared) => {
+                // only one backend gets to make the trial call
+                let trial = CircuitState::HalfOpen.to_bits(current as u32);
+                let won = shared
+                    .state
+                    .compare_exchange(current, trial, Ordering::Relaxed, Ordering::Relaxed)
+                    .is_ok();
+                if won {
+                    shared.changed_at.store(now(), Ordering::Relaxed);
+                }
+                won
+            }
+            CircuitState::Open | CircuitState::HalfOpen => false,
+        }
+    }
+
+    /// Report that a call succeeded, closing the circuit
+    pub fn record_success(&self) {
+        self.shared().state.store(CircuitState::Closed.to_bits(0), Ordering::Relaxed);
+    }
+
+    /// Report that a call failed, opening the circuit if it's failed too often
+    pub fn record_failure(&self) {
+        let shared = self.shared();
+        let previous = shared
+            .state
+            .fetch_update(Ordering::Relaxed, Ordering::Relaxed, |current| {
+                let failures = (current as u32).saturating_add(1);
+                match CircuitState::from_bits(current) {
+                    CircuitState::Closed if failures < self.failure_threshold => {
+                        Some(CircuitState::Closed.to_bits(failures))
+                    }
+                    _ => Some(CircuitState::Open.to_bits(failures)),
+                }
+            })
+            .unwrap();
+        let failures = (previous as u32).saturating_add(1);
+        let opened = CircuitState::from_bits(previous) != CircuitState::Closed
+            || failures >= self.failure_threshold;
+        if opened {
+            shared.changed_at.store(now(), Ordering::Relaxed);
+        }
+    }
+
+    /// Close the circuit, forgetting any failures
+    pub fn reset(&self) {
+        self.record_success();
+    }
+
+    /// Make a call through the circuit breaker, if it's allowed, and record its outcome
+    pub fn call<T, E>(
+        &self,
+        f: impl FnOnce() -> Result<T, E>,
+    ) -> Result<T, CircuitBreakerError<E>> {
+        if !self.allow() {
+            return Err(CircuitBreakerError::Open);
+        }
+        match f() {
+            Ok(value) => {
+                self.record_success();
+                Ok(value)
+            }
+            Err(e) => {
+                self.record_failure();
+                Err(CircuitBreakerError::Failed(e))
+            }
+        }
+    }
+}
+
+impl PgSharedMemoryInitialization for CircuitBreaker {
+    fn pg_init(&'static self) {
+        unsafe {
+            pg_sys::RequestAddinShmemSpace(std::mem::size_of::<BreakerState>());
+        }
+    }
+
+    fn shmem_init(&'static self) {
+        self.state.store(unsafe { attach("circuit breaker", self.name) }, Ordering::Release);
+    }
+}
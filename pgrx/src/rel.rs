@@ -8,6 +8,8 @@ Use of this source code is governed by the MIT license that can be found in the
 */
 
 //! Provides a safe wrapper around Postgres' `pg_sys::RelationData` struct
+use crate::pg_sys::AsPgCStr;
+use crate::spi::{self, quote_identifier, Spi};
 use crate::{
     direct_function_call, name_data_to_str, pg_sys, FromDatum, IntoDatum, PgBox, PgTupleDesc,
 };
@@ -285,6 +287,88 @@ impl PgRelation {
         rd_rel.relkind == pg_sys::RELKIND_TOASTVALUE as c_char
     }
 
+    /// Is this a temporary relation?
+    pub fn is_temp(&self) -> bool {
+        let rd_rel: &pg_sys::FormData_pg_class =
+            unsafe { self.boxed.rd_rel.as_ref().expect("rd_rel is NULL") };
+        rd_rel.relpersistence == pg_sys::RELPERSISTENCE_TEMP as c_char
+    }
+
+    /// Is this an unlogged relation?
+    pub fn is_unlogged(&self) -> bool {
+        let rd_rel: &pg_sys::FormData_pg_class =
+            unsafe { self.boxed.rd_rel.as_ref().expect("rd_rel is NULL") };
+        rd_rel.relpersistence == pg_sys::RELPERSISTENCE_UNLOGGED as c_char
+    }
+
+    /// Create a temporary table named `name` with the specified columns, which is dropped at the
+    /// end of the session, and open it with an `AccessShareLock`
+    ///
+    /// ```rust,no_run
+    /// use pgrx::rel::ColumnSpec;
+    /// use pgrx::PgRelation;
+    ///
+    /// let scratch = PgRelation::create_temp(
+    ///     "scratch",
+    ///     &[ColumnSpec::new::<i64>("id").not_null(), ColumnSpec::new::<String>("label")],
+    /// )
+    /// .unwrap();
+    /// assert!(scratch.is_temp());
+    /// ```
+    pub fn create_temp(name: &str, columns: &[ColumnSpec]) -> spi::Result<Self> {
+        Self::create_scratch(name, columns, pg_sys::RELPERSISTENCE_TEMP, OnCommit::PreserveRows)
+    }
+
+    /// Like [`PgRelation::create_temp()`], but the table's rows are deleted, or the table is
+    /// dropped, at the end of the current transaction according to `on_commit`
+    pub fn create_temp_on_commit(
+        name: &str,
+        columns: &[ColumnSpec],
+        on_commit: OnCommit,
+    ) -> spi::Result<Self> {
+        Self::create_scratch(name, columns, pg_sys::RELPERSISTENCE_TEMP, on_commit)
+    }
+
+    /// Create an unlogged table named `name` with the specified columns, in the first schema of
+    /// the `search_path`, and open it with an `AccessShareLock`.  Unlike a temporary table it is
+    /// visible to other sessions and survives the end of this one, but is emptied after a crash
+    pub fn create_unlogged(name: &str, columns: &[ColumnSpec]) -> spi::Result<Self> {
+        Self::create_scratch(name, columns, pg_sys::RELPERSISTENCE_UNLOGGED, OnCommit::PreserveRows)
+    }
+
+    fn create_scratch(
+        name: &str,
+        columns: &[ColumnSpec],
+        relpersistence: u8,
+        on_commit: OnCommit,
+    ) -> spi::Result<Self> {
+        // find the schema Postgres would create the table in, so we can look it up there after
+        let nspoid = unsafe {
+            let rv = pg_sys::makeRangeVar(std::ptr::null_mut(), name.as_pg_cstr(), -1);
+            (*rv).relpersistence = relpersistence as c_char;
+            pg_sys::RangeVarGetCreationNamespace(rv)
+        };
+        let nspname = unsafe { core::ffi::CStr::from_ptr(pg_sys::get_namespace_name(nspoid)) }
+            .to_str()
+            .expect("unable to convert namespace name to UTF8");
+
+        let columns = columns.iter().map(ColumnSpec::to_sql).collect::<Vec<_>>();
+        let persistence =
+            if relpersistence == pg_sys::RELPERSISTENCE_TEMP { "TEMPORARY" } else { "UNLOGGED" };
+        Spi::run(&format!(
+            "CREATE {persistence} TABLE {}.{} ({}){}",
+            quote_identifier(nspname),
+            quote_identifier(name),
+            columns.join(", "),
+            on_commit.to_sql()
+        ))?;
+
+        unsafe {
+            let oid = pg_sys::get_relname_relid(name.as_pg_cstr(), nspoid);
+            Ok(PgRelation::with_lock(oid, pg_sys::AccessShareLock as pg_sys::LOCKMODE))
+        }
+    }
+
     /// ensures that the returned `PgRelation` is closed by Rust when it is dropped
     pub fn to_owned(mut self) -> Self {
         self.need_close = true;
@@ -292,6 +376,73 @@ impl PgRelation {
     }
 }
 
+/// A column of a table created by [`PgRelation::create_temp()`] or
+/// [`PgRelation::create_unlogged()`], or of a tuple descriptor built by a
+/// [`TupleDescBuilder`](crate::tupdesc::TupleDescBuilder)
+#[derive(Debug, Clone, PartialEq)]
+pub struct ColumnSpec {
+    pub(crate) name: String,
+    pub(crate) type_oid: pg_sys::Oid,
+    pub(crate) typmod: i32,
+    pub(crate) not_null: bool,
+}
+
+impl ColumnSpec {
+    /// A column named `name` of the SQL type Rust type `T` maps to
+    pub fn new<T: IntoDatum>(name: &str) -> Self {
+        Self::with_type_oid(name, T::type_oid())
+    }
+
+    /// A column named `name` of the type with the specified oid
+    pub fn with_type_oid(name: &str, type_oid: pg_sys::Oid) -> Self {
+        ColumnSpec { name: name.to_string(), type_oid, typmod: -1, not_null: false }
+    }
+
+    /// Set the column's type modifier, such as the length of a `varchar(n)`, as stored in
+    /// `pg_attribute.atttypmod`
+    pub fn typmod(mut self, typmod: i32) -> Self {
+        self.typmod = typmod;
+        self
+    }
+
+    /// Make the column `NOT NULL`
+    pub fn not_null(mut self) -> Self {
+        self.not_null = true;
+        self
+    }
+
+    fn to_sql(&self) -> String {
+        let typname = unsafe {
+            core::ffi::CStr::from_ptr(pg_sys::format_type_with_typemod(self.type_oid, self.typmod))
+        }
+        .to_str()
+        .expect("unable to convert type name to UTF8");
+        let not_null = if self.not_null { " NOT NULL" } else { "" };
+        format!("{} {typname}{not_null}", quote_identifier(&self.name))
+    }
+}
+
+/// What happens to a temporary table at the end of each transaction
+#[derive(Debug, Copy, Clone, Eq, PartialEq)]
+pub enum OnCommit {
+    /// Nothing:  the table and its rows stay until the end of the session
+    PreserveRows,
+    /// The table is truncated
+    DeleteRows,
+    /// The table is dropped
+    Drop,
+}
+
+impl OnCommit {
+    fn to_sql(&self) -> &'static str {
+        match self {
+            OnCommit::PreserveRows => "",
+            OnCommit::DeleteRows => " ON COMMIT DELETE ROWS",
+            OnCommit::Drop => " ON COMMIT DROP",
+        }
+    }
+}
+
 impl Clone for PgRelation {
     /// Same as calling `PgRelation::with_lock(AccessShareLock)` on the underlying relation id
     fn clone(&self) -> Self {
@@ -0,0 +1,327 @@
+/*
+Portions Copyright 2019-2021 ZomboDB, LLC.
+Portions Copyright 2021-2022 Technology Concepts & Design, Inc. <support@tcdi.com>
+
+All rights reserved.
+
+Use of this source code is governed by the MIT license that can be found in the LICENSE file.
+*/
+
+//! Block-level access to a relation's forks, through the buffer manager
+//!
+//! Each relation's storage is split into forks:  its main data, its free space map, its
+//! visibility map, and, for unlogged relations, the "init" fork it's reset to after a crash.  The
+//! set of forks is fixed by Postgres, so an extension can't add one of its own.  An extension that
+//! keeps an auxiliary structure, like a filter or a summary, on disk stores it in the forks of a
+//! relation whose pages nothing else interprets, such as an index of an access method the extension
+//! implements.  [`RelationFork`] reads and writes the pages of those forks.
+//!
+//! Pages are read and written through shared buffers, so they're cached and locked the same way
+//! Postgres' own are.  Changes are WAL-logged with generic WAL records, which compare the page
+//! before and after the change.  Like Postgres' own, a page should have the standard layout set up
+//! by [`BlockWrite::init()`]:  the contents of the unused "hole" between its line pointers and its
+//! tuples aren't logged, and are lost if the page is restored after a crash.  An extension's own data
+//! belongs in the page's special space, at its end, or in tuples added with `PageAddItem()`.
+//!
+//! ## Examples
+//!
+//! ```rust,no_run
+//! use pgrx::prelude::*;
+//! use pgrx::relfork::{Fork, RelationFork};
+//! use pgrx::PgRelation;
+//!
+//! let relation = PgRelation::open_with_name_and_share_lock("my_index").unwrap();
+//! let fork = RelationFork::new(&relation, Fork::Main);
+//!
+//! // append a page with 16 bytes of special space, and fill it in
+//! let mut block = fork.extend();
+//! block.init(16);
+//! block.special_mut().copy_from_slice(&[42; 16]);
+//! let blkno = block.finish();
+//!
+//! assert_eq!(fork.read(blkno).special(), &[42; 16]);
+//! ```
+use crate::{pg_sys, PgRelation};
+
+/// One of a relation's forks
+#[derive(Debug, Copy, Clone, Eq, PartialEq)]
+pub enum Fork {
+    /// The relation's data
+    Main,
+    /// The free space map, maintained by Postgres for heap and some index relations
+    FreeSpaceMap,
+    /// The visibility map, maintained by Postgres for heap relations
+    VisibilityMap,
+    /// The contents an unlogged relation is reset to after a crash
+    Init,
+}
+
+impl Fork {
+    pub fn as_pg(&self) -> pg_sys::ForkNumber {
+        match self {
+            Fork::Main => pg_sys::ForkNumber_MAIN_FORKNUM,
+            Fork::FreeSpaceMap => pg_sys::ForkNumber_FSM_FORKNUM,
+            Fork::VisibilityMap => pg_sys::ForkNumber_VISIBILITYMAP_FORKNUM,
+            Fork::Init => pg_sys::ForkNumber_INIT_FORKNUM,
+        }
+    }
+}
+
+/// A fork of an open relation, whose blocks are read and written through the buffer manager
+pub struct RelationFork<'a> {
+    relation: &'a PgRelation,
+    fork: Fork,
+}
+
+impl<'a> RelationFork<'a> {
+    pub fn new(relation: &'a PgRelation, fork: Fork) -> Self {
+        RelationFork { relation, fork }
+    }
+
+    pub fn fork(&self) -> Fork {
+        self.fork
+    }
+
+    /// Does the fork's storage exist?  A relation's main fork always does, but the others are only
+    /// created when they're needed
+    pub fn exists(&self) -> bool {
+        unsafe {
+            // SAFETY:  a PgRelation is an open relation
+            let smgr = pg_sys::smgropen(self.relation.rd_node, self.relation.rd_backend);
+            pg_sys::smgrexists(smgr, self.fork.as_pg())
+        }
+    }
+
+    /// Create the fork's storage, empty, if it doesn't exist already.  Its creation is WAL-logged
+    /// if the relation is
+    pub fn create(&self) {
+        if self.exists() {
+            return;
+        }
+        unsafe {
+            // SAFETY:  a PgRelation is an open relation
+            let smgr = pg_sys::smgropen(self.relation.rd_node, self.relation.rd_backend);
+            pg_sys::smgrcreate(smgr, self.fork.as_pg(), false);
+            if self.needs_wal() {
+                pg_sys::log_smgrcreate(&self.relation.rd_node, self.fork.as_pg());
+            }
+        }
+    }
+
+    /// The number of blocks in the fork
+    pub fn blocks(&self) -> pg_sys::BlockNumber {
+        unsafe {
+            // SAFETY:  a PgRelation is an open relation
+            pg_sys::RelationGetNumberOfBlocksInFork(self.relation.as_ptr(), self.fork.as_pg())
+        }
+    }
+
+    /// Read block `blkno`, and hold a share lock on it until the [`BlockRead`] is dropped
+    ///
+    /// # Panics
+    ///
+    /// Raises a Postgres ERROR if `blkno` is past the end of the fork
+    pub fn read(&self, blkno: pg_sys::BlockNumber) -> BlockRead {
+        let buffer = self.read_buffer(blkno);
+        unsafe {
+            // SAFETY:  we just pinned the buffer
+            pg_sys::LockBuffer(buffer, pg_sys::BUFFER_LOCK_SHARE as _);
+        }
+        BlockRead { buffer }
+    }
+
+    /// Read block `blkno`, and hold an exclusive lock on it so it can be changed.  The changes are
+    /// made, and WAL-logged, by [`BlockWrite::finish()`]
+    ///
+    /// # Panics
+    ///
+    /// Raises a Postgres ERROR if `blkno` is past the end of the fork
+    pub fn write(&self, blkno: pg_sys::BlockNumber) -> BlockWrite {
+        let buffer = self.read_buffer(blkno);
+        unsafe {
+            // SAFETY:  we just pinned the buffer
+            pg_sys::LockBuffer(buffer, pg_sys::BUFFER_LOCK_EXCLUSIVE as _);
+            BlockWrite::start(self.relation, buffer, 0)
+        }
+    }
+
+    /// Add a new, zeroed, block to the end of the fork, and hold an exclusive lock on it so it can
+    /// be filled in.  The fork's storage is created first, if it doesn't exist.
+    ///
+    /// The block is added whether or not its [`BlockWrite`] is finished, but its contents are only
+    /// WAL-logged if it is.
+    pub fn extend(&self) -> BlockWrite {
+        self.create();
+        unsafe {
+            // SAFETY:  a PgRelation is an open relation, and holding its extension lock keeps
+            // other backends from adding the same block
+            pg_sys::LockRelationForExtension(self.relation.as_ptr(), pg_sys::ExclusiveLock as _);
+            let buffer = pg_sys::ReadBufferExtended(
+                self.relation.as_ptr(),
+                self.fork.as_pg(),
+                pg_sys::InvalidBlockNumber, // P_NEW
+                pg_sys::ReadBufferMode_RBM_NORMAL,
+                std::ptr::null_mut(),
+            );
+            pg_sys::UnlockRelationForExtension(self.relation.as_ptr(), pg_sys::ExclusiveLock as _);
+            pg_sys::LockBuffer(buffer, pg_sys::BUFFER_LOCK_EXCLUSIVE as _);
+            // the page is all zeros, so log all of it
+            BlockWrite::start(self.relation, buffer, pg_sys::GENERIC_XLOG_FULL_IMAGE as _)
+        }
+    }
+
+    fn read_buffer(&self, blkno: pg_sys::BlockNumber) -> pg_sys::Buffer {
+        unsafe {
+            // SAFETY:  a PgRelation is an open relation, and ReadBufferExtended() raises an ERROR
+            // if `blkno` doesn't exist
+            pg_sys::ReadBufferExtended(
+                self.relation.as_ptr(),
+                self.fork.as_pg(),
+                blkno,
+                pg_sys::ReadBufferMode_RBM_NORMAL,
+                std::ptr::null_mut(),
+            )
+        }
+    }
+
+    fn needs_wal(&self) -> bool {
+        unsafe {
+            // SAFETY:  a PgRelation is an open relation, with a pg_class entry
+            (*self.relation.rd_rel).relpersistence as u8 == pg_sys::RELPERSISTENCE_PERMANENT
+        }
+    }
+}
+
+fn page_bytes<'a>(page: pg_sys::Page) -> &'a [u8] {
+    unsafe {
+        // SAFETY:  a page is BLCKSZ bytes
+        std::slice::from_raw_parts(page as *const u8, pg_sys::BLCKSZ as usize)
+    }
+}
+
+fn special_range(page: &[u8]) -> std::ops::Range<usize> {
+    // the header's `pd_special` is the offset of the special space, which runs to the end of the
+    // page.  A page that was never initialized has none
+    let header = page.as_ptr() as *const pg_sys::PageHeaderData;
+    let special = unsafe { (*header).pd_special as usize };
+    special.min(page.len())..page.len()
+}
+
+/// A block of a [`RelationFork`] that's share-locked, so it can be read.  The lock and the
+/// buffer's pin are released when it's dropped
+pub struct BlockRead {
+    buffer: pg_sys::Buffer,
+}
+
+impl BlockRead {
+    pub fn block_number(&self) -> pg_sys::BlockNumber {
+        unsafe { pg_sys::BufferGetBlockNumber(self.buffer) }
+    }
+
+    /// The whole page, including its header
+    pub fn page(&self) -> &[u8] {
+        unsafe {
+            // SAFETY:  we hold a pin and a lock on the buffer
+            page_bytes(pg_sys::BufferGetPage(self.buffer))
+        }
+    }
+
+    /// The page's special space, which is empty if the page was never initialized
+    pub fn special(&self) -> &[u8] {
+        let page = self.page();
+        &page[special_range(page)]
+    }
+}
+
+impl Drop for BlockRead {
+    fn drop(&mut self) {
+        unsafe {
+            // SAFETY:  we hold a pin and a lock on the buffer
+            pg_sys::UnlockReleaseBuffer(self.buffer);
+        }
+    }
+}
+
+/// A block of a [`RelationFork`] that's exclusively locked, so it can be changed.
+///
+/// Changes are made to a copy of the page, which [`BlockWrite::finish()`] writes back to the
+/// buffer and WAL-logs.  If it's dropped without being finished, the changes are thrown away.
+pub struct BlockWrite {
+    buffer: pg_sys::Buffer,
+    state: *mut pg_sys::GenericXLogState,
+    page: pg_sys::Page,
+}
+
+impl BlockWrite {
+    /// # Safety
+    ///
+    /// `buffer` must be pinned and exclusively locked, and belong to `relation`
+    unsafe fn start(relation: &PgRelation, buffer: pg_sys::Buffer, flags: i32) -> Self {
+        let state = pg_sys::GenericXLogStart(relation.as_ptr());
+        let page = pg_sys::GenericXLogRegisterBuffer(state, buffer, flags);
+        BlockWrite { buffer, state, page }
+    }
+
+    pub fn block_number(&self) -> pg_sys::BlockNumber {
+        unsafe { pg_sys::BufferGetBlockNumber(self.buffer) }
+    }
+
+    /// Set the page up with the standard layout, empty, with `special_size` bytes of special space
+    /// at its end.  The page's existing contents are lost
+    pub fn init(&mut self, special_size: usize) {
+        unsafe {
+            // SAFETY:  `self.page` is our BLCKSZ copy of the page, and PageInit() raises an ERROR
+            // if `special_size` doesn't fit
+            pg_sys::PageInit(self.page, pg_sys::BLCKSZ as _, special_size);
+        }
+    }
+
+    /// The whole page, including its header
+    pub fn page(&self) -> &[u8] {
+        page_bytes(self.page)
+    }
+
+    /// The whole page, including its header
+    pub fn page_mut(&mut self) -> &mut [u8] {
+        unsafe {
+            // SAFETY:  a page is BLCKSZ bytes, and `self.page` is our own copy of it
+            std::slice::from_raw_parts_mut(self.page as *mut u8, pg_sys::BLCKSZ as usize)
+        }
+    }
+
+    /// The page's special space, which is empty if the page hasn't been initialized
+    pub fn special(&self) -> &[u8] {
+        let page = self.page();
+        &page[special_range(page)]
+    }
+
+    /// The page's special space, which is empty if the page hasn't been initialized
+    pub fn special_mut(&mut self) -> &mut [u8] {
+        let range = special_range(self.page());
+        &mut self.page_mut()[range]
+    }
+
+    /// Write the changes to the buffer, and WAL-log them, if the relation is logged.  Returns the
+    /// block's number
+    pub fn finish(self) -> pg_sys::BlockNumber {
+        let blkno = self.block_number();
+        let this = std::mem::ManuallyDrop::new(self);
+        unsafe {
+            // SAFETY:  `this.state` was started by us, and is only finished once, since `this`
+            // won't be dropped
+            pg_sys::GenericXLogFinish(this.state);
+            pg_sys::UnlockReleaseBuffer(this.buffer);
+        }
+        blkno
+    }
+}
+
+impl Drop for BlockWrite {
+    fn drop(&mut self) {
+        unsafe {
+            // SAFETY:  we hold a pin and a lock on the buffer, and `self.state` wasn't finished
+            pg_sys::GenericXLogAbort(self.state);
+            pg_sys::UnlockReleaseBuffer(self.buffer);
+        }
+    }
+}
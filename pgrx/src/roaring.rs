@@ -0,0 +1,740 @@
+/*
+Portions Copyright 2019-2021 ZomboDB, LLC.
+Portions Copyright 2021-2022 Technology Concepts & Design, Inc. <support@tcdi.com>
+
+All rights reserved.
+
+Use of this source code is governed by the MIT license that can be found in the LICENSE file.
+*/
+
+//! A compressed set of `u32`s, as a Roaring bitmap
+//!
+//! A [`RoaringBitmap`] splits its values into chunks of 65536, by their upper 16 bits, and stores
+//! each chunk as a sorted array of the lower 16 bits while it's sparse, or as a 65536-bit bitmap
+//! once it's dense.  Sets of row ids, tag ids, and the like stay small, and the set operations
+//! between them stay fast.
+//!
+//! It's stored as a `bytea` in the Roaring "portable" serialization format, so bitmaps written by
+//! other Roaring implementations can be read, and vice versa.
+//!
+//! ## Examples
+//!
+//! An extension can expose the set operations as functions and operators, and an aggregate that
+//! unions the bitmaps of a group:
+//!
+//! ```rust,no_run
+//! use pgrx::prelude::*;
+//! use pgrx::roaring::RoaringBitmap;
+//! use pgrx::{Aggregate, Internal};
+//!
+//! #[pg_extern]
+//! fn rb_build(values: Array<i32>) -> RoaringBitmap {
+//!     values.iter().flatten().map(|value| value as u32).collect()
+//! }
+//!
+//! #[pg_operator(immutable, parallel_safe)]
+//! #[opname(&)]
+//! fn rb_and(left: RoaringBitmap, right: RoaringBitmap) -> RoaringBitmap {
+//!     &left & &right
+//! }
+//!
+//! #[pg_operator(immutable, parallel_safe)]
+//! #[opname(@>)]
+//! fn rb_contains(bitmap: RoaringBitmap, value: i32) -> bool {
+//!     bitmap.contains(value as u32)
+//! }
+//!
+//! pub struct RbOrAgg;
+//!
+//! #[pg_aggregate]
+//! impl Aggregate for RbOrAgg {
+//!     const NAME: &'static str = "rb_or_agg";
+//!     type Args = RoaringBitmap;
+//!     type State = Internal;
+//!     type Finalize = RoaringBitmap;
+//!
+//!     fn state(
+//!         mut current: Self::State,
+//!         arg: Self::Args,
+//!         _fcinfo: pg_sys::FunctionCallInfo,
+//!     ) -> Self::State {
+//!         *unsafe { current.get_or_insert_default::<RoaringBitmap>() } |= &arg;
+//!         current
+//!     }
+//!
+//!     fn finalize(
+//!         mut current: Self::State,
+//!         _direct_args: Self::OrderedSetArgs,
+//!         _fcinfo: pg_sys::FunctionCallInfo,
+//!     ) -> Self::Finalize {
+//!         unsafe { current.get_or_insert_default::<RoaringBitmap>() }.clone()
+//!     }
+//! }
+//! ```
+use crate::{pg_sys, FromDatum, IntoDatum};
+use core::fmt;
+use core::ops::{BitAnd, BitAndAssign, BitOr, BitOrAssign, BitXor, BitXorAssign, Sub, SubAssign};
+use pgrx_sql_entity_graph::metadata::{
+    ArgumentError, Returns, ReturnsError, SqlMapping, SqlTranslatable,
+};
+use std::borrow::Cow;
+use std::cmp::Ordering;
+
+/// The most values a chunk holds as an array, rather than as a bitmap
+const ARRAY_MAX: usize = 4096;
+
+/// The number of words in a chunk's bitmap
+const BITMAP_WORDS: usize = 1024;
+
+/// The portable format's cookies, for bitmaps without and with run-length encoded chunks
+const SERIAL_COOKIE_NO_RUNCONTAINER: u32 = 12346;
+const SERIAL_COOKIE: u32 = 12347;
+
+/// Bitmaps with run-length encoded chunks only have an offset header with at least this many chunks
+const NO_OFFSET_THRESHOLD: usize = 4;
+
+/// Why serialized bytes couldn't be read back as a [`RoaringBitmap`]
+#[derive(thiserror::Error, Debug, Clone, Copy, PartialEq, Eq)]
+pub enum RoaringError {
+    #[error("the bytes are too short")]
+    Truncated,
+    #[error("unrecognized cookie {0}")]
+    UnknownCookie(u32),
+    #[error("the bytes aren't a valid roaring bitmap")]
+    Invalid,
+}
+
+/// A compressed set of `u32`s
+#[derive(Clone, Default, PartialEq, Eq)]
+pub struct RoaringBitmap {
+    /// The chunks, sorted by their keys, none of them empty
+    containers: Vec<(u16, Container)>,
+}
+
+/// The lower 16 bits of the values in one chunk: an array while there are at most [`ARRAY_MAX`],
+/// a bitmap otherwise, so that equal chunks are always stored the same way
+#[derive(Clone, PartialEq, Eq)]
+enum Container {
+    Array(Vec<u16>),
+    Bitmap(Box<[u64; BITMAP_WORDS]>, u32),
+}
+
+#[derive(Copy, Clone, PartialEq, Eq)]
+enum Op {
+    And,
+    Or,
+    Xor,
+    AndNot,
+}
+
+impl Op {
+    /// Does the result keep values only in the left, values in both, and values only in the right?
+    fn keeps(self) -> (bool, bool, bool) {
+        match self {
+            Op::And => (false, true, false),
+            Op::Or => (true, true, true),
+            Op::Xor => (true, false, true),
+            Op::AndNot => (true, false, false),
+        }
+    }
+
+    fn apply(self, left: u64, right: u64) -> u64 {
+        match self {
+            Op::And => left & right,
+            Op::Or => left | right,
+            Op::Xor => left ^ right,
+            Op::AndNot => left & !right,
+        }
+    }
+}
+
+/// Combine two sorted slices into a sorted `Vec`, keeping the values `op` does
+fn merge(left: &[u16], right: &[u16], op: Op) -> Vec<u16> {
+    let (keep_left, keep_both, keep_right) = op.keeps();
+    let mut merged = Vec::new();
+    let (mut i, mut j) = (0, 0);
+    while i < left.len() && j < right.len() {
+        match left[i].cmp(&right[j]) {
+            Ordering::Less => {
+                if keep_left {
+                    merged.push(left[i]);
+                }
+                i += 1;
+            }
+            Ordering::Greater => {
+                if keep_right {
+                    merged.push(right[j]);
+                }
+                j += 1;
+            }
+            Ordering::Equal => {
+                if keep_both {
+                    merged.push(left[i]);
+                }
+                i += 1;
+                j += 1;
+            }
+        }
+    }
+    if keep_left {
+        merged.extend_from_slice(&left[i..]);
+    }
+    if keep_right {
+        merged.extend_from_slice(&right[j..]);
+    }
+    merged
+}
+
+impl Container {
+    fn from_array(values: Vec<u16>) -> Option<Container> {
+        match values.len() {
+            0 => None,
+            len if len <= ARRAY_MAX => Some(Container::Array(values)),
+            _ => {
+                let mut bits = Box::new([0; BITMAP_WORDS]);
+                for value in values {
+                    bits[value as usize / 64] |= 1 << (value % 64);
+                }
+                Container::from_bits(bits)
+            }
+        }
+    }
+
+    fn from_bits(bits: Box<[u64; BITMAP_WORDS]>) -> Option<Container> {
+        let len = bits.iter().map(|word| word.count_ones()).sum::<u32>();
+        match len as usize {
+            0 => None,
+            len if len <= ARRAY_MAX => {
+                Some(Container::Array(ContainerIter::bitmap(&bits).collect()))
+            }
+            _ => Some(Container::Bitmap(bits, len)),
+        }
+    }
+
+    fn len(&self) -> u32 {
+        match self {
+            Container::Array(values) => values.len() as u32,
+            Container::Bitmap(_, len) => *len,
+        }
+    }
+
+    fn bits(&self) -> Cow<'_, [u64; BITMAP_WORDS]> {
+        match self {
+            Container::Array(values) => {
+                let mut bits = [0; BITMAP_WORDS];
+                for value in values {
+                    bits[*value as usize / 64] |= 1 << (value % 64);
+                }
+                Cow::Owned(bits)
+            }
+            Container::Bitmap(bits, _) => Cow::Borrowed(bits),
+        }
+    }
+
+    fn contains(&self, value: u16) -> bool {
+        match self {
+            Container::Array(values) => values.binary_search(&value).is_ok(),
+            Container::Bitmap(bits, _) => bits[value as usize / 64] & (1 << (value % 64)) != 0,
+        }
+    }
+
+    fn insert(&mut self, value: u16) -> bool {
+        match self {
+            Container::Array(values) => match values.binary_search(&value) {
+                Ok(_) => false,
+                Err(index) => {
+                    values.insert(index, value);
+                    if values.len() > ARRAY_MAX {
+                        *self = Container::from_array(std::mem::take(values)).unwrap();
+                    }
+                    true
+                }
+            },
+            Container::Bitmap(bits, len) => {
+                let (word, bit) = (&mut bits[value as usize / 64], 1 << (value % 64));
+                let inserted = *word & bit == 0;
+                *word |= bit;
+                *len += inserted as u32;
+                inserted
+            }
+        }
+    }
+
+    /// Remove `value`, which may leave the container empty
+    fn remove(&mut self, value: u16) -> bool {
+        match self {
+            Container::Array(values) => match values.binary_search(&value) {
+                Ok(index) => {
+                    values.remove(index);
+                    true
+                }
+                Err(_) => false,
+            },
+            Container::Bitmap(bits, len) => {
+                let (word, bit) = (&mut bits[value as usize / 64], 1 << (value % 64));
+                let removed = *word & bit != 0;
+                *word &= !bit;
+                *len -= removed as u32;
+                if *len as usize <= ARRAY_MAX {
+                    *self = Container::Array(ContainerIter::bitmap(bits).collect());
+                }
+                removed
+            }
+        }
+    }
+
+    fn iter(&self) -> ContainerIter<'_> {
+        match self {
+            Container::Array(values) => ContainerIter::Array(values.iter()),
+            Container::Bitmap(bits, _) => ContainerIter::bitmap(bits),
+        }
+    }
+
+    fn min(&self) -> u16 {
+        self.iter().next().unwrap()
+    }
+
+    fn max(&self) -> u16 {
+        match self {
+            Container::Array(values) => *values.last().unwrap(),
+            Container::Bitmap(bits, _) => {
+                let index = bits.iter().rposition(|word| *word != 0).unwrap();
+                (index * 64 + 63 - bits[index].leading_zeros() as usize) as u16
+            }
+        }
+    }
+
+    fn combine(&self, other: &Container, op: Op) -> Option<Container> {
+        match (self, other) {
+            (Container::Array(left), Container::Array(right)) => {
+                Container::from_array(merge(left, right, op))
+            }
+            (Container::Array(left), Container::Bitmap(..))
+                if op == Op::And || op == Op::AndNot =>
+            {
+                let keep = op == Op::And;
+                Container::from_array(
+                    left.iter().copied().filter(|value| other.contains(*value) == keep).collect(),
+                )
+            }
+            (Container::Bitmap(..), Container::Array(right)) if op == Op::And => {
+                Container::from_array(
+                    right.iter().copied().filter(|value| self.contains(*value)).collect(),
+                )
+            }
+            _ => {
+                let (left, right) = (self.bits(), other.bits());
+                let mut bits = Box::new([0; BITMAP_WORDS]);
+                for (word, (left, right)) in bits.iter_mut().zip(left.iter().zip(right.iter())) {
+                    *word = op.apply(*left, *right);
+                }
+                Container::from_bits(bits)
+            }
+        }
+    }
+
+    fn serialized_size(&self) -> usize {
+        match self {
+            Container::Array(values) => values.len() * 2,
+            Container::Bitmap(..) => BITMAP_WORDS * 8,
+        }
+    }
+}
+
+enum ContainerIter<'a> {
+    Array(std::slice::Iter<'a, u16>),
+    Bitmap { bits: &'a [u64; BITMAP_WORDS], index: usize, word: u64 },
+}
+
+impl<'a> ContainerIter<'a> {
+    fn bitmap(bits: &'a [u64; BITMAP_WORDS]) -> Self {
+        ContainerIter::Bitmap { bits, index: 0, word: bits[0] }
+    }
+}
+
+impl Iterator for ContainerIter<'_> {
+    type Item = u16;
+
+    fn next(&mut self) -> Option<u16> {
+        match self {
+            ContainerIter::Array(values) => values.next().copied(),
+            ContainerIter::Bitmap { bits, index, word } => loop {
+                if *word != 0 {
+                    let bit = word.trailing_zeros() as usize;
+                    *word &= *word - 1;
+                    return Some((*index * 64 + bit) as u16);
+                }
+                *index += 1;
+                *word = *bits.get(*index)?;
+            },
+        }
+    }
+}
+
+/// The key of the chunk `value` is in, and its lower bits within it
+fn split(value: u32) -> (u16, u16) {
+    ((value >> 16) as u16, value as u16)
+}
+
+impl RoaringBitmap {
+    /// An empty bitmap
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    fn container(&self, key: u16) -> Option<&Container> {
+        let index = self.containers.binary_search_by_key(&key, |(key, _)| *key).ok()?;
+        Some(&self.containers[index].1)
+    }
+
+    /// Add `value`, returning whether it wasn't already in the bitmap
+    pub fn insert(&mut self, value: u32) -> bool {
+        let (key, low) = split(value);
+        match self.containers.binary_search_by_key(&key, |(key, _)| *key) {
+            Ok(index) => self.containers[index].1.insert(low),
+            Err(index) => {
+                self.containers.insert(index, (key, Container::Array(vec![low])));
+                true
+            }
+        }
+    }
+
+    /// Remove `value`, returning whether it was in the bitmap
+    pub fn remove(&mut self, value: u32) -> bool {
+        let (key, low) = split(value);
+        let Ok(index) = self.containers.binary_search_by_key(&key, |(key, _)| *key) else {
+            return false;
+        };
+        let removed = self.containers[index].1.remove(low);
+        if self.containers[index].1.len() == 0 {
+            self.containers.remove(index);
+        }
+        removed
+    }
+
+    /// Is `value` in the bitmap?
+    pub fn contains(&self, value: u32) -> bool {
+        let (key, low) = split(value);
+        self.container(key).map_or(false, |container| container.contains(low))
+    }
+
+    /// The number of values in the bitmap
+    pub fn len(&self) -> u64 {
+        self.containers.iter().map(|(_, container)| container.len() as u64).sum()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.containers.is_empty()
+    }
+
+    /// The smallest value in the bitmap, if it isn't empty
+    pub fn min(&self) -> Option<u32> {
+        let (key, container) = self.containers.first()?;
+        Some((*key as u32) << 16 | container.min() as u32)
+    }
+
+    /// The largest value in the bitmap, if it isn't empty
+    pub fn max(&self) -> Option<u32> {
+        let (key, container) = self.containers.last()?;
+        Some((*key as u32) << 16 | container.max() as u32)
+    }
+
+    /// The values in the bitmap, in ascending order
+    pub fn iter(&self) -> Iter<'_> {
+        Iter { containers: self.containers.iter(), current: None }
+    }
+
+    /// Is every value in this bitmap also in `other`?
+    pub fn is_subset(&self, other: &RoaringBitmap) -> bool {
+        self.combine(other, Op::AndNot).is_empty()
+    }
+
+    /// Is any value in this bitmap also in `other`?
+    pub fn intersects(&self, other: &RoaringBitmap) -> bool {
+        !self.combine(other, Op::And).is_empty()
+    }
+
+    fn combine(&self, other: &RoaringBitmap, op: Op) -> RoaringBitmap {
+        let (keep_left, _, keep_right) = op.keeps();
+        let mut containers = Vec::new();
+        let (left, right) = (&self.containers, &other.containers);
+        let (mut i, mut j) = (0, 0);
+        while i < left.len() && j < right.len() {
+            match left[i].0.cmp(&right[j].0) {
+                Ordering::Less => {
+                    if keep_left {
+                        containers.push(left[i].clone());
+                    }
+                    i += 1;
+                }
+                Ordering::Greater => {
+                    if keep_right {
+                        containers.push(right[j].clone());
+                    }
+                    j += 1;
+                }
+                Ordering::Equal => {
+                    if let Some(container) = left[i].1.combine(&right[j].1, op) {
+                        containers.push((left[i].0, container));
+                    }
+                    i += 1;
+                    j += 1;
+                }
+            }
+        }
+        if keep_left {
+            containers.extend_from_slice(&left[i..]);
+        }
+        if keep_right {
+            containers.extend_from_slice(&right[j..]);
+        }
+        RoaringBitmap { containers }
+    }
+
+    /// The number of bytes [`RoaringBitmap::to_bytes()`] will return
+    pub fn serialized_size(&self) -> usize {
+        let headers = 8 + self.containers.len() * 8;
+        headers
+            + self
+                .containers
+                .iter()
+                .map(|(_, container)| container.serialized_size())
+                .sum::<usize>()
+    }
+
+    /// Serialize the bitmap in the Roaring portable format
+    pub fn to_bytes(&self) -> Vec<u8> {
+        let mut bytes = Vec::with_capacity(self.serialized_size());
+        bytes.extend(SERIAL_COOKIE_NO_RUNCONTAINER.to_le_bytes());
+        bytes.extend((self.containers.len() as u32).to_le_bytes());
+        for (key, container) in &self.containers {
+            bytes.extend(key.to_le_bytes());
+            bytes.extend(((container.len() - 1) as u16).to_le_bytes());
+        }
+        let mut offset = 8 + self.containers.len() * 8;
+        for (_, container) in &self.containers {
+            bytes.extend((offset as u32).to_le_bytes());
+            offset += container.serialized_size();
+        }
+        for (_, container) in &self.containers {
+            match container {
+                Container::Array(values) => {
+                    values.iter().for_each(|v| bytes.extend(v.to_le_bytes()))
+                }
+                Container::Bitmap(bits, _) => {
+                    bits.iter().for_each(|w| bytes.extend(w.to_le_bytes()))
+                }
+            }
+        }
+        bytes
+    }
+
+    /// Read back a bitmap serialized in the Roaring portable format, with or without run-length
+    /// encoded chunks
+    pub fn from_bytes(bytes: &[u8]) -> Result<Self, RoaringError> {
+        let mut reader = Reader { bytes };
+        let cookie = reader.u32()?;
+        let (count, runs) = if cookie == SERIAL_COOKIE_NO_RUNCONTAINER {
+            (reader.u32()? as usize, None)
+        } else if cookie & 0xFFFF == SERIAL_COOKIE {
+            let count = (cookie >> 16) as usize + 1;
+            (count, Some(reader.take((count + 7) / 8)?))
+        } else {
+            return Err(RoaringError::UnknownCookie(cookie));
+        };
+        if count > 1 << 16 {
+            return Err(RoaringError::Invalid);
+        }
+
+        let header = reader.take(count * 4)?;
+        if runs.is_none() || count >= NO_OFFSET_THRESHOLD {
+            // the containers follow one another, so their offsets aren't needed
+            reader.take(count * 4)?;
+        }
+
+        let mut containers: Vec<(u16, Container)> = Vec::with_capacity(count);
+        for (i, entry) in header.chunks_exact(4).enumerate() {
+            let key = u16::from_le_bytes([entry[0], entry[1]]);
+            let len = u16::from_le_bytes([entry[2], entry[3]]) as usize + 1;
+            let is_run = runs.map_or(false, |runs| runs[i / 8] & (1 << (i % 8)) != 0);
+            let container = if is_run {
+                let mut bits = Box::new([0u64; BITMAP_WORDS]);
+                for _ in 0..reader.u16()? {
+                    let start = reader.u16()? as usize;
+                    let end = start + reader.u16()? as usize;
+                    if end > u16::MAX as usize {
+                        return Err(RoaringError::Invalid);
+                    }
+                    (start..=end).for_each(|value| bits[value / 64] |= 1 << (value % 64));
+                }
+                Container::from_bits(bits)
+            } else if len > ARRAY_MAX {
+                let mut bits = Box::new([0u64; BITMAP_WORDS]);
+                for word in bits.iter_mut() {
+                    *word = reader.u64()?;
+                }
+                Container::from_bits(bits)
+            } else {
+                let values = (0..len).map(|_| reader.u16()).collect::<Result<Vec<_>, _>>()?;
+                if values.windows(2).any(|pair| pair[0] >= pair[1]) {
+                    return Err(RoaringError::Invalid);
+                }
+                Container::from_array(values)
+            };
+
+            match (container, containers.last()) {
+                (Some(container), last)
+                    if container.len() as usize == len
+                        && last.map_or(true, |(last, _)| *last < key) =>
+                {
+                    containers.push((key, container))
+                }
+                _ => return Err(RoaringError::Invalid),
+            }
+        }
+        if !reader.bytes.is_empty() {
+            return Err(RoaringError::Invalid);
+        }
+        Ok(RoaringBitmap { containers })
+    }
+}
+
+/// Reads the fields of serialized bytes, in order
+struct Reader<'a> {
+    bytes: &'a [u8],
+}
+
+impl<'a> Reader<'a> {
+    fn take(&mut self, len: usize) -> Result<&'a [u8], RoaringError> {
+        if self.bytes.len() < len {
+            return Err(RoaringError::Truncated);
+        }
+        let (taken, rest) = self.bytes.split_at(len);
+        self.bytes = rest;
+        Ok(taken)
+    }
+
+    fn u16(&mut self) -> Result<u16, RoaringError> {
+        Ok(u16::from_le_bytes(self.take(2)?.try_into().unwrap()))
+    }
+
+    fn u32(&mut self) -> Result<u32, RoaringError> {
+        Ok(u32::from_le_bytes(self.take(4)?.try_into().unwrap()))
+    }
+
+    fn u64(&mut self) -> Result<u64, RoaringError> {
+        Ok(u64::from_le_bytes(self.take(8)?.try_into().unwrap()))
+    }
+}
+
+/// The values of a [`RoaringBitmap`], in ascending order
+pub struct Iter<'a> {
+    containers: std::slice::Iter<'a, (u16, Container)>,
+    current: Option<(u16, ContainerIter<'a>)>,
+}
+
+impl Iterator for Iter<'_> {
+    type Item = u32;
+
+    fn next(&mut self) -> Option<u32> {
+        loop {
+            if let Some((key, values)) = &mut self.current {
+                if let Some(low) = values.next() {
+                    return Some((*key as u32) << 16 | low as u32);
+                }
+            }
+            let (key, container) = self.containers.next()?;
+            self.current = Some((*key, container.iter()));
+        }
+    }
+}
+
+impl<'a> IntoIterator for &'a RoaringBitmap {
+    type Item = u32;
+    type IntoIter = Iter<'a>;
+
+    fn into_iter(self) -> Iter<'a> {
+        self.iter()
+    }
+}
+
+impl Extend<u32> for RoaringBitmap {
+    fn extend<I: IntoIterator<Item = u32>>(&mut self, values: I) {
+        values.into_iter().for_each(|value| {
+            self.insert(value);
+        });
+    }
+}
+
+impl FromIterator<u32> for RoaringBitmap {
+    fn from_iter<I: IntoIterator<Item = u32>>(values: I) -> Self {
+        let mut bitmap = RoaringBitmap::new();
+        bitmap.extend(values);
+        bitmap
+    }
+}
+
+impl fmt::Debug for RoaringBitmap {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_set().entries(self.iter()).finish()
+    }
+}
+
+macro_rules! impl_set_op {
+    ($($trait:ident::$method:ident, $assign_trait:ident::$assign_method:ident => $op:expr;)+) => {$(
+        impl $trait<&RoaringBitmap> for &RoaringBitmap {
+            type Output = RoaringBitmap;
+
+            fn $method(self, other: &RoaringBitmap) -> RoaringBitmap {
+                self.combine(other, $op)
+            }
+        }
+
+        impl $assign_trait<&RoaringBitmap> for RoaringBitmap {
+            fn $assign_method(&mut self, other: &RoaringBitmap) {
+                *self = self.combine(other, $op);
+            }
+        }
+    )+};
+}
+
+impl_set_op! {
+    BitAnd::bitand, BitAndAssign::bitand_assign => Op::And;
+    BitOr::bitor, BitOrAssign::bitor_assign => Op::Or;
+    BitXor::bitxor, BitXorAssign::bitxor_assign => Op::Xor;
+    Sub::sub, SubAssign::sub_assign => Op::AndNot;
+}
+
+/// A `RoaringBitmap` is stored as a `bytea`
+impl IntoDatum for RoaringBitmap {
+    fn into_datum(self) -> Option<pg_sys::Datum> {
+        self.to_bytes().into_datum()
+    }
+
+    fn type_oid() -> pg_sys::Oid {
+        pg_sys::BYTEAOID
+    }
+}
+
+impl FromDatum for RoaringBitmap {
+    unsafe fn from_polymorphic_datum(
+        datum: pg_sys::Datum,
+        is_null: bool,
+        typoid: pg_sys::Oid,
+    ) -> Option<Self> {
+        let bytes = <&[u8]>::from_polymorphic_datum(datum, is_null, typoid)?;
+        match RoaringBitmap::from_bytes(bytes) {
+            Ok(bitmap) => Some(bitmap),
+            Err(e) => panic!("invalid RoaringBitmap: {e}"),
+        }
+    }
+}
+
+unsafe impl SqlTranslatable for RoaringBitmap {
+    fn argument_sql() -> Result<SqlMapping, ArgumentError> {
+        Ok(SqlMapping::literal("bytea"))
+    }
+    fn return_sql() -> Result<Returns, ReturnsError> {
+        Ok(Returns::One(SqlMapping::literal("bytea")))
+    }
+}
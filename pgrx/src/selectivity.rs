@@ -0,0 +1,420 @@
+/*
+Portions Copyright 2019-2021 ZomboDB, LLC.
+Portions Copyright 2021-2022 Technology Concepts & Design, Inc. <support@tcdi.com>
+
+All rights reserved.
+
+Use of this source code is governed by the MIT license that can be found in the LICENSE file.
+*/
+
+//! Selectivity estimation from the column statistics `ANALYZE` stores in `pg_statistic`
+//!
+//! [`ColumnStatistics`] looks up the statistics of a single column and estimates the fraction of
+//! rows for which `column OP constant` holds, the same way Postgres' own `eqsel` and
+//! `scalarltsel` do.  With the `cshim` feature, [`RestrictionClause`] picks apart the arguments a
+//! restriction selectivity function is called with, so a custom operator's `RESTRICT` function
+//! can be a few lines:
+//!
+//! ```rust,no_run
+//! use pgrx::prelude::*;
+//! use pgrx::selectivity::{ColumnStatistics, RestrictionClause};
+//!
+//! #[pg_extern]
+//! fn my_op_sel(root: Internal, operator: pg_sys::Oid, args: Internal, var_relid: i32) -> f64 {
+//!     unsafe {
+//!         let root = root.unwrap().unwrap().cast_mut_ptr();
+//!         let args = args.unwrap().unwrap().cast_mut_ptr();
+//!         RestrictionClause::from_planner(root, args, var_relid)
+//!             .and_then(|clause| clause.eq_selectivity(operator))
+//!             .unwrap_or(pg_sys::DEFAULT_EQ_SEL)
+//!     }
+//! }
+//! ```
+use crate::{pg_sys, FromDatum};
+
+/// The statistics `ANALYZE` gathered for one column of a relation
+///
+/// The underlying `pg_statistic` tuple is held in the syscache until this is dropped.
+pub struct ColumnStatistics {
+    tuple: pg_sys::HeapTuple,
+    relid: pg_sys::Oid,
+    collation: pg_sys::Oid,
+}
+
+impl ColumnStatistics {
+    /// Look up the statistics of column `attnum` of relation `relid`.  If `inherited` is true the
+    /// statistics covering the relation's inheritance children are used instead.
+    ///
+    /// Returns `None` if the column hasn't been analyzed.
+    pub fn lookup(relid: pg_sys::Oid, attnum: i16, inherited: bool) -> Option<Self> {
+        let tuple = unsafe {
+            pg_sys::SearchSysCache3(
+                pg_sys::SysCacheIdentifier_STATRELATTINH as _,
+                pg_sys::Datum::from(relid),
+                pg_sys::Datum::from(attnum),
+                pg_sys::Datum::from(inherited),
+            )
+        };
+        if tuple.is_null() {
+            return None;
+        }
+
+        let mut typid = pg_sys::InvalidOid;
+        let mut typmod = -1;
+        let mut collation = pg_sys::InvalidOid;
+        unsafe {
+            pg_sys::get_atttypetypmodcoll(relid, attnum, &mut typid, &mut typmod, &mut collation);
+        }
+        Some(ColumnStatistics { tuple, relid, collation })
+    }
+
+    /// Look up the statistics of the column named `column` of relation `relid`
+    ///
+    /// Returns `None` if there is no such column, or it hasn't been analyzed.
+    pub fn lookup_by_name(relid: pg_sys::Oid, column: &str, inherited: bool) -> Option<Self> {
+        let column = alloc::ffi::CString::new(column).ok()?;
+        let attnum = unsafe { pg_sys::get_attnum(relid, column.as_ptr()) };
+        if attnum == pg_sys::InvalidAttrNumber as i16 {
+            return None;
+        }
+        ColumnStatistics::lookup(relid, attnum, inherited)
+    }
+
+    fn form(&self) -> &pg_sys::FormData_pg_statistic {
+        unsafe {
+            // SAFETY:  `self.tuple` is a valid pg_statistic tuple, pinned in the syscache
+            &*(pg_sys::GETSTRUCT(self.tuple) as *const pg_sys::FormData_pg_statistic)
+        }
+    }
+
+    /// The fraction of the column's values that are NULL
+    pub fn null_frac(&self) -> f64 {
+        self.form().stanullfrac as f64
+    }
+
+    /// The average width, in bytes, of the column's non-NULL values
+    pub fn avg_width(&self) -> i32 {
+        self.form().stawidth
+    }
+
+    /// The estimated number of distinct non-NULL values in the column
+    pub fn n_distinct(&self) -> f64 {
+        let stadistinct = self.form().stadistinct as f64;
+        if stadistinct > 0.0 {
+            stadistinct
+        } else if stadistinct < 0.0 {
+            // a negative value is a multiple of the number of rows
+            (-stadistinct * self.reltuples()).max(1.0).round()
+        } else {
+            pg_sys::DEFAULT_NUM_DISTINCT as f64
+        }
+    }
+
+    fn reltuples(&self) -> f64 {
+        unsafe {
+            let tuple = pg_sys::SearchSysCache1(
+                pg_sys::SysCacheIdentifier_RELOID as _,
+                pg_sys::Datum::from(self.relid),
+            );
+            if tuple.is_null() {
+                return 0.0;
+            }
+            let reltuples =
+                (*(pg_sys::GETSTRUCT(tuple) as *const pg_sys::FormData_pg_class)).reltuples;
+            pg_sys::ReleaseSysCache(tuple);
+            reltuples.max(0.0) as f64
+        }
+    }
+
+    /// The column's most common values along with the fraction of rows each appears in, most
+    /// common first.  Returns `None` if there is no most-common-values list.
+    ///
+    /// `T` should be an owned type, the values don't outlive this call.
+    pub fn most_common_values<T: FromDatum>(&self) -> Option<Vec<(T, f64)>> {
+        self.with_slot(pg_sys::STATISTIC_KIND_MCV, |values, numbers, typid| {
+            values
+                .iter()
+                .zip(numbers)
+                .filter_map(|(value, freq)| unsafe {
+                    T::from_polymorphic_datum(*value, false, typid).map(|v| (v, *freq as f64))
+                })
+                .collect()
+        })
+    }
+
+    /// The bounds of the column's equal-frequency histogram, in ascending order.  Returns `None`
+    /// if there is no histogram.
+    ///
+    /// `T` should be an owned type, the values don't outlive this call.
+    pub fn histogram<T: FromDatum>(&self) -> Option<Vec<T>> {
+        self.with_slot(pg_sys::STATISTIC_KIND_HISTOGRAM, |values, _, typid| {
+            values
+                .iter()
+                .filter_map(|value| unsafe { T::from_polymorphic_datum(*value, false, typid) })
+                .collect()
+        })
+    }
+
+    /// Estimate the selectivity of `column = value`, where `operator` is an equality operator.
+    ///
+    /// If `var_on_left` is false the clause is `value = column` instead.
+    pub fn eq_selectivity(
+        &self,
+        operator: pg_sys::Oid,
+        value: pg_sys::Datum,
+        var_on_left: bool,
+    ) -> f64 {
+        let op = OperatorCall::new(operator, self.collation, var_on_left);
+        let mcv = self.with_slot(pg_sys::STATISTIC_KIND_MCV, |values, numbers, _| {
+            let matched = values.iter().position(|mcv| op.call(*mcv, value));
+            (
+                matched.map(|i| numbers[i] as f64),
+                numbers.iter().map(|&n| n as f64).sum::<f64>(),
+                values.len(),
+                numbers.last().map(|&n| n as f64),
+            )
+        });
+
+        let (sumcommon, nmcv, least_common) = match mcv {
+            Some((Some(freq), ..)) => return freq,
+            Some((None, sumcommon, nmcv, least_common)) => (sumcommon, nmcv, least_common),
+            None => (0.0, 0, None),
+        };
+
+        // spread whatever isn't NULL or a common value evenly across the remaining distinct values
+        let mut selec = (1.0 - sumcommon - self.null_frac()).clamp(0.0, 1.0);
+        let other_distinct = self.n_distinct() - nmcv as f64;
+        if other_distinct > 1.0 {
+            selec /= other_distinct;
+        }
+        // and no uncommon value should be more common than the least common of the common ones
+        if let Some(least_common) = least_common {
+            selec = selec.min(least_common);
+        }
+        selec.clamp(0.0, 1.0)
+    }
+
+    /// Estimate the selectivity of `column OP value`, where `operator` is an inequality operator
+    /// such as `<` or `>=` that is consistent with the column's histogram ordering.
+    ///
+    /// If `var_on_left` is false the clause is `value OP column` instead.
+    pub fn ineq_selectivity(
+        &self,
+        operator: pg_sys::Oid,
+        value: pg_sys::Datum,
+        var_on_left: bool,
+    ) -> f64 {
+        let op = OperatorCall::new(operator, self.collation, var_on_left);
+
+        // the common values are counted exactly...
+        let (mcv_selec, sumcommon) = self
+            .with_slot(pg_sys::STATISTIC_KIND_MCV, |values, numbers, _| {
+                values.iter().zip(numbers).fold((0.0, 0.0), |(selec, sum), (mcv, &freq)| {
+                    let freq = freq as f64;
+                    (if op.call(*mcv, value) { selec + freq } else { selec }, sum + freq)
+                })
+            })
+            .unwrap_or((0.0, 0.0));
+
+        // ...and the rest are assumed to be distributed like the histogram
+        let hist_selec = self
+            .with_slot(pg_sys::STATISTIC_KIND_HISTOGRAM, |values, _, _| {
+                let matched = values.iter().filter(|bound| op.call(**bound, value)).count();
+                if values.len() < 2 || matched == 0 {
+                    0.0
+                } else if matched == values.len() {
+                    1.0
+                } else {
+                    // assume `value` falls in the middle of the bin it's in
+                    (matched as f64 - 0.5) / (values.len() - 1) as f64
+                }
+            })
+            .unwrap_or(pg_sys::DEFAULT_INEQ_SEL);
+
+        let other = (1.0 - sumcommon - self.null_frac()).clamp(0.0, 1.0);
+        (mcv_selec + hist_selec * other).clamp(0.0, 1.0)
+    }
+
+    /// Run `f` with the values, numbers, and value type of the statistics slot of kind `kind`
+    fn with_slot<R>(
+        &self,
+        kind: u32,
+        f: impl FnOnce(&[pg_sys::Datum], &[f32], pg_sys::Oid) -> R,
+    ) -> Option<R> {
+        unsafe {
+            let mut slot = pg_sys::AttStatsSlot::default();
+            let flags = pg_sys::ATTSTATSSLOT_VALUES | pg_sys::ATTSTATSSLOT_NUMBERS;
+            if !pg_sys::get_attstatsslot(
+                &mut slot,
+                self.tuple,
+                kind as _,
+                pg_sys::InvalidOid,
+                flags as _,
+            ) {
+                return None;
+            }
+
+            let values = slice_or_empty(slot.values, slot.nvalues);
+            let numbers = slice_or_empty(slot.numbers, slot.nnumbers);
+            let result = f(values, numbers, slot.valuetype);
+            pg_sys::free_attstatsslot(&mut slot);
+            Some(result)
+        }
+    }
+}
+
+impl Drop for ColumnStatistics {
+    fn drop(&mut self) {
+        unsafe {
+            // SAFETY:  we got `self.tuple` from the syscache and haven't released it yet
+            pg_sys::ReleaseSysCache(self.tuple);
+        }
+    }
+}
+
+unsafe fn slice_or_empty<'a, T>(ptr: *const T, len: i32) -> &'a [T] {
+    if ptr.is_null() || len <= 0 {
+        &[]
+    } else {
+        std::slice::from_raw_parts(ptr, len as usize)
+    }
+}
+
+/// Calls the function behind a boolean operator, with the column's value on the correct side
+struct OperatorCall {
+    flinfo: pg_sys::FmgrInfo,
+    collation: pg_sys::Oid,
+    var_on_left: bool,
+}
+
+impl OperatorCall {
+    fn new(operator: pg_sys::Oid, collation: pg_sys::Oid, var_on_left: bool) -> Self {
+        let mut flinfo = pg_sys::FmgrInfo::default();
+        unsafe {
+            pg_sys::fmgr_info(pg_sys::get_opcode(operator), &mut flinfo);
+        }
+        OperatorCall { flinfo, collation, var_on_left }
+    }
+
+    fn call(&self, column_value: pg_sys::Datum, constant: pg_sys::Datum) -> bool {
+        let (left, right) =
+            if self.var_on_left { (column_value, constant) } else { (constant, column_value) };
+        unsafe {
+            // SAFETY:  the FmgrInfo is only read by the function it describes
+            let flinfo = &self.flinfo as *const _ as *mut pg_sys::FmgrInfo;
+            let result = pg_sys::FunctionCall2Coll(flinfo, self.collation, left, right);
+            bool::from_datum(result, false).unwrap_or(false)
+        }
+    }
+}
+
+/// A `column OP constant` clause, as seen by a restriction selectivity function
+#[cfg(feature = "cshim")]
+#[derive(Debug, Copy, Clone)]
+pub struct RestrictionClause {
+    /// The relation the column belongs to
+    pub relid: pg_sys::Oid,
+    /// The column's attribute number
+    pub attnum: i16,
+    /// Whether the relation's inheritance children are included
+    pub inherited: bool,
+    /// The constant, or `None` if it is NULL
+    pub constant: Option<pg_sys::Datum>,
+    /// Whether the column is the operator's left argument
+    pub var_on_left: bool,
+}
+
+#[cfg(feature = "cshim")]
+impl RestrictionClause {
+    /// Interpret the arguments of a restriction selectivity function (`root`, the operator's
+    /// `args`, and `varRelid`).  Returns `None` unless one argument is a plain column of a table
+    /// and the other reduces to a constant.
+    ///
+    /// # Safety
+    ///
+    /// The arguments must be the ones Postgres passed to the selectivity function.
+    pub unsafe fn from_planner(
+        root: *mut pg_sys::PlannerInfo,
+        args: *mut pg_sys::List,
+        var_relid: i32,
+    ) -> Option<Self> {
+        let args = crate::PgList::<pg_sys::Node>::from_pg(args);
+        if args.len() != 2 {
+            return None;
+        }
+        let (left, right) = (strip_relabel(args.get_ptr(0)?), strip_relabel(args.get_ptr(1)?));
+
+        let (var, other, var_on_left) = if is_plain_var(left, var_relid) {
+            (left as *mut pg_sys::Var, right, true)
+        } else if is_plain_var(right, var_relid) {
+            (right as *mut pg_sys::Var, left, false)
+        } else {
+            return None;
+        };
+
+        let rte = *(*root).simple_rte_array.add((*var).varno as usize);
+        if rte.is_null() || (*rte).rtekind != pg_sys::RTEKind_RTE_RELATION {
+            return None;
+        }
+
+        let other = pg_sys::estimate_expression_value(root, other);
+        if !crate::is_a(other, pg_sys::NodeTag_T_Const) {
+            return None;
+        }
+        let other = other as *mut pg_sys::Const;
+
+        Some(RestrictionClause {
+            relid: (*rte).relid,
+            attnum: (*var).varattno,
+            inherited: (*rte).inh,
+            constant: if (*other).constisnull { None } else { Some((*other).constvalue) },
+            var_on_left,
+        })
+    }
+
+    /// The statistics of the clause's column, if it has been analyzed
+    pub fn statistics(&self) -> Option<ColumnStatistics> {
+        ColumnStatistics::lookup(self.relid, self.attnum, self.inherited)
+    }
+
+    /// Estimate the clause's selectivity for an equality `operator`.  Returns `None` if the column
+    /// hasn't been analyzed.  Strict operators never match NULL, so a NULL constant selects
+    /// nothing.
+    pub fn eq_selectivity(&self, operator: pg_sys::Oid) -> Option<f64> {
+        let stats = self.statistics()?;
+        Some(match self.constant {
+            Some(value) => stats.eq_selectivity(operator, value, self.var_on_left),
+            None => 0.0,
+        })
+    }
+
+    /// Estimate the clause's selectivity for an inequality `operator`.  Returns `None` if the
+    /// column hasn't been analyzed.  Strict operators never match NULL, so a NULL constant
+    /// selects nothing.
+    pub fn ineq_selectivity(&self, operator: pg_sys::Oid) -> Option<f64> {
+        let stats = self.statistics()?;
+        Some(match self.constant {
+            Some(value) => stats.ineq_selectivity(operator, value, self.var_on_left),
+            None => 0.0,
+        })
+    }
+}
+
+#[cfg(feature = "cshim")]
+unsafe fn strip_relabel(mut node: *mut pg_sys::Node) -> *mut pg_sys::Node {
+    while crate::is_a(node, pg_sys::NodeTag_T_RelabelType) {
+        node = (*(node as *mut pg_sys::RelabelType)).arg as *mut pg_sys::Node;
+    }
+    node
+}
+
+#[cfg(feature = "cshim")]
+unsafe fn is_plain_var(node: *mut pg_sys::Node, var_relid: i32) -> bool {
+    if !crate::is_a(node, pg_sys::NodeTag_T_Var) {
+        return false;
+    }
+    let var = node as *mut pg_sys::Var;
+    (*var).varlevelsup == 0
+        && (*var).varattno > 0
+        && (var_relid == 0 || (*var).varno as i32 == var_relid)
+}
@@ -0,0 +1,406 @@
+/*
+Portions Copyright 2019-2021 ZomboDB, LLC.
+Portions Copyright 2021-2022 Technology Concepts & Design, Inc. <support@tcdi.com>
+
+All rights reserved.
+
+Use of this source code is governed by the MIT license that can be found in the LICENSE file.
+*/
+
+//! Keeping track of the session-level state an extension sets up, so it can be put back
+//!
+//! Under transaction-level connection pooling, one backend serves many clients, one transaction
+//! at a time.  Between clients, the pooler typically issues `DISCARD ALL`, which resets every
+//! setting, drops temporary tables, and deallocates prepared statements, including the ones the
+//! extension itself relies on.
+//!
+//! A [`SessionState`] records the settings, temporary objects, and prepared statements an
+//! extension creates through it.  That record can be captured as a [`SessionSnapshot`] and
+//! restored later, and with [`SessionState::restore_on_reset()`] it's re-established
+//! automatically whenever a `DISCARD`, `RESET ALL`, or `DEALLOCATE ALL` tears it down.
+//! Extension state that lives in Rust, like caches, can be cleared at the same moments with
+//! [`on_session_reset()`].
+//!
+//! ## Examples
+//!
+//! ```rust,no_run
+//! use pgrx::prelude::*;
+//! use pgrx::session::SessionState;
+//!
+//! static SESSION: SessionState = SessionState::new();
+//!
+//! #[pg_guard]
+//! pub extern "C" fn _PG_init() {
+//!     SESSION.restore_on_reset();
+//! }
+//!
+//! #[pg_extern]
+//! fn start_tracking() -> Result<(), spi::Error> {
+//!     SESSION.set("work_mem", "256MB")?;
+//!     SESSION.create_temp("tracked", "CREATE TEMP TABLE tracked (id bigint)")?;
+//!     SESSION.prepare("lookup_tracked", "SELECT id FROM tracked WHERE id = $1")
+//! }
+//! ```
+use crate as pgrx; // for #[pg_guard] support from within ourself
+use crate::prelude::*;
+use crate::spi::quote_identifier;
+use std::cell::RefCell;
+use std::sync::{Mutex, MutexGuard, PoisonError};
+
+/// A statement that resets session-level state, as seen by [`on_session_reset()`] callbacks
+#[derive(Debug, Copy, Clone, Eq, PartialEq)]
+pub enum SessionReset {
+    /// `DISCARD ALL`, which resets everything below, and more
+    DiscardAll,
+    /// `DISCARD PLANS`
+    DiscardPlans,
+    /// `DISCARD SEQUENCES`
+    DiscardSequences,
+    /// `DISCARD TEMP`, which drops temporary objects
+    DiscardTemp,
+    /// `RESET ALL`, which resets every setting
+    ResetAll,
+    /// `DEALLOCATE ALL`, which deallocates every prepared statement
+    DeallocateAll,
+}
+
+impl SessionReset {
+    fn resets_settings(&self) -> bool {
+        matches!(self, SessionReset::DiscardAll | SessionReset::ResetAll)
+    }
+
+    fn drops_temp_objects(&self) -> bool {
+        matches!(self, SessionReset::DiscardAll | SessionReset::DiscardTemp)
+    }
+
+    fn deallocates_statements(&self) -> bool {
+        matches!(self, SessionReset::DiscardAll | SessionReset::DeallocateAll)
+    }
+}
+
+/// The session-level state recorded by a [`SessionState`]
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct SessionSnapshot {
+    /// Settings, as `(name, value)`
+    pub settings: Vec<(String, String)>,
+    /// Temporary objects, as `(name, the statement that creates it)`
+    pub temp_objects: Vec<(String, String)>,
+    /// Prepared statements, as `(name, query)`
+    pub prepared_statements: Vec<(String, String)>,
+}
+
+/// The session-level state an extension has set up.  It's meant to be a `static`
+pub struct SessionState {
+    recorded: Mutex<SessionSnapshot>,
+}
+
+thread_local! {
+    static RESTORE_ON_RESET: RefCell<Vec<&'static SessionState>> = const { RefCell::new(Vec::new()) };
+    static RESET_CALLBACKS: RefCell<Vec<fn(SessionReset)>> = const { RefCell::new(Vec::new()) };
+}
+
+static mut PREV_PROCESS_UTILITY_HOOK: pg_sys::ProcessUtility_hook_type = None;
+
+impl SessionState {
+    pub const fn new() -> Self {
+        SessionState {
+            recorded: Mutex::new(SessionSnapshot {
+                settings: Vec::new(),
+                temp_objects: Vec::new(),
+                prepared_statements: Vec::new(),
+            }),
+        }
+    }
+
+    /// Set the setting `name` to `value` for the rest of the session, like `SET`, and record it
+    pub fn set(&self, name: &str, value: &str) -> spi::Result<()> {
+        set_config(name, value)?;
+        upsert(&mut self.recorded().settings, name, value);
+        Ok(())
+    }
+
+    /// Create a temporary table, view, or sequence named `name` by running `create`, and record
+    /// it
+    pub fn create_temp(&self, name: &str, create: &str) -> spi::Result<()> {
+        Spi::run(create)?;
+        upsert(&mut self.recorded().temp_objects, name, create);
+        Ok(())
+    }
+
+    /// Prepare `query` as the SQL-level prepared statement `name`, like `PREPARE`, and record it
+    pub fn prepare(&self, name: &str, query: &str) -> spi::Result<()> {
+        prepare(name, query)?;
+        upsert(&mut self.recorded().prepared_statements, name, query);
+        Ok(())
+    }
+
+    /// Capture the recorded state, with each setting's current value
+    pub fn snapshot(&self) -> spi::Result<SessionSnapshot> {
+        let mut snapshot = self.recorded().clone();
+        for (name, value) in snapshot.settings.iter_mut() {
+            if let Some(current) = current_setting(name)? {
+                *value = current;
+            }
+        }
+        Ok(snapshot)
+    }
+
+    /// Put the session back the way `snapshot` describes:  apply its settings, and create any of
+    /// its temporary objects and prepared statements that don't exist.  It becomes the recorded
+    /// state
+    pub fn restore(&self, snapshot: &SessionSnapshot) -> spi::Result<()> {
+        restore(snapshot, true, true, true)?;
+        *self.recorded() = snapshot.clone();
+        Ok(())
+    }
+
+    /// Put the recorded state back, as [`SessionState::restore()`] does
+    pub fn reestablish(&self) -> spi::Result<()> {
+        let recorded = self.recorded().clone();
+        restore(&recorded, true, true, true)
+    }
+
+    /// Re-establish the parts of the recorded state that are reset by `DISCARD`, `RESET ALL`,
+    /// and `DEALLOCATE ALL`, right after they run.  This should be called from `_PG_init()`
+    pub fn restore_on_reset(&'static self) {
+        install_hook();
+        RESTORE_ON_RESET.with(|states| states.borrow_mut().push(self));
+    }
+
+    /// The recorded state.  Nothing that can fail happens while it's locked, so a poisoned lock
+    /// still holds a consistent snapshot
+    fn recorded(&self) -> MutexGuard<'_, SessionSnapshot> {
+        self.recorded.lock().unwrap_or_else(PoisonError::into_inner)
+    }
+}
+
+impl Default for SessionState {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// Call `callback` right after each statement that resets session-level state, so
+/// extension-owned state can be reset along with it.  This should be called from `_PG_init()`
+pub fn on_session_reset(callback: fn(SessionReset)) {
+    install_hook();
+    RESET_CALLBACKS.with(|callbacks| callbacks.borrow_mut().push(callback));
+}
+
+fn upsert(entries: &mut Vec<(String, String)>, name: &str, value: &str) {
+    match entries.iter_mut().find(|(existing, _)| existing == name) {
+        Some(entry) => entry.1 = value.to_string(),
+        None => entries.push((name.to_string(), value.to_string())),
+    }
+}
+
+fn text_arg(value: &str) -> (PgOid, Option<pg_sys::Datum>) {
+    (PgBuiltInOids::TEXTOID.oid(), value.into_datum())
+}
+
+fn set_config(name: &str, value: &str) -> spi::Result<()> {
+    Spi::run_with_args(
+        "SELECT pg_catalog.set_config($1, $2, false)",
+        Some(vec![text_arg(name), text_arg(value)]),
+    )
+}
+
+fn current_setting(name: &str) -> spi::Result<Option<String>> {
+    Spi::get_one_with_args("SELECT pg_catalog.current_setting($1, true)", vec![text_arg(name)])
+}
+
+fn prepare(name: &str, query: &str) -> spi::Result<()> {
+    Spi::run(&format!("PREPARE {} AS {query}", quote_identifier(name)))
+}
+
+fn restore(
+    snapshot: &SessionSnapshot,
+    settings: bool,
+    temp_objects: bool,
+    prepared_statements: bool,
+) -> spi::Result<()> {
+    if settings {
+        for (name, value) in &snapshot.settings {
+            set_config(name, value)?;
+        }
+    }
+
+    if temp_objects {
+        for (name, create) in &snapshot.temp_objects {
+            let exists = Spi::get_one_with_args::<bool>(
+                "SELECT pg_catalog.to_regclass($1) IS NOT NULL",
+                vec![text_arg(&format!("pg_temp.{}", quote_identifier(name)))],
+            )?;
+            if exists != Some(true) {
+                Spi::run(create)?;
+            }
+        }
+    }
+
+    if prepared_statements {
+        for (name, query) in &snapshot.prepared_statements {
+            let exists = Spi::get_one_with_args::<bool>(
+                "SELECT EXISTS (SELECT 1 FROM pg_catalog.pg_prepared_statements WHERE name = $1)",
+                vec![text_arg(name)],
+            )?;
+            if exists != Some(true) {
+                prepare(name, query)?;
+            }
+        }
+    }
+
+    Ok(())
+}
+
+fn install_hook() {
+    let installed = RESTORE_ON_RESET.with(|states| !states.borrow().is_empty())
+        || RESET_CALLBACKS.with(|callbacks| !callbacks.borrow().is_empty());
+    if !installed {
+        // SAFETY:  this runs once, before anything is registered, so our hook isn't installed yet
+        // and the previous one is only ever read by it
+        unsafe {
+            PREV_PROCESS_UTILITY_HOOK = pg_sys::ProcessUtility_hook;
+            pg_sys::ProcessUtility_hook = Some(pgrx_session_process_utility);
+        }
+    }
+}
+
+/// Which kind of reset, if any, a utility statement is
+unsafe fn session_reset(pstmt: *mut pg_sys::PlannedStmt) -> Option<SessionReset> {
+    let stmt = (*pstmt).utilityStmt;
+    if stmt.is_null() {
+        return None;
+    }
+
+    match (*stmt).type_ {
+        pg_sys::NodeTag_T_DiscardStmt => match (*stmt.cast::<pg_sys::DiscardStmt>()).target {
+            pg_sys::DiscardMode_DISCARD_ALL => Some(SessionReset::DiscardAll),
+            pg_sys::DiscardMode_DISCARD_PLANS => Some(SessionReset::DiscardPlans),
+            pg_sys::DiscardMode_DISCARD_SEQUENCES => Some(SessionReset::DiscardSequences),
+            _ => Some(SessionReset::DiscardTemp),
+        },
+        pg_sys::NodeTag_T_VariableSetStmt
+            if (*stmt.cast::<pg_sys::VariableSetStmt>()).kind
+                == pg_sys::VariableSetKind_VAR_RESET_ALL =>
+        {
+            Some(SessionReset::ResetAll)
+        }
+        pg_sys::NodeTag_T_DeallocateStmt
+            if (*stmt.cast::<pg_sys::DeallocateStmt>()).name.is_null() =>
+        {
+            Some(SessionReset::DeallocateAll)
+        }
+        _ => None,
+    }
+}
+
+/// Run after a reset statement has done its work
+unsafe fn after_reset(reset: SessionReset) {
+    // clone the registrations, so restoring or a callback can register more
+    let states = RESTORE_ON_RESET.with(|states| states.borrow().clone());
+    for state in states {
+        let recorded = state.recorded().clone();
+        restore(
+            &recorded,
+            reset.resets_settings(),
+            reset.drops_temp_objects(),
+            reset.deallocates_statements(),
+        )
+        .unwrap_or_else(|e| panic!("failed to restore session state after {reset:?}: {e}"));
+    }
+    let callbacks = RESET_CALLBACKS.with(|callbacks| callbacks.borrow().clone());
+    for callback in callbacks {
+        callback(reset);
+    }
+}
+
+#[cfg(any(feature = "pg11", feature = "pg12"))]
+#[pg_guard]
+unsafe extern "C" fn pgrx_session_process_utility(
+    pstmt: *mut pg_sys::PlannedStmt,
+    query_string: *const std::os::raw::c_char,
+    context: pg_sys::ProcessUtilityContext,
+    params: pg_sys::ParamListInfo,
+    query_env: *mut pg_sys::QueryEnvironment,
+    dest: *mut pg_sys::DestReceiver,
+    completion_tag: *mut std::os::raw::c_char,
+) {
+    let reset = session_reset(pstmt);
+    match PREV_PROCESS_UTILITY_HOOK {
+        Some(prev) => prev(pstmt, query_string, context, params, query_env, dest, completion_tag),
+        None => pg_sys::standard_ProcessUtility(
+            pstmt,
+            query_string,
+            context,
+            params,
+            query_env,
+            dest,
+            completion_tag,
+        ),
+    }
+    if let Some(reset) = reset {
+        after_reset(reset);
+    }
+}
+
+#[cfg(feature = "pg13")]
+#[pg_guard]
+unsafe extern "C" fn pgrx_session_process_utility(
+    pstmt: *mut pg_sys::PlannedStmt,
+    query_string: *const std::os::raw::c_char,
+    context: pg_sys::ProcessUtilityContext,
+    params: pg_sys::ParamListInfo,
+    query_env: *mut pg_sys::QueryEnvironment,
+    dest: *mut pg_sys::DestReceiver,
+    qc: *mut pg_sys::QueryCompletion,
+) {
+    let reset = session_reset(pstmt);
+    match PREV_PROCESS_UTILITY_HOOK {
+        Some(prev) => prev(pstmt, query_string, context, params, query_env, dest, qc),
+        None => pg_sys::standard_ProcessUtility(
+            pstmt,
+            query_string,
+            context,
+            params,
+            query_env,
+            dest,
+            qc,
+        ),
+    }
+    if let Some(reset) = reset {
+        after_reset(reset);
+    }
+}
+
+#[cfg(any(feature = "pg14", feature = "pg15"))]
+#[pg_guard]
+unsafe extern "C" fn pgrx_session_process_utility(
+    pstmt: *mut pg_sys::PlannedStmt,
+    query_string: *const std::os::raw::c_char,
+    read_only_tree: bool,
+    context: pg_sys::ProcessUtilityContext,
+    params: pg_sys::ParamListInfo,
+    query_env: *mut pg_sys::QueryEnvironment,
+    dest: *mut pg_sys::DestReceiver,
+    qc: *mut pg_sys::QueryCompletion,
+) {
+    let reset = session_reset(pstmt);
+    match PREV_PROCESS_UTILITY_HOOK {
+        Some(prev) => {
+            prev(pstmt, query_string, read_only_tree, context, params, query_env, dest, qc)
+        }
+        None => pg_sys::standard_ProcessUtility(
+            pstmt,
+            query_string,
+            read_only_tree,
+            context,
+            params,
+            query_env,
+            dest,
+            qc,
+        ),
+    }
+    if let Some(reset) = reset {
+        after_reset(reset);
+    }
+}
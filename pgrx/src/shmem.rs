@@ -9,7 +9,6 @@ Use of this source code is governed by the MIT license that can be found in the
 use crate::lwlock::*;
 use crate::{pg_sys, PgAtomic};
 use std::hash::Hash;
-use uuid::Uuid;
 
 /// Custom types that want to participate in shared memory must implement this marker trait
 pub unsafe trait PGRXSharedMemory {}
@@ -109,6 +108,56 @@ macro_rules! pg_shmem_init {
     };
 }
 
+/// Declare `static`s living in Postgres Shared Memory, named after their path so that each backend
+/// finds the same memory and LWLock, even where it must attach to shared memory itself, as on
+/// Windows.
+///
+/// Each is a [`PgLwLock`] (or a [`PgAtomic`]) created with `new_named()`, and must still be passed
+/// to [`pg_shmem_init!()`] during `_PG_init()`.
+///
+/// # Example
+///
+/// ```rust,no_run
+/// use pgrx::prelude::*;
+/// use pgrx::{pg_shmem, pg_shmem_init, PgAtomic, PgLwLock, PgSharedMemoryInitialization, PGRXSharedMemory};
+/// use std::sync::atomic::AtomicU64;
+///
+/// #[derive(Copy, Clone, Default)]
+/// pub struct Counters {
+///     hits: u64,
+///     misses: u64,
+/// }
+/// unsafe impl PGRXSharedMemory for Counters {}
+///
+/// pg_shmem! {
+///     static COUNTERS: PgLwLock<Counters>;
+///     pub static CALLS: PgAtomic<AtomicU64>;
+/// }
+///
+/// #[pg_guard]
+/// pub extern "C" fn _PG_init() {
+///     pg_shmem_init!(COUNTERS);
+///     pg_shmem_init!(CALLS);
+/// }
+///
+/// #[pg_extern]
+/// fn record_hit() -> i64 {
+///     let mut counters = COUNTERS.exclusive();
+///     counters.hits += 1;
+///     counters.hits as i64
+/// }
+/// ```
+#[macro_export]
+macro_rules! pg_shmem {
+    ($($(#[$attr:meta])* $vis:vis static $name:ident: $ty:ty);+ $(;)?) => {
+        $(
+            $(#[$attr])*
+            $vis static $name: $ty =
+                <$ty>::new_named(concat!(module_path!(), "::", stringify!($name)));
+        )+
+    };
+}
+
 /// A trait that types can implement to provide their own Postgres Shared Memory initialization process
 pub trait PgSharedMemoryInitialization {
     /// Automatically called when the an extension is loaded.  If using the `pg_shmem_init!()` macro
@@ -153,7 +202,7 @@ impl PgSharedMem {
     /// Must be run from PG_init, use for types which are guarded by a LWLock
     pub fn pg_init_locked<T: Default + PGRXSharedMemory>(lock: &PgLwLock<T>) {
         unsafe {
-            let lock = alloc::ffi::CString::new(lock.get_name()).expect("CString::new failed");
+            let lock = shmem_key(lock.get_name());
             pg_sys::RequestAddinShmemSpace(std::mem::size_of::<T>());
             pg_sys::RequestNamedLWLockTranche(lock.as_ptr(), 1);
         }
@@ -170,7 +219,7 @@ impl PgSharedMem {
     pub fn shmem_init_locked<T: Default + PGRXSharedMemory>(lock: &PgLwLock<T>) {
         let mut found = false;
         unsafe {
-            let shm_name = alloc::ffi::CString::new(lock.get_name()).expect("CString::new failed");
+            let shm_name = shmem_key(lock.get_name());
             let addin_shmem_init_lock: *mut pg_sys::LWLock =
                 &mut (*pg_sys::MainLWLockArray.add(21)).lock;
             pg_sys::LWLockAcquire(addin_shmem_init_lock, pg_sys::LWLockMode_LW_EXCLUSIVE);
@@ -179,7 +228,10 @@ impl PgSharedMem {
                 pg_sys::ShmemInitStruct(shm_name.into_raw(), std::mem::size_of::<T>(), &mut found)
                     as *mut T;
 
-            std::ptr::write(fv_shmem, <T>::default());
+            // a backend attaching to memory another has already initialized mustn't reset it
+            if !found {
+                std::ptr::write(fv_shmem, <T>::default());
+            }
 
             lock.attach(fv_shmem);
             pg_sys::LWLockRelease(addin_shmem_init_lock);
@@ -189,8 +241,7 @@ impl PgSharedMem {
     /// Must be run from the shared memory init hook, use for rust atomics behind `PgAtomic`
     pub fn shmem_init_atomic<T: atomic_traits::Atomic + Default>(atomic: &PgAtomic<T>) {
         unsafe {
-            let shm_name = alloc::ffi::CString::new(Uuid::new_v4().to_string())
-                .expect("CString::new() failed");
+            let shm_name = shmem_key(atomic.get_name());
 
             let addin_shmem_init_lock: *mut pg_sys::LWLock =
                 &mut (*pg_sys::MainLWLockArray.add(21)).lock;
@@ -202,13 +253,48 @@ impl PgSharedMem {
                     as *mut T;
 
             atomic.attach(fv_shmem);
-            let atomic = T::default();
-            std::ptr::copy(&atomic, fv_shmem, 1);
+            if !found {
+                let atomic = T::default();
+                std::ptr::copy(&atomic, fv_shmem, 1);
+            }
             pg_sys::LWLockRelease(addin_shmem_init_lock);
         }
     }
 }
 
+/// The name Postgres knows a shared memory struct and its LWLock tranche by.  Postgres only
+/// compares the first `SHMEM_INDEX_KEYSIZE - 1` bytes of shared memory names, and truncates tranche
+/// names, so longer names are replaced by a hash of the whole name
+pub(crate) fn shmem_key(name: &str) -> alloc::ffi::CString {
+    let key = if name.len() < pg_sys::SHMEM_INDEX_KEYSIZE as usize {
+        name.to_string()
+    } else {
+        // FNV-1a, which is stable across builds and platforms, unlike `DefaultHasher`
+        let hash = name
+            .bytes()
+            .fold(0xcbf29ce484222325u64, |hash, b| (hash ^ b as u64).wrapping_mul(0x100000001b3));
+        format!("pgrx-shmem-{:016x}", hash)
+    };
+    alloc::ffi::CString::new(key).expect("CString::new failed")
+}
+
+/// Attach to the `size` bytes of shared memory named after `name`, zeroing them if this is the
+/// first backend to.  Must be run from the shared memory init hook
+pub(crate) unsafe fn shmem_init_zeroed(name: &str, size: usize) -> *mut std::ffi::c_void {
+    let shm_name = shmem_key(name);
+    let addin_shmem_init_lock: *mut pg_sys::LWLock = &mut (*pg_sys::MainLWLockArray.add(21)).lock;
+
+    let mut found = false;
+    pg_sys::LWLockAcquire(addin_shmem_init_lock, pg_sys::LWLockMode_LW_EXCLUSIVE);
+    let shmem = pg_sys::ShmemInitStruct(shm_name.into_raw(), size, &mut found);
+    // a backend attaching to memory another has already initialized mustn't reset it
+    if !found {
+        std::ptr::write_bytes(shmem.cast::<u8>(), 0, size);
+    }
+    pg_sys::LWLockRelease(addin_shmem_init_lock);
+    shmem
+}
+
 unsafe impl PGRXSharedMemory for bool {}
 unsafe impl PGRXSharedMemory for char {}
 unsafe impl PGRXSharedMemory for str {}
@@ -0,0 +1,504 @@
+/*
+Portions Copyright 2019-2021 ZomboDB, LLC.
+Portions Copyright 2021-2022 Technology Concepts & Design, Inc. <support@tcdi.com>
+
+All rights reserved.
+
+Use of this source code is governed by the MIT license that can be found in the LICENSE file.
+*/
+
+//! Probabilistic data structures for approximate analytics
+//!
+//! [`BloomFilter`] answers "have I seen this value?", [`HyperLogLog`] estimates how many distinct
+//! values there have been, and [`CountMinSketch`] estimates how many times a value has been seen.
+//! Each takes values as Datums, hashed with [`hash_datum_extended()`], so values of any hashable
+//! type can be added, and two values that Postgres considers equal hash the same.  Values that are
+//! already hashed can be added with the `*_hash()` methods.
+//!
+//! Each serializes to a compact, versioned, byte representation, and converts to and from a
+//! `bytea` Datum, so it can be stored in a table, or be the state of an aggregate.
+//!
+//! ```rust,no_run
+//! use pgrx::prelude::*;
+//! use pgrx::sketch::HyperLogLog;
+//!
+//! #[pg_extern]
+//! fn approx_distinct(values: Array<String>) -> i64 {
+//!     let mut hll = HyperLogLog::new(12);
+//!     for value in values.iter().flatten() {
+//!         hll.add(value);
+//!     }
+//!     hll.estimate().round() as i64
+//! }
+//! ```
+use crate::hash::hash_datum_extended;
+use crate::{pg_sys, FromDatum, IntoDatum};
+use pgrx_sql_entity_graph::metadata::{
+    ArgumentError, Returns, ReturnsError, SqlMapping, SqlTranslatable,
+};
+
+/// The seed values are hashed with, so every structure agrees on a value's hash
+const SEED: u64 = 0;
+
+/// The version of the serialized format
+const FORMAT_VERSION: u8 = 1;
+
+/// Why serialized bytes couldn't be read back, or two structures couldn't be merged
+#[derive(thiserror::Error, Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SketchError {
+    #[error("the bytes are too short")]
+    Truncated,
+    #[error("the bytes are a {found}, not a {expected}")]
+    WrongKind { expected: &'static str, found: &'static str },
+    #[error("unsupported format version {0}")]
+    UnsupportedVersion(u8),
+    #[error("invalid parameters")]
+    InvalidParameters,
+    #[error("the structures have different parameters")]
+    Incompatible,
+}
+
+/// The hash of `value`, or `None` if it's NULL
+fn hash_value<T: IntoDatum>(value: T) -> Option<u64> {
+    let typoid = T::type_oid();
+    value.into_datum().map(|datum| hash_datum_extended(datum, typoid, SEED))
+}
+
+/// The `i`th of a family of hashes derived from one, as in Kirsch and Mitzenmacher's "Less Hashing,
+/// Same Performance"
+fn nth_hash(hash: u64, i: u64) -> u64 {
+    let (h1, h2) = (hash & 0xFFFF_FFFF, hash >> 32);
+    h1.wrapping_add(i.wrapping_mul(h2))
+}
+
+const KINDS: [(u8, &str); 3] =
+    [(b'B', "BloomFilter"), (b'H', "HyperLogLog"), (b'C', "CountMinSketch")];
+
+fn kind_name(tag: u8) -> &'static str {
+    KINDS.iter().find(|(t, _)| *t == tag).map(|(_, name)| *name).unwrap_or("unknown structure")
+}
+
+fn header(tag: u8) -> Vec<u8> {
+    vec![tag, FORMAT_VERSION]
+}
+
+/// Reads the fields of serialized bytes, in order
+struct Reader<'a> {
+    bytes: &'a [u8],
+}
+
+impl<'a> Reader<'a> {
+    fn new(bytes: &'a [u8], tag: u8) -> Result<Self, SketchError> {
+        let mut reader = Reader { bytes };
+        let found = reader.take(1)?[0];
+        if found != tag {
+            return Err(SketchError::WrongKind {
+                expected: kind_name(tag),
+                found: kind_name(found),
+            });
+        }
+        match reader.take(1)?[0] {
+            FORMAT_VERSION => Ok(reader),
+            version => Err(SketchError::UnsupportedVersion(version)),
+        }
+    }
+
+    fn take(&mut self, len: usize) -> Result<&'a [u8], SketchError> {
+        if self.bytes.len() < len {
+            return Err(SketchError::Truncated);
+        }
+        let (taken, rest) = self.bytes.split_at(len);
+        self.bytes = rest;
+        Ok(taken)
+    }
+
+    fn u32(&mut self) -> Result<u32, SketchError> {
+        Ok(u32::from_le_bytes(self.take(4)?.try_into().unwrap()))
+    }
+
+    fn u64(&mut self) -> Result<u64, SketchError> {
+        Ok(u64::from_le_bytes(self.take(8)?.try_into().unwrap()))
+    }
+
+    fn u64s(&mut self, count: usize) -> Result<Vec<u64>, SketchError> {
+        let bytes = self.take(count.checked_mul(8).ok_or(SketchError::Truncated)?)?;
+        Ok(bytes
+            .chunks_exact(8)
+            .map(|chunk| u64::from_le_bytes(chunk.try_into().unwrap()))
+            .collect())
+    }
+}
+
+/// A set of values that can tell, with some rate of false positives but no false negatives,
+/// whether a value is in it
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct BloomFilter {
+    bits: Vec<u64>,
+    hashes: u32,
+}
+
+impl BloomFilter {
+    /// A filter sized to hold `expected_items` values with a `false_positive_rate` chance of
+    /// reporting a value that isn't in it as though it is
+    ///
+    /// # Panics
+    ///
+    /// Panics unless `false_positive_rate` is between zero and one
+    pub fn new(expected_items: usize, false_positive_rate: f64) -> Self {
+        assert!(
+            false_positive_rate > 0.0 && false_positive_rate < 1.0,
+            "false_positive_rate must be between 0 and 1"
+        );
+        let items = expected_items.max(1) as f64;
+        let ln2 = std::f64::consts::LN_2;
+        let bits = (-items * false_positive_rate.ln() / (ln2 * ln2)).ceil().max(64.0);
+        let hashes = (bits / items * ln2).round().max(1.0);
+        BloomFilter::with_params(bits as u64, hashes as u32)
+    }
+
+    /// A filter of `bits` bits, rounded up to a multiple of 64, that sets `hashes` bits per value
+    ///
+    /// # Panics
+    ///
+    /// Panics if `bits` or `hashes` is zero
+    pub fn with_params(bits: u64, hashes: u32) -> Self {
+        assert!(bits > 0 && hashes > 0, "a bloom filter needs at least one bit and one hash");
+        BloomFilter { bits: vec![0; ((bits + 63) / 64) as usize], hashes }
+    }
+
+    /// The number of bits in the filter
+    pub fn bits(&self) -> u64 {
+        self.bits.len() as u64 * 64
+    }
+
+    /// The number of bits set per value
+    pub fn hashes(&self) -> u32 {
+        self.hashes
+    }
+
+    fn positions(&self, hash: u64) -> impl Iterator<Item = u64> {
+        let bits = self.bits();
+        (0..self.hashes as u64).map(move |i| nth_hash(hash, i) % bits)
+    }
+
+    /// Add `value` to the filter.  A NULL is ignored
+    pub fn insert<T: IntoDatum>(&mut self, value: T) {
+        if let Some(hash) = hash_value(value) {
+            self.insert_hash(hash)
+        }
+    }
+
+    /// Add `datum`, a value of type `typoid`, to the filter
+    pub fn insert_datum(&mut self, datum: pg_sys::Datum, typoid: pg_sys::Oid) {
+        self.insert_hash(hash_datum_extended(datum, typoid, SEED))
+    }
+
+    /// Add a value that's already been hashed to the filter
+    pub fn insert_hash(&mut self, hash: u64) {
+        for position in self.positions(hash).collect::<Vec<_>>() {
+            self.bits[(position / 64) as usize] |= 1 << (position % 64);
+        }
+    }
+
+    /// Might `value` be in the filter?  A NULL never is
+    pub fn contains<T: IntoDatum>(&self, value: T) -> bool {
+        hash_value(value).map_or(false, |hash| self.contains_hash(hash))
+    }
+
+    /// Might `datum`, a value of type `typoid`, be in the filter?
+    pub fn contains_datum(&self, datum: pg_sys::Datum, typoid: pg_sys::Oid) -> bool {
+        self.contains_hash(hash_datum_extended(datum, typoid, SEED))
+    }
+
+    /// Might a value that's already been hashed be in the filter?
+    pub fn contains_hash(&self, hash: u64) -> bool {
+        self.positions(hash)
+            .all(|position| self.bits[(position / 64) as usize] & (1 << (position % 64)) != 0)
+    }
+
+    /// Add every value in `other` to this filter
+    ///
+    /// # Errors
+    ///
+    /// Returns [`SketchError::Incompatible`] if the filters' sizes or number of hashes differ
+    pub fn union(&mut self, other: &BloomFilter) -> Result<(), SketchError> {
+        if self.bits.len() != other.bits.len() || self.hashes != other.hashes {
+            return Err(SketchError::Incompatible);
+        }
+        self.bits.iter_mut().zip(&other.bits).for_each(|(bits, other)| *bits |= other);
+        Ok(())
+    }
+
+    pub fn to_bytes(&self) -> Vec<u8> {
+        let mut bytes = header(b'B');
+        bytes.extend(self.hashes.to_le_bytes());
+        bytes.extend((self.bits.len() as u64).to_le_bytes());
+        self.bits.iter().for_each(|word| bytes.extend(word.to_le_bytes()));
+        bytes
+    }
+
+    pub fn from_bytes(bytes: &[u8]) -> Result<Self, SketchError> {
+        let mut reader = Reader::new(bytes, b'B')?;
+        let hashes = reader.u32()?;
+        let words = reader.u64()?;
+        let bits = reader.u64s(words.try_into().map_err(|_| SketchError::Truncated)?)?;
+        if hashes == 0 || bits.is_empty() {
+            return Err(SketchError::InvalidParameters);
+        }
+        Ok(BloomFilter { bits, hashes })
+    }
+}
+
+/// An estimate of the number of distinct values in a set, in a fixed amount of memory
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct HyperLogLog {
+    precision: u8,
+    registers: Vec<u8>,
+}
+
+impl HyperLogLog {
+    /// An estimator with `2^precision` registers.  Its standard error is about
+    /// `1.04 / sqrt(2^precision)`, so a `precision` of 12 uses 4KiB and is accurate to about 1.6%
+    ///
+    /// # Panics
+    ///
+    /// Panics unless `precision` is between 4 and 18
+    pub fn new(precision: u8) -> Self {
+        assert!((4..=18).contains(&precision), "precision must be between 4 and 18");
+        HyperLogLog { precision, registers: vec![0; 1 << precision] }
+    }
+
+    pub fn precision(&self) -> u8 {
+        self.precision
+    }
+
+    /// Count `value`.  A NULL is ignored
+    pub fn add<T: IntoDatum>(&mut self, value: T) {
+        if let Some(hash) = hash_value(value) {
+            self.add_hash(hash)
+        }
+    }
+
+    /// Count `datum`, a value of type `typoid`
+    pub fn add_datum(&mut self, datum: pg_sys::Datum, typoid: pg_sys::Oid) {
+        self.add_hash(hash_datum_extended(datum, typoid, SEED))
+    }
+
+    /// Count a value that's already been hashed
+    pub fn add_hash(&mut self, hash: u64) {
+        let p = self.precision as u32;
+        let index = (hash >> (64 - p)) as usize;
+        // the bit after the hash's remaining bits caps the rank
+        let rank = ((hash << p) | (1 << (p - 1))).leading_zeros() as u8 + 1;
+        self.registers[index] = self.registers[index].max(rank);
+    }
+
+    /// The estimated number of distinct values counted
+    pub fn estimate(&self) -> f64 {
+        let m = self.registers.len() as f64;
+        let alpha = match self.registers.len() {
+            16 => 0.673,
+            32 => 0.697,
+            64 => 0.709,
+            _ => 0.7213 / (1.0 + 1.079 / m),
+        };
+        let sum: f64 = self.registers.iter().map(|&rank| 2f64.powi(-(rank as i32))).sum();
+        let raw = alpha * m * m / sum;
+
+        let zeros = self.registers.iter().filter(|&&rank| rank == 0).count();
+        if raw <= 2.5 * m && zeros > 0 {
+            // linear counting is more accurate for small sets
+            m * (m / zeros as f64).ln()
+        } else {
+            raw
+        }
+    }
+
+    /// Count every value `other` has counted
+    ///
+    /// # Errors
+    ///
+    /// Returns [`SketchError::Incompatible`] if the estimators' precisions differ
+    pub fn merge(&mut self, other: &HyperLogLog) -> Result<(), SketchError> {
+        if self.precision != other.precision {
+            return Err(SketchError::Incompatible);
+        }
+        self.registers.iter_mut().zip(&other.registers).for_each(|(rank, &other)| {
+            *rank = (*rank).max(other);
+        });
+        Ok(())
+    }
+
+    pub fn to_bytes(&self) -> Vec<u8> {
+        let mut bytes = header(b'H');
+        bytes.push(self.precision);
+        bytes.extend(&self.registers);
+        bytes
+    }
+
+    pub fn from_bytes(bytes: &[u8]) -> Result<Self, SketchError> {
+        let mut reader = Reader::new(bytes, b'H')?;
+        let precision = reader.take(1)?[0];
+        if !(4..=18).contains(&precision) {
+            return Err(SketchError::InvalidParameters);
+        }
+        let registers = reader.take(1 << precision)?.to_vec();
+        Ok(HyperLogLog { precision, registers })
+    }
+}
+
+/// An estimate of how many times each value has been counted, which may overestimate but never
+/// underestimates
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct CountMinSketch {
+    width: u32,
+    depth: u32,
+    counters: Vec<u64>,
+}
+
+impl CountMinSketch {
+    /// A sketch whose estimates are, with probability `1 - delta`, within `epsilon` times the total
+    /// of all counts of the true count
+    ///
+    /// # Panics
+    ///
+    /// Panics unless `epsilon` and `delta` are between zero and one
+    pub fn new(epsilon: f64, delta: f64) -> Self {
+        assert!(epsilon > 0.0 && epsilon < 1.0, "epsilon must be between 0 and 1");
+        assert!(delta > 0.0 && delta < 1.0, "delta must be between 0 and 1");
+        let width = (std::f64::consts::E / epsilon).ceil();
+        let depth = (1.0 / delta).ln().ceil().max(1.0);
+        CountMinSketch::with_params(width as u32, depth as u32)
+    }
+
+    /// A sketch with `depth` rows of `width` counters
+    ///
+    /// # Panics
+    ///
+    /// Panics if `width` or `depth` is zero
+    pub fn with_params(width: u32, depth: u32) -> Self {
+        assert!(width > 0 && depth > 0, "a count-min sketch needs at least one counter");
+        CountMinSketch { width, depth, counters: vec![0; width as usize * depth as usize] }
+    }
+
+    pub fn width(&self) -> u32 {
+        self.width
+    }
+
+    pub fn depth(&self) -> u32 {
+        self.depth
+    }
+
+    fn cells(&self, hash: u64) -> impl Iterator<Item = usize> {
+        let width = self.width as u64;
+        (0..self.depth as u64).map(move |row| (row * width + nth_hash(hash, row) % width) as usize)
+    }
+
+    /// Count `value` `count` times.  A NULL is ignored
+    pub fn add<T: IntoDatum>(&mut self, value: T, count: u64) {
+        if let Some(hash) = hash_value(value) {
+            self.add_hash(hash, count)
+        }
+    }
+
+    /// Count `datum`, a value of type `typoid`, `count` times
+    pub fn add_datum(&mut self, datum: pg_sys::Datum, typoid: pg_sys::Oid, count: u64) {
+        self.add_hash(hash_datum_extended(datum, typoid, SEED), count)
+    }
+
+    /// Count a value that's already been hashed `count` times
+    pub fn add_hash(&mut self, hash: u64, count: u64) {
+        for cell in self.cells(hash).collect::<Vec<_>>() {
+            self.counters[cell] = self.counters[cell].saturating_add(count);
+        }
+    }
+
+    /// The estimated number of times `value` has been counted.  A NULL never has been
+    pub fn estimate<T: IntoDatum>(&self, value: T) -> u64 {
+        hash_value(value).map_or(0, |hash| self.estimate_hash(hash))
+    }
+
+    /// The estimated number of times `datum`, a value of type `typoid`, has been counted
+    pub fn estimate_datum(&self, datum: pg_sys::Datum, typoid: pg_sys::Oid) -> u64 {
+        self.estimate_hash(hash_datum_extended(datum, typoid, SEED))
+    }
+
+    /// The estimated number of times a value that's already been hashed has been counted
+    pub fn estimate_hash(&self, hash: u64) -> u64 {
+        self.cells(hash).map(|cell| self.counters[cell]).min().unwrap_or(0)
+    }
+
+    /// Add every count in `other` to this sketch
+    ///
+    /// # Errors
+    ///
+    /// Returns [`SketchError::Incompatible`] if the sketches' widths or depths differ
+    pub fn merge(&mut self, other: &CountMinSketch) -> Result<(), SketchError> {
+        if self.width != other.width || self.depth != other.depth {
+            return Err(SketchError::Incompatible);
+        }
+        self.counters.iter_mut().zip(&other.counters).for_each(|(count, &other)| {
+            *count = count.saturating_add(other);
+        });
+        Ok(())
+    }
+
+    pub fn to_bytes(&self) -> Vec<u8> {
+        let mut bytes = header(b'C');
+        bytes.extend(self.width.to_le_bytes());
+        bytes.extend(self.depth.to_le_bytes());
+        self.counters.iter().for_each(|count| bytes.extend(count.to_le_bytes()));
+        bytes
+    }
+
+    pub fn from_bytes(bytes: &[u8]) -> Result<Self, SketchError> {
+        let mut reader = Reader::new(bytes, b'C')?;
+        let width = reader.u32()?;
+        let depth = reader.u32()?;
+        if width == 0 || depth == 0 {
+            return Err(SketchError::InvalidParameters);
+        }
+        let counters = reader.u64s(width as usize * depth as usize)?;
+        Ok(CountMinSketch { width, depth, counters })
+    }
+}
+
+/// Each structure is stored as a `bytea`
+macro_rules! impl_bytea_datum {
+    ($($t:ty),+) => {$(
+        impl IntoDatum for $t {
+            fn into_datum(self) -> Option<pg_sys::Datum> {
+                self.to_bytes().into_datum()
+            }
+
+            fn type_oid() -> pg_sys::Oid {
+                pg_sys::BYTEAOID
+            }
+        }
+
+        impl FromDatum for $t {
+            unsafe fn from_polymorphic_datum(
+                datum: pg_sys::Datum,
+                is_null: bool,
+                typoid: pg_sys::Oid,
+            ) -> Option<Self> {
+                let bytes = <&[u8]>::from_polymorphic_datum(datum, is_null, typoid)?;
+                match <$t>::from_bytes(bytes) {
+                    Ok(value) => Some(value),
+                    Err(e) => panic!("invalid {}: {}", stringify!($t), e),
+                }
+            }
+        }
+
+        unsafe impl SqlTranslatable for $t {
+            fn argument_sql() -> Result<SqlMapping, ArgumentError> {
+                Ok(SqlMapping::literal("bytea"))
+            }
+            fn return_sql() -> Result<Returns, ReturnsError> {
+                Ok(Returns::One(SqlMapping::literal("bytea")))
+            }
+        }
+    )+};
+}
+
+impl_bytea_datum!(BloomFilter, HyperLogLog, CountMinSketch);
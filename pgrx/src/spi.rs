@@ -9,15 +9,28 @@ Use of this source code is governed by the MIT license that can be found in the
 
 //! Safe access to Postgres' *Server Programming Interface* (SPI).
 
-use crate::{pg_sys, FromDatum, IntoDatum, Json, PgMemoryContexts, PgOid, TryFromDatumError};
+use crate::callbacks::{
+    register_session_subxact_callback, register_session_xact_callback, PgSubXactCallbackEvent,
+    PgXactCallbackEvent,
+};
+use crate::memcxt::run_in_per_tuple_context;
+use crate::{
+    pg_sys, FromDatum, IntoDatum, Json, PgMemoryContexts, PgOid, PgRelation, PgSqlErrorCode,
+    PgTryBuilder, TryFromDatumError, Value,
+};
 use core::fmt::Formatter;
-use pgrx_pg_sys::panic::ErrorReportable;
+use pgrx_pg_sys::panic::{CaughtError, ErrorReportable};
+use serde::de::DeserializeOwned;
+use std::any::{Any, TypeId};
+use std::cell::RefCell;
+use std::collections::HashMap;
 use std::ffi::{CStr, CString};
 use std::fmt::Debug;
 use std::marker::PhantomData;
 use std::mem;
 use std::ops::{Deref, Index};
 use std::ptr::NonNull;
+use std::rc::Rc;
 
 pub type Result<T> = std::result::Result<T, Error>;
 
@@ -180,6 +193,18 @@ pub enum Error {
     /// The [`pg_sys::SPI_tuptable`] is null
     #[error("The active `SPI_tuptable` is NULL")]
     NoTupleTable,
+
+    /// A row could not be deserialized into the requested Rust type
+    #[error("Row deserialization error: {0}")]
+    DeserializeError(String),
+
+    /// A column is NULL, but the field of a [`FromSpiRow`] type it's decoded into isn't an `Option`
+    #[error("Column {0} is NULL, but its field isn't an Option")]
+    UnexpectedNull(String),
+
+    /// An ERROR was raised within a subtransaction, which was rolled back
+    #[error("Subtransaction rolled back: {message}")]
+    SubTransactionAborted { code: PgSqlErrorCode, message: String },
 }
 
 pub struct Spi;
@@ -270,6 +295,214 @@ impl SpiConnection {
     }
 }
 
+type PlanCache = HashMap<(String, Vec<pg_sys::Oid>), Rc<OwnedPreparedStatement>>;
+
+thread_local! {
+    /// Saved plans made by [`Spi::prepare_cached()`], keyed by their query and argument types
+    static PLAN_CACHE: RefCell<PlanCache> = RefCell::new(HashMap::new());
+}
+
+impl Spi {
+    /// Prepares a statement whose plan is saved until it's dropped, so it can be executed again
+    /// by later calls, or in later transactions, without being planned again
+    ///
+    /// ```rust,no_run
+    /// use pgrx::prelude::*;
+    /// # fn foo() -> spi::Result<()> {
+    /// let statement = Spi::prepare("SELECT $1 + 1", Some(vec![PgBuiltInOids::INT4OID.into()]))?;
+    /// for i in 0..10 {
+    ///     let next = Spi::connect(|client| {
+    ///         client.select(&statement, None, Some(vec![i.into_datum()]))?.first().get::<i32>(1)
+    ///     })?;
+    /// }
+    /// # Ok(())
+    /// # }
+    /// ```
+    ///
+    /// # Panics
+    ///
+    /// This function will panic if the supplied `query` string contained a NULL byte
+    pub fn prepare(query: &str, args: Option<Vec<PgOid>>) -> Result<OwnedPreparedStatement> {
+        Spi::connect(|client| client.prepare(query, args).map(PreparedStatement::keep))
+    }
+
+    /// Like [`Spi::prepare()`], but the statement is kept in a per-backend cache, so every call
+    /// with the same `query` and `args` after the first returns the same statement.  Postgres
+    /// plans it again only if something it depends on, like a table or the `search_path`, changes.
+    ///
+    /// This makes a `#[pg_extern]` function that runs the same query on every call plan it once
+    /// per session instead of once per call:
+    ///
+    /// ```rust,no_run
+    /// use pgrx::prelude::*;
+    ///
+    /// #[pg_extern]
+    /// fn lookup_name(id: i64) -> Result<Option<String>, spi::Error> {
+    ///     let statement = Spi::prepare_cached(
+    ///         "SELECT name FROM my_table WHERE id = $1",
+    ///         Some(vec![PgBuiltInOids::INT8OID.into()]),
+    ///     )?;
+    ///     Spi::connect(|client| {
+    ///         client.select(&*statement, Some(1), Some(vec![id.into_datum()]))?.first().get(1)
+    ///     })
+    /// }
+    /// ```
+    ///
+    /// # Panics
+    ///
+    /// This function will panic if the supplied `query` string contained a NULL byte
+    pub fn prepare_cached(
+        query: &str,
+        args: Option<Vec<PgOid>>,
+    ) -> Result<Rc<OwnedPreparedStatement>> {
+        let args = args.unwrap_or_default();
+        let key = (query.to_string(), args.iter().map(|oid| oid.value()).collect::<Vec<_>>());
+        if let Some(statement) = PLAN_CACHE.with(|cache| cache.borrow().get(&key).cloned()) {
+            return Ok(statement);
+        }
+
+        let statement = Rc::new(Spi::prepare(query, Some(args))?);
+        PLAN_CACHE.with(|cache| cache.borrow_mut().insert(key, statement.clone()));
+        Ok(statement)
+    }
+
+    /// Forget every statement [`Spi::prepare_cached()`] has cached.  Each one's plan is freed once
+    /// nothing else holds on to it.
+    pub fn clear_plan_cache() {
+        // the plans are freed after the cache is released
+        let plans = PLAN_CACHE.with(|cache| cache.take());
+        drop(plans);
+    }
+}
+
+/// The arguments of a query memoized by [`Spi::memoized()`], by their type and text
+type MemoArgs = Vec<(pg_sys::Oid, Option<String>)>;
+
+/// Results memoized by [`Spi::memoized()`], keyed by their query, its arguments, and the function
+/// and type they were converted with
+type MemoCache = HashMap<(String, MemoArgs, usize, TypeId), Box<dyn Any>>;
+
+thread_local! {
+    /// `None` until the invalidation callbacks are registered
+    static MEMO_CACHE: RefCell<Option<MemoCache>> = const { RefCell::new(None) };
+}
+
+impl Spi {
+    /// Run a read-only query, and convert its result with `f`, remembering that result for the rest
+    /// of the transaction:  every later call with the same `query`, `args` and `f` returns it
+    /// again, without running the query.
+    ///
+    /// This suits lookups repeated many times by a statement, of data the transaction doesn't
+    /// change itself:
+    ///
+    /// ```rust,no_run
+    /// use pgrx::prelude::*;
+    ///
+    /// #[pg_extern]
+    /// fn country_name(code: &str) -> Result<Option<String>, spi::Error> {
+    ///     Spi::memoized(
+    ///         "SELECT name FROM countries WHERE code = $1",
+    ///         Some(vec![(PgBuiltInOids::TEXTOID.oid(), code.into_datum())]),
+    ///         |table| table.first().get_one::<String>(),
+    ///     )
+    /// }
+    /// ```
+    ///
+    /// The results are forgotten when the transaction ends, when a subtransaction aborts, and when
+    /// any table, type, function or schema is created, altered or dropped.  Writes to the tables
+    /// the query reads are not noticed.
+    ///
+    /// # Panics
+    ///
+    /// This function will panic if the supplied `query` string contained a NULL byte
+    pub fn memoized<R: Clone + 'static>(
+        query: &str,
+        args: Option<Vec<(PgOid, Option<pg_sys::Datum>)>>,
+        f: fn(SpiTupleTable) -> Result<R>,
+    ) -> Result<R> {
+        let memo_args = args
+            .iter()
+            .flatten()
+            .map(|(oid, datum)| {
+                // SAFETY:  each argument is a Datum of the type it's paired with
+                let text = datum
+                    .map(|datum| unsafe { crate::datum::output_function_call(datum, oid.value()) });
+                (oid.value(), text)
+            })
+            .collect();
+        let key = (query.to_string(), memo_args, f as usize, TypeId::of::<R>());
+        let memoized = MEMO_CACHE.with(|cache| {
+            cache.borrow().as_ref().and_then(|cache| cache.get(&key)).map(|result| {
+                result.downcast_ref::<R>().expect("a memoized result of another type").clone()
+            })
+        });
+        if let Some(result) = memoized {
+            return Ok(result);
+        }
+
+        if MEMO_CACHE.with(|cache| cache.borrow().is_none()) {
+            register_memo_invalidation();
+            MEMO_CACHE.with(|cache| *cache.borrow_mut() = Some(HashMap::new()));
+        }
+
+        // the query can invalidate the cache, so it runs with the cache released
+        let result = Spi::connect(|client| f(client.select(query, None, args)?))?;
+        MEMO_CACHE.with(|cache| {
+            if let Some(cache) = cache.borrow_mut().as_mut() {
+                cache.insert(key, Box::new(result.clone()));
+            }
+        });
+        Ok(result)
+    }
+
+    /// Forget every result [`Spi::memoized()`] has remembered
+    pub fn clear_memoized() {
+        // the results are dropped after the cache is released
+        let results = MEMO_CACHE.with(|cache| cache.borrow_mut().as_mut().map(mem::take));
+        drop(results);
+    }
+}
+
+/// Clear the memoized results whenever they may be out of date
+fn register_memo_invalidation() {
+    unsafe extern "C" fn relcache_invalidated(_arg: pg_sys::Datum, _relid: pg_sys::Oid) {
+        Spi::clear_memoized();
+    }
+
+    unsafe extern "C" fn syscache_invalidated(_arg: pg_sys::Datum, _cacheid: i32, _hashvalue: u32) {
+        Spi::clear_memoized();
+    }
+
+    unsafe {
+        pg_sys::CacheRegisterRelcacheCallback(Some(relcache_invalidated), pg_sys::Datum::from(0));
+        for cacheid in [
+            pg_sys::SysCacheIdentifier_TYPEOID,
+            pg_sys::SysCacheIdentifier_PROCOID,
+            pg_sys::SysCacheIdentifier_NAMESPACEOID,
+        ] {
+            pg_sys::CacheRegisterSyscacheCallback(
+                cacheid as _,
+                Some(syscache_invalidated),
+                pg_sys::Datum::from(0),
+            );
+        }
+    }
+
+    register_session_xact_callback(|event| match event {
+        PgXactCallbackEvent::Commit | PgXactCallbackEvent::Abort | PgXactCallbackEvent::Prepare => {
+            Spi::clear_memoized()
+        }
+        _ => {}
+    })
+    .leak();
+    register_session_subxact_callback(|event, _subxact, _parent| {
+        if event == PgSubXactCallbackEvent::AbortSub {
+            Spi::clear_memoized()
+        }
+    })
+    .leak();
+}
+
 /// A generalized interface to what constitutes a query
 ///
 /// Its primary purpose is to abstract away differences between
@@ -382,29 +615,41 @@ impl<'a> Query for &'a str {
         _client: &'cc SpiClient<'c>,
         args: Self::Arguments,
     ) -> SpiCursor<'c> {
-        let src = CString::new(self).expect("query contained a null byte");
-        let args = args.unwrap_or_default();
-
-        let nargs = args.len();
-        let (types, data): (Vec<_>, Vec<_>) = args.into_iter().unzip();
-        let mut argtypes = types.into_iter().map(PgOid::value).collect::<Vec<_>>();
-        let (mut datums, nulls): (Vec<_>, Vec<_>) = data.into_iter().map(prepare_datum).unzip();
+        SpiCursor { ptr: open_cursor_with_options(self, args, 0), __marker: PhantomData }
+    }
+}
 
-        let ptr = unsafe {
-            // SAFETY: arguments are prepared above and SPI_cursor_open_with_args will never return
-            // the null pointer.  It'll raise an ERROR if something is invalid for it to create the cursor
-            NonNull::new_unchecked(pg_sys::SPI_cursor_open_with_args(
-                std::ptr::null_mut(), // let postgres assign a name
-                src.as_ptr(),
-                nargs as i32,
-                argtypes.as_mut_ptr(),
-                datums.as_mut_ptr(),
-                nulls.as_ptr(),
-                Spi::is_xact_still_immutable(),
-                0,
-            ))
-        };
-        SpiCursor { ptr, __marker: PhantomData }
+/// Open a cursor for `query` using the specified `CURSOR_OPT_*` flags
+///
+/// # Panics
+///
+/// This function will panic if somehow the specified query contains a null byte.
+fn open_cursor_with_options(
+    query: &str,
+    args: Option<Vec<(PgOid, Option<pg_sys::Datum>)>>,
+    cursor_options: i32,
+) -> NonNull<pg_sys::PortalData> {
+    let src = CString::new(query).expect("query contained a null byte");
+    let args = args.unwrap_or_default();
+
+    let nargs = args.len();
+    let (types, data): (Vec<_>, Vec<_>) = args.into_iter().unzip();
+    let mut argtypes = types.into_iter().map(PgOid::value).collect::<Vec<_>>();
+    let (mut datums, nulls): (Vec<_>, Vec<_>) = data.into_iter().map(prepare_datum).unzip();
+
+    unsafe {
+        // SAFETY: arguments are prepared above and SPI_cursor_open_with_args will never return
+        // the null pointer.  It'll raise an ERROR if something is invalid for it to create the cursor
+        NonNull::new_unchecked(pg_sys::SPI_cursor_open_with_args(
+            std::ptr::null_mut(), // let postgres assign a name
+            src.as_ptr(),
+            nargs as i32,
+            argtypes.as_mut_ptr(),
+            datums.as_mut_ptr(),
+            nulls.as_ptr(),
+            Spi::is_xact_still_immutable(),
+            cursor_options,
+        ))
     }
 }
 
@@ -430,6 +675,54 @@ pub struct SpiHeapTupleData {
     entries: Vec<SpiHeapTupleDataEntry>,
 }
 
+/// A single row of an SPI result
+pub type SpiRow = SpiHeapTupleData;
+
+/// Describes a single column of an SPI result
+#[derive(Debug, Clone, PartialEq)]
+pub struct SpiColumn {
+    ordinal: usize,
+    name: String,
+    type_oid: PgOid,
+    typmod: i32,
+}
+
+impl SpiColumn {
+    /// # Safety
+    ///
+    /// `tupdesc` must be a valid pointer and `ordinal` must be in bounds for it
+    unsafe fn from_tupdesc(tupdesc: pg_sys::TupleDesc, ordinal: usize) -> Self {
+        let attr = &(*tupdesc).attrs.as_slice((*tupdesc).natts as usize)[ordinal - 1];
+        SpiColumn {
+            ordinal,
+            name: attr.name().to_string(),
+            type_oid: attr.type_oid(),
+            typmod: attr.type_mod(),
+        }
+    }
+
+    /// The 1-based position of this column
+    pub fn ordinal(&self) -> usize {
+        self.ordinal
+    }
+
+    /// The name of this column
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+
+    /// The type oid of this column
+    pub fn type_oid(&self) -> PgOid {
+        self.type_oid
+    }
+
+    /// The type modifier of this column, such as the length of a `varchar(n)`.  `-1` if
+    /// the type has no modifier
+    pub fn typmod(&self) -> i32 {
+        self.typmod
+    }
+}
+
 impl Spi {
     pub fn get_one<A: FromDatum + IntoDatum>(query: &str) -> Result<Option<A>> {
         Spi::connect(|mut client| client.update(query, Some(1), None)?.first().get_one())
@@ -482,6 +775,39 @@ impl Spi {
         })
     }
 
+    /// Run a query and deserialize each of its result rows into a `T`, matching columns to
+    /// fields by name.
+    ///
+    /// # Examples
+    ///
+    /// ```rust,no_run
+    /// use pgrx::prelude::*;
+    /// #[derive(serde::Deserialize)]
+    /// struct Person {
+    ///     name: String,
+    ///     age: Option<i32>,
+    /// }
+    /// # fn foo() -> spi::Result<()> {
+    /// let people = Spi::get_as::<Person>("SELECT 'Bob' AS name, 42 AS age")?;
+    /// assert_eq!(people[0].name, "Bob");
+    /// # Ok(())
+    /// # }
+    /// ```
+    ///
+    /// See [`SpiHeapTupleData::deserialize()`] for how column values are converted.
+    pub fn get_as<T: DeserializeOwned>(query: &str) -> Result<Vec<T>> {
+        Spi::get_as_with_args(query, None)
+    }
+
+    /// Run a query with args and deserialize each of its result rows into a `T`, matching
+    /// columns to fields by name.
+    pub fn get_as_with_args<T: DeserializeOwned>(
+        query: &str,
+        args: Option<Vec<(PgOid, Option<pg_sys::Datum>)>>,
+    ) -> Result<Vec<T>> {
+        Spi::connect(|mut client| client.update(query, None, args)?.deserialize_rows().collect())
+    }
+
     /// just run an arbitrary SQL statement.
     ///
     /// ## Safety
@@ -522,6 +848,269 @@ impl Spi {
         .unwrap())
     }
 
+    /// Run a query and export its result as a JSON array of objects, one per row, keyed by
+    /// column name.
+    ///
+    /// Values are the text from their types' output functions, so they're formatted exactly as
+    /// Postgres would, and are written as JSON strings, except that, like Postgres'
+    /// `row_to_json()`:
+    ///
+    /// - `NULL`s are `null`
+    /// - `bool`s are `true` or `false`
+    /// - integers, floats, and `numeric`s are numbers, unless they're `NaN` or infinite
+    /// - `json` and `jsonb` values are embedded as they are
+    ///
+    /// The rows are fetched [`Spi::EXPORT_FETCH_SIZE`] at a time, so the whole result set is
+    /// never in memory at once, only its JSON.
+    ///
+    /// ```rust,no_run
+    /// use pgrx::prelude::*;
+    /// # fn foo() -> spi::Result<()> {
+    /// let json = Spi::query_to_json("SELECT 1 AS id, 'Bob' AS name")?;
+    /// assert_eq!(json, r#"[{"id":1,"name":"Bob"}]"#);
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn query_to_json(query: &str) -> Result<String> {
+        Spi::query_to_json_with_args(query, None)
+    }
+
+    /// Run a query with args and export its result as a JSON array of objects.  See
+    /// [`Spi::query_to_json()`].
+    pub fn query_to_json_with_args(
+        query: &str,
+        args: Option<Vec<(PgOid, Option<pg_sys::Datum>)>>,
+    ) -> Result<String> {
+        let (mut json, _) = Spi::export_rows(
+            query,
+            args,
+            |columns| {
+                let keys = columns
+                    .iter()
+                    .map(|column| {
+                        let key = serde_json::to_string(column.name()).unwrap();
+                        (key, JsonKind::of(column.type_oid().value()))
+                    })
+                    .collect::<Vec<_>>();
+                (String::from("["), keys)
+            },
+            |(json, keys), values| {
+                if json.len() > 1 {
+                    json.push(',');
+                }
+                json.push('{');
+                for (i, ((key, kind), value)) in keys.iter().zip(values).enumerate() {
+                    if i > 0 {
+                        json.push(',');
+                    }
+                    json.push_str(key);
+                    json.push(':');
+                    match value {
+                        None => json.push_str("null"),
+                        Some(value) => kind.write(json, value),
+                    }
+                }
+                json.push('}');
+            },
+        )?;
+        json.push(']');
+        Ok(json)
+    }
+
+    /// Run a query and export its result as CSV, as described by RFC 4180.
+    ///
+    /// The first line is a header of the column names.  Values are the text from their types'
+    /// output functions, so they're formatted exactly as Postgres would.  Fields are quoted if
+    /// they contain a comma, a double quote, or a line break, and double quotes within them are
+    /// doubled.  Lines end with CRLF.  Like Postgres' `COPY ... (FORMAT csv)`, `NULL`s are empty
+    /// fields and empty strings are quoted, `""`, so the two can be told apart.
+    ///
+    /// The rows are fetched [`Spi::EXPORT_FETCH_SIZE`] at a time, so the whole result set is
+    /// never in memory at once, only its CSV.
+    ///
+    /// ```rust,no_run
+    /// use pgrx::prelude::*;
+    /// # fn foo() -> spi::Result<()> {
+    /// let csv = Spi::query_to_csv("SELECT 1 AS id, 'Bob, Jr.' AS name")?;
+    /// assert_eq!(csv, "id,name\r\n1,\"Bob, Jr.\"\r\n");
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn query_to_csv(query: &str) -> Result<String> {
+        Spi::query_to_csv_with_args(query, None)
+    }
+
+    /// Run a query with args and export its result as CSV.  See [`Spi::query_to_csv()`].
+    pub fn query_to_csv_with_args(
+        query: &str,
+        args: Option<Vec<(PgOid, Option<pg_sys::Datum>)>>,
+    ) -> Result<String> {
+        Spi::export_rows(
+            query,
+            args,
+            |columns| {
+                let mut csv = String::new();
+                for (i, column) in columns.iter().enumerate() {
+                    if i > 0 {
+                        csv.push(',');
+                    }
+                    write_csv_field(&mut csv, column.name());
+                }
+                csv.push_str("\r\n");
+                csv
+            },
+            |csv, values| {
+                for (i, value) in values.iter().enumerate() {
+                    if i > 0 {
+                        csv.push(',');
+                    }
+                    if let Some(value) = value {
+                        write_csv_field(csv, value);
+                    }
+                }
+                csv.push_str("\r\n");
+            },
+        )
+    }
+
+    /// The number of rows [`Spi::query_to_json()`] and [`Spi::query_to_csv()`] fetch at a time
+    pub const EXPORT_FETCH_SIZE: libc::c_long = 1000;
+
+    /// Run `query` through a cursor, calling `start` with its columns for the state of the export,
+    /// and then `row` with that state and the text of each row's values from their types' output
+    /// functions, `None` for `NULL`s.  Each batch of rows is freed once it's been exported.
+    fn export_rows<S>(
+        query: &str,
+        args: Option<Vec<(PgOid, Option<pg_sys::Datum>)>>,
+        start: impl FnOnce(&[SpiColumn]) -> S,
+        mut row: impl FnMut(&mut S, &[Option<String>]),
+    ) -> Result<S> {
+        Spi::connect(|client| {
+            let mut cursor = client.open_cursor(query, args);
+            let mut table = cursor.fetch(Spi::EXPORT_FETCH_SIZE)?;
+            let mut state = start(&table.column_metadata()?);
+            let mut values = Vec::new();
+            loop {
+                let fetched = table.len();
+                while let Some(tuple) = table.next() {
+                    values.clear();
+                    values.extend(tuple.entries.iter().map(|entry| {
+                        // SAFETY:  the datum and its type oid came from the same heap tuple
+                        entry.datum.map(|datum| unsafe {
+                            crate::datum::output_function_call(datum, entry.type_oid)
+                        })
+                    }));
+                    row(&mut state, &values);
+                }
+                if let Some(tuptable) = table.table.take() {
+                    // SAFETY:  nothing refers to the rows anymore, as their values are now text
+                    unsafe { pg_sys::SPI_freetuptable(tuptable) }
+                }
+
+                // a short fetch means the cursor has run off the end of its rows
+                if (fetched as libc::c_long) < Spi::EXPORT_FETCH_SIZE {
+                    return Ok(state);
+                }
+                table = cursor.fetch(Spi::EXPORT_FETCH_SIZE)?;
+            }
+        })
+    }
+
+    /// Insert `rows` into `relation`, [`Spi::DEFAULT_BATCH_SIZE`] at a time, returning the number
+    /// of rows inserted.
+    ///
+    /// Each batch is a single multi-row `INSERT ... VALUES` statement, prepared once and reused,
+    /// which is much faster than running an `INSERT` for each row.  Each row must have a value for
+    /// every column of the table, in column order.
+    ///
+    /// ```rust,no_run
+    /// use pgrx::prelude::*;
+    /// use pgrx::PgRelation;
+    ///
+    /// # fn foo() -> spi::Result<()> {
+    /// let relation = PgRelation::open_with_name_and_share_lock("people").unwrap();
+    /// let people = vec![(1, "Bob".to_string()), (2, "Alice".to_string())];
+    /// assert_eq!(Spi::insert_batch(&relation, people)?, 2);
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn insert_batch<R: IntoComposite, I: IntoIterator<Item = R>>(
+        relation: &PgRelation,
+        rows: I,
+    ) -> Result<u64> {
+        Spi::insert_batch_with_size(relation, rows, Spi::DEFAULT_BATCH_SIZE)
+    }
+
+    /// The number of rows [`Spi::insert_batch()`] inserts with each statement
+    pub const DEFAULT_BATCH_SIZE: usize = 1000;
+
+    /// Like [`Spi::insert_batch()`], inserting up to `batch_size` rows with each statement.  Larger
+    /// batches mean fewer statements, but more memory for each one.  Postgres allows at most 65535
+    /// parameters per statement, so the batch size is reduced to fit if need be
+    pub fn insert_batch_with_size<R: IntoComposite, I: IntoIterator<Item = R>>(
+        relation: &PgRelation,
+        rows: I,
+        batch_size: usize,
+    ) -> Result<u64> {
+        const MAX_PARAMETERS: usize = u16::MAX as usize;
+
+        let types = R::type_oids();
+        let ncolumns = types.len();
+        assert!(ncolumns > 0, "rows must have at least one column");
+        let batch_size = batch_size.clamp(1, MAX_PARAMETERS / ncolumns);
+        let table = quote_qualified_identifier(relation.namespace(), relation.name());
+
+        let insert = |nrows: usize| {
+            let values = (0..nrows)
+                .map(|row| {
+                    let params = (1..=ncolumns)
+                        .map(|column| format!("${}", row * ncolumns + column))
+                        .collect::<Vec<_>>();
+                    format!("({})", params.join(", "))
+                })
+                .collect::<Vec<_>>();
+            format!("INSERT INTO {table} VALUES {}", values.join(", "))
+        };
+        let arg_types =
+            |nrows: usize| types.iter().copied().cycle().take(nrows * ncolumns).collect::<Vec<_>>();
+
+        Spi::connect(|client| {
+            Spi::mark_mutable();
+            let mut full_batch = None;
+            let mut rows = rows.into_iter();
+            let mut inserted = 0;
+            loop {
+                // each batch's datums are freed once it's inserted
+                let nrows = run_in_per_tuple_context(|| {
+                    let mut args = Vec::with_capacity(batch_size * ncolumns);
+                    let mut nrows = 0;
+                    for row in rows.by_ref().take(batch_size) {
+                        args.extend(row.into_datums());
+                        nrows += 1;
+                    }
+
+                    if nrows == batch_size {
+                        if full_batch.is_none() {
+                            full_batch =
+                                Some(client.prepare(&insert(nrows), Some(arg_types(nrows)))?);
+                        }
+                        client.execute(full_batch.as_ref().unwrap(), None, Some(args))?;
+                    } else if nrows > 0 {
+                        // the last batch is short, so needs a statement of its own
+                        let partial = client.prepare(&insert(nrows), Some(arg_types(nrows)))?;
+                        client.execute(&partial, None, Some(args))?;
+                    }
+                    Ok::<_, Error>(nrows)
+                })?;
+
+                inserted += nrows as u64;
+                if nrows < batch_size {
+                    return Ok(inserted);
+                }
+            }
+        })
+    }
+
     /// Execute SPI commands via the provided `SpiClient`.
     ///
     /// While inside the provided closure, code executes under a short-lived "SPI Memory Context",
@@ -572,6 +1161,7 @@ impl Spi {
         // otherwise this function would need to return a `Result<R, spi::Error>` and that's a
         // fucking nightmare for users to deal with.  There's ample discussion around coming to
         // this decision at https://github.com/tcdi/pgrx/pull/977
+        crate::volatility::check_volatility("uses SPI");
         let connection =
             SpiConnection::connect().expect("SPI_connect indicated an unexpected failure");
 
@@ -582,6 +1172,31 @@ impl Spi {
         f(connection.client())
     }
 
+    /// Connect to SPI, and run `f` in a subtransaction of the current transaction.  See
+    /// [`SpiClient::subtransaction()`] for how the subtransaction is committed or rolled back.
+    ///
+    /// # Examples
+    ///
+    /// ```rust,no_run
+    /// use pgrx::prelude::*;
+    /// # fn foo() -> spi::Result<()> {
+    /// let inserted = Spi::transaction(|mut client| {
+    ///     client.update("INSERT INTO t VALUES (1)", None, None)?;
+    ///     // if this raises an ERROR, the row inserted above is rolled back too
+    ///     client.update("INSERT INTO t VALUES (1 / 0)", None, None)
+    /// });
+    /// assert!(matches!(inserted, Err(spi::Error::SubTransactionAborted { .. })));
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn transaction<R, E, F>(f: F) -> std::result::Result<R, E>
+    where
+        E: From<Error>,
+        F: FnOnce(SpiClient<'_>) -> std::result::Result<R, E> + std::panic::UnwindSafe,
+    {
+        Spi::connect(|client| client.subtransaction(f))
+    }
+
     #[track_caller]
     pub fn check_status(status_code: i32) -> std::result::Result<SpiOkCodes, Error> {
         match SpiOkCodes::try_from(status_code) {
@@ -592,7 +1207,110 @@ impl Spi {
     }
 }
 
+/// How [`Spi::query_to_json()`] writes the values of a type
+#[derive(Debug, Copy, Clone)]
+enum JsonKind {
+    Bool,
+    Number,
+    Json,
+    String,
+}
+
+impl JsonKind {
+    fn of(type_oid: pg_sys::Oid) -> Self {
+        // SAFETY:  `getBaseType()` returns `type_oid` itself when it isn't a domain
+        match unsafe { pg_sys::getBaseType(type_oid) } {
+            pg_sys::BOOLOID => JsonKind::Bool,
+            pg_sys::INT2OID
+            | pg_sys::INT4OID
+            | pg_sys::INT8OID
+            | pg_sys::FLOAT4OID
+            | pg_sys::FLOAT8OID
+            | pg_sys::NUMERICOID => JsonKind::Number,
+            pg_sys::JSONOID | pg_sys::JSONBOID => JsonKind::Json,
+            _ => JsonKind::String,
+        }
+    }
+
+    /// Write `value`, the text from its type's output function, to `json`
+    fn write(self, json: &mut String, value: &str) {
+        match self {
+            JsonKind::Bool => json.push_str(if value == "t" { "true" } else { "false" }),
+            JsonKind::Number if is_json_number(value) => json.push_str(value),
+            JsonKind::Json => json.push_str(value),
+            _ => json.push_str(&serde_json::to_string(value).unwrap()),
+        }
+    }
+}
+
+/// Is the output of a numeric type a JSON number?  `NaN` and `Infinity` aren't
+fn is_json_number(value: &str) -> bool {
+    value.starts_with(|c: char| c == '-' || c.is_ascii_digit())
+        && value
+            .bytes()
+            .all(|b| b.is_ascii_digit() || matches!(b, b'-' | b'+' | b'.' | b'e' | b'E'))
+}
+
+/// Write `value` to `csv` as a field, quoted if it's empty or contains a comma, a double quote,
+/// or a line break
+fn write_csv_field(csv: &mut String, value: &str) {
+    if value.is_empty() || value.contains(|c| matches!(c, ',' | '"' | '\r' | '\n')) {
+        csv.push('"');
+        csv.push_str(&value.replace('"', "\"\""));
+        csv.push('"');
+    } else {
+        csv.push_str(value);
+    }
+}
+
 impl<'a> SpiClient<'a> {
+    /// Run `f` in a subtransaction of the current transaction, with `BeginInternalSubTransaction()`.
+    /// Subtransactions can be nested, by calling this on the client `f` is given.
+    ///
+    /// If `f` returns `Ok`, the subtransaction is committed, so its changes become part of the
+    /// current transaction.  If it returns `Err`, or raises an ERROR, the subtransaction is rolled
+    /// back, undoing its changes, and the current transaction carries on.  A raised ERROR is
+    /// returned as an [`Error::SubTransactionAborted`].  A Rust panic also rolls the subtransaction
+    /// back, and then continues to unwind.
+    pub fn subtransaction<R, E, F>(&self, f: F) -> std::result::Result<R, E>
+    where
+        E: From<Error>,
+        F: FnOnce(SpiClient<'a>) -> std::result::Result<R, E> + std::panic::UnwindSafe,
+    {
+        unsafe {
+            // SAFETY:  being connected to SPI means we're in a transaction.  Like PL/pgSQL, we
+            // stay in the caller's memory context while in the subtransaction, and restore the
+            // caller's resource owner when we leave it
+            let memcxt = pg_sys::CurrentMemoryContext;
+            let owner = pg_sys::CurrentResourceOwner;
+            pg_sys::BeginInternalSubTransaction(std::ptr::null());
+            pg_sys::CurrentMemoryContext = memcxt;
+
+            let client = SpiClient { __marker: PhantomData };
+            let result = PgTryBuilder::new(move || Ok(f(client)))
+                .catch_others(|caught| Err(caught))
+                .execute();
+
+            match result {
+                Ok(Ok(_)) => pg_sys::ReleaseCurrentSubTransaction(),
+                _ => pg_sys::RollbackAndReleaseCurrentSubTransaction(),
+            }
+            pg_sys::CurrentMemoryContext = memcxt;
+            pg_sys::CurrentResourceOwner = owner;
+
+            match result {
+                Ok(result) => result,
+                Err(CaughtError::PostgresError(ereport))
+                | Err(CaughtError::ErrorReport(ereport)) => Err(Error::SubTransactionAborted {
+                    code: ereport.sql_error_code(),
+                    message: ereport.message().to_string(),
+                }
+                .into()),
+                Err(panic @ CaughtError::RustPanic { .. }) => panic.rethrow(),
+            }
+        }
+    }
+
     /// perform a SELECT statement
     pub fn select<Q: Query>(
         &self,
@@ -668,6 +1386,81 @@ impl<'a> SpiClient<'a> {
         query.open_cursor(&self, args)
     }
 
+    /// Set up a holdable (`WITH HOLD`) cursor that will execute the specified query
+    ///
+    /// Unlike cursors from [`SpiClient::open_cursor()`], a holdable cursor survives the commit of
+    /// the transaction that created it.  Use [`SpiCursor::detach_into_name()`] to keep it open,
+    /// and [`SpiClient::find_cursor()`] to retrieve it in a later transaction of the same session
+    /// (or background worker), which makes it suitable for processing a large table in chunks,
+    /// committing between each.
+    ///
+    /// When the creating transaction commits, Postgres runs the remainder of the query and stores
+    /// its results, so later fetches read from that snapshot.  If the creating transaction aborts,
+    /// the cursor is closed.  A detached holdable cursor stays open until it's dropped or the
+    /// session ends.
+    ///
+    /// # Examples
+    ///
+    /// ```rust,no_run
+    /// use pgrx::prelude::*;
+    /// # fn foo() -> spi::Result<()> {
+    /// let cursor_name = Spi::connect(|client| {
+    ///     client
+    ///         .open_holdable_cursor("SELECT * FROM generate_series(1, 1000000)", None)
+    ///         .detach_into_name()
+    /// });
+    ///
+    /// // ... then, in a later transaction ...
+    /// Spi::connect(|client| {
+    ///     let mut cursor = client.find_cursor(&cursor_name)?;
+    ///     let chunk = cursor.fetch(1000)?;
+    ///     // process `chunk`, and detach the cursor again if there's more to do
+    ///     Ok::<_, spi::Error>(())
+    /// })
+    /// # }
+    /// ```
+    ///
+    /// # Panics
+    ///
+    /// This function will panic if somehow the specified query contains a null byte.
+    pub fn open_holdable_cursor(
+        &self,
+        query: &str,
+        args: Option<Vec<(PgOid, Option<pg_sys::Datum>)>>,
+    ) -> SpiCursor {
+        let ptr = open_cursor_with_options(query, args, pg_sys::CURSOR_OPT_HOLD as i32);
+        SpiCursor { ptr, __marker: PhantomData }
+    }
+
+    /// Run a read-only query, handing each row to `receiver` as it's produced instead of
+    /// collecting the rows into an [`SpiTupleTable`].  Returns the number of rows received, which
+    /// is less than the query would produce if the receiver stopped it early.
+    ///
+    /// See [`RowReceiver`](crate::dest_receiver::RowReceiver) for an example.
+    ///
+    /// # Panics
+    ///
+    /// This function will panic if somehow the specified query contains a null byte.
+    pub fn select_into(
+        &self,
+        query: &str,
+        args: Option<Vec<(PgOid, Option<pg_sys::Datum>)>>,
+        receiver: &mut crate::dest_receiver::RowReceiver,
+    ) -> Result<u64> {
+        let mut portal = open_cursor_with_options(query, args, 0);
+        unsafe {
+            // SAFETY:  the portal was just created, and the receiver outlives this call
+            pg_sys::PortalRunFetch(
+                portal.as_mut(),
+                pg_sys::FetchDirection_FETCH_FORWARD,
+                libc::c_long::MAX,
+                receiver.as_dest_receiver(),
+            );
+            pg_sys::SPI_cursor_close(portal.as_mut());
+        }
+        Ok(receiver.rows())
+    }
+
     /// Find a cursor in transaction by name
     ///
     /// A cursor for a query can be opened using [`SpiClient::open_cursor`].
@@ -682,6 +1475,52 @@ impl<'a> SpiClient<'a> {
             .ok_or(Error::CursorNotFound(name.to_string()))?;
         Ok(SpiCursor { ptr, __marker: PhantomData })
     }
+    /// Fetch rows from the cursor named `name`, leaving it open afterwards
+    ///
+    /// Unlike [`SpiClient::find_cursor()`], this is meant for cursors that belong to someone else,
+    /// such as ones `DECLARE`d by the client, so it never closes the cursor.  `direction` says
+    /// which rows to fetch, as the corresponding `FETCH` command would.  Anything but
+    /// [`FetchDirection::Forward`] requires a `SCROLL` cursor.
+    ///
+    /// See also [`portal::active_portals()`](crate::portal::active_portals).
+    pub fn fetch_from_cursor(
+        &self,
+        name: &str,
+        direction: FetchDirection,
+    ) -> Result<SpiTupleTable> {
+        use pgrx_pg_sys::AsPgCStr;
+
+        let portal = unsafe { pg_sys::SPI_cursor_find(name.as_pg_cstr()) };
+        if portal.is_null() {
+            return Err(Error::CursorNotFound(name.to_string()));
+        }
+
+        let (direction, count) = match direction {
+            FetchDirection::Forward(count) => (pg_sys::FetchDirection_FETCH_FORWARD, count),
+            FetchDirection::Backward(count) => (pg_sys::FetchDirection_FETCH_BACKWARD, count),
+            FetchDirection::Absolute(row) => (pg_sys::FetchDirection_FETCH_ABSOLUTE, row),
+            FetchDirection::Relative(offset) => (pg_sys::FetchDirection_FETCH_RELATIVE, offset),
+        };
+        unsafe {
+            // SAFETY: no concurrent access, and we just found the portal
+            pg_sys::SPI_tuptable = std::ptr::null_mut();
+            pg_sys::SPI_scroll_cursor_fetch(portal, direction, count);
+        }
+        SpiClient::prepare_tuple_table(SpiOkCodes::Fetch as i32)
+    }
+}
+
+/// Which rows [`SpiClient::fetch_from_cursor()`] fetches, like the direction of a `FETCH` command
+#[derive(Debug, Copy, Clone, Eq, PartialEq)]
+pub enum FetchDirection {
+    /// The next `n` rows
+    Forward(libc::c_long),
+    /// The previous `n` rows
+    Backward(libc::c_long),
+    /// The `n`th row, counting from one, or from the end if it's negative
+    Absolute(libc::c_long),
+    /// The `n`th row after the current one, or before it if it's negative
+    Relative(libc::c_long),
 }
 
 type CursorName = String;
@@ -695,7 +1534,8 @@ type CursorName = String;
 /// A cursor can be created via [`SpiClient::open_cursor()`] from a query.
 /// Cursors are automatically closed on drop, unless explicitly left open using
 /// [`Self::detach_into_name()`], which returns the cursor name; cursors left open can be retrieved
-/// by name (in the same transaction) via [`SpiClient::find_cursor()`].
+/// by name (in the same transaction) via [`SpiClient::find_cursor()`].  Holdable cursors, created
+/// via [`SpiClient::open_holdable_cursor()`], can also be retrieved in later transactions.
 ///
 /// # Important notes about memory usage
 /// Result sets ([`SpiTupleTable`]s) returned by [`SpiCursor::fetch()`] will not be freed until
@@ -760,9 +1600,19 @@ impl SpiCursor<'_> {
         Ok(SpiClient::prepare_tuple_table(SpiOkCodes::Fetch as i32)?)
     }
 
+    /// Was this cursor created `WITH HOLD`, so that it outlives its transaction?
+    ///
+    /// See [`SpiClient::open_holdable_cursor()`].
+    pub fn is_holdable(&self) -> bool {
+        // SAFETY: SPI functions to create/find cursors fail via elog, so self.ptr is valid if we successfully set it
+        let cursor_options = unsafe { self.ptr.as_ref().cursorOptions };
+        cursor_options & pg_sys::CURSOR_OPT_HOLD as i32 != 0
+    }
+
     /// Consume the cursor, returning its name
     ///
-    /// The actual Postgres cursor is kept alive for the duration of the transaction.
+    /// The actual Postgres cursor is kept alive for the duration of the transaction, or, for
+    /// holdable cursors, until it's found again and dropped.
     /// This allows to fetch it in a later SPI session within the same transaction
     /// using [`SpiClient::find_cursor()`]
     ///
@@ -783,6 +1633,154 @@ impl SpiCursor<'_> {
     }
 }
 
+impl<'client> SpiCursor<'client> {
+    /// Consume the cursor, returning an iterator over its rows that fetches `fetch_size` rows at
+    /// a time, so a large result set can be processed without running the whole query up front.
+    ///
+    /// See the [`SpiCursor`] docs for notes about memory usage.
+    ///
+    /// # Examples
+    ///
+    /// ```rust,no_run
+    /// use pgrx::prelude::*;
+    /// # fn foo() -> spi::Result<()> {
+    /// let sum = Spi::connect(|client| {
+    ///     client
+    ///         .open_cursor("SELECT i FROM generate_series(1, 1000000) i", None)
+    ///         .rows(1000)
+    ///         .map(|row| row.get::<i64>(1).map(Option::unwrap_or_default))
+    ///         .sum::<spi::Result<i64>>()
+    /// })?;
+    /// # Ok(())
+    /// # }
+    /// ```
+    ///
+    /// # Panics
+    ///
+    /// Panics if `fetch_size` isn't greater than zero
+    pub fn rows(self, fetch_size: libc::c_long) -> SpiCursorIter<'client> {
+        assert!(fetch_size > 0, "fetch_size must be greater than zero");
+        SpiCursorIter { cursor: self, fetch_size, table: None, exhausted: false }
+    }
+
+    /// Consume the cursor, returning an iterator that deserializes each row into a `T`, fetching
+    /// `fetch_size` rows at a time.
+    ///
+    /// See [`SpiHeapTupleData::deserialize()`] for how column values are converted, and the
+    /// [`SpiCursor`] docs for notes about memory usage.
+    ///
+    /// # Examples
+    ///
+    /// ```rust,no_run
+    /// use pgrx::prelude::*;
+    /// #[derive(serde::Deserialize)]
+    /// struct Row {
+    ///     i: i32,
+    /// }
+    /// # fn foo() -> spi::Result<()> {
+    /// let sum = Spi::connect(|client| {
+    ///     client
+    ///         .open_cursor("SELECT i FROM generate_series(1, 1000) i", None)
+    ///         .deserialize_rows::<Row>(100)
+    ///         .map(|row| row.map(|row| row.i))
+    ///         .sum::<spi::Result<i32>>()
+    /// })?;
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn deserialize_rows<T: DeserializeOwned>(
+        self,
+        fetch_size: libc::c_long,
+    ) -> SpiCursorRows<'client, T> {
+        SpiCursorRows {
+            cursor: self,
+            fetch_size,
+            table: None,
+            exhausted: false,
+            __marker: PhantomData,
+        }
+    }
+}
+
+/// An iterator over the rows of a [`SpiCursor`], fetching them a batch at a time, as created by
+/// [`SpiCursor::rows()`]
+pub struct SpiCursorIter<'client> {
+    cursor: SpiCursor<'client>,
+    fetch_size: libc::c_long,
+    table: Option<SpiTupleTable>,
+    exhausted: bool,
+}
+
+impl SpiCursorIter<'_> {
+    /// The number of rows fetched at a time
+    pub fn fetch_size(&self) -> libc::c_long {
+        self.fetch_size
+    }
+}
+
+impl Iterator for SpiCursorIter<'_> {
+    type Item = SpiHeapTupleData;
+
+    /// # Panics
+    ///
+    /// This method will panic if for some reason the underlying heap tuple cannot be retrieved
+    fn next(&mut self) -> Option<Self::Item> {
+        loop {
+            if let Some(row) = self.table.as_mut().and_then(|table| table.next()) {
+                return Some(row);
+            }
+
+            if self.exhausted {
+                return None;
+            }
+
+            let table = self.cursor.fetch(self.fetch_size).report();
+            // a short fetch means the cursor has run off the end of its rows
+            self.exhausted = (table.len() as libc::c_long) < self.fetch_size;
+            self.table = Some(table);
+        }
+    }
+}
+
+/// An iterator that deserializes the rows of a [`SpiCursor`], as created by
+/// [`SpiCursor::deserialize_rows()`]
+pub struct SpiCursorRows<'client, T> {
+    cursor: SpiCursor<'client>,
+    fetch_size: libc::c_long,
+    table: Option<SpiTupleTable>,
+    exhausted: bool,
+    __marker: PhantomData<T>,
+}
+
+impl<T: DeserializeOwned> Iterator for SpiCursorRows<'_, T> {
+    type Item = Result<T>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        loop {
+            if let Some(row) = self.table.as_mut().and_then(|table| table.next()) {
+                return Some(row.deserialize());
+            }
+
+            if self.exhausted {
+                return None;
+            }
+
+            match self.cursor.fetch(self.fetch_size) {
+                Ok(table) => {
+                    // a short fetch means the cursor has run off the end of its rows
+                    self.exhausted = (table.len() as libc::c_long) < self.fetch_size;
+                    self.table = Some(table);
+                }
+                Err(e) => {
+                    self.exhausted = true;
+                    self.table = None;
+                    return Some(Err(e));
+                }
+            }
+        }
+    }
+}
+
 impl Drop for SpiCursor<'_> {
     fn drop(&mut self) {
         // SAFETY: SPI functions to create/find cursors fail via elog, so self.ptr is valid if we successfully set it
@@ -792,6 +1790,90 @@ impl Drop for SpiCursor<'_> {
     }
 }
 
+/// A Rust type that can be decoded from an SPI result row
+///
+/// This is usually derived, with `#[derive(FromSpiRow)]`.  For a struct with named fields, each
+/// field is decoded from the column with the same name, or with the name given by a
+/// `#[spi(rename = "...")]` attribute on the field.  For a tuple struct, each field is decoded from
+/// the column at the same position.  Every field's type must be [`FromDatum`] + [`IntoDatum`], or an
+/// `Option` of one, which is checked when the struct is compiled.  Whether each column's type can
+/// be converted to its field's type is checked as each row is decoded.
+///
+/// # Examples
+///
+/// ```rust,no_run
+/// use pgrx::prelude::*;
+/// use pgrx::spi::FromSpiRow;
+///
+/// #[derive(FromSpiRow)]
+/// struct Dog {
+///     name: String,
+///     #[spi(rename = "age_in_years")]
+///     age: i32,
+///     owner: Option<String>,
+/// }
+///
+/// # fn foo() -> spi::Result<()> {
+/// let dogs = Spi::connect(|client| {
+///     client
+///         .select("SELECT name, age_in_years, owner FROM dogs", None, None)?
+///         .decode_rows::<Dog>()
+///         .collect::<spi::Result<Vec<_>>>()
+/// })?;
+/// # Ok(())
+/// # }
+/// ```
+pub trait FromSpiRow: Sized {
+    /// Decode a `Self` from `row`
+    ///
+    /// # Errors
+    ///
+    /// Returns [`Error::SpiError(SpiErrorCodes::NoAttribute)`] if a column is missing,
+    /// [`Error::DatumError`] if a column's type can't be converted to its field's type, and
+    /// [`Error::UnexpectedNull`] if a column is NULL but its field isn't an `Option`
+    fn from_spi_row(row: &SpiHeapTupleData) -> Result<Self>;
+}
+
+/// A row for [`Spi::insert_batch()`]:  a value for each column of a table, in column order
+///
+/// Implemented for tuples of up to twelve [`IntoDatum`] values.
+pub trait IntoComposite {
+    /// The types of the values, which are the same for every row
+    fn type_oids() -> Vec<PgOid>;
+
+    /// The values, as datums
+    fn into_datums(self) -> Vec<Option<pg_sys::Datum>>;
+}
+
+macro_rules! impl_into_composite {
+    ($($T:ident),+) => {
+        impl<$($T: IntoDatum),+> IntoComposite for ($($T,)+) {
+            fn type_oids() -> Vec<PgOid> {
+                vec![$(PgOid::from($T::type_oid())),+]
+            }
+
+            #[allow(non_snake_case)]
+            fn into_datums(self) -> Vec<Option<pg_sys::Datum>> {
+                let ($($T,)+) = self;
+                vec![$($T.into_datum()),+]
+            }
+        }
+    };
+}
+
+impl_into_composite!(A);
+impl_into_composite!(A, B);
+impl_into_composite!(A, B, C);
+impl_into_composite!(A, B, C, D);
+impl_into_composite!(A, B, C, D, E);
+impl_into_composite!(A, B, C, D, E, F);
+impl_into_composite!(A, B, C, D, E, F, G);
+impl_into_composite!(A, B, C, D, E, F, G, H);
+impl_into_composite!(A, B, C, D, E, F, G, H, I);
+impl_into_composite!(A, B, C, D, E, F, G, H, I, J);
+impl_into_composite!(A, B, C, D, E, F, G, H, I, J, K);
+impl_into_composite!(A, B, C, D, E, F, G, H, I, J, K, L);
+
 /// Client lifetime-bound prepared statement
 pub struct PreparedStatement<'a> {
     plan: NonNull<pg_sys::_SPI_plan>,
@@ -1012,6 +2094,22 @@ impl SpiTupleTable {
         self
     }
 
+    /// Consume the table, returning an iterator that deserializes each of its remaining rows
+    /// into a `T`.
+    ///
+    /// See [`SpiHeapTupleData::deserialize()`] for how column values are converted.
+    pub fn deserialize_rows<T: DeserializeOwned>(self) -> impl Iterator<Item = Result<T>> {
+        self.map(|row| row.deserialize())
+    }
+
+    /// Consume the table, returning an iterator that decodes each of its remaining rows into a
+    /// `T`.
+    ///
+    /// See [`FromSpiRow`] for how rows are decoded.
+    pub fn decode_rows<T: FromSpiRow>(self) -> impl Iterator<Item = Result<T>> {
+        self.map(|row| row.decode())
+    }
+
     /// How many rows were processed?
     pub fn len(&self) -> usize {
         self.size
@@ -1096,7 +2194,7 @@ impl SpiTupleTable {
         let is_null = datum.is_none();
         let datum = datum.unwrap_or_else(|| pg_sys::Datum::from(0));
 
-        unsafe {
+        crate::datum::counted_from_datum::<T, _>(|| unsafe {
             // SAFETY:  we know the constraints around `datum` and `is_null` match because we
             // just got them from the underlying heap tuple
             Ok(T::try_from_datum_in_memory_context(
@@ -1109,7 +2207,7 @@ impl SpiTupleTable {
                 // `self.get_datum_by_ordinal()` above already decided that for us
                 pg_sys::SPI_gettypeid(tupdesc, ordinal as _),
             )?)
-        }
+        })
     }
 
     /// Get a typed value by its name.
@@ -1195,6 +2293,35 @@ impl SpiTupleTable {
         }
     }
 
+    /// Returns column type modifier
+    ///
+    /// The ordinal position is 1-based
+    pub fn column_typmod(&self, ordinal: usize) -> Result<i32> {
+        Ok(self.column(ordinal)?.typmod())
+    }
+
+    /// Returns the name, type oid, and type modifier of the 1-based `ordinal` column
+    ///
+    /// # Errors
+    ///
+    /// Returns [`Error::SpiError(SpiError::NoAttribute)`] if the specified ordinal value is out of bounds
+    /// If we have no backing tuple table a [`Error::NoTupleTable`] is returned
+    pub fn column(&self, ordinal: usize) -> Result<SpiColumn> {
+        self.check_ordinal_bounds(ordinal)?;
+        let (_, tupdesc) = self.get_spi_tuptable()?;
+        // SAFETY:  we just got a valid tupdesc and we know ordinal is in bounds
+        Ok(unsafe { SpiColumn::from_tupdesc(tupdesc, ordinal) })
+    }
+
+    /// Returns the [`SpiColumn`] metadata of every column, in ordinal order
+    ///
+    /// # Errors
+    ///
+    /// If we have no backing tuple table a [`Error::NoTupleTable`] is returned
+    pub fn column_metadata(&self) -> Result<Vec<SpiColumn>> {
+        (1..=self.columns()?).map(|ordinal| self.column(ordinal)).collect()
+    }
+
     /// Returns column name of the 1-based `ordinal` position
     ///
     /// # Errors
@@ -1347,6 +2474,74 @@ impl SpiHeapTupleData {
         }
     }
 
+    /// Get a dynamically-typed [`Value`] from this HeapTuple by its ordinal position.
+    ///
+    /// The ordinal position is 1-based
+    ///
+    /// # Errors
+    ///
+    /// If the specified ordinal is out of bounds a [`Error::SpiError(SpiError::NoAttribute)`] is returned
+    pub fn get_value(&self, ordinal: usize) -> Result<Value> {
+        Ok(self.get_datum_by_ordinal(ordinal)?.to_value())
+    }
+
+    /// Get a dynamically-typed [`Value`] from this HeapTuple by its name in the resultset.
+    ///
+    /// # Errors
+    ///
+    /// If the specified name isn't valid a [`Error::SpiError(SpiError::NoAttribute)`] is returned
+    pub fn get_value_by_name<S: AsRef<str>>(&self, name: S) -> Result<Value> {
+        Ok(self.get_datum_by_name(name.as_ref())?.to_value())
+    }
+
+    /// Deserialize this row into a `T`, matching columns to fields by name.
+    ///
+    /// Each column is first decoded as a [`Value`] and then converted to its natural JSON
+    /// representation, so any `T` that could be deserialized from a JSON object of the row
+    /// works.  `numeric` columns are converted to JSON numbers so they can be deserialized into
+    /// Rust integer and float fields, and types without a dedicated [`Value`] variant become
+    /// the text from their output function.
+    ///
+    /// # Errors
+    ///
+    /// Returns [`Error::DeserializeError`] if the row's columns don't match the shape of `T`
+    pub fn deserialize<T: DeserializeOwned>(&self) -> Result<T> {
+        let mut object = serde_json::Map::with_capacity(self.columns());
+        for column in self.column_metadata() {
+            let value = match self.get_value(column.ordinal())? {
+                Value::Numeric(numeric) => {
+                    let string = numeric.to_string();
+                    match serde_json::from_str::<serde_json::Number>(&string) {
+                        Ok(number) => serde_json::Value::Number(number),
+                        // NaN and Infinity have no JSON number representation
+                        Err(_) => serde_json::Value::String(string),
+                    }
+                }
+                value => serde_json::to_value(value)
+                    .map_err(|e| Error::DeserializeError(e.to_string()))?,
+            };
+            object.insert(column.name().to_string(), value);
+        }
+
+        serde_json::from_value(serde_json::Value::Object(object))
+            .map_err(|e| Error::DeserializeError(e.to_string()))
+    }
+
+    /// Decode this row into a `T`.  See [`FromSpiRow`] for how rows are decoded.
+    pub fn decode<T: FromSpiRow>(&self) -> Result<T> {
+        T::from_spi_row(self)
+    }
+
+    /// Returns the [`SpiColumn`] metadata of every column, in ordinal order
+    pub fn column_metadata(&self) -> Vec<SpiColumn> {
+        (1..=self.columns())
+            .map(|ordinal| unsafe {
+                // SAFETY: we know self.tupdesc is valid and ordinal is in bounds
+                SpiColumn::from_tupdesc(self.tupdesc.as_ptr(), ordinal)
+            })
+            .collect()
+    }
+
     /// Set a datum value for the specified ordinal position
     ///
     /// # Errors
@@ -1410,13 +2605,23 @@ impl SpiHeapTupleData {
 impl SpiHeapTupleDataEntry {
     pub fn value<T: IntoDatum + FromDatum>(&self) -> Result<Option<T>> {
         match self.datum.as_ref() {
-            Some(datum) => unsafe {
+            Some(datum) => crate::datum::counted_from_datum::<T, _>(|| unsafe {
                 T::try_from_datum(*datum, false, self.type_oid).map_err(|e| Error::DatumError(e))
-            },
+            }),
             None => Ok(None),
         }
     }
 
+    /// Decode this entry into a dynamically-typed [`Value`] based on its type oid
+    pub fn to_value(&self) -> Value {
+        let (datum, is_null) = match self.datum {
+            Some(datum) => (datum, false),
+            None => (pg_sys::Datum::from(0), true),
+        };
+        // SAFETY: the datum and its type oid came from the same heap tuple
+        unsafe { Value::from_datum(datum, is_null, self.type_oid) }
+    }
+
     pub fn oid(&self) -> pg_sys::Oid {
         self.type_oid
     }
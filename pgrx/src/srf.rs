@@ -5,6 +5,42 @@ use crate::{
     pg_return_null, pg_sys, srf_first_call_init, srf_is_first_call, srf_per_call_setup,
     srf_return_done, srf_return_next, IntoDatum, IntoHeapTuple, PgMemoryContexts,
 };
+use pgrx_pg_sys::panic::ErrorReportable;
+use std::any::Any;
+use std::fmt::Display;
+
+/// Normalizes the return value of a set-returning `#[pg_extern]` function, which may wrap its
+/// [`SetOfIterator`] or [`TableIterator`] in any nesting of `Option` and `Result`, into the
+/// `Option<Iterator>` that `srf_next()` expects.  `None` produces an empty set and `Err` values are
+/// raised as Postgres ERRORs.
+#[doc(hidden)]
+pub trait IntoSrfIterator<I> {
+    fn into_srf_iterator(self) -> Option<I>;
+}
+
+impl<'a, T> IntoSrfIterator<SetOfIterator<'a, T>> for SetOfIterator<'a, T> {
+    fn into_srf_iterator(self) -> Option<SetOfIterator<'a, T>> {
+        Some(self)
+    }
+}
+
+impl<'a, T> IntoSrfIterator<TableIterator<'a, T>> for TableIterator<'a, T> {
+    fn into_srf_iterator(self) -> Option<TableIterator<'a, T>> {
+        Some(self)
+    }
+}
+
+impl<I, T: IntoSrfIterator<I>> IntoSrfIterator<I> for Option<T> {
+    fn into_srf_iterator(self) -> Option<I> {
+        self.and_then(IntoSrfIterator::into_srf_iterator)
+    }
+}
+
+impl<I, T: IntoSrfIterator<I>, E: Any + Display> IntoSrfIterator<I> for Result<T, E> {
+    fn into_srf_iterator(self) -> Option<I> {
+        self.report().into_srf_iterator()
+    }
+}
 
 impl<'a, T: IntoDatum> SetOfIterator<'a, T> {
     #[doc(hidden)]
@@ -54,7 +90,7 @@ impl<'a, T: IntoDatum> SetOfIterator<'a, T> {
         match setof_iterator.next() {
             Some(datum) => {
                 srf_return_next(fcinfo, funcctx);
-                datum.into_datum().unwrap_or_else(|| pg_return_null(fcinfo))
+                crate::datum::counted_into_datum(datum).unwrap_or_else(|| pg_return_null(fcinfo))
             }
             None => {
                 srf_return_done(fcinfo, funcctx);
@@ -0,0 +1,444 @@
+/*
+Portions Copyright 2019-2021 ZomboDB, LLC.
+Portions Copyright 2021-2022 Technology Concepts & Design, Inc. <support@tcdi.com>
+
+All rights reserved.
+
+Use of this source code is governed by the MIT license that can be found in the LICENSE file.
+*/
+
+//! Inject planner statistics into a relation, as if `ANALYZE` had computed them
+//!
+//! A test cluster can't plan queries the way production does without production's statistics.
+//! [`RelationStats`] sets the size of a relation in `pg_class`, and [`AttributeStats`] sets the
+//! statistics of one of its columns in `pg_statistic`, from values read out of production's
+//! `pg_class` and `pg_stats`:
+//!
+//! ```rust,no_run
+//! use pgrx::stats_import::{AttributeStats, RelationStats};
+//!
+//! RelationStats::new("public.orders").relpages(10_000).reltuples(1_000_000.0).set().unwrap();
+//! AttributeStats::new("public.orders", "status")
+//!     .null_frac(0.0)
+//!     .avg_width(8)
+//!     .n_distinct(3.0)
+//!     .most_common_values(&["shipped", "pending", "cancelled"], &[0.9, 0.08, 0.02])
+//!     .set()
+//!     .unwrap();
+//! ```
+//!
+//! Like `ANALYZE`, injecting statistics requires owning the relation, and takes a
+//! `SHARE UPDATE EXCLUSIVE` lock on it.  The statistics are transactional, and last until the next
+//! `ANALYZE` of the relation, including one by autovacuum.
+use crate::{direct_function_call, pg_sys, IntoDatum, PgBuiltInOids};
+use std::ffi::CString;
+
+/// Why statistics couldn't be injected
+#[derive(thiserror::Error, Debug, Clone, PartialEq, Eq)]
+pub enum StatsImportError {
+    #[error("relation \"{0}\" does not exist")]
+    UndefinedTable(String),
+    #[error("column \"{1}\" of relation \"{0}\" does not exist")]
+    UndefinedColumn(String, String),
+    #[error("must be owner of relation \"{0}\"")]
+    NotOwner(String),
+    #[error("{0} most common values were given {1} frequencies")]
+    MostCommonMismatch(usize, usize),
+    #[error("column \"{0}\" has no default {1} operator")]
+    NoOperator(String, &'static str),
+    #[error("statistics value \"{0}\" contains a nul byte")]
+    InvalidValue(String),
+}
+
+/// Look up `relation`, lock it like `ANALYZE` does, and check the current user owns it
+fn open_relation(relation: &str) -> Result<pg_sys::Oid, StatsImportError> {
+    let relid = unsafe {
+        direct_function_call::<pg_sys::Oid>(pg_sys::to_regclass, &[relation.into_datum()])
+    }
+    .ok_or_else(|| StatsImportError::UndefinedTable(relation.to_string()))?;
+    unsafe {
+        pg_sys::LockRelationOid(relid, pg_sys::ShareUpdateExclusiveLock as _);
+        if !pg_sys::pg_class_ownercheck(relid, pg_sys::GetUserId()) {
+            return Err(StatsImportError::NotOwner(relation.to_string()));
+        }
+    }
+    Ok(relid)
+}
+
+/// The size of a relation, as `VACUUM` and `ANALYZE` record it in `pg_class`
+///
+/// Only the values given are changed.
+#[derive(Debug, Clone)]
+pub struct RelationStats {
+    relation: String,
+    relpages: Option<i32>,
+    reltuples: Option<f32>,
+    relallvisible: Option<i32>,
+}
+
+impl RelationStats {
+    /// Statistics for `relation`, which may be schema-qualified, and is used as-is, so must already
+    /// be quoted where necessary
+    pub fn new(relation: &str) -> Self {
+        RelationStats {
+            relation: relation.to_string(),
+            relpages: None,
+            reltuples: None,
+            relallvisible: None,
+        }
+    }
+
+    /// The number of pages of the relation
+    pub fn relpages(mut self, relpages: i32) -> Self {
+        self.relpages = Some(relpages);
+        self
+    }
+
+    /// The number of live rows of the relation
+    pub fn reltuples(mut self, reltuples: f32) -> Self {
+        self.reltuples = Some(reltuples);
+        self
+    }
+
+    /// The number of pages of the relation marked all-visible in its visibility map
+    pub fn relallvisible(mut self, relallvisible: i32) -> Self {
+        self.relallvisible = Some(relallvisible);
+        self
+    }
+
+    /// Update the relation's `pg_class` row with these statistics
+    pub fn set(&self) -> Result<(), StatsImportError> {
+        let relid = open_relation(&self.relation)?;
+        unsafe {
+            let pg_class = crate::compat::table_open(
+                pg_sys::RelationRelationId,
+                pg_sys::RowExclusiveLock as _,
+            );
+            let tuple = pg_sys::SearchSysCacheCopy(
+                pg_sys::SysCacheIdentifier_RELOID as _,
+                pg_sys::Datum::from(relid),
+                pg_sys::Datum::from(0),
+                pg_sys::Datum::from(0),
+                pg_sys::Datum::from(0),
+            );
+            if tuple.is_null() {
+                panic!("cache lookup failed for relation {:?}", relid);
+            }
+
+            // SAFETY:  `tuple` is our own copy of the relation's pg_class row
+            let form = &mut *(pg_sys::GETSTRUCT(tuple) as *mut pg_sys::FormData_pg_class);
+            if let Some(relpages) = self.relpages {
+                form.relpages = relpages;
+            }
+            if let Some(reltuples) = self.reltuples {
+                form.reltuples = reltuples;
+            }
+            if let Some(relallvisible) = self.relallvisible {
+                form.relallvisible = relallvisible;
+            }
+            pg_sys::CatalogTupleUpdate(pg_class, &mut (*tuple).t_self, tuple);
+
+            pg_sys::heap_freetuple(tuple);
+            crate::compat::table_close(pg_class, pg_sys::RowExclusiveLock as _);
+            pg_sys::CommandCounterIncrement();
+        }
+        Ok(())
+    }
+}
+
+/// The statistics of one column of a relation, as `ANALYZE` records them in `pg_statistic`
+///
+/// Values are given as text, in the form the `pg_stats` view shows them, and converted to the
+/// column's type.  Setting these statistics replaces all of the column's existing ones.
+#[derive(Debug, Clone)]
+pub struct AttributeStats {
+    relation: String,
+    column: String,
+    inherited: bool,
+    null_frac: f32,
+    avg_width: i32,
+    n_distinct: f32,
+    most_common: Option<(Vec<String>, Vec<f32>)>,
+    histogram: Option<Vec<String>>,
+    correlation: Option<f32>,
+}
+
+impl AttributeStats {
+    /// Statistics for the column `column` of `relation`.  `relation` may be schema-qualified, and
+    /// is used as-is, so must already be quoted where necessary.  `column` is not quoted
+    pub fn new(relation: &str, column: &str) -> Self {
+        AttributeStats {
+            relation: relation.to_string(),
+            column: column.to_string(),
+            inherited: false,
+            null_frac: 0.0,
+            avg_width: 0,
+            n_distinct: 0.0,
+            most_common: None,
+            histogram: None,
+            correlation: None,
+        }
+    }
+
+    /// Whether these are the statistics of the relation and its inheritance children, rather than
+    /// of the relation alone
+    pub fn inherited(mut self, inherited: bool) -> Self {
+        self.inherited = inherited;
+        self
+    }
+
+    /// The fraction of the column's values that are NULL
+    pub fn null_frac(mut self, null_frac: f32) -> Self {
+        self.null_frac = null_frac;
+        self
+    }
+
+    /// The average width, in bytes, of the column's non-NULL values
+    pub fn avg_width(mut self, avg_width: i32) -> Self {
+        self.avg_width = avg_width;
+        self
+    }
+
+    /// The number of distinct non-NULL values of the column.  A negative value is minus that number
+    /// divided by the number of rows, and zero means it's unknown
+    pub fn n_distinct(mut self, n_distinct: f32) -> Self {
+        self.n_distinct = n_distinct;
+        self
+    }
+
+    /// The column's most common values, and the fraction of rows each is found in
+    pub fn most_common_values<S: AsRef<str>>(mut self, values: &[S], freqs: &[f32]) -> Self {
+        let values = values.iter().map(|value| value.as_ref().to_string()).collect();
+        self.most_common = Some((values, freqs.to_vec()));
+        self
+    }
+
+    /// Values dividing the column's values, other than its most common ones, into groups of about
+    /// the same number of rows, in ascending order
+    pub fn histogram_bounds<S: AsRef<str>>(mut self, bounds: &[S]) -> Self {
+        self.histogram = Some(bounds.iter().map(|bound| bound.as_ref().to_string()).collect());
+        self
+    }
+
+    /// The correlation between the physical order of the rows and the order of the column's
+    /// values, from `-1.0` to `1.0`
+    pub fn correlation(mut self, correlation: f32) -> Self {
+        self.correlation = Some(correlation);
+        self
+    }
+
+    /// Write these statistics into `pg_statistic`, replacing any the column has
+    pub fn set(&self) -> Result<(), StatsImportError> {
+        if let Some((values, freqs)) = &self.most_common {
+            if values.len() != freqs.len() {
+                return Err(StatsImportError::MostCommonMismatch(values.len(), freqs.len()));
+            }
+        }
+
+        let relid = open_relation(&self.relation)?;
+        let undefined_column =
+            || StatsImportError::UndefinedColumn(self.relation.clone(), self.column.clone());
+        let column = CString::new(self.column.as_str()).map_err(|_| undefined_column())?;
+        let attnum = unsafe { pg_sys::get_attnum(relid, column.as_ptr()) };
+        if attnum <= 0 {
+            return Err(undefined_column());
+        }
+
+        let column = Column::new(relid, attnum);
+        let mut slots = Vec::new();
+        if let Some((values, freqs)) = &self.most_common {
+            slots.push(Slot {
+                kind: pg_sys::STATISTIC_KIND_MCV as _,
+                op: column.operator(&self.column, pg_sys::TYPECACHE_EQ_OPR, "equality")?,
+                collation: column.collation,
+                numbers: Some(float4_array(freqs)),
+                values: Some(column.array(values)?),
+            });
+        }
+        if let Some(bounds) = &self.histogram {
+            slots.push(Slot {
+                kind: pg_sys::STATISTIC_KIND_HISTOGRAM as _,
+                op: column.operator(&self.column, pg_sys::TYPECACHE_LT_OPR, "ordering")?,
+                collation: column.collation,
+                numbers: None,
+                values: Some(column.array(bounds)?),
+            });
+        }
+        if let Some(correlation) = self.correlation {
+            slots.push(Slot {
+                kind: pg_sys::STATISTIC_KIND_CORRELATION as _,
+                op: column.operator(&self.column, pg_sys::TYPECACHE_LT_OPR, "ordering")?,
+                collation: column.collation,
+                numbers: Some(float4_array(&[correlation])),
+                values: None,
+            });
+        }
+
+        let mut values = [pg_sys::Datum::from(0); pg_sys::Natts_pg_statistic as usize];
+        let mut nulls = [false; pg_sys::Natts_pg_statistic as usize];
+        let mut set = |anum: u32, value: Option<pg_sys::Datum>| {
+            values[anum as usize - 1] = value.unwrap_or(pg_sys::Datum::from(0));
+            nulls[anum as usize - 1] = value.is_none();
+        };
+        set(pg_sys::Anum_pg_statistic_starelid, relid.into_datum());
+        set(pg_sys::Anum_pg_statistic_staattnum, attnum.into_datum());
+        set(pg_sys::Anum_pg_statistic_stainherit, self.inherited.into_datum());
+        set(pg_sys::Anum_pg_statistic_stanullfrac, self.null_frac.into_datum());
+        set(pg_sys::Anum_pg_statistic_stawidth, self.avg_width.into_datum());
+        set(pg_sys::Anum_pg_statistic_stadistinct, self.n_distinct.into_datum());
+        for i in 0..pg_sys::STATISTIC_NUM_SLOTS {
+            let slot = slots.get(i as usize);
+            set(
+                pg_sys::Anum_pg_statistic_stakind1 + i,
+                slot.map_or(0i16, |slot| slot.kind).into_datum(),
+            );
+            set(
+                pg_sys::Anum_pg_statistic_staop1 + i,
+                slot.map_or(pg_sys::InvalidOid, |slot| slot.op).into_datum(),
+            );
+            #[cfg(not(feature = "pg11"))]
+            set(
+                pg_sys::Anum_pg_statistic_stacoll1 + i,
+                slot.map_or(pg_sys::InvalidOid, |slot| slot.collation).into_datum(),
+            );
+            set(pg_sys::Anum_pg_statistic_stanumbers1 + i, slot.and_then(|slot| slot.numbers));
+            set(pg_sys::Anum_pg_statistic_stavalues1 + i, slot.and_then(|slot| slot.values));
+        }
+
+        unsafe {
+            // the same as `ANALYZE` does in `update_attstats()`
+            let pg_statistic = crate::compat::table_open(
+                pg_sys::StatisticRelationId,
+                pg_sys::RowExclusiveLock as _,
+            );
+            let tupdesc = (*pg_statistic).rd_att;
+            let old = pg_sys::SearchSysCache3(
+                pg_sys::SysCacheIdentifier_STATRELATTINH as _,
+                pg_sys::Datum::from(relid),
+                pg_sys::Datum::from(attnum),
+                pg_sys::Datum::from(self.inherited),
+            );
+            let tuple = if old.is_null() {
+                let tuple =
+                    pg_sys::heap_form_tuple(tupdesc, values.as_mut_ptr(), nulls.as_mut_ptr());
+                pg_sys::CatalogTupleInsert(pg_statistic, tuple);
+                tuple
+            } else {
+                let mut replace = [true; pg_sys::Natts_pg_statistic as usize];
+                let tuple = pg_sys::heap_modify_tuple(
+                    old,
+                    tupdesc,
+                    values.as_mut_ptr(),
+                    nulls.as_mut_ptr(),
+                    replace.as_mut_ptr(),
+                );
+                pg_sys::ReleaseSysCache(old);
+                pg_sys::CatalogTupleUpdate(pg_statistic, &mut (*tuple).t_self, tuple);
+                tuple
+            };
+
+            pg_sys::heap_freetuple(tuple);
+            crate::compat::table_close(pg_statistic, pg_sys::RowExclusiveLock as _);
+            pg_sys::CommandCounterIncrement();
+        }
+        Ok(())
+    }
+}
+
+/// One of the `pg_statistic` slots of a column's statistics
+struct Slot {
+    kind: i16,
+    op: pg_sys::Oid,
+    #[cfg_attr(feature = "pg11", allow(dead_code))]
+    collation: pg_sys::Oid,
+    numbers: Option<pg_sys::Datum>,
+    values: Option<pg_sys::Datum>,
+}
+
+/// The type of a column, for making its statistics
+struct Column {
+    typid: pg_sys::Oid,
+    typmod: i32,
+    collation: pg_sys::Oid,
+}
+
+impl Column {
+    fn new(relid: pg_sys::Oid, attnum: i16) -> Self {
+        let mut typid = pg_sys::InvalidOid;
+        let mut typmod = -1;
+        let mut collation = pg_sys::InvalidOid;
+        unsafe {
+            pg_sys::get_atttypetypmodcoll(relid, attnum, &mut typid, &mut typmod, &mut collation);
+        }
+        Column { typid, typmod, collation }
+    }
+
+    /// The type's default operator of the kind `flag` asks the type cache for
+    fn operator(
+        &self,
+        column: &str,
+        flag: u32,
+        kind: &'static str,
+    ) -> Result<pg_sys::Oid, StatsImportError> {
+        let typentry = unsafe {
+            // SAFETY:  type cache entries live as long as the backend
+            &*pg_sys::lookup_type_cache(self.typid, flag as _)
+        };
+        let op = if flag == pg_sys::TYPECACHE_EQ_OPR { typentry.eq_opr } else { typentry.lt_opr };
+        if op == pg_sys::InvalidOid {
+            return Err(StatsImportError::NoOperator(column.to_string(), kind));
+        }
+        Ok(op)
+    }
+
+    /// An array of the column's type, of `values` converted by the type's input function
+    fn array(&self, values: &[String]) -> Result<pg_sys::Datum, StatsImportError> {
+        unsafe {
+            let mut typinput = pg_sys::InvalidOid;
+            let mut typioparam = pg_sys::InvalidOid;
+            pg_sys::getTypeInputInfo(self.typid, &mut typinput, &mut typioparam);
+
+            let mut elems = Vec::with_capacity(values.len());
+            for value in values {
+                let cstr = CString::new(value.as_str())
+                    .map_err(|_| StatsImportError::InvalidValue(value.clone()))?;
+                elems.push(pg_sys::OidInputFunctionCall(
+                    typinput,
+                    cstr.as_ptr() as *mut _,
+                    typioparam,
+                    self.typmod,
+                ));
+            }
+
+            let mut typlen = 0;
+            let mut typbyval = false;
+            let mut typalign = 0;
+            pg_sys::get_typlenbyvalalign(self.typid, &mut typlen, &mut typbyval, &mut typalign);
+            let array = pg_sys::construct_array(
+                elems.as_mut_ptr(),
+                elems.len() as _,
+                self.typid,
+                typlen as _,
+                typbyval,
+                typalign,
+            );
+            Ok(pg_sys::Datum::from(array))
+        }
+    }
+}
+
+/// A `real[]` of `numbers`
+fn float4_array(numbers: &[f32]) -> pg_sys::Datum {
+    let mut elems = numbers.iter().map(|n| n.into_datum().unwrap()).collect::<Vec<_>>();
+    unsafe {
+        let array = pg_sys::construct_array(
+            elems.as_mut_ptr(),
+            elems.len() as _,
+            PgBuiltInOids::FLOAT4OID.value(),
+            std::mem::size_of::<f32>() as _,
+            true,
+            b'i' as _,
+        );
+        pg_sys::Datum::from(array)
+    }
+}
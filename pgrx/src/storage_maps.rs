@@ -0,0 +1,204 @@
+/*
+Portions Copyright 2019-2021 ZomboDB, LLC.
+Portions Copyright 2021-2022 Technology Concepts & Design, Inc. <support@tcdi.com>
+
+All rights reserved.
+
+Use of this source code is governed by the MIT license that can be found in the LICENSE file.
+*/
+
+//! Read-only access to a relation's visibility map and free space map
+//!
+//! These are the same maps `pg_visibility` and `pg_freespacemap` report on.  Neither is WAL-logged
+//! with any urgency, so the answers are hints about the relation's storage, not guarantees.
+//!
+//! ## Examples
+//!
+//! ```rust,no_run
+//! use pgrx::prelude::*;
+//! use pgrx::storage_maps::{FreeSpaceMap, VisibilityMap};
+//! use pgrx::PgRelation;
+//!
+//! let relation = PgRelation::open_with_name_and_share_lock("my_table").unwrap();
+//! let summary = VisibilityMap::new(&relation).summary();
+//! info!("{:.1}% all-visible", summary.all_visible_fraction() * 100.0);
+//!
+//! let free: usize = FreeSpaceMap::new(&relation).iter().map(|(_, bytes)| bytes).sum();
+//! info!("{free} bytes free");
+//! ```
+use crate::{pg_sys, PgRelation};
+
+/// The visibility map bits of a single heap block
+#[derive(Debug, Default, Copy, Clone, Eq, PartialEq)]
+pub struct VisibilityStatus {
+    /// Every tuple on the block is visible to all transactions
+    pub all_visible: bool,
+    /// Every tuple on the block is frozen
+    pub all_frozen: bool,
+}
+
+/// Block counts for a relation's visibility map, as returned by [`VisibilityMap::summary()`]
+#[derive(Debug, Default, Copy, Clone, Eq, PartialEq)]
+pub struct VisibilityMapSummary {
+    /// The number of blocks in the relation's main fork
+    pub blocks: pg_sys::BlockNumber,
+    /// The number of blocks marked all-visible
+    pub all_visible: pg_sys::BlockNumber,
+    /// The number of blocks marked all-frozen
+    pub all_frozen: pg_sys::BlockNumber,
+}
+
+impl VisibilityMapSummary {
+    /// The fraction, between `0.0` and `1.0`, of blocks that are all-visible.  An empty relation
+    /// is entirely all-visible
+    pub fn all_visible_fraction(&self) -> f64 {
+        fraction(self.all_visible, self.blocks)
+    }
+
+    /// The fraction, between `0.0` and `1.0`, of blocks that are all-frozen.  An empty relation is
+    /// entirely all-frozen
+    pub fn all_frozen_fraction(&self) -> f64 {
+        fraction(self.all_frozen, self.blocks)
+    }
+}
+
+fn fraction(count: pg_sys::BlockNumber, blocks: pg_sys::BlockNumber) -> f64 {
+    if blocks == 0 {
+        1.0
+    } else {
+        count as f64 / blocks as f64
+    }
+}
+
+/// Reads the visibility map of a table, materialized view, or TOAST table
+///
+/// The visibility map page most recently read stays pinned until this is dropped, so looking up
+/// consecutive blocks is cheap.
+pub struct VisibilityMap<'a> {
+    relation: &'a PgRelation,
+    vmbuffer: pg_sys::Buffer,
+}
+
+impl<'a> VisibilityMap<'a> {
+    /// Read the visibility map of `relation`, which the caller should hold at least
+    /// `AccessShareLock` on
+    ///
+    /// # Panics
+    ///
+    /// Panics if `relation` is not a kind of relation that has a visibility map
+    pub fn new(relation: &'a PgRelation) -> Self {
+        if !(relation.is_table() || relation.is_matview() || relation.is_toast_value()) {
+            panic!("`{}` does not have a visibility map", relation.name());
+        }
+        VisibilityMap { relation, vmbuffer: pg_sys::InvalidBuffer as pg_sys::Buffer }
+    }
+
+    /// The number of blocks in the relation's main fork
+    pub fn blocks(&self) -> pg_sys::BlockNumber {
+        block_count(self.relation)
+    }
+
+    /// The visibility map bits of block `blkno`.  Blocks past the end of the map read as neither
+    /// all-visible nor all-frozen
+    pub fn status(&mut self, blkno: pg_sys::BlockNumber) -> VisibilityStatus {
+        let bits = unsafe {
+            // SAFETY:  the relation is open and `self.vmbuffer` is either invalid or the buffer
+            // `visibilitymap_get_status()` pinned the last time we called it
+            pg_sys::visibilitymap_get_status(self.relation.as_ptr(), blkno, &mut self.vmbuffer)
+        } as u32;
+
+        VisibilityStatus {
+            all_visible: bits & pg_sys::VISIBILITYMAP_ALL_VISIBLE != 0,
+            all_frozen: bits & pg_sys::VISIBILITYMAP_ALL_FROZEN != 0,
+        }
+    }
+
+    /// The status of every block in the relation, in block order
+    pub fn iter<'m>(&'m mut self) -> VisibilityMapIter<'m, 'a> {
+        let blocks = 0..self.blocks();
+        VisibilityMapIter { map: self, blocks }
+    }
+
+    /// Count the all-visible and all-frozen blocks of the relation
+    pub fn summary(&self) -> VisibilityMapSummary {
+        let mut all_visible = 0;
+        let mut all_frozen = 0;
+        unsafe {
+            // SAFETY:  the relation is open and both out-params point to valid BlockNumbers
+            pg_sys::visibilitymap_count(self.relation.as_ptr(), &mut all_visible, &mut all_frozen);
+        }
+        VisibilityMapSummary { blocks: self.blocks(), all_visible, all_frozen }
+    }
+}
+
+impl Drop for VisibilityMap<'_> {
+    fn drop(&mut self) {
+        if self.vmbuffer != pg_sys::InvalidBuffer as pg_sys::Buffer {
+            unsafe {
+                // SAFETY:  we hold the pin `visibilitymap_get_status()` left us
+                pg_sys::ReleaseBuffer(self.vmbuffer);
+            }
+        }
+    }
+}
+
+/// Iterator over the [`VisibilityStatus`] of each block of a relation, returned by
+/// [`VisibilityMap::iter()`]
+pub struct VisibilityMapIter<'m, 'a> {
+    map: &'m mut VisibilityMap<'a>,
+    blocks: std::ops::Range<pg_sys::BlockNumber>,
+}
+
+impl Iterator for VisibilityMapIter<'_, '_> {
+    type Item = (pg_sys::BlockNumber, VisibilityStatus);
+
+    fn next(&mut self) -> Option<Self::Item> {
+        let blkno = self.blocks.next()?;
+        Some((blkno, self.map.status(blkno)))
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        self.blocks.size_hint()
+    }
+}
+
+/// Reads the free space map of a relation
+///
+/// Recorded free space is rounded down to the map's granularity of `BLCKSZ / 256` bytes, and
+/// blocks the map doesn't know about read as having no free space.
+pub struct FreeSpaceMap<'a> {
+    relation: &'a PgRelation,
+}
+
+impl<'a> FreeSpaceMap<'a> {
+    /// Read the free space map of `relation`, which the caller should hold at least
+    /// `AccessShareLock` on
+    pub fn new(relation: &'a PgRelation) -> Self {
+        FreeSpaceMap { relation }
+    }
+
+    /// The number of blocks in the relation's main fork
+    pub fn blocks(&self) -> pg_sys::BlockNumber {
+        block_count(self.relation)
+    }
+
+    /// The free space, in bytes, recorded for block `blkno`
+    pub fn free_space(&self, blkno: pg_sys::BlockNumber) -> usize {
+        unsafe {
+            // SAFETY:  the relation is open
+            pg_sys::GetRecordedFreeSpace(self.relation.as_ptr(), blkno)
+        }
+    }
+
+    /// The free space, in bytes, recorded for every block in the relation, in block order
+    pub fn iter(&self) -> impl Iterator<Item = (pg_sys::BlockNumber, usize)> + '_ {
+        (0..self.blocks()).map(move |blkno| (blkno, self.free_space(blkno)))
+    }
+}
+
+fn block_count(relation: &PgRelation) -> pg_sys::BlockNumber {
+    unsafe {
+        // SAFETY:  the relation is open
+        pg_sys::RelationGetNumberOfBlocksInFork(relation.as_ptr(), pg_sys::ForkNumber_MAIN_FORKNUM)
+    }
+}
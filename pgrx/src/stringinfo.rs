@@ -10,6 +10,7 @@ Use of this source code is governed by the MIT license that can be found in the
 //! A safe wrapper around Postgres `StringInfo` structure
 #![allow(dead_code, non_snake_case)]
 
+use crate::varlena::set_varsize;
 use crate::{pg_sys, AllocatedByPostgres, AllocatedByRust, PgBox, WhoAllocated};
 use core::fmt::{Display, Formatter};
 use core::str::Utf8Error;
@@ -46,6 +47,13 @@ impl<AllocatedBy: WhoAllocated> std::io::Write for StringInfo<AllocatedBy> {
     }
 }
 
+impl<AllocatedBy: WhoAllocated> core::fmt::Write for StringInfo<AllocatedBy> {
+    fn write_str(&mut self, s: &str) -> core::fmt::Result {
+        self.push_str(s);
+        Ok(())
+    }
+}
+
 impl<AllocatedBy: WhoAllocated> Display for StringInfo<AllocatedBy> {
     /// Convert this [`StringInfo`] into a Rust string.  This uses [`String::from_utf8_lossy`] as
     /// it's fine for a Postgres [`StringInfo`] to contain null bytes and also not even be proper
@@ -78,6 +86,37 @@ impl StringInfo<AllocatedByRust> {
         si.enlarge(len);
         si
     }
+
+    /// Construct a `StringInfo` over an existing palloc'd buffer of `capacity` bytes, the first
+    /// `len` of which are in use.  The `StringInfo` takes ownership of the buffer:  it will be
+    /// `repalloc()`'d as the `StringInfo` grows, and `pfree()`'d when it's dropped.
+    ///
+    /// # Panics
+    ///
+    /// Panics if there's no room in the buffer for a trailing null byte after `len` bytes, or if
+    /// `capacity` is larger than an `i32`
+    ///
+    /// # Safety
+    ///
+    /// `data` must be a pointer returned by `palloc()`, to at least `capacity` bytes, that isn't
+    /// used or freed by anything else afterwards
+    pub unsafe fn from_raw_parts(
+        data: *mut std::os::raw::c_char,
+        len: usize,
+        capacity: usize,
+    ) -> Self {
+        assert!(len < capacity, "no room for a trailing null byte");
+        let maxlen = capacity.try_into().expect("capacity doesn't fit in an i32");
+
+        // SAFETY:  we know `len` is within the buffer, and the caller says the buffer is valid
+        *data.add(len) = 0;
+        let mut sid = PgBox::<pg_sys::StringInfoData, AllocatedByRust>::alloc();
+        sid.data = data;
+        sid.len = len as _;
+        sid.maxlen = maxlen;
+        sid.cursor = 0;
+        StringInfo { inner: sid }
+    }
 }
 
 impl StringInfo<AllocatedByPostgres> {
@@ -243,6 +282,37 @@ impl<AllocatedBy: WhoAllocated> StringInfo<AllocatedBy> {
             sid_ptr.as_ref().unwrap_unchecked().data
         }
     }
+
+    /// Convert this `StringInfo` into a varlena, such as a `text` or `bytea`, that is wholly owned
+    /// and now managed by Postgres.  This reuses the backing buffer:  the bytes are shifted over
+    /// to make room for the 4-byte varlena header, rather than being copied into a new allocation
+    #[inline]
+    pub fn into_varlena(mut self) -> *mut pg_sys::varlena {
+        let len = self.len();
+        self.enlarge(pg_sys::VARHDRSZ as i32);
+        unsafe {
+            // SAFETY:  we just made sure there's room for the header after the current contents
+            let data = self.inner.data;
+            std::ptr::copy(data, data.add(pg_sys::VARHDRSZ), len);
+            set_varsize(data.cast(), (len + pg_sys::VARHDRSZ) as i32);
+        }
+        self.into_char_ptr() as *mut pg_sys::varlena
+    }
+
+    /// Convert this `StringInfo` into a `text` datum without copying it, as
+    /// [`StringInfo::into_varlena()`] does.  The contents should be valid in the database's
+    /// encoding
+    #[inline]
+    pub fn into_text_datum(self) -> pg_sys::Datum {
+        self.into_varlena().into()
+    }
+
+    /// Convert this `StringInfo` into a `bytea` datum without copying it, as
+    /// [`StringInfo::into_varlena()`] does.  Useful for the result of a `send` function
+    #[inline]
+    pub fn into_bytea_datum(self) -> pg_sys::Datum {
+        self.into_varlena().into()
+    }
 }
 
 impl Default for StringInfo<AllocatedByRust> {
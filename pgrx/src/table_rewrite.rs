@@ -0,0 +1,331 @@
+/*
+Portions Copyright 2019-2021 ZomboDB, LLC.
+Portions Copyright 2021-2022 Technology Concepts & Design, Inc. <support@tcdi.com>
+
+All rights reserved.
+
+Use of this source code is governed by the MIT license that can be found in the LICENSE file.
+*/
+
+//! Rewrite every row of a table, in the manner of `VACUUM FULL` or a rewriting `ALTER TABLE`
+//!
+//! [`TableRewrite`] copies a table's rows into a new relfilenode, passing each through a
+//! caller-provided transform closure, then swaps the new storage in and rebuilds the table's
+//! indexes.  It's the building block for extensions that need to re-encode or compress column
+//! values in place.
+//!
+//! The table is held with an `EXCLUSIVE` lock while rows are copied, so concurrent readers carry
+//! on but writers wait.  The lock is upgraded to `ACCESS EXCLUSIVE` for the final swap.  Everything
+//! happens in the current transaction; if it aborts, the table is left untouched.
+//!
+//! On Postgres 12 and later, progress is visible in `pg_stat_progress_cluster`.
+use crate::heap_tuple::PgHeapTuple;
+use crate::lock::LockMode;
+use crate::TryFromDatumError;
+use crate::{check_for_interrupts, pg_sys, AllocatedByRust, PgMemoryContexts, PgTupleDesc};
+use pg_sys::AsPgCStr;
+
+/// Where a [`TableRewrite`] is at
+#[derive(Debug, Copy, Clone, Eq, PartialEq)]
+pub enum RewritePhase {
+    /// Scanning the table and writing transformed rows to the new relfilenode
+    CopyingRows,
+    /// Swapping the new relfilenode in and rebuilding indexes
+    SwappingRelationFiles,
+    /// All done
+    Done,
+}
+
+/// Progress of a [`TableRewrite`], as passed to its [`on_progress()`](TableRewrite::on_progress)
+/// callback and returned from [`run()`](TableRewrite::run)
+#[derive(Debug, Copy, Clone, Eq, PartialEq)]
+pub struct RewriteProgress {
+    pub phase: RewritePhase,
+    /// The number of blocks in the table when the rewrite started
+    pub total_blocks: u32,
+    /// The number of blocks scanned so far
+    pub blocks_scanned: u32,
+    /// The number of rows passed to the transform closure so far
+    pub tuples_scanned: u64,
+    /// The number of rows written to the new relfilenode so far
+    pub tuples_written: u64,
+}
+
+/// What the transform closure of a [`TableRewrite`] wants done with a row
+#[derive(Debug, Copy, Clone, Eq, PartialEq)]
+pub enum RewriteAction {
+    /// Write the (possibly modified) row to the new relfilenode
+    Keep,
+    /// Leave the row out of the rewritten table
+    Discard,
+}
+
+type ProgressCallback<'a> = Box<dyn FnMut(&RewriteProgress) + 'a>;
+
+/// Rewrites a table, row by row.  See the [module docs](crate::table_rewrite)
+///
+/// ## Examples
+///
+/// ```rust,no_run
+/// use pgrx::prelude::*;
+/// use pgrx::table_rewrite::{RewriteAction, TableRewrite};
+///
+/// # fn foo(relid: pg_sys::Oid) {
+/// let stats = TableRewrite::new(relid)
+///     .on_progress(100_000, |progress| {
+///         notice!("{} of {} blocks", progress.blocks_scanned, progress.total_blocks)
+///     })
+///     .run(|row| {
+///         let body = row.get_by_name::<String>("body")?;
+///         row.set_by_name("body", body.map(|body| body.to_uppercase()))?;
+///         Ok(RewriteAction::Keep)
+///     });
+/// notice!("rewrote {} rows", stats.tuples_written);
+/// # }
+/// ```
+pub struct TableRewrite<'a> {
+    relid: pg_sys::Oid,
+    progress_interval: u64,
+    progress: Option<ProgressCallback<'a>>,
+}
+
+// from `commands/progress.h`, which isn't part of our bindings
+#[cfg(not(feature = "pg11"))]
+mod progress {
+    pub const PROGRESS_CLUSTER_COMMAND: i32 = 0;
+    pub const PROGRESS_CLUSTER_PHASE: i32 = 1;
+    pub const PROGRESS_CLUSTER_HEAP_TUPLES_SCANNED: i32 = 3;
+    pub const PROGRESS_CLUSTER_HEAP_TUPLES_WRITTEN: i32 = 4;
+    pub const PROGRESS_CLUSTER_TOTAL_HEAP_BLKS: i32 = 5;
+    pub const PROGRESS_CLUSTER_HEAP_BLKS_SCANNED: i32 = 6;
+
+    pub const PROGRESS_CLUSTER_COMMAND_VACUUM_FULL: i64 = 2;
+    pub const PROGRESS_CLUSTER_PHASE_SEQ_SCAN_HEAP: i64 = 1;
+    pub const PROGRESS_CLUSTER_PHASE_SWAP_REL_FILES: i64 = 5;
+}
+
+impl<'a> TableRewrite<'a> {
+    /// Prepare to rewrite the table with the specified oid
+    pub fn new(relid: pg_sys::Oid) -> Self {
+        TableRewrite { relid, progress_interval: 1000, progress: None }
+    }
+
+    /// Call `callback` every `every_n_rows` rows, and as the rewrite changes [`RewritePhase`]
+    pub fn on_progress<F: FnMut(&RewriteProgress) + 'a>(
+        mut self,
+        every_n_rows: u64,
+        callback: F,
+    ) -> Self {
+        self.progress_interval = every_n_rows.max(1);
+        self.progress = Some(Box::new(callback));
+        self
+    }
+
+    /// Rewrite the table, passing every live row through `transform`, and return the final
+    /// [`RewriteProgress`]
+    ///
+    /// `transform` can modify the row in place, with [`PgHeapTuple::set_by_name()`] and friends.
+    /// It's called in a short-lived memory context that's reset after each row.  Errors it returns
+    /// are raised as a Postgres `ERROR`, aborting the transaction and leaving the table untouched.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the relation isn't an ordinary heap table.
+    pub fn run<F>(mut self, mut transform: F) -> RewriteProgress
+    where
+        F: FnMut(&mut PgHeapTuple<'_, AllocatedByRust>) -> Result<RewriteAction, TryFromDatumError>,
+    {
+        unsafe {
+            // SAFETY:  relation_open() raises an ERROR if the relation doesn't exist, and we keep
+            // it open (and locked) until we're done with it
+            let old_rel = pg_sys::relation_open(self.relid, LockMode::Exclusive.as_pg());
+            if (*(*old_rel).rd_rel).relkind != pg_sys::RELKIND_RELATION as std::os::raw::c_char {
+                let relname = std::ffi::CStr::from_ptr((*(*old_rel).rd_rel).relname.data.as_ptr());
+                panic!("`{}` is not a table", relname.to_string_lossy());
+            }
+            pg_sys::CheckTableNotInUse(old_rel, "table rewrite".as_pg_cstr());
+
+            let relpersistence = (*(*old_rel).rd_rel).relpersistence;
+            let tablespace = (*(*old_rel).rd_rel).reltablespace;
+            let mut progress = RewriteProgress {
+                phase: RewritePhase::CopyingRows,
+                total_blocks: pg_sys::RelationGetNumberOfBlocksInFork(
+                    old_rel,
+                    pg_sys::ForkNumber_MAIN_FORKNUM,
+                ),
+                blocks_scanned: 0,
+                tuples_scanned: 0,
+                tuples_written: 0,
+            };
+            self.start_progress(progress.total_blocks);
+            self.report_progress(&progress);
+
+            #[cfg(not(feature = "pg15"))]
+            let new_relid = pg_sys::make_new_heap(
+                self.relid,
+                tablespace,
+                relpersistence,
+                LockMode::AccessExclusive.as_pg(),
+            );
+            #[cfg(feature = "pg15")]
+            let new_relid = pg_sys::make_new_heap(
+                self.relid,
+                tablespace,
+                (*(*old_rel).rd_rel).relam,
+                relpersistence,
+                LockMode::AccessExclusive.as_pg(),
+            );
+            let new_rel = pg_sys::relation_open(new_relid, LockMode::AccessExclusive.as_pg());
+
+            let snapshot = pg_sys::RegisterSnapshot(pg_sys::GetLatestSnapshot());
+            let scan = begin_scan(old_rel, snapshot);
+            let mut per_row = PgMemoryContexts::new("table rewrite");
+            loop {
+                check_for_interrupts!();
+
+                let tuple = pg_sys::heap_getnext(scan, pg_sys::ScanDirection_ForwardScanDirection);
+                if tuple.is_null() {
+                    break;
+                }
+
+                progress.tuples_scanned += 1;
+                progress.blocks_scanned = (*(scan as *mut pg_sys::HeapScanDescData)).rs_cblock + 1;
+
+                let keep = per_row.switch_to(|_| {
+                    let tupdesc = PgTupleDesc::from_pg_unchecked((*old_rel).rd_att);
+                    let mut row = PgHeapTuple::from_heap_tuple(tupdesc, tuple).into_owned();
+                    match transform(&mut row) {
+                        Ok(RewriteAction::Keep) => {
+                            pg_sys::simple_heap_insert(new_rel, row.into_pg());
+                            true
+                        }
+                        Ok(RewriteAction::Discard) => false,
+                        Err(e) => panic!("{e}"),
+                    }
+                });
+                per_row.reset();
+
+                if keep {
+                    progress.tuples_written += 1;
+                }
+                if progress.tuples_scanned % self.progress_interval == 0 {
+                    self.report_progress(&progress);
+                }
+            }
+            end_scan(scan);
+            pg_sys::UnregisterSnapshot(snapshot);
+            drop(per_row);
+
+            pg_sys::relation_close(new_rel, pg_sys::NoLock as _);
+            pg_sys::relation_close(old_rel, pg_sys::NoLock as _);
+
+            // swapping the relation files requires that nobody else is looking at the table
+            pg_sys::LockRelationOid(self.relid, LockMode::AccessExclusive.as_pg());
+
+            progress.blocks_scanned = progress.total_blocks.max(progress.blocks_scanned);
+            progress.phase = RewritePhase::SwappingRelationFiles;
+            self.report_progress(&progress);
+
+            pg_sys::finish_heap_swap(
+                self.relid,
+                new_relid,
+                false,
+                false,
+                true,
+                true,
+                pg_sys::RecentXmin,
+                pg_sys::ReadNextMultiXactId(),
+                relpersistence,
+            );
+
+            progress.phase = RewritePhase::Done;
+            self.report_progress(&progress);
+            self.end_progress();
+            progress
+        }
+    }
+
+    fn start_progress(&mut self, _total_blocks: u32) {
+        #[cfg(not(feature = "pg11"))]
+        unsafe {
+            use progress::*;
+            pg_sys::pgstat_progress_start_command(
+                pg_sys::ProgressCommandType_PROGRESS_COMMAND_CLUSTER,
+                self.relid,
+            );
+            pg_sys::pgstat_progress_update_param(
+                PROGRESS_CLUSTER_COMMAND,
+                PROGRESS_CLUSTER_COMMAND_VACUUM_FULL,
+            );
+            pg_sys::pgstat_progress_update_param(
+                PROGRESS_CLUSTER_TOTAL_HEAP_BLKS,
+                _total_blocks as i64,
+            );
+        }
+    }
+
+    fn report_progress(&mut self, progress: &RewriteProgress) {
+        #[cfg(not(feature = "pg11"))]
+        unsafe {
+            use self::progress::*;
+            let phase = match progress.phase {
+                RewritePhase::CopyingRows => PROGRESS_CLUSTER_PHASE_SEQ_SCAN_HEAP,
+                // `finish_heap_swap()` reports the phases after this one itself
+                RewritePhase::SwappingRelationFiles | RewritePhase::Done => {
+                    PROGRESS_CLUSTER_PHASE_SWAP_REL_FILES
+                }
+            };
+            if progress.phase != RewritePhase::Done {
+                pg_sys::pgstat_progress_update_param(PROGRESS_CLUSTER_PHASE, phase);
+            }
+            pg_sys::pgstat_progress_update_param(
+                PROGRESS_CLUSTER_HEAP_BLKS_SCANNED,
+                progress.blocks_scanned as i64,
+            );
+            pg_sys::pgstat_progress_update_param(
+                PROGRESS_CLUSTER_HEAP_TUPLES_SCANNED,
+                progress.tuples_scanned as i64,
+            );
+            pg_sys::pgstat_progress_update_param(
+                PROGRESS_CLUSTER_HEAP_TUPLES_WRITTEN,
+                progress.tuples_written as i64,
+            );
+        }
+
+        if let Some(callback) = self.progress.as_mut() {
+            callback(progress);
+        }
+    }
+
+    fn end_progress(&mut self) {
+        #[cfg(not(feature = "pg11"))]
+        unsafe {
+            pg_sys::pgstat_progress_end_command();
+        }
+    }
+}
+
+#[cfg(feature = "pg11")]
+unsafe fn begin_scan(rel: pg_sys::Relation, snapshot: pg_sys::Snapshot) -> pg_sys::HeapScanDesc {
+    // no synchronized scans, so blocks are visited in order
+    pg_sys::heap_beginscan_strat(rel, snapshot, 0, std::ptr::null_mut(), true, false)
+}
+
+#[cfg(not(feature = "pg11"))]
+unsafe fn begin_scan(rel: pg_sys::Relation, snapshot: pg_sys::Snapshot) -> pg_sys::TableScanDesc {
+    // no synchronized scans, so blocks are visited in order
+    let flags = pg_sys::ScanOptions_SO_TYPE_SEQSCAN
+        | pg_sys::ScanOptions_SO_ALLOW_STRAT
+        | pg_sys::ScanOptions_SO_ALLOW_PAGEMODE;
+    pg_sys::heap_beginscan(rel, snapshot, 0, std::ptr::null_mut(), std::ptr::null_mut(), flags)
+}
+
+#[cfg(feature = "pg11")]
+unsafe fn end_scan(scan: pg_sys::HeapScanDesc) {
+    pg_sys::heap_endscan(scan)
+}
+
+#[cfg(not(feature = "pg11"))]
+unsafe fn end_scan(scan: pg_sys::TableScanDesc) {
+    pg_sys::heap_endscan(scan)
+}
@@ -0,0 +1,165 @@
+/*
+Portions Copyright 2019-2021 ZomboDB, LLC.
+Portions Copyright 2021-2022 Technology Concepts & Design, Inc. <support@tcdi.com>
+
+All rights reserved.
+
+Use of this source code is governed by the MIT license that can be found in the LICENSE file.
+*/
+
+//! Scoping a session to a tenant, for multi-tenant extensions
+//!
+//! A [`TenantSetting`] is a string GUC, like `my_extension.tenant_id`, that an application sets
+//! to the tenant a session (or transaction) acts for.  The extension reads it with
+//! [`TenantSetting::current()`], which parses the setting into the tenant id's type and caches the
+//! result until the setting changes.
+//!
+//! [`tenant_policy!`](macro@crate::tenant_policy) enables row level security on a table the
+//! extension creates, with a policy only letting the current tenant's rows be seen or written:
+//!
+//! ```rust,no_run
+//! use pgrx::prelude::*;
+//! use pgrx::tenancy::TenantSetting;
+//!
+//! static TENANT: TenantSetting<i64> =
+//!     TenantSetting::new("my_extension.tenant_id", "The tenant this session acts for");
+//!
+//! extension_sql!(
+//!     "CREATE TABLE documents (tenant_id bigint NOT NULL, body text);",
+//!     name = "documents"
+//! );
+//!
+//! pgrx::tenant_policy!(
+//!     table = "documents",
+//!     column = "tenant_id",
+//!     setting = "my_extension.tenant_id",
+//!     column_type = "bigint",
+//!     requires = ["documents"]
+//! );
+//!
+//! #[pg_guard]
+//! pub extern "C" fn _PG_init() {
+//!     TENANT.register();
+//! }
+//!
+//! #[pg_extern]
+//! fn current_tenant() -> i64 {
+//!     TENANT.require()
+//! }
+//! ```
+//!
+//! ```sql
+//! SET my_extension.tenant_id = '42';
+//! SELECT * FROM documents;  -- only tenant 42's documents
+//! ```
+//!
+//! Policies don't apply to superusers, or roles with `BYPASSRLS`, and only apply to the table's
+//! owner with `force = true`.
+use crate::guc::{GucContext, GucFlags, GucRegistry, GucSetting};
+use crate::{spi, IntoDatum, PgBuiltInOids, PgLogLevel, PgSqlErrorCode, Spi};
+use std::ffi::CStr;
+use std::str::FromStr;
+use std::sync::{Mutex, PoisonError};
+
+/// A string GUC naming the tenant the current session acts for, as a `T`
+pub struct TenantSetting<T> {
+    name: &'static str,
+    description: &'static str,
+    setting: GucSetting<Option<&'static str>>,
+    // the setting's text as of the last time it was read, and its parsed value
+    cache: Mutex<Option<(Vec<u8>, T)>>,
+}
+
+impl<T: FromStr + Clone> TenantSetting<T> {
+    /// A tenant setting whose GUC is named `name`, which should be prefixed with the extension's
+    /// name, like `my_extension.tenant_id`
+    pub const fn new(name: &'static str, description: &'static str) -> Self {
+        TenantSetting { name, description, setting: GucSetting::new(None), cache: Mutex::new(None) }
+    }
+
+    /// Define the setting's GUC, settable by any user.  This should be called from `_PG_init()`
+    pub fn register(&'static self) {
+        self.register_with_context(GucContext::Userset)
+    }
+
+    /// Like [`TenantSetting::register()`], but only allow the setting to be set as `context`
+    /// allows, such as only by superusers with [`GucContext::Suset`]
+    pub fn register_with_context(&'static self, context: GucContext) {
+        GucRegistry::define_string_guc(
+            self.name,
+            self.description,
+            self.description,
+            &self.setting,
+            context,
+            GucFlags::default(),
+        );
+    }
+
+    /// The name of the setting's GUC
+    pub fn name(&self) -> &'static str {
+        self.name
+    }
+
+    /// The current tenant, or `None` if the setting is unset or empty.  Raises an error if it
+    /// isn't a valid `T`
+    pub fn current(&self) -> Option<T> {
+        crate::volatility::check_volatility("reads a GUC");
+        let ptr = self.setting.get_char_ptr();
+        if ptr.is_null() {
+            return None;
+        }
+        let text = unsafe { CStr::from_ptr(ptr) }.to_bytes();
+        if text.is_empty() {
+            return None;
+        }
+
+        // the cache is only assigned a parsed value, so a poisoned lock still holds a good one
+        let mut cache = self.cache.lock().unwrap_or_else(PoisonError::into_inner);
+        match &*cache {
+            Some((cached, value)) if cached == text => Some(value.clone()),
+            _ => {
+                let value = std::str::from_utf8(text)
+                    .ok()
+                    .and_then(|text| text.parse::<T>().ok())
+                    .unwrap_or_else(|| {
+                        crate::ereport!(
+                            PgLogLevel::ERROR,
+                            PgSqlErrorCode::ERRCODE_INVALID_PARAMETER_VALUE,
+                            format!(
+                                "invalid value for parameter \"{}\": \"{}\"",
+                                self.name,
+                                String::from_utf8_lossy(text)
+                            )
+                        );
+                        unreachable!()
+                    });
+                *cache = Some((text.to_vec(), value.clone()));
+                Some(value)
+            }
+        }
+    }
+
+    /// The current tenant.  Raises an error if the setting is unset or empty
+    pub fn require(&self) -> T {
+        self.current().unwrap_or_else(|| {
+            crate::ereport!(
+                PgLogLevel::ERROR,
+                PgSqlErrorCode::ERRCODE_OBJECT_NOT_IN_PREREQUISITE_STATE,
+                format!("no tenant is set in \"{}\"", self.name)
+            );
+            unreachable!()
+        })
+    }
+
+    /// Set the current tenant until the end of the current transaction, like `SET LOCAL`
+    pub fn set_local(&self, tenant: &str) -> spi::Result<()> {
+        Spi::get_one_with_args::<String>(
+            "SELECT pg_catalog.set_config($1, $2, true)",
+            vec![
+                (PgBuiltInOids::TEXTOID.oid(), self.name.into_datum()),
+                (PgBuiltInOids::TEXTOID.oid(), tenant.into_datum()),
+            ],
+        )
+        .map(|_| ())
+    }
+}
@@ -0,0 +1,210 @@
+/*
+Portions Copyright 2019-2021 ZomboDB, LLC.
+Portions Copyright 2021-2022 Technology Concepts & Design, Inc. <support@tcdi.com>
+
+All rights reserved.
+
+Use of this source code is governed by the MIT license that can be found in the LICENSE file.
+*/
+
+//! Timeouts and interruptible sleeping, with Postgres' timeout framework
+//!
+//! A [`Timeout`] is a named timer, like the ones behind `statement_timeout` and `lock_timeout`.
+//! Once it's enabled, it fires after the given delay, from a signal handler.  Depending on its
+//! [`TimeoutAction`], firing either only records that it fired, or also cancels the running
+//! query at its next `CHECK_FOR_INTERRUPTS()`, which makes it a watchdog for code that may take
+//! too long.
+//!
+//! [`sleep()`] is like `pg_sleep()`:  it waits on the backend's latch, so it wakes up for query
+//! cancellation, timeouts, and postmaster death, instead of blocking them the way
+//! [`std::thread::sleep()`] does.
+//!
+//! ## Examples
+//!
+//! ```rust,no_run
+//! use pgrx::prelude::*;
+//! use pgrx::timeout::{Timeout, TimeoutAction};
+//! use std::time::Duration;
+//!
+//! thread_local! {
+//!     // registered once per backend, the first time it's used
+//!     static WATCHDOG: Timeout = Timeout::register("crunch", TimeoutAction::Cancel);
+//! }
+//!
+//! #[pg_extern]
+//! fn crunch() {
+//!     let watchdog = WATCHDOG.with(|watchdog| *watchdog);
+//!     watchdog.enable_after(Duration::from_secs(5));
+//!     for chunk in 0..1_000_000 {
+//!         // CHECK_FOR_INTERRUPTS() cancels the query once the watchdog fires, but checking
+//!         // the watchdog first reports which timeout it was
+//!         watchdog.check();
+//!         check_for_interrupts!();
+//!         // ... work on `chunk` ...
+//!     }
+//!     watchdog.disable();
+//! }
+//! ```
+use crate::pg_sys;
+use crate::prelude::*;
+use seq_macro::seq;
+use std::sync::atomic::{AtomicU8, Ordering};
+use std::time::{Duration, Instant};
+
+/// What a [`Timeout`] does when it fires
+#[derive(Debug, Copy, Clone, Eq, PartialEq)]
+pub enum TimeoutAction {
+    /// Only record that it fired, for [`Timeout::fired()`] and [`Timeout::check()`]
+    Flag,
+    /// Also cancel the running query, the next time interrupts are checked, as if the client had
+    /// canceled it
+    Cancel,
+}
+
+/// The number of timeouts Postgres leaves for extensions
+const MAX_USER_TIMEOUTS: usize =
+    (pg_sys::TimeoutId_MAX_TIMEOUTS - pg_sys::TimeoutId_USER_TIMEOUT) as usize;
+
+/// Each slot's [`TimeoutAction`], as [`encode()`] makes it.  They're atomics so the signal
+/// handlers can read them
+static ACTIONS: [AtomicU8; MAX_USER_TIMEOUTS] =
+    [const { AtomicU8::new(UNUSED) }; MAX_USER_TIMEOUTS];
+
+const UNUSED: u8 = 0;
+
+fn encode(action: TimeoutAction) -> u8 {
+    match action {
+        TimeoutAction::Flag => 1,
+        TimeoutAction::Cancel => 2,
+    }
+}
+
+const _: () = assert!(MAX_USER_TIMEOUTS == 10);
+
+seq!(N in 0..10 {
+    /// Timeout handlers take no arguments, so each registered timeout gets its own
+    static HANDLERS: [unsafe extern "C" fn(); MAX_USER_TIMEOUTS] = [#(handler~N,)*];
+
+    #(
+        // not #[pg_guard]:  it runs in a signal handler, and can't panic
+        unsafe extern "C" fn handler~N() {
+            fired(N);
+        }
+    )*
+});
+
+unsafe fn fired(slot: usize) {
+    if ACTIONS[slot].load(Ordering::Relaxed) == encode(TimeoutAction::Cancel) {
+        pg_sys::QueryCancelPending = true.into();
+        pg_sys::InterruptPending = true.into();
+    }
+    // the timeout framework sets the backend's latch after running handlers
+}
+
+/// A named timeout registered with Postgres' timeout framework.  See the [module
+/// documentation](self)
+#[derive(Debug, Copy, Clone, Eq, PartialEq)]
+pub struct Timeout {
+    id: pg_sys::TimeoutId,
+    name: &'static str,
+}
+
+impl Timeout {
+    /// Register a new timeout.  Postgres has room for ten timeouts, for all extensions together,
+    /// so a timeout should be registered once per backend, and reused.
+    ///
+    /// # Panics
+    ///
+    /// Panics if called from the postmaster, as from `_PG_init()` when the extension is in
+    /// `shared_preload_libraries`, because each backend starts with no timeouts registered.
+    /// [`on_backend_startup()`](crate::backend::on_backend_startup) runs code in each backend
+    /// instead.  Also panics if ten timeouts were already registered with this function.
+    ///
+    /// Raises a FATAL error if Postgres has no room for another timeout, as when other extensions
+    /// registered theirs directly
+    pub fn register(name: &'static str, action: TimeoutAction) -> Timeout {
+        // SAFETY:  these are set once, when the process starts
+        assert!(
+            unsafe { pg_sys::IsUnderPostmaster || !pg_sys::IsPostmasterEnvironment },
+            "timeouts can only be registered in a backend"
+        );
+        let slot = ACTIONS
+            .iter()
+            .position(|slot| {
+                slot.compare_exchange(UNUSED, encode(action), Ordering::Relaxed, Ordering::Relaxed)
+                    .is_ok()
+            })
+            .unwrap_or_else(|| panic!("cannot register more than {MAX_USER_TIMEOUTS} timeouts"));
+        // SAFETY:  the handler's slot is claimed, so it has an action before the timeout can fire
+        let id = unsafe {
+            pg_sys::RegisterTimeout(pg_sys::TimeoutId_USER_TIMEOUT, Some(HANDLERS[slot]))
+        };
+        Timeout { id, name }
+    }
+
+    pub fn name(&self) -> &'static str {
+        self.name
+    }
+
+    /// Fire after `delay`, replacing any previous delay.  Delays are in whole milliseconds
+    pub fn enable_after(&self, delay: Duration) {
+        let delay_ms = delay.as_millis().min(i32::MAX as u128) as i32;
+        unsafe { pg_sys::enable_timeout_after(self.id, delay_ms) }
+    }
+
+    /// Stop the timeout from firing, and forget whether it fired
+    pub fn disable(&self) {
+        unsafe { pg_sys::disable_timeout(self.id, false) }
+    }
+
+    /// Has the timeout fired, since it was enabled or last reset?
+    pub fn fired(&self) -> bool {
+        unsafe { pg_sys::get_timeout_indicator(self.id, false) }
+    }
+
+    /// Has the timeout fired?  Then forget that it did
+    pub fn reset(&self) -> bool {
+        unsafe { pg_sys::get_timeout_indicator(self.id, true) }
+    }
+
+    /// Raise a "canceling statement due to `name` timeout" ERROR if the timeout has fired
+    pub fn check(&self) {
+        if self.reset() {
+            ereport!(
+                ERROR,
+                PgSqlErrorCode::ERRCODE_QUERY_CANCELED,
+                format!("canceling statement due to {} timeout", self.name)
+            );
+        }
+    }
+}
+
+/// Sleep for `duration`, like `pg_sleep()`.  Interrupts are checked while sleeping, so the sleep
+/// is cut short by an ERROR if the query is canceled or a [`TimeoutAction::Cancel`] timeout
+/// fires, and the backend exits if the postmaster dies
+pub fn sleep(duration: Duration) {
+    let deadline = Instant::now() + duration;
+    loop {
+        check_for_interrupts!();
+        let remaining = deadline.saturating_duration_since(Instant::now());
+        if remaining.is_zero() {
+            break;
+        }
+
+        // round up, so the last sleep doesn't spin
+        let timeout_ms = (remaining.as_micros() + 999) / 1000;
+        unsafe {
+            // SAFETY:  MyLatch is the backend's own latch
+            let rc = pg_sys::WaitLatch(
+                pg_sys::MyLatch,
+                (pg_sys::WL_LATCH_SET | pg_sys::WL_TIMEOUT | pg_sys::WL_POSTMASTER_DEATH) as i32,
+                timeout_ms.min(i32::MAX as u128) as _,
+                pg_sys::WaitEventTimeout_WAIT_EVENT_PG_SLEEP,
+            );
+            pg_sys::ResetLatch(pg_sys::MyLatch);
+            if rc & pg_sys::WL_POSTMASTER_DEATH as i32 != 0 {
+                pg_sys::proc_exit(1);
+            }
+        }
+    }
+}
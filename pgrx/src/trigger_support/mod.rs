@@ -171,6 +171,37 @@ fn example_lifetimes<'a, 'b>(trigger: &'a PgTrigger<'a>) -> Result<
 }
 ```
 
+# Transition tables
+
+Statement-level `AFTER` triggers declared with `REFERENCING OLD TABLE` or `REFERENCING NEW TABLE`
+can read every affected row from [`PgTrigger::old_transition_table()`] and
+[`PgTrigger::new_transition_table()`], without querying the tables through SPI:
+
+```rust,no_run
+use pgrx::prelude::*;
+
+#[pg_trigger]
+fn count_inserted<'a>(trigger: &'a PgTrigger<'a>) -> Result<
+    Option<PgHeapTuple<'a, AllocatedByRust>>,
+    Box<dyn std::error::Error>,
+> {
+    let mut total = 0;
+    for row in trigger.new_transition_table().expect("no new transition table") {
+        total += row.get_by_name::<i64>("amount")?.unwrap_or_default();
+    }
+    notice!("inserted {} in total", total);
+    Ok(None)
+}
+```
+
+```sql
+CREATE TRIGGER count_inserted
+    AFTER INSERT ON orders
+    REFERENCING NEW TABLE AS inserted
+    FOR EACH STATEMENT
+    EXECUTE PROCEDURE count_inserted();
+```
+
 # Escape hatches
 
 Unsafe [`pgrx::pg_sys::FunctionCallInfo`][crate::pg_sys::FunctionCallInfo] and
@@ -185,6 +216,7 @@ mod pg_trigger_error;
 mod pg_trigger_level;
 mod pg_trigger_option;
 mod pg_trigger_when;
+mod transition_table;
 mod trigger_tuple;
 
 pub use pg_trigger::PgTrigger;
@@ -192,6 +224,7 @@ pub use pg_trigger_error::PgTriggerError;
 pub use pg_trigger_level::PgTriggerLevel;
 pub use pg_trigger_option::PgTriggerOperation;
 pub use pg_trigger_when::PgTriggerWhen;
+pub use transition_table::TransitionTable;
 pub use trigger_tuple::TriggerTuple;
 
 use crate::{is_a, pg_sys};
@@ -4,7 +4,7 @@ use crate::pgbox::AllocatedByPostgres;
 use crate::rel::PgRelation;
 use crate::trigger_support::{
     called_as_trigger, PgTriggerError, PgTriggerLevel, PgTriggerOperation, PgTriggerWhen,
-    TriggerEvent, TriggerTuple,
+    TransitionTable, TriggerEvent, TriggerTuple,
 };
 use std::ffi::c_char;
 
@@ -166,6 +166,33 @@ impl<'a> PgTrigger<'a> {
         }
     }
 
+    /// The rows of the old transition table of this trigger invocation, the rows as they were
+    /// before an UPDATE or DELETE.
+    ///
+    /// Returns `None` unless the trigger was declared with `REFERENCING OLD TABLE`.
+    // Derived from `pgrx_pg_sys::TriggerData.tg_oldtable`
+    pub fn old_transition_table(&self) -> Option<TransitionTable<'a>> {
+        self.transition_table(self.trigger_data.tg_oldtable)
+    }
+
+    /// The rows of the new transition table of this trigger invocation, the rows as they are
+    /// after an INSERT or UPDATE.
+    ///
+    /// Returns `None` unless the trigger was declared with `REFERENCING NEW TABLE`.
+    // Derived from `pgrx_pg_sys::TriggerData.tg_newtable`
+    pub fn new_transition_table(&self) -> Option<TransitionTable<'a>> {
+        self.transition_table(self.trigger_data.tg_newtable)
+    }
+
+    fn transition_table(&self, store: *mut pg_sys::Tuplestorestate) -> Option<TransitionTable<'a>> {
+        if store.is_null() {
+            return None;
+        }
+        // Safety: Postgres keeps transition tables for as long as the triggers that fired for them
+        // run, and their rows are in the shape of the trigger's relation
+        unsafe { Some(TransitionTable::new(store, (*self.trigger_data.tg_relation).rd_att)) }
+    }
+
     /// The `PgRelation` corresponding to the trigger.
     pub fn relation(&self) -> Result<crate::PgRelation, PgTriggerError> {
         // SAFETY:  The creator of this PgTrigger asserted they used a correctly initialized
@@ -0,0 +1,83 @@
+use crate::heap_tuple::PgHeapTuple;
+use crate::pgbox::AllocatedByRust;
+use crate::{compat, pg_sys, PgTupleDesc};
+use std::marker::PhantomData;
+
+/// The rows of a transition table, declared with `REFERENCING OLD TABLE` or
+/// `REFERENCING NEW TABLE` on an `AFTER` trigger
+///
+/// Rows are copied into the `CurrentMemoryContext` as they're read.  Each `TransitionTable` reads
+/// from the start of the table, independently of any others, or any queries of the table by name.
+pub struct TransitionTable<'a> {
+    store: *mut pg_sys::Tuplestorestate,
+    read_pointer: i32,
+    slot: *mut pg_sys::TupleTableSlot,
+    tupdesc: pg_sys::TupleDesc,
+    _marker: PhantomData<&'a pg_sys::Tuplestorestate>,
+}
+
+impl<'a> TransitionTable<'a> {
+    /// # Safety
+    ///
+    /// `store` must be a valid transition table tuplestore of rows of the shape `tupdesc`
+    /// describes, that outlives `'a`
+    pub(crate) unsafe fn new(
+        store: *mut pg_sys::Tuplestorestate,
+        tupdesc: pg_sys::TupleDesc,
+    ) -> Self {
+        // Postgres reads transition tables by name through read pointers of their own, so take one
+        // of ours rather than moving theirs
+        let read_pointer =
+            pg_sys::tuplestore_alloc_read_pointer(store, pg_sys::EXEC_FLAG_REWIND as _);
+        pg_sys::tuplestore_select_read_pointer(store, read_pointer);
+        pg_sys::tuplestore_rescan(store);
+        let slot = compat::make_minimal_tuple_slot(tupdesc);
+        TransitionTable { store, read_pointer, slot, tupdesc, _marker: PhantomData }
+    }
+
+    /// The number of rows in the table
+    pub fn len(&self) -> usize {
+        unsafe { pg_sys::tuplestore_tuple_count(self.store) as usize }
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+}
+
+impl<'a> Iterator for TransitionTable<'a> {
+    type Item = PgHeapTuple<'a, AllocatedByRust>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        unsafe {
+            pg_sys::tuplestore_select_read_pointer(self.store, self.read_pointer);
+            if !pg_sys::tuplestore_gettupleslot(self.store, true, false, self.slot) {
+                return None;
+            }
+
+            #[cfg(feature = "pg11")]
+            let tuple = pg_sys::ExecCopySlotTuple(self.slot);
+            #[cfg(not(feature = "pg11"))]
+            let tuple = {
+                // a minimal tuple slot makes a new heap tuple for us
+                let mut should_free = false;
+                let tuple = pg_sys::ExecFetchSlotHeapTuple(self.slot, false, &mut should_free);
+                if should_free {
+                    tuple
+                } else {
+                    pg_sys::heap_copytuple(tuple)
+                }
+            };
+            Some(PgHeapTuple::from_owned_heap_tuple(
+                PgTupleDesc::from_pg_unchecked(self.tupdesc),
+                tuple,
+            ))
+        }
+    }
+}
+
+impl Drop for TransitionTable<'_> {
+    fn drop(&mut self) {
+        unsafe { pg_sys::ExecDropSingleTupleTableSlot(self.slot) }
+    }
+}
@@ -8,6 +8,7 @@ Use of this source code is governed by the MIT license that can be found in the
 */
 
 //! Provides a safe wrapper around Postgres' `pg_sys::TupleDescData` struct
+use crate::rel::ColumnSpec;
 use crate::{pg_sys, void_mut_ptr, PgBox, PgRelation};
 
 use pgrx_pg_sys::AsPgCStr;
@@ -274,6 +275,117 @@ impl<'a> Drop for PgTupleDesc<'a> {
     }
 }
 
+/// Builds a tuple descriptor for a row type that's only known at runtime, such as the result of
+/// a set-returning function whose columns depend on its arguments
+///
+/// ## Examples
+///
+/// ```rust,no_run
+/// use pgrx::{ColumnSpec, TupleDescBuilder};
+///
+/// let tupdesc = TupleDescBuilder::new()
+///     .column(ColumnSpec::new::<i64>("id").not_null())
+///     .column(ColumnSpec::new::<String>("label"))
+///     .bless();
+/// assert_eq!(tupdesc.len(), 2);
+/// ```
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct TupleDescBuilder {
+    columns: Vec<ColumnSpec>,
+}
+
+impl TupleDescBuilder {
+    pub fn new() -> Self {
+        TupleDescBuilder { columns: Vec::new() }
+    }
+
+    /// Add a column after the ones already added
+    pub fn column(mut self, column: ColumnSpec) -> Self {
+        self.columns.push(column);
+        self
+    }
+
+    /// Add each of `columns`, in order
+    pub fn columns(mut self, columns: impl IntoIterator<Item = ColumnSpec>) -> Self {
+        self.columns.extend(columns);
+        self
+    }
+
+    /// The number of columns added so far
+    pub fn len(&self) -> usize {
+        self.columns.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.columns.is_empty()
+    }
+
+    /// Create the tuple descriptor, allocated in the `CurrentMemoryContext`.  It describes an
+    /// anonymous `RECORD` type, which can be used to form tuples but not yet to return them as
+    /// composite datums.  For that, see [`TupleDescBuilder::bless()`]
+    pub fn build<'a>(self) -> PgTupleDesc<'a> {
+        let tupdesc = crate::compat::create_template_tuple_desc(self.columns.len());
+        for (i, column) in self.columns.iter().enumerate() {
+            unsafe {
+                // SAFETY:  the descriptor was created with room for every column, and attribute
+                // numbers start at one
+                pg_sys::TupleDescInitEntry(
+                    tupdesc,
+                    (i + 1) as pg_sys::AttrNumber,
+                    column.name.as_str().as_pg_cstr(),
+                    column.type_oid,
+                    column.typmod,
+                    0,
+                );
+                (*tupdesc).attrs.as_mut_slice(self.columns.len())[i].attnotnull = column.not_null;
+            }
+        }
+
+        unsafe {
+            // SAFETY:  we just made the descriptor, and it isn't reference counted
+            PgTupleDesc::from_pg_is_copy(tupdesc)
+        }
+    }
+
+    /// Create the tuple descriptor, as [`TupleDescBuilder::build()`] does, and register it with
+    /// `BlessTupleDesc()` so that composite datums of its row type can be returned from functions
+    /// declared to return `RECORD`
+    pub fn bless<'a>(self) -> PgTupleDesc<'a> {
+        let tupdesc = self.build();
+        unsafe {
+            // SAFETY:  `tupdesc` is valid, and blessing it only fills in its typmod
+            pg_sys::BlessTupleDesc(tupdesc.as_ptr());
+        }
+        tupdesc
+    }
+}
+
+/// A Rust type whose values are rows of a particular shape, usually implemented with
+/// `#[derive(TupleDescriptor)]`.  The derive makes one column per field, named after it, of the
+/// SQL type the field's type maps to.  Fields that aren't `Option`s are `NOT NULL`.
+///
+/// ```rust,no_run
+/// use pgrx::prelude::*;
+/// use pgrx::TupleDescriptor;
+///
+/// #[derive(TupleDescriptor)]
+/// struct Dog {
+///     name: String,
+///     age: Option<i32>,
+/// }
+///
+/// let tupdesc = Dog::tuple_desc();
+/// ```
+pub trait TupleDescriptor {
+    /// The columns, in order
+    fn columns() -> Vec<ColumnSpec>;
+
+    /// A blessed tuple descriptor with [`TupleDescriptor::columns()`]
+    fn tuple_desc<'a>() -> PgTupleDesc<'a> {
+        TupleDescBuilder::new().columns(Self::columns()).bless()
+    }
+}
+
 pub unsafe fn release_tupdesc(ptr: pg_sys::TupleDesc) {
     if (*ptr).tdrefcount >= 0 {
         pg_sys::DecrTupleDescRefCount(ptr)
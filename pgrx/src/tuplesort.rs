@@ -0,0 +1,474 @@
+/*
+Portions Copyright 2019-2021 ZomboDB, LLC.
+Portions Copyright 2021-2022 Technology Concepts & Design, Inc. <support@tcdi.com>
+
+All rights reserved.
+
+Use of this source code is governed by the MIT license that can be found in the LICENSE file.
+*/
+
+//! Sorting with Postgres' tuplesort
+//!
+//! [`DatumSorter`] sorts values, and [`TupleSorter`] sorts heap tuples by some of their attributes,
+//! both with the same machinery Postgres sorts with for `ORDER BY`.  A sort is done in `work_mem`
+//! if it fits, and spills to temporary files if it doesn't, so it can sort more than fits in
+//! memory.  A sort can be bounded to keep only the first `n` values, which it does with a heap
+//! rather than by sorting them all, as Postgres does for `ORDER BY ... LIMIT n`.
+//!
+//! Values are compared with the btree ordering of their types, so they sort as they would in SQL.
+//!
+//! ## Examples
+//!
+//! ```rust,no_run
+//! use pgrx::prelude::*;
+//! use pgrx::tuplesort::{DatumSorter, SortOrder};
+//!
+//! /// The three largest values
+//! #[pg_extern]
+//! fn top_three(values: Array<i64>) -> Vec<i64> {
+//!     let mut sorter = DatumSorter::<i64>::new(SortOrder::Descending);
+//!     sorter.set_bound(3);
+//!     for value in values.iter() {
+//!         sorter.push(value);
+//!     }
+//!     sorter.sort().flatten().collect()
+//! }
+//! ```
+use crate::compat::{make_heap_tuple_slot, make_minimal_tuple_slot, store_heap_tuple};
+use crate::heap_tuple::PgHeapTuple;
+use crate::{pg_sys, AllocatedByRust, FromDatum, IntoDatum, PgTupleDesc, WhoAllocated};
+use std::marker::PhantomData;
+
+/// Which way to sort
+#[derive(Debug, Copy, Clone, Eq, PartialEq)]
+pub enum SortOrder {
+    Ascending,
+    Descending,
+}
+
+/// How a sort was done, as `EXPLAIN ANALYZE` reports for a `Sort` node
+#[derive(Debug, Copy, Clone, Eq, PartialEq)]
+pub enum SortMethod {
+    /// A bounded sort kept only the first values, in a heap
+    TopNHeapsort,
+    /// Everything was sorted in memory
+    Quicksort,
+    /// Sorted runs were written to disk and merged
+    ExternalSort,
+    /// Sorted runs were written to disk and merged as they were read back
+    ExternalMerge,
+}
+
+/// What a finished sort reports about itself
+#[derive(Debug, Copy, Clone, Eq, PartialEq)]
+pub struct SortStats {
+    pub method: SortMethod,
+    /// Did the sort spill to disk, rather than fit in `work_mem`?
+    pub spilled: bool,
+    /// The memory, or disk space if it spilled, the sort used, in kilobytes
+    pub space_used_kb: i64,
+}
+
+/// An attribute a [`TupleSorter`] sorts by
+#[derive(Debug, Copy, Clone, Eq, PartialEq)]
+pub struct SortKey {
+    attno: usize,
+    order: SortOrder,
+    nulls_first: bool,
+}
+
+impl SortKey {
+    /// Sort by the attribute numbered `attno`, counting from one.  As in SQL, NULLs sort last
+    /// when ascending, and first when descending.
+    pub fn new(attno: usize, order: SortOrder) -> Self {
+        SortKey { attno, order, nulls_first: order == SortOrder::Descending }
+    }
+
+    /// Sort NULLs first, or last
+    pub fn nulls_first(mut self, nulls_first: bool) -> Self {
+        self.nulls_first = nulls_first;
+        self
+    }
+}
+
+/// The `<` or `>` operator of `typoid`'s default btree ordering
+fn sort_operator(typoid: pg_sys::Oid, order: SortOrder) -> pg_sys::Oid {
+    let (mut lt, mut eq, mut gt, mut hashable) =
+        (pg_sys::InvalidOid, pg_sys::InvalidOid, pg_sys::InvalidOid, false);
+    let ascending = order == SortOrder::Ascending;
+    unsafe {
+        // errors if the type has no ordering
+        pg_sys::get_sort_group_operators(
+            typoid,
+            ascending,
+            false,
+            !ascending,
+            &mut lt,
+            &mut eq,
+            &mut gt,
+            &mut hashable,
+        );
+    }
+    if ascending {
+        lt
+    } else {
+        gt
+    }
+}
+
+/// A tuplesort, which is ended when it's dropped
+struct Sort {
+    state: *mut pg_sys::Tuplesortstate,
+    len: u64,
+}
+
+impl Sort {
+    fn new(state: *mut pg_sys::Tuplesortstate) -> Self {
+        Sort { state, len: 0 }
+    }
+
+    fn set_bound(&mut self, bound: usize) {
+        assert!(self.len == 0, "a sort's bound must be set before anything is added to it");
+        unsafe { pg_sys::tuplesort_set_bound(self.state, bound.try_into().unwrap_or(i64::MAX)) }
+    }
+
+    fn perform(&mut self) {
+        unsafe { pg_sys::tuplesort_performsort(self.state) }
+    }
+
+    fn stats(&self) -> SortStats {
+        let mut stats = pg_sys::TuplesortInstrumentation::default();
+        unsafe { pg_sys::tuplesort_get_stats(self.state, &mut stats) };
+        let method = match stats.sortMethod {
+            pg_sys::TuplesortMethod_SORT_TYPE_TOP_N_HEAPSORT => SortMethod::TopNHeapsort,
+            pg_sys::TuplesortMethod_SORT_TYPE_QUICKSORT => SortMethod::Quicksort,
+            pg_sys::TuplesortMethod_SORT_TYPE_EXTERNAL_SORT => SortMethod::ExternalSort,
+            pg_sys::TuplesortMethod_SORT_TYPE_EXTERNAL_MERGE => SortMethod::ExternalMerge,
+            other => panic!("unrecognized sort method {other} for a finished sort"),
+        };
+        SortStats {
+            method,
+            spilled: stats.spaceType == pg_sys::TuplesortSpaceType_SORT_SPACE_TYPE_DISK,
+            space_used_kb: stats.spaceUsed as i64,
+        }
+    }
+
+    fn skip(&mut self, n: usize) -> bool {
+        n == 0 || unsafe { pg_sys::tuplesort_skiptuples(self.state, n as _, true) }
+    }
+}
+
+impl Drop for Sort {
+    fn drop(&mut self) {
+        unsafe { pg_sys::tuplesort_end(self.state) }
+    }
+}
+
+/// Sorts values of type `T`
+///
+/// Sorted values are copied into the `CurrentMemoryContext` as they're read back.
+pub struct DatumSorter<T> {
+    sort: Sort,
+    typoid: pg_sys::Oid,
+    __marker: PhantomData<T>,
+}
+
+impl<T: IntoDatum + FromDatum> DatumSorter<T> {
+    /// Sort in `order`, by the default ordering of `T`'s SQL type and its default collation
+    ///
+    /// # Panics
+    ///
+    /// Panics if `T`'s SQL type has no default btree ordering
+    pub fn new(order: SortOrder) -> Self {
+        let typoid = T::type_oid();
+        let collation = unsafe { pg_sys::get_typcollation(typoid) };
+        Self::with_operator(sort_operator(typoid, order), collation, order == SortOrder::Descending)
+    }
+
+    /// Sort with `sortop`, the `<` or `>` operator of a btree operator family for `T`'s SQL type,
+    /// in `collation`
+    pub fn with_operator(sortop: pg_sys::Oid, collation: pg_sys::Oid, nulls_first: bool) -> Self {
+        let typoid = T::type_oid();
+        let state = unsafe {
+            pg_sys::tuplesort_begin_datum(
+                typoid,
+                sortop,
+                collation,
+                nulls_first,
+                pg_sys::work_mem,
+                std::ptr::null_mut(),
+                #[cfg(any(
+                    feature = "pg11",
+                    feature = "pg12",
+                    feature = "pg13",
+                    feature = "pg14"
+                ))]
+                false,
+                #[cfg(feature = "pg15")]
+                {
+                    pg_sys::TUPLESORT_NONE as _
+                },
+            )
+        };
+        DatumSorter { sort: Sort::new(state), typoid, __marker: PhantomData }
+    }
+
+    /// Keep only the first `bound` values
+    ///
+    /// # Panics
+    ///
+    /// Panics if values have already been added
+    pub fn set_bound(&mut self, bound: usize) {
+        self.sort.set_bound(bound)
+    }
+
+    /// Add a value, or a NULL, to be sorted
+    pub fn push(&mut self, value: Option<T>) {
+        let datum = value.and_then(IntoDatum::into_datum);
+        unsafe {
+            pg_sys::tuplesort_putdatum(
+                self.sort.state,
+                datum.unwrap_or_else(|| pg_sys::Datum::from(0)),
+                datum.is_none(),
+            )
+        }
+        self.sort.len += 1;
+    }
+
+    /// The number of values added
+    pub fn len(&self) -> u64 {
+        self.sort.len
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.sort.len == 0
+    }
+
+    /// Sort the values, to read them back in order.  `None` is a NULL value.
+    pub fn sort(mut self) -> SortedDatums<T> {
+        self.sort.perform();
+        SortedDatums { sort: self.sort, typoid: self.typoid, __marker: PhantomData }
+    }
+}
+
+/// The sorted values of a [`DatumSorter`]
+pub struct SortedDatums<T> {
+    sort: Sort,
+    typoid: pg_sys::Oid,
+    __marker: PhantomData<T>,
+}
+
+impl<T> SortedDatums<T> {
+    /// How the values were sorted
+    pub fn stats(&self) -> SortStats {
+        self.sort.stats()
+    }
+}
+
+impl<T: FromDatum> Iterator for SortedDatums<T> {
+    type Item = Option<T>;
+
+    fn next(&mut self) -> Option<Option<T>> {
+        let (mut datum, mut is_null) = (pg_sys::Datum::from(0), false);
+        unsafe {
+            if !pg_sys::tuplesort_getdatum(
+                self.sort.state,
+                true,
+                &mut datum,
+                &mut is_null,
+                std::ptr::null_mut(),
+            ) {
+                return None;
+            }
+            Some(T::from_polymorphic_datum(datum, is_null, self.typoid))
+        }
+    }
+
+    fn nth(&mut self, n: usize) -> Option<Option<T>> {
+        if !self.sort.skip(n) {
+            return None;
+        }
+        self.next()
+    }
+}
+
+/// A standalone slot, which is dropped with it
+struct Slot(*mut pg_sys::TupleTableSlot);
+
+impl Drop for Slot {
+    fn drop(&mut self) {
+        unsafe { pg_sys::ExecDropSingleTupleTableSlot(self.0) }
+    }
+}
+
+/// Sorts heap tuples of the shape a [`PgTupleDesc`] describes, by some of their attributes
+///
+/// Sorted tuples are copied into the `CurrentMemoryContext` as they're read back.
+pub struct TupleSorter<'a> {
+    // the slots are dropped before the sort, as they may point into it
+    input: Slot,
+    output: Slot,
+    sort: Sort,
+    tupdesc: &'a PgTupleDesc<'a>,
+}
+
+impl<'a> TupleSorter<'a> {
+    /// Sort tuples of the shape `tupdesc` describes by `keys`, in order.  Each attribute is
+    /// compared by the default ordering of its type, in its collation.
+    ///
+    /// # Panics
+    ///
+    /// Panics if there are no keys, a key isn't an attribute of `tupdesc`, or an attribute's type
+    /// has no default btree ordering
+    pub fn new(tupdesc: &'a PgTupleDesc<'a>, keys: &[SortKey]) -> Self {
+        assert!(!keys.is_empty(), "a TupleSorter needs something to sort by");
+        let mut attnums = Vec::with_capacity(keys.len());
+        let mut sortops = Vec::with_capacity(keys.len());
+        let mut collations = Vec::with_capacity(keys.len());
+        let mut nulls_first = Vec::with_capacity(keys.len());
+        for key in keys {
+            let attribute = key
+                .attno
+                .checked_sub(1)
+                .and_then(|index| tupdesc.get(index))
+                .filter(|attribute| !attribute.attisdropped)
+                .unwrap_or_else(|| panic!("there's no attribute {} to sort by", key.attno));
+            attnums.push(key.attno as pg_sys::AttrNumber);
+            sortops.push(sort_operator(attribute.atttypid, key.order));
+            collations.push(attribute.attcollation);
+            nulls_first.push(key.nulls_first);
+        }
+
+        unsafe {
+            // tuplesort copies the keys, but keeps using the tuple descriptor
+            let state = pg_sys::tuplesort_begin_heap(
+                tupdesc.as_ptr(),
+                keys.len() as _,
+                attnums.as_mut_ptr(),
+                sortops.as_mut_ptr(),
+                collations.as_mut_ptr(),
+                nulls_first.as_mut_ptr(),
+                pg_sys::work_mem,
+                std::ptr::null_mut(),
+                #[cfg(any(
+                    feature = "pg11",
+                    feature = "pg12",
+                    feature = "pg13",
+                    feature = "pg14"
+                ))]
+                false,
+                #[cfg(feature = "pg15")]
+                {
+                    pg_sys::TUPLESORT_NONE as _
+                },
+            );
+            TupleSorter {
+                input: Slot(make_heap_tuple_slot(tupdesc.as_ptr())),
+                output: Slot(make_minimal_tuple_slot(tupdesc.as_ptr())),
+                sort: Sort::new(state),
+                tupdesc,
+            }
+        }
+    }
+
+    /// Keep only the first `bound` tuples
+    ///
+    /// # Panics
+    ///
+    /// Panics if tuples have already been added
+    pub fn set_bound(&mut self, bound: usize) {
+        self.sort.set_bound(bound)
+    }
+
+    /// Add a copy of a tuple, which must be of the shape the sorter's tuple descriptor describes,
+    /// to be sorted
+    ///
+    /// # Panics
+    ///
+    /// Panics if the tuple doesn't have as many attributes as the sorter's tuple descriptor
+    pub fn push<AllocatedBy: WhoAllocated>(&mut self, tuple: &PgHeapTuple<'_, AllocatedBy>) {
+        assert_eq!(
+            self.tupdesc.len(),
+            tuple.len(),
+            "the tuple doesn't have the shape of the TupleSorter's tuple descriptor"
+        );
+        unsafe {
+            store_heap_tuple(tuple.as_ptr(), self.input.0, false);
+            pg_sys::tuplesort_puttupleslot(self.sort.state, self.input.0);
+        }
+        self.sort.len += 1;
+    }
+
+    /// The number of tuples added
+    pub fn len(&self) -> u64 {
+        self.sort.len
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.sort.len == 0
+    }
+
+    /// Sort the tuples, to read them back in order
+    pub fn sort(mut self) -> SortedTuples<'a> {
+        self.sort.perform();
+        SortedTuples { output: self.output, sort: self.sort, tupdesc: self.tupdesc }
+    }
+}
+
+/// The sorted tuples of a [`TupleSorter`]
+pub struct SortedTuples<'a> {
+    output: Slot,
+    sort: Sort,
+    tupdesc: &'a PgTupleDesc<'a>,
+}
+
+impl<'a> SortedTuples<'a> {
+    /// How the tuples were sorted
+    pub fn stats(&self) -> SortStats {
+        self.sort.stats()
+    }
+}
+
+impl<'a> Iterator for SortedTuples<'a> {
+    type Item = PgHeapTuple<'a, AllocatedByRust>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        unsafe {
+            let slot = self.output.0;
+            if !pg_sys::tuplesort_gettupleslot(
+                self.sort.state,
+                true,
+                false,
+                slot,
+                std::ptr::null_mut(),
+            ) {
+                return None;
+            }
+
+            #[cfg(feature = "pg11")]
+            let tuple = pg_sys::ExecCopySlotTuple(slot);
+            #[cfg(not(feature = "pg11"))]
+            let tuple = {
+                // a minimal tuple slot makes a new heap tuple for us
+                let mut should_free = false;
+                let tuple = pg_sys::ExecFetchSlotHeapTuple(slot, false, &mut should_free);
+                if should_free {
+                    tuple
+                } else {
+                    pg_sys::heap_copytuple(tuple)
+                }
+            };
+            Some(PgHeapTuple::from_owned_heap_tuple(
+                PgTupleDesc::from_pg_unchecked(self.tupdesc.as_ptr()),
+                tuple,
+            ))
+        }
+    }
+
+    fn nth(&mut self, n: usize) -> Option<Self::Item> {
+        if !self.sort.skip(n) {
+            return None;
+        }
+        self.next()
+    }
+}
@@ -0,0 +1,280 @@
+/*
+Portions Copyright 2019-2021 ZomboDB, LLC.
+Portions Copyright 2021-2022 Technology Concepts & Design, Inc. <support@tcdi.com>
+
+All rights reserved.
+
+Use of this source code is governed by the MIT license that can be found in the LICENSE file.
+*/
+
+//! A typed wrapper around Postgres' [`List`][crate::pg_sys::List], which knows what kind of list
+//! it holds
+//!
+//! Postgres has three kinds of lists, told apart by their node tag:  lists of pointers
+//! (`T_List`), of integers (`T_IntList`), and of oids (`T_OidList`).  [`List<T>`] checks that a
+//! list from Postgres is the kind its element type expects, and reads its cells correctly for
+//! both the linked-list layout used until Postgres 13 and the array layout used since.  Unlike
+//! [`PgList`](crate::PgList), it doesn't need the `cshim` feature.
+//!
+//! Lists are allocated in the `CurrentMemoryContext`, and are never freed by `List<T>`.
+//!
+//! ## Examples
+//!
+//! ```rust,no_run
+//! use pgrx::prelude::*;
+//! use pgrx::typed_list::List;
+//!
+//! let oids: List<pg_sys::Oid> = vec![pg_sys::INT4OID, pg_sys::TEXTOID].into_iter().collect();
+//! # unsafe fn takes_list(_: *mut pg_sys::List) {}
+//! unsafe { takes_list(oids.into_pg()) };
+//!
+//! # let query = std::ptr::null_mut::<pg_sys::Query>();
+//! let rtable = unsafe { List::<*mut pg_sys::RangeTblEntry>::from_pg((*query).rtable) };
+//! for rte in rtable.iter() {
+//!     // ...
+//! }
+//! ```
+use crate::compat::{cell_data, list_concat, list_length, list_nth_cell};
+use crate::pg_sys;
+use std::fmt::{Debug, Formatter};
+use std::marker::PhantomData;
+
+/// A type that can be an element of a Postgres `List`
+pub trait ListElement: Copy {
+    /// The node tag of a list of this type
+    const LIST_TAG: pg_sys::NodeTag;
+
+    /// Read the element in `cell`
+    ///
+    /// # Safety
+    ///
+    /// `cell` must be a valid cell of a list of this type
+    unsafe fn read(cell: *mut pg_sys::ListCell) -> Self;
+
+    /// Append `value` to `list`, returning the (possibly reallocated) list
+    ///
+    /// # Safety
+    ///
+    /// `list` must be a valid list of this type, or null
+    unsafe fn append(list: *mut pg_sys::List, value: Self) -> *mut pg_sys::List;
+}
+
+impl<T> ListElement for *mut T {
+    const LIST_TAG: pg_sys::NodeTag = pg_sys::NodeTag_T_List;
+
+    unsafe fn read(cell: *mut pg_sys::ListCell) -> Self {
+        cell_data(cell).ptr_value.cast()
+    }
+
+    unsafe fn append(list: *mut pg_sys::List, value: Self) -> *mut pg_sys::List {
+        pg_sys::lappend(list, value.cast())
+    }
+}
+
+impl ListElement for i32 {
+    const LIST_TAG: pg_sys::NodeTag = pg_sys::NodeTag_T_IntList;
+
+    unsafe fn read(cell: *mut pg_sys::ListCell) -> Self {
+        cell_data(cell).int_value
+    }
+
+    unsafe fn append(list: *mut pg_sys::List, value: Self) -> *mut pg_sys::List {
+        pg_sys::lappend_int(list, value)
+    }
+}
+
+impl ListElement for pg_sys::Oid {
+    const LIST_TAG: pg_sys::NodeTag = pg_sys::NodeTag_T_OidList;
+
+    unsafe fn read(cell: *mut pg_sys::ListCell) -> Self {
+        cell_data(cell).oid_value
+    }
+
+    unsafe fn append(list: *mut pg_sys::List, value: Self) -> *mut pg_sys::List {
+        pg_sys::lappend_oid(list, value)
+    }
+}
+
+/// A Postgres `List` of `T`s:  pointers, `i32`s, or `Oid`s
+pub struct List<T: ListElement> {
+    list: *mut pg_sys::List,
+    _marker: PhantomData<T>,
+}
+
+impl<T: ListElement> List<T> {
+    /// An empty list, which is `NIL`, the null pointer, until something is pushed onto it
+    pub fn new() -> Self {
+        List { list: std::ptr::null_mut(), _marker: PhantomData }
+    }
+
+    /// Wrap a list from Postgres
+    ///
+    /// # Panics
+    ///
+    /// Panics if `list` isn't the kind of list `T` expects, such as an integer list for
+    /// `List<pg_sys::Oid>`
+    ///
+    /// # Safety
+    ///
+    /// `list` must be a valid `List`, or null.  If `T` is a pointer, the list's pointers must point
+    /// to `T`s
+    pub unsafe fn from_pg(list: *mut pg_sys::List) -> Self {
+        if let Some(list) = list.as_ref() {
+            assert_eq!(
+                list.type_,
+                T::LIST_TAG,
+                "list has node tag {}, but {} was expected",
+                list.type_,
+                T::LIST_TAG
+            );
+        }
+        List { list, _marker: PhantomData }
+    }
+
+    /// The underlying list, which may be null
+    pub fn as_ptr(&self) -> *mut pg_sys::List {
+        self.list
+    }
+
+    /// Give the underlying list, which may be null, back to Postgres
+    pub fn into_pg(self) -> *mut pg_sys::List {
+        self.list
+    }
+
+    pub fn len(&self) -> usize {
+        unsafe { list_length(self.list) }
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    /// The element at position `i`, counting from zero.  Before Postgres 13, this takes time
+    /// proportional to `i`
+    pub fn get(&self, i: usize) -> Option<T> {
+        unsafe { list_nth_cell(self.list, i).map(|cell| T::read(cell)) }
+    }
+
+    pub fn first(&self) -> Option<T> {
+        self.get(0)
+    }
+
+    pub fn last(&self) -> Option<T> {
+        self.len().checked_sub(1).and_then(|i| self.get(i))
+    }
+
+    /// Iterate over the elements, in order
+    pub fn iter(&self) -> ListIter<'_, T> {
+        #[cfg(any(feature = "pg11", feature = "pg12"))]
+        let iter = ListIter {
+            _marker: PhantomData,
+            next: unsafe {
+                self.list.as_ref().map(|list| list.head).unwrap_or(std::ptr::null_mut())
+            },
+        };
+        #[cfg(not(any(feature = "pg11", feature = "pg12")))]
+        let iter = ListIter { list: self, next: 0 };
+        iter
+    }
+
+    /// Append `value`, like `lappend()`
+    pub fn push(&mut self, value: T) {
+        self.list = unsafe { T::append(self.list, value) };
+    }
+
+    /// Append every element of `other`, like `list_concat()`
+    pub fn concat(&mut self, other: List<T>) {
+        self.list = unsafe { list_concat(self.list, other.list) };
+    }
+
+    /// Copy the elements into a `Vec`
+    pub fn to_vec(&self) -> Vec<T> {
+        self.iter().collect()
+    }
+}
+
+impl<T: ListElement> Default for List<T> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<T: ListElement + Debug> Debug for List<T> {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        f.debug_list().entries(self.iter()).finish()
+    }
+}
+
+impl<T: ListElement> FromIterator<T> for List<T> {
+    fn from_iter<I: IntoIterator<Item = T>>(iter: I) -> Self {
+        let mut list = List::new();
+        list.extend(iter);
+        list
+    }
+}
+
+impl<T: ListElement> Extend<T> for List<T> {
+    fn extend<I: IntoIterator<Item = T>>(&mut self, iter: I) {
+        for value in iter {
+            self.push(value);
+        }
+    }
+}
+
+impl<T: ListElement> From<Vec<T>> for List<T> {
+    fn from(values: Vec<T>) -> Self {
+        values.into_iter().collect()
+    }
+}
+
+impl<T: ListElement> From<List<T>> for Vec<T> {
+    fn from(list: List<T>) -> Self {
+        list.to_vec()
+    }
+}
+
+impl<'a, T: ListElement> IntoIterator for &'a List<T> {
+    type Item = T;
+    type IntoIter = ListIter<'a, T>;
+
+    fn into_iter(self) -> Self::IntoIter {
+        self.iter()
+    }
+}
+
+/// An iterator over the elements of a [`List<T>`]
+pub struct ListIter<'a, T: ListElement> {
+    #[cfg(any(feature = "pg11", feature = "pg12"))]
+    _marker: PhantomData<&'a List<T>>,
+    #[cfg(not(any(feature = "pg11", feature = "pg12")))]
+    list: &'a List<T>,
+    /// the next cell, as lists were linked lists until Postgres 13
+    #[cfg(any(feature = "pg11", feature = "pg12"))]
+    next: *mut pg_sys::ListCell,
+    /// the index of the next cell, as lists are arrays since Postgres 13
+    #[cfg(not(any(feature = "pg11", feature = "pg12")))]
+    next: usize,
+}
+
+impl<T: ListElement> Iterator for ListIter<'_, T> {
+    type Item = T;
+
+    #[cfg(any(feature = "pg11", feature = "pg12"))]
+    fn next(&mut self) -> Option<T> {
+        if self.next.is_null() {
+            return None;
+        }
+        unsafe {
+            let value = T::read(self.next);
+            self.next = (*self.next).next;
+            Some(value)
+        }
+    }
+
+    #[cfg(not(any(feature = "pg11", feature = "pg12")))]
+    fn next(&mut self) -> Option<T> {
+        let value = self.list.get(self.next)?;
+        self.next += 1;
+        Some(value)
+    }
+}
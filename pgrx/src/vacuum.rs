@@ -0,0 +1,210 @@
+/*
+Portions Copyright 2019-2021 ZomboDB, LLC.
+Portions Copyright 2021-2022 Technology Concepts & Design, Inc. <support@tcdi.com>
+
+All rights reserved.
+
+Use of this source code is governed by the MIT license that can be found in the LICENSE file.
+*/
+
+//! Run `VACUUM` and `ANALYZE` on a relation directly, without going through SPI
+//!
+//! `VACUUM` manages its own transactions, so Postgres refuses to run it from within a function or
+//! a transaction block, which is all SPI can offer.  [`vacuum_relation()`] instead drives it the
+//! way autovacuum does, and is meant to be called from a background worker's main loop, outside
+//! of [`BackgroundWorker::transaction()`](crate::bgworkers::BackgroundWorker::transaction).
+//!
+//! `ANALYZE` has no such restriction and [`analyze_relation()`] can be called from anywhere.
+use crate::{ereport, pg_sys, PgLogLevel, PgMemoryContexts, PgSqlErrorCode};
+#[cfg(not(feature = "pg11"))]
+use pg_sys::AsPgCStr;
+
+/// Options for [`vacuum_relation()`], equivalent to those of the SQL `VACUUM` command
+#[derive(Debug, Default, Copy, Clone, Eq, PartialEq)]
+pub struct VacuumOptions {
+    /// `VACUUM FULL`:  rewrite the table to reclaim all dead space
+    pub full: bool,
+    /// `VACUUM FREEZE`:  aggressively freeze tuples
+    pub freeze: bool,
+    /// `VACUUM ANALYZE`:  also update planner statistics
+    pub analyze: bool,
+    /// `VACUUM (SKIP_LOCKED)`:  skip the relation, rather than wait, if it can't be locked
+    /// immediately
+    pub skip_locked: bool,
+    /// `VACUUM VERBOSE`:  report progress at `INFO` level
+    pub verbose: bool,
+    /// `VACUUM (DISABLE_PAGE_SKIPPING)`:  don't skip pages based on the visibility map
+    pub disable_page_skipping: bool,
+}
+
+/// Options for [`analyze_relation()`], equivalent to those of the SQL `ANALYZE` command
+#[derive(Debug, Default, Copy, Clone, Eq, PartialEq)]
+pub struct AnalyzeOptions {
+    /// `ANALYZE (SKIP_LOCKED)`:  skip the relation, rather than wait, if it can't be locked
+    /// immediately
+    pub skip_locked: bool,
+    /// `ANALYZE VERBOSE`:  report progress at `INFO` level
+    pub verbose: bool,
+}
+
+/// `VACUUM` the relation with the specified oid
+///
+/// Like autovacuum, this starts and commits its own transactions, so it must be called when no
+/// transaction is in progress, typically from a background worker connected to a database.
+///
+/// # Errors
+///
+/// Raises a Postgres `ERROR` if called while a transaction is in progress, if the relation doesn't
+/// exist, or for any of the reasons `VACUUM` itself would.
+pub fn vacuum_relation(relid: pg_sys::Oid, options: VacuumOptions) {
+    let VacuumOptions { full, freeze, analyze, skip_locked, verbose, disable_page_skipping } =
+        options;
+    let flags = VacuumFlags {
+        vacuum: true,
+        analyze,
+        verbose,
+        freeze,
+        full,
+        skip_locked,
+        disable_page_skipping,
+    };
+
+    if unsafe { pg_sys::IsTransactionState() } {
+        ereport!(
+            PgLogLevel::ERROR,
+            PgSqlErrorCode::ERRCODE_ACTIVE_SQL_TRANSACTION,
+            "vacuum_relation() cannot be called while a transaction is in progress"
+        );
+    }
+    in_own_transactions(|| unsafe { exec_vacuum(relid, &flags, true) });
+}
+
+/// `ANALYZE` the relation with the specified oid
+///
+/// If a transaction is in progress the relation is analyzed within it, otherwise a new
+/// transaction is started and committed for it.
+///
+/// # Errors
+///
+/// Raises a Postgres `ERROR` if the relation doesn't exist, or for any of the reasons `ANALYZE`
+/// itself would.
+pub fn analyze_relation(relid: pg_sys::Oid, options: AnalyzeOptions) {
+    let AnalyzeOptions { skip_locked, verbose } = options;
+    let flags = VacuumFlags { analyze: true, verbose, skip_locked, ..Default::default() };
+
+    if unsafe { pg_sys::IsTransactionState() } {
+        unsafe { exec_vacuum(relid, &flags, false) }
+    } else {
+        in_own_transactions(|| unsafe { exec_vacuum(relid, &flags, true) });
+    }
+}
+
+#[derive(Default)]
+struct VacuumFlags {
+    vacuum: bool,
+    analyze: bool,
+    verbose: bool,
+    freeze: bool,
+    full: bool,
+    skip_locked: bool,
+    disable_page_skipping: bool,
+}
+
+/// Run `f`, which calls into `vacuum()` as a top-level command, in the manner of autovacuum
+fn in_own_transactions<F: FnOnce()>(f: F) {
+    struct RestorePortalContext(pg_sys::MemoryContext);
+    impl Drop for RestorePortalContext {
+        fn drop(&mut self) {
+            unsafe {
+                // SAFETY:  this puts back the `PortalContext` we found, whether `vacuum()` returned
+                // or unwound, before our context is deleted out from under it
+                pg_sys::PortalContext = self.0;
+            }
+        }
+    }
+
+    // `vacuum()` commits and starts transactions as it goes, so the nodes describing what to
+    // vacuum need to live outside of them.  It also allocates its working memory under
+    // `PortalContext`, which only exists here if we make one
+    let mut context = PgMemoryContexts::new("pgrx vacuum");
+    let _restore = unsafe {
+        let restore = RestorePortalContext(pg_sys::PortalContext);
+        if pg_sys::PortalContext.is_null() {
+            pg_sys::PortalContext = context.value();
+        }
+        restore
+    };
+
+    unsafe {
+        pg_sys::SetCurrentStatementStartTimestamp();
+        pg_sys::StartTransactionCommand();
+        context.switch_to(|_| f());
+        pg_sys::CommitTransactionCommand();
+    }
+}
+
+/// Build a `VacuumStmt` for `relid` and hand it to `ExecVacuum()`
+unsafe fn exec_vacuum(relid: pg_sys::Oid, flags: &VacuumFlags, is_top_level: bool) {
+    let relname = pg_sys::get_rel_name(relid);
+    if relname.is_null() {
+        ereport!(
+            PgLogLevel::ERROR,
+            PgSqlErrorCode::ERRCODE_UNDEFINED_TABLE,
+            format!("relation with OID {} does not exist", relid.as_u32())
+        );
+    }
+    let nspname = pg_sys::get_namespace_name(pg_sys::get_rel_namespace(relid));
+    let rangevar = pg_sys::makeRangeVar(nspname, relname, -1);
+
+    let rel = pg_sys::makeVacuumRelation(rangevar, relid, std::ptr::null_mut());
+
+    let mut stmt = crate::PgBox::<pg_sys::VacuumStmt>::alloc_node(pg_sys::NodeTag_T_VacuumStmt);
+    stmt.rels = pg_sys::lappend(std::ptr::null_mut(), rel.cast());
+
+    #[cfg(feature = "pg11")]
+    {
+        let options = [
+            (flags.vacuum, pg_sys::VacuumOption_VACOPT_VACUUM),
+            (flags.analyze, pg_sys::VacuumOption_VACOPT_ANALYZE),
+            (flags.verbose, pg_sys::VacuumOption_VACOPT_VERBOSE),
+            (flags.freeze, pg_sys::VacuumOption_VACOPT_FREEZE),
+            (flags.full, pg_sys::VacuumOption_VACOPT_FULL),
+            // Postgres 11 has no SQL-level SKIP_LOCKED, but autovacuum's NOWAIT is the same thing
+            (flags.skip_locked, pg_sys::VacuumOption_VACOPT_NOWAIT),
+            (flags.disable_page_skipping, pg_sys::VacuumOption_VACOPT_DISABLE_PAGE_SKIPPING),
+        ];
+        stmt.options = options
+            .into_iter()
+            .filter(|(enabled, _)| *enabled)
+            .fold(0, |options, (_, option)| options | option as i32);
+
+        pg_sys::ExecVacuum(stmt.as_ptr(), is_top_level);
+    }
+
+    #[cfg(not(feature = "pg11"))]
+    {
+        // `ANALYZE` rejects the `analyze` option, it's implied
+        let options = [
+            (flags.vacuum && flags.analyze, "analyze"),
+            (flags.verbose, "verbose"),
+            (flags.freeze, "freeze"),
+            (flags.full, "full"),
+            (flags.skip_locked, "skip_locked"),
+            (flags.disable_page_skipping, "disable_page_skipping"),
+        ];
+        let mut defelems = std::ptr::null_mut();
+        for (_, name) in options.into_iter().filter(|(enabled, _)| *enabled) {
+            // a DefElem without an argument means "true"
+            let defelem = pg_sys::makeDefElem(name.as_pg_cstr(), std::ptr::null_mut(), -1);
+            defelems = pg_sys::lappend(defelems, defelem.cast());
+        }
+        stmt.options = defelems;
+        stmt.is_vacuumcmd = flags.vacuum;
+
+        pg_sys::ExecVacuum(
+            pg_sys::make_parsestate(std::ptr::null_mut()),
+            stmt.as_ptr(),
+            is_top_level,
+        );
+    }
+}
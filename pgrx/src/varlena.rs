@@ -194,7 +194,36 @@ pub unsafe fn varatt_is_1b_e(ptr: *const pg_sys::varlena) -> bool {
 /// ```
 #[inline]
 pub unsafe fn varatt_not_pad_byte(ptr: *const pg_sys::varlena) -> bool {
-    !ptr.is_null()
+    *ptr.cast::<u8>() != 0
+}
+
+/// Is this varlena "packed", with an inline, 1-byte ("short") header?
+///
+/// Postgres packs varlenas of up to 126 bytes of data when storing them in tuples and arrays, and
+/// packed varlenas are not aligned:  their data can't be read in place as anything but bytes.
+/// Use [`detoast_packed`] to decompress or fetch a varlena while leaving it packed, or
+/// [`pg_sys::pg_detoast_datum`] to unpack it into a 4-byte header, aligned, copy.
+///
+/// ## Safety
+///
+/// The caller asserts the specified `ptr` really is a non-null [`pg_sys::varlena`] pointer
+#[inline]
+pub unsafe fn is_packed(ptr: *const pg_sys::varlena) -> bool {
+    varatt_is_1b(ptr) && !varatt_is_1b_e(ptr)
+}
+
+/// Decompresses or fetches the external value of `ptr`, if it's toasted, but leaves it packed if
+/// it has a 1-byte header.  Unless it was toasted, `ptr` itself is returned.
+///
+/// The result's data is only byte-aligned, so is to be read with [`vardata_any`] and
+/// [`varsize_any_exhdr`].
+///
+/// ## Safety
+///
+/// The caller asserts the specified `ptr` really is a non-null [`pg_sys::varlena`] pointer
+#[inline]
+pub unsafe fn detoast_packed(ptr: *mut pg_sys::varlena) -> *mut pg_sys::varlena {
+    pg_sys::pg_detoast_datum_packed(ptr)
 }
 
 /// ```c
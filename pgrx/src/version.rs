@@ -0,0 +1,160 @@
+/*
+Portions Copyright 2019-2021 ZomboDB, LLC.
+Portions Copyright 2021-2022 Technology Concepts & Design, Inc. <support@tcdi.com>
+
+All rights reserved.
+
+Use of this source code is governed by the MIT license that can be found in the LICENSE file.
+*/
+
+//! The version of the running Postgres server, and the features it supports
+//!
+//! An extension is built against the headers of one Postgres major version, and the `pg11` ...
+//! `pg15` features choose between majors at compile time.  The server loading the extension can
+//! be at a different minor version than the headers were, though, so behavior that changed in a
+//! minor release has to be checked for at runtime, with [`pg_version()`]:
+//!
+//! ```rust,no_run
+//! use pgrx::pg_version;
+//! use pgrx::version::Feature;
+//!
+//! if pg_version().supports(Feature::CustomRmgr) {
+//!     // register a custom WAL resource manager
+//! }
+//! if pg_version().at_least(15, 2) {
+//!     // rely on a fix made in 15.2
+//! }
+//! ```
+use crate::pg_sys;
+use once_cell::sync::OnceCell;
+use std::ffi::CStr;
+use std::fmt::{Display, Formatter};
+
+/// A Postgres version, such as 15.2
+#[derive(Debug, Copy, Clone, Eq, PartialEq, Ord, PartialOrd, Hash)]
+pub struct PgVersion {
+    // in the form of `PG_VERSION_NUM`, such as 150002 for 15.2
+    num: u32,
+}
+
+impl PgVersion {
+    /// The version with the specified major and minor numbers
+    pub const fn new(major: u32, minor: u32) -> Self {
+        PgVersion { num: major * 10000 + minor }
+    }
+
+    /// The version in the form of `PG_VERSION_NUM` and the `server_version_num` GUC, such as
+    /// `150002` for 15.2
+    pub const fn from_num(num: u32) -> Self {
+        PgVersion { num }
+    }
+
+    /// The version of the Postgres headers the extension was compiled against.  Its major version
+    /// is always that of the running server, but its minor version may not be, see
+    /// [`pg_version()`]
+    pub const fn compiled() -> Self {
+        PgVersion::from_num(pg_sys::PG_VERSION_NUM)
+    }
+
+    pub const fn major(&self) -> u32 {
+        self.num / 10000
+    }
+
+    pub const fn minor(&self) -> u32 {
+        self.num % 10000
+    }
+
+    /// The version in the form of `PG_VERSION_NUM`
+    pub const fn as_num(&self) -> u32 {
+        self.num
+    }
+
+    /// Is this version `major.minor` or later?
+    pub const fn at_least(&self, major: u32, minor: u32) -> bool {
+        self.num >= PgVersion::new(major, minor).num
+    }
+
+    /// Does this version have `feature`?
+    pub const fn supports(&self, feature: Feature) -> bool {
+        let (major, minor) = feature.since();
+        self.at_least(major, minor)
+    }
+}
+
+impl Display for PgVersion {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}.{}", self.major(), self.minor())
+    }
+}
+
+/// The version of the running Postgres server, from its `server_version_num` GUC
+pub fn pg_version() -> PgVersion {
+    static VERSION: OnceCell<PgVersion> = OnceCell::new();
+
+    *VERSION.get_or_init(|| unsafe {
+        let num = pg_sys::GetConfigOption(b"server_version_num\0".as_ptr().cast(), true, false);
+        if num.is_null() {
+            return PgVersion::compiled();
+        }
+        CStr::from_ptr(num)
+            .to_str()
+            .ok()
+            .and_then(|num| num.parse().ok())
+            .map_or_else(PgVersion::compiled, PgVersion::from_num)
+    })
+}
+
+/// Postgres features an extension may need to adapt to, as of the version each appeared in.  See
+/// [`PgVersion::supports()`]
+#[derive(Debug, Copy, Clone, Eq, PartialEq, Hash)]
+#[non_exhaustive]
+pub enum Feature {
+    /// Transaction control in procedures, with `CALL`
+    Procedures,
+    /// JIT compilation of expressions and tuple deforming
+    Jit,
+    /// `CREATE ACCESS METHOD ... TYPE TABLE`
+    TableAccessMethods,
+    /// `GENERATED ALWAYS AS (...) STORED` columns
+    GeneratedColumns,
+    /// Planner support functions, set with `CREATE FUNCTION ... SUPPORT`
+    PlannerSupportFunctions,
+    /// Incremental sort plans
+    IncrementalSort,
+    /// Vacuuming a table's indexes with parallel workers
+    ParallelVacuum,
+    /// Multirange types
+    Multiranges,
+    /// Query identifiers computed by the server itself, with `compute_query_id`
+    ComputeQueryId,
+    /// `ProcessUtility_hook` implementations are passed whether the statement's tree is read-only
+    ProcessUtilityReadOnlyTree,
+    /// Custom WAL resource managers, registered with `RegisterCustomRmgr()`
+    CustomRmgr,
+    /// The `MERGE` command
+    Merge,
+    /// Row filters and column lists in publications
+    PublicationRowFilters,
+    /// `CREATE VIEW ... WITH (security_invoker)`
+    SecurityInvokerViews,
+}
+
+impl Feature {
+    /// The major and minor version the feature first appeared in
+    pub const fn since(&self) -> (u32, u32) {
+        match self {
+            Feature::Procedures | Feature::Jit => (11, 0),
+            Feature::TableAccessMethods
+            | Feature::GeneratedColumns
+            | Feature::PlannerSupportFunctions => (12, 0),
+            Feature::IncrementalSort | Feature::ParallelVacuum => (13, 0),
+            Feature::Multiranges
+            | Feature::ComputeQueryId
+            | Feature::ProcessUtilityReadOnlyTree => (14, 0),
+            Feature::CustomRmgr
+            | Feature::Merge
+            | Feature::PublicationRowFilters
+            | Feature::SecurityInvokerViews => (15, 0),
+        }
+    }
+}
@@ -0,0 +1,93 @@
+/*
+Portions Copyright 2019-2021 ZomboDB, LLC.
+Portions Copyright 2021-2022 Technology Concepts & Design, Inc. <support@tcdi.com>
+
+All rights reserved.
+
+Use of this source code is governed by the MIT license that can be found in the LICENSE file.
+*/
+
+//! An opt-in, debug-build lint for `#[pg_extern(immutable)]` functions that read mutable state
+//!
+//! Postgres trusts a function's declared volatility.  An `IMMUTABLE` function that actually reads
+//! the database or a GUC can be constant-folded into query plans or used in index expressions,
+//! and then silently returns stale results or corrupts indexes when that state changes.
+//!
+//! When the extension is compiled with `debug_assertions`, every `#[pg_extern(immutable)]`
+//! function records that it's running, and pgrx's SPI ([`Spi::connect()`](crate::Spi::connect))
+//! and GUC ([`GucSetting::get()`](crate::GucSetting)) entry points report being called from one.
+//! The lint is off until enabled, typically from `_PG_init()`:
+//!
+//! ```rust,no_run
+//! use pgrx::volatility::{set_volatility_lint, VolatilityLint};
+//!
+//! #[cfg(debug_assertions)]
+//! set_volatility_lint(VolatilityLint::Warn);
+//! ```
+use crate::pg_sys::panic::ErrorReport;
+use crate::{function_name, PgLogLevel, PgSqlErrorCode};
+use std::cell::Cell;
+
+/// How [`set_volatility_lint()`] reports an `IMMUTABLE` function touching mutable state
+#[derive(Debug, Copy, Clone, Eq, PartialEq)]
+pub enum VolatilityLint {
+    /// Don't check anything.  This is the default
+    Off,
+    /// Raise a Postgres `WARNING` and carry on
+    Warn,
+    /// Raise a Postgres `ERROR`, aborting the transaction
+    Error,
+}
+
+thread_local! {
+    static LINT: Cell<VolatilityLint> = const { Cell::new(VolatilityLint::Off) };
+    static CURRENT_IMMUTABLE_FUNCTION: Cell<Option<&'static str>> = const { Cell::new(None) };
+}
+
+/// Enable (or disable) the volatility lint for the current backend
+pub fn set_volatility_lint(lint: VolatilityLint) {
+    LINT.with(|current| current.set(lint));
+}
+
+/// The volatility lint currently in effect for this backend
+pub fn volatility_lint() -> VolatilityLint {
+    LINT.with(Cell::get)
+}
+
+/// Marks an `IMMUTABLE` function as running until dropped
+#[doc(hidden)]
+pub struct ImmutableFunctionGuard(Option<&'static str>);
+
+impl Drop for ImmutableFunctionGuard {
+    fn drop(&mut self) {
+        CURRENT_IMMUTABLE_FUNCTION.with(|current| current.set(self.0));
+    }
+}
+
+/// Called by the `#[pg_extern(immutable)]` wrapper function, in debug builds, for the duration
+/// of the call
+#[doc(hidden)]
+pub fn enter_immutable_function(name: &'static str) -> ImmutableFunctionGuard {
+    ImmutableFunctionGuard(CURRENT_IMMUTABLE_FUNCTION.with(|current| current.replace(Some(name))))
+}
+
+/// Report `what` according to the current [`VolatilityLint`] if it happens while an `IMMUTABLE`
+/// function is running
+pub(crate) fn check_volatility(what: &str) {
+    let (lint, function) = (volatility_lint(), CURRENT_IMMUTABLE_FUNCTION.with(Cell::get));
+
+    let Some(function) = function else { return };
+    let level = match lint {
+        VolatilityLint::Off => return,
+        VolatilityLint::Warn => PgLogLevel::WARNING,
+        VolatilityLint::Error => PgLogLevel::ERROR,
+    };
+
+    ErrorReport::new(
+        PgSqlErrorCode::ERRCODE_INVALID_FUNCTION_DEFINITION,
+        format!("IMMUTABLE function `{function}` {what}"),
+        function_name!(),
+    )
+    .set_hint("Declare the function STABLE or VOLATILE instead")
+    .report(level);
+}
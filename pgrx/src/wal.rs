@@ -0,0 +1,202 @@
+/*
+Portions Copyright 2019-2021 ZomboDB, LLC.
+Portions Copyright 2021-2022 Technology Concepts & Design, Inc. <support@tcdi.com>
+
+All rights reserved.
+
+Use of this source code is governed by the MIT license that can be found in the LICENSE file.
+*/
+
+//! Write-ahead log locations, replication lag, and waiting for a location to be applied
+//!
+//! A primary reports how far it has written the WAL with [`insert_lsn()`], [`write_lsn()`] and
+//! [`flush_lsn()`], and how far each of its standbys has received and replayed it with
+//! [`standbys()`].  A standby reports how far it has replayed with [`replay_lsn()`].
+//!
+//! An extension routing reads to standbys can give a session read-your-writes consistency by
+//! remembering the location of its last commit on the primary, and waiting for a standby to
+//! replay that far before reading from it:
+//!
+//! ```rust,no_run
+//! use pgrx::wal;
+//! use std::time::Duration;
+//!
+//! // on the primary, after committing
+//! let commit_lsn = wal::insert_lsn().unwrap();
+//!
+//! // on a standby, before reading
+//! if !wal::wait_for_lsn(commit_lsn, Some(Duration::from_secs(1))) {
+//!     // too far behind, read from the primary instead
+//! }
+//! ```
+use crate::prelude::*;
+use crate::spi;
+use std::time::{Duration, Instant};
+
+/// Is this server a standby, still replaying WAL from its primary or archive?
+pub fn is_in_recovery() -> bool {
+    unsafe { pg_sys::RecoveryInProgress() }
+}
+
+/// The location WAL is being inserted at, like `pg_current_wal_insert_lsn()`.  `None` on a standby
+pub fn insert_lsn() -> Option<pg_sys::XLogRecPtr> {
+    (!is_in_recovery()).then(|| unsafe { pg_sys::GetXLogInsertRecPtr() })
+}
+
+/// The location WAL has been written out to the kernel up to, like `pg_current_wal_lsn()`.  `None`
+/// on a standby
+pub fn write_lsn() -> Option<pg_sys::XLogRecPtr> {
+    (!is_in_recovery()).then(|| unsafe { pg_sys::GetXLogWriteRecPtr() })
+}
+
+/// The location WAL has been flushed to durable storage up to, like
+/// `pg_current_wal_flush_lsn()`.  `None` on a standby
+pub fn flush_lsn() -> Option<pg_sys::XLogRecPtr> {
+    if is_in_recovery() {
+        return None;
+    }
+    unsafe {
+        #[cfg(not(feature = "pg15"))]
+        let lsn = pg_sys::GetFlushRecPtr();
+        #[cfg(feature = "pg15")]
+        let lsn = pg_sys::GetFlushRecPtr(std::ptr::null_mut());
+        Some(lsn)
+    }
+}
+
+/// The location WAL has been replayed up to on this standby, like `pg_last_wal_replay_lsn()`.
+/// `None` on a primary
+pub fn replay_lsn() -> Option<pg_sys::XLogRecPtr> {
+    is_in_recovery().then(|| unsafe { pg_sys::GetXLogReplayRecPtr(std::ptr::null_mut()) })
+}
+
+/// The location changes are visible to new snapshots up to:  the flushed location on a primary,
+/// or the replayed location on a standby
+pub fn applied_lsn() -> pg_sys::XLogRecPtr {
+    replay_lsn().or_else(flush_lsn).unwrap_or(pg_sys::InvalidXLogRecPtr as _)
+}
+
+/// The number of bytes of WAL from `behind` to `ahead`, or zero if `behind` isn't behind
+pub fn lag_bytes(ahead: pg_sys::XLogRecPtr, behind: pg_sys::XLogRecPtr) -> u64 {
+    ahead.saturating_sub(behind)
+}
+
+/// Block until this server has applied WAL up to `lsn`, as [`applied_lsn()`] reports it, or
+/// `timeout` has passed.  Returns whether it has, checking for interrupts while it waits.
+pub fn wait_for_lsn(lsn: pg_sys::XLogRecPtr, timeout: Option<Duration>) -> bool {
+    // there's no one to wake us when WAL is applied, so poll
+    const POLL_INTERVAL: Duration = Duration::from_millis(10);
+
+    let start = Instant::now();
+    loop {
+        if applied_lsn() >= lsn {
+            return true;
+        }
+        let wait = match timeout {
+            Some(timeout) => match timeout.checked_sub(start.elapsed()) {
+                Some(remaining) if !remaining.is_zero() => remaining.min(POLL_INTERVAL),
+                _ => return false,
+            },
+            None => POLL_INTERVAL,
+        };
+        unsafe {
+            let rc = pg_sys::WaitLatch(
+                pg_sys::MyLatch,
+                (pg_sys::WL_LATCH_SET | pg_sys::WL_TIMEOUT | pg_sys::WL_POSTMASTER_DEATH) as _,
+                wait.as_millis().max(1) as _,
+                pg_sys::PG_WAIT_EXTENSION,
+            );
+            if rc & pg_sys::WL_POSTMASTER_DEATH as i32 != 0 {
+                pg_sys::proc_exit(1);
+            }
+            pg_sys::ResetLatch(pg_sys::MyLatch);
+        }
+        pg_sys::check_for_interrupts!();
+    }
+}
+
+/// A standby streaming WAL from this server, as `pg_stat_replication` reports it
+#[derive(Debug, Clone, PartialEq)]
+pub struct StandbyStatus {
+    /// The process id of the WAL sender streaming to it
+    pub pid: i32,
+    pub application_name: String,
+    /// Its address, or `None` if it's connected through a Unix socket
+    pub client_addr: Option<String>,
+    /// `startup`, `catchup`, `streaming`, `backup` or `stopping`
+    pub state: String,
+    /// `async`, `potential`, `sync` or `quorum`
+    pub sync_state: String,
+    /// The last location sent to it
+    pub sent_lsn: Option<pg_sys::XLogRecPtr>,
+    /// The last location it has written to disk
+    pub write_lsn: Option<pg_sys::XLogRecPtr>,
+    /// The last location it has flushed to durable storage
+    pub flush_lsn: Option<pg_sys::XLogRecPtr>,
+    /// The last location it has replayed
+    pub replay_lsn: Option<pg_sys::XLogRecPtr>,
+    /// How long it recently took between flushing WAL locally and the standby writing it
+    pub write_lag: Option<Duration>,
+    /// How long it recently took between flushing WAL locally and the standby flushing it
+    pub flush_lag: Option<Duration>,
+    /// How long it recently took between flushing WAL locally and the standby replaying it
+    pub replay_lag: Option<Duration>,
+}
+
+impl StandbyStatus {
+    /// The number of bytes of WAL the standby has yet to replay, of what's been flushed here, or
+    /// `None` if it hasn't reported replaying anything
+    pub fn replay_lag_bytes(&self) -> Option<u64> {
+        Some(lag_bytes(flush_lsn()?, self.replay_lsn?))
+    }
+
+    /// The number of bytes of WAL the standby has yet to flush, of what's been flushed here, or
+    /// `None` if it hasn't reported flushing anything
+    pub fn flush_lag_bytes(&self) -> Option<u64> {
+        Some(lag_bytes(flush_lsn()?, self.flush_lsn?))
+    }
+}
+
+/// The standbys streaming WAL from this server, in WAL sender process id order
+pub fn standbys() -> spi::Result<Vec<StandbyStatus>> {
+    const QUERY: &str = "SELECT pid, application_name, client_addr::text, state, sync_state, \
+                                (sent_lsn - '0/0'::pg_lsn)::int8 AS sent_lsn, \
+                                (write_lsn - '0/0'::pg_lsn)::int8 AS write_lsn, \
+                                (flush_lsn - '0/0'::pg_lsn)::int8 AS flush_lsn, \
+                                (replay_lsn - '0/0'::pg_lsn)::int8 AS replay_lsn, \
+                                (extract(epoch FROM write_lag) * 1000000)::int8 AS write_lag, \
+                                (extract(epoch FROM flush_lag) * 1000000)::int8 AS flush_lag, \
+                                (extract(epoch FROM replay_lag) * 1000000)::int8 AS replay_lag \
+                           FROM pg_catalog.pg_stat_replication \
+                          ORDER BY pid";
+
+    fn lsn(row: &spi::SpiHeapTupleData, name: &str) -> spi::Result<Option<pg_sys::XLogRecPtr>> {
+        Ok(row.get_by_name::<i64, _>(name)?.map(|lsn| lsn as _))
+    }
+
+    fn lag(row: &spi::SpiHeapTupleData, name: &str) -> spi::Result<Option<Duration>> {
+        Ok(row.get_by_name::<i64, _>(name)?.map(|micros| Duration::from_micros(micros as _)))
+    }
+
+    Spi::connect(|client| {
+        client
+            .select(QUERY, None, None)?
+            .map(|row| {
+                Ok(StandbyStatus {
+                    pid: row.get_by_name("pid")?.unwrap_or_default(),
+                    application_name: row.get_by_name("application_name")?.unwrap_or_default(),
+                    client_addr: row.get_by_name("client_addr")?,
+                    state: row.get_by_name("state")?.unwrap_or_default(),
+                    sync_state: row.get_by_name("sync_state")?.unwrap_or_default(),
+                    sent_lsn: lsn(&row, "sent_lsn")?,
+                    write_lsn: lsn(&row, "write_lsn")?,
+                    flush_lsn: lsn(&row, "flush_lsn")?,
+                    replay_lsn: lsn(&row, "replay_lsn")?,
+                    write_lag: lag(&row, "write_lag")?,
+                    flush_lag: lag(&row, "flush_lag")?,
+                    replay_lag: lag(&row, "replay_lag")?,
+                })
+            })
+            .collect()
+    })
+}
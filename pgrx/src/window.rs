@@ -0,0 +1,209 @@
+/*
+Portions Copyright 2019-2021 ZomboDB, LLC.
+Portions Copyright 2021-2022 Technology Concepts & Design, Inc. <support@tcdi.com>
+
+All rights reserved.
+
+Use of this source code is governed by the MIT license that can be found in the LICENSE file.
+*/
+
+//! Support for writing `WINDOW` functions
+//!
+//! A window function is a `#[pg_extern(window)]` function.  Postgres calls it once for each row
+//! of a partition, with all of its arguments NULL:  it instead reads them, for the current row or
+//! any other row of the partition or window frame, through the [`WindowObject`] made from its
+//! `fcinfo`.
+//!
+//! ## Examples
+//!
+//! ```rust,no_run
+//! use pgrx::prelude::*;
+//! use pgrx::window::{WindowObject, WindowSeek};
+//!
+//! /// The value of the row `offset` rows before the current one, like `lag()`
+//! #[pg_extern(window)]
+//! fn my_lag(fcinfo: pg_sys::FunctionCallInfo, _value: Option<i32>, _offset: Option<i32>) -> Option<i32> {
+//!     let window = unsafe { WindowObject::from_fcinfo(fcinfo) };
+//!     let offset = window.get_func_arg_current::<i32>(1).unwrap_or(1);
+//!     window.get_func_arg_in_partition::<i32>(0, -offset, WindowSeek::Current, false).flatten()
+//! }
+//! ```
+//!
+//! ```sql
+//! SELECT i, my_lag(i, 2) OVER (ORDER BY i) FROM generate_series(1, 5) i;
+//! ```
+use crate::{is_a, pg_sys, FromDatum};
+use std::marker::PhantomData;
+
+/// Where a position within a partition or frame is relative to
+#[derive(Debug, Copy, Clone, Eq, PartialEq)]
+pub enum WindowSeek {
+    /// The current row
+    Current,
+    /// The first row
+    Head,
+    /// The last row
+    Tail,
+}
+
+impl WindowSeek {
+    fn as_pg(&self) -> i32 {
+        (match self {
+            WindowSeek::Current => pg_sys::WINDOW_SEEK_CURRENT,
+            WindowSeek::Head => pg_sys::WINDOW_SEEK_HEAD,
+            WindowSeek::Tail => pg_sys::WINDOW_SEEK_TAIL,
+        }) as i32
+    }
+}
+
+/// A window function's view of the partition it's being called for
+pub struct WindowObject<'fcx> {
+    winobj: pg_sys::WindowObject,
+    fcinfo: pg_sys::FunctionCallInfo,
+    __marker: PhantomData<&'fcx ()>,
+}
+
+impl<'fcx> WindowObject<'fcx> {
+    /// The `WindowObject` a window function is called with
+    ///
+    /// # Safety
+    ///
+    /// `fcinfo` must be the valid `FunctionCallInfo` the function was called with, and the
+    /// `WindowObject` mustn't outlive the call
+    ///
+    /// # Panics
+    ///
+    /// Panics if the function wasn't called as a window function, as happens if it isn't declared
+    /// with `#[pg_extern(window)]`
+    pub unsafe fn from_fcinfo(fcinfo: pg_sys::FunctionCallInfo) -> Self {
+        let context = (*fcinfo).context;
+        assert!(
+            !context.is_null() && is_a(context, pg_sys::NodeTag_T_WindowObjectData),
+            "window function called in non-window context"
+        );
+        WindowObject { winobj: context.cast(), fcinfo, __marker: PhantomData }
+    }
+
+    /// The position of the current row within its partition, counting from zero
+    pub fn current_position(&self) -> i64 {
+        unsafe { pg_sys::WinGetCurrentPosition(self.winobj) }
+    }
+
+    /// The number of rows in the current partition.  Postgres reads the whole partition to count
+    /// them, if it hasn't already
+    pub fn partition_row_count(&self) -> i64 {
+        unsafe { pg_sys::WinGetPartitionRowCount(self.winobj) }
+    }
+
+    /// Let Postgres discard the partition's rows before `position`, which the function promises it
+    /// won't read again
+    pub fn set_mark_position(&self, position: i64) {
+        unsafe { pg_sys::WinSetMarkPosition(self.winobj, position) }
+    }
+
+    /// Are the rows at two positions of the partition peers, sorting equally by the window's
+    /// `ORDER BY`?  Every row is a peer of every other if there's no `ORDER BY`
+    pub fn rows_are_peers(&self, position1: i64, position2: i64) -> bool {
+        unsafe { pg_sys::WinRowsArePeers(self.winobj, position1, position2) }
+    }
+
+    /// The function's argument `argno`, counting from zero, evaluated for the current row
+    pub fn get_func_arg_current<T: FromDatum>(&self, argno: usize) -> Option<T> {
+        let mut is_null = false;
+        unsafe {
+            let datum = pg_sys::WinGetFuncArgCurrent(self.winobj, argno as _, &mut is_null);
+            T::from_polymorphic_datum(datum, is_null, self.arg_type(argno))
+        }
+    }
+
+    /// The function's argument `argno`, counting from zero, evaluated for the row `relpos` rows
+    /// from `seek` in the partition.  Returns `None` if there's no such row, or `Some(None)` if the
+    /// argument is NULL for it.
+    ///
+    /// With `set_mark`, rows before it are discarded, as [`WindowObject::set_mark_position()`] does.
+    pub fn get_func_arg_in_partition<T: FromDatum>(
+        &self,
+        argno: usize,
+        relpos: i32,
+        seek: WindowSeek,
+        set_mark: bool,
+    ) -> Option<Option<T>> {
+        let (mut is_null, mut is_out) = (false, false);
+        unsafe {
+            let datum = pg_sys::WinGetFuncArgInPartition(
+                self.winobj,
+                argno as _,
+                relpos,
+                seek.as_pg(),
+                set_mark,
+                &mut is_null,
+                &mut is_out,
+            );
+            (!is_out).then(|| T::from_polymorphic_datum(datum, is_null, self.arg_type(argno)))
+        }
+    }
+
+    /// The function's argument `argno`, counting from zero, evaluated for the row `relpos` rows
+    /// from `seek` in the current row's window frame.  Returns `None` if there's no such row, or
+    /// `Some(None)` if the argument is NULL for it.
+    ///
+    /// With `set_mark`, rows before it are discarded, as [`WindowObject::set_mark_position()`] does.
+    pub fn get_func_arg_in_frame<T: FromDatum>(
+        &self,
+        argno: usize,
+        relpos: i32,
+        seek: WindowSeek,
+        set_mark: bool,
+    ) -> Option<Option<T>> {
+        let (mut is_null, mut is_out) = (false, false);
+        unsafe {
+            let datum = pg_sys::WinGetFuncArgInFrame(
+                self.winobj,
+                argno as _,
+                relpos,
+                seek.as_pg(),
+                set_mark,
+                &mut is_null,
+                &mut is_out,
+            );
+            (!is_out).then(|| T::from_polymorphic_datum(datum, is_null, self.arg_type(argno)))
+        }
+    }
+
+    /// A `T` that lives as long as the current partition, for the function to keep state in from
+    /// one row to the next.  It starts out as `T::default()` in each partition.
+    ///
+    /// Postgres frees the memory when the partition is done with, without dropping the `T`, so it
+    /// must be `Copy`.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `T` needs to be aligned more strictly than Postgres aligns its allocations
+    pub fn partition_local<T: Copy + Default>(&mut self) -> &mut T {
+        #[repr(C)]
+        struct Local<T> {
+            initialized: bool,
+            value: T,
+        }
+
+        assert!(
+            std::mem::align_of::<Local<T>>() <= pg_sys::MAXIMUM_ALIGNOF as usize,
+            "partition-local state is too strictly aligned"
+        );
+        unsafe {
+            // SAFETY:  the memory is zeroed when it's first allocated for the partition, so
+            // `initialized` is false until we've written a valid `T`
+            let local =
+                pg_sys::WinGetPartitionLocalMemory(self.winobj, std::mem::size_of::<Local<T>>())
+                    as *mut Local<T>;
+            if !(*local).initialized {
+                local.write(Local { initialized: true, value: T::default() });
+            }
+            &mut (*local).value
+        }
+    }
+
+    fn arg_type(&self, argno: usize) -> pg_sys::Oid {
+        unsafe { pg_sys::get_fn_expr_argtype((*self.fcinfo).flinfo, argno as _) }
+    }
+}